@@ -42,6 +42,10 @@ else
     exit 1
 fi
 
+# Stage the engine as an app resource
+echo "Staging built-in engine..."
+bash scripts/bundle-builtin-engine.sh || exit 1
+
 # Build the Tauri application
 echo "Building Tauri application..."
 npm run tauri:build
@@ -0,0 +1,71 @@
+#!/bin/bash
+
+# Stage the built-in engine for bundling with the Tauri app
+# Copies the release usi-engine binary, plus any eval data files given, into
+# src-tauri/resources/builtin-engine and writes manifest.json with each file's SHA-256.
+# The app copies these files into its config directory on first launch and checks them
+# against the manifest.
+#
+# Usage: ./scripts/bundle-builtin-engine.sh [eval_file ...]
+#
+# The version recorded in the manifest defaults to the package version in Cargo.toml;
+# set ENGINE_VERSION to override it.
+
+set -e
+
+ROOT="$(cd "$(dirname "$0")/.." && pwd)"
+DEST="$ROOT/src-tauri/resources/builtin-engine"
+
+EXE_SUFFIX=""
+case "$(uname -s)" in
+    MINGW*|MSYS*|CYGWIN*) EXE_SUFFIX=".exe" ;;
+esac
+ENGINE="$ROOT/target/release/usi-engine$EXE_SUFFIX"
+
+if [ ! -f "$ENGINE" ]; then
+    echo "Error: $ENGINE not found. Run 'cargo build --bin usi-engine --release' first."
+    exit 1
+fi
+
+VERSION="${ENGINE_VERSION:-$(grep -m1 '^version' "$ROOT/Cargo.toml" | cut -d'"' -f2)}"
+if [ -z "$VERSION" ]; then
+    echo "Error: could not determine the engine version; set ENGINE_VERSION"
+    exit 1
+fi
+
+sha256() {
+    if command -v sha256sum &> /dev/null; then
+        sha256sum "$1" | cut -d' ' -f1
+    else
+        shasum -a 256 "$1" | cut -d' ' -f1
+    fi
+}
+
+mkdir -p "$DEST"
+find "$DEST" -mindepth 1 -maxdepth 1 ! -name README.txt -exec rm -rf {} +
+
+FILES=("usi-engine$EXE_SUFFIX")
+cp "$ENGINE" "$DEST/usi-engine$EXE_SUFFIX"
+for eval_file in "$@"; do
+    mkdir -p "$DEST/eval"
+    cp "$eval_file" "$DEST/eval/"
+    FILES+=("eval/$(basename "$eval_file")")
+done
+
+{
+    echo "{"
+    echo "  \"version\": \"$VERSION\","
+    echo "  \"executable\": \"usi-engine$EXE_SUFFIX\","
+    echo "  \"files\": ["
+    for i in "${!FILES[@]}"; do
+        separator=","
+        if [ "$i" -eq $((${#FILES[@]} - 1)) ]; then
+            separator=""
+        fi
+        echo "    { \"path\": \"${FILES[$i]}\", \"sha256\": \"$(sha256 "$DEST/${FILES[$i]}")\" }$separator"
+    done
+    echo "  ]"
+    echo "}"
+} > "$DEST/manifest.json"
+
+echo "Built-in engine $VERSION staged in $DEST"
@@ -0,0 +1,543 @@
+//! Provisioning of the built-in engine
+//! Release builds ship the engine and any eval data under `builtin-engine/` in the app's
+//! resources, with a manifest.json giving each file's SHA-256 (written by
+//! scripts/bundle-builtin-engine.sh). On first launch, and whenever the bundle changes, the
+//! files are copied into `<config dir>/builtin-engine` and checked against the manifest; the
+//! copy is what gets registered. Debug builds use the engine built in the workspace instead.
+//! A newer engine release can also be downloaded from the URL in the settings; its version,
+//! platform and binary hash must be signed with the key the app was built with
+//! (YSU_ENGINE_UPDATE_PUBLIC_KEY), so an old binary cannot be passed off as new. A downloaded
+//! engine is kept until an app update bundles a newer one.
+//! A newly registered built-in engine starts from its own option defaults, with the values in
+//! resources/builtin-engine-options.json in their place; a file of the same name in the config
+//! directory replaces the shipped one.
+
+use crate::engine_installer::{download, make_executable};
+use crate::engine_storage::EngineStorage;
+use crate::engine_validator::{self, EngineMetadata};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+const BUNDLE_DIR: &str = "builtin-engine";
+const MANIFEST_FILE: &str = "manifest.json";
+const PRESET_FILE: &str = "builtin-engine-options.json";
+const SHIPPED_PRESET: &str = include_str!("../resources/builtin-engine-options.json");
+/// Hex Ed25519 public key that signs engine releases; updates are refused without one
+const UPDATE_PUBLIC_KEY: Option<&str> = option_env!("YSU_ENGINE_UPDATE_PUBLIC_KEY");
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledFile {
+    /// Relative to the bundle directory
+    pub path: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuiltinManifest {
+    pub version: String,
+    /// The engine binary; also listed in `files`
+    pub executable: String,
+    pub files: Vec<BundledFile>,
+}
+
+impl BuiltinManifest {
+    pub fn parse(contents: &str) -> Result<Self> {
+        let manifest: Self = serde_json::from_str(contents)?;
+        if !manifest.files.iter().any(|file| file.path == manifest.executable) {
+            return Err(anyhow!("Manifest does not list the engine executable {}", manifest.executable));
+        }
+        // Files are copied to the same relative path, which must stay inside the directory
+        for file in &manifest.files {
+            if !Path::new(&file.path).components().all(|c| matches!(c, Component::Normal(_))) {
+                return Err(anyhow!("Invalid path in built-in engine manifest: {}", file.path));
+            }
+        }
+        Ok(manifest)
+    }
+
+    async fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let contents = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&contents)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvisionedEngine {
+    pub path: String,
+    /// None for an engine built in the workspace
+    pub version: Option<String>,
+    /// The files were copied out of the bundle by this call
+    pub copied: bool,
+}
+
+/// Directory the bundled files are copied into
+pub fn installed_dir() -> Result<PathBuf> {
+    Ok(EngineStorage::get_config_dir()?.join(BUNDLE_DIR))
+}
+
+/// Check every file listed in `manifest` against its hash
+pub async fn verify(dir: &Path, manifest: &BuiltinManifest) -> Result<()> {
+    for file in &manifest.files {
+        let actual = engine_validator::binary_sha256(&dir.join(&file.path).display().to_string()).await?;
+        if !actual.eq_ignore_ascii_case(&file.sha256) {
+            return Err(anyhow!("{} is damaged: expected SHA-256 {}, got {}", file.path, file.sha256, actual));
+        }
+    }
+    Ok(())
+}
+
+/// Copy the bundle in `bundle_dir` to `dest`, unless a verified copy of the same bundle is
+/// already there. `force` copies regardless.
+pub async fn provision(bundle_dir: &Path, dest: &Path, force: bool) -> Result<ProvisionedEngine> {
+    let manifest = BuiltinManifest::load(bundle_dir).await?;
+    let provisioned = |copied| ProvisionedEngine {
+        path: dest.join(&manifest.executable).display().to_string(),
+        version: Some(manifest.version.clone()),
+        copied,
+    };
+    if let (false, Ok(installed)) = (force, BuiltinManifest::load(dest).await) {
+        // Same bundle, or a downloaded release newer than it
+        let downloaded = is_newer(&installed.version, &manifest.version);
+        if installed == manifest || downloaded {
+            match verify(dest, &installed).await {
+                Ok(()) => {
+                    return Ok(ProvisionedEngine {
+                        path: dest.join(&installed.executable).display().to_string(),
+                        version: Some(installed.version),
+                        copied: false,
+                    })
+                }
+                Err(e) => log::warn!("Installed built-in engine failed verification, copying it again: {}", e),
+            }
+        } else {
+            log::info!("Replacing built-in engine {} with bundled {}", installed.version, manifest.version);
+        }
+    }
+    verify(bundle_dir, &manifest)
+        .await
+        .map_err(|e| anyhow!("The engine bundled with the app is damaged, please reinstall the app: {}", e))?;
+
+    let parent = dest.parent().ok_or_else(|| anyhow!("Invalid install directory: {}", dest.display()))?;
+    let staging = parent.join(format!(".{}-{}", BUNDLE_DIR, Uuid::new_v4()));
+    let result = async {
+        for file in &manifest.files {
+            let to = staging.join(&file.path);
+            if let Some(dir) = to.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            tokio::fs::copy(bundle_dir.join(&file.path), &to).await?;
+        }
+        make_executable(&staging.join(&manifest.executable))?;
+        verify(&staging, &manifest).await?;
+        // The manifest goes in last: a copy without one is never trusted
+        tokio::fs::write(staging.join(MANIFEST_FILE), serde_json::to_string_pretty(&manifest)?).await?;
+        if dest.exists() {
+            tokio::fs::remove_dir_all(dest).await?;
+        }
+        tokio::fs::rename(&staging, dest).await?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        return Err(e);
+    }
+
+    log::info!("Installed built-in engine {} to {}", manifest.version, dest.display());
+    Ok(provisioned(true))
+}
+
+/// Whether dotted version `candidate` is later than `current`, comparing numeric parts as
+/// numbers ("1.10" is later than "1.9"). A `-suffix` marks a prerelease, which comes before
+/// the release itself ("1.2.0-beta" before "1.2.0").
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<(u64, String)> {
+        version.split('.').map(|part| (part.parse().unwrap_or(0), part.to_string())).collect()
+    };
+    // (release parts, whether it is the release itself, prerelease parts)
+    let key = |version: &str| {
+        let version = version.trim_start_matches('v');
+        match version.split_once('-') {
+            Some((release, prerelease)) => (parts(release), false, parts(prerelease)),
+            None => (parts(version), true, Vec::new()),
+        }
+    };
+    key(candidate) > key(current)
+}
+
+/// One platform's download in an engine release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseDownload {
+    pub url: String,
+    pub sha256: String,
+    /// Hex Ed25519 signature of [`signed_payload`] for this download
+    pub signature: String,
+}
+
+/// The JSON document at the configured update URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineRelease {
+    pub version: String,
+    /// Keyed by `<os>-<arch>`, e.g. "linux-x86_64" or "windows-x86_64"
+    pub downloads: HashMap<String, ReleaseDownload>,
+}
+
+/// Key of this platform's download, e.g. "linux-x86_64"
+fn platform_key() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+impl EngineRelease {
+    pub fn for_this_platform(&self) -> Option<&ReleaseDownload> {
+        self.downloads.get(&platform_key())
+    }
+}
+
+/// What the release key signs for one download: the release version, the platform and the
+/// binary's SHA-256 in lowercase hex, one per line after a fixed first line. Binding the
+/// version stops a signed old binary being offered again under a higher version.
+pub fn signed_payload(version: &str, platform: &str, sha256: &str) -> String {
+    format!("ysu-engine-release\n{}\n{}\n{}\n", version, platform, sha256.to_lowercase())
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    let hex = hex.trim();
+    if hex.len() % 2 != 0 {
+        return Err(anyhow!("Invalid hex string"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("??"), 16).map_err(|_| anyhow!("Invalid hex string")))
+        .collect()
+}
+
+/// Check an Ed25519 signature of `data`, all keys and signatures hex-encoded
+pub fn verify_signature(data: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    let key: [u8; 32] = decode_hex(public_key)?
+        .try_into()
+        .map_err(|_| anyhow!("Update public key must be 32 bytes"))?;
+    let signature: [u8; 64] = decode_hex(signature)?
+        .try_into()
+        .map_err(|_| anyhow!("Signature must be 64 bytes"))?;
+    VerifyingKey::from_bytes(&key)
+        .map_err(|e| anyhow!("Invalid update public key: {}", e))?
+        .verify(data, &Signature::from_bytes(&signature))
+        .map_err(|_| anyhow!("Signature verification failed"))
+}
+
+/// Fetch the release description at `url`
+pub async fn fetch_release(url: &str) -> Result<EngineRelease> {
+    let dest = std::env::temp_dir().join(format!("ysu-engine-release-{}.json", Uuid::new_v4()));
+    let downloaded = download(url, &dest).await;
+    let contents = match downloaded {
+        Ok(()) => tokio::fs::read_to_string(&dest).await.map_err(anyhow::Error::from),
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&dest).await;
+    serde_json::from_str(&contents?).map_err(|e| anyhow!("Invalid engine release description: {}", e))
+}
+
+/// Replace the engine binary in `dest` with a downloaded release, keeping its data files.
+/// Nothing changes unless the download matches its hash and signature.
+pub async fn install_release(release: &EngineRelease, dest: &Path) -> Result<ProvisionedEngine> {
+    let public_key = UPDATE_PUBLIC_KEY.ok_or_else(|| anyhow!("This build cannot verify engine updates"))?;
+    let download_info = release
+        .for_this_platform()
+        .ok_or_else(|| anyhow!("Engine release {} has no download for this platform", release.version))?;
+    let mut manifest = BuiltinManifest::load(dest).await?;
+
+    let parent = dest.parent().ok_or_else(|| anyhow!("Invalid install directory: {}", dest.display()))?;
+    let staging = parent.join(format!(".{}-{}", BUNDLE_DIR, Uuid::new_v4()));
+    let result = async {
+        tokio::fs::create_dir_all(&staging).await?;
+        let binary = staging.join(&manifest.executable);
+        download(&download_info.url, &binary).await?;
+        let actual = engine_validator::binary_sha256(&binary.display().to_string()).await?;
+        if !actual.eq_ignore_ascii_case(&download_info.sha256) {
+            return Err(anyhow!("Download is damaged: expected SHA-256 {}, got {}", download_info.sha256, actual));
+        }
+        let payload = signed_payload(&release.version, &platform_key(), &download_info.sha256);
+        verify_signature(payload.as_bytes(), &download_info.signature, public_key)?;
+        for file in manifest.files.iter_mut() {
+            if file.path == manifest.executable {
+                file.sha256 = download_info.sha256.to_lowercase();
+            } else {
+                let to = staging.join(&file.path);
+                if let Some(dir) = to.parent() {
+                    tokio::fs::create_dir_all(dir).await?;
+                }
+                tokio::fs::copy(dest.join(&file.path), &to).await?;
+            }
+        }
+        manifest.version = release.version.clone();
+        make_executable(&binary)?;
+        verify(&staging, &manifest).await?;
+        tokio::fs::write(staging.join(MANIFEST_FILE), serde_json::to_string_pretty(&manifest)?).await?;
+        tokio::fs::remove_dir_all(dest).await?;
+        tokio::fs::rename(&staging, dest).await?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        return Err(e);
+    }
+
+    log::info!("Installed downloaded built-in engine {}", release.version);
+    Ok(ProvisionedEngine {
+        path: dest.join(&manifest.executable).display().to_string(),
+        version: Some(release.version.clone()),
+        copied: true,
+    })
+}
+
+/// A JSON object of option names to values; numbers and booleans are accepted as values
+fn parse_preset(contents: &str) -> Result<HashMap<String, String>> {
+    let preset: HashMap<String, serde_json::Value> = serde_json::from_str(contents)?;
+    preset
+        .into_iter()
+        .map(|(name, value)| match value {
+            serde_json::Value::String(value) => Ok((name, value)),
+            serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Ok((name, value.to_string())),
+            _ => Err(anyhow!("Option {} must be a string, number or boolean", name)),
+        })
+        .collect()
+}
+
+/// Option values the app prefers for the built-in engine: the user's preset file if there is a
+/// valid one, else the shipped preset
+pub fn option_preset() -> HashMap<String, String> {
+    let user_preset = EngineStorage::get_config_dir()
+        .map(|dir| dir.join(PRESET_FILE))
+        .ok()
+        .filter(|path| path.exists());
+    if let Some(path) = user_preset {
+        let preset = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| parse_preset(&contents));
+        match preset {
+            Ok(preset) => return preset,
+            Err(e) => log::warn!("Ignoring {}: {}", path.display(), e),
+        }
+    }
+    parse_preset(SHIPPED_PRESET).unwrap_or_default()
+}
+
+/// Saved options for a newly registered built-in engine: the defaults the engine reports, with
+/// preset values in their place. Preset values for options the engine lacks are dropped;
+/// without metadata the preset is used as is.
+pub fn default_options(metadata: Option<&EngineMetadata>, preset: &HashMap<String, String>) -> HashMap<String, String> {
+    let Some(metadata) = metadata else {
+        return preset.clone();
+    };
+    let mut options: HashMap<String, String> = metadata
+        .options
+        .iter()
+        .filter(|option| option.option_type != "button")
+        .filter_map(|option| Some((option.name.clone(), option.default.clone()?)))
+        .collect();
+    for (name, value) in preset {
+        if options.contains_key(name) {
+            options.insert(name.clone(), value.clone());
+        } else {
+            log::info!("Built-in engine has no option {}, ignoring its preset value", name);
+        }
+    }
+    options
+}
+
+/// The engine built in the workspace: the debug binary if there is one, else the release one
+fn workspace_engine() -> Result<ProvisionedEngine> {
+    let workspace_root = crate::commands::find_workspace_root()
+        .ok_or_else(|| anyhow!("Could not find workspace root"))?;
+    let binary = format!("usi-engine{}", std::env::consts::EXE_SUFFIX);
+    let path = ["debug", "release"]
+        .iter()
+        .map(|profile| workspace_root.join("target").join(profile).join(&binary))
+        .find(|path| path.exists())
+        .ok_or_else(|| {
+            anyhow!(
+                "Engine not found in {}. Please run 'cargo build --bin usi-engine --release' (or --debug) first.",
+                workspace_root.join("target").display()
+            )
+        })?;
+    Ok(ProvisionedEngine {
+        path: path.display().to_string(),
+        version: None,
+        copied: false,
+    })
+}
+
+/// Find the built-in engine, copying it out of the app's resources when needed
+pub async fn locate(app_handle: &tauri::AppHandle, force: bool) -> Result<ProvisionedEngine> {
+    use tauri::Manager;
+
+    if cfg!(debug_assertions) {
+        return workspace_engine();
+    }
+    // Resources listed by relative path keep it inside the resource directory
+    let bundle_dir = app_handle.path().resource_dir()?.join("resources").join(BUNDLE_DIR);
+    provision(&bundle_dir, &installed_dir()?, force).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shogi-vibe-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn sha256_of(path: &Path) -> String {
+        engine_validator::binary_sha256(&path.display().to_string()).await.unwrap()
+    }
+
+    async fn write_bundle(dir: &Path, engine: &str, sha256: Option<&str>) {
+        std::fs::write(dir.join("usi-engine"), engine).unwrap();
+        std::fs::create_dir_all(dir.join("eval")).unwrap();
+        std::fs::write(dir.join("eval/nn.bin"), "weights").unwrap();
+        let engine_sha256 = sha256_of(&dir.join("usi-engine")).await;
+        let manifest = BuiltinManifest {
+            version: "1.2.0".to_string(),
+            executable: "usi-engine".to_string(),
+            files: vec![
+                BundledFile {
+                    path: "usi-engine".to_string(),
+                    sha256: sha256.unwrap_or(&engine_sha256).to_string(),
+                },
+                BundledFile {
+                    path: "eval/nn.bin".to_string(),
+                    sha256: sha256_of(&dir.join("eval/nn.bin")).await,
+                },
+            ],
+        };
+        std::fs::write(dir.join(MANIFEST_FILE), serde_json::to_string(&manifest).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_default_options() {
+        use crate::engine_validator::EngineOption;
+
+        let option = |name: &str, option_type: &str, default: Option<&str>| EngineOption {
+            name: name.to_string(),
+            option_type: option_type.to_string(),
+            default: default.map(String::from),
+            min: None,
+            max: None,
+            var: Vec::new(),
+        };
+        let metadata = EngineMetadata {
+            name: "Built-in".to_string(),
+            author: None,
+            options: vec![
+                option("MaxDepth", "spin", Some("8")),
+                option("EnableTimeBudget", "check", Some("false")),
+                option("ClearHash", "button", None),
+            ],
+            encoding: Default::default(),
+            raw_banner: Vec::new(),
+            version: None,
+            family: None,
+        };
+        let preset = parse_preset(r#"{ "MaxDepth": 0, "Removed": "1" }"#).unwrap();
+
+        let options = default_options(Some(&metadata), &preset);
+        assert_eq!(options.len(), 2);
+        assert_eq!(options["MaxDepth"], "0");
+        assert_eq!(options["EnableTimeBudget"], "false");
+        assert_eq!(default_options(None, &preset), preset);
+
+        assert!(parse_preset(SHIPPED_PRESET).is_ok());
+        assert!(parse_preset(r#"{ "MaxDepth": [0] }"#).is_err());
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("1.10.0", "1.9.3"));
+        assert!(is_newer("v2.0", "1.99"));
+        assert!(!is_newer("1.2.0", "1.2.0"));
+        assert!(!is_newer("1.2", "1.2.1"));
+        assert!(!is_newer("1.2.0-beta", "1.2.0"));
+        assert!(is_newer("1.2.0", "1.2.0-beta"));
+        assert!(is_newer("1.2.0-beta.2", "1.2.0-beta.1"));
+        assert!(is_newer("1.2.0-beta", "1.1.9"));
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let public_key = hex(key.verifying_key().as_bytes());
+        let signature = hex(&key.sign(b"engine").to_bytes());
+
+        assert!(verify_signature(b"engine", &signature, &public_key).is_ok());
+        assert!(verify_signature(b"tampered", &signature, &public_key).is_err());
+        assert!(verify_signature(b"engine", "00", &public_key).is_err());
+
+        let payload = signed_payload("1.3.0", "linux-x86_64", "AB12");
+        assert_eq!(payload, "ysu-engine-release\n1.3.0\nlinux-x86_64\nab12\n");
+        let signature = hex(&key.sign(payload.as_bytes()).to_bytes());
+        assert!(verify_signature(payload.as_bytes(), &signature, &public_key).is_ok());
+        // The same binary offered under another version does not verify
+        let replayed = signed_payload("9.0.0", "linux-x86_64", "ab12");
+        assert!(verify_signature(replayed.as_bytes(), &signature, &public_key).is_err());
+    }
+
+    #[test]
+    fn test_manifest_paths_stay_inside_bundle() {
+        let manifest = |path: &str| {
+            format!(r#"{{ "version": "1", "executable": "{0}", "files": [{{ "path": "{0}", "sha256": "" }}] }}"#, path)
+        };
+        assert!(BuiltinManifest::parse(&manifest("bin/usi-engine")).is_ok());
+        assert!(BuiltinManifest::parse(&manifest("../usi-engine")).is_err());
+        assert!(BuiltinManifest::parse(&manifest("/usr/bin/usi-engine")).is_err());
+        assert!(BuiltinManifest::parse(r#"{ "version": "1", "executable": "usi-engine", "files": [] }"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provision_copies_and_verifies() {
+        let root = temp_dir();
+        let bundle = root.join("bundle");
+        let dest = root.join(BUNDLE_DIR);
+        std::fs::create_dir_all(&bundle).unwrap();
+        write_bundle(&bundle, "engine v1", None).await;
+
+        let engine = provision(&bundle, &dest, false).await.unwrap();
+        assert!(engine.copied);
+        assert_eq!(engine.version.as_deref(), Some("1.2.0"));
+        assert_eq!(std::fs::read_to_string(&engine.path).unwrap(), "engine v1");
+        assert!(!provision(&bundle, &dest, false).await.unwrap().copied);
+
+        // A downloaded release newer than the bundle is kept
+        let mut installed = BuiltinManifest::load(&dest).await.unwrap();
+        installed.version = "9.0.0".to_string();
+        std::fs::write(dest.join(MANIFEST_FILE), serde_json::to_string(&installed).unwrap()).unwrap();
+        assert_eq!(provision(&bundle, &dest, false).await.unwrap().version.as_deref(), Some("9.0.0"));
+
+        // A damaged copy is replaced
+        std::fs::write(dest.join("eval/nn.bin"), "truncated").unwrap();
+        let engine = provision(&bundle, &dest, false).await.unwrap();
+        assert!(engine.copied);
+        assert_eq!(engine.version.as_deref(), Some("1.2.0"));
+        assert_eq!(std::fs::read_to_string(dest.join("eval/nn.bin")).unwrap(), "weights");
+
+        // A damaged bundle is never installed
+        write_bundle(&bundle, "engine v2", Some("00")).await;
+        assert!(provision(&bundle, &dest, true).await.is_err());
+        assert_eq!(std::fs::read_to_string(dest.join("usi-engine")).unwrap(), "engine v1");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,236 @@
+//! Audit trail of the commands the UI invoked, for working out what it actually asked for
+//! Every invocation is recorded as it is dispatched, with its arguments: values under secret
+//! keys are redacted and long strings shortened. Tauri has no hook on a command's response, so
+//! the frontend's invoke wrapper tags each call with an ID in the `ysu-call-id` header and
+//! reports outcomes by that ID, batched into one record_command_results call. Concurrent calls
+//! of the same command therefore each get their own outcome. A record without an outcome is a
+//! command still running, or one whose caller never reported back.
+
+use crate::metrics;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::ipc::{Invoke, InvokeBody};
+use tauri::Runtime;
+
+const AUDIT_CAPACITY: usize = 500;
+
+/// Commands that only serve the audit trail itself
+const UNAUDITED_COMMANDS: [&str; 2] = ["record_command_results", "get_command_history"];
+
+/// Header carrying the frontend's ID for a call, which it reports the outcome under
+const CALL_ID_HEADER: &str = "ysu-call-id";
+
+/// Argument keys whose values are never recorded, matched case-insensitively as substrings
+const SECRET_KEYS: [&str; 3] = ["password", "secret", "token"];
+
+/// Longer string arguments (KIF text, SFEN lists) are cut to this many characters
+const MAX_ARGUMENT_CHARS: usize = 200;
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandOutcome {
+    Success,
+    /// The command ran and answered with `success: false`
+    Failure,
+    /// The invocation was rejected, e.g. for missing or malformed arguments
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandRecord {
+    pub id: u64,
+    pub command: String,
+    pub args: serde_json::Value,
+    pub started_at: String,
+    pub duration_ms: Option<u64>,
+    pub outcome: Option<CommandOutcome>,
+    /// Error message of a failed command
+    pub message: Option<String>,
+    #[serde(skip)]
+    call_id: Option<String>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+/// How one call ended, as reported by the frontend
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandResult {
+    pub call_id: String,
+    pub outcome: CommandOutcome,
+    pub message: Option<String>,
+}
+
+struct AuditLog {
+    records: VecDeque<CommandRecord>,
+    next_id: u64,
+    capacity: usize,
+}
+
+impl AuditLog {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::new(),
+            next_id: 1,
+            capacity,
+        }
+    }
+
+    fn begin(&mut self, command: &str, args: serde_json::Value, call_id: Option<String>) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(CommandRecord {
+            id: self.next_id,
+            command: command.to_string(),
+            args,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            duration_ms: None,
+            outcome: None,
+            message: None,
+            call_id,
+            started: Some(Instant::now()),
+        });
+        self.next_id += 1;
+    }
+
+    /// Complete the unfinished call tagged `call_id`; false if there is none
+    fn finish(&mut self, call_id: &str, outcome: CommandOutcome, message: Option<String>) -> bool {
+        let Some(record) = self
+            .records
+            .iter_mut()
+            .find(|record| record.call_id.as_deref() == Some(call_id) && record.outcome.is_none())
+        else {
+            return false;
+        };
+        if let Some(started) = record.started {
+            metrics::COMMAND_DURATION.record_since(started);
+            record.duration_ms = Some(started.elapsed().as_millis() as u64);
+        }
+        record.outcome = Some(outcome);
+        record.message = message;
+        true
+    }
+
+    /// The last `count` records, optionally only those of `command`, oldest first
+    fn history(&self, count: usize, command: Option<&str>) -> Vec<CommandRecord> {
+        let mut history: Vec<CommandRecord> = self
+            .records
+            .iter()
+            .rev()
+            .filter(|record| command.map_or(true, |command| record.command == command))
+            .take(count)
+            .cloned()
+            .collect();
+        history.reverse();
+        history
+    }
+}
+
+static AUDIT_LOG: Mutex<AuditLog> = Mutex::new(AuditLog::new(AUDIT_CAPACITY));
+
+fn audit_log() -> std::sync::MutexGuard<'static, AuditLog> {
+    AUDIT_LOG.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Wrap the generated command handler so every invocation is recorded before it runs
+pub fn audited<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        metrics::COMMANDS_INVOKED.increment();
+        let command = invoke.message.command();
+        if !UNAUDITED_COMMANDS.contains(&command) {
+            let args = match invoke.message.payload() {
+                InvokeBody::Json(args) => redact(args.clone()),
+                InvokeBody::Raw(bytes) => serde_json::json!(format!("<{} bytes>", bytes.len())),
+            };
+            let call_id = invoke
+                .message
+                .headers()
+                .get(CALL_ID_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            audit_log().begin(command, args, call_id);
+        }
+        handler(invoke)
+    }
+}
+
+/// Record how a call ended, as reported by the frontend; false if no unfinished call has its ID
+pub fn finish(result: CommandResult) -> bool {
+    audit_log().finish(&result.call_id, result.outcome, result.message)
+}
+
+/// The last `count` recorded invocations, optionally only those of `command`, oldest first
+pub fn history(count: usize, command: Option<&str>) -> Vec<CommandRecord> {
+    audit_log().history(count, command)
+}
+
+/// Replace values under secret keys and shorten long strings
+fn redact(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| {
+                let lowercase = key.to_ascii_lowercase();
+                if SECRET_KEYS.iter().any(|secret| lowercase.contains(secret)) {
+                    (key, serde_json::json!(REDACTED))
+                } else {
+                    (key, redact(value))
+                }
+            })
+            .collect(),
+        serde_json::Value::Array(values) => values.into_iter().map(redact).collect(),
+        serde_json::Value::String(text) if text.chars().count() > MAX_ARGUMENT_CHARS => {
+            let kept: String = text.chars().take(MAX_ARGUMENT_CHARS).collect();
+            serde_json::json!(format!("{}... ({} chars)", kept, text.chars().count()))
+        }
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let args = serde_json::json!({
+            "config": { "host": "wdoor.c.u-tokyo.ac.jp", "password": "hunter2", "apiToken": "abc" },
+            "kif": "x".repeat(MAX_ARGUMENT_CHARS + 50),
+            "moves": ["7g7f"],
+        });
+        let redacted = redact(args);
+        assert_eq!(redacted["config"]["host"], "wdoor.c.u-tokyo.ac.jp");
+        assert_eq!(redacted["config"]["password"], REDACTED);
+        assert_eq!(redacted["config"]["apiToken"], REDACTED);
+        assert!(redacted["kif"].as_str().unwrap().ends_with("... (250 chars)"));
+        assert_eq!(redacted["moves"][0], "7g7f");
+    }
+
+    #[test]
+    fn test_finish_completes_call_by_id() {
+        let mut log = AuditLog::new(3);
+        log.begin("stop_engine", serde_json::json!({ "engineId": "a" }), Some("1".to_string()));
+        log.begin("stop_engine", serde_json::json!({ "engineId": "b" }), Some("2".to_string()));
+        log.begin("get_engines", serde_json::json!({}), Some("3".to_string()));
+
+        // The second of two concurrent calls finishes first
+        assert!(log.finish("2", CommandOutcome::Failure, Some("Engine not found".to_string())));
+        assert!(!log.finish("2", CommandOutcome::Success, None));
+        assert!(!log.finish("4", CommandOutcome::Success, None));
+        let history = log.history(10, Some("stop_engine"));
+        assert_eq!(history[0].outcome, None);
+        assert_eq!(history[1].outcome, Some(CommandOutcome::Failure));
+        assert!(history[1].duration_ms.is_some());
+
+        log.begin("get_settings", serde_json::json!({}), None);
+        assert_eq!(log.history(10, None).len(), 3);
+        assert_eq!(log.history(10, None)[0].args["engineId"], "b");
+    }
+}
@@ -1,12 +1,58 @@
-use crate::engine_manager::EngineStatus;
-use crate::engine_storage::EngineConfig;
-use crate::engine_validator;
-use crate::engine_vs_engine::{EngineVsEngineConfig, EngineVsEngineManager};
-use crate::state::AppState;
+use crate::adjudication::AdjudicationConfig;
+use crate::analysis_queue::{AnalysisJobRequest, AnalysisTarget, JobStatus};
+use crate::autosave::{AutosaveStorage, SavedGame};
+use crate::batch_eval;
+use crate::builtin_engine;
+use crate::command_audit::{self, CommandResult};
+use crate::diagnostic_bundle::{self, BundleFile};
+use crate::cpu_affinity::CpuAffinity;
+use crate::engine_health::{self, EngineHealth, HealthStatus};
+use crate::engine_installer;
+use crate::engine_manager::{self, EngineStatus, InstancePurpose};
+use crate::engine_permissions;
+use crate::engine_storage::{EngineConfig, EnginePurpose};
+use crate::engine_validator::{self, ValidationOptions};
+use crate::engine_vs_engine::{seat_labels, EngineVsEngineConfig, EngineVsEngineManager, SearchLimit};
+use crate::eval_file::EvalFile;
+use crate::events;
+use crate::eval_graph::EvalGraph;
+use crate::commentary;
+use crate::game_record::{GameRecord, GameRecordStorage, MoveSymbol, Variation};
+use crate::game_session::{GameClocks, GameSession};
+use crate::gpu;
+use crate::handicap::Handicap;
+use crate::kif;
+use crate::log_buffer;
+use crate::message;
+use crate::message::Message;
+use crate::metrics;
+use crate::network_play::{self, CsaClientHandle, CsaClientState, CsaConnectionConfig, CsaStatus};
+use crate::notation::{self, NotationStyle};
+use crate::opening_book::{self, BookFilter, OpeningBook};
+use crate::position_match::{self, PositionMatchConfig};
+use crate::process_priority::ProcessPriority;
+use crate::selfplay::{self, DatagenConfig, DatagenProgress};
+use crate::settings_storage::AppSettings;
+use crate::shogi_rules::{Color, Position};
+use crate::state::{AppState, RunningMatch};
+use crate::tauri_events::{self, TauriEventSink};
+use crate::test_suite;
+use crate::think_log::ThinkLogStorage;
+use crate::usi_macro::UsiMacro;
+use crate::timeouts::{TimeoutConfig, TimeoutOverrides};
+use crate::tournament::{self, ResultsFormat, Tournament, TournamentConfig, TournamentStatus, TournamentStorage};
+use crate::strength::{self, StrengthLevel};
+use crate::tuning::{self, TuningConfig, TuningSession, TuningStatus, TuningStorage};
+use crate::usi_bridge::{self, BridgeStatus};
+use crate::usi_info::{self, Score, UsiInfo};
+use crate::win_rate::{self, WinRateCalibration};
 use anyhow::Result;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use tauri::State;
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EngineInfo {
     pub id: String,
@@ -15,11 +61,15 @@ pub struct EngineInfo {
     pub status: EngineStatus,
 }
 
+/// Result of a command. Failures carry a message code and its parameters alongside the
+/// English text, so the UI can show them in the user's language.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CommandResponse {
     pub success: bool,
     pub message: Option<String>,
     pub data: Option<serde_json::Value>,
+    pub code: Option<String>,
+    pub params: Option<BTreeMap<String, String>>,
 }
 
 impl CommandResponse {
@@ -28,6 +78,8 @@ impl CommandResponse {
             success: true,
             message: None,
             data: None,
+            code: None,
+            params: None,
         }
     }
 
@@ -36,25 +88,54 @@ impl CommandResponse {
             success: true,
             message: None,
             data: Some(data),
+            code: None,
+            params: None,
         }
     }
 
+    /// An error without a message code, for text that comes from elsewhere
     pub fn error(message: String) -> Self {
         Self {
             success: false,
             message: Some(message),
             data: None,
+            code: None,
+            params: None,
+        }
+    }
+
+    /// An error with details the UI can act on
+    pub fn error_with_data(message: Message, data: serde_json::Value) -> Self {
+        Self {
+            data: Some(data),
+            ..Self::failure(message)
+        }
+    }
+
+    pub fn failure(message: Message) -> Self {
+        Self {
+            success: false,
+            message: Some(message.text),
+            data: None,
+            code: Some(message.code),
+            params: Some(message.params),
         }
     }
 }
 
 /// Spawn a new USI engine process
+/// `engine_id` is the config ID; the returned `engine_id` is the runtime ID of the new instance,
+/// so the same engine can run several times at once
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_engine(
+    app_handle: tauri::AppHandle,
     engine_id: String,
     name: String,
     path: String,
     temp_options: Option<std::collections::HashMap<String, String>>,
+    purpose: Option<InstancePurpose>,
+    priority: Option<ProcessPriority>,
     state: State<'_, AppState>,
 ) -> Result<CommandResponse, String> {
     log::info!("Command: spawn_engine - id: {}, name: {}, path: {}", engine_id, name, path);
@@ -62,29 +143,123 @@ pub async fn spawn_engine(
         log::info!("Using {} temporary options for this game", opts.len());
     }
 
+    // Fall back to the priority configured for the engine
+    let priority = match priority {
+        Some(priority) => Some(priority),
+        None => state.engine_storage.read().await.get_engine(&engine_id).and_then(|e| e.priority),
+    };
+
+    spawn_and_initialize(&app_handle, &state, &engine_id, name, path, purpose.unwrap_or_default(), priority, temp_options, None).await
+}
+
+/// Spawn another instance of a configured engine, e.g. an analysis engine alongside
+/// the one playing the game, and return its runtime ID.
+/// `eval_file_id` selects a different registered eval file for this instance only.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn spawn_engine_instance(
+    app_handle: tauri::AppHandle,
+    config_id: String,
+    purpose: InstancePurpose,
+    temp_options: Option<std::collections::HashMap<String, String>>,
+    priority: Option<ProcessPriority>,
+    eval_file_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: spawn_engine_instance - config_id: {}, purpose: {:?}", config_id, purpose);
+
+    let storage = state.engine_storage.read().await;
+    let (name, path, priority) = match storage.usable_engine(&config_id) {
+        Ok(engine) => (engine.name.clone(), engine.path.clone(), priority.or(engine.priority)),
+        Err(e) => return Ok(CommandResponse::error(e.to_string())),
+    };
+    drop(storage);
+
+    spawn_and_initialize(&app_handle, &state, &config_id, name, path, purpose, priority, temp_options, eval_file_id).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn spawn_and_initialize(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    config_id: &str,
+    name: String,
+    path: String,
+    purpose: InstancePurpose,
+    priority: Option<ProcessPriority>,
+    temp_options: Option<std::collections::HashMap<String, String>>,
+    eval_file_id: Option<String>,
+) -> Result<CommandResponse, String> {
     let manager = &state.engine_manager;
+    let engine_path = path.clone();
+
+    // Warn when the binary was overwritten since it was validated, e.g. by a rebuild
+    let mut warnings = Vec::new();
+    let engine = state.engine_storage.read().await.get_engine(config_id).cloned();
+    if let Some(engine) = engine {
+        check_engine_binary(app_handle, &engine, &mut warnings).await;
+    }
     
-    match manager.spawn_engine(engine_id.clone(), name, path).await {
-        Ok(_) => {
-            // Initialize the engine with USI protocol and send options
-            // Use temp_options if provided, otherwise use saved options from storage
-            if let Err(e) = manager.initialize_engine_with_temp_options(
-                &engine_id, 
-                &state.engine_storage,
-                temp_options.as_ref()
-            ).await {
-                log::error!("Failed to initialize engine: {}", e);
-                let _ = manager.stop_engine(&engine_id).await;
-                return Ok(CommandResponse::error(format!("Failed to initialize engine: {}", e)));
+    match manager
+        .start_engine(
+            config_id,
+            name,
+            path,
+            purpose,
+            priority,
+            &state.engine_storage,
+            temp_options.as_ref(),
+            eval_file_id.as_deref(),
+        )
+        .await
+    {
+        Ok(runtime_id) => {
+            let runtime_id = runtime_id.to_string();
+            Ok(CommandResponse::success_with_data(serde_json::json!({
+                "engine_id": runtime_id,
+                "runtime_id": runtime_id,
+                "config_id": config_id,
+                "purpose": purpose,
+                "warnings": warnings,
+            })))
+        }
+        Err(e) => {
+            log::error!("Failed to start engine: {}", e);
+            // Gatekeeper kills a quarantined engine as it starts; say so instead of the bare failure
+            let e = engine_permissions::ensure_not_quarantined(std::path::Path::new(&engine_path)).await.err().unwrap_or(e);
+            Ok(engine_error(message!("failed_to_start_engine", "Failed to start engine: {error}", error = e), &e))
+        }
+    }
+}
+
+/// An error response for an engine that failed to validate or start. Failures the UI explains
+/// specially, such as a quarantined engine, get their own message code instead of `message`.
+fn engine_error(message: Message, error: &anyhow::Error) -> CommandResponse {
+    match error.downcast_ref::<engine_permissions::Quarantined>() {
+        Some(quarantined) => CommandResponse::failure(quarantined.message()),
+        None => CommandResponse::failure(message),
+    }
+}
+
+/// Hash an engine binary before it is used and return the hash. A mismatch with the hash
+/// recorded at validation is logged, emitted as `engine-binary-changed` and added to `warnings`.
+async fn check_engine_binary(
+    app_handle: &tauri::AppHandle,
+    engine: &EngineConfig,
+    warnings: &mut Vec<String>,
+) -> Option<String> {
+    match engine.check_binary().await {
+        Ok((hash, changed)) => {
+            if let Some(changed) = changed {
+                log::warn!("{} (expected {}, found {})", changed, changed.expected_sha256, changed.actual_sha256);
+                let _ = tauri_events::send(app_handle, &events::ENGINE_BINARY_CHANGED, &changed);
+                warnings.push(changed.to_string());
             }
-            
-            Ok(CommandResponse::success_with_data(
-                serde_json::json!({ "engine_id": engine_id })
-            ))
+            Some(hash)
         }
         Err(e) => {
-            log::error!("Failed to spawn engine: {}", e);
-            Ok(CommandResponse::error(format!("Failed to spawn engine: {}", e)))
+            log::warn!("Could not check engine binary {}: {}", engine.path, e);
+            None
         }
     }
 }
@@ -104,7 +279,64 @@ pub async fn send_usi_command(
         Ok(_) => Ok(CommandResponse::success()),
         Err(e) => {
             log::error!("Failed to send command to engine: {}", e);
-            Ok(CommandResponse::error(format!("Failed to send command: {}", e)))
+            Ok(CommandResponse::failure(message!(
+                "failed_to_send_command", "Failed to send command: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Start a new game on a running engine: stop any search, wait for readyok, then send
+/// usinewgame. Use this rather than sending the commands one by one.
+#[tauri::command]
+pub async fn new_game(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: new_game - engine_id: {}", engine_id);
+
+    match state.engine_manager.new_game(&engine_id, &state.engine_storage).await {
+        Ok(_) => Ok(CommandResponse::success()),
+        Err(e) => {
+            log::error!("Failed to start a new game: {}", e);
+            Ok(CommandResponse::failure(message!("failed_to_start_new_game", "Failed to start a new game: {error}", error = e)))
+        }
+    }
+}
+
+/// Put a running engine into console mode: lines sent with send_usi_command and everything
+/// the engine prints arrive as `engine-console::<runtime id>`. Returns the runtime ID.
+#[tauri::command]
+pub async fn open_console(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: open_console - engine_id: {}", engine_id);
+
+    match state.engine_manager.open_console(&engine_id).await {
+        Ok(runtime_id) => Ok(CommandResponse::success_with_data(serde_json::json!({
+            "runtime_id": runtime_id.to_string(),
+        }))),
+        Err(e) => {
+            log::error!("Failed to open console: {}", e);
+            Ok(CommandResponse::failure(message!("failed_to_open_console", "Failed to open console: {error}", error = e)))
+        }
+    }
+}
+
+/// Take an engine out of console mode
+#[tauri::command]
+pub async fn close_console(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: close_console - engine_id: {}", engine_id);
+
+    match state.engine_manager.close_console(&engine_id).await {
+        Ok(_) => Ok(CommandResponse::success()),
+        Err(e) => {
+            log::error!("Failed to close console: {}", e);
+            Ok(CommandResponse::failure(message!("failed_to_close_console", "Failed to close console: {error}", error = e)))
         }
     }
 }
@@ -123,11 +355,28 @@ pub async fn stop_engine(
         Ok(_) => Ok(CommandResponse::success()),
         Err(e) => {
             log::error!("Failed to stop engine: {}", e);
-            Ok(CommandResponse::error(format!("Failed to stop engine: {}", e)))
+            Ok(CommandResponse::failure(message!("failed_to_stop_engine", "Failed to stop engine: {error}", error = e)))
         }
     }
 }
 
+/// Exempt a running engine from the idle policy, or make it subject to it again
+#[tauri::command]
+pub async fn set_engine_keep_alive(
+    engine_id: String,
+    keep_alive: bool,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_keep_alive - engine_id: {}, keep_alive: {}", engine_id, keep_alive);
+
+    match state.engine_manager.set_keep_alive(&engine_id, keep_alive).await {
+        Ok(()) => Ok(CommandResponse::success()),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_set_keep_alive", "Failed to set keep-alive: {error}", error = e
+        ))),
+    }
+}
+
 /// Get the status of a specific engine
 #[tauri::command]
 pub async fn get_engine_status(
@@ -140,7 +389,112 @@ pub async fn get_engine_status(
         Some(status) => Ok(CommandResponse::success_with_data(
             serde_json::json!({ "status": status })
         )),
-        None => Ok(CommandResponse::error("Engine not found".to_string())),
+        None => Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = engine_id
+        ))),
+    }
+}
+
+/// Get the crashes of an engine config's instances this session, oldest first
+#[tauri::command]
+pub async fn get_engine_crash_history(
+    config_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: get_engine_crash_history - config_id: {}", config_id);
+    let crashes = state.engine_manager.crash_history(&config_id);
+    Ok(CommandResponse::success_with_data(
+        serde_json::json!({ "crashes": crashes })
+    ))
+}
+
+/// Get the last `info string` messages of a running engine, oldest first
+#[tauri::command]
+pub async fn get_engine_messages(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    match state.engine_manager.engine_messages(&engine_id).await {
+        Ok(messages) => Ok(CommandResponse::success_with_data(
+            serde_json::json!({ "messages": messages })
+        )),
+        Err(e) => Ok(CommandResponse::error(e.to_string())),
+    }
+}
+
+/// List the USI lines that starting the engine would send (usi, setoption..., isready), from
+/// its stored config and validation metadata, without spawning it. `options` replace the saved
+/// options, as temporary options do when an engine is started.
+#[tauri::command]
+pub async fn preview_engine_init(
+    engine_id: String,
+    options: Option<std::collections::HashMap<String, String>>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: preview_engine_init - engine_id: {}", engine_id);
+
+    let storage = state.engine_storage.read().await;
+    match engine_manager::preview_init(&storage, &engine_id, options.as_ref()) {
+        Ok(preview) => Ok(CommandResponse::success_with_data(
+            serde_json::to_value(&preview).unwrap_or(serde_json::json!({}))
+        )),
+        Err(_) => Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = engine_id
+        ))),
+    }
+}
+
+/// Get the consolidated analysis of a running engine's current or last search, for frontends
+/// that poll instead of listening to `analysis-snapshot` events
+#[tauri::command]
+pub async fn get_latest_analysis(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    match state.engine_manager.latest_analysis(&engine_id).await {
+        Ok(snapshot) => Ok(CommandResponse::success_with_data(
+            serde_json::to_value(&snapshot).unwrap_or(serde_json::json!({}))
+        )),
+        Err(e) => Ok(CommandResponse::error(e.to_string())),
+    }
+}
+
+/// Get the last position sent to a running engine with its move list; null before the first
+#[tauri::command]
+pub async fn get_engine_position(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: get_engine_position - engine_id: {}", engine_id);
+
+    match state.engine_manager.engine_position(&engine_id).await {
+        Ok(position) => Ok(CommandResponse::success_with_data(
+            serde_json::to_value(&position).unwrap_or(serde_json::json!(null))
+        )),
+        Err(e) => Ok(CommandResponse::error(e.to_string())),
+    }
+}
+
+/// Get an engine's usage totals, including usage not yet written to storage
+#[tauri::command]
+pub async fn get_engine_stats(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: get_engine_stats - engine_id: {}", engine_id);
+    if let Err(e) = state.engine_manager.flush_usage(&state.engine_storage).await {
+        log::error!("Failed to save engine usage: {}", e);
+    }
+    let storage = state.engine_storage.read().await;
+    match storage.get_engine(&engine_id) {
+        Some(engine) => Ok(CommandResponse::success_with_data(serde_json::json!({
+            "usage": engine.usage,
+            "analysis_hours": engine.usage.analysis_ms as f64 / 3_600_000.0,
+            "last_used": engine.last_used,
+        }))),
+        None => Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = engine_id
+        ))),
     }
 }
 
@@ -157,6 +511,77 @@ pub async fn list_engines(
     ))
 }
 
+/// List running engine instances, optionally only those of one engine config
+#[tauri::command]
+pub async fn list_engine_instances(
+    config_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let manager = &state.engine_manager;
+    let instances = manager.list_instances(config_id.as_deref()).await;
+
+    Ok(CommandResponse::success_with_data(
+        serde_json::json!({ "instances": instances })
+    ))
+}
+
+/// Describe everything running in the backend: engine instances with their status,
+/// unfinished analysis jobs, matches, game sessions, tuning runs, tournaments, health checks and network
+/// connections. A reloaded webview calls this to reattach instead of starting over.
+#[tauri::command]
+pub async fn describe_runtime_state(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: describe_runtime_state");
+
+    let instances = state.engine_manager.list_instances(None).await;
+    let analysis_jobs: Vec<_> = state
+        .analysis_queue
+        .list()
+        .await
+        .into_iter()
+        .filter(|job| !job.status.is_finished())
+        .collect();
+
+    let mut matches = Vec::new();
+    for running in state.running_matches.read().await.values() {
+        let match_state = running.state.lock().await.clone();
+        matches.push(serde_json::json!({
+            "match_id": match_state.match_id,
+            "engine1_name": running.config.engine1_name,
+            "engine2_name": running.config.engine2_name,
+            "move_delay_ms": running.move_delay_ms.load(std::sync::atomic::Ordering::Relaxed),
+            "state": match_state,
+        }));
+    }
+    let game_sessions: Vec<_> = state.game_sessions.read().await.iter().map(game_session_json).collect();
+    let tuning_runs: Vec<String> = state.tuning_runs.read().await.keys().cloned().collect();
+    let tournament_runs: Vec<String> = state.tournament_runs.read().await.keys().cloned().collect();
+    let datagen_runs: Vec<String> = state.datagen_runs.read().await.keys().cloned().collect();
+    let health_checks: Vec<String> = state.health_checks.read().await.keys().cloned().collect();
+    let csa_status = match state.csa_client.read().await.as_ref() {
+        Some(handle) => serde_json::to_value(&*handle.state.read().await).unwrap_or(serde_json::json!({})),
+        None => serde_json::json!({ "status": CsaStatus::Disconnected }),
+    };
+    let bridge_status = match state.bridge.read().await.as_ref() {
+        Some(handle) => handle.status(),
+        None => BridgeStatus { running: false, port: None, connections: 0 },
+    };
+
+    Ok(CommandResponse::success_with_data(serde_json::json!({
+        "instances": instances,
+        "analysis_jobs": analysis_jobs,
+        "matches": matches,
+        "game_sessions": game_sessions,
+        "tuning_runs": tuning_runs,
+        "tournament_runs": tournament_runs,
+        "datagen_runs": datagen_runs,
+        "health_checks": health_checks,
+        "csa": csa_status,
+        "bridge": bridge_status,
+    })))
+}
+
 /// Stop all engines (cleanup)
 #[tauri::command]
 pub async fn stop_all_engines(
@@ -170,7 +595,9 @@ pub async fn stop_all_engines(
         Ok(_) => Ok(CommandResponse::success()),
         Err(e) => {
             log::error!("Failed to stop all engines: {}", e);
-            Ok(CommandResponse::error(format!("Failed to stop all engines: {}", e)))
+            Ok(CommandResponse::failure(message!(
+                "failed_to_stop_all_engines", "Failed to stop all engines: {error}", error = e
+            )))
         }
     }
 }
@@ -214,8 +641,8 @@ pub fn find_workspace_root() -> Option<std::path::PathBuf> {
                     for (i, line) in lines.iter().enumerate() {
                         if line.trim() == "[[bin]]" && i + 1 < lines.len() {
                             // Check next few lines for name = "usi-engine"
-                            for j in (i + 1)..std::cmp::min(i + 5, lines.len()) {
-                                if lines[j].contains("name = \"usi-engine\"") || lines[j].contains("name = 'usi-engine'") {
+                            for next_line in lines.iter().take(std::cmp::min(i + 5, lines.len())).skip(i + 1) {
+                                if next_line.contains("name = \"usi-engine\"") || next_line.contains("name = 'usi-engine'") {
                                     log::debug!("Found workspace root via Cargo.toml: {}", current.display());
                                     return Some(current.to_path_buf());
                                 }
@@ -245,127 +672,20 @@ pub fn find_workspace_root() -> Option<std::path::PathBuf> {
     None
 }
 
-/// Get the path to the bundled built-in engine
+/// Get the path to the built-in engine, copying it out of the app bundle if needed
 #[tauri::command]
 pub async fn get_builtin_engine_path(
     app_handle: tauri::AppHandle,
 ) -> Result<CommandResponse, String> {
-    use tauri::Manager;
-
-    if cfg!(debug_assertions) {
-        // Development mode - find the engine in the workspace
-        // Try debug binary first (for faster iteration), then release
-        let workspace_root = find_workspace_root()
-            .ok_or_else(|| "Could not find workspace root".to_string())?;
-        
-        // Try debug binary first (if it exists and is recent)
-        let debug_engine_path = workspace_root.join("target/debug/usi-engine");
-        let release_engine_path = workspace_root.join("target/release/usi-engine");
-        
-        // Prefer debug binary if it exists (for development), otherwise use release
-        let engine_path = if debug_engine_path.exists() {
-            debug_engine_path
-        } else if release_engine_path.exists() {
-            release_engine_path
-        } else {
-            log::warn!("Engine not found at debug or release path. Attempting to use release...");
-            release_engine_path
-        };
-        
-        let engine_path_str = engine_path.display().to_string();
-        
-        if !engine_path.exists() {
-            log::warn!("Engine not found at: {}. Attempting to build it...", engine_path_str);
-            return Ok(CommandResponse::error(format!(
-                "Engine not found at: {}. Please run 'cargo build --bin usi-engine --release' (or --debug) first.",
-                engine_path_str
-            )));
-        }
-        
-        log::info!("Built-in engine path: {}", engine_path_str);
-        return Ok(CommandResponse::success_with_data(
-            serde_json::json!({ "path": engine_path_str })
-        ));
-    }
-    
-    // Production mode - try to find the engine relative to the executable
-    // In a bundled Tauri app, the executable is in the app bundle
-    // The engine should be in the same directory or a resources directory
-    
-    // First, try to find it relative to the executable
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            // Try common locations in a Tauri bundle
-            let mut possible_paths = vec![
-                exe_dir.join("usi-engine"),
-                exe_dir.join("resources").join("usi-engine"),
-            ];
-            
-            // Add macOS Resources path if it exists
-            if let Some(resources_path) = exe_dir.parent()
-                .and_then(|p| p.parent())
-                .map(|p| p.join("Resources").join("usi-engine")) {
-                possible_paths.push(resources_path);
-            }
-            
-            // On Windows, also try with .exe extension
-            #[cfg(target_os = "windows")]
-            {
-                let mut windows_paths = Vec::new();
-                for p in &possible_paths {
-                    if let Some(parent) = p.parent() {
-                        if let Some(stem) = p.file_stem().and_then(|s| s.to_str()) {
-                            windows_paths.push(parent.join(format!("{}.exe", stem)));
-                        }
-                    }
-                }
-                // Check Windows paths first
-                if let Some(path) = windows_paths.iter().find(|p| p.exists()) {
-                    log::info!("Built-in engine path: {}", path.display());
-                    return Ok(CommandResponse::success_with_data(
-                        serde_json::json!({ "path": path.display().to_string() })
-                    ));
-                }
-            }
-            
-            if let Some(path) = possible_paths.iter().find(|p| p.exists()) {
-                log::info!("Built-in engine path: {}", path.display());
-                return Ok(CommandResponse::success_with_data(
-                    serde_json::json!({ "path": path.display().to_string() })
-                ));
-            }
-        }
-    }
-    
-    // Fallback: try resource directory
-    if let Ok(resource_dir) = app_handle.path().resource_dir() {
-        let engine_path = resource_dir.join("usi-engine");
-        #[cfg(target_os = "windows")]
-        let engine_path = resource_dir.join("usi-engine.exe");
-        
-        if engine_path.exists() {
-            log::info!("Built-in engine path: {}", engine_path.display());
-            return Ok(CommandResponse::success_with_data(
-                serde_json::json!({ "path": engine_path.display().to_string() })
-            ));
-        }
-    }
-    
-    // Last resort: try workspace root (for development builds that are "release")
-    if let Some(workspace_root) = find_workspace_root() {
-        let engine_path = workspace_root.join("target/release/usi-engine");
-        if engine_path.exists() {
-            log::info!("Built-in engine path: {}", engine_path.display());
-            return Ok(CommandResponse::success_with_data(
-                serde_json::json!({ "path": engine_path.display().to_string() })
-            ));
+    match builtin_engine::locate(&app_handle, false).await {
+        Ok(engine) => {
+            log::info!("Built-in engine path: {}", engine.path);
+            Ok(CommandResponse::success_with_data(
+                serde_json::json!({ "path": engine.path, "version": engine.version })
+            ))
         }
+        Err(e) => Ok(CommandResponse::error(e.to_string())),
     }
-    
-    // Could not find engine
-    Ok(CommandResponse::error(
-        "Engine binary not found in production bundle".to_string()
-    ))
 }
 
 /// Add a new engine to the configuration
@@ -373,9 +693,28 @@ pub async fn get_builtin_engine_path(
 pub async fn add_engine(
     name: String,
     path: String,
+    self_test: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<CommandResponse, String> {
-    log::info!("Command: add_engine - name: {}, path: {}", name, path);
+    log::info!("Command: add_engine - name: {}, path: {}, self_test: {:?}", name, path, self_test);
+
+    // Catch files the OS would refuse to run before trying to start them; the UI can offer
+    // fix_engine_permissions for the fixable ones
+    match engine_permissions::check(std::path::Path::new(&path)).await {
+        Ok(issues) if !issues.is_empty() => {
+            log::warn!("Engine {} cannot be run: {:?}", path, issues);
+            let problems = issues.iter().map(|issue| issue.describe()).collect::<Vec<_>>().join("; ");
+            return Ok(CommandResponse::error_with_data(
+                message!("engine_cannot_run", "Engine cannot be run: {problems}", problems = problems),
+                serde_json::json!({
+                    "permission_issues": issues,
+                    "fixable": issues.iter().all(|issue| issue.is_fixable()),
+                }),
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => return Ok(CommandResponse::error(e.to_string())),
+    }
 
     // Validate the engine
     let metadata = match engine_validator::validate_engine(&path).await {
@@ -385,12 +724,16 @@ pub async fn add_engine(
         }
         Err(e) => {
             log::error!("Engine validation failed: {}", e);
-            return Ok(CommandResponse::error(format!("Engine validation failed: {}", e)));
+            return Ok(engine_error(message!(
+                "engine_validation_failed", "Engine validation failed: {error}", error = e
+            ), &e));
         }
     };
 
     // Create engine config
-    let config = EngineConfig::new(name, path, metadata, false);
+    let mut config = EngineConfig::new(name, path, metadata, false);
+    config.record_binary_hash().await;
+    let warnings = apply_gpu_profile(&mut config).await;
     let engine_id = config.id.clone();
 
     // Add to storage
@@ -400,17 +743,108 @@ pub async fn add_engine(
             // Save to disk
             if let Err(e) = storage.save().await {
                 log::error!("Failed to save engine storage: {}", e);
-                return Ok(CommandResponse::error(format!("Failed to save configuration: {}", e)));
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+                )));
             }
 
             log::info!("Engine added successfully: {}", engine_id);
-            Ok(CommandResponse::success_with_data(
-                serde_json::to_value(&config).unwrap_or(serde_json::json!({}))
-            ))
+            drop(storage);
+
+            let mut data = serde_json::to_value(&config).unwrap_or(serde_json::json!({}));
+            data["warnings"] = serde_json::json!(warnings);
+            // A quick search from the starting position, reported alongside the new config;
+            // the engine stays added either way
+            if self_test.unwrap_or(false) {
+                let result = engine_validator::self_test(&config.path, &engine_validator::ValidationOptions::default()).await;
+                log::info!("Engine self-test for {}: passed={}", engine_id, result.passed);
+                data["self_test"] = serde_json::to_value(&result).unwrap_or(serde_json::Value::Null);
+            }
+            Ok(CommandResponse::success_with_data(data))
+        }
+        Err(e) => {
+            log::error!("Failed to add engine: {}", e);
+            Ok(CommandResponse::failure(message!("failed_to_add_engine", "Failed to add engine: {error}", error = e)))
+        }
+    }
+}
+
+/// Give a new dlshogi-style engine the GPU profile (see the gpu module), returning warnings
+/// for the user, e.g. when there is no CUDA GPU
+async fn apply_gpu_profile(config: &mut EngineConfig) -> Vec<String> {
+    if !config.metadata.as_ref().is_some_and(gpu::is_deep_learning) {
+        return Vec::new();
+    }
+    let warnings = gpu::apply_profile(config, &gpu::detect().await);
+    for warning in &warnings {
+        log::warn!("{}", warning);
+    }
+    warnings
+}
+
+/// Detected GPUs and whether CUDA engines can run
+#[tauri::command]
+pub async fn get_gpu_info() -> Result<CommandResponse, String> {
+    log::info!("Command: get_gpu_info");
+    let info = gpu::detect().await;
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(&info).unwrap_or(serde_json::json!({}))
+    ))
+}
+
+/// Install an engine from a .zip or .tar.gz archive (local path or http(s) URL) into the
+/// managed engines directory, then validate and register it. Either the archive's published
+/// `sha256` or the confirmed `executable` (relative to the archive root) is required before
+/// anything in it is run; with neither, the failure lists the executables found.
+#[tauri::command]
+pub async fn install_engine_from_archive(
+    path_or_url: String,
+    dest_name: String,
+    sha256: Option<String>,
+    executable: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: install_engine_from_archive - source: {}, dest_name: {}", path_or_url, dest_name);
+
+    let installed = engine_installer::install_from_archive(
+        &path_or_url,
+        &dest_name,
+        sha256.as_deref(),
+        executable.as_deref(),
+    )
+    .await;
+    let mut config = match installed {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Engine installation failed: {}", e);
+            return Ok(CommandResponse::failure(message!(
+                "engine_installation_failed", "Engine installation failed: {error}", error = e
+            )));
+        }
+    };
+    let warnings = apply_gpu_profile(&mut config).await;
+    let engine_id = config.id.clone();
+
+    // Add to storage
+    let mut storage = state.engine_storage.write().await;
+    match storage.add_engine(config.clone()) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+                )));
+            }
+
+            log::info!("Engine installed successfully: {}", engine_id);
+            let mut data = serde_json::to_value(&config).unwrap_or(serde_json::json!({}));
+            data["warnings"] = serde_json::json!(warnings);
+            Ok(CommandResponse::success_with_data(data))
         }
         Err(e) => {
             log::error!("Failed to add engine: {}", e);
-            Ok(CommandResponse::error(format!("Failed to add engine: {}", e)))
+            Ok(CommandResponse::failure(message!("failed_to_add_engine", "Failed to add engine: {error}", error = e)))
         }
     }
 }
@@ -428,7 +862,9 @@ pub async fn remove_engine(
     // Check if it's the built-in engine
     if let Some(engine) = storage.get_engine(&engine_id) {
         if engine.is_builtin {
-            return Ok(CommandResponse::error("Cannot remove the built-in engine".to_string()));
+            return Ok(CommandResponse::failure(message!(
+                "cannot_remove_builtin_engine", "Cannot remove the built-in engine"
+            )));
         }
     }
 
@@ -437,40 +873,162 @@ pub async fn remove_engine(
             // Save to disk
             if let Err(e) = storage.save().await {
                 log::error!("Failed to save engine storage: {}", e);
-                return Ok(CommandResponse::error(format!("Failed to save configuration: {}", e)));
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+                )));
             }
 
-            log::info!("Engine removed successfully: {}", engine_id);
+            log::info!("Engine moved to trash: {}", engine_id);
             Ok(CommandResponse::success())
         }
         Err(e) => {
             log::error!("Failed to remove engine: {}", e);
-            Ok(CommandResponse::error(format!("Failed to remove engine: {}", e)))
+            Ok(CommandResponse::failure(message!(
+                "failed_to_remove_engine", "Failed to remove engine: {error}", error = e
+            )))
         }
     }
 }
 
-/// Get all configured engines
+/// List removed engines that can be restored, oldest first
 #[tauri::command]
-pub async fn get_engines(
-    state: State<'_, AppState>,
-) -> Result<CommandResponse, String> {
+pub async fn get_engine_trash(state: State<'_, AppState>) -> Result<CommandResponse, String> {
     let storage = state.engine_storage.read().await;
-    let engines = storage.get_all_engines();
-    
     Ok(CommandResponse::success_with_data(
-        serde_json::to_value(engines).unwrap_or(serde_json::json!([]))
+        serde_json::to_value(&storage.trash).unwrap_or(serde_json::json!([]))
     ))
 }
 
-/// Validate an engine at a given path
+/// Restore a removed engine with its saved options and usage
 #[tauri::command]
-pub async fn validate_engine_path(
-    path: String,
+pub async fn restore_engine(
+    engine_id: String,
+    state: State<'_, AppState>,
 ) -> Result<CommandResponse, String> {
-    log::info!("Command: validate_engine_path - path: {}", path);
+    log::info!("Command: restore_engine - engine_id: {}", engine_id);
 
-    match engine_validator::validate_engine(&path).await {
+    let mut storage = state.engine_storage.write().await;
+    if let Err(e) = storage.restore_engine(&engine_id) {
+        log::error!("Failed to restore engine: {}", e);
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_restore_engine", "Failed to restore engine: {error}", error = e
+        )));
+    }
+    if let Err(e) = storage.save().await {
+        log::error!("Failed to save engine storage: {}", e);
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+        )));
+    }
+    let engine = storage.get_engine(&engine_id).cloned();
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(engine).unwrap_or(serde_json::json!({}))
+    ))
+}
+
+/// Permanently delete one removed engine, or the whole trash when `engine_id` is not given
+#[tauri::command]
+pub async fn purge_trash(
+    engine_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: purge_trash - engine_id: {:?}", engine_id);
+
+    let mut storage = state.engine_storage.write().await;
+    let purged = match storage.purge_trash(engine_id.as_deref()) {
+        Ok(purged) => purged,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_empty_trash", "Failed to empty trash: {error}", error = e
+        ))),
+    };
+    if let Err(e) = storage.save().await {
+        log::error!("Failed to save engine storage: {}", e);
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+        )));
+    }
+    Ok(CommandResponse::success_with_data(serde_json::json!({ "purged": purged })))
+}
+
+/// Make the engine file at `path` runnable: set its execute bit, or clear the download
+/// marks macOS and Windows put on it. Returns the issues that are left.
+#[tauri::command]
+pub async fn fix_engine_permissions(path: String) -> Result<CommandResponse, String> {
+    log::info!("Command: fix_engine_permissions - path: {}", path);
+
+    match engine_permissions::fix(std::path::Path::new(&path)).await {
+        Ok(remaining) => Ok(CommandResponse::success_with_data(
+            serde_json::json!({ "remaining_issues": remaining })
+        )),
+        Err(e) => {
+            log::error!("Failed to fix engine permissions: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_fix_permissions", "Failed to fix permissions: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Clear macOS's quarantine attribute from a downloaded engine so Gatekeeper lets it run.
+/// The UI asks the user first; this trusts the file as if opened from Finder.
+#[tauri::command]
+pub async fn clear_engine_quarantine(path: String) -> Result<CommandResponse, String> {
+    log::info!("Command: clear_engine_quarantine - path: {}", path);
+
+    match engine_permissions::remove_quarantine(std::path::Path::new(&path)).await {
+        Ok(()) => Ok(CommandResponse::success()),
+        Err(e) => {
+            log::error!("Failed to clear quarantine: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_clear_quarantine", "Failed to clear quarantine: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Get all configured engines
+#[tauri::command]
+pub async fn get_engines(
+    state: State<'_, AppState>,
+    grouped: Option<bool>,
+    tag: Option<String>,
+    enabled: Option<bool>,
+) -> Result<CommandResponse, String> {
+    let storage = state.engine_storage.read().await;
+    let tag = tag.as_deref().filter(|t| !t.trim().is_empty());
+
+    // Grouped output is `{ groups: [{ name, engines }], ungrouped }`; otherwise a flat list in manual order
+    if grouped.unwrap_or(false) {
+        return Ok(CommandResponse::success_with_data(
+            serde_json::to_value(storage.grouped_engines(tag, enabled)).unwrap_or(serde_json::json!({}))
+        ));
+    }
+
+    let engines = storage.filtered_engines(tag, enabled);
+    
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(engines).unwrap_or(serde_json::json!([]))
+    ))
+}
+
+/// Validate an engine at a given path. With `validation.deep`, also wait for `readyok`;
+/// `engine_id` then sends that engine's saved and eval file options first, so a missing
+/// eval file fails here instead of in the first game.
+#[tauri::command]
+pub async fn validate_engine_path(
+    path: String,
+    validation: Option<ValidationOptions>,
+    engine_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: validate_engine_path - path: {}", path);
+
+    let mut validation = validation.unwrap_or_default();
+    if let (true, Some(engine_id)) = (validation.deep, engine_id.as_deref()) {
+        validation.options = state.engine_storage.read().await.startup_options(engine_id, None);
+    }
+
+    match engine_validator::validate_engine_with(&path, &validation).await {
         Ok(metadata) => {
             log::info!("Engine validation successful: {}", metadata.name);
             Ok(CommandResponse::success_with_data(
@@ -479,7 +1037,7 @@ pub async fn validate_engine_path(
         }
         Err(e) => {
             log::error!("Engine validation failed: {}", e);
-            Ok(CommandResponse::error(format!("Validation failed: {}", e)))
+            Ok(engine_error(message!("validation_failed", "Validation failed: {error}", error = e), &e))
         }
     }
 }
@@ -495,41 +1053,45 @@ pub async fn revalidate_engine_metadata(
     let mut storage = state.engine_storage.write().await;
     
     // Use a scoped block to limit the mutable borrow
-    let engine_clone = {
+    let engine_data = {
         let engine = storage.get_engine_mut(&engine_id)
             .ok_or_else(|| "Engine not found".to_string())?;
         
         let engine_path = engine.path.clone();
         
         // Re-validate the engine to get latest options
-        let metadata = match engine_validator::validate_engine(&engine_path).await {
+        let (option_diff, migration) = match engine_validator::validate_engine(&engine_path).await {
             Ok(meta) => {
                 log::info!("Re-validated engine metadata for {}, found {} options", engine_id, meta.options.len());
-                Some(meta)
+                let (diff, migration) = engine.update_metadata(meta).await;
+                (Some(diff), Some(migration))
             },
             Err(e) => {
                 log::warn!("Engine re-validation failed for {}: {}", engine_id, e);
                 // Keep existing metadata if validation fails
-                engine.metadata.clone()
+                (None, None)
             }
         };
         
-        engine.metadata = metadata;
-        
         // Clone engine data before ending mutable borrow
-        engine.clone()
+        let mut data = serde_json::to_value(engine.clone()).unwrap_or(serde_json::json!({}));
+        if let Some(object) = data.as_object_mut() {
+            object.insert("option_diff".to_string(), serde_json::to_value(option_diff).unwrap_or_default());
+            object.insert("option_migration".to_string(), serde_json::to_value(migration).unwrap_or_default());
+        }
+        data
     }; // Mutable borrow ends here
     
     // Save to disk (now that mutable borrow is released)
     if let Err(e) = storage.save().await {
         log::error!("Failed to save engine storage: {}", e);
-        return Ok(CommandResponse::error(format!("Failed to save configuration: {}", e)));
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+        )));
     }
     
     log::info!("Engine metadata re-validated successfully for: {}", engine_id);
-    Ok(CommandResponse::success_with_data(
-        serde_json::to_value(engine_clone).unwrap_or(serde_json::json!({}))
-    ))
+    Ok(CommandResponse::success_with_data(engine_data))
 }
 
 /// Register the built-in engine if not already present, or update the path if it's incorrect
@@ -546,15 +1108,17 @@ pub async fn register_builtin_engine(
         return Ok(path_response);
     }
 
-    let engine_path = path_response
-        .data
-        .and_then(|d| d.get("path").and_then(|p| p.as_str().map(String::from)))
+    let data = path_response.data.unwrap_or_default();
+    let engine_path = data
+        .get("path")
+        .and_then(|p| p.as_str().map(String::from))
         .ok_or_else(|| "Failed to get engine path".to_string())?;
+    let version = data.get("version").and_then(|v| v.as_str().map(String::from));
 
     let mut storage = state.engine_storage.write().await;
 
     // Check if already registered - if so, update path if it's different and always re-validate metadata
-    let options_count = if let Some(builtin_engine) = storage.engines.iter_mut().find(|e| e.is_builtin) {
+    let (options_count, option_diff, migration, previous_version) = if let Some(builtin_engine) = storage.engines.iter_mut().find(|e| e.is_builtin) {
         let path_exists = std::path::Path::new(&builtin_engine.path).exists();
         let path_is_correct = builtin_engine.path == engine_path;
         
@@ -565,66 +1129,161 @@ pub async fn register_builtin_engine(
         } else {
             log::info!("Built-in engine path is correct, re-validating metadata to pick up new options");
         }
+
+        let previous_version = std::mem::replace(&mut builtin_engine.builtin_version, version.clone());
+        if previous_version != version {
+            log::info!("Built-in engine version changed from {:?} to {:?}", previous_version, version);
+        }
         
         // Always re-validate metadata to get latest options (Task 8.0: new options added)
         // This ensures the UI shows all available options after engine code updates
-        let metadata = match engine_validator::validate_engine(&engine_path).await {
+        let option_diff = match engine_validator::validate_engine(&engine_path).await {
             Ok(meta) => {
                 log::info!("Re-validated built-in engine metadata, found {} options", meta.options.len());
-                Some(meta)
+                let old_options = builtin_engine.metadata.as_ref().map(|m| m.options.clone()).unwrap_or_default();
+                let diff = engine_validator::diff_options(&old_options, &meta.options);
+                builtin_engine.metadata = Some(meta);
+                builtin_engine.record_binary_hash().await;
+                Some(diff)
             },
             Err(e) => {
                 log::warn!("Built-in engine validation failed: {}, keeping existing metadata", e);
                 // Keep existing metadata if validation fails (might be running engine issue)
-                builtin_engine.metadata.clone()
+                None
             }
         };
-        builtin_engine.metadata = metadata;
         
         // Update saved options if they don't exist (migrate to new defaults)
         if builtin_engine.saved_options.is_none() {
-            use std::collections::HashMap;
-            let mut default_options = HashMap::new();
-            default_options.insert("MaxDepth".to_string(), "0".to_string()); // Unlimited/adaptive
-            default_options.insert("TimeCheckFrequency".to_string(), "1024".to_string());
-            default_options.insert("TimeSafetyMargin".to_string(), "100".to_string());
-            default_options.insert("TimeAllocationStrategy".to_string(), "Adaptive".to_string());
-            default_options.insert("EnableTimeBudget".to_string(), "true".to_string());
-            default_options.insert("EnableCheckOptimization".to_string(), "true".to_string());
-            default_options.insert("EnableAspirationWindows".to_string(), "true".to_string());
-            default_options.insert("AspirationWindowSize".to_string(), "25".to_string());
-            default_options.insert("EnablePositionTypeTracking".to_string(), "true".to_string());
-            builtin_engine.saved_options = Some(default_options);
+            let preset = crate::builtin_engine::option_preset();
+            builtin_engine.saved_options = Some(crate::builtin_engine::default_options(builtin_engine.metadata.as_ref(), &preset));
             log::info!("Set default options for built-in engine");
         }
+
+        // Drop or fix saved options the updated engine would reject
+        let migration = option_diff.as_ref().map(|diff| builtin_engine.migrate_saved_options(diff));
         
         // Capture options count before ending mutable borrow
-        builtin_engine.metadata.as_ref().map(|m| m.options.len()).unwrap_or(0)
+        let options_count = builtin_engine.metadata.as_ref().map(|m| m.options.len()).unwrap_or(0);
+        (options_count, option_diff, migration, previous_version)
     } else {
         // Engine not found - will create new registration
-        return register_new_builtin_engine(storage, engine_path).await;
+        return register_new_builtin_engine(storage, engine_path, version).await;
     }; // Mutable borrow ends here - builtin_engine goes out of scope
     
     // Save to disk (now that mutable borrow is released)
     if let Err(e) = storage.save().await {
         log::error!("Failed to save engine storage: {}", e);
-        return Ok(CommandResponse::error(format!("Failed to save configuration: {}", e)));
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+        )));
     }
     
     log::info!("Built-in engine metadata updated successfully with {} options", options_count);
-    return Ok(CommandResponse::success_with_data(
+    Ok(CommandResponse::success_with_data(
         serde_json::json!({ 
             "updated": true, 
             "path": engine_path,
-            "options_count": options_count
+            "version": version,
+            "previous_version": previous_version,
+            "options_count": options_count,
+            "option_diff": option_diff,
+            "option_migration": migration,
         })
-    ));
+    ))
+}
+
+/// Copy the built-in engine out of the app bundle again, replacing a damaged or deleted copy,
+/// and re-register it
+#[tauri::command]
+pub async fn repair_builtin_engine(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: repair_builtin_engine");
+
+    let engine = match builtin_engine::locate(&app_handle, true).await {
+        Ok(engine) => engine,
+        Err(e) => {
+            log::error!("Failed to repair built-in engine: {}", e);
+            return Ok(CommandResponse::failure(message!(
+                "failed_to_repair_builtin_engine", "Failed to repair built-in engine: {error}", error = e
+            )));
+        }
+    };
+    let mut response = register_builtin_engine(app_handle, state).await?;
+    if let Some(data) = response.data.as_mut().filter(|data| data.is_object()) {
+        data["provisioned"] = serde_json::json!(engine);
+    }
+    Ok(response)
+}
+
+/// Check the configured update URL for a newer built-in engine, and with `install` download,
+/// verify and register it
+#[tauri::command]
+pub async fn check_builtin_engine_update(
+    install: bool,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: check_builtin_engine_update - install: {}", install);
+
+    let Some(url) = state.settings.read().await.builtin_engine_update_url.clone() else {
+        return Ok(CommandResponse::failure(message!(
+            "no_engine_update_url", "No built-in engine update URL is configured"
+        )));
+    };
+    let current_version = state
+        .engine_storage
+        .read()
+        .await
+        .engines
+        .iter()
+        .find(|e| e.is_builtin)
+        .and_then(|e| e.builtin_version.clone());
+    let release = match builtin_engine::fetch_release(&url).await {
+        Ok(release) => release,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_check_for_engine_updates", "Failed to check for engine updates: {error}", error = e
+        ))),
+    };
+    let update_available = release.for_this_platform().is_some()
+        && current_version
+            .as_deref()
+            .is_some_and(|current| builtin_engine::is_newer(&release.version, current));
+
+    let mut data = serde_json::json!({
+        "current_version": current_version,
+        "latest_version": release.version,
+        "update_available": update_available,
+        "installed": false,
+    });
+    if install && update_available {
+        let installed = match builtin_engine::installed_dir() {
+            Ok(dir) => builtin_engine::install_release(&release, &dir).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = installed {
+            log::error!("Failed to install built-in engine {}: {}", release.version, e);
+            return Ok(CommandResponse::failure(message!(
+                "failed_to_install_engine_update", "Failed to install engine update: {error}", error = e
+            )));
+        }
+        let registered = register_builtin_engine(app_handle, state).await?;
+        if !registered.success {
+            return Ok(registered);
+        }
+        data["installed"] = serde_json::json!(true);
+        data["registration"] = registered.data.unwrap_or_default();
+    }
+    Ok(CommandResponse::success_with_data(data))
 }
 
 /// Helper function to register a new built-in engine
 async fn register_new_builtin_engine(
     mut storage: tokio::sync::RwLockWriteGuard<'_, crate::engine_storage::EngineStorage>,
     engine_path: String,
+    version: Option<String>,
 ) -> Result<CommandResponse, String> {
     // Validate the built-in engine (for new registration)
     let metadata = match engine_validator::validate_engine(&engine_path).await {
@@ -642,20 +1301,12 @@ async fn register_new_builtin_engine(
         metadata,
         true,
     );
+    config.record_binary_hash().await;
+    config.builtin_version = version;
     
-    // Set default saved options for built-in engine (Task 8.0, 4.0, 7.0)
-    use std::collections::HashMap;
-    let mut default_options = HashMap::new();
-    default_options.insert("MaxDepth".to_string(), "0".to_string()); // Unlimited/adaptive
-    default_options.insert("TimeCheckFrequency".to_string(), "1024".to_string());
-    default_options.insert("TimeSafetyMargin".to_string(), "100".to_string());
-    default_options.insert("TimeAllocationStrategy".to_string(), "Adaptive".to_string());
-    default_options.insert("EnableTimeBudget".to_string(), "true".to_string());
-    default_options.insert("EnableCheckOptimization".to_string(), "true".to_string());
-    default_options.insert("EnableAspirationWindows".to_string(), "true".to_string());
-    default_options.insert("AspirationWindowSize".to_string(), "25".to_string());
-    default_options.insert("EnablePositionTypeTracking".to_string(), "true".to_string());
-    config.saved_options = Some(default_options);
+    // Start from the engine's own defaults with the preset's values in their place
+    let preset = builtin_engine::option_preset();
+    config.saved_options = Some(builtin_engine::default_options(config.metadata.as_ref(), &preset));
 
     // Add to storage
     match storage.add_engine(config.clone()) {
@@ -663,7 +1314,9 @@ async fn register_new_builtin_engine(
             // Save to disk
             if let Err(e) = storage.save().await {
                 log::error!("Failed to save engine storage: {}", e);
-                return Ok(CommandResponse::error(format!("Failed to save configuration: {}", e)));
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+                )));
             }
 
             log::info!("Built-in engine registered successfully");
@@ -673,433 +1326,3184 @@ async fn register_new_builtin_engine(
         }
         Err(e) => {
             log::error!("Failed to register built-in engine: {}", e);
-            Ok(CommandResponse::error(format!("Failed to register engine: {}", e)))
+            Ok(CommandResponse::failure(message!(
+                "failed_to_register_engine", "Failed to register engine: {error}", error = e
+            )))
         }
     }
 }
 
-/// Perform health checks on all configured engines
+/// Progress of health_check_engines, emitted as each engine's check finishes
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct HealthCheckProgress {
+    pub check_id: String,
+    pub index: usize,
+    pub total: usize,
+    pub result: EngineHealth,
+}
+
+const DEFAULT_HEALTH_CHECK_PARALLELISM: usize = 4;
+
+/// Perform health checks on all configured engines, a few at a time, emitting
+/// `engine-health-progress` as each finishes. `check_id` lets cancel_health_check stop it.
 #[tauri::command]
 pub async fn health_check_engines(
+    app_handle: tauri::AppHandle,
+    validation: Option<ValidationOptions>,
+    check_id: Option<String>,
+    max_parallel: Option<usize>,
     state: State<'_, AppState>,
 ) -> Result<CommandResponse, String> {
-    log::info!("Command: health_check_engines");
+    let check_id = check_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    log::info!("Command: health_check_engines - check_id: {}, max_parallel: {:?}", check_id, max_parallel);
 
-    let storage = state.engine_storage.read().await;
-    let engines = storage.get_all_engines();
-    let mut results = Vec::new();
-
-    for engine in engines {
-        if !engine.enabled {
-            results.push(serde_json::json!({
-                "id": engine.id,
-                "name": engine.name,
-                "status": "disabled",
-            }));
-            continue;
-        }
-
-        log::info!("Health checking engine: {}", engine.name);
-        match engine_validator::validate_engine(&engine.path).await {
-            Ok(_) => {
-                results.push(serde_json::json!({
-                    "id": engine.id,
-                    "name": engine.name,
-                    "status": "healthy",
-                }));
-            }
-            Err(e) => {
-                log::warn!("Engine {} health check failed: {}", engine.name, e);
-                results.push(serde_json::json!({
-                    "id": engine.id,
-                    "name": engine.name,
-                    "status": "unhealthy",
-                    "error": e.to_string(),
-                }));
-            }
-        }
-    }
+    // Copy what the checks need so the storage is not locked while engines load
+    let engines: Vec<(EngineConfig, Vec<(String, String)>)> = {
+        let storage = state.engine_storage.read().await;
+        storage
+            .get_all_engines()
+            .iter()
+            .map(|engine| (engine.clone(), storage.startup_options(&engine.id, None)))
+            .collect()
+    };
+    let validation = validation.unwrap_or_default();
+    let total = engines.len();
+
+    let cancel = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    state.health_checks.write().await.insert(check_id.clone(), cancel.clone());
+    let results = engine_health::check_engines(
+        &engines,
+        &validation,
+        max_parallel.unwrap_or(DEFAULT_HEALTH_CHECK_PARALLELISM),
+        &cancel,
+        |index, result| {
+            let _ = tauri_events::send(&app_handle, &tauri_events::ENGINE_HEALTH_PROGRESS, &HealthCheckProgress {
+                check_id: check_id.clone(),
+                index,
+                total,
+                result: result.clone(),
+            });
+        },
+    )
+    .await;
+    state.health_checks.write().await.remove(&check_id);
 
+    let cancelled = cancel.load(std::sync::atomic::Ordering::SeqCst);
     Ok(CommandResponse::success_with_data(
-        serde_json::json!({ "results": results })
+        serde_json::json!({ "check_id": check_id, "results": results, "cancelled": cancelled })
     ))
 }
 
-/// Start an engine-vs-engine match
+/// Write a zip for bug reports to `path`: recent logs, engine configs, a fresh health check of
+/// each engine and the last match record. Paths are redacted unless `redact_paths` is false.
 #[tauri::command]
-pub async fn start_engine_vs_engine(
-    app_handle: tauri::AppHandle,
+pub async fn generate_diagnostic_bundle(
     state: State<'_, AppState>,
-    engine1_id: String,
-    engine2_id: String,
-    initial_sfen: Option<String>,
-    time_per_move_ms: Option<u64>,
-    max_moves: Option<usize>,
+    path: String,
+    redact_paths: Option<bool>,
 ) -> Result<CommandResponse, String> {
-    log::info!("Command: start_engine_vs_engine - {} vs {}", engine1_id, engine2_id);
+    log::info!("Command: generate_diagnostic_bundle - path: {}", path);
 
-    // Get engine configurations
-    let storage = state.engine_storage.read().await;
-    
-    let engine1 = storage.get_engine(&engine1_id)
-        .ok_or_else(|| "Engine 1 not found".to_string())?;
-    let engine2 = storage.get_engine(&engine2_id)
-        .ok_or_else(|| "Engine 2 not found".to_string())?;
-
-    let config = EngineVsEngineConfig {
-        engine1_id: engine1_id.clone(),
-        engine1_path: engine1.path.clone(),
-        engine1_name: engine1.name.clone(),
-        engine2_id: engine2_id.clone(),
-        engine2_path: engine2.path.clone(),
-        engine2_name: engine2.name.clone(),
-        initial_sfen,
-        time_per_move_ms: time_per_move_ms.unwrap_or(5000),
-        max_moves: max_moves.unwrap_or(200),
+    let engines: Vec<(EngineConfig, Vec<(String, String)>)> = {
+        let storage = state.engine_storage.read().await;
+        storage
+            .get_all_engines()
+            .iter()
+            .map(|engine| (engine.clone(), storage.startup_options(&engine.id, None)))
+            .collect()
+    };
+    let configs: Vec<EngineConfig> = engines.iter().map(|(engine, _)| engine.clone()).collect();
+    let private_paths = if redact_paths.unwrap_or(true) {
+        diagnostic_bundle::private_paths(&configs)
+    } else {
+        Vec::new()
     };
 
-    drop(storage);
+    let health = engine_health::check_engines(
+        &engines,
+        &ValidationOptions::default(),
+        DEFAULT_HEALTH_CHECK_PARALLELISM,
+        &std::sync::atomic::AtomicBool::new(false),
+        |_, _| {},
+    )
+    .await;
 
-    // Spawn the game loop in a background task
-    let manager = EngineVsEngineManager::new(app_handle, config, state.engine_storage.clone());
-    
-    tokio::spawn(async move {
-        if let Err(e) = manager.run_match().await {
-            log::error!("Engine-vs-engine match error: {}", e);
+    // The newest record that came from an engine-vs-engine match
+    let mut last_match = None;
+    for summary in GameRecordStorage::list().await.unwrap_or_default() {
+        if let Ok(record) = GameRecordStorage::load(&summary.id).await {
+            if record.match_config.is_some() {
+                last_match = Some(record);
+                break;
+            }
         }
+    }
+
+    let system = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "generated_at": chrono::Utc::now().to_rfc3339(),
     });
+    let logs: String = log_buffer::recent(usize::MAX, log::LevelFilter::Trace)
+        .iter()
+        .map(|entry| format!("{} {:<5} {}: {}\n", entry.timestamp, entry.level, entry.target, entry.message))
+        .collect();
+    let json = |mut value: serde_json::Value| {
+        diagnostic_bundle::redact_value(&mut value, &private_paths);
+        serde_json::to_string_pretty(&value).unwrap_or_default()
+    };
+    let files = vec![
+        BundleFile { name: "system.json", contents: json(system) },
+        BundleFile { name: "logs.txt", contents: diagnostic_bundle::redact(&logs, &private_paths) },
+        BundleFile { name: "engines.json", contents: json(serde_json::json!(configs)) },
+        BundleFile { name: "engine_health.json", contents: json(serde_json::json!(health)) },
+        BundleFile { name: "last_match.json", contents: json(serde_json::json!(last_match)) },
+        BundleFile {
+            name: "commands.json",
+            contents: json(serde_json::json!(command_audit::history(usize::MAX, None))),
+        },
+        BundleFile { name: "metrics.json", contents: json(serde_json::json!(metrics::snapshot())) },
+    ];
 
-    Ok(CommandResponse::success())
+    let bundle_path = std::path::PathBuf::from(&path);
+    let written = tokio::task::spawn_blocking(move || diagnostic_bundle::write_zip(&bundle_path, &files)).await;
+    match written.map_err(anyhow::Error::from).and_then(|result| result) {
+        Ok(()) => Ok(CommandResponse::success_with_data(serde_json::json!({ "path": path }))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_write_diagnostic_bundle", "Failed to write diagnostic bundle: {error}", error = e
+        ))),
+    }
 }
 
-/// Save engine options
+/// Stop a running health check; engines still being checked are killed and left out of its results
 #[tauri::command]
-pub async fn save_engine_options(
-    engine_id: String,
-    options: std::collections::HashMap<String, String>,
+pub async fn cancel_health_check(
     state: State<'_, AppState>,
+    check_id: String,
 ) -> Result<CommandResponse, String> {
-    log::info!("Command: save_engine_options - engine_id: {}, options: {:?}", engine_id, options);
+    log::info!("Command: cancel_health_check - check_id: {}", check_id);
 
-    let mut storage = state.engine_storage.write().await;
-    
-    match storage.save_engine_options(&engine_id, options) {
-        Ok(_) => {
-            // Save to disk
-            if let Err(e) = storage.save().await {
-                log::error!("Failed to save engine storage: {}", e);
-                return Ok(CommandResponse::error(format!("Failed to save options: {}", e)));
-            }
-            
-            log::info!("Engine options saved successfully for engine: {}", engine_id);
+    match state.health_checks.read().await.get(&check_id) {
+        Some(cancel) => {
+            cancel.store(true, std::sync::atomic::Ordering::SeqCst);
             Ok(CommandResponse::success())
         }
-        Err(e) => {
-            log::error!("Failed to save engine options: {}", e);
-            Ok(CommandResponse::error(format!("Failed to save options: {}", e)))
-        }
+        None => Ok(CommandResponse::failure(message!(
+            "health_check_not_running", "Health check {check_id} is not running", check_id = check_id
+        ))),
     }
 }
 
-/// Get saved engine options
-#[tauri::command]
-pub async fn get_engine_options(
-    engine_id: String,
-    state: State<'_, AppState>,
-) -> Result<CommandResponse, String> {
-    log::info!("Command: get_engine_options - engine_id: {}", engine_id);
+/// What bulk_engine_action does to each engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkEngineAction {
+    Enable,
+    Disable,
+    Delete,
+    Revalidate,
+    HealthCheck,
+}
 
-    let storage = state.engine_storage.read().await;
-    
-    match storage.get_engine_options(&engine_id) {
-        Some(options) => {
-            log::info!("Retrieved {} saved options for engine: {}", options.len(), engine_id);
-            Ok(CommandResponse::success_with_data(serde_json::to_value(options).unwrap()))
-        }
-        None => {
-            log::info!("No saved options found for engine: {}", engine_id);
-            Ok(CommandResponse::success_with_data(serde_json::Value::Object(serde_json::Map::new())))
-        }
+/// Outcome of bulk_engine_action for one engine
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkEngineResult {
+    pub engine_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+    /// Option changes for `revalidate`, the check for `health_check`
+    pub data: Option<serde_json::Value>,
+}
+
+impl BulkEngineResult {
+    fn ok(engine_id: &str, data: Option<serde_json::Value>) -> Self {
+        Self { engine_id: engine_id.to_string(), success: true, error: None, data }
+    }
+
+    fn failed(engine_id: &str, error: String) -> Self {
+        Self { engine_id: engine_id.to_string(), success: false, error: Some(error), data: None }
     }
 }
 
-/// Clone an engine with a new display name
+/// Apply one action to several engines with a single storage save. Enable, disable and
+/// delete are all-or-nothing: if any engine is unknown (or built-in, for delete) nothing
+/// changes. Revalidation runs a few engines at a time without holding the storage; engines
+/// that fail to validate keep their metadata and are reported in the results.
 #[tauri::command]
-pub async fn clone_engine(
-    engine_id: String,
-    new_display_name: String,
+pub async fn bulk_engine_action(
     state: State<'_, AppState>,
+    ids: Vec<String>,
+    action: BulkEngineAction,
 ) -> Result<CommandResponse, String> {
-    log::info!("Command: clone_engine - engine_id: {}, new_display_name: {}", engine_id, new_display_name);
+    log::info!("Command: bulk_engine_action - {:?} on {} engines", action, ids.len());
 
-    let mut storage = state.engine_storage.write().await;
-    
-    match storage.clone_engine(&engine_id, new_display_name) {
-        Ok(new_engine_id) => {
-            // Save to disk
+    let mut ids = ids;
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| seen.insert(id.clone()));
+    let engines: Vec<EngineConfig> = {
+        let storage = state.engine_storage.read().await;
+        let missing: Vec<&str> = ids.iter().filter(|id| storage.get_engine(id).is_none()).map(String::as_str).collect();
+        if !missing.is_empty() {
+            return Ok(CommandResponse::failure(message!(
+                "engines_not_found", "Engines not found: {engine_ids}", engine_ids = missing.join(", ")
+            )));
+        }
+        ids.iter().filter_map(|id| storage.get_engine(id).cloned()).collect()
+    };
+
+    let results: Vec<BulkEngineResult> = match action {
+        BulkEngineAction::Enable | BulkEngineAction::Disable | BulkEngineAction::Delete => {
+            if action == BulkEngineAction::Delete && engines.iter().any(|engine| engine.is_builtin) {
+                return Ok(CommandResponse::failure(message!(
+                    "cannot_remove_builtin_engine", "Cannot remove the built-in engine"
+                )));
+            }
+            let mut storage = state.engine_storage.write().await;
+            for id in &ids {
+                let applied = match action {
+                    BulkEngineAction::Delete => storage.remove_engine(id),
+                    _ => storage.set_engine_enabled(id, action == BulkEngineAction::Enable),
+                };
+                if let Err(e) = applied {
+                    // Checked above; only a concurrent removal gets here
+                    log::warn!("Bulk {:?} on {}: {}", action, id, e);
+                }
+            }
             if let Err(e) = storage.save().await {
                 log::error!("Failed to save engine storage: {}", e);
-                return Ok(CommandResponse::error(format!("Failed to save cloned engine: {}", e)));
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+                )));
             }
-            
-            log::info!("Engine cloned successfully: {} -> {}", engine_id, new_engine_id);
-            Ok(CommandResponse::success_with_data(serde_json::json!({ "new_engine_id": new_engine_id })))
+            ids.iter().map(|id| BulkEngineResult::ok(id, None)).collect()
         }
-        Err(e) => {
-            log::error!("Failed to clone engine: {}", e);
-            Ok(CommandResponse::error(format!("Failed to clone engine: {}", e)))
+        BulkEngineAction::Revalidate => {
+            let results = revalidate_engines(&state, &engines).await;
+            if let Err(e) = state.engine_storage.read().await.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+                )));
+            }
+            results
+        }
+        BulkEngineAction::HealthCheck => {
+            let engines: Vec<(EngineConfig, Vec<(String, String)>)> = {
+                let storage = state.engine_storage.read().await;
+                engines.into_iter().map(|engine| {
+                    let options = storage.startup_options(&engine.id, None);
+                    (engine, options)
+                }).collect()
+            };
+            engine_health::check_engines(
+                &engines,
+                &ValidationOptions::default(),
+                DEFAULT_HEALTH_CHECK_PARALLELISM,
+                &std::sync::atomic::AtomicBool::new(false),
+                |_, _| {},
+            )
+            .await
+            .into_iter()
+            .map(|health| {
+                let data = serde_json::to_value(&health).ok();
+                if health.status == HealthStatus::Unhealthy {
+                    let error = health.error.clone().unwrap_or_else(|| "Unhealthy".to_string());
+                    BulkEngineResult { data, ..BulkEngineResult::failed(&health.id, error) }
+                } else {
+                    BulkEngineResult::ok(&health.id, data)
+                }
+            })
+            .collect()
         }
+    };
+
+    let failed = results.iter().filter(|result| !result.success).count();
+    Ok(CommandResponse::success_with_data(serde_json::json!({
+        "results": results,
+        "succeeded": results.len() - failed,
+        "failed": failed,
+    })))
+}
+
+/// Validate engines a few at a time without holding the storage, then give those that
+/// validated their new metadata. Results are in the order of `engines`; the caller saves.
+async fn revalidate_engines(state: &AppState, engines: &[EngineConfig]) -> Vec<BulkEngineResult> {
+    let validated: Vec<(String, Result<engine_validator::EngineMetadata>)> = futures::stream::iter(engines)
+        .map(|engine| async move { (engine.id.clone(), engine_validator::validate_engine(&engine.path).await) })
+        .buffer_unordered(DEFAULT_HEALTH_CHECK_PARALLELISM)
+        .collect()
+        .await;
+
+    let mut storage = state.engine_storage.write().await;
+    let mut results = Vec::with_capacity(validated.len());
+    for (id, metadata) in validated {
+        let result = match (metadata, storage.get_engine_mut(&id)) {
+            (Ok(metadata), Some(engine)) => {
+                let (diff, migration) = engine.update_metadata(metadata).await;
+                BulkEngineResult::ok(&id, Some(serde_json::json!({ "option_diff": diff, "option_migration": migration })))
+            }
+            (Err(e), _) => BulkEngineResult::failed(&id, format!("Validation failed: {}", e)),
+            (_, None) => BulkEngineResult::failed(&id, "Engine not found".to_string()),
+        };
+        results.push(result);
     }
+    results.sort_by_key(|result| engines.iter().position(|engine| engine.id == result.engine_id));
+    results
 }
 
-/// Update engine display name
+/// Set or clear the directory engine paths are stored relative to
 #[tauri::command]
-pub async fn update_engine_display_name(
-    engine_id: String,
-    new_display_name: String,
+pub async fn set_engines_root(
+    root: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<CommandResponse, String> {
-    log::info!("Command: update_engine_display_name - engine_id: {}, new_display_name: {}", engine_id, new_display_name);
+    log::info!("Command: set_engines_root - root: {:?}", root);
 
     let mut storage = state.engine_storage.write().await;
-    
-    match storage.update_display_name(&engine_id, new_display_name) {
-        Ok(_) => {
-            // Save to disk
-            if let Err(e) = storage.save().await {
-                log::error!("Failed to save engine storage: {}", e);
-                return Ok(CommandResponse::error(format!("Failed to save display name: {}", e)));
-            }
-            
-            log::info!("Engine display name updated successfully: {}", engine_id);
-            Ok(CommandResponse::success())
-        }
-        Err(e) => {
-            log::error!("Failed to update display name: {}", e);
-            Ok(CommandResponse::error(format!("Failed to update display name: {}", e)))
-        }
+    if let Err(e) = storage.set_engines_root(root) {
+        return Ok(CommandResponse::error(e.to_string()));
+    }
+    if let Err(e) = storage.save().await {
+        log::error!("Failed to save engine storage: {}", e);
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+        )));
     }
+    Ok(CommandResponse::success())
 }
 
-/// Set an engine as favorite
+/// Point every engine under `old_prefix` at the same place under `new_prefix`, e.g. after
+/// the engines folder moved, and revalidate the engines that moved
 #[tauri::command]
-pub async fn set_favorite_engine(
-    engine_id: String,
+pub async fn relocate_engines(
+    old_prefix: String,
+    new_prefix: String,
     state: State<'_, AppState>,
 ) -> Result<CommandResponse, String> {
-    log::info!("Command: set_favorite_engine - engine_id: {}", engine_id);
+    log::info!("Command: relocate_engines - {} -> {}", old_prefix, new_prefix);
 
-    let mut storage = state.engine_storage.write().await;
-    
-    match storage.set_favorite_engine(&engine_id) {
-        Ok(_) => {
-            // Save to disk
-            if let Err(e) = storage.save().await {
-                log::error!("Failed to save engine storage: {}", e);
-                return Ok(CommandResponse::error(format!("Failed to save favorite status: {}", e)));
+    let moved: Vec<EngineConfig> = {
+        let mut storage = state.engine_storage.write().await;
+        match storage.relocate_engines(&old_prefix, &new_prefix) {
+            Ok(ids) => ids.iter().filter_map(|id| storage.get_engine(id).cloned()).collect(),
+            Err(e) => return Ok(CommandResponse::error(e.to_string())),
+        }
+    };
+    log::info!("Relocated {} engines", moved.len());
+
+    let results = revalidate_engines(&state, &moved).await;
+    if let Err(e) = state.engine_storage.read().await.save().await {
+        log::error!("Failed to save engine storage: {}", e);
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+        )));
+    }
+    let failed = results.iter().filter(|result| !result.success).count();
+    Ok(CommandResponse::success_with_data(serde_json::json!({
+        "results": results,
+        "succeeded": results.len() - failed,
+        "failed": failed,
+    })))
+}
+
+/// Run a suite of positions with expected best moves through an engine, `time_per_pos`
+/// milliseconds each. Each result is also emitted as `test-suite-progress`.
+#[tauri::command]
+pub async fn run_test_suite(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    engine_id: String,
+    suite_path: String,
+    time_per_pos: u64,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: run_test_suite - engine_id: {}, suite_path: {}, time_per_pos: {}", engine_id, suite_path, time_per_pos);
+
+    let engine = match state.engine_storage.read().await.get_engine(&engine_id) {
+        Some(engine) => engine.clone(),
+        None => return Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = engine_id
+        ))),
+    };
+    let positions = match tokio::fs::read_to_string(&suite_path).await {
+        Ok(text) => match test_suite::parse_suite(&text) {
+            Ok(positions) => positions,
+            Err(e) => return Ok(CommandResponse::failure(message!(
+                "invalid_test_suite", "Invalid test suite: {error}", error = e
+            ))),
+        },
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_read_test_suite", "Failed to read test suite: {error}", error = e
+        ))),
+    };
+
+    let events = TauriEventSink::shared(app_handle);
+    match test_suite::run_suite(&engine, &state.engine_storage, &positions, time_per_pos, events).await {
+        Ok(report) => {
+            log::info!("Test suite {}: {}/{} solved by {}", suite_path, report.solved, report.total, engine.display_name);
+            Ok(CommandResponse::success_with_data(
+                serde_json::to_value(&report).unwrap_or(serde_json::json!({}))
+            ))
+        }
+        Err(e) => {
+            log::error!("Test suite run failed: {}", e);
+            Ok(CommandResponse::failure(message!("test_suite_run_failed", "Test suite run failed: {error}", error = e)))
+        }
+    }
+}
+
+/// Search every position in `sfens` for `movetime` milliseconds with one instance of the
+/// engine, started once and reused, and return the best move and score of each in order
+#[tauri::command]
+pub async fn evaluate_positions(
+    state: State<'_, AppState>,
+    engine_id: String,
+    sfens: Vec<String>,
+    movetime: u64,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: evaluate_positions - engine_id: {}, positions: {}, movetime: {}", engine_id, sfens.len(), movetime);
+
+    let engine = match state.engine_storage.read().await.get_engine(&engine_id) {
+        Some(engine) => engine.clone(),
+        None => return Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = engine_id
+        ))),
+    };
+    match batch_eval::evaluate_positions(&engine, &state.engine_storage, &sfens, movetime).await {
+        Ok(evaluations) => Ok(CommandResponse::success_with_data(serde_json::json!(evaluations))),
+        Err(e) => {
+            log::error!("Batch evaluation failed: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "batch_evaluation_failed", "Failed to evaluate positions: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Play each listed start position twice between two engines, with colours swapped, and
+/// report engine A's score per position with 95% confidence intervals, and overall as Elo
+/// with error bars and LOS. Each finished position is emitted as `position-match-progress`.
+#[tauri::command]
+pub async fn run_position_match(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    config: PositionMatchConfig,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: run_position_match - {} vs {}, positions: {}", config.engine_a_id, config.engine_b_id, config.positions.len());
+
+    if let Err(e) = config.validate() {
+        return Ok(CommandResponse::failure(message!(
+            "invalid_position_match_configuration", "Invalid position match configuration: {error}", error = e
+        )));
+    }
+    let events = TauriEventSink::shared(app_handle);
+    match position_match::run_position_match(events, state.engine_storage.clone(), config).await {
+        Ok(report) => {
+            log::info!(
+                "Position match {} vs {}: score {:.3} over {} games",
+                report.engine_a_name,
+                report.engine_b_name,
+                report.overall.score,
+                report.overall.games
+            );
+            Ok(CommandResponse::success_with_data(
+                serde_json::to_value(&report).unwrap_or(serde_json::json!({}))
+            ))
+        }
+        Err(e) => {
+            log::error!("Position match failed: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "position_match_failed", "Position match failed: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Queue a game or position for background analysis
+#[tauri::command]
+pub async fn enqueue_analysis_job(
+    state: State<'_, AppState>,
+    mut request: AnalysisJobRequest,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: enqueue_analysis_job - engine_id: {}, target: {:?}", request.engine_id, request.target);
+
+    {
+        let storage = state.engine_storage.read().await;
+        if request.engine_id.is_empty() {
+            match storage.default_engine(EnginePurpose::Analysis) {
+                Some(engine) => request.engine_id = engine.id.clone(),
+                None => return Ok(CommandResponse::failure(message!(
+                    "no_engine_available_for_analysis", "No engine available for analysis"
+                ))),
             }
-            
-            log::info!("Engine set as favorite successfully: {}", engine_id);
+        } else if storage.get_engine(&request.engine_id).is_none() {
+            return Ok(CommandResponse::failure(message!(
+                "engine_not_found", "Engine not found: {engine_id}", engine_id = request.engine_id
+            )));
+        }
+    }
+    match state.analysis_queue.enqueue(request).await {
+        Ok(job) => Ok(CommandResponse::success_with_data(
+            serde_json::to_value(&job).unwrap_or(serde_json::json!({}))
+        )),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_queue_analysis", "Failed to queue analysis: {error}", error = e
+        ))),
+    }
+}
+
+/// List queued, running and finished analysis jobs
+#[tauri::command]
+pub async fn list_analysis_jobs(state: State<'_, AppState>) -> Result<CommandResponse, String> {
+    log::info!("Command: list_analysis_jobs");
+
+    let jobs = state.analysis_queue.list().await;
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(jobs).unwrap_or(serde_json::json!([]))
+    ))
+}
+
+/// Get one analysis job with its results so far
+#[tauri::command]
+pub async fn get_analysis_job(state: State<'_, AppState>, job_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: get_analysis_job - job_id: {}", job_id);
+
+    match state.analysis_queue.get(&job_id).await {
+        Some(job) => Ok(CommandResponse::success_with_data(
+            serde_json::to_value(&job).unwrap_or(serde_json::json!({}))
+        )),
+        None => Ok(CommandResponse::failure(message!(
+            "analysis_job_not_found", "Analysis job not found: {job_id}", job_id = job_id
+        ))),
+    }
+}
+
+/// Cancel a queued analysis job, or stop a running one after its current position
+#[tauri::command]
+pub async fn cancel_analysis_job(state: State<'_, AppState>, job_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: cancel_analysis_job - job_id: {}", job_id);
+
+    match state.analysis_queue.cancel(&job_id).await {
+        Ok(()) => Ok(CommandResponse::success()),
+        Err(e) => Ok(CommandResponse::error(e.to_string())),
+    }
+}
+
+/// Remove completed, failed and cancelled jobs from the analysis queue
+#[tauri::command]
+pub async fn clear_finished_analysis_jobs(state: State<'_, AppState>) -> Result<CommandResponse, String> {
+    log::info!("Command: clear_finished_analysis_jobs");
+
+    let removed = state.analysis_queue.clear_finished().await;
+    Ok(CommandResponse::success_with_data(serde_json::json!({ "removed": removed })))
+}
+
+/// Score series of a saved game for charting, normalized to Black's point of view. Includes
+/// the most recent completed analysis of the game, if any.
+#[tauri::command]
+pub async fn get_eval_graph(state: State<'_, AppState>, game_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: get_eval_graph - game_id: {}", game_id);
+
+    let record = match GameRecordStorage::load(&game_id).await {
+        Ok(record) => record,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_game_record", "Failed to load game record: {error}", error = e
+        ))),
+    };
+    let target = AnalysisTarget::Game { record_id: game_id.clone() };
+    let analysis = state
+        .analysis_queue
+        .list()
+        .await
+        .into_iter()
+        .filter(|job| job.target == target && job.status == JobStatus::Completed)
+        .max_by(|a, b| a.finished_at.cmp(&b.finished_at));
+
+    let graph = EvalGraph::from_record(&record).and_then(|graph| match &analysis {
+        Some(job) => graph.with_analysis(&record, &job.results),
+        None => Ok(graph),
+    });
+    match graph {
+        Ok(graph) => Ok(CommandResponse::success_with_data(
+            serde_json::to_value(&graph).unwrap_or(serde_json::json!({}))
+        )),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_build_eval_graph", "Failed to build eval graph: {error}", error = e
+        ))),
+    }
+}
+
+/// Start an engine-vs-engine match
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_engine_vs_engine(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    engine1_id: String,
+    engine2_id: String,
+    initial_sfen: Option<String>,
+    handicap: Option<Handicap>,
+    time_per_move_ms: Option<u64>,
+    search_limit: Option<SearchLimit>,
+    max_moves: Option<usize>,
+    adjudication: Option<AdjudicationConfig>,
+    cpu_affinity: Option<CpuAffinity>,
+    seed: Option<u64>,
+    openings: Option<Vec<String>>,
+    priority: Option<ProcessPriority>,
+    engine1_options: Option<std::collections::HashMap<String, String>>,
+    engine2_options: Option<std::collections::HashMap<String, String>>,
+    engine1_eval_file_id: Option<String>,
+    engine2_eval_file_id: Option<String>,
+    engine1_label: Option<String>,
+    engine2_label: Option<String>,
+    move_delay_ms: Option<u64>,
+    engine1_time_ms: Option<u64>,
+    engine2_time_ms: Option<u64>,
+    opening_moves: Option<Vec<String>>,
+    record_thinking: Option<bool>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: start_engine_vs_engine - {} vs {}", engine1_id, engine2_id);
+
+    if let Some(affinity) = &cpu_affinity {
+        if let Err(e) = affinity.validate() {
+            return Ok(CommandResponse::failure(message!(
+                "invalid_cpu_affinity", "Invalid CPU affinity: {error}", error = e
+            )));
+        }
+    }
+    let search_limit = search_limit.unwrap_or_default();
+    if let Err(e) = search_limit.validate() {
+        return Ok(CommandResponse::failure(message!(
+            "invalid_search_limit", "Invalid search limit: {error}", error = e
+        )));
+    }
+
+    // Get engine configurations
+    let storage = state.engine_storage.read().await;
+    
+    let engine1 = storage.get_engine(&engine1_id).cloned()
+        .ok_or_else(|| "Engine 1 not found".to_string())?;
+    let engine2 = storage.get_engine(&engine2_id).cloned()
+        .ok_or_else(|| "Engine 2 not found".to_string())?;
+    for engine in [&engine1, &engine2] {
+        if let Err(e) = engine.ensure_enabled() {
+            return Ok(CommandResponse::error(e.to_string()));
+        }
+    }
+
+    // Tell the seats apart by what differs between them, including the eval file
+    let mut label_options = [engine1_options.clone().unwrap_or_default(), engine2_options.clone().unwrap_or_default()];
+    for (seat, engine, eval_override) in [
+        (0, &engine1, engine1_eval_file_id.as_ref()),
+        (1, &engine2, engine2_eval_file_id.as_ref()),
+    ] {
+        let eval_id = eval_override.or(engine.eval_file_id.as_ref());
+        if let Some(eval) = eval_id.and_then(|id| storage.get_eval_file(id)) {
+            label_options[seat].insert("eval".to_string(), eval.name.clone());
+        }
+    }
+    let (engine1_name, engine2_name) = seat_labels(
+        (&engine1.name, &engine2.name),
+        (&label_options[0], &label_options[1]),
+        (engine1_label, engine2_label),
+    );
+
+    drop(storage);
+
+    // Record exactly which builds played, so results can be traced back to a binary
+    let mut warnings = Vec::new();
+    let engine1_sha256 = check_engine_binary(&app_handle, &engine1, &mut warnings).await;
+    let engine2_sha256 = check_engine_binary(&app_handle, &engine2, &mut warnings).await;
+
+    let config = EngineVsEngineConfig {
+        engine1_id: engine1_id.clone(),
+        engine1_path: engine1.path.clone(),
+        engine1_name,
+        engine2_id: engine2_id.clone(),
+        engine2_path: engine2.path.clone(),
+        engine2_name,
+        initial_sfen,
+        handicap,
+        time_per_move_ms: match time_per_move_ms {
+            Some(time_ms) => time_ms,
+            None => state.settings.read().await.default_time_control.byoyomi_ms,
+        },
+        search_limit,
+        max_moves: max_moves.unwrap_or(200),
+        adjudication: adjudication.unwrap_or_default(),
+        cpu_affinity,
+        engine1_priority: priority.or(engine1.priority),
+        engine2_priority: priority.or(engine2.priority),
+        engine1_sha256,
+        engine2_sha256,
+        engine1_options,
+        engine2_options,
+        engine1_eval_file_id,
+        engine2_eval_file_id,
+        seed,
+        openings: openings.unwrap_or_default(),
+        move_delay_ms: move_delay_ms.unwrap_or(500),
+        engine1_time_ms,
+        engine2_time_ms,
+        opening_moves: opening_moves.unwrap_or_default(),
+        record_thinking: record_thinking.unwrap_or(false),
+    };
+    if let Err(e) = config.validate_time_odds() {
+        return Ok(CommandResponse::failure(message!("invalid_time_odds", "Invalid time odds: {error}", error = e)));
+    }
+
+    // Spawn the game loop in a background task
+    let mut manager = EngineVsEngineManager::new(TauriEventSink::shared(app_handle), config, state.engine_storage.clone());
+    if let Err(e) = manager.play_opening_moves().await {
+        return Ok(CommandResponse::error(e.to_string()));
+    }
+    if state.settings.read().await.autosave_interval_secs > 0 {
+        manager.enable_autosave();
+    }
+    let match_id = spawn_match(&state, manager).await;
+
+    Ok(CommandResponse::success_with_data(serde_json::json!({
+        "match_id": match_id,
+        "warnings": warnings,
+    })))
+}
+
+/// Run a match in the background, listed in `running_matches` until it ends
+async fn spawn_match(state: &AppState, manager: EngineVsEngineManager) -> String {
+    let match_id = manager.match_id().await;
+    let running_matches = state.running_matches.clone();
+    running_matches.write().await.insert(
+        match_id.clone(),
+        RunningMatch {
+            config: manager.config().clone(),
+            state: manager.state_handle(),
+            move_delay_ms: manager.move_delay_handle(),
+        },
+    );
+
+    let id = match_id.clone();
+    tokio::spawn(async move {
+        if let Err(e) = manager.run_match().await {
+            log::error!("Engine-vs-engine match error: {}", e);
+        }
+        running_matches.write().await.remove(&id);
+    });
+    match_id
+}
+
+/// Change the pause after each move of a running match, e.g. 0 to finish it at full speed
+#[tauri::command]
+pub async fn set_match_move_delay(
+    match_id: String,
+    move_delay_ms: u64,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_match_move_delay - match_id: {}, move_delay_ms: {}", match_id, move_delay_ms);
+
+    match state.running_matches.read().await.get(&match_id) {
+        Some(running) => {
+            running.move_delay_ms.store(move_delay_ms, std::sync::atomic::Ordering::Relaxed);
             Ok(CommandResponse::success())
         }
+        None => Ok(CommandResponse::failure(message!(
+            "no_running_match", "No running match: {match_id}", match_id = match_id
+        ))),
+    }
+}
+
+/// Get the configuration of a finished match, including its seed, so it can be replayed
+/// by passing the same values back to `start_engine_vs_engine`
+#[tauri::command]
+pub async fn replay_match_config(match_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: replay_match_config - match_id: {}", match_id);
+
+    let record = match GameRecordStorage::load(&match_id).await {
+        Ok(record) => record,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_match", "Failed to load match: {error}", error = e
+        ))),
+    };
+
+    match record.match_config {
+        Some(config) => Ok(CommandResponse::success_with_data(
+            serde_json::to_value(config).unwrap_or(serde_json::json!({}))
+        )),
+        None => Ok(CommandResponse::failure(message!(
+            "game_record_not_from_match", "Game record {match_id} was not produced by a match", match_id = match_id
+        ))),
+    }
+}
+
+/// Recover engines from a corrupt engines.json, or from the copy kept when it failed to load
+#[tauri::command]
+pub async fn repair_storage(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: repair_storage");
+
+    let mut storage = state.engine_storage.write().await;
+    let report = match storage.repair().await {
+        Ok(report) => report,
         Err(e) => {
-            log::error!("Failed to set favorite engine: {}", e);
-            Ok(CommandResponse::error(format!("Failed to set favorite engine: {}", e)))
+            log::error!("Failed to repair engine storage: {}", e);
+            return Ok(CommandResponse::failure(message!(
+                "failed_to_repair_storage", "Failed to repair storage: {error}", error = e
+            )));
+        }
+    };
+
+    if let Err(e) = storage.save().await {
+        log::error!("Failed to save engine storage: {}", e);
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+        )));
+    }
+
+    log::info!("Engine storage repaired: {} engines recovered", report.recovered_engines);
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(report).unwrap_or(serde_json::json!({}))
+    ))
+}
+
+/// Get the app-wide settings
+#[tauri::command]
+pub async fn get_settings(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: get_settings");
+
+    let settings = state.settings.read().await;
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(&*settings).unwrap_or(serde_json::json!({}))
+    ))
+}
+
+/// Update some settings, given as an object of the fields to change, and emit `settings-changed`
+#[tauri::command]
+pub async fn update_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    patch: serde_json::Value,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: update_settings - {}", patch);
+
+    let mut settings = state.settings.write().await;
+    let updated = match settings.apply_patch(&patch) {
+        Ok(updated) => updated,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "invalid_settings", "Invalid settings: {error}", error = e
+        ))),
+    };
+    store_settings(&app_handle, &state, &mut settings, updated).await
+}
+
+/// Restore the default settings and emit `settings-changed`
+#[tauri::command]
+pub async fn reset_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: reset_settings");
+
+    let mut settings = state.settings.write().await;
+    store_settings(&app_handle, &state, &mut settings, AppSettings::default()).await
+}
+
+async fn store_settings(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    settings: &mut AppSettings,
+    updated: AppSettings,
+) -> Result<CommandResponse, String> {
+    if let Err(e) = updated.save().await {
+        log::error!("Failed to save settings: {}", e);
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_settings", "Failed to save settings: {error}", error = e
+        )));
+    }
+    *settings = updated;
+    state.engine_manager.set_output_throttle(settings.engine_output);
+    state.engine_manager.set_startup_policy(settings.engine_startup);
+    TimeoutConfig::set_global(settings.engine_timeouts);
+    state.engine_manager.set_idle_policy(settings.engine_idle);
+    state.engine_manager.set_heartbeat_policy(settings.engine_heartbeat);
+    log_buffer::set_level(settings.log_level_filter());
+
+    let _ = tauri_events::send(&app_handle, &tauri_events::SETTINGS_CHANGED, &*settings);
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(&*settings).unwrap_or(serde_json::json!({}))
+    ))
+}
+
+/// Change the log level and save it in the settings
+#[tauri::command]
+pub async fn set_log_level(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    level: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_log_level - level: {}", level);
+
+    let mut settings = state.settings.write().await;
+    let updated = match settings.apply_patch(&serde_json::json!({ "log_level": level.to_lowercase() })) {
+        Ok(updated) => updated,
+        Err(e) => return Ok(CommandResponse::error(e.to_string())),
+    };
+    store_settings(&app_handle, &state, &mut settings, updated).await
+}
+
+/// Version of the event schema (`events::SCHEMA_VERSION`), so the frontend can tell whether its
+/// generated event definitions match this backend
+#[tauri::command]
+pub async fn get_event_schema() -> Result<CommandResponse, String> {
+    Ok(CommandResponse::success_with_data(serde_json::json!({ "version": events::SCHEMA_VERSION })))
+}
+
+/// The last `count` log records kept in memory (200 by default), oldest first, optionally
+/// only those at `level_filter` or more severe
+#[tauri::command]
+pub async fn get_recent_logs(count: Option<usize>, level_filter: Option<String>) -> Result<CommandResponse, String> {
+    let min_level = match level_filter.as_deref().map(str::parse::<log::LevelFilter>) {
+        None => log::LevelFilter::Trace,
+        Some(Ok(level)) => level,
+        Some(Err(_)) => {
+            let level = level_filter.unwrap_or_default();
+            return Ok(CommandResponse::failure(message!(
+                "unknown_log_level", "Unknown log level: {level}", level = level
+            )));
+        }
+    };
+    let entries = log_buffer::recent(count.unwrap_or(200), min_level);
+    Ok(CommandResponse::success_with_data(serde_json::json!(entries)))
+}
+
+/// The last `count` invoked commands (100 by default), oldest first, optionally only those
+/// named `command`: arguments with secrets redacted, duration and outcome
+#[tauri::command]
+pub async fn get_command_history(count: Option<usize>, command: Option<String>) -> Result<CommandResponse, String> {
+    let history = command_audit::history(count.unwrap_or(100), command.as_deref());
+    Ok(CommandResponse::success_with_data(serde_json::json!(history)))
+}
+
+/// Current values of the backend's local performance metrics: engines spawned, command and
+/// event rates, emit latency and match throughput. Nothing is collected beyond this process.
+#[tauri::command]
+pub async fn get_metrics_snapshot() -> Result<CommandResponse, String> {
+    Ok(CommandResponse::success_with_data(serde_json::json!(metrics::snapshot())))
+}
+
+/// Record how invoked commands ended; sent in batches by the frontend's invoke wrapper
+#[tauri::command]
+pub async fn record_command_results(results: Vec<CommandResult>) -> Result<CommandResponse, String> {
+    for result in results {
+        let call_id = result.call_id.clone();
+        if !command_audit::finish(result) {
+            log::debug!("No pending invocation {} to record a result for", call_id);
         }
     }
+    Ok(CommandResponse::success())
+}
+
+/// Save engine options
+#[tauri::command]
+pub async fn save_engine_options(
+    engine_id: String,
+    options: std::collections::HashMap<String, String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: save_engine_options - engine_id: {}, options: {:?}", engine_id, options);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.save_engine_options(&engine_id, options) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_options", "Failed to save options: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine options saved successfully for engine: {}", engine_id);
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to save engine options: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_save_options", "Failed to save options: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Get saved engine options
+#[tauri::command]
+pub async fn get_engine_options(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: get_engine_options - engine_id: {}", engine_id);
+
+    let storage = state.engine_storage.read().await;
+    
+    match storage.get_engine_options(&engine_id) {
+        Some(options) => {
+            log::info!("Retrieved {} saved options for engine: {}", options.len(), engine_id);
+            Ok(CommandResponse::success_with_data(serde_json::to_value(options).unwrap()))
+        }
+        None => {
+            log::info!("No saved options found for engine: {}", engine_id);
+            Ok(CommandResponse::success_with_data(serde_json::Value::Object(serde_json::Map::new())))
+        }
+    }
+}
+
+/// Clone an engine with a new display name
+#[tauri::command]
+pub async fn clone_engine(
+    engine_id: String,
+    new_display_name: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: clone_engine - engine_id: {}, new_display_name: {}", engine_id, new_display_name);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.clone_engine(&engine_id, new_display_name) {
+        Ok(new_engine_id) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_cloned_engine", "Failed to save cloned engine: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine cloned successfully: {} -> {}", engine_id, new_engine_id);
+            Ok(CommandResponse::success_with_data(serde_json::json!({ "new_engine_id": new_engine_id })))
+        }
+        Err(e) => {
+            log::error!("Failed to clone engine: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_clone_engine", "Failed to clone engine: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Update engine display name
+#[tauri::command]
+pub async fn update_engine_display_name(
+    engine_id: String,
+    new_display_name: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: update_engine_display_name - engine_id: {}, new_display_name: {}", engine_id, new_display_name);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.update_display_name(&engine_id, new_display_name) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_display_name", "Failed to save display name: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine display name updated successfully: {}", engine_id);
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to update display name: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_update_display_name", "Failed to update display name: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Set the default engine for games, analysis or tsume solving; no `engine_id` clears it
+#[tauri::command]
+pub async fn set_default_engine(
+    purpose: EnginePurpose,
+    engine_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_default_engine - purpose: {:?}, engine_id: {:?}", purpose, engine_id);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.set_default_engine(purpose, engine_id.as_deref()) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_default_engine", "Failed to save default engine: {error}", error = e
+                )));
+            }
+            
+            log::info!("Default {:?} engine set to {:?}", purpose, engine_id);
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set default engine: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_default_engine", "Failed to set default engine: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Get the default engine chosen for each purpose, and the engine each purpose resolves to
+/// after falling back to the game default, the built-in engine and the first engine
+#[tauri::command]
+pub async fn get_default_engines(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let storage = state.engine_storage.read().await;
+    let resolved = |purpose| storage.default_engine(purpose).map(|engine| engine.id.clone());
+    Ok(CommandResponse::success_with_data(serde_json::json!({
+        "defaults": storage.default_engines,
+        "resolved": {
+            "game": resolved(EnginePurpose::Game),
+            "analysis": resolved(EnginePurpose::Analysis),
+            "tsume": resolved(EnginePurpose::Tsume),
+        },
+    })))
+}
+
+/// Move an engine into a named group, or out of its group when `group` is empty
+#[tauri::command]
+pub async fn set_engine_group(
+    engine_id: String,
+    group: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_group - engine_id: {}, group: {:?}", engine_id, group);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.set_engine_group(&engine_id, group) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_engine_group", "Failed to save engine group: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine group updated successfully: {}", engine_id);
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set engine group: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_engine_group", "Failed to set engine group: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Set the manual sort order of engines and, optionally, of groups
+#[tauri::command]
+pub async fn reorder_engines(
+    engine_ids: Vec<String>,
+    group_order: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: reorder_engines - engine_ids: {:?}, group_order: {:?}", engine_ids, group_order);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    let result = storage.reorder_engines(&engine_ids).and_then(|_| match group_order {
+        Some(groups) => storage.reorder_groups(groups),
+        None => Ok(()),
+    });
+
+    match result {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_engine_order", "Failed to save engine order: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine order updated successfully");
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to reorder engines: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_reorder_engines", "Failed to reorder engines: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Replace the tags of an engine
+#[tauri::command]
+pub async fn set_engine_tags(
+    engine_id: String,
+    tags: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_tags - engine_id: {}, tags: {:?}", engine_id, tags);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.set_engine_tags(&engine_id, tags) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_engine_tags", "Failed to save engine tags: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine tags updated successfully: {}", engine_id);
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set engine tags: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_engine_tags", "Failed to set engine tags: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Replace the notes of an engine
+#[tauri::command]
+pub async fn set_engine_notes(
+    engine_id: String,
+    notes: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_notes - engine_id: {}", engine_id);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.set_engine_notes(&engine_id, notes) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_engine_notes", "Failed to save engine notes: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine notes updated successfully: {}", engine_id);
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set engine notes: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_engine_notes", "Failed to set engine notes: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Set the process priority an engine is spawned with
+#[tauri::command]
+pub async fn set_engine_priority(
+    engine_id: String,
+    priority: Option<ProcessPriority>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_priority - engine_id: {}, priority: {:?}", engine_id, priority);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.set_engine_priority(&engine_id, priority) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_engine_priority", "Failed to save engine priority: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine priority updated successfully: {}", engine_id);
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set engine priority: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_engine_priority", "Failed to set engine priority: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Enable or disable an engine. Disabled engines stay configured but cannot be started,
+/// whether for a game, analysis or a match.
+#[tauri::command]
+pub async fn set_engine_enabled(
+    engine_id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_enabled - engine_id: {}, enabled: {}", engine_id, enabled);
+
+    let mut storage = state.engine_storage.write().await;
+
+    match storage.set_engine_enabled(&engine_id, enabled) {
+        Ok(_) => {
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_configuration", "Failed to save configuration: {error}", error = e
+                )));
+            }
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set engine enabled: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_engine_enabled", "Failed to set engine enabled: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Keep an engine warm: an initialized instance is started shortly after this, at every app
+/// start and again whenever it crashes or is stopped, until keep-warm is turned off
+#[tauri::command]
+pub async fn set_engine_keep_warm(
+    engine_id: String,
+    keep_warm: bool,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_keep_warm - engine_id: {}, keep_warm: {}", engine_id, keep_warm);
+
+    let mut storage = state.engine_storage.write().await;
+
+    match storage.set_engine_keep_warm(&engine_id, keep_warm) {
+        Ok(_) => {
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_keep_warm_setting", "Failed to save keep-warm setting: {error}", error = e
+                )));
+            }
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set keep-warm: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_keep_warm", "Failed to set keep-warm: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Set or clear an engine's timeout overrides; unset fields use the app-wide timeouts
+#[tauri::command]
+pub async fn set_engine_timeouts(
+    engine_id: String,
+    timeouts: Option<TimeoutOverrides>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_timeouts - engine_id: {}, timeouts: {:?}", engine_id, timeouts);
+
+    let mut storage = state.engine_storage.write().await;
+
+    match storage.set_engine_timeouts(&engine_id, timeouts) {
+        Ok(_) => {
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_timeouts", "Failed to save timeouts: {error}", error = e
+                )));
+            }
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set timeouts: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_timeouts", "Failed to set timeouts: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Replace the command macros of an engine
+#[tauri::command]
+pub async fn set_engine_macros(
+    engine_id: String,
+    macros: Vec<UsiMacro>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_macros - engine_id: {}, macros: {}", engine_id, macros.len());
+
+    let mut storage = state.engine_storage.write().await;
+
+    match storage.set_engine_macros(&engine_id, macros) {
+        Ok(_) => {
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_macros", "Failed to save macros: {error}", error = e
+                )));
+            }
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set macros: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_macros", "Failed to set macros: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Send the lines of one of a running engine's macros, placeholders filled from `params`.
+/// Returns the lines as sent.
+#[tauri::command]
+pub async fn run_engine_macro(
+    engine_id: String,
+    macro_name: String,
+    params: Option<std::collections::HashMap<String, String>>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: run_engine_macro - engine_id: {}, macro: {}", engine_id, macro_name);
+
+    let runtime_id = match state.engine_manager.runtime_id(&engine_id).await {
+        Ok(runtime_id) => runtime_id,
+        Err(e) => {
+            return Ok(CommandResponse::failure(message!(
+                "engine_not_running", "Engine is not running: {error}", error = e
+            )));
+        }
+    };
+    let usi_macro = {
+        let storage = state.engine_storage.read().await;
+        storage
+            .get_engine(&runtime_id.config_id)
+            .and_then(|engine| engine.macros.iter().find(|usi_macro| usi_macro.name == macro_name).cloned())
+    };
+    let Some(usi_macro) = usi_macro else {
+        return Ok(CommandResponse::failure(message!(
+            "macro_not_found", "The engine has no macro named {name}", name = macro_name
+        )));
+    };
+    let lines = match usi_macro.expand(&params.unwrap_or_default()) {
+        Ok(lines) => lines,
+        Err(e) => {
+            return Ok(CommandResponse::failure(message!(
+                "invalid_macro_params", "Cannot run macro {name}: {error}", name = macro_name, error = e
+            )));
+        }
+    };
+
+    for line in &lines {
+        if let Err(e) = state.engine_manager.send_command(&runtime_id.to_string(), line).await {
+            log::error!("Failed to send macro line {} to engine: {}", line, e);
+            return Ok(CommandResponse::failure(message!(
+                "failed_to_send_command", "Failed to send command: {error}", error = e
+            )));
+        }
+    }
+    Ok(CommandResponse::success_with_data(serde_json::json!({ "lines": lines })))
+}
+
+/// Runtime ID of the warm instance of an engine, or null while it is not (yet) running
+#[tauri::command]
+pub async fn get_warm_engine(
+    config_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let runtime_id = state.engine_manager.warm_instance(&config_id).await;
+
+    Ok(CommandResponse::success_with_data(
+        serde_json::json!({ "runtime_id": runtime_id.map(|id| id.to_string()) })
+    ))
+}
+
+/// Set the win-rate calibration of an engine, or clear it to use the default scale
+#[tauri::command]
+pub async fn set_engine_win_rate(
+    engine_id: String,
+    calibration: Option<WinRateCalibration>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_win_rate - engine_id: {}, calibration: {:?}", engine_id, calibration);
+
+    if calibration.is_some_and(|calibration| !calibration.scale_cp.is_finite() || calibration.scale_cp <= 0.0) {
+        return Ok(CommandResponse::failure(message!("invalid_win_rate_scale", "Win-rate scale must be positive")));
+    }
+
+    let mut storage = state.engine_storage.write().await;
+
+    match storage.set_engine_win_rate(&engine_id, calibration) {
+        Ok(_) => {
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_win_rate_calibration", "Failed to save win-rate calibration: {error}", error = e
+                )));
+            }
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set win-rate calibration: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_win_rate_calibration", "Failed to set win-rate calibration: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Format a score an engine reported with `side_to_move` to move as Black's score and
+/// winning chance, using the engine's calibration (or the default when no engine is given)
+#[tauri::command]
+pub async fn format_engine_score(
+    engine_id: Option<String>,
+    score: Score,
+    side_to_move: Color,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let calibration = match &engine_id {
+        Some(engine_id) => state
+            .engine_storage
+            .read()
+            .await
+            .get_engine(engine_id)
+            .and_then(|engine| engine.win_rate)
+            .unwrap_or_default(),
+        None => WinRateCalibration::default(),
+    };
+
+    let formatted = win_rate::format_score(score, side_to_move, &calibration);
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(formatted).unwrap_or_default()
+    ))
+}
+
+/// Register an evaluation file (e.g. an NNUE net) so engines can be pointed at it
+#[tauri::command]
+pub async fn add_eval_file(
+    name: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: add_eval_file - name: {}, path: {}", name, path);
+
+    let eval = match EvalFile::from_path(name, path).await {
+        Ok(eval) => eval,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_add_eval_file", "Failed to add eval file: {error}", error = e
+        ))),
+    };
+
+    let mut storage = state.engine_storage.write().await;
+    let eval_file_id = storage.add_eval_file(eval);
+    if let Err(e) = storage.save().await {
+        log::error!("Failed to save engine storage: {}", e);
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_eval_file", "Failed to save eval file: {error}", error = e
+        )));
+    }
+
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(storage.get_eval_file(&eval_file_id)).unwrap_or(serde_json::json!({}))
+    ))
+}
+
+/// List registered evaluation files
+#[tauri::command]
+pub async fn list_eval_files(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let storage = state.engine_storage.read().await;
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(&storage.eval_files).unwrap_or(serde_json::json!([]))
+    ))
+}
+
+/// Forget an evaluation file; engines using it go back to their default eval
+#[tauri::command]
+pub async fn remove_eval_file(
+    eval_file_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: remove_eval_file - eval_file_id: {}", eval_file_id);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.remove_eval_file(&eval_file_id) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_remove_eval_file", "Failed to remove eval file: {error}", error = e
+                )));
+            }
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to remove eval file: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_remove_eval_file", "Failed to remove eval file: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Choose the evaluation file an engine loads, or clear it with `None`
+#[tauri::command]
+pub async fn set_engine_eval_file(
+    engine_id: String,
+    eval_file_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: set_engine_eval_file - engine_id: {}, eval_file_id: {:?}", engine_id, eval_file_id);
+
+    let mut storage = state.engine_storage.write().await;
+    
+    match storage.set_engine_eval_file(&engine_id, eval_file_id) {
+        Ok(_) => {
+            // Save to disk
+            if let Err(e) = storage.save().await {
+                log::error!("Failed to save engine storage: {}", e);
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_save_eval_file_selection", "Failed to save eval file selection: {error}", error = e
+                )));
+            }
+            
+            log::info!("Engine eval file updated successfully: {}", engine_id);
+            Ok(CommandResponse::success())
+        }
+        Err(e) => {
+            log::error!("Failed to set engine eval file: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_set_engine_eval_file", "Failed to set engine eval file: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// List every tag used by any engine
+#[tauri::command]
+pub async fn get_engine_tags(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let storage = state.engine_storage.read().await;
+    Ok(CommandResponse::success_with_data(
+        serde_json::json!({ "tags": storage.all_tags() })
+    ))
+}
+
+/// Read image files from a directory
+/// Supports both bundled resources and user data directories
+#[tauri::command]
+pub async fn list_image_files(
+    directory: String, // 'wallpapers' or 'boards'
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    use std::fs;
+    use std::path::Path;
+    use tauri::Manager;
+    
+    let image_extensions = ["jpg", "jpeg", "png", "svg", "webp"];
+    let mut image_files = Vec::new();
+    
+    // Helper to check if a file is an image
+    fn is_image_file(path: &Path, extensions: &[&str]) -> bool {
+        if let Some(ext) = path.extension() {
+            if let Some(ext_str) = ext.to_str() {
+                return extensions.iter().any(|&e| e.eq_ignore_ascii_case(ext_str));
+            }
+        }
+        false
+    }
+    
+    // Helper to recursively collect image files
+    fn collect_images(
+        dir: &Path,
+        base_path: &str,
+        directory: &str,
+        image_files: &mut Vec<String>,
+        extensions: &[&str],
+    ) {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    let new_base = if base_path.is_empty() {
+                        entry.file_name().to_string_lossy().to_string()
+                    } else {
+                        format!("{}/{}", base_path, entry.file_name().to_string_lossy())
+                    };
+                    collect_images(&path, &new_base, directory, image_files, extensions);
+                } else if is_image_file(&path, extensions) {
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+                    let image_path = if base_path.is_empty() {
+                        format!("/{}/{}", directory, file_name)
+                    } else {
+                        format!("/{}/{}/{}", directory, base_path, file_name)
+                    };
+                    image_files.push(image_path);
+                }
+            }
+        }
+    }
+    
+    // Try to read from multiple locations:
+    // 1. Development: public directory (Vite serves these)
+    // 2. Development: dist directory (built assets)
+    // 3. Production: Resource directory (bundled images)
+    // 4. User data directory (for custom images)
+    
+    // 1. Development public directory (highest priority in dev)
+    #[cfg(debug_assertions)]
+    {
+        // Try multiple ways to find the public directory
+        let mut tried_paths = Vec::new();
+        
+        // Method 1: Try workspace root
+        if let Some(workspace_root) = find_workspace_root() {
+            let public_dir = workspace_root.join("public").join(&directory);
+            tried_paths.push(public_dir);
+        }
+        
+        // Method 2: Try current directory (most common case)
+        if let Ok(current_dir) = std::env::current_dir() {
+            tried_paths.push(current_dir.join("public").join(&directory));
+            // Also try going up one level (in case we're in src-tauri)
+            if let Some(parent) = current_dir.parent() {
+                tried_paths.push(parent.join("public").join(&directory));
+            }
+        }
+        
+        // Method 3: Try relative to executable (for tauri dev)
+        if let Ok(exe_path) = std::env::current_exe() {
+            if let Some(exe_dir) = exe_path.parent() {
+                // Go up to workspace root from src-tauri/target/...
+                let mut current = exe_dir;
+                for _ in 0..6 {
+                    if let Some(parent) = current.parent() {
+                        let public_dir = parent.join("public").join(&directory);
+                        if !tried_paths.contains(&public_dir) {
+                            tried_paths.push(public_dir);
+                        }
+                        // Stop if we found a Cargo.toml (likely workspace root)
+                        if parent.join("Cargo.toml").exists() || parent.join("package.json").exists() {
+                            break;
+                        }
+                        current = parent;
+                    } else {
+                        break;
+                    }
+                }
+            }
+        }
+        
+        // Try each path and use the first one that exists
+        for public_dir in &tried_paths {
+            if public_dir.exists() {
+                log::info!("Reading images from development public directory: {}", public_dir.display());
+                collect_images(public_dir, "", &directory, &mut image_files, &image_extensions);
+                break; // Use first found directory
+            }
+        }
+        
+        // Log if we didn't find any public directory
+        if image_files.is_empty() {
+            log::warn!("No images found in public/{} directory. Tried paths:", directory);
+            for path in &tried_paths {
+                log::warn!("  - {} (exists: {})", path.display(), path.exists());
+            }
+        }
+        
+        // Also try dist directory (built assets)
+        if let Ok(current_dir) = std::env::current_dir() {
+            let dist_dir = current_dir.join("dist").join(&directory);
+            if dist_dir.exists() {
+                log::info!("Reading images from dist directory: {}", dist_dir.display());
+                collect_images(&dist_dir, "", &directory, &mut image_files, &image_extensions);
+            } else if let Some(parent) = current_dir.parent() {
+                let dist_dir = parent.join("dist").join(&directory);
+                if dist_dir.exists() {
+                    log::info!("Reading images from dist directory: {}", dist_dir.display());
+                    collect_images(&dist_dir, "", &directory, &mut image_files, &image_extensions);
+                }
+            }
+        }
+    }
+    
+    // 2. Resource directory (bundled images in production)
+    // These are the images bundled with the app from dist/wallpapers and dist/boards
+    // The resources are configured in tauri.conf.json to bundle ../dist/wallpapers/**/* and ../dist/boards/**/*
+    if let Ok(resource_dir) = app_handle.path().resource_dir() {
+        // Try direct path first (wallpapers/ or boards/ in resource dir)
+        let resource_path = resource_dir.join(&directory);
+        if resource_path.exists() {
+            log::info!("Reading images from resource directory: {}", resource_path.display());
+            collect_images(&resource_path, "", &directory, &mut image_files, &image_extensions);
+        } else {
+            // Also try looking in dist subdirectory (if resources preserved dist/ structure)
+            let dist_resource_path = resource_dir.join("dist").join(&directory);
+            if dist_resource_path.exists() {
+                log::info!("Reading images from dist resource directory: {}", dist_resource_path.display());
+                collect_images(&dist_resource_path, "", &directory, &mut image_files, &image_extensions);
+            } else {
+                log::debug!("Resource directory exists but {} subdirectory not found at: {} or {}", 
+                    directory, resource_path.display(), dist_resource_path.display());
+            }
+        }
+    } else {
+        log::debug!("Could not access resource directory (this is normal in development)");
+    }
+    
+    // 3. Production: Also check dist directory if accessible (for development builds)
+    #[cfg(not(debug_assertions))]
+    {
+        if let Some(workspace_root) = find_workspace_root() {
+            let dist_dir = workspace_root.join("dist").join(&directory);
+            if dist_dir.exists() {
+                log::info!("Reading images from production dist directory: {}", dist_dir.display());
+                collect_images(&dist_dir, "", &directory, &mut image_files, &image_extensions);
+            }
+        }
+    }
+    
+    // 4. User data directory (for custom images - works in both dev and production)
+    // Users can add their own images to ~/.config/shogi-vibe/wallpapers/ or boards/
+    if let Some(config_dir) = dirs::config_dir() {
+        let user_dir = config_dir.join("shogi-vibe").join(&directory);
+        if user_dir.exists() {
+            log::info!("Reading images from user directory: {}", user_dir.display());
+            collect_images(&user_dir, "", &directory, &mut image_files, &image_extensions);
+        } else {
+            log::debug!("User directory does not exist: {} (users can create this to add custom images)", user_dir.display());
+        }
+    }
+    
+    // Remove duplicates and sort
+    image_files.sort();
+    image_files.dedup();
+    
+    log::info!("Found {} images in {} directory", image_files.len(), directory);
+    Ok(image_files)
+}
+
+/// Format USI moves played from a position in the requested notation
+#[tauri::command]
+pub async fn format_moves(
+    sfen: String,
+    moves: Vec<String>,
+    style: NotationStyle,
+) -> Result<CommandResponse, String> {
+    log::debug!("Command: format_moves - sfen: {}, {} moves, style: {:?}", sfen, moves.len(), style);
+
+    let position = match Position::from_sfen(&sfen) {
+        Ok(position) => position,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "invalid_position", "Invalid position: {error}", error = e
+        ))),
+    };
+
+    match notation::format_moves(&position, &moves, style) {
+        Ok(formatted) => Ok(CommandResponse::success_with_data(
+            serde_json::json!({ "moves": formatted })
+        )),
+        Err(e) => {
+            log::warn!("Failed to format moves: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_format_moves", "Failed to format moves: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Parse moves written in the given notation back into USI moves
+#[tauri::command]
+pub async fn parse_moves(
+    sfen: String,
+    moves: Vec<String>,
+    style: NotationStyle,
+) -> Result<CommandResponse, String> {
+    log::debug!("Command: parse_moves - sfen: {}, {} moves, style: {:?}", sfen, moves.len(), style);
+
+    let position = match Position::from_sfen(&sfen) {
+        Ok(position) => position,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "invalid_position", "Invalid position: {error}", error = e
+        ))),
+    };
+
+    match notation::parse_moves(&position, &moves, style) {
+        Ok(usi_moves) => Ok(CommandResponse::success_with_data(
+            serde_json::json!({ "moves": usi_moves })
+        )),
+        Err(e) => {
+            log::warn!("Failed to parse moves: {}", e);
+            Ok(CommandResponse::failure(message!("failed_to_parse_moves", "Failed to parse moves: {error}", error = e)))
+        }
+    }
+}
+
+/// Convert moves played from a position between notations, e.g. CSA to KIF
+#[tauri::command]
+pub async fn convert_moves(
+    sfen: String,
+    moves: Vec<String>,
+    from: NotationStyle,
+    to: NotationStyle,
+) -> Result<CommandResponse, String> {
+    log::debug!("Command: convert_moves - sfen: {}, {} moves, {:?} -> {:?}", sfen, moves.len(), from, to);
+
+    let position = match Position::from_sfen(&sfen) {
+        Ok(position) => position,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "invalid_position", "Invalid position: {error}", error = e
+        ))),
+    };
+
+    match notation::convert_moves(&position, &moves, from, to) {
+        Ok(converted) => Ok(CommandResponse::success_with_data(
+            serde_json::json!({ "moves": converted })
+        )),
+        Err(e) => {
+            log::warn!("Failed to convert moves: {}", e);
+            Ok(CommandResponse::failure(message!(
+                "failed_to_convert_moves", "Failed to convert moves: {error}", error = e
+            )))
+        }
+    }
+}
+
+/// Check an edited position for problems that would make engines misbehave
+#[tauri::command]
+pub async fn validate_position(sfen: String) -> Result<CommandResponse, String> {
+    log::debug!("Command: validate_position - sfen: {}", sfen);
+
+    let errors = match Position::from_sfen(&sfen) {
+        Ok(position) => position.validation_errors(),
+        Err(e) => vec![e.to_string()],
+    };
+    Ok(CommandResponse::success_with_data(serde_json::json!({
+        "valid": errors.is_empty(),
+        "errors": errors,
+    })))
+}
+
+/// Rewrite a valid position as canonical SFEN (hand order, counts and move number)
+#[tauri::command]
+pub async fn normalize_position(sfen: String) -> Result<CommandResponse, String> {
+    log::debug!("Command: normalize_position - sfen: {}", sfen);
+
+    let position = match Position::from_sfen(&sfen) {
+        Ok(position) => position,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "invalid_position", "Invalid position: {error}", error = e
+        ))),
+    };
+    let errors = position.validation_errors();
+    if !errors.is_empty() {
+        return Ok(CommandResponse::failure(message!(
+            "invalid_position", "Invalid position: {error}", error = errors.join("; ")
+        )));
+    }
+    Ok(CommandResponse::success_with_data(
+        serde_json::json!({ "sfen": position.to_sfen() })
+    ))
+}
+
+/// List the standard handicap starting positions
+#[tauri::command]
+pub async fn get_handicap_presets() -> Result<CommandResponse, String> {
+    let presets: Vec<serde_json::Value> = Handicap::ALL
+        .iter()
+        .map(|handicap| serde_json::json!({
+            "id": handicap,
+            "name": handicap.name(),
+            "japanese_name": handicap.japanese_name(),
+            "sfen": handicap.sfen(),
+        }))
+        .collect();
+
+    Ok(CommandResponse::success_with_data(
+        serde_json::json!({ "presets": presets })
+    ))
+}
+
+/// Serialize a game session together with its derived position data
+fn game_session_json(session: &GameSession) -> serde_json::Value {
+    let mut value = serde_json::to_value(session).unwrap_or(serde_json::json!({}));
+    value["move_history"] = serde_json::json!(session.move_history());
+    value["main_line"] = serde_json::json!(session.moves.main_line());
+    if let Ok(position) = session.position() {
+        value["current_sfen"] = serde_json::json!(position.to_sfen());
+        value["side_to_move"] = serde_json::json!(position.side_to_move());
+    }
+    value["position_command"] = serde_json::json!(session.position_command());
+    if let Some(limit) = &session.search_limit {
+        // Non-clock limits ignore the time argument
+        value["go_command"] = serde_json::json!(limit.go_command(0));
+    }
+    value
+}
+
+/// Start a new game session from an optional SFEN or handicap. With an engine, its strength
+/// level (or the default one from settings) is applied by sending the level's options; the
+/// session then reports the `go_command` to use for the engine's moves.
+#[tauri::command]
+pub async fn start_game_session(
+    initial_sfen: Option<String>,
+    handicap: Option<Handicap>,
+    engine_id: Option<String>,
+    strength: Option<StrengthLevel>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!(
+        "Command: start_game_session - handicap: {:?}, engine_id: {:?}, strength: {:?}",
+        handicap,
+        engine_id,
+        strength
+    );
+
+    let mut session = match GameSession::new(initial_sfen, handicap, engine_id) {
+        Ok(session) => session,
+        Err(e) => {
+            log::error!("Failed to start game session: {}", e);
+            return Ok(CommandResponse::failure(message!(
+                "failed_to_start_game_session", "Failed to start game session: {error}", error = e
+            )));
+        }
+    };
+
+    let settings = state.settings.read().await.clone();
+    let level = strength.or(settings.default_strength);
+    let mut resolved = None;
+    if let (Some(level), Some(engine_id)) = (level, session.engine_id.as_deref()) {
+        let runtime_id = match state.engine_manager.runtime_id(engine_id).await {
+            Ok(runtime_id) => runtime_id,
+            Err(e) => return Ok(CommandResponse::failure(message!(
+                "failed_to_apply_strength_level", "Failed to apply strength level: {error}", error = e
+            ))),
+        };
+        let Some(engine) = state.engine_storage.read().await.get_engine(&runtime_id.config_id).cloned() else {
+            return Ok(CommandResponse::failure(message!(
+                "engine_not_found", "Engine not found: {engine_id}", engine_id = runtime_id.config_id
+            )));
+        };
+
+        let strength = strength::resolve(level, &engine, &settings.strength_presets);
+        for (name, value) in &strength.options {
+            let command = format!("setoption name {} value {}", name, value);
+            if let Err(e) = state.engine_manager.send_command(engine_id, &command).await {
+                return Ok(CommandResponse::failure(message!(
+                    "failed_to_apply_strength_level", "Failed to apply strength level: {error}", error = e
+                )));
+            }
+        }
+        if !strength.skipped.is_empty() {
+            log::info!("{} has no {} option(s); skipped", engine.name, strength.skipped.join(", "));
+        }
+        session.strength = Some(level);
+        session.search_limit = strength.search_limit;
+        session.randomization = strength.randomization.clone();
+        resolved = Some(strength);
+    }
+
+    let mut data = game_session_json(&session);
+    data["strength_applied"] = serde_json::to_value(&resolved).unwrap_or(serde_json::json!(null));
+    state.game_sessions.write().await.insert(session);
+    Ok(CommandResponse::success_with_data(data))
+}
+
+/// List the strength levels with the preset each would use for an engine
+#[tauri::command]
+pub async fn get_strength_levels(
+    engine_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: get_strength_levels - engine_id: {}", engine_id);
+
+    let Some(engine) = state.engine_storage.read().await.get_engine(&engine_id).cloned() else {
+        return Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = engine_id
+        )));
+    };
+    let settings = state.settings.read().await;
+    let levels: Vec<_> = StrengthLevel::ALL
+        .into_iter()
+        .map(|level| strength::resolve(level, &engine, &settings.strength_presets))
+        .collect();
+
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(levels).unwrap_or(serde_json::json!([]))
+    ))
+}
+
+/// Get a game session with its current position
+#[tauri::command]
+pub async fn get_game_session(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let sessions = state.game_sessions.read().await;
+
+    match sessions.get(&session_id) {
+        Some(session) => Ok(CommandResponse::success_with_data(game_session_json(session))),
+        None => Ok(CommandResponse::failure(message!(
+            "game_session_not_found", "Game session not found: {session_id}", session_id = session_id
+        ))),
+    }
+}
+
+/// Play a move in a game session (validated against the rules)
+#[tauri::command]
+pub async fn apply_game_session_move(
+    session_id: String,
+    usi_move: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: apply_game_session_move - session_id: {}, move: {}", session_id, usi_move);
+
+    let mut sessions = state.game_sessions.write().await;
+    let result = sessions
+        .get_mut(&session_id)
+        .and_then(|session| session.apply_move(&usi_move).map(|_| game_session_json(session)));
+
+    match result {
+        Ok(data) => Ok(CommandResponse::success_with_data(data)),
+        Err(e) => {
+            log::warn!("Rejected move {} in session {}: {}", usi_move, session_id, e);
+            Ok(CommandResponse::failure(message!("failed_to_apply_move", "Failed to apply move: {error}", error = e)))
+        }
+    }
+}
+
+/// Play the engine's reply in a game session. `info_lines` are the engine's `info` output
+/// for this search; with a randomizing strength level one of its top lines is picked
+/// instead of `best_move`.
+#[tauri::command]
+pub async fn apply_engine_move(
+    session_id: String,
+    best_move: String,
+    info_lines: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: apply_engine_move - session_id: {}, bestmove: {}", session_id, best_move);
+
+    let infos: Vec<UsiInfo> = info_lines.iter().filter_map(|line| usi_info::parse_info(line)).collect();
+    let mut sessions = state.game_sessions.write().await;
+    let result = sessions.get_mut(&session_id).and_then(|session| {
+        let chosen = session.choose_engine_move(&best_move, &infos);
+        session.apply_move(&chosen)?;
+        let mut data = game_session_json(session);
+        data["move"] = serde_json::json!(chosen);
+        Ok(data)
+    });
+
+    match result {
+        Ok(data) => Ok(CommandResponse::success_with_data(data)),
+        Err(e) => {
+            log::warn!("Rejected engine move {} in session {}: {}", best_move, session_id, e);
+            Ok(CommandResponse::failure(message!("failed_to_apply_move", "Failed to apply move: {error}", error = e)))
+        }
+    }
+}
+
+/// Take back the last move of a game session
+#[tauri::command]
+pub async fn undo_game_session_move(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: undo_game_session_move - session_id: {}", session_id);
+
+    let mut sessions = state.game_sessions.write().await;
+    let result = sessions
+        .get_mut(&session_id)
+        .and_then(|session| session.undo_move().map(|_| game_session_json(session)));
+
+    match result {
+        Ok(data) => Ok(CommandResponse::success_with_data(data)),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_undo_move", "Failed to undo move: {error}", error = e
+        ))),
+    }
+}
+
+/// Show the position at a node of a game session's move tree. `path` holds the child index
+/// taken at each move from the initial position; an empty path is the initial position.
+#[tauri::command]
+pub async fn navigate_game_session(
+    session_id: String,
+    path: Vec<usize>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: navigate_game_session - session_id: {}, path: {:?}", session_id, path);
+
+    let mut sessions = state.game_sessions.write().await;
+    let result = sessions
+        .get_mut(&session_id)
+        .and_then(|session| session.navigate(&path).map(|_| game_session_json(session)));
+
+    match result {
+        Ok(data) => Ok(CommandResponse::success_with_data(data)),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_navigate", "Failed to navigate: {error}", error = e
+        ))),
+    }
+}
+
+/// Make a variation of a game session the main line at its branching point
+#[tauri::command]
+pub async fn promote_game_variation(
+    session_id: String,
+    path: Vec<usize>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: promote_game_variation - session_id: {}, path: {:?}", session_id, path);
+
+    let mut sessions = state.game_sessions.write().await;
+    let result = sessions
+        .get_mut(&session_id)
+        .and_then(|session| session.promote_variation(&path).map(|_| game_session_json(session)));
+
+    match result {
+        Ok(data) => Ok(CommandResponse::success_with_data(data)),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_promote_variation", "Failed to promote variation: {error}", error = e
+        ))),
+    }
+}
+
+/// Delete a variation of a game session with all moves after it
+#[tauri::command]
+pub async fn delete_game_variation(
+    session_id: String,
+    path: Vec<usize>,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: delete_game_variation - session_id: {}, path: {:?}", session_id, path);
+
+    let mut sessions = state.game_sessions.write().await;
+    let result = sessions
+        .get_mut(&session_id)
+        .and_then(|session| session.delete_variation(&path).map(|_| game_session_json(session)));
+
+    match result {
+        Ok(data) => Ok(CommandResponse::success_with_data(data)),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_delete_variation", "Failed to delete variation: {error}", error = e
+        ))),
+    }
+}
+
+/// End and discard a game session
+#[tauri::command]
+pub async fn end_game_session(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: end_game_session - session_id: {}", session_id);
+
+    let mut sessions = state.game_sessions.write().await;
+    if let Err(e) = sessions.remove(&session_id) {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_end_game_session", "Failed to end game session: {error}", error = e
+        )));
+    }
+    if let Err(e) = AutosaveStorage::remove(&session_id).await {
+        log::warn!("Failed to remove auto-save of game session {}: {}", session_id, e);
+    }
+    Ok(CommandResponse::success())
+}
+
+/// Record the clocks of a game session, which the frontend runs, so auto-saves include them
+#[tauri::command]
+pub async fn update_game_session_clocks(
+    session_id: String,
+    black_remaining_ms: u64,
+    white_remaining_ms: u64,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let mut sessions = state.game_sessions.write().await;
+    match sessions.get_mut(&session_id) {
+        Ok(session) => {
+            session.clocks = Some(GameClocks { black_remaining_ms, white_remaining_ms });
+            Ok(CommandResponse::success())
+        }
+        Err(e) => Ok(CommandResponse::error(e.to_string())),
+    }
+}
+
+/// List games that were in progress when the app last closed or crashed
+#[tauri::command]
+pub async fn recover_sessions(state: State<'_, AppState>) -> Result<CommandResponse, String> {
+    log::info!("Command: recover_sessions");
+
+    let entries = match AutosaveStorage::list().await {
+        Ok(entries) => entries,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_list_auto_saved_games", "Failed to list auto-saved games: {error}", error = e
+        ))),
+    };
+    // Games of this run are saved too; only the ones no longer active are resumable
+    let sessions = state.game_sessions.read().await;
+    let running_matches = state.running_matches.read().await;
+    let resumable: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| match &entry.game {
+            SavedGame::Session { session } => sessions.get(&session.id).is_none(),
+            SavedGame::Match { state, .. } => !running_matches.contains_key(&state.match_id),
+        })
+        .collect();
+
+    Ok(CommandResponse::success_with_data(serde_json::to_value(resumable).unwrap_or(serde_json::json!([]))))
+}
+
+/// Reopen an auto-saved game session. Engine processes do not survive a restart, so the
+/// session comes back without an engine; the frontend attaches a new one.
+#[tauri::command]
+pub async fn resume_game_session(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: resume_game_session - session_id: {}", session_id);
+
+    let mut session = match AutosaveStorage::load(&session_id).await {
+        Ok(entry) => match entry.game {
+            SavedGame::Session { session } => *session,
+            SavedGame::Match { .. } => return Ok(CommandResponse::failure(message!(
+                "saved_game_is_match", "{session_id} is a match, not a game session", session_id = session_id
+            ))),
+        },
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_auto_saved_game", "Failed to load auto-saved game: {error}", error = e
+        ))),
+    };
+    let previous_engine_id = session.engine_id.take();
+
+    let mut data = game_session_json(&session);
+    data["previous_engine_id"] = serde_json::json!(previous_engine_id);
+    state.game_sessions.write().await.insert(session);
+    Ok(CommandResponse::success_with_data(data))
+}
+
+/// Continue an auto-saved engine-vs-engine match from its last move
+#[tauri::command]
+pub async fn resume_match(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    match_id: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: resume_match - match_id: {}", match_id);
+
+    let (config, match_state) = match AutosaveStorage::load(&match_id).await {
+        Ok(entry) => match entry.game {
+            SavedGame::Match { config, state } => (*config, *state),
+            SavedGame::Session { .. } => return Ok(CommandResponse::failure(message!(
+                "saved_game_is_session", "{match_id} is a game session, not a match", match_id = match_id
+            ))),
+        },
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_auto_saved_game", "Failed to load auto-saved game: {error}", error = e
+        ))),
+    };
+    let moves_played = match_state.move_history.len();
+    let mut manager = match EngineVsEngineManager::resume(
+        TauriEventSink::shared(app_handle),
+        config,
+        match_state,
+        state.engine_storage.clone(),
+    ) {
+        Ok(manager) => manager,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_resume_match", "Failed to resume match: {error}", error = e
+        ))),
+    };
+    manager.enable_autosave();
+    spawn_match(&state, manager).await;
+
+    Ok(CommandResponse::success_with_data(serde_json::json!({
+        "match_id": match_id,
+        "moves_played": moves_played,
+    })))
+}
+
+/// Delete an auto-saved game that will not be resumed
+#[tauri::command]
+pub async fn discard_autosave(id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: discard_autosave - id: {}", id);
+
+    match AutosaveStorage::remove(&id).await {
+        Ok(()) => Ok(CommandResponse::success()),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_discard_auto_saved_game", "Failed to discard auto-saved game: {error}", error = e
+        ))),
+    }
+}
+
+/// List saved game records, newest first
+#[tauri::command]
+pub async fn list_game_records() -> Result<CommandResponse, String> {
+    log::info!("Command: list_game_records");
+
+    match GameRecordStorage::list().await {
+        Ok(records) => Ok(CommandResponse::success_with_data(serde_json::to_value(records).unwrap_or(serde_json::json!([])))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_list_game_records", "Failed to list game records: {error}", error = e
+        ))),
+    }
+}
+
+/// Build an opening book from the stored games `filter` selects: their first `max_depth`
+/// moves (24 by default), each weighted by the results of the games it was played in, keeping
+/// moves played at least `min_count` times (1 by default). Written to `path` as JSON.
+#[tauri::command]
+pub async fn build_book_from_games(
+    filter: Option<BookFilter>,
+    min_count: Option<u32>,
+    max_depth: Option<usize>,
+    path: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: build_book_from_games - filter: {:?}, min_count: {:?}, max_depth: {:?}, path: {}", filter, min_count, max_depth, path);
+
+    let filter = filter.unwrap_or_default();
+    let records: Vec<GameRecord> = match GameRecordStorage::load_all().await {
+        Ok(records) => records.into_iter().filter(|record| filter.matches(record)).collect(),
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_list_game_records", "Failed to list game records: {error}", error = e
+        ))),
+    };
+    let book = opening_book::build(&records, min_count.unwrap_or(1), max_depth.unwrap_or(24));
+    if let Err(e) = book.save(std::path::Path::new(&path)).await {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_write", "Failed to write {path}: {error}", path = path, error = e
+        )));
+    }
+
+    log::info!("Built a book of {} positions from {} games", book.positions.len(), book.games);
+    Ok(CommandResponse::success_with_data(serde_json::json!({
+        "path": path,
+        "games": book.games,
+        "positions": book.positions.len(),
+        "moves": book.move_count(),
+    })))
+}
+
+/// Convert a book built by build_book_from_games to YaneuraOu's format (YANEURAOU-DB2016),
+/// for engines to load as their BookFile
+#[tauri::command]
+pub async fn export_opening_book(book_path: String, path: String) -> Result<CommandResponse, String> {
+    log::info!("Command: export_opening_book - book_path: {}, path: {}", book_path, path);
+
+    let book = match OpeningBook::load(std::path::Path::new(&book_path)).await {
+        Ok(book) => book,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_opening_book", "Failed to load opening book: {error}", error = e
+        ))),
+    };
+    if let Err(e) = tokio::fs::write(&path, opening_book::to_yaneuraou(&book)).await {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_write", "Failed to write {path}: {error}", path = path, error = e
+        )));
+    }
+    Ok(CommandResponse::success_with_data(serde_json::json!({ "path": path, "positions": book.positions.len() })))
+}
+
+/// Load a saved game record
+#[tauri::command]
+pub async fn get_game_record(record_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: get_game_record - record_id: {}", record_id);
+
+    match GameRecordStorage::load(&record_id).await {
+        Ok(record) => Ok(CommandResponse::success_with_data(serde_json::to_value(record).unwrap_or(serde_json::json!({})))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_load_game_record", "Failed to load game record: {error}", error = e
+        ))),
+    }
+}
+
+/// The archived thinking behind the move at `ply` (1-based) of a game played with
+/// record_thinking
+#[tauri::command]
+pub async fn get_game_think_log(game_id: String, ply: usize) -> Result<CommandResponse, String> {
+    log::info!("Command: get_game_think_log - game_id: {}, ply: {}", game_id, ply);
+
+    match GameRecordStorage::load(&game_id).await {
+        Ok(record) if record.think_log.is_none() => {
+            return Ok(CommandResponse::failure(message!(
+                "no_thinking_recorded", "No thinking was recorded for game {game_id}", game_id = game_id
+            )));
+        }
+        Ok(_) => {}
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_game_record", "Failed to load game record: {error}", error = e
+        ))),
+    }
+    match ThinkLogStorage::load_ply(&game_id, ply).await {
+        Ok(thinking) => Ok(CommandResponse::success_with_data(serde_json::to_value(thinking).unwrap_or(serde_json::json!({})))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_load_thinking_log", "Failed to load thinking log: {error}", error = e
+        ))),
+    }
+}
+
+/// Delete a saved game record
+#[tauri::command]
+pub async fn delete_game_record(record_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: delete_game_record - record_id: {}", record_id);
+
+    match GameRecordStorage::delete(&record_id).await {
+        Ok(_) => Ok(CommandResponse::success()),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_delete_game_record", "Failed to delete game record: {error}", error = e
+        ))),
+    }
+}
+
+/// Regenerate the machine commentary of a saved game record
+#[tauri::command]
+pub async fn generate_game_commentary(record_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: generate_game_commentary - record_id: {}", record_id);
+
+    let mut record = match GameRecordStorage::load(&record_id).await {
+        Ok(record) => record,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_game_record", "Failed to load game record: {error}", error = e
+        ))),
+    };
+    if let Err(e) = commentary::annotate(&mut record) {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_generate_commentary", "Failed to generate commentary: {error}", error = e
+        )));
+    }
+    if let Err(e) = GameRecordStorage::save(&record).await {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_game_record", "Failed to save game record: {error}", error = e
+        )));
+    }
+
+    Ok(CommandResponse::success_with_data(serde_json::to_value(record).unwrap_or(serde_json::json!({}))))
+}
+
+/// Export a saved game record as KIF text, including its commentary
+#[tauri::command]
+pub async fn export_game_record_kif(record_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: export_game_record_kif - record_id: {}", record_id);
+
+    let result = GameRecordStorage::load(&record_id)
+        .await
+        .and_then(|record| kif::export_kif(&record));
+    match result {
+        Ok(kif) => Ok(CommandResponse::success_with_data(serde_json::json!({ "kif": kif }))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_export_kif", "Failed to export KIF: {error}", error = e
+        ))),
+    }
+}
+
+/// Import KIF text as a new saved game record, keeping its comments and variations
+#[tauri::command]
+pub async fn import_game_record_kif(kif: String) -> Result<CommandResponse, String> {
+    log::info!("Command: import_game_record_kif - {} bytes", kif.len());
+
+    let record = match kif::import_kif(&kif) {
+        Ok(record) => record,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_import_kif", "Failed to import KIF: {error}", error = e
+        ))),
+    };
+    if let Err(e) = GameRecordStorage::save(&record).await {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_game_record", "Failed to save game record: {error}", error = e
+        )));
+    }
+
+    Ok(CommandResponse::success_with_data(serde_json::to_value(record).unwrap_or(serde_json::json!({}))))
+}
+
+/// Load a saved record, change it and save it back, returning the updated record
+async fn update_game_record(
+    record_id: &str,
+    update: impl FnOnce(&mut GameRecord) -> Result<()>,
+) -> Result<CommandResponse, String> {
+    let mut record = match GameRecordStorage::load(record_id).await {
+        Ok(record) => record,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_game_record", "Failed to load game record: {error}", error = e
+        ))),
+    };
+    if let Err(e) = update(&mut record) {
+        return Ok(CommandResponse::error(e.to_string()));
+    }
+    if let Err(e) = GameRecordStorage::save(&record).await {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_game_record", "Failed to save game record: {error}", error = e
+        )));
+    }
+
+    Ok(CommandResponse::success_with_data(serde_json::to_value(record).unwrap_or(serde_json::json!({}))))
+}
+
+/// Set or clear the comment and symbol of a move (0-based index) in a saved record
+#[tauri::command]
+pub async fn annotate_game_move(
+    record_id: String,
+    move_index: usize,
+    comment: Option<String>,
+    symbol: Option<MoveSymbol>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: annotate_game_move - record_id: {}, move: {}", record_id, move_index);
+    update_game_record(&record_id, |record| record.annotate_move(move_index, comment, symbol)).await
+}
+
+/// Add a line of USI moves as an alternative to a move (0-based index) of a saved record
+#[tauri::command]
+pub async fn add_game_variation(
+    record_id: String,
+    move_index: usize,
+    moves: Vec<String>,
+    comment: Option<String>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: add_game_variation - record_id: {}, move: {}, {} moves", record_id, move_index, moves.len());
+    update_game_record(&record_id, |record| record.add_variation(move_index, Variation { moves, comment })).await
+}
+
+#[tauri::command]
+pub async fn remove_game_variation(
+    record_id: String,
+    move_index: usize,
+    variation_index: usize,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: remove_game_variation - record_id: {}, move: {}, variation: {}", record_id, move_index, variation_index);
+    update_game_record(&record_id, |record| record.remove_variation(move_index, variation_index).map(|_| ())).await
+}
+
+/// Start tuning numeric options of an engine with SPSA. Progress is emitted as `tuning-progress`.
+#[tauri::command]
+pub async fn start_tuning(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    config: TuningConfig,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: start_tuning - engine: {}, {} parameters", config.engine_id, config.params.len());
+
+    let storage = state.engine_storage.read().await;
+    let Some(engine) = storage.get_engine(&config.engine_id) else {
+        return Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = config.engine_id
+        )));
+    };
+    if let Err(e) = config.validate(engine) {
+        return Ok(CommandResponse::failure(message!(
+            "invalid_tuning_configuration", "Invalid tuning configuration: {error}", error = e
+        )));
+    }
+    drop(storage);
+
+    let session = TuningSession::new(config);
+    if let Err(e) = TuningStorage::save(&session).await {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_tuning_session", "Failed to save tuning session: {error}", error = e
+        )));
+    }
+    launch_tuning(app_handle, &state, session).await
+}
+
+/// Continue a paused, failed or interrupted tuning session from its last completed iteration
+#[tauri::command]
+pub async fn resume_tuning(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: resume_tuning - session_id: {}", session_id);
+
+    if state.tuning_runs.read().await.contains_key(&session_id) {
+        return Ok(CommandResponse::failure(message!(
+            "tuning_session_already_running", "Tuning session {session_id} is already running", session_id = session_id
+        )));
+    }
+    let mut session = match TuningStorage::load(&session_id).await {
+        Ok(session) => session,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_tuning_session", "Failed to load tuning session: {error}", error = e
+        ))),
+    };
+    if session.status == TuningStatus::Completed {
+        return Ok(CommandResponse::failure(message!(
+            "tuning_session_completed", "Tuning session {session_id} has already completed", session_id = session_id
+        )));
+    }
+    session.error = None;
+    launch_tuning(app_handle, &state, session).await
+}
+
+async fn launch_tuning(
+    app_handle: tauri::AppHandle,
+    state: &AppState,
+    mut session: TuningSession,
+) -> Result<CommandResponse, String> {
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    state.tuning_runs.write().await.insert(session.id.clone(), stop.clone());
+    session.status = TuningStatus::Running;
+    let data = serde_json::to_value(&session).unwrap_or(serde_json::json!({}));
+
+    let engine_storage = state.engine_storage.clone();
+    let tuning_runs = state.tuning_runs.clone();
+    tokio::spawn(async move {
+        let session = tuning::run_session(app_handle, engine_storage, session, stop).await;
+        tuning_runs.write().await.remove(&session.id);
+        log::info!("Tuning session {} stopped after {} iterations", session.id, session.iteration);
+    });
+
+    Ok(CommandResponse::success_with_data(data))
+}
+
+/// Ask a running tuning session to stop after its current iteration
+#[tauri::command]
+pub async fn stop_tuning(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: stop_tuning - session_id: {}", session_id);
+
+    match state.tuning_runs.read().await.get(&session_id) {
+        Some(stop) => {
+            stop.store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok(CommandResponse::success())
+        }
+        None => Ok(CommandResponse::failure(message!(
+            "tuning_session_not_running", "Tuning session {session_id} is not running", session_id = session_id
+        ))),
+    }
+}
+
+/// Load a tuning session with its iteration history
+#[tauri::command]
+pub async fn get_tuning_session(session_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: get_tuning_session - session_id: {}", session_id);
+
+    match TuningStorage::load(&session_id).await {
+        Ok(session) => Ok(CommandResponse::success_with_data(serde_json::json!({
+            "session": session,
+            "current_options": session.current_options(),
+        }))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_load_tuning_session", "Failed to load tuning session: {error}", error = e
+        ))),
+    }
+}
+
+/// List saved tuning sessions, newest first
+#[tauri::command]
+pub async fn list_tuning_sessions() -> Result<CommandResponse, String> {
+    log::info!("Command: list_tuning_sessions");
+
+    match TuningStorage::list().await {
+        Ok(sessions) => Ok(CommandResponse::success_with_data(serde_json::to_value(sessions).unwrap_or(serde_json::json!([])))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_list_tuning_sessions", "Failed to list tuning sessions: {error}", error = e
+        ))),
+    }
+}
+
+/// Start a round-robin or Swiss tournament between configured engines. Progress, standings
+/// and an ETA are emitted as `tournament-progress`, and standings after each round as
+/// `tournament-standings`.
+#[tauri::command]
+pub async fn start_tournament(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    config: TournamentConfig,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: start_tournament - {} with {} engines", config.name, config.engine_ids.len());
+
+    if let Err(e) = config.validate() {
+        return Ok(CommandResponse::failure(message!(
+            "invalid_tournament_configuration", "Invalid tournament configuration: {error}", error = e
+        )));
+    }
+    let storage = state.engine_storage.read().await;
+    let mut engine_names = std::collections::HashMap::new();
+    for engine_id in &config.engine_ids {
+        let engine = match storage.usable_engine(engine_id) {
+            Ok(engine) => engine,
+            Err(e) => return Ok(CommandResponse::error(e.to_string())),
+        };
+        let name = if engine.display_name.is_empty() { &engine.name } else { &engine.display_name };
+        engine_names.insert(engine_id.clone(), name.clone());
+    }
+    drop(storage);
+
+    let tournament = Tournament::new(config, engine_names);
+    if let Err(e) = TournamentStorage::save(&tournament).await {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_save_tournament", "Failed to save tournament: {error}", error = e
+        )));
+    }
+    launch_tournament(app_handle, &state, tournament).await
+}
+
+/// Continue a paused, failed or interrupted tournament with its first unplayed game
+#[tauri::command]
+pub async fn resume_tournament(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    tournament_id: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: resume_tournament - tournament_id: {}", tournament_id);
+
+    if state.tournament_runs.read().await.contains_key(&tournament_id) {
+        return Ok(CommandResponse::failure(message!(
+            "tournament_already_running",
+            "Tournament {tournament_id} is already running",
+            tournament_id = tournament_id
+        )));
+    }
+    let mut tournament = match TournamentStorage::load(&tournament_id).await {
+        Ok(tournament) => tournament,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_tournament", "Failed to load tournament: {error}", error = e
+        ))),
+    };
+    if tournament.status == TournamentStatus::Completed {
+        return Ok(CommandResponse::failure(message!(
+            "tournament_completed", "Tournament {tournament_id} has already completed", tournament_id = tournament_id
+        )));
+    }
+    tournament.error = None;
+    launch_tournament(app_handle, &state, tournament).await
+}
+
+async fn launch_tournament(
+    app_handle: tauri::AppHandle,
+    state: &AppState,
+    mut tournament: Tournament,
+) -> Result<CommandResponse, String> {
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    state.tournament_runs.write().await.insert(tournament.id.clone(), stop.clone());
+    tournament.status = TournamentStatus::Running;
+    let data = serde_json::to_value(&tournament).unwrap_or(serde_json::json!({}));
+
+    let engine_storage = state.engine_storage.clone();
+    let tournament_runs = state.tournament_runs.clone();
+    tokio::spawn(async move {
+        let events = TauriEventSink::shared(app_handle);
+        let tournament = tournament::run_tournament(events, engine_storage, tournament, stop).await;
+        tournament_runs.write().await.remove(&tournament.id);
+        log::info!("Tournament {} stopped with status {:?}", tournament.id, tournament.status);
+    });
+
+    Ok(CommandResponse::success_with_data(data))
+}
+
+/// Ask a running tournament to stop after its current game
+#[tauri::command]
+pub async fn stop_tournament(
+    state: State<'_, AppState>,
+    tournament_id: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: stop_tournament - tournament_id: {}", tournament_id);
+
+    match state.tournament_runs.read().await.get(&tournament_id) {
+        Some(stop) => {
+            stop.store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok(CommandResponse::success())
+        }
+        None => Ok(CommandResponse::failure(message!(
+            "tournament_not_running", "Tournament {tournament_id} is not running", tournament_id = tournament_id
+        ))),
+    }
+}
+
+/// Load a tournament with its games and current progress
+#[tauri::command]
+pub async fn get_tournament(tournament_id: String) -> Result<CommandResponse, String> {
+    log::info!("Command: get_tournament - tournament_id: {}", tournament_id);
+
+    match TournamentStorage::load(&tournament_id).await {
+        Ok(tournament) => Ok(CommandResponse::success_with_data(serde_json::json!({
+            "progress": tournament.progress(0),
+            "tournament": tournament,
+        }))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_load_tournament", "Failed to load tournament: {error}", error = e
+        ))),
+    }
+}
+
+/// Write the results of a tournament's played games to `path` for rating tools such as
+/// ordo or bayeselo
+#[tauri::command]
+pub async fn export_tournament_results(
+    tournament_id: String,
+    path: String,
+    format: ResultsFormat,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: export_tournament_results - tournament_id: {}, path: {}, format: {:?}", tournament_id, path, format);
+
+    let tournament = match TournamentStorage::load(&tournament_id).await {
+        Ok(tournament) => tournament,
+        Err(e) => return Ok(CommandResponse::failure(message!(
+            "failed_to_load_tournament", "Failed to load tournament: {error}", error = e
+        ))),
+    };
+    let contents = tournament::export_results(&tournament, format);
+    if let Err(e) = tokio::fs::write(&path, contents).await {
+        return Ok(CommandResponse::failure(message!(
+            "failed_to_write", "Failed to write {path}: {error}", path = path, error = e
+        )));
+    }
+
+    let games = tournament.games.iter().filter(|game| game.outcome.is_some()).count();
+    Ok(CommandResponse::success_with_data(serde_json::json!({ "path": path, "games": games })))
+}
+
+/// List saved tournaments, newest first
+#[tauri::command]
+pub async fn list_tournaments() -> Result<CommandResponse, String> {
+    log::info!("Command: list_tournaments");
+
+    match TournamentStorage::list().await {
+        Ok(tournaments) => Ok(CommandResponse::success_with_data(serde_json::to_value(tournaments).unwrap_or(serde_json::json!([])))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_list_tournaments", "Failed to list tournaments: {error}", error = e
+        ))),
+    }
 }
 
-/// Read image files from a directory
-/// Supports both bundled resources and user data directories
+/// Connect to a CSA game server (floodgate, shogi-server) and play the games it offers with
+/// a local engine. Progress is emitted as `csa-game-update` and engine thinking as `csa-engine-info`.
 #[tauri::command]
-pub async fn list_image_files(
-    directory: String, // 'wallpapers' or 'boards'
+pub async fn connect_csa_server(
     app_handle: tauri::AppHandle,
-) -> Result<Vec<String>, String> {
-    use std::fs;
-    use std::path::Path;
-    use tauri::Manager;
-    
-    let image_extensions = ["jpg", "jpeg", "png", "svg", "webp"];
-    let mut image_files = Vec::new();
-    
-    // Helper to check if a file is an image
-    fn is_image_file(path: &Path, extensions: &[&str]) -> bool {
-        if let Some(ext) = path.extension() {
-            if let Some(ext_str) = ext.to_str() {
-                return extensions.iter().any(|&e| e.eq_ignore_ascii_case(ext_str));
-            }
+    state: State<'_, AppState>,
+    config: CsaConnectionConfig,
+) -> Result<CommandResponse, String> {
+    log::info!(
+        "Command: connect_csa_server - {}@{}:{}, engine: {}",
+        config.username,
+        config.host,
+        config.port,
+        config.engine_id
+    );
+
+    let mut client = state.csa_client.write().await;
+    if let Some(handle) = client.as_ref() {
+        if handle.state.read().await.status != CsaStatus::Disconnected {
+            return Ok(CommandResponse::failure(message!("csa_already_connected", "Already connected to a CSA server")));
         }
-        false
     }
-    
-    // Helper to recursively collect image files
-    fn collect_images(
-        dir: &Path,
-        base_path: &str,
-        directory: &str,
-        image_files: &mut Vec<String>,
-        extensions: &[&str],
-    ) {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    let new_base = if base_path.is_empty() {
-                        entry.file_name().to_string_lossy().to_string()
-                    } else {
-                        format!("{}/{}", base_path, entry.file_name().to_string_lossy())
-                    };
-                    collect_images(&path, &new_base, directory, image_files, extensions);
-                } else if is_image_file(&path, extensions) {
-                    let file_name = entry.file_name().to_string_lossy().to_string();
-                    let image_path = if base_path.is_empty() {
-                        format!("/{}/{}", directory, file_name)
-                    } else {
-                        format!("/{}/{}/{}", directory, base_path, file_name)
-                    };
-                    image_files.push(image_path);
-                }
-            }
-        }
+    if state.engine_storage.read().await.get_engine(&config.engine_id).is_none() {
+        return Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = config.engine_id
+        )));
     }
-    
-    // Try to read from multiple locations:
-    // 1. Development: public directory (Vite serves these)
-    // 2. Development: dist directory (built assets)
-    // 3. Production: Resource directory (bundled images)
-    // 4. User data directory (for custom images)
-    
-    // 1. Development public directory (highest priority in dev)
-    #[cfg(debug_assertions)]
-    {
-        // Try multiple ways to find the public directory
-        let mut tried_paths = Vec::new();
-        
-        // Method 1: Try workspace root
-        if let Some(workspace_root) = find_workspace_root() {
-            let public_dir = workspace_root.join("public").join(&directory);
-            tried_paths.push(public_dir);
-        }
-        
-        // Method 2: Try current directory (most common case)
-        if let Ok(current_dir) = std::env::current_dir() {
-            tried_paths.push(current_dir.join("public").join(&directory));
-            // Also try going up one level (in case we're in src-tauri)
-            if let Some(parent) = current_dir.parent() {
-                tried_paths.push(parent.join("public").join(&directory));
-            }
-        }
-        
-        // Method 3: Try relative to executable (for tauri dev)
-        if let Ok(exe_path) = std::env::current_exe() {
-            if let Some(exe_dir) = exe_path.parent() {
-                // Go up to workspace root from src-tauri/target/...
-                let mut current = exe_dir;
-                for _ in 0..6 {
-                    if let Some(parent) = current.parent() {
-                        let public_dir = parent.join("public").join(&directory);
-                        if !tried_paths.contains(&public_dir) {
-                            tried_paths.push(public_dir);
-                        }
-                        // Stop if we found a Cargo.toml (likely workspace root)
-                        if parent.join("Cargo.toml").exists() || parent.join("package.json").exists() {
-                            break;
-                        }
-                        current = parent;
-                    } else {
-                        break;
-                    }
-                }
-            }
-        }
-        
-        // Try each path and use the first one that exists
-        for public_dir in &tried_paths {
-            if public_dir.exists() {
-                log::info!("Reading images from development public directory: {}", public_dir.display());
-                collect_images(public_dir, "", &directory, &mut image_files, &image_extensions);
-                break; // Use first found directory
-            }
-        }
-        
-        // Log if we didn't find any public directory
-        if image_files.is_empty() {
-            log::warn!("No images found in public/{} directory. Tried paths:", directory);
-            for path in &tried_paths {
-                log::warn!("  - {} (exists: {})", path.display(), path.exists());
-            }
+
+    let client_state = std::sync::Arc::new(tokio::sync::RwLock::new(CsaClientState::new(&config)));
+    let stop = std::sync::Arc::new(tokio::sync::Notify::new());
+    *client = Some(CsaClientHandle {
+        state: client_state.clone(),
+        stop: stop.clone(),
+    });
+    let data = serde_json::to_value(&*client_state.read().await).unwrap_or(serde_json::json!({}));
+
+    let engine_storage = state.engine_storage.clone();
+    tokio::spawn(network_play::run_client(app_handle, engine_storage, config, client_state, stop));
+
+    Ok(CommandResponse::success_with_data(data))
+}
+
+/// Disconnect from the CSA server, abandoning a game in progress
+#[tauri::command]
+pub async fn disconnect_csa_server(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: disconnect_csa_server");
+
+    match state.csa_client.read().await.as_ref() {
+        Some(handle) => {
+            handle.stop.notify_one();
+            Ok(CommandResponse::success())
         }
-        
-        // Also try dist directory (built assets)
-        if let Ok(current_dir) = std::env::current_dir() {
-            let dist_dir = current_dir.join("dist").join(&directory);
-            if dist_dir.exists() {
-                log::info!("Reading images from dist directory: {}", dist_dir.display());
-                collect_images(&dist_dir, "", &directory, &mut image_files, &image_extensions);
-            } else if let Some(parent) = current_dir.parent() {
-                let dist_dir = parent.join("dist").join(&directory);
-                if dist_dir.exists() {
-                    log::info!("Reading images from dist directory: {}", dist_dir.display());
-                    collect_images(&dist_dir, "", &directory, &mut image_files, &image_extensions);
-                }
-            }
+        None => Ok(CommandResponse::failure(message!("csa_not_connected", "Not connected to a CSA server"))),
+    }
+}
+
+/// Get the state of the CSA server connection and its current game
+#[tauri::command]
+pub async fn get_csa_status(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    match state.csa_client.read().await.as_ref() {
+        Some(handle) => Ok(CommandResponse::success_with_data(
+            serde_json::to_value(&*handle.state.read().await).unwrap_or(serde_json::json!({}))
+        )),
+        None => Ok(CommandResponse::success_with_data(serde_json::json!({ "status": CsaStatus::Disconnected }))),
+    }
+}
+
+/// Start the local USI bridge so external tools can drive engines over newline-delimited JSON
+/// on 127.0.0.1:`port` (0 picks a free port). Every connection must authenticate with `token`.
+#[tauri::command]
+pub async fn start_bridge(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    port: u16,
+    token: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: start_bridge - port: {}", port);
+
+    let mut bridge = state.bridge.write().await;
+    if let Some(handle) = bridge.as_ref() {
+        return Ok(CommandResponse::failure(message!(
+            "bridge_already_running", "Bridge already running on port {port}", port = handle.port
+        )));
+    }
+    match usi_bridge::start(app_handle, state.engine_manager.clone(), state.engine_storage.clone(), port, token).await {
+        Ok(handle) => {
+            let data = serde_json::to_value(handle.status()).unwrap_or(serde_json::json!({}));
+            *bridge = Some(handle);
+            Ok(CommandResponse::success_with_data(data))
         }
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_start_bridge", "Failed to start bridge: {error}", error = e
+        ))),
     }
-    
-    // 2. Resource directory (bundled images in production)
-    // These are the images bundled with the app from dist/wallpapers and dist/boards
-    // The resources are configured in tauri.conf.json to bundle ../dist/wallpapers/**/* and ../dist/boards/**/*
-    if let Ok(resource_dir) = app_handle.path().resource_dir() {
-        // Try direct path first (wallpapers/ or boards/ in resource dir)
-        let resource_path = resource_dir.join(&directory);
-        if resource_path.exists() {
-            log::info!("Reading images from resource directory: {}", resource_path.display());
-            collect_images(&resource_path, "", &directory, &mut image_files, &image_extensions);
-        } else {
-            // Also try looking in dist subdirectory (if resources preserved dist/ structure)
-            let dist_resource_path = resource_dir.join("dist").join(&directory);
-            if dist_resource_path.exists() {
-                log::info!("Reading images from dist resource directory: {}", dist_resource_path.display());
-                collect_images(&dist_resource_path, "", &directory, &mut image_files, &image_extensions);
-            } else {
-                log::debug!("Resource directory exists but {} subdirectory not found at: {} or {}", 
-                    directory, resource_path.display(), dist_resource_path.display());
-            }
+}
+
+/// Stop the USI bridge, closing its connections and the engines they spawned
+#[tauri::command]
+pub async fn stop_bridge(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: stop_bridge");
+
+    match state.bridge.write().await.take() {
+        Some(handle) => {
+            handle.stop();
+            Ok(CommandResponse::success())
         }
-    } else {
-        log::debug!("Could not access resource directory (this is normal in development)");
+        None => Ok(CommandResponse::failure(message!("bridge_not_running", "Bridge is not running"))),
     }
-    
-    // 3. Production: Also check dist directory if accessible (for development builds)
-    #[cfg(not(debug_assertions))]
+}
+
+/// Get whether the USI bridge is running, its port and open connections
+#[tauri::command]
+pub async fn get_bridge_status(
+    state: State<'_, AppState>,
+) -> Result<CommandResponse, String> {
+    let status = match state.bridge.read().await.as_ref() {
+        Some(handle) => handle.status(),
+        None => BridgeStatus { running: false, port: None, connections: 0 },
+    };
+    Ok(CommandResponse::success_with_data(
+        serde_json::to_value(status).unwrap_or(serde_json::json!({}))
+    ))
+}
+
+/// Start generating self-play training data in the background: the engine plays itself with
+/// a fixed node budget and every searched position is written to shards in `output_dir`.
+/// A directory that already holds a run is continued. Counts are emitted as
+/// `datagen-progress` after each game.
+#[tauri::command]
+pub async fn start_selfplay_datagen(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    config: DatagenConfig,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: start_selfplay_datagen - engine_id: {}, output_dir: {}, nodes: {}", config.engine_id, config.output_dir, config.nodes);
+
+    if let Err(e) = config.validate() {
+        return Ok(CommandResponse::failure(message!(
+            "invalid_datagen_configuration", "Invalid data generation configuration: {error}", error = e
+        )));
+    }
+    if state.engine_storage.read().await.get_engine(&config.engine_id).is_none() {
+        return Ok(CommandResponse::failure(message!(
+            "engine_not_found", "Engine not found: {engine_id}", engine_id = config.engine_id
+        )));
+    }
+    let output_dir = config.output_dir.clone();
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     {
-        if let Some(workspace_root) = find_workspace_root() {
-            let dist_dir = workspace_root.join("dist").join(&directory);
-            if dist_dir.exists() {
-                log::info!("Reading images from production dist directory: {}", dist_dir.display());
-                collect_images(&dist_dir, "", &directory, &mut image_files, &image_extensions);
-            }
+        let mut datagen_runs = state.datagen_runs.write().await;
+        if datagen_runs.contains_key(&output_dir) {
+            return Ok(CommandResponse::failure(message!(
+                "datagen_already_running", "Data generation is already writing to {output_dir}", output_dir = output_dir
+            )));
         }
+        datagen_runs.insert(output_dir.clone(), stop.clone());
     }
-    
-    // 4. User data directory (for custom images - works in both dev and production)
-    // Users can add their own images to ~/.config/shogi-vibe/wallpapers/ or boards/
-    if let Some(config_dir) = dirs::config_dir() {
-        let user_dir = config_dir.join("shogi-vibe").join(&directory);
-        if user_dir.exists() {
-            log::info!("Reading images from user directory: {}", user_dir.display());
-            collect_images(&user_dir, "", &directory, &mut image_files, &image_extensions);
-        } else {
-            log::debug!("User directory does not exist: {} (users can create this to add custom images)", user_dir.display());
+
+    let engine_storage = state.engine_storage.clone();
+    let datagen_runs = state.datagen_runs.clone();
+    tokio::spawn(async move {
+        let events = TauriEventSink::shared(app_handle);
+        match selfplay::run_datagen(events, engine_storage, config, stop).await {
+            Ok(progress) => log::info!(
+                "Self-play data generation in {} stopped after {} games, {} positions",
+                progress.output_dir,
+                progress.games_played,
+                progress.positions_written
+            ),
+            Err(e) => log::error!("Self-play data generation failed: {}", e),
+        }
+        datagen_runs.write().await.remove(&output_dir);
+    });
+
+    Ok(CommandResponse::success())
+}
+
+/// Ask a data generation run to stop after its current game
+#[tauri::command]
+pub async fn stop_selfplay_datagen(
+    state: State<'_, AppState>,
+    output_dir: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: stop_selfplay_datagen - output_dir: {}", output_dir);
+
+    match state.datagen_runs.read().await.get(&output_dir) {
+        Some(stop) => {
+            stop.store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok(CommandResponse::success())
         }
+        None => Ok(CommandResponse::failure(message!(
+            "datagen_not_running", "No data generation is writing to {output_dir}", output_dir = output_dir
+        ))),
     }
-    
-    // Remove duplicates and sort
-    image_files.sort();
-    image_files.dedup();
-    
-    log::info!("Found {} images in {} directory", image_files.len(), directory);
-    Ok(image_files)
 }
 
+/// Counts of the data generation run in `output_dir`, running or not, and whether it is running
+#[tauri::command]
+pub async fn get_selfplay_datagen_progress(
+    state: State<'_, AppState>,
+    output_dir: String,
+) -> Result<CommandResponse, String> {
+    log::info!("Command: get_selfplay_datagen_progress - output_dir: {}", output_dir);
+
+    let running = state.datagen_runs.read().await.contains_key(&output_dir);
+    match DatagenProgress::load(std::path::Path::new(&output_dir)).await {
+        Ok(progress) => Ok(CommandResponse::success_with_data(serde_json::json!({
+            "running": running,
+            "progress": progress,
+        }))),
+        Err(e) => Ok(CommandResponse::failure(message!(
+            "failed_to_load_datagen_progress", "Failed to load data generation progress: {error}", error = e
+        ))),
+    }
+}
@@ -0,0 +1,78 @@
+//! Diagnostic bundle for bug reports
+//! One zip file holding recent logs, engine configs, a fresh health check of each engine and
+//! the last match record. Paths can be redacted: the home directory and every engine's
+//! directory are replaced by "<redacted>" wherever they appear.
+
+use crate::engine_storage::EngineConfig;
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+
+const REDACTED: &str = "<redacted>";
+
+pub struct BundleFile {
+    pub name: &'static str,
+    pub contents: String,
+}
+
+/// Directories that identify the user: the home directory and each engine's directory
+pub fn private_paths(engines: &[EngineConfig]) -> Vec<String> {
+    let mut paths: Vec<String> = engines
+        .iter()
+        .filter_map(|engine| Path::new(&engine.path).parent())
+        .map(|dir| dir.display().to_string())
+        .chain(dirs::home_dir().map(|home| home.display().to_string()))
+        .filter(|path| !path.is_empty())
+        .collect();
+    // Longest first, so an engine directory inside the home directory is replaced whole
+    paths.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    paths.dedup();
+    paths
+}
+
+pub fn redact(text: &str, private_paths: &[String]) -> String {
+    private_paths
+        .iter()
+        .fold(text.to_string(), |text, path| text.replace(path.as_str(), REDACTED))
+}
+
+/// Redact every string in a JSON value; done before serializing so escaped Windows paths
+/// are still found
+pub fn redact_value(value: &mut serde_json::Value, private_paths: &[String]) {
+    match value {
+        serde_json::Value::String(text) => *text = redact(text, private_paths),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| redact_value(item, private_paths)),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(|field| redact_value(field, private_paths)),
+        _ => {}
+    }
+}
+
+pub fn write_zip(path: &Path, files: &[BundleFile]) -> Result<()> {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for file in files {
+        zip.start_file(file.name, options)?;
+        zip.write_all(file.contents.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction() {
+        let paths = vec!["/home/user/engines/yane".to_string(), "/home/user".to_string()];
+        assert_eq!(
+            redact("Started /home/user/engines/yane/YaneuraOu from /home/user", &paths),
+            "Started <redacted>/YaneuraOu from <redacted>"
+        );
+
+        let mut value = serde_json::json!({ "path": "/home/user/engines/yane/YaneuraOu", "options": ["/home/user/eval"] });
+        redact_value(&mut value, &paths);
+        assert_eq!(value["path"], "<redacted>/YaneuraOu");
+        assert_eq!(value["options"][0], "<redacted>/eval");
+    }
+}
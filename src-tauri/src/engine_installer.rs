@@ -0,0 +1,467 @@
+//! Installing engines from downloaded archives
+//! Archives (.zip, .tar.gz) are unpacked in-process into a managed engines directory; only
+//! downloads go through curl, which ships with Windows 10 and later, macOS and Linux. Nothing
+//! from an archive runs until it is trusted: either it matches the SHA-256 its publisher gave,
+//! and then the likeliest executables are tried with the USI handshake, or the user names the
+//! engine executable and only that file is run.
+
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_validator::{self, EngineMetadata};
+use anyhow::{anyhow, Result};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// Executables tried before giving up on an archive
+const MAX_CANDIDATES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Detect the archive type from a file name or URL
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.split(['?', '#']).next().unwrap_or(name).to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ArchiveKind::Zip => "zip",
+            ArchiveKind::TarGz => "tar.gz",
+        }
+    }
+}
+
+/// Directory holding one subdirectory per installed engine
+pub fn get_engines_dir() -> Result<PathBuf> {
+    let dir = EngineStorage::get_config_dir()?.join("engines");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Check that `name` is usable as a single directory name
+pub fn validate_dest_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.starts_with('.') {
+        return Err(anyhow!("Invalid install name: {:?}", name));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ')) {
+        return Err(anyhow!("Install name may only contain letters, digits, spaces, '-', '_' and '.'"));
+    }
+    Ok(name)
+}
+
+pub(crate) async fn download(url: &str, dest: &Path) -> Result<()> {
+    log::info!("Downloading {}", url);
+    let output = Command::new("curl")
+        .args(["-fsSL".as_ref(), "-o".as_ref(), dest.as_os_str(), url.as_ref()])
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Download failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Unpack `archive` into `dest`; archives with entries outside it are refused
+fn extract(archive: &Path, kind: ArchiveKind, dest: &Path) -> Result<()> {
+    log::info!("Extracting {} to {}", archive.display(), dest.display());
+    let file = std::fs::File::open(archive)?;
+    match kind {
+        ArchiveKind::Zip => zip::ZipArchive::new(file)?.extract(dest)?,
+        ArchiveKind::TarGz => unpack_tar(flate2::read::GzDecoder::new(file), dest)?,
+    }
+    Ok(())
+}
+
+/// Octal number field of a tar header
+fn tar_number(field: &[u8]) -> Result<u64> {
+    let text = std::str::from_utf8(field).map_err(|_| anyhow!("Invalid tar header"))?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| anyhow!("Invalid tar header number: {:?}", text))
+}
+
+/// NUL-terminated string field of a tar header
+fn tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Unpack the regular files and directories of a tar stream (ustar, with GNU and pax long
+/// names) into `dest`. Links and special files are skipped.
+fn unpack_tar(mut reader: impl Read, dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+    let mut long_name: Option<String> = None;
+    let mut header = [0u8; 512];
+    loop {
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        if header.iter().all(|&b| b == 0) {
+            return Ok(());
+        }
+        let size = tar_number(&header[124..136])?;
+        let padding = (512 - size % 512) % 512;
+        let typeflag = header[156];
+
+        if matches!(typeflag, b'L' | b'x') {
+            let mut data = Vec::new();
+            (&mut reader).take(size).read_to_end(&mut data)?;
+            std::io::copy(&mut (&mut reader).take(padding), &mut std::io::sink())?;
+            long_name = if typeflag == b'L' {
+                Some(tar_string(&data))
+            } else {
+                // Records are "<length> <key>=<value>\n"
+                String::from_utf8_lossy(&data)
+                    .lines()
+                    .filter_map(|record| record.split_once(' ').map(|(_, field)| field))
+                    .find_map(|field| field.strip_prefix("path="))
+                    .map(str::to_string)
+                    .or(long_name)
+            };
+            continue;
+        }
+
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => {
+                let (name, prefix) = (tar_string(&header[0..100]), tar_string(&header[345..500]));
+                if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                    format!("{}/{}", prefix, name)
+                } else {
+                    name
+                }
+            }
+        };
+        let relative = Path::new(&name);
+        if !relative.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+            return Err(anyhow!("Archive entry outside the install directory: {}", name));
+        }
+        let path = dest.join(relative);
+        let mut data = (&mut reader).take(size);
+        match typeflag {
+            b'5' => std::fs::create_dir_all(&path)?,
+            b'0' | 0 => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let written = std::io::copy(&mut data, &mut std::fs::File::create(&path)?)?;
+                if written != size {
+                    return Err(anyhow!("Truncated tar archive"));
+                }
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    let mode = tar_number(&header[100..108])? as u32 & 0o755;
+                    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode | 0o600))?;
+                }
+            }
+            _ => log::debug!("Skipping tar entry {} of type {}", name, typeflag as char),
+        }
+        std::io::copy(&mut data, &mut std::io::sink())?;
+        std::io::copy(&mut (&mut reader).take(padding), &mut std::io::sink())?;
+    }
+}
+
+fn is_candidate(path: &Path, metadata: &std::fs::Metadata) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_lowercase);
+    if cfg!(windows) {
+        return extension.as_deref() == Some("exe");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 != 0 {
+            return !matches!(extension.as_deref(), Some("so" | "dylib" | "sh" | "py"));
+        }
+    }
+    let _ = metadata;
+    // Zip archives often lose the executable bit; consider extension-less files too
+    extension.is_none()
+}
+
+/// Executable-looking files under `dir`, largest first (engines dwarf helper scripts)
+fn find_candidates(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let path = entry.path();
+            if metadata.is_dir() {
+                pending.push(path);
+            } else if metadata.is_file() && is_candidate(&path, &metadata) {
+                found.push((metadata.len(), path));
+            }
+        }
+    }
+    found.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    Ok(found.into_iter().map(|(_, path)| path).collect())
+}
+
+#[cfg(unix)]
+pub(crate) fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o755);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Resolve an executable the user named, relative to the archive root. Paths leaving the
+/// archive and symbolic links are refused.
+fn confirmed_executable(dir: &Path, executable: &str) -> Result<PathBuf> {
+    let relative = Path::new(executable.trim());
+    if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+        return Err(anyhow!("Executable must be a path inside the archive: {}", executable));
+    }
+    let path = dir.join(relative);
+    match std::fs::symlink_metadata(&path) {
+        Ok(metadata) if metadata.is_file() => Ok(path),
+        _ => Err(anyhow!("No file {} in the archive", executable)),
+    }
+}
+
+/// Find the engine executable in an extracted archive. A confirmed executable is the only
+/// file run. Otherwise the archive must have matched its checksum before the likeliest
+/// candidates are tried with the USI handshake; if it did not, the candidates are listed so
+/// the user can pick one.
+async fn find_engine(dir: &Path, executable: Option<&str>, verified: bool) -> Result<(PathBuf, EngineMetadata)> {
+    if let Some(executable) = executable {
+        let path = confirmed_executable(dir, executable)?;
+        make_executable(&path)?;
+        let metadata = engine_validator::validate_engine(&path.display().to_string())
+            .await
+            .map_err(|e| anyhow!("{} is not a USI engine: {}", executable, e))?;
+        return Ok((path, metadata));
+    }
+
+    let candidates = find_candidates(dir)?;
+    if candidates.is_empty() {
+        return Err(anyhow!("No executable found in archive"));
+    }
+    if !verified {
+        let names: Vec<String> = candidates
+            .iter()
+            .filter_map(|candidate| candidate.strip_prefix(dir).ok())
+            .map(|candidate| candidate.display().to_string())
+            .collect();
+        return Err(anyhow!(
+            "Choose the engine executable or give the archive's SHA-256; files found: {}",
+            names.join(", ")
+        ));
+    }
+    for candidate in candidates.into_iter().take(MAX_CANDIDATES) {
+        make_executable(&candidate)?;
+        let path = candidate.display().to_string();
+        match engine_validator::validate_engine(&path).await {
+            Ok(metadata) => return Ok((candidate, metadata)),
+            Err(e) => log::info!("{} is not a USI engine: {}", path, e),
+        }
+    }
+    Err(anyhow!("No USI engine found in archive"))
+}
+
+/// Unpack an archive (local path or http(s) URL) into `<engines dir>/<dest_name>` and
+/// return a config for the engine inside it, ready to be added to storage. `sha256` is the
+/// archive's published checksum and `executable` the engine file the user confirmed, relative
+/// to the archive root; one of them is needed before anything in the archive is run.
+pub async fn install_from_archive(
+    path_or_url: &str,
+    dest_name: &str,
+    sha256: Option<&str>,
+    executable: Option<&str>,
+) -> Result<EngineConfig> {
+    let dest_name = validate_dest_name(dest_name)?;
+    let kind = ArchiveKind::from_name(path_or_url)
+        .ok_or_else(|| anyhow!("Unsupported archive type (expected .zip or .tar.gz): {}", path_or_url))?;
+    let sha256 = sha256.map(str::trim).filter(|sha256| !sha256.is_empty());
+    let executable = executable.map(str::trim).filter(|executable| !executable.is_empty());
+
+    let engines_dir = get_engines_dir()?;
+    let dest = engines_dir.join(dest_name);
+    if dest.exists() {
+        return Err(anyhow!("An engine is already installed as {}", dest_name));
+    }
+
+    let staging = engines_dir.join(format!(".install-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&staging)?;
+    let result = async {
+        let is_url = path_or_url.starts_with("http://") || path_or_url.starts_with("https://");
+        let (archive, downloaded) = if is_url {
+            let archive = engines_dir.join(format!(".download-{}.{}", Uuid::new_v4(), kind.extension()));
+            (archive, true)
+        } else {
+            (PathBuf::from(path_or_url), false)
+        };
+        let unpacked = async {
+            if downloaded {
+                download(path_or_url, &archive).await?;
+            } else if !archive.is_file() {
+                return Err(anyhow!("Archive not found: {}", archive.display()));
+            }
+            if let Some(expected) = sha256 {
+                let actual = engine_validator::binary_sha256(&archive.display().to_string()).await?;
+                if !actual.eq_ignore_ascii_case(expected) {
+                    return Err(anyhow!("Archive does not match its checksum: expected SHA-256 {}, got {}", expected, actual));
+                }
+            }
+            let (archive, staging) = (archive.clone(), staging.clone());
+            tokio::task::spawn_blocking(move || extract(&archive, kind, &staging)).await?
+        }
+        .await;
+        if downloaded {
+            let _ = std::fs::remove_file(&archive);
+        }
+        unpacked?;
+
+        std::fs::rename(&staging, &dest)?;
+        find_engine(&dest, executable, sha256.is_some()).await
+    }
+    .await;
+
+    match result {
+        Ok((engine_path, metadata)) => {
+            log::info!("Installed engine {} at {}", metadata.name, engine_path.display());
+            let mut config = EngineConfig::new(
+                metadata.name.clone(),
+                engine_path.display().to_string(),
+                Some(metadata),
+                false,
+            );
+            config.display_name = dest_name.to_string();
+            config.record_binary_hash().await;
+            Ok(config)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&staging);
+            let _ = std::fs::remove_dir_all(&dest);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_kind_and_dest_name() {
+        assert_eq!(ArchiveKind::from_name("YaneuraOu-v8.zip"), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::from_name("https://example.com/e.tar.gz?raw=1"), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::from_name("/tmp/ENGINE.TGZ"), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::from_name("engine.7z"), None);
+
+        assert_eq!(validate_dest_name(" Suisho 5 ").unwrap(), "Suisho 5");
+        assert!(validate_dest_name("../escape").is_err());
+        assert!(validate_dest_name("a/b").is_err());
+        assert!(validate_dest_name(".hidden").is_err());
+    }
+
+    #[test]
+    fn test_extract_and_confirm_executable() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("ysu-installer-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write_zip = |name: &str, entry: &str| {
+            let archive = dir.join(name);
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+            zip.start_file(entry, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"binary").unwrap();
+            zip.finish().unwrap();
+            archive
+        };
+
+        let escaping = write_zip("escaping.zip", "../escape");
+        assert!(extract(&escaping, ArchiveKind::Zip, &dir.join("escaping")).is_err());
+        assert!(!dir.join("escape").exists());
+
+        let dest = dir.join("unpacked");
+        extract(&write_zip("engine.zip", "Engine/engine"), ArchiveKind::Zip, &dest).unwrap();
+
+        assert_eq!(confirmed_executable(&dest, "Engine/engine").unwrap(), dest.join("Engine/engine"));
+        assert!(confirmed_executable(&dest, "Engine/missing").is_err());
+        assert!(confirmed_executable(&dest, "../engine.zip").is_err());
+        assert!(confirmed_executable(&dest, "/bin/sh").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn tar_entry(name: &str, typeflag: u8, data: &[u8]) -> Vec<u8> {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000755");
+        header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        header[156] = typeflag;
+        header[257..262].copy_from_slice(b"ustar");
+        let mut entry = header.to_vec();
+        entry.extend_from_slice(data);
+        entry.resize(entry.len().div_ceil(512) * 512, 0);
+        entry
+    }
+
+    #[test]
+    fn test_extract_tar_gz() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("ysu-installer-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write_tar_gz = |name: &str, entries: &[Vec<u8>]| {
+            let archive = dir.join(name);
+            let mut gz = flate2::write::GzEncoder::new(std::fs::File::create(&archive).unwrap(), flate2::Compression::fast());
+            for entry in entries {
+                gz.write_all(entry).unwrap();
+            }
+            gz.write_all(&[0; 1024]).unwrap();
+            gz.finish().unwrap();
+            archive
+        };
+
+        let long_name = format!("Engine/{}", "x".repeat(120));
+        let archive = write_tar_gz(
+            "engine.tar.gz",
+            &[
+                tar_entry("Engine/", b'5', b""),
+                tar_entry("Engine/engine", b'0', &[7; 600]),
+                tar_entry("././@LongLink", b'L', long_name.as_bytes()),
+                tar_entry("Engine/xxxx", b'0', b"eval"),
+                tar_entry("Engine/link", b'2', b""),
+            ],
+        );
+        let dest = dir.join("unpacked");
+        extract(&archive, ArchiveKind::TarGz, &dest).unwrap();
+        assert_eq!(std::fs::read(dest.join("Engine/engine")).unwrap(), vec![7; 600]);
+        assert_eq!(std::fs::read(dest.join(&long_name)).unwrap(), b"eval");
+        assert!(!dest.join("Engine/link").exists());
+
+        let escaping = write_tar_gz("escaping.tar.gz", &[tar_entry("../escape", b'0', b"outside")]);
+        assert!(extract(&escaping, ArchiveKind::TarGz, &dir.join("escaping")).is_err());
+        assert!(!dir.join("escape").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
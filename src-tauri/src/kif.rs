@@ -0,0 +1,642 @@
+//! KIF (Kakinoki format) export and import of game records
+//! Standard and handicap games use a 手合割 header; other positions are written as a board diagram.
+//! Move comments and symbols are `*` lines under the move, and variations follow the main line
+//! as `変化：N手` blocks, with the branching main-line move marked by a trailing `+`.
+
+use crate::game_record::{GameRecord, MoveSymbol, RecordedMove, Termination, Variation};
+use crate::handicap::Handicap;
+use crate::notation::{self, NotationStyle};
+use crate::shogi_rules::{Color, Move, PieceType, Position, Square, HAND_PIECE_TYPES, STARTPOS_SFEN};
+use anyhow::{anyhow, Result};
+
+const KANJI_NUMERALS: [&str; 10] = ["", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+
+/// Kanji count for hands, e.g. 2 -> "二", 12 -> "十二"
+fn kanji_count(count: u8) -> String {
+    match count {
+        0..=9 => KANJI_NUMERALS[count as usize].to_string(),
+        _ => format!("十{}", KANJI_NUMERALS[(count % 10) as usize]),
+    }
+}
+
+/// Single-character piece names used in board diagrams
+fn diagram_kanji(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::PromotedLance => "杏",
+        PieceType::PromotedKnight => "圭",
+        PieceType::PromotedSilver => "全",
+        other => notation::piece_kanji(other),
+    }
+}
+
+fn hand_text(position: &Position, color: Color) -> String {
+    let pieces: Vec<String> = HAND_PIECE_TYPES
+        .iter()
+        .filter_map(|&piece_type| match position.hand_count(color, piece_type) {
+            0 => None,
+            1 => Some(notation::piece_kanji(piece_type).to_string()),
+            count => Some(format!("{}{}", notation::piece_kanji(piece_type), kanji_count(count))),
+        })
+        .collect();
+    if pieces.is_empty() {
+        "なし".to_string()
+    } else {
+        pieces.join("　")
+    }
+}
+
+/// BOD board diagram for a non-standard starting position
+fn board_diagram(position: &Position) -> String {
+    let mut lines = vec![format!("後手の持駒：{}", hand_text(position, Color::White))];
+    lines.push("  ９ ８ ７ ６ ５ ４ ３ ２ １".to_string());
+    lines.push("+---------------------------+".to_string());
+    for rank in 1..=9 {
+        let mut row = String::from("|");
+        for file in (1..=9).rev() {
+            let square = Square::new(file, rank).expect("valid square");
+            match position.piece_at(square) {
+                Some(piece) => {
+                    row.push(if piece.color == Color::White { 'v' } else { ' ' });
+                    row.push_str(diagram_kanji(piece.piece_type));
+                }
+                None => row.push_str(" ・"),
+            }
+        }
+        row.push('|');
+        row.push_str(KANJI_NUMERALS[rank as usize]);
+        lines.push(row);
+    }
+    lines.push("+---------------------------+".to_string());
+    lines.push(format!("先手の持駒：{}", hand_text(position, Color::Black)));
+    if position.side_to_move() == Color::White {
+        lines.push("後手番".to_string());
+    }
+    lines.join("\n")
+}
+
+fn format_clock(move_ms: u64, total_ms: u64) -> String {
+    let move_secs = move_ms / 1000;
+    let total_secs = total_ms / 1000;
+    format!(
+        "({:>2}:{:02}/{:02}:{:02}:{:02})",
+        move_secs / 60,
+        move_secs % 60,
+        total_secs / 3600,
+        (total_secs / 60) % 60,
+        total_secs % 60
+    )
+}
+
+fn terminal_word(termination: Termination) -> &'static str {
+    match termination {
+        Termination::Resignation => "投了",
+        Termination::Checkmate => "詰み",
+        Termination::EnteringKing => "入玉勝ち",
+        Termination::IllegalMove => "反則負け",
+        Termination::EngineFailure => "切れ負け",
+        Termination::Adjudication | Termination::MaxMoves => "中断",
+    }
+}
+
+fn comment_lines(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.lines().map(|line| format!("*{}", line))
+}
+
+/// Comment lines under a move: its symbol, the user comment, then the machine commentary
+fn annotation_lines(recorded: &RecordedMove) -> Vec<String> {
+    let mut lines: Vec<String> = recorded.symbol.map(|symbol| format!("*{}", symbol.as_str())).into_iter().collect();
+    if let Some(comment) = &recorded.comment {
+        lines.extend(comment_lines(comment));
+    }
+    lines.extend(recorded.commentary.iter().map(|comment| format!("*{}", comment)));
+    lines
+}
+
+/// `変化：N手` block for a variation replacing move `first_number`
+fn variation_block(
+    position: &Position,
+    previous_to: Option<Square>,
+    first_number: usize,
+    variation: &Variation,
+) -> Result<Vec<String>> {
+    let mut lines = vec![String::new(), format!("変化：{}手", first_number)];
+    let mut position = position.clone();
+    let mut previous_to = previous_to;
+    for (offset, usi) in variation.moves.iter().enumerate() {
+        let number = first_number + offset;
+        let mv = Move::from_usi(usi)?;
+        let text = notation::format_kif_move(&position, &mv, previous_to)
+            .map_err(|e| anyhow!("Variation move {} ({}): {}", number, usi, e))?;
+        lines.push(format!("{:>4} {}", number, text));
+        if offset == 0 {
+            if let Some(comment) = &variation.comment {
+                lines.extend(comment_lines(comment));
+            }
+        }
+        position.apply_move(&mv).map_err(|e| anyhow!("Variation move {} ({}): {}", number, usi, e))?;
+        previous_to = Some(mv.to());
+    }
+    Ok(lines)
+}
+
+/// Render a game record as KIF text, with its comments, symbols and commentary as `*` comment
+/// lines and its variations as `変化` blocks
+pub fn export_kif(record: &GameRecord) -> Result<String> {
+    let initial = Position::from_sfen(&record.initial_sfen)?;
+    let initial_sfen = initial.to_sfen();
+    let handicap = Handicap::ALL.into_iter().find(|h| h.sfen() == initial_sfen);
+    // Handicap games name the players 下手 (Black) and 上手 (White)
+    let (black_label, white_label) = if handicap.is_some() { ("下手", "上手") } else { ("先手", "後手") };
+
+    let mut lines = vec!["# ---- Shogi Vibe 棋譜ファイル ----".to_string()];
+    if let Ok(started) = chrono::DateTime::parse_from_rfc3339(&record.created_at) {
+        lines.push(format!("開始日時：{}", started.format("%Y/%m/%d %H:%M:%S")));
+    }
+    if let Some(handicap) = handicap {
+        lines.push(format!("手合割：{}", handicap.japanese_name()));
+    } else if initial_sfen == STARTPOS_SFEN {
+        lines.push("手合割：平手".to_string());
+    } else {
+        lines.push(board_diagram(&initial));
+    }
+    lines.push(format!("{}：{}", black_label, record.black_name));
+    lines.push(format!("{}：{}", white_label, record.white_name));
+    lines.push("手数----指手---------消費時間--".to_string());
+
+    let mut position = initial;
+    let mut previous_to = None;
+    let mut totals_ms = [0u64; 2];
+    let mut variation_lines = Vec::new();
+    for (index, recorded) in record.moves.iter().enumerate() {
+        let mv = Move::from_usi(&recorded.usi)?;
+        let text = notation::format_kif_move(&position, &mv, previous_to)
+            .map_err(|e| anyhow!("Move {} ({}): {}", index + 1, recorded.usi, e))?;
+        let side = if position.side_to_move() == Color::Black { 0 } else { 1 };
+        let elapsed_ms = recorded.elapsed_ms.unwrap_or(0);
+        totals_ms[side] += elapsed_ms;
+
+        let branch_mark = if recorded.variations.is_empty() { "" } else { "+" };
+        lines.push(format!(
+            "{:>4} {}   {}{}",
+            index + 1,
+            text,
+            format_clock(elapsed_ms, totals_ms[side]),
+            branch_mark
+        ));
+        lines.extend(annotation_lines(recorded));
+        for variation in &recorded.variations {
+            variation_lines.extend(variation_block(&position, previous_to, index + 1, variation)?);
+        }
+
+        position.apply_move(&mv).map_err(|e| anyhow!("Move {} ({}): {}", index + 1, recorded.usi, e))?;
+        previous_to = Some(mv.to());
+    }
+
+    let move_count = record.moves.len();
+    if let Some(termination) = record.termination {
+        lines.push(format!("{:>4} {}", move_count + 1, terminal_word(termination)));
+    }
+    let outcome = match record.winner.as_deref() {
+        Some("black") => format!("{}の勝ち", black_label),
+        Some("white") => format!("{}の勝ち", white_label),
+        _ => "中断".to_string(),
+    };
+    lines.push(format!("まで{}手で{}", move_count, outcome));
+    lines.extend(variation_lines);
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// SFEN letters of a piece written in a board diagram or hand list
+fn sfen_letters(kanji: char) -> Option<&'static str> {
+    Some(match kanji {
+        '歩' => "P",
+        '香' => "L",
+        '桂' => "N",
+        '銀' => "S",
+        '金' => "G",
+        '角' => "B",
+        '飛' => "R",
+        '玉' | '王' => "K",
+        'と' => "+P",
+        '杏' => "+L",
+        '圭' => "+N",
+        '全' => "+S",
+        '馬' => "+B",
+        '龍' | '竜' => "+R",
+        _ => return None,
+    })
+}
+
+/// Parse a kanji count, e.g. "" -> 1, "二" -> 2, "十二" -> 12
+fn parse_kanji_count(text: &str) -> Option<u8> {
+    if text.is_empty() {
+        return Some(1);
+    }
+    let digit = |c: char| KANJI_NUMERALS.iter().position(|numeral| numeral.starts_with(c)).map(|n| n as u8);
+    let mut chars = text.chars();
+    match (chars.next()?, chars.next(), chars.next()) {
+        ('十', None, None) => Some(10),
+        ('十', Some(ones), None) => digit(ones).map(|ones| 10 + ones),
+        (ones, None, None) => digit(ones),
+        _ => None,
+    }
+}
+
+/// SFEN hand field part for one side's 持駒 list ("歩二　角" or "なし")
+fn parse_hand(text: &str, color: Color) -> Result<String> {
+    let mut hand = String::new();
+    for entry in text.split(char::is_whitespace).filter(|entry| !entry.is_empty() && *entry != "なし") {
+        let mut chars = entry.chars();
+        let kanji = chars.next().unwrap_or_default();
+        let letter = sfen_letters(kanji)
+            .filter(|letter| letter.len() == 1 && *letter != "K")
+            .ok_or_else(|| anyhow!("Unknown piece in hand: {}", entry))?;
+        let count = parse_kanji_count(chars.as_str()).ok_or_else(|| anyhow!("Invalid hand count: {}", entry))?;
+        if count > 1 {
+            hand.push_str(&count.to_string());
+        }
+        hand.push_str(&if color == Color::Black { letter.to_string() } else { letter.to_lowercase() });
+    }
+    Ok(hand)
+}
+
+/// SFEN board field for the nine `|...|` rows of a board diagram
+fn parse_diagram_rows(rows: &[String]) -> Result<String> {
+    if rows.len() != 9 {
+        return Err(anyhow!("Board diagram has {} ranks instead of 9", rows.len()));
+    }
+    let mut ranks = Vec::with_capacity(9);
+    for row in rows {
+        let cells: Vec<char> = row.trim_start_matches('|').chars().take_while(|&c| c != '|').collect();
+        if cells.len() != 18 {
+            return Err(anyhow!("Invalid board diagram row: {}", row));
+        }
+        let mut rank = String::new();
+        let mut empty = 0;
+        for cell in cells.chunks(2) {
+            if cell[1] == '・' {
+                empty += 1;
+                continue;
+            }
+            let letters = sfen_letters(cell[1]).ok_or_else(|| anyhow!("Unknown piece in diagram: {}", cell[1]))?;
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+                empty = 0;
+            }
+            rank.push_str(&if cell[0] == 'v' { letters.to_lowercase() } else { letters.to_string() });
+        }
+        if empty > 0 {
+            rank.push_str(&empty.to_string());
+        }
+        ranks.push(rank);
+    }
+    Ok(ranks.join("/"))
+}
+
+/// Starting position: the board diagram when the header had one, else the 手合割 position
+fn initial_position(header_sfen: &str, diagram_rows: &[String], hands: &[String; 2], white_to_move: bool) -> Result<Position> {
+    if diagram_rows.is_empty() {
+        return Position::from_sfen(header_sfen);
+    }
+    let hand = format!("{}{}", hands[0], hands[1]);
+    Position::from_sfen(&format!(
+        "{} {} {} 1",
+        parse_diagram_rows(diagram_rows)?,
+        if white_to_move { "w" } else { "b" },
+        if hand.is_empty() { "-" } else { hand.as_str() }
+    ))
+}
+
+fn parse_terminal_word(word: &str) -> Option<Termination> {
+    match word {
+        "投了" => Some(Termination::Resignation),
+        "詰み" => Some(Termination::Checkmate),
+        "入玉勝ち" => Some(Termination::EnteringKing),
+        "反則負け" => Some(Termination::IllegalMove),
+        "切れ負け" => Some(Termination::EngineFailure),
+        _ => None,
+    }
+}
+
+/// Whether a move column holds a game-ending word instead of a move
+fn is_terminal(move_text: &str) -> bool {
+    ["投了", "詰み", "入玉勝ち", "反則負け", "切れ負け", "中断", "千日手", "持将棋"].contains(&move_text)
+}
+
+/// Thinking time of a move from its clock column, e.g. "( 1:01/00:01:01)"
+fn parse_clock(text: &str) -> Option<u64> {
+    let inner = text.trim().strip_prefix('(')?;
+    let (minutes, seconds) = inner.split('/').next()?.trim().split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds: u64 = seconds.trim().parse().ok()?;
+    Some((minutes * 60 + seconds) * 1000)
+}
+
+/// Split a move line into its number, move text and clock column
+fn split_move_line(line: &str) -> Option<(usize, &str, &str)> {
+    let line = line.trim().trim_end_matches('+').trim_end();
+    let digits = line.find(|c: char| !c.is_ascii_digit()).unwrap_or(line.len());
+    let number = line[..digits].parse().ok()?;
+    let rest = line[digits..].trim_start_matches(' ');
+    let (text, clock) = rest.split_once(' ').unwrap_or((rest, ""));
+    Some((number, text, clock))
+}
+
+fn append_comment(target: &mut Option<String>, line: &str) {
+    match target {
+        Some(comment) => {
+            comment.push('\n');
+            comment.push_str(line);
+        }
+        None => *target = Some(line.to_string()),
+    }
+}
+
+/// A variation being read, replacing main-line move `index` (0-based)
+struct VariationInProgress {
+    index: usize,
+    position: Position,
+    previous_to: Option<Square>,
+    variation: Variation,
+}
+
+/// Parse KIF text into a new game record. Comments become the moves' user comments (a comment
+/// line holding only a symbol such as `!?` sets the symbol), and `変化` blocks become variations
+/// of the main-line move they replace; variations branching off other variations are not kept.
+pub fn import_kif(text: &str) -> Result<GameRecord> {
+    let mut black_name = String::new();
+    let mut white_name = String::new();
+    let mut created_at = chrono::Utc::now().to_rfc3339();
+    let mut header_sfen = STARTPOS_SFEN.to_string();
+    let mut diagram_rows = Vec::new();
+    let mut hands = [String::new(), String::new()];
+    let mut white_to_move = false;
+    let mut winner = None;
+    let mut termination = None;
+
+    let mut position: Option<Position> = None;
+    let mut positions_before: Vec<(Position, Option<Square>)> = Vec::new();
+    let mut previous_to = None;
+    let mut moves: Vec<RecordedMove> = Vec::new();
+    let mut main_line_ended = false;
+    let mut current_variation: Option<VariationInProgress> = None;
+    let mut variations: Vec<(usize, Variation)> = Vec::new();
+
+    for (line_index, raw_line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('&') {
+            continue;
+        }
+
+        if let Some(comment) = trimmed.strip_prefix('*') {
+            if let Some(current) = current_variation.as_mut() {
+                append_comment(&mut current.variation.comment, comment);
+            } else if let Some(last) = moves.last_mut().filter(|_| !main_line_ended) {
+                match MoveSymbol::parse(comment) {
+                    Some(symbol) if last.symbol.is_none() => last.symbol = Some(symbol),
+                    _ => append_comment(&mut last.comment, comment),
+                }
+            }
+            continue;
+        }
+
+        if let Some(start) = trimmed.strip_prefix("変化：").or_else(|| trimmed.strip_prefix("変化:")) {
+            variations.extend(current_variation.take().map(|current| (current.index, current.variation)));
+            let number: usize = start
+                .trim_end_matches('手')
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Line {}: invalid variation start '{}'", line_number, trimmed))?;
+            let index = number.saturating_sub(1);
+            let (position, previous_to) = positions_before
+                .get(index)
+                .filter(|_| number > 0)
+                .cloned()
+                .ok_or_else(|| anyhow!("Line {}: variation starts at move {} past the main line", line_number, number))?;
+            current_variation = Some(VariationInProgress {
+                index,
+                position,
+                previous_to,
+                variation: Variation { moves: Vec::new(), comment: None },
+            });
+            continue;
+        }
+
+        if trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+            let (number, move_text, clock) =
+                split_move_line(trimmed).ok_or_else(|| anyhow!("Line {}: invalid move line", line_number))?;
+            if let Some(current) = current_variation.as_mut() {
+                if is_terminal(move_text) {
+                    continue;
+                }
+                let mv = notation::parse_move(&current.position, move_text, current.previous_to, NotationStyle::Kif)
+                    .map_err(|e| anyhow!("Line {}: move {}: {}", line_number, number, e))?;
+                current.position.apply_move_unchecked(&mv);
+                current.previous_to = Some(mv.to());
+                current.variation.moves.push(mv.to_usi());
+                continue;
+            }
+            if main_line_ended {
+                continue;
+            }
+            if position.is_none() {
+                // The header is complete once the first move appears
+                position = Some(initial_position(&header_sfen, &diagram_rows, &hands, white_to_move)?);
+            }
+            let Some(board) = position.as_mut() else { continue };
+            if is_terminal(move_text) {
+                termination = parse_terminal_word(move_text);
+                if matches!(termination, Some(Termination::Resignation | Termination::Checkmate)) {
+                    // The side to move resigned or is mated
+                    winner = Some(if board.side_to_move() == Color::Black { "white" } else { "black" });
+                }
+                main_line_ended = true;
+                continue;
+            }
+            let mv = notation::parse_move(board, move_text, previous_to, NotationStyle::Kif)
+                .map_err(|e| anyhow!("Line {}: move {}: {}", line_number, number, e))?;
+            positions_before.push((board.clone(), previous_to));
+            board.apply_move_unchecked(&mv);
+            previous_to = Some(mv.to());
+            moves.push(RecordedMove {
+                usi: mv.to_usi(),
+                elapsed_ms: parse_clock(clock),
+                score: None,
+                depth: None,
+                commentary: Vec::new(),
+                comment: None,
+                symbol: None,
+                variations: Vec::new(),
+                book: false,
+            });
+            continue;
+        }
+
+        if trimmed.starts_with('|') {
+            diagram_rows.push(trimmed.to_string());
+            continue;
+        }
+        if let Some(result) = trimmed.strip_prefix("まで") {
+            if current_variation.is_none() {
+                if result.ends_with("先手の勝ち") || result.ends_with("下手の勝ち") {
+                    winner = Some("black");
+                } else if result.ends_with("後手の勝ち") || result.ends_with("上手の勝ち") {
+                    winner = Some("white");
+                }
+            }
+            continue;
+        }
+        if trimmed == "後手番" || trimmed == "上手番" {
+            white_to_move = true;
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once('：').or_else(|| trimmed.split_once(':')) else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "先手" | "下手" => black_name = value.to_string(),
+            "後手" | "上手" => white_name = value.to_string(),
+            "先手の持駒" | "下手の持駒" => hands[0] = parse_hand(value, Color::Black)?,
+            "後手の持駒" | "上手の持駒" => hands[1] = parse_hand(value, Color::White)?,
+            "手合割" => {
+                if let Some(handicap) = Handicap::ALL.into_iter().find(|h| h.japanese_name() == value) {
+                    header_sfen = handicap.sfen().to_string();
+                } else if value != "平手" {
+                    return Err(anyhow!("Line {}: unsupported handicap '{}'", line_number, value));
+                }
+            }
+            "開始日時" => {
+                if let Ok(started) = chrono::NaiveDateTime::parse_from_str(value, "%Y/%m/%d %H:%M:%S") {
+                    created_at = started.and_utc().to_rfc3339();
+                }
+            }
+            _ => {}
+        }
+    }
+    variations.extend(current_variation.take().map(|current| (current.index, current.variation)));
+
+    let initial_sfen = initial_position(&header_sfen, &diagram_rows, &hands, white_to_move)?.to_sfen();
+    for (index, variation) in variations {
+        if !variation.moves.is_empty() {
+            moves[index].variations.push(variation);
+        }
+    }
+
+    Ok(GameRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        black_name,
+        white_name,
+        initial_sfen,
+        moves,
+        winner: winner.map(str::to_string),
+        result: None,
+        termination,
+        black_timing: None,
+        white_timing: None,
+        match_config: None,
+        think_log: None,
+        created_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_record::RecordedMove;
+
+    fn record(initial_sfen: &str, moves: &[&str]) -> GameRecord {
+        GameRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            black_name: "Sente Engine".to_string(),
+            white_name: "Gote Engine".to_string(),
+            initial_sfen: initial_sfen.to_string(),
+            moves: moves
+                .iter()
+                .map(|usi| RecordedMove {
+                    usi: usi.to_string(),
+                    elapsed_ms: Some(61_000),
+                    score: None,
+                    depth: None,
+                    commentary: Vec::new(),
+                    comment: None,
+                    symbol: None,
+                    variations: Vec::new(),
+                    book: false,
+                })
+                .collect(),
+            winner: Some("black".to_string()),
+            result: None,
+            termination: Some(Termination::Resignation),
+            black_timing: None,
+            white_timing: None,
+            match_config: None,
+            think_log: None,
+            created_at: "2024-05-01T09:30:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_export_kif() {
+        let mut even = record(STARTPOS_SFEN, &["7g7f", "3c3d", "8h2b+", "3a2b"]);
+        even.moves[2].commentary = vec!["Out of book".to_string()];
+        let kif = export_kif(&even).unwrap();
+        assert!(kif.contains("開始日時：2024/05/01 09:30:00\n手合割：平手\n先手：Sente Engine\n後手：Gote Engine"));
+        assert!(kif.contains("   1 ７六歩(77)   ( 1:01/00:01:01)"));
+        assert!(kif.contains("   3 ２二角成(88)   ( 1:01/00:02:02)\n*Out of book\n   4 同　銀(31)"));
+        assert!(kif.ends_with("   5 投了\nまで4手で先手の勝ち\n"));
+
+        let handicap = export_kif(&record(Handicap::Bishop.sfen(), &["5a4b"])).unwrap();
+        assert!(handicap.contains("手合割：角落ち\n下手：Sente Engine\n上手：Gote Engine"));
+
+        let custom = export_kif(&record("4k4/9/9/9/9/9/9/9/4K4 w 2P 1", &[])).unwrap();
+        assert!(custom.contains("| ・ ・ ・ ・v玉 ・ ・ ・ ・|一"));
+        assert!(custom.contains("先手の持駒：歩二\n後手番"));
+    }
+
+    #[test]
+    fn test_kif_roundtrip_with_annotations() {
+        let mut game = record(STARTPOS_SFEN, &["7g7f", "3c3d", "8h2b+", "3a2b"]);
+        game.annotate_move(2, Some("Trades bishops early\nStill balanced".to_string()), Some(MoveSymbol::Dubious))
+            .unwrap();
+        // White is to move before move 4
+        assert!(game.add_variation(3, Variation { moves: vec!["7g7f".to_string()], comment: None }).is_err());
+        game.add_variation(
+            2,
+            Variation {
+                moves: vec!["2g2f".to_string(), "8c8d".to_string()],
+                comment: Some("Quieter".to_string()),
+            },
+        )
+        .unwrap();
+
+        let kif = export_kif(&game).unwrap();
+        assert!(kif.contains("   3 ２二角成(88)   ( 1:01/00:02:02)+\n*?!\n*Trades bishops early\n*Still balanced\n"));
+        assert!(kif.contains("\n\n変化：3手\n   3 ２六歩(27)\n*Quieter\n   4 ８四歩(83)\n"));
+
+        let imported = import_kif(&kif).unwrap();
+        assert_eq!(imported.initial_sfen, STARTPOS_SFEN);
+        assert_eq!(imported.black_name, "Sente Engine");
+        assert_eq!(imported.created_at, "2024-05-01T09:30:00+00:00");
+        assert_eq!(imported.winner.as_deref(), Some("black"));
+        assert_eq!(imported.termination, Some(Termination::Resignation));
+        let usi: Vec<&str> = imported.moves.iter().map(|m| m.usi.as_str()).collect();
+        assert_eq!(usi, vec!["7g7f", "3c3d", "8h2b+", "3a2b"]);
+        assert_eq!(imported.moves[0].elapsed_ms, Some(61_000));
+        assert_eq!(imported.moves[2].symbol, Some(MoveSymbol::Dubious));
+        assert_eq!(imported.moves[2].comment.as_deref(), Some("Trades bishops early\nStill balanced"));
+        assert_eq!(imported.moves[2].variations, game.moves[2].variations);
+        assert_eq!(export_kif(&imported).unwrap().lines().skip(1).collect::<Vec<_>>(), kif.lines().skip(1).collect::<Vec<_>>());
+
+        let custom = record("4k4/9/9/9/9/9/9/9/4K4 w 2P 1", &["5a4b"]);
+        let imported = import_kif(&export_kif(&custom).unwrap()).unwrap();
+        assert_eq!(imported.initial_sfen, "4k4/9/9/9/9/9/9/9/4K4 w 2P 1");
+        assert_eq!(imported.moves[0].usi, "5a4b");
+
+        let handicap = import_kif("手合割：角落ち\n   1 ５二玉(51)\n").unwrap();
+        assert_eq!(handicap.initial_sfen, Handicap::Bishop.sfen());
+        assert!(import_kif("手合割：平手\n   1 ７五歩(77)\n").is_err());
+    }
+}
@@ -1,13 +1,32 @@
+mod builtin_engine;
+mod command_audit;
 mod commands;
-mod engine_manager;
-mod engine_storage;
-mod engine_validator;
-mod engine_vs_engine;
+mod diagnostic_bundle;
+mod engine_installer;
+mod kif;
+mod log_buffer;
+mod network_play;
+mod settings_storage;
 mod state;
+mod tauri_events;
+mod tuning;
+mod usi_bridge;
 
+// Engine management lives in the ysu-core library; keep the `crate::` paths the app uses
+use ysu_core::{
+  adjudication, analysis_queue, atomic_file, autosave, batch_eval, commentary, cpu_affinity, engine_health,
+  engine_manager, engine_permissions, engine_process, engine_storage, engine_validator, engine_vs_engine, eval_file,
+  eval_graph, events, game_record, game_session, gpu, handicap, message, metrics, notation, opening_book,
+  output_buffer, position_match, process_priority, selfplay, shogi_rules, strength, test_suite, think_log, timeouts,
+  tournament, usi_info, usi_macro, win_rate,
+};
+
+use analysis_queue::AnalysisQueue;
 use engine_manager::EngineManager;
 use engine_storage::EngineStorage;
+use settings_storage::AppSettings;
 use state::AppState;
+use tauri_events::TauriEventSink;
 use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -15,16 +34,16 @@ pub fn run() {
   tauri::Builder::default()
     .plugin(tauri_plugin_dialog::init())
     .setup(|app| {
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
-      }
+      // Log in release builds too, keeping recent records in memory for get_recent_logs.
+      // The plugin passes everything; the level set from the settings does the filtering.
+      let (log_plugin, _, logger) = tauri_plugin_log::Builder::default()
+        .level(log::LevelFilter::Trace)
+        .split(app.handle())?;
+      app.handle().plugin(log_plugin)?;
+      log_buffer::install(logger, log::LevelFilter::Info)?;
 
       // Initialize engine manager
-      let engine_manager = EngineManager::new(app.handle().clone());
+      let engine_manager = EngineManager::new(TauriEventSink::shared(app.handle().clone()));
       
       // Load engine storage
       let mut engine_storage = match tauri::async_runtime::block_on(EngineStorage::load()) {
@@ -36,37 +55,42 @@ pub fn run() {
       };
       
       // Auto-register built-in engine if not present, or fix path if it's incorrect
-      // Get the correct path first
-      let correct_path = if cfg!(debug_assertions) {
-        commands::find_workspace_root()
-          .map(|workspace_root| workspace_root.join("target/release/usi-engine"))
-          .filter(|engine_path| engine_path.exists())
-          .map(|engine_path| engine_path.display().to_string())
-      } else {
-        None
+      // Get the correct path first, copying the engine out of the app bundle on first launch
+      let builtin = match tauri::async_runtime::block_on(builtin_engine::locate(app.handle(), false)) {
+        Ok(engine) => Some(engine),
+        Err(e) => {
+          log::warn!("Built-in engine unavailable: {}", e);
+          None
+        }
       };
       
       if !engine_storage.has_builtin_engine() {
         log::info!("Built-in engine not registered, registering now...");
         
-        if let Some(engine_path) = correct_path.as_ref() {
-          log::info!("Found built-in engine at: {}", engine_path);
+        if let Some(builtin) = builtin.as_ref() {
+          log::info!("Found built-in engine at: {}", builtin.path);
           
           // Validate the engine
           let metadata = tauri::async_runtime::block_on(
-            crate::engine_validator::validate_engine(&engine_path)
+            crate::engine_validator::validate_engine(&builtin.path)
           ).ok();
           
           // Create config
-          let config = crate::engine_storage::EngineConfig::new(
+          let mut config = crate::engine_storage::EngineConfig::new(
             "Built-in Engine".to_string(),
-            engine_path.clone(),
+            builtin.path.clone(),
             metadata,
             true,
           );
+          tauri::async_runtime::block_on(config.record_binary_hash());
+          config.builtin_version = builtin.version.clone();
+          config.saved_options = Some(builtin_engine::default_options(
+            config.metadata.as_ref(),
+            &builtin_engine::option_preset(),
+          ));
           
           // Add to storage
-          if let Ok(_) = engine_storage.add_engine(config) {
+          if engine_storage.add_engine(config).is_ok() {
             // Save to disk
             if let Err(e) = tauri::async_runtime::block_on(engine_storage.save()) {
               log::error!("Failed to save engine storage: {}", e);
@@ -77,35 +101,109 @@ pub fn run() {
         } else {
           log::warn!("Could not find built-in engine executable");
         }
-      } else if let Some(correct_path) = correct_path.as_ref() {
-        // Builtin engine exists - check if path needs updating
+      } else if let Some(builtin) = builtin.as_ref() {
+        // Builtin engine exists - check if path or version needs updating
         if let Some(builtin_engine) = engine_storage.engines.iter_mut().find(|e| e.is_builtin) {
           let path_exists = std::path::Path::new(&builtin_engine.path).exists();
-          let path_is_correct = builtin_engine.path == *correct_path;
+          let path_is_correct = builtin_engine.path == builtin.path;
+          let version_changed = builtin_engine.builtin_version != builtin.version;
           
-          if !path_is_correct || !path_exists {
-            log::info!("Updating built-in engine path from '{}' to '{}'", builtin_engine.path, correct_path);
-            builtin_engine.path = correct_path.clone();
+          if !path_is_correct || !path_exists || version_changed {
+            log::info!(
+              "Updating built-in engine from '{}' ({:?}) to '{}' ({:?})",
+              builtin_engine.path, builtin_engine.builtin_version, builtin.path, builtin.version
+            );
+            builtin_engine.path = builtin.path.clone();
+            builtin_engine.builtin_version = builtin.version.clone();
             
-            // Validate the engine and update metadata
+            // Validate the engine and update metadata, carrying saved options over to the new option set
+            let old_options = builtin_engine.metadata.as_ref().map(|m| m.options.clone()).unwrap_or_default();
             let metadata = tauri::async_runtime::block_on(
-              crate::engine_validator::validate_engine(correct_path)
+              crate::engine_validator::validate_engine(&builtin.path)
             ).ok();
+            let option_diff = metadata
+              .as_ref()
+              .map(|meta| crate::engine_validator::diff_options(&old_options, &meta.options));
             builtin_engine.metadata = metadata;
+            if let Some(diff) = option_diff {
+              let migration = builtin_engine.migrate_saved_options(&diff);
+              log::info!("Migrated built-in engine saved options: {:?}", migration);
+            }
+            tauri::async_runtime::block_on(builtin_engine.record_binary_hash());
             
             // Save to disk
             if let Err(e) = tauri::async_runtime::block_on(engine_storage.save()) {
               log::error!("Failed to save engine storage: {}", e);
             } else {
-              log::info!("Built-in engine path updated successfully");
+              log::info!("Built-in engine updated successfully");
             }
           } else {
-            log::info!("Built-in engine already has correct path: {}", correct_path);
+            log::info!("Built-in engine already has correct path: {}", builtin.path);
           }
         }
       }
       
-      let app_state = AppState::new(engine_manager, engine_storage);
+      // Load app-wide settings
+      let settings = match tauri::async_runtime::block_on(AppSettings::load()) {
+        Ok(settings) => settings,
+        Err(e) => {
+          log::error!("Failed to load settings: {}", e);
+          AppSettings::default()
+        }
+      };
+
+      log_buffer::set_level(settings.log_level_filter());
+      let expired = engine_storage.purge_expired_trash(settings.engine_trash_retention_days);
+      if expired > 0 {
+        log::info!("Deleted {} engines from the trash after {} days", expired, settings.engine_trash_retention_days);
+        if let Err(e) = tauri::async_runtime::block_on(engine_storage.save()) {
+          log::error!("Failed to save engine storage: {}", e);
+        }
+      }
+      engine_manager.set_output_throttle(settings.engine_output);
+      engine_manager.set_startup_policy(settings.engine_startup);
+      timeouts::TimeoutConfig::set_global(settings.engine_timeouts);
+      engine_manager.set_idle_policy(settings.engine_idle);
+      engine_manager.set_heartbeat_policy(settings.engine_heartbeat);
+      // Analysis jobs left in the queue resume in the background
+      let analysis_queue = match tauri::async_runtime::block_on(AnalysisQueue::load(TauriEventSink::shared(app.handle().clone()))) {
+        Ok(queue) => queue,
+        Err(e) => {
+          log::error!("Failed to load analysis queue: {}", e);
+          AnalysisQueue::in_memory(TauriEventSink::shared(app.handle().clone()))
+        }
+      };
+      let analysis_workers = settings.analysis_parallelism;
+      let app_state = AppState::new(engine_manager, engine_storage, settings, analysis_queue.clone());
+      tauri::async_runtime::spawn({
+        let engine_storage = app_state.engine_storage.clone();
+        async move { analysis_queue.start(engine_storage, analysis_workers) }
+      });
+
+      // Stop engines that sit idle, per the idle policy
+      tauri::async_runtime::spawn(app_state.engine_manager.clone().run_idle_monitor());
+
+      // Start keep-warm engines now and restart them if they crash
+      tauri::async_runtime::spawn(app_state.engine_manager.clone().run_warm_pool(app_state.engine_storage.clone()));
+
+      // Add engine usage from games and analysis to the stored totals
+      tauri::async_runtime::spawn(app_state.engine_manager.clone().run_usage_recorder(app_state.engine_storage.clone()));
+
+      // Auto-save game sessions so they can be recovered after a crash
+      tauri::async_runtime::spawn({
+        let game_sessions = app_state.game_sessions.clone();
+        let settings = app_state.settings.clone();
+        async move {
+          loop {
+            let interval_secs = settings.read().await.autosave_interval_secs;
+            // While auto-save is off, check again later in case it is turned on
+            tokio::time::sleep(std::time::Duration::from_secs(if interval_secs == 0 { 30 } else { interval_secs })).await;
+            if interval_secs > 0 {
+              autosave::save_sessions(&game_sessions).await;
+            }
+          }
+        }
+      });
 
       // Store state
       app.manage(app_state);
@@ -114,29 +212,149 @@ pub fn run() {
 
       Ok(())
     })
-    .invoke_handler(tauri::generate_handler![
+    .invoke_handler(command_audit::audited(tauri::generate_handler![
       commands::spawn_engine,
+      commands::spawn_engine_instance,
       commands::send_usi_command,
+      commands::new_game,
+      commands::open_console,
+      commands::close_console,
       commands::stop_engine,
+      commands::set_engine_keep_alive,
       commands::get_engine_status,
+      commands::get_engine_crash_history,
+      commands::get_engine_messages,
+      commands::get_latest_analysis,
+      commands::get_engine_position,
+      commands::preview_engine_init,
+      commands::get_engine_stats,
       commands::list_engines,
+      commands::list_engine_instances,
+      commands::describe_runtime_state,
       commands::stop_all_engines,
       commands::get_builtin_engine_path,
       commands::add_engine,
+      commands::install_engine_from_archive,
+      commands::get_gpu_info,
       commands::remove_engine,
+      commands::fix_engine_permissions,
+      commands::clear_engine_quarantine,
       commands::get_engines,
+      commands::get_engine_trash,
+      commands::restore_engine,
+      commands::purge_trash,
       commands::validate_engine_path,
       commands::register_builtin_engine,
+      commands::repair_builtin_engine,
+      commands::check_builtin_engine_update,
       commands::health_check_engines,
+      commands::run_test_suite,
+      commands::evaluate_positions,
+      commands::run_position_match,
+      commands::enqueue_analysis_job,
+      commands::list_analysis_jobs,
+      commands::get_analysis_job,
+      commands::cancel_analysis_job,
+      commands::clear_finished_analysis_jobs,
+      commands::get_eval_graph,
+      commands::cancel_health_check,
+      commands::bulk_engine_action,
+      commands::set_engines_root,
+      commands::relocate_engines,
+      commands::generate_diagnostic_bundle,
       commands::start_engine_vs_engine,
+      commands::set_match_move_delay,
+      commands::replay_match_config,
+      commands::repair_storage,
+      commands::get_settings,
+      commands::update_settings,
+      commands::reset_settings,
+      commands::set_log_level,
+      commands::get_event_schema,
+      commands::get_recent_logs,
+      commands::get_command_history,
+      commands::record_command_results,
+      commands::get_metrics_snapshot,
       commands::save_engine_options,
       commands::get_engine_options,
       commands::clone_engine,
       commands::update_engine_display_name,
-      commands::set_favorite_engine,
+      commands::set_default_engine,
+      commands::get_default_engines,
+      commands::set_engine_group,
+      commands::reorder_engines,
+      commands::set_engine_tags,
+      commands::set_engine_notes,
+      commands::set_engine_priority,
+      commands::set_engine_enabled,
+      commands::set_engine_keep_warm,
+      commands::set_engine_timeouts,
+      commands::set_engine_macros,
+      commands::run_engine_macro,
+      commands::get_warm_engine,
+      commands::set_engine_win_rate,
+      commands::format_engine_score,
+      commands::add_eval_file,
+      commands::list_eval_files,
+      commands::remove_eval_file,
+      commands::set_engine_eval_file,
+      commands::get_engine_tags,
       commands::revalidate_engine_metadata,
       commands::list_image_files,
-    ])
+      commands::format_moves,
+      commands::parse_moves,
+      commands::convert_moves,
+      commands::validate_position,
+      commands::normalize_position,
+      commands::get_handicap_presets,
+      commands::start_game_session,
+      commands::get_strength_levels,
+      commands::get_game_session,
+      commands::apply_game_session_move,
+      commands::apply_engine_move,
+      commands::undo_game_session_move,
+      commands::navigate_game_session,
+      commands::promote_game_variation,
+      commands::delete_game_variation,
+      commands::end_game_session,
+      commands::update_game_session_clocks,
+      commands::recover_sessions,
+      commands::resume_game_session,
+      commands::resume_match,
+      commands::discard_autosave,
+      commands::list_game_records,
+      commands::build_book_from_games,
+      commands::export_opening_book,
+      commands::get_game_record,
+      commands::get_game_think_log,
+      commands::delete_game_record,
+      commands::generate_game_commentary,
+      commands::export_game_record_kif,
+      commands::import_game_record_kif,
+      commands::annotate_game_move,
+      commands::add_game_variation,
+      commands::remove_game_variation,
+      commands::start_tuning,
+      commands::resume_tuning,
+      commands::stop_tuning,
+      commands::get_tuning_session,
+      commands::list_tuning_sessions,
+      commands::start_tournament,
+      commands::resume_tournament,
+      commands::stop_tournament,
+      commands::get_tournament,
+      commands::list_tournaments,
+      commands::start_selfplay_datagen,
+      commands::stop_selfplay_datagen,
+      commands::get_selfplay_datagen_progress,
+      commands::export_tournament_results,
+      commands::connect_csa_server,
+      commands::disconnect_csa_server,
+      commands::get_csa_status,
+      commands::start_bridge,
+      commands::stop_bridge,
+      commands::get_bridge_status,
+    ]))
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
@@ -0,0 +1,143 @@
+//! Recent log records kept in memory, so diagnostics can be pulled from a release build
+//! The tauri_plugin_log logger is wrapped: every record that passes the current level goes on
+//! to the plugin's targets and into a ring buffer of the last LOG_BUFFER_CAPACITY records.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+const LOG_BUFFER_CAPACITY: usize = 2000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+struct LogRing {
+    entries: VecDeque<(Level, LogEntry)>,
+    capacity: usize,
+}
+
+impl LogRing {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, level: Level, entry: LogEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((level, entry));
+    }
+
+    /// The last `count` entries at `min_level` or more severe, oldest first
+    fn recent(&self, count: usize, min_level: LevelFilter) -> Vec<LogEntry> {
+        let mut recent: Vec<LogEntry> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|(level, _)| *level <= min_level)
+            .take(count)
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        recent.reverse();
+        recent
+    }
+}
+
+struct BufferedLogger {
+    inner: Box<dyn Log>,
+    ring: Mutex<LogRing>,
+}
+
+impl Log for BufferedLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.inner.log(record);
+        let entry = LogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        // A panic while logging must not take logging down with it
+        let mut ring = self.ring.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        ring.push(record.level(), entry);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+static LOGGER: std::sync::OnceLock<&'static BufferedLogger> = std::sync::OnceLock::new();
+
+/// Install the plugin's logger, wrapped by the buffer, as the global logger
+pub fn install(inner: Box<dyn Log>, level: LevelFilter) -> Result<(), log::SetLoggerError> {
+    let logger: &'static BufferedLogger = Box::leak(Box::new(BufferedLogger {
+        inner,
+        ring: Mutex::new(LogRing::new(LOG_BUFFER_CAPACITY)),
+    }));
+    log::set_logger(logger)?;
+    let _ = LOGGER.set(logger);
+    set_level(level);
+    Ok(())
+}
+
+/// Change the most verbose level that is logged
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+/// The last `count` buffered records at `min_level` or more severe, oldest first
+pub fn recent(count: usize, min_level: LevelFilter) -> Vec<LogEntry> {
+    match LOGGER.get() {
+        Some(logger) => logger
+            .ring
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .recent(count, min_level),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str) -> LogEntry {
+        LogEntry {
+            timestamp: String::new(),
+            level: String::new(),
+            target: "test".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_ring_keeps_latest_entries() {
+        let mut ring = LogRing::new(3);
+        ring.push(Level::Info, entry("one"));
+        ring.push(Level::Warn, entry("two"));
+        ring.push(Level::Debug, entry("three"));
+        ring.push(Level::Error, entry("four"));
+
+        let messages = |entries: Vec<LogEntry>| entries.into_iter().map(|e| e.message).collect::<Vec<_>>();
+        assert_eq!(messages(ring.recent(10, LevelFilter::Trace)), ["two", "three", "four"]);
+        assert_eq!(messages(ring.recent(2, LevelFilter::Trace)), ["three", "four"]);
+        assert_eq!(messages(ring.recent(10, LevelFilter::Warn)), ["two", "four"]);
+    }
+}
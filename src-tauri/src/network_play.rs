@@ -0,0 +1,825 @@
+//! CSA server protocol client (floodgate, shogi-server)
+//! Logs in, accepts the games the server offers and relays moves between the server and a
+//! local engine, keeping both clocks from the times the server reports for each move.
+
+use crate::engine_process;
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_vs_engine::{move_timeout, EngineVsEngineManager};
+use crate::game_record::{GameRecord, GameRecordStorage, RecordedMove, Termination};
+use crate::notation::{csa_to_usi, parse_csa_piece, parse_csa_square, usi_to_csa};
+use crate::process_priority;
+use crate::shogi_rules::{Color, Move, PieceType, Position, Square, HAND_PIECE_TYPES, STARTPOS_SFEN};
+use crate::tauri_events::{self, CSA_ENGINE_INFO, CSA_GAME_UPDATE};
+use crate::usi_info::UsiInfo;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::process::{ChildStdin, ChildStdout, Command};
+use tokio::sync::{mpsc, Notify, RwLock};
+use tokio::time::{timeout, Instant};
+use uuid::Uuid;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn default_port() -> u16 {
+    4081
+}
+
+fn default_games() -> u32 {
+    1
+}
+
+fn default_keepalive_secs() -> u64 {
+    30
+}
+
+fn default_time_margin_ms() -> u64 {
+    1000
+}
+
+/// Where to connect and which engine plays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsaConnectionConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub username: String,
+    /// Never sent back to the frontend or written to logs
+    #[serde(skip_serializing, default)]
+    pub password: String,
+    /// Config ID of the engine that plays
+    pub engine_id: String,
+    /// Games to play before logging out; 0 keeps playing until disconnected
+    #[serde(default = "default_games")]
+    pub games: u32,
+    /// Seconds of silence after which an empty keep-alive line is sent
+    #[serde(default = "default_keepalive_secs")]
+    pub keepalive_secs: u64,
+    /// Subtracted from the time given to the engine to cover network lag
+    #[serde(default = "default_time_margin_ms")]
+    pub time_margin_ms: u64,
+}
+
+/// Clock settings from a game summary
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct CsaTimeControl {
+    pub total_ms: u64,
+    pub byoyomi_ms: u64,
+    pub increment_ms: u64,
+    /// Length of one unit in move times (`T<n>`)
+    pub unit_ms: u64,
+}
+
+/// A game offered by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct CsaGameSummary {
+    pub game_id: String,
+    pub black_name: String,
+    pub white_name: String,
+    pub my_color: Color,
+    pub initial_sfen: String,
+    /// Moves already played in the starting position (USI), with their times in ms
+    pub moves: Vec<(String, u64)>,
+    pub time: CsaTimeControl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum CsaStatus {
+    Connecting,
+    WaitingForGame,
+    Playing,
+    Disconnected,
+}
+
+/// Connection and game state, emitted as `csa-game-update`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct CsaClientState {
+    pub status: CsaStatus,
+    pub host: String,
+    pub username: String,
+    pub game: Option<CsaGameSummary>,
+    /// All moves of the current game (USI), including those from the summary
+    pub moves: Vec<String>,
+    pub black_time_ms: u64,
+    pub white_time_ms: u64,
+    /// "win", "lose", "draw", "censored" or "chudan" once the game is over
+    pub result: Option<String>,
+    /// Why it ended, e.g. "RESIGN" or "TIME_UP"
+    pub reason: Option<String>,
+    /// ID of the saved game record of the last finished game
+    pub record_id: Option<String>,
+    pub games_played: u32,
+    pub error: Option<String>,
+}
+
+impl CsaClientState {
+    pub fn new(config: &CsaConnectionConfig) -> Self {
+        Self {
+            status: CsaStatus::Connecting,
+            host: format!("{}:{}", config.host, config.port),
+            username: config.username.clone(),
+            game: None,
+            moves: Vec::new(),
+            black_time_ms: 0,
+            white_time_ms: 0,
+            result: None,
+            reason: None,
+            record_id: None,
+            games_played: 0,
+            error: None,
+        }
+    }
+}
+
+/// A running client. `stop` ends it at the next opportunity, abandoning a game in progress.
+pub struct CsaClientHandle {
+    pub state: Arc<RwLock<CsaClientState>>,
+    pub stop: Arc<Notify>,
+}
+
+/// Milliseconds per unit for a `Time_Unit` value such as "1sec", "1min" or "1msec"
+fn parse_time_unit(value: &str) -> Result<u64> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let count: u64 = if split == 0 { 1 } else { value[..split].parse()? };
+    let unit_ms = match &value[split..] {
+        "msec" => 1,
+        "sec" => 1000,
+        "min" => 60_000,
+        other => return Err(anyhow!("Unknown Time_Unit: {}", other)),
+    };
+    Ok(count * unit_ms)
+}
+
+/// Time of a move line ("+7776FU,T12"), in ms
+fn move_time_ms(line: &str, unit_ms: u64) -> u64 {
+    line.split(',')
+        .find_map(|field| field.strip_prefix('T'))
+        .and_then(|t| t.trim().parse::<u64>().ok())
+        .map_or(0, |t| t * unit_ms)
+}
+
+fn is_move_line(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    bytes.len() >= 7 && matches!(bytes[0], b'+' | b'-') && bytes[1].is_ascii_digit()
+}
+
+/// Board described by a CSA `Position` block, as an SFEN
+fn csa_position_sfen(lines: &[&str]) -> Result<String> {
+    let mut board: [[Option<(Color, PieceType)>; 9]; 9] = [[None; 9]; 9];
+    let mut hands = [[0u8; 7]; 2];
+    let mut side_to_move = Color::Black;
+
+    for line in lines {
+        if let Some(removed) = line.strip_prefix("PI") {
+            let start = Position::from_sfen(STARTPOS_SFEN)?;
+            for rank in 1..=9u8 {
+                for file in 1..=9u8 {
+                    let square = Square::new(file, rank).expect("valid square");
+                    board[rank as usize - 1][file as usize - 1] =
+                        start.piece_at(square).map(|piece| (piece.color, piece.piece_type));
+                }
+            }
+            // Handicaps list the removed pieces, e.g. "PI82HI22KA"
+            for chunk in removed.as_bytes().chunks(4).filter(|chunk| chunk.len() == 4) {
+                let chunk = std::str::from_utf8(chunk)?;
+                if let Some(square) = parse_csa_square(&chunk[..2])? {
+                    board[square.rank as usize - 1][square.file as usize - 1] = None;
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix('P').filter(|rest| rest.starts_with(|c: char| ('1'..='9').contains(&c))) {
+            let rank = rest[..1].parse::<usize>()?;
+            let cells = &rest[1..];
+            for (index, file) in (1..=9usize).rev().enumerate() {
+                let cell = cells.get(index * 3..index * 3 + 3).unwrap_or(" * ");
+                board[rank - 1][file - 1] = match cell.chars().next() {
+                    Some('+') => Some((Color::Black, parse_csa_piece(&cell[1..]).ok_or_else(|| anyhow!("Invalid piece: {}", cell))?)),
+                    Some('-') => Some((Color::White, parse_csa_piece(&cell[1..]).ok_or_else(|| anyhow!("Invalid piece: {}", cell))?)),
+                    _ => None,
+                };
+            }
+        } else if let Some(rest) = line.strip_prefix("P+").or_else(|| line.strip_prefix("P-")) {
+            let color = if line.starts_with("P+") { Color::Black } else { Color::White };
+            for chunk in rest.as_bytes().chunks(4).filter(|chunk| chunk.len() == 4) {
+                let chunk = std::str::from_utf8(chunk)?;
+                if &chunk[2..] == "AL" {
+                    log::warn!("Ignoring unsupported CSA position line: {}", line);
+                    continue;
+                }
+                let piece_type = parse_csa_piece(&chunk[2..]).ok_or_else(|| anyhow!("Invalid piece: {}", chunk))?;
+                match parse_csa_square(&chunk[..2])? {
+                    Some(square) => board[square.rank as usize - 1][square.file as usize - 1] = Some((color, piece_type)),
+                    None => {
+                        let index = HAND_PIECE_TYPES
+                            .iter()
+                            .position(|&pt| pt == piece_type)
+                            .ok_or_else(|| anyhow!("{} cannot be held in hand", chunk))?;
+                        hands[if color == Color::Black { 0 } else { 1 }][index] += 1;
+                    }
+                }
+            }
+        } else if *line == "+" {
+            side_to_move = Color::Black;
+        } else if *line == "-" {
+            side_to_move = Color::White;
+        }
+    }
+
+    let rows: Vec<String> = board
+        .iter()
+        .map(|row| {
+            let mut text = String::new();
+            let mut empty = 0;
+            for file in (1..=9usize).rev() {
+                match row[file - 1] {
+                    Some((color, piece_type)) => {
+                        if empty > 0 {
+                            text.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        text.push_str(&crate::shogi_rules::Piece { piece_type, color }.to_sfen());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                text.push_str(&empty.to_string());
+            }
+            text
+        })
+        .collect();
+    let mut hand = String::new();
+    for (side, counts) in hands.iter().enumerate() {
+        for (index, &count) in counts.iter().enumerate() {
+            let letter = HAND_PIECE_TYPES[index].usi_char();
+            let letter = if side == 0 { letter } else { letter.to_ascii_lowercase() };
+            match count {
+                0 => {}
+                1 => hand.push(letter),
+                n => hand.push_str(&format!("{}{}", n, letter)),
+            }
+        }
+    }
+    let sfen = format!(
+        "{} {} {} 1",
+        rows.join("/"),
+        if side_to_move == Color::Black { 'b' } else { 'w' },
+        if hand.is_empty() { "-".to_string() } else { hand }
+    );
+    // Round-trip through the rules module to validate it
+    Ok(Position::from_sfen(&sfen)?.to_sfen())
+}
+
+/// Parse the lines between `BEGIN Game_Summary` and `END Game_Summary`
+pub fn parse_game_summary(lines: &[String]) -> Result<CsaGameSummary> {
+    let mut game_id = None;
+    let mut names = (String::new(), String::new());
+    let mut my_color = None;
+    let mut time = CsaTimeControl { unit_ms: 1000, ..Default::default() };
+    let mut position_lines = Vec::new();
+    let mut section = "";
+
+    for line in lines.iter().map(|line| line.trim()) {
+        match line {
+            "BEGIN Time" => section = "time",
+            "BEGIN Position" => section = "position",
+            "END Time" | "END Position" => section = "",
+            _ if section == "position" => position_lines.push(line),
+            _ => {
+                let Some((key, value)) = line.split_once(':') else { continue };
+                match (section, key) {
+                    ("", "Game_ID") => game_id = Some(value.to_string()),
+                    ("", "Name+") => names.0 = value.to_string(),
+                    ("", "Name-") => names.1 = value.to_string(),
+                    ("", "Your_Turn") => my_color = Some(if value == "-" { Color::White } else { Color::Black }),
+                    ("time", "Time_Unit") => time.unit_ms = parse_time_unit(value)?,
+                    ("time", "Total_Time") => time.total_ms = value.parse()?,
+                    ("time", "Byoyomi") => time.byoyomi_ms = value.parse()?,
+                    ("time", "Increment") => time.increment_ms = value.parse()?,
+                    _ => {}
+                }
+            }
+        }
+    }
+    // Times are given in units until Time_Unit is known
+    time.total_ms *= time.unit_ms;
+    time.byoyomi_ms *= time.unit_ms;
+    time.increment_ms *= time.unit_ms;
+
+    let board_lines: Vec<&str> = position_lines.iter().copied().filter(|line| !is_move_line(line)).collect();
+    let initial_sfen = csa_position_sfen(&board_lines)?;
+    let mut position = Position::from_sfen(&initial_sfen)?;
+    let mut moves = Vec::new();
+    for line in position_lines.iter().filter(|line| is_move_line(line)) {
+        let mv = csa_to_usi(&position, line)?;
+        position.apply_move(&mv)?;
+        moves.push((mv.to_usi(), move_time_ms(line, time.unit_ms)));
+    }
+
+    Ok(CsaGameSummary {
+        game_id: game_id.ok_or_else(|| anyhow!("Game summary has no Game_ID"))?,
+        black_name: names.0,
+        white_name: names.1,
+        my_color: my_color.ok_or_else(|| anyhow!("Game summary has no Your_Turn"))?,
+        initial_sfen,
+        moves,
+        time,
+    })
+}
+
+/// Line-based connection to the server with keep-alives
+struct Connection {
+    writer: OwnedWriteHalf,
+    lines: mpsc::UnboundedReceiver<String>,
+    last_sent: Instant,
+    keepalive: Duration,
+}
+
+impl Connection {
+    async fn send(&mut self, line: &str) -> Result<()> {
+        self.writer.write_all(format!("{}\n", line).as_bytes()).await?;
+        self.writer.flush().await?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    /// Next line from the server, sending keep-alives while waiting.
+    /// Returns None when `stop` is notified.
+    async fn next_line(&mut self, stop: &Notify) -> Result<Option<String>> {
+        loop {
+            let keepalive_at = self.last_sent + self.keepalive;
+            tokio::select! {
+                line = self.lines.recv() => {
+                    return line.map(Some).ok_or_else(|| anyhow!("Server closed the connection"));
+                }
+                _ = tokio::time::sleep_until(keepalive_at) => self.send("").await?,
+                _ = stop.notified() => return Ok(None),
+            }
+        }
+    }
+}
+
+/// The playing engine's process
+struct Engine {
+    config: EngineConfig,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+    _child: tokio::process::Child,
+}
+
+impl Engine {
+    async fn start(config: EngineConfig, engine_storage: &RwLock<EngineStorage>) -> Result<Self> {
+        let dir = std::path::Path::new(&config.path)
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid engine path"))?;
+        let mut command = Command::new(&config.path);
+        command
+            .current_dir(dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true);
+        engine_process::prepare(&mut command);
+        process_priority::apply_before_spawn(&mut command, config.priority);
+        let mut child = command.spawn().map_err(|e| anyhow!("Failed to spawn engine: {}", e))?;
+        engine_process::attach(&child);
+        if let Err(e) = process_priority::apply_after_spawn(&child, config.priority) {
+            log::warn!("Engine priority: {}", e);
+        }
+        let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("Failed to get engine stdin"))?;
+        let mut stdout = child.stdout.take().ok_or_else(|| anyhow!("Failed to get engine stdout"))?;
+        EngineVsEngineManager::initialize_engine_with_options(
+            &mut stdin,
+            &mut stdout,
+            &config.id,
+            engine_storage,
+            None,
+            None,
+            None,
+        )
+        .await?;
+        Ok(Self { config, stdin, stdout, _child: child })
+    }
+
+    async fn send(&mut self, command: &str) -> Result<()> {
+        self.stdin.write_all(format!("{}\n", command).as_bytes()).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Abort a search and consume its bestmove, so it isn't taken as the reply to the next `go`
+    async fn stop_search(&mut self) {
+        if self.send("stop").await.is_err() {
+            return;
+        }
+        let mut reader = BufReader::new(&mut self.stdout);
+        let mut line = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while let Ok(Ok(read)) = tokio::time::timeout_at(deadline, reader.read_until(b'\n', &mut line)).await {
+            if read == 0 || line.starts_with(b"bestmove") {
+                return;
+            }
+            line.clear();
+        }
+        log::warn!("Engine {} did not answer stop", self.config.name);
+    }
+}
+
+/// `go` command for the engine's clock, less the network margin
+fn go_command(time: &CsaTimeControl, clocks: [u64; 2], margin_ms: u64) -> String {
+    let mut command = format!("go btime {} wtime {}", clocks[0], clocks[1]);
+    if time.increment_ms > 0 {
+        command.push_str(&format!(" binc {} winc {}", time.increment_ms, time.increment_ms));
+    } else if time.byoyomi_ms > 0 {
+        command.push_str(&format!(" byoyomi {}", time.byoyomi_ms.saturating_sub(margin_ms)));
+    }
+    command
+}
+
+/// How a game ended, from `#` lines
+#[derive(Default)]
+struct GameEnd {
+    result: Option<String>,
+    reason: Option<String>,
+}
+
+fn termination_for(reason: Option<&str>) -> Option<Termination> {
+    match reason? {
+        "RESIGN" => Some(Termination::Resignation),
+        "ILLEGAL_MOVE" | "OUTE_SENNICHITE" => Some(Termination::IllegalMove),
+        "TIME_UP" => Some(Termination::EngineFailure),
+        "JISHOGI" => Some(Termination::EnteringKing),
+        "MAX_MOVES" => Some(Termination::MaxMoves),
+        _ => None,
+    }
+}
+
+/// Position, clocks and moves of the game being played
+struct GameProgress {
+    position: Position,
+    clocks: [u64; 2],
+    moves: Vec<String>,
+    recorded: Vec<RecordedMove>,
+    increment_ms: u64,
+}
+
+impl GameProgress {
+    /// Play a move reported by the server, charging its time to the mover's clock
+    fn apply(&mut self, usi: &str, elapsed_ms: u64) -> Result<()> {
+        let side = if self.position.side_to_move() == Color::Black { 0 } else { 1 };
+        self.clocks[side] = self.clocks[side].saturating_sub(elapsed_ms) + self.increment_ms;
+        self.position.apply_usi_move(usi)?;
+        self.moves.push(usi.to_string());
+        self.recorded.push(RecordedMove {
+            usi: usi.to_string(),
+            elapsed_ms: Some(elapsed_ms),
+            score: None,
+            depth: None,
+            commentary: Vec::new(),
+            comment: None,
+            symbol: None,
+            variations: Vec::new(),
+            book: false,
+        });
+        Ok(())
+    }
+}
+
+struct Client {
+    app_handle: AppHandle,
+    config: CsaConnectionConfig,
+    state: Arc<RwLock<CsaClientState>>,
+    stop: Arc<Notify>,
+}
+
+impl Client {
+    async fn update(&self, change: impl FnOnce(&mut CsaClientState)) {
+        let mut state = self.state.write().await;
+        change(&mut state);
+        let _ = tauri_events::send(&self.app_handle, &CSA_GAME_UPDATE, &*state);
+    }
+
+    async fn run(&self, engine_storage: &RwLock<EngineStorage>) -> Result<()> {
+        let engine_config = engine_storage
+            .read()
+            .await
+            .get_engine(&self.config.engine_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Engine not found: {}", self.config.engine_id))?;
+        let mut engine = Engine::start(engine_config, engine_storage).await?;
+
+        log::info!("Connecting to CSA server {}:{}", self.config.host, self.config.port);
+        let stream = timeout(CONNECT_TIMEOUT, TcpStream::connect((self.config.host.as_str(), self.config.port)))
+            .await
+            .map_err(|_| anyhow!("Timed out connecting to {}", self.config.host))??;
+        let (reader, writer) = stream.into_split();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                log::debug!("CSA server: {}", line);
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut conn = Connection {
+            writer,
+            lines: rx,
+            last_sent: Instant::now(),
+            keepalive: Duration::from_secs(self.config.keepalive_secs.max(1)),
+        };
+
+        conn.send(&format!("LOGIN {} {}", self.config.username, self.config.password)).await?;
+        loop {
+            let Some(line) = conn.next_line(&self.stop).await? else { return Ok(()) };
+            if let Some(reply) = line.strip_prefix("LOGIN:") {
+                if !reply.ends_with(" OK") {
+                    return Err(anyhow!("Login failed: {}", reply));
+                }
+                break;
+            }
+        }
+        log::info!("Logged in to CSA server as {}", self.config.username);
+        self.update(|state| state.status = CsaStatus::WaitingForGame).await;
+
+        let mut games_played = 0;
+        while self.config.games == 0 || games_played < self.config.games {
+            let Some(summary) = self.wait_for_game(&mut conn).await? else { break };
+            let finished = self.play_game(&mut conn, &mut engine, summary).await?;
+            if !finished {
+                break;
+            }
+            games_played += 1;
+            self.update(|state| {
+                state.games_played = games_played;
+                state.status = CsaStatus::WaitingForGame;
+            })
+            .await;
+        }
+
+        let _ = conn.send("LOGOUT").await;
+        let _ = engine.send("quit").await;
+        Ok(())
+    }
+
+    /// Receive a game summary and agree to it; None if stopped
+    async fn wait_for_game(&self, conn: &mut Connection) -> Result<Option<CsaGameSummary>> {
+        loop {
+            let mut summary_lines = Vec::new();
+            loop {
+                let Some(line) = conn.next_line(&self.stop).await? else { return Ok(None) };
+                if line == "BEGIN Game_Summary" {
+                    summary_lines.clear();
+                } else if line == "END Game_Summary" {
+                    break;
+                } else {
+                    summary_lines.push(line);
+                }
+            }
+            let summary = parse_game_summary(&summary_lines)?;
+            log::info!("CSA game offered: {} ({} vs {})", summary.game_id, summary.black_name, summary.white_name);
+            conn.send(&format!("AGREE {}", summary.game_id)).await?;
+
+            loop {
+                let Some(line) = conn.next_line(&self.stop).await? else { return Ok(None) };
+                if line.starts_with("START:") {
+                    return Ok(Some(summary));
+                }
+                if line.starts_with("REJECT:") {
+                    log::info!("CSA game rejected: {}", line);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Play one game to its end. Returns false if the client was stopped during the game.
+    async fn play_game(&self, conn: &mut Connection, engine: &mut Engine, summary: CsaGameSummary) -> Result<bool> {
+        let mut game = GameProgress {
+            position: Position::from_sfen(&summary.initial_sfen)?,
+            clocks: [summary.time.total_ms; 2],
+            moves: Vec::new(),
+            recorded: Vec::new(),
+            increment_ms: summary.time.increment_ms,
+        };
+        for (usi, elapsed_ms) in &summary.moves {
+            game.apply(usi, *elapsed_ms)?;
+        }
+        // The initial SFEN sent to the engine is the summary's, before its moves
+        let initial_sfen = summary.initial_sfen.clone();
+
+        let my_color = summary.my_color;
+        self.update(|state| {
+            state.status = CsaStatus::Playing;
+            state.game = Some(summary.clone());
+            state.moves = game.moves.clone();
+            state.black_time_ms = game.clocks[0];
+            state.white_time_ms = game.clocks[1];
+            state.result = None;
+            state.reason = None;
+            state.error = None;
+        })
+        .await;
+        engine.send("usinewgame").await?;
+
+        let mut end = GameEnd::default();
+        let mut sent_at_ply = None;
+        while end.result.is_none() {
+            let line = if game.position.side_to_move() == my_color && sent_at_ply != Some(game.moves.len()) {
+                let own = if my_color == Color::Black { game.clocks[0] } else { game.clocks[1] };
+                let go_cmd = go_command(&summary.time, game.clocks, self.config.time_margin_ms);
+                let think_timeout = move_timeout(own + summary.time.byoyomi_ms + summary.time.increment_ms);
+                let app_handle = &self.app_handle;
+                let on_info = |info: &UsiInfo, _: &str| {
+                    let _ = tauri_events::send(app_handle, &CSA_ENGINE_INFO, info);
+                };
+                let search = EngineVsEngineManager::request_move(
+                    &mut engine.stdin,
+                    &mut engine.stdout,
+                    &initial_sfen,
+                    &game.moves,
+                    &go_cmd,
+                    think_timeout,
+                    on_info,
+                );
+                tokio::select! {
+                    reply = search => {
+                        let best_move = reply?.best_move;
+                        let csa = match best_move.as_str() {
+                            "resign" => "%TORYO".to_string(),
+                            "win" => "%KACHI".to_string(),
+                            usi => usi_to_csa(&game.position, &Move::from_usi(usi)?)?,
+                        };
+                        log::info!("{} plays {} ({})", engine.config.name, csa, best_move);
+                        conn.send(&csa).await?;
+                        sent_at_ply = Some(game.moves.len());
+                        continue;
+                    }
+                    // The game can end on the server while the engine thinks, e.g. on time
+                    line = conn.lines.recv() => {
+                        engine.stop_search().await;
+                        line.ok_or_else(|| anyhow!("Server closed the connection"))?
+                    }
+                    _ = self.stop.notified() => {
+                        engine.stop_search().await;
+                        return Ok(false);
+                    }
+                }
+            } else {
+                match conn.next_line(&self.stop).await? {
+                    Some(line) => line,
+                    None => return Ok(false),
+                }
+            };
+
+            if is_move_line(&line) {
+                let mv = csa_to_usi(&game.position, &line)?;
+                game.apply(&mv.to_usi(), move_time_ms(&line, summary.time.unit_ms))?;
+                self.update(|state| {
+                    state.moves = game.moves.clone();
+                    state.black_time_ms = game.clocks[0];
+                    state.white_time_ms = game.clocks[1];
+                })
+                .await;
+            } else if let Some(word) = line.strip_prefix('#') {
+                match word {
+                    "WIN" | "LOSE" | "DRAW" | "CENSORED" | "CHUDAN" => end.result = Some(word.to_lowercase()),
+                    reason => end.reason = Some(reason.to_string()),
+                }
+            }
+        }
+
+        let result = end.result.unwrap_or_default();
+        let gameover = match result.as_str() {
+            "win" => "gameover win",
+            "lose" => "gameover lose",
+            _ => "gameover draw",
+        };
+        let _ = engine.send(gameover).await;
+        log::info!("CSA game {} over: {} ({:?})", summary.game_id, result, end.reason);
+
+        let winner = match result.as_str() {
+            "win" => Some(my_color.as_str().to_string()),
+            "lose" => Some(my_color.opponent().as_str().to_string()),
+            "draw" => Some("draw".to_string()),
+            _ => None,
+        };
+        let record = GameRecord {
+            id: Uuid::new_v4().to_string(),
+            black_name: summary.black_name.clone(),
+            white_name: summary.white_name.clone(),
+            initial_sfen: summary.initial_sfen.clone(),
+            moves: game.recorded,
+            winner,
+            result: Some(match &end.reason {
+                Some(reason) => format!("{} ({})", result, reason),
+                None => result.clone(),
+            }),
+            termination: termination_for(end.reason.as_deref()),
+            black_timing: None,
+            white_timing: None,
+            match_config: None,
+            think_log: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let record_id = match GameRecordStorage::save(&record).await {
+            Ok(_) => Some(record.id.clone()),
+            Err(e) => {
+                log::error!("Failed to save CSA game record: {}", e);
+                None
+            }
+        };
+        self.update(|state| {
+            state.result = Some(result);
+            state.reason = end.reason;
+            state.record_id = record_id;
+        })
+        .await;
+        Ok(true)
+    }
+}
+
+/// Run the client until it finishes its games, fails or is stopped. The final state
+/// (status `disconnected`, with `error` set on failure) is emitted as `csa-game-update`.
+pub async fn run_client(
+    app_handle: AppHandle,
+    engine_storage: Arc<RwLock<EngineStorage>>,
+    config: CsaConnectionConfig,
+    state: Arc<RwLock<CsaClientState>>,
+    stop: Arc<Notify>,
+) {
+    let client = Client { app_handle, config, state, stop };
+    let result = client.run(&engine_storage).await;
+    if let Err(e) = &result {
+        log::error!("CSA client error: {}", e);
+    }
+    client
+        .update(|state| {
+            state.status = CsaStatus::Disconnected;
+            state.error = result.err().map(|e| e.to_string());
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_summary_and_moves() {
+        let lines: Vec<String> = "Protocol_Version:1.2
+Format:Shogi 1.0
+Game_ID:wdoor+floodgate-300-10F+a+b+20240501090000
+Name+:alpha
+Name-:beta
+Your_Turn:-
+To_Move:+
+BEGIN Time
+Time_Unit:1sec
+Total_Time:300
+Byoyomi:0
+Increment:10
+END Time
+BEGIN Position
+PI
+P+
+P-
++
++7776FU,T3
+END Position"
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let summary = parse_game_summary(&lines).unwrap();
+        assert_eq!(summary.my_color, Color::White);
+        assert_eq!(summary.initial_sfen, STARTPOS_SFEN);
+        assert_eq!(summary.moves, vec![("7g7f".to_string(), 3000)]);
+        assert_eq!(summary.time, CsaTimeControl { total_ms: 300_000, byoyomi_ms: 0, increment_ms: 10_000, unit_ms: 1000 });
+        assert_eq!(go_command(&summary.time, [297_000, 300_000], 1000), "go btime 297000 wtime 300000 binc 10000 winc 10000");
+
+        let (mut position, opening) = crate::shogi_rules::parse_position("startpos moves 7g7f 3c3d").unwrap();
+        for mv in &opening {
+            position.apply_move(mv).unwrap();
+        }
+        let mv = csa_to_usi(&position, "+8822UM,T1").unwrap();
+        assert_eq!(mv.to_usi(), "8h2b+");
+        assert_eq!(usi_to_csa(&position, &mv).unwrap(), "+8822UM");
+        assert!(csa_to_usi(&position, "-3334FU").is_err());
+        position.apply_move(&mv).unwrap();
+        position.apply_usi_move("3a2b").unwrap();
+        assert_eq!(usi_to_csa(&position, &Move::from_usi("B*5e").unwrap()).unwrap(), "+0055KA");
+
+        let handicap = csa_position_sfen(&["PI22KA", "-"]).unwrap();
+        assert_eq!(handicap, "lnsgkgsnl/1r7/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1");
+    }
+}
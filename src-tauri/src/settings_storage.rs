@@ -0,0 +1,214 @@
+//! App-wide settings persisted in settings.json, separate from engine configuration
+
+use crate::atomic_file;
+use crate::engine_storage::EngineStorage;
+use crate::engine_manager::{HeartbeatPolicy, IdlePolicy, StartupPolicy};
+use crate::output_buffer::OutputThrottle;
+use crate::strength::{StrengthLevel, StrengthPreset};
+use crate::timeouts::TimeoutConfig;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const SETTINGS_SCHEMA_VERSION: u32 = 1;
+const BACKUP_COUNT: usize = 3;
+
+/// Clock settings for a game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct TimeControl {
+    /// Main thinking time per side
+    pub main_time_ms: u64,
+    /// Time per move once main time is used up
+    pub byoyomi_ms: u64,
+    /// Fischer increment added after each move
+    pub increment_ms: u64,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            main_time_ms: 0,
+            byoyomi_ms: 5000,
+            increment_ms: 0,
+        }
+    }
+}
+
+/// Persisted application preferences
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(default)]
+pub struct AppSettings {
+    pub schema_version: u32,
+    pub default_time_control: TimeControl,
+    /// Strength level used for new human-vs-engine games when none is chosen
+    pub default_strength: Option<StrengthLevel>,
+    /// Per-engine replacements for the built-in strength presets, keyed by engine config ID
+    pub strength_presets: HashMap<String, HashMap<StrengthLevel, StrengthPreset>>,
+    /// Buffering and info rate limit for engine output sent to the UI
+    pub engine_output: OutputThrottle,
+    /// Retries for starting engines
+    pub engine_startup: StartupPolicy,
+    /// How long to wait on engines; each engine's config can override these
+    pub engine_timeouts: TimeoutConfig,
+    /// When engines without commands are stopped to free memory
+    pub engine_idle: IdlePolicy,
+    /// How idle engines are probed with `isready` to detect hangs
+    pub engine_heartbeat: HeartbeatPolicy,
+    /// Analysis jobs run at the same time; applied at the next start
+    pub analysis_parallelism: usize,
+    /// Seconds between auto-saves of game sessions (matches save after every move);
+    /// 0 turns auto-save off
+    pub autosave_interval_secs: u64,
+    /// Days removed engines are kept in the trash before they are deleted for good, checked
+    /// at start; 0 keeps them until the trash is emptied
+    pub engine_trash_retention_days: u32,
+    /// Most verbose level logged: "off", "error", "warn", "info", "debug" or "trace"
+    pub log_level: String,
+    /// https URL of a JSON description of the latest built-in engine release; unset turns
+    /// update checks off
+    pub builtin_engine_update_url: Option<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            schema_version: SETTINGS_SCHEMA_VERSION,
+            default_time_control: TimeControl::default(),
+            default_strength: None,
+            strength_presets: HashMap::new(),
+            engine_output: OutputThrottle::default(),
+            engine_startup: StartupPolicy::default(),
+            engine_timeouts: TimeoutConfig::default(),
+            engine_idle: IdlePolicy::default(),
+            engine_heartbeat: HeartbeatPolicy::default(),
+            analysis_parallelism: 1,
+            autosave_interval_secs: 30,
+            engine_trash_retention_days: 30,
+            log_level: "info".to_string(),
+            builtin_engine_update_url: None,
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn get_storage_path() -> Result<PathBuf> {
+        Ok(EngineStorage::get_config_dir()?.join("settings.json"))
+    }
+
+    /// Load settings, falling back to a backup and then to defaults if the file is unreadable
+    pub async fn load() -> Result<Self> {
+        let path = Self::get_storage_path()?;
+        if !path.exists() {
+            log::info!("Settings file not found, using defaults");
+            return Ok(Self::default());
+        }
+
+        let candidates = std::iter::once(path.clone()).chain(atomic_file::backup_paths(&path, BACKUP_COUNT)?);
+        for candidate in candidates {
+            let Ok(contents) = tokio::fs::read_to_string(&candidate).await else {
+                continue;
+            };
+            match serde_json::from_str::<Self>(&contents) {
+                Ok(settings) => {
+                    log::info!("Loaded settings from: {}", candidate.display());
+                    return Ok(settings);
+                }
+                Err(e) => log::warn!("Skipping unreadable settings file {}: {}", candidate.display(), e),
+            }
+        }
+
+        log::warn!("No readable settings file found, using defaults");
+        Ok(Self::default())
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let path = Self::get_storage_path()?;
+        let contents = serde_json::to_string_pretty(self)?;
+        atomic_file::rotate_backups(&path, BACKUP_COUNT).await?;
+        atomic_file::write_atomic(&path, contents.as_bytes()).await?;
+        log::info!("Saved settings to: {}", path.display());
+        Ok(())
+    }
+
+    /// Apply a partial update given as a JSON object of fields to change
+    pub fn apply_patch(&self, patch: &serde_json::Value) -> Result<Self> {
+        let patch = patch.as_object().ok_or_else(|| anyhow!("Settings update must be an object"))?;
+        let mut value = serde_json::to_value(self)?;
+        let fields = value.as_object_mut().ok_or_else(|| anyhow!("Settings are not an object"))?;
+        for (key, new_value) in patch {
+            if key == "schema_version" || !fields.contains_key(key) {
+                return Err(anyhow!("Unknown setting: {}", key));
+            }
+            fields.insert(key.clone(), new_value.clone());
+        }
+        let updated: Self = serde_json::from_value(value)?;
+        updated.validate()?;
+        Ok(updated)
+    }
+
+    /// The log level, or info if it is not a valid level
+    pub fn log_level_filter(&self) -> log::LevelFilter {
+        self.log_level.parse().unwrap_or(log::LevelFilter::Info)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.analysis_parallelism == 0 {
+            return Err(anyhow!("analysis_parallelism must be at least 1"));
+        }
+        if self.log_level.parse::<log::LevelFilter>().is_err() {
+            return Err(anyhow!("Unknown log level: {}", self.log_level));
+        }
+        if let Some(url) = &self.builtin_engine_update_url {
+            if !url.starts_with("https://") {
+                return Err(anyhow!("builtin_engine_update_url must be an https URL"));
+            }
+        }
+        if self.engine_output.buffer_lines == 0 {
+            return Err(anyhow!("engine_output.buffer_lines must be at least 1"));
+        }
+        self.engine_timeouts.validate()?;
+        if self.engine_heartbeat.timeout_ms == 0 {
+            return Err(anyhow!("engine_heartbeat.timeout_ms must be positive"));
+        }
+        for (engine_id, levels) in &self.strength_presets {
+            for preset in levels.values() {
+                if let Some(limit) = &preset.search_limit {
+                    limit.validate().map_err(|e| anyhow!("Strength preset for {}: {}", engine_id, e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_patch() {
+        let settings = AppSettings::default();
+        let updated = settings
+            .apply_patch(&serde_json::json!({
+                "analysis_parallelism": 4,
+                "default_time_control": { "main_time_ms": 60000, "byoyomi_ms": 1000, "increment_ms": 0 }
+            }))
+            .unwrap();
+        assert_eq!(updated.analysis_parallelism, 4);
+        assert_eq!(updated.default_time_control.byoyomi_ms, 1000);
+        assert_eq!(updated.log_level, "info");
+
+        assert!(settings.apply_patch(&serde_json::json!({ "analysis_paralelism": 4 })).is_err());
+        assert!(settings.apply_patch(&serde_json::json!({ "analysis_parallelism": 0 })).is_err());
+        assert!(settings.apply_patch(&serde_json::json!({ "analysis_parallelism": "many" })).is_err());
+        assert!(settings.apply_patch(&serde_json::json!({ "log_level": "verbose" })).is_err());
+
+        // Older files missing newer fields still load
+        let partial: AppSettings = serde_json::from_str(r#"{ "log_level": "debug" }"#).unwrap();
+        assert_eq!(partial.log_level, "debug");
+        assert_eq!(partial.analysis_parallelism, 1);
+    }
+}
@@ -1,19 +1,68 @@
+use crate::analysis_queue::AnalysisQueue;
 use crate::engine_manager::EngineManager;
 use crate::engine_storage::EngineStorage;
+use crate::engine_vs_engine::{EngineVsEngineConfig, EngineVsEngineState};
+use crate::game_session::GameSessionStore;
+use crate::network_play::CsaClientHandle;
+use crate::settings_storage::AppSettings;
+use crate::usi_bridge::BridgeHandle;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
+
+/// An engine-vs-engine match running in the background
+pub struct RunningMatch {
+    pub config: EngineVsEngineConfig,
+    pub state: Arc<Mutex<EngineVsEngineState>>,
+    /// Pause after each move, adjustable while the match runs
+    pub move_delay_ms: Arc<AtomicU64>,
+}
 
 /// Application state that is shared across the Tauri app
 pub struct AppState {
     pub engine_manager: Arc<EngineManager>,
     pub engine_storage: Arc<RwLock<EngineStorage>>,
+    pub game_sessions: Arc<RwLock<GameSessionStore>>,
+    pub settings: Arc<RwLock<AppSettings>>,
+    /// Stop flags of tuning sessions currently running, by session ID
+    pub tuning_runs: Arc<RwLock<HashMap<String, Arc<AtomicBool>>>>,
+    /// Stop flags of tournaments currently running, by tournament ID
+    pub tournament_runs: Arc<RwLock<HashMap<String, Arc<AtomicBool>>>>,
+    /// Stop flags of self-play data generation runs, by output directory
+    pub datagen_runs: Arc<RwLock<HashMap<String, Arc<AtomicBool>>>>,
+    /// Cancel flags of engine health checks currently running, by check ID
+    pub health_checks: Arc<RwLock<HashMap<String, Arc<AtomicBool>>>>,
+    /// Connection to a CSA game server, if any
+    pub csa_client: Arc<RwLock<Option<CsaClientHandle>>>,
+    /// USI bridge server for external tools, if started
+    pub bridge: Arc<RwLock<Option<BridgeHandle>>>,
+    /// Background analysis jobs
+    pub analysis_queue: Arc<AnalysisQueue>,
+    /// Engine-vs-engine matches in progress, by match ID
+    pub running_matches: Arc<RwLock<HashMap<String, RunningMatch>>>,
 }
 
 impl AppState {
-    pub fn new(engine_manager: EngineManager, engine_storage: EngineStorage) -> Self {
+    pub fn new(
+        engine_manager: EngineManager,
+        engine_storage: EngineStorage,
+        settings: AppSettings,
+        analysis_queue: Arc<AnalysisQueue>,
+    ) -> Self {
         Self {
             engine_manager: Arc::new(engine_manager),
             engine_storage: Arc::new(RwLock::new(engine_storage)),
+            game_sessions: Arc::new(RwLock::new(GameSessionStore::default())),
+            settings: Arc::new(RwLock::new(settings)),
+            tuning_runs: Arc::new(RwLock::new(HashMap::new())),
+            tournament_runs: Arc::new(RwLock::new(HashMap::new())),
+            datagen_runs: Arc::new(RwLock::new(HashMap::new())),
+            health_checks: Arc::new(RwLock::new(HashMap::new())),
+            csa_client: Arc::new(RwLock::new(None)),
+            bridge: Arc::new(RwLock::new(None)),
+            analysis_queue,
+            running_matches: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -0,0 +1,64 @@
+//! Forwards core library events to the webview, and declares the events the app itself emits
+
+use crate::commands::HealthCheckProgress;
+use crate::network_play::CsaClientState;
+use crate::settings_storage::AppSettings;
+use crate::tuning::TuningSession;
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+use ysu_core::events::{Event, SharedEventSink};
+use ysu_core::usi_info::UsiInfo;
+use ysu_core::EventSink;
+
+pub const ENGINE_HEALTH_PROGRESS: Event<HealthCheckProgress> = Event::new("engine-health-progress");
+pub const SETTINGS_CHANGED: Event<AppSettings> = Event::new("settings-changed");
+pub const TUNING_PROGRESS: Event<TuningSession> = Event::new("tuning-progress");
+pub const CSA_GAME_UPDATE: Event<CsaClientState> = Event::new("csa-game-update");
+/// Search info of the local engine during a CSA game
+pub const CSA_ENGINE_INFO: Event<UsiInfo> = Event::new("csa-engine-info");
+
+/// Emit a typed event to the webview
+pub fn send<P: Serialize>(app_handle: &AppHandle, event: &Event<P>, payload: &P) -> tauri::Result<()> {
+    app_handle.emit(&event.name, payload)
+}
+
+pub struct TauriEventSink {
+    app_handle: AppHandle,
+}
+
+impl TauriEventSink {
+    pub fn shared(app_handle: AppHandle) -> SharedEventSink {
+        Arc::new(Self { app_handle })
+    }
+}
+
+impl EventSink for TauriEventSink {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) -> Result<()> {
+        self.app_handle.emit(event, payload)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "ts"))]
+mod tests {
+    use super::*;
+    use ysu_core::events::typescript;
+
+    /// Writes the TypeScript definitions of every event to `src/types/generated`
+    #[test]
+    fn export_typescript() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../src/types/generated");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut bindings = typescript::bindings();
+        bindings.extend([
+            ENGINE_HEALTH_PROGRESS.binding(),
+            SETTINGS_CHANGED.binding(),
+            TUNING_PROGRESS.binding(),
+            CSA_GAME_UPDATE.binding(),
+            CSA_ENGINE_INFO.binding(),
+        ]);
+        typescript::write(&dir, &bindings).unwrap();
+    }
+}
@@ -0,0 +1,468 @@
+//! SPSA tuning of numeric engine options
+//! Each iteration perturbs every parameter by ±c_k, plays game pairs between the two
+//! perturbed versions of the engine through the match runner, and moves the parameters
+//! towards the side that scored better. Sessions are saved after every iteration so an
+//! interrupted run can be resumed where it stopped.
+
+use crate::adjudication::AdjudicationConfig;
+use crate::atomic_file;
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_vs_engine::{splitmix64, EngineVsEngineConfig, EngineVsEngineManager, SearchLimit};
+use crate::tauri_events::{self, TauriEventSink, TUNING_PROGRESS};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Gain schedule exponents recommended by Spall
+const ALPHA: f64 = 0.602;
+const GAMMA: f64 = 0.101;
+
+fn default_r_end() -> f64 {
+    0.002
+}
+
+fn default_games_per_iteration() -> u32 {
+    2
+}
+
+fn default_max_moves() -> usize {
+    256
+}
+
+/// A spin option being tuned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct TuningParameter {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub start: f64,
+    /// Perturbation size at the final iteration
+    pub c_end: f64,
+    /// Learning rate (a_end / c_end²) at the final iteration
+    #[serde(default = "default_r_end")]
+    pub r_end: f64,
+}
+
+impl TuningParameter {
+    /// Perturbation size c_k and learning rate R_k for iteration `k` (1-based) of `n`,
+    /// scaled so that they reach c_end and r_end at the last iteration
+    fn gains(&self, k: u32, n: u32) -> (f64, f64) {
+        let (k, n) = (k as f64, n as f64);
+        let stability = 0.1 * n;
+        let c = self.c_end * n.powf(GAMMA);
+        let a = self.r_end * self.c_end * self.c_end * (stability + n).powf(ALPHA);
+        let c_k = c / k.powf(GAMMA);
+        let a_k = a / (stability + k).powf(ALPHA);
+        (c_k, a_k / (c_k * c_k))
+    }
+
+    fn clamp(&self, value: f64) -> f64 {
+        value.clamp(self.min, self.max)
+    }
+}
+
+/// What to tune and how to play the games
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct TuningConfig {
+    /// Config ID of the engine whose options are tuned
+    pub engine_id: String,
+    pub params: Vec<TuningParameter>,
+    pub iterations: u32,
+    /// Games per iteration; played in pairs with colours swapped
+    #[serde(default = "default_games_per_iteration")]
+    pub games_per_iteration: u32,
+    pub time_per_move_ms: u64,
+    /// Fixed nodes or depth give far less noisy results than clock-based games
+    #[serde(default)]
+    pub search_limit: SearchLimit,
+    #[serde(default = "default_max_moves")]
+    pub max_moves: usize,
+    #[serde(default)]
+    pub adjudication: AdjudicationConfig,
+    /// Starting positions; both games of a pair use the same one
+    #[serde(default)]
+    pub openings: Vec<String>,
+}
+
+impl TuningConfig {
+    pub fn validate(&self, engine: &EngineConfig) -> Result<()> {
+        if self.params.is_empty() {
+            return Err(anyhow!("No parameters to tune"));
+        }
+        if self.iterations == 0 {
+            return Err(anyhow!("iterations must be at least 1"));
+        }
+        if self.games_per_iteration == 0 || self.games_per_iteration % 2 != 0 {
+            return Err(anyhow!("games_per_iteration must be a positive even number"));
+        }
+        self.search_limit.validate()?;
+        let options = engine.metadata.as_ref().map(|m| m.options.as_slice()).unwrap_or_default();
+        for param in &self.params {
+            if param.min >= param.max || !(param.min..=param.max).contains(&param.start) {
+                return Err(anyhow!("{}: start must lie within min < max", param.name));
+            }
+            if param.c_end <= 0.0 || param.r_end <= 0.0 {
+                return Err(anyhow!("{}: c_end and r_end must be positive", param.name));
+            }
+            match options.iter().find(|option| option.name == param.name) {
+                Some(option) if option.option_type == "spin" => {}
+                Some(option) => {
+                    return Err(anyhow!("{} is a {} option; only spin options can be tuned", param.name, option.option_type))
+                }
+                None => return Err(anyhow!("Engine has no option named {}", param.name)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum TuningStatus {
+    Running,
+    Paused,
+    Completed,
+    Failed,
+}
+
+/// Games played in one iteration, scored from the θ+ side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct TuningIteration {
+    pub iteration: u32,
+    pub plus: Vec<f64>,
+    pub minus: Vec<f64>,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    /// Parameter values after the update
+    pub theta: Vec<f64>,
+    pub match_ids: Vec<String>,
+}
+
+/// A tuning run and its progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct TuningSession {
+    pub id: String,
+    pub config: TuningConfig,
+    pub status: TuningStatus,
+    /// Completed iterations
+    pub iteration: u32,
+    /// Current parameter values, in the order of `config.params`
+    pub theta: Vec<f64>,
+    pub history: Vec<TuningIteration>,
+    /// Drives perturbation directions and opening choice, so a resumed run continues the same sequence
+    pub seed: u64,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl TuningSession {
+    pub fn new(config: TuningConfig) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            theta: config.params.iter().map(|param| param.start).collect(),
+            config,
+            status: TuningStatus::Paused,
+            iteration: 0,
+            history: Vec::new(),
+            seed: Uuid::new_v4().as_u64_pair().0,
+            error: None,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    /// Current values as `setoption` values (spin options take integers)
+    pub fn current_options(&self) -> HashMap<String, String> {
+        options_for(&self.config.params, &self.theta)
+    }
+
+    /// ±1 perturbation direction of each parameter for iteration `k`
+    fn flips(&self, k: u32) -> Vec<f64> {
+        (0..self.config.params.len())
+            .map(|i| {
+                let bits = splitmix64(self.seed ^ ((k as u64) << 32) ^ i as u64);
+                if bits & 1 == 0 { 1.0 } else { -1.0 }
+            })
+            .collect()
+    }
+}
+
+fn options_for(params: &[TuningParameter], values: &[f64]) -> HashMap<String, String> {
+    params
+        .iter()
+        .zip(values)
+        .map(|(param, value)| (param.name.clone(), format!("{}", value.round() as i64)))
+        .collect()
+}
+
+/// The θ+ and θ- values played against each other at iteration `k` of `n`
+pub fn perturb(params: &[TuningParameter], theta: &[f64], flips: &[f64], k: u32, n: u32) -> (Vec<f64>, Vec<f64>) {
+    params
+        .iter()
+        .zip(theta.iter().zip(flips))
+        .map(|(param, (&value, &flip))| {
+            let (c_k, _) = param.gains(k, n);
+            (param.clamp(value + c_k * flip), param.clamp(value - c_k * flip))
+        })
+        .unzip()
+}
+
+/// Move θ along the estimated gradient given θ+'s net score (wins - losses)
+pub fn spsa_update(params: &[TuningParameter], theta: &[f64], flips: &[f64], k: u32, n: u32, score: f64) -> Vec<f64> {
+    params
+        .iter()
+        .zip(theta.iter().zip(flips))
+        .map(|(param, (&value, &flip))| {
+            let (c_k, r_k) = param.gains(k, n);
+            param.clamp(value + r_k * c_k * score * flip)
+        })
+        .collect()
+}
+
+pub struct TuningStorage;
+
+impl TuningStorage {
+    /// Directory holding one `<id>.json` file per session
+    pub fn get_sessions_dir() -> Result<PathBuf> {
+        let dir = EngineStorage::get_config_dir()?.join("tuning");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    fn session_path(session_id: &str) -> Result<PathBuf> {
+        Uuid::parse_str(session_id).map_err(|_| anyhow!("Invalid tuning session ID: {}", session_id))?;
+        Ok(Self::get_sessions_dir()?.join(format!("{}.json", session_id)))
+    }
+
+    pub async fn save(session: &TuningSession) -> Result<()> {
+        let path = Self::session_path(&session.id)?;
+        let contents = serde_json::to_string_pretty(session)?;
+        atomic_file::write_atomic(&path, contents.as_bytes()).await
+    }
+
+    pub async fn load(session_id: &str) -> Result<TuningSession> {
+        let path = Self::session_path(session_id)?;
+        if !path.exists() {
+            return Err(anyhow!("Tuning session not found: {}", session_id));
+        }
+        let contents = tokio::fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// List all saved sessions, newest first. Unreadable files are skipped.
+    pub async fn list() -> Result<Vec<TuningSession>> {
+        let mut sessions = Vec::new();
+        let mut entries = tokio::fs::read_dir(Self::get_sessions_dir()?).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let parsed = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(serde_json::from_str::<TuningSession>(&contents)?));
+            match parsed {
+                Ok(session) => sessions.push(session),
+                Err(e) => log::warn!("Skipping unreadable tuning session {}: {}", path.display(), e),
+            }
+        }
+        sessions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(sessions)
+    }
+}
+
+/// Play one game and return θ+'s score: 1 for a win, -1 for a loss, 0 for a draw
+#[allow(clippy::too_many_arguments)]
+async fn play_game(
+    app_handle: &AppHandle,
+    engine_storage: &Arc<RwLock<EngineStorage>>,
+    engine: &EngineConfig,
+    config: &TuningConfig,
+    plus: HashMap<String, String>,
+    minus: HashMap<String, String>,
+    plus_is_black: bool,
+    seed: u64,
+) -> Result<(i32, String)> {
+    let (engine1_options, engine2_options) = if plus_is_black { (plus, minus) } else { (minus, plus) };
+    let (engine1_label, engine2_label) = if plus_is_black { ("θ+", "θ-") } else { ("θ-", "θ+") };
+    let match_config = EngineVsEngineConfig {
+        engine1_id: engine.id.clone(),
+        engine1_path: engine.path.clone(),
+        engine1_name: format!("{} {}", engine.name, engine1_label),
+        engine2_id: engine.id.clone(),
+        engine2_path: engine.path.clone(),
+        engine2_name: format!("{} {}", engine.name, engine2_label),
+        initial_sfen: None,
+        handicap: None,
+        time_per_move_ms: config.time_per_move_ms,
+        search_limit: config.search_limit,
+        max_moves: config.max_moves,
+        adjudication: config.adjudication.clone(),
+        cpu_affinity: None,
+        engine1_priority: engine.priority,
+        engine2_priority: engine.priority,
+        engine1_sha256: engine.binary_sha256.clone(),
+        engine2_sha256: engine.binary_sha256.clone(),
+        engine1_options: Some(engine1_options),
+        engine2_options: Some(engine2_options),
+        engine1_eval_file_id: None,
+        engine2_eval_file_id: None,
+        seed: Some(seed),
+        openings: config.openings.clone(),
+        // Tuning games are never watched
+        move_delay_ms: 0,
+        engine1_time_ms: None,
+        engine2_time_ms: None,
+        opening_moves: Vec::new(),
+        record_thinking: false,
+    };
+
+    let manager = EngineVsEngineManager::new(TauriEventSink::shared(app_handle.clone()), match_config, engine_storage.clone());
+    let final_state = manager.run_match().await?;
+    let plus_side = if plus_is_black { "black" } else { "white" };
+    let score = match final_state.winner.as_deref() {
+        Some("draw") | None => 0,
+        Some(side) if side == plus_side => 1,
+        Some(_) => -1,
+    };
+    Ok((score, final_state.match_id))
+}
+
+/// Run iterations until the session completes or `stop` is set, saving after each one
+/// and emitting `tuning-progress`
+pub async fn run_session(
+    app_handle: AppHandle,
+    engine_storage: Arc<RwLock<EngineStorage>>,
+    mut session: TuningSession,
+    stop: Arc<AtomicBool>,
+) -> TuningSession {
+    let result = run_iterations(&app_handle, &engine_storage, &mut session, &stop).await;
+    session.status = match result {
+        Ok(()) if session.iteration >= session.config.iterations => TuningStatus::Completed,
+        Ok(()) => TuningStatus::Paused,
+        Err(e) => {
+            log::error!("Tuning session {} failed: {}", session.id, e);
+            session.error = Some(e.to_string());
+            TuningStatus::Failed
+        }
+    };
+    session.updated_at = chrono::Utc::now().to_rfc3339();
+    if let Err(e) = TuningStorage::save(&session).await {
+        log::error!("Failed to save tuning session {}: {}", session.id, e);
+    }
+    let _ = tauri_events::send(&app_handle, &TUNING_PROGRESS, &session);
+    session
+}
+
+async fn run_iterations(
+    app_handle: &AppHandle,
+    engine_storage: &Arc<RwLock<EngineStorage>>,
+    session: &mut TuningSession,
+    stop: &AtomicBool,
+) -> Result<()> {
+    let engine = engine_storage
+        .read()
+        .await
+        .get_engine(&session.config.engine_id)
+        .cloned()
+        .ok_or_else(|| anyhow!("Engine not found: {}", session.config.engine_id))?;
+    session.config.validate(&engine)?;
+
+    let params = session.config.params.clone();
+    let n = session.config.iterations;
+    while session.iteration < n && !stop.load(Ordering::SeqCst) {
+        let k = session.iteration + 1;
+        let flips = session.flips(k);
+        let (plus, minus) = perturb(&params, &session.theta, &flips, k, n);
+        log::info!("Tuning session {} iteration {}/{}", session.id, k, n);
+
+        let (mut wins, mut losses, mut draws) = (0, 0, 0);
+        let mut match_ids = Vec::new();
+        for pair in 0..session.config.games_per_iteration / 2 {
+            let seed = splitmix64(session.seed ^ ((k as u64) << 16) ^ pair as u64);
+            for plus_is_black in [true, false] {
+                let (score, match_id) = play_game(
+                    app_handle,
+                    engine_storage,
+                    &engine,
+                    &session.config,
+                    options_for(&params, &plus),
+                    options_for(&params, &minus),
+                    plus_is_black,
+                    seed,
+                )
+                .await?;
+                match score {
+                    1 => wins += 1,
+                    -1 => losses += 1,
+                    _ => draws += 1,
+                }
+                match_ids.push(match_id);
+            }
+        }
+
+        let score = wins as f64 - losses as f64;
+        session.theta = spsa_update(&params, &session.theta, &flips, k, n, score);
+        session.history.push(TuningIteration {
+            iteration: k,
+            plus,
+            minus,
+            wins,
+            losses,
+            draws,
+            theta: session.theta.clone(),
+            match_ids,
+        });
+        session.iteration = k;
+        session.updated_at = chrono::Utc::now().to_rfc3339();
+        TuningStorage::save(session).await?;
+        let _ = tauri_events::send(app_handle, &TUNING_PROGRESS, &*session);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spsa_schedule_and_update() {
+        let param = TuningParameter {
+            name: "Aggressiveness".to_string(),
+            min: 0.0,
+            max: 200.0,
+            start: 100.0,
+            c_end: 10.0,
+            r_end: 0.002,
+        };
+        let (c_last, r_last) = param.gains(1000, 1000);
+        assert!((c_last - 10.0).abs() < 1e-9);
+        assert!((r_last - 0.002).abs() < 1e-9);
+        let (c_first, _) = param.gains(1, 1000);
+        assert!(c_first > c_last);
+
+        let params = [param];
+        let (plus, minus) = perturb(&params, &[100.0], &[-1.0], 1000, 1000);
+        assert_eq!((plus[0], minus[0]), (90.0, 110.0));
+        // θ+ won both games, so θ moves in θ+'s direction
+        let theta = spsa_update(&params, &[100.0], &[-1.0], 1000, 1000, 2.0);
+        assert!((theta[0] - 99.96).abs() < 1e-9);
+        assert_eq!(perturb(&params, &[195.0], &[1.0], 1000, 1000).0, vec![200.0]);
+        assert_eq!(options_for(&params, &[99.6])["Aggressiveness"], "100");
+    }
+}
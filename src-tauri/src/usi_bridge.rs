@@ -0,0 +1,354 @@
+//! Local bridge server that lets external tools drive managed engines
+//! Speaks newline-delimited JSON over TCP on 127.0.0.1. Each connection must send an `auth`
+//! request with the bridge token before anything else; engines it spawns are stopped when it closes.
+
+use crate::engine_manager::{EngineManager, InstancePurpose};
+use crate::engine_storage::EngineStorage;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, EventId, Listener};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch, RwLock};
+
+/// A request from a bridge client. `id` is echoed in the reply so clients can match them up.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BridgeRequest {
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    pub command: BridgeCommand,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeCommand {
+    Auth { token: String },
+    /// Configured engines
+    ListEngines,
+    /// Running engine instances
+    ListInstances,
+    /// Spawn an instance of a configured engine; its output is sent as `usi` events
+    Spawn { config_id: String },
+    /// Send a raw USI command to an instance spawned by this connection
+    Send { engine_id: String, command: String },
+    Stop { engine_id: String },
+}
+
+/// Everything the server writes: replies to requests and engine output events
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum BridgeMessage {
+    Reply {
+        id: Option<serde_json::Value>,
+        ok: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        data: Option<serde_json::Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Event {
+        event: &'static str,
+        engine_id: String,
+        line: String,
+    },
+}
+
+impl BridgeMessage {
+    fn ok(id: Option<serde_json::Value>, data: Option<serde_json::Value>) -> Self {
+        BridgeMessage::Reply { id, ok: true, data, error: None }
+    }
+
+    fn error(id: Option<serde_json::Value>, error: String) -> Self {
+        BridgeMessage::Reply { id, ok: false, data: None, error: Some(error) }
+    }
+}
+
+/// Bridge status reported to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+    pub connections: usize,
+}
+
+/// A running bridge server. Dropping the handle does not stop it; call `stop`.
+pub struct BridgeHandle {
+    pub port: u16,
+    connections: Arc<AtomicUsize>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl BridgeHandle {
+    pub fn status(&self) -> BridgeStatus {
+        BridgeStatus {
+            running: true,
+            port: Some(self.port),
+            connections: self.connections.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stop accepting connections and close the open ones
+    pub fn stop(&self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+/// What a connection handler needs from the app
+#[derive(Clone)]
+struct BridgeContext {
+    app_handle: AppHandle,
+    engine_manager: Arc<EngineManager>,
+    engine_storage: Arc<RwLock<EngineStorage>>,
+    token: Arc<String>,
+}
+
+/// Compare tokens without bailing out at the first differing byte
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected.bytes().zip(given.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Bind the bridge on 127.0.0.1:`port` (0 picks a free port) and serve until stopped
+pub async fn start(
+    app_handle: AppHandle,
+    engine_manager: Arc<EngineManager>,
+    engine_storage: Arc<RwLock<EngineStorage>>,
+    port: u16,
+    token: String,
+) -> Result<BridgeHandle> {
+    if token.is_empty() {
+        return Err(anyhow!("Bridge token must not be empty"));
+    }
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| anyhow!("Failed to bind bridge on port {}: {}", port, e))?;
+    let port = listener.local_addr()?.port();
+    log::info!("USI bridge listening on 127.0.0.1:{}", port);
+
+    let (shutdown, mut shutdown_rx) = watch::channel(false);
+    let connections = Arc::new(AtomicUsize::new(0));
+    let context = BridgeContext {
+        app_handle,
+        engine_manager,
+        engine_storage,
+        token: Arc::new(token),
+    };
+
+    let open = connections.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, addr)) => {
+                        log::info!("USI bridge connection from {}", addr);
+                        let context = context.clone();
+                        let open = open.clone();
+                        let shutdown = shutdown_rx.clone();
+                        tokio::spawn(async move {
+                            open.fetch_add(1, Ordering::Relaxed);
+                            if let Err(e) = serve_connection(stream, context, shutdown).await {
+                                log::warn!("USI bridge connection from {} ended: {}", addr, e);
+                            }
+                            open.fetch_sub(1, Ordering::Relaxed);
+                        });
+                    }
+                    Err(e) => log::warn!("USI bridge accept failed: {}", e),
+                },
+                _ = shutdown_rx.changed() => break,
+            }
+        }
+        log::info!("USI bridge on port {} stopped", port);
+    });
+
+    Ok(BridgeHandle { port, connections, shutdown })
+}
+
+/// Per-connection state: whether it has authenticated and which engines it owns
+struct Session {
+    context: BridgeContext,
+    authenticated: bool,
+    /// Engines spawned by this connection, with the listener forwarding their output
+    engines: HashMap<String, EventId>,
+    out: mpsc::UnboundedSender<BridgeMessage>,
+}
+
+impl Session {
+    async fn handle(&mut self, request: BridgeRequest) -> BridgeMessage {
+        let id = request.id;
+        if !self.authenticated {
+            return match request.command {
+                BridgeCommand::Auth { token } if token_matches(&self.context.token, &token) => {
+                    self.authenticated = true;
+                    BridgeMessage::ok(id, None)
+                }
+                BridgeCommand::Auth { .. } => BridgeMessage::error(id, "Invalid token".to_string()),
+                _ => BridgeMessage::error(id, "Not authenticated".to_string()),
+            };
+        }
+        match self.execute(request.command).await {
+            Ok(data) => BridgeMessage::ok(id, data),
+            Err(e) => BridgeMessage::error(id, e.to_string()),
+        }
+    }
+
+    async fn execute(&mut self, command: BridgeCommand) -> Result<Option<serde_json::Value>> {
+        match command {
+            BridgeCommand::Auth { .. } => Ok(None),
+            BridgeCommand::ListEngines => {
+                let storage = self.context.engine_storage.read().await;
+                let engines: Vec<serde_json::Value> = storage
+                    .engines
+                    .iter()
+                    .map(|engine| serde_json::json!({ "id": engine.id, "name": engine.display_name }))
+                    .collect();
+                Ok(Some(serde_json::Value::Array(engines)))
+            }
+            BridgeCommand::ListInstances => {
+                let instances = self.context.engine_manager.list_instances(None).await;
+                Ok(Some(serde_json::to_value(instances)?))
+            }
+            BridgeCommand::Spawn { config_id } => {
+                let engine = self
+                    .context
+                    .engine_storage
+                    .read()
+                    .await
+                    .get_engine(&config_id)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Engine not found: {}", config_id))?;
+                let runtime_id = self
+                    .context
+                    .engine_manager
+                    .spawn_engine(&config_id, engine.name.clone(), engine.path.clone(), InstancePurpose::Other, engine.priority)
+                    .await?
+                    .to_string();
+
+                let out = self.out.clone();
+                let engine_id = runtime_id.clone();
+                let listener = self.context.app_handle.listen(format!("usi-message::{}", runtime_id), move |event| {
+                    if let Ok(line) = serde_json::from_str::<String>(event.payload()) {
+                        let _ = out.send(BridgeMessage::Event { event: "usi", engine_id: engine_id.clone(), line });
+                    }
+                });
+                self.engines.insert(runtime_id.clone(), listener);
+                Ok(Some(serde_json::json!({ "engine_id": runtime_id })))
+            }
+            BridgeCommand::Send { engine_id, command } => {
+                self.owned(&engine_id)?;
+                self.context.engine_manager.send_command(&engine_id, &command).await?;
+                Ok(None)
+            }
+            BridgeCommand::Stop { engine_id } => {
+                self.owned(&engine_id)?;
+                self.release(&engine_id).await;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Clients may only drive the engines they spawned, never the GUI's own
+    fn owned(&self, engine_id: &str) -> Result<()> {
+        if self.engines.contains_key(engine_id) {
+            Ok(())
+        } else {
+            Err(anyhow!("Engine {} was not spawned by this connection", engine_id))
+        }
+    }
+
+    async fn release(&mut self, engine_id: &str) {
+        if let Some(listener) = self.engines.remove(engine_id) {
+            self.context.app_handle.unlisten(listener);
+        }
+        if let Err(e) = self.context.engine_manager.stop_engine(engine_id).await {
+            log::warn!("Failed to stop bridge engine {}: {}", engine_id, e);
+        }
+    }
+}
+
+async fn serve_connection(stream: TcpStream, context: BridgeContext, mut shutdown: watch::Receiver<bool>) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let (out, mut outgoing) = mpsc::unbounded_channel::<BridgeMessage>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(message) = outgoing.recv().await {
+            let Ok(mut line) = serde_json::to_string(&message) else { continue };
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session {
+        context,
+        authenticated: false,
+        engines: HashMap::new(),
+        out: out.clone(),
+    };
+    let mut lines = BufReader::new(reader).lines();
+    let result = loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line,
+            _ = shutdown.changed() => break Ok(()),
+        };
+        let line = match line {
+            Ok(Some(line)) => line,
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e.into()),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<BridgeRequest>(&line) {
+            Ok(request) => session.handle(request).await,
+            Err(e) => BridgeMessage::error(None, format!("Invalid request: {}", e)),
+        };
+        if out.send(reply).is_err() {
+            break Ok(());
+        }
+    };
+
+    let engine_ids: Vec<String> = session.engines.keys().cloned().collect();
+    for engine_id in engine_ids {
+        session.release(&engine_id).await;
+    }
+    drop(session);
+    drop(out);
+    let _ = writer_task.await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_and_messages() {
+        let request: BridgeRequest =
+            serde_json::from_str(r#"{"id": 3, "type": "send", "engine_id": "e:1", "command": "go byoyomi 1000"}"#).unwrap();
+        assert_eq!(request.id, Some(serde_json::json!(3)));
+        assert_eq!(
+            request.command,
+            BridgeCommand::Send { engine_id: "e:1".to_string(), command: "go byoyomi 1000".to_string() }
+        );
+        let request: BridgeRequest = serde_json::from_str(r#"{"type": "list_engines"}"#).unwrap();
+        assert_eq!(request.command, BridgeCommand::ListEngines);
+        assert!(serde_json::from_str::<BridgeRequest>(r#"{"type": "format_disk"}"#).is_err());
+
+        assert_eq!(
+            serde_json::to_string(&BridgeMessage::error(Some(serde_json::json!("a")), "Not authenticated".to_string())).unwrap(),
+            r#"{"id":"a","ok":false,"error":"Not authenticated"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&BridgeMessage::Event { event: "usi", engine_id: "e:1".to_string(), line: "readyok".to_string() }).unwrap(),
+            r#"{"event":"usi","engine_id":"e:1","line":"readyok"}"#
+        );
+
+        assert!(token_matches("secret", "secret"));
+        assert!(!token_matches("secret", "secreT"));
+        assert!(!token_matches("secret", "secret2"));
+    }
+}
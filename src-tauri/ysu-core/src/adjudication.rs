@@ -0,0 +1,139 @@
+//! Score-based match adjudication, modelled on cutechess-cli's `-resign` and `-draw` options
+//! Saves long self-play runs from playing out hopeless or dead-drawn positions
+
+use crate::shogi_rules::Color;
+use crate::usi_info::Score;
+use serde::{Deserialize, Serialize};
+
+/// Adjudicate a win when both engines agree one side is ahead by at least `score_cp`
+/// for `move_count` consecutive moves each
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ResignAdjudication {
+    pub score_cp: i32,
+    pub move_count: usize,
+}
+
+/// Adjudicate a draw once `move_number` plies have been played and both engines report
+/// a score within ±`score_cp` for `move_count` consecutive moves each
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct DrawAdjudication {
+    #[serde(default)]
+    pub move_number: usize,
+    pub score_cp: i32,
+    pub move_count: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct AdjudicationConfig {
+    #[serde(default)]
+    pub resign: Option<ResignAdjudication>,
+    #[serde(default)]
+    pub draw: Option<DrawAdjudication>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjudication {
+    Win(Color),
+    Draw,
+}
+
+/// Tracks reported scores over a game and decides when to adjudicate
+#[derive(Debug)]
+pub struct Adjudicator {
+    config: AdjudicationConfig,
+    /// Score for each ply from Black's point of view; None if the engine reported none
+    scores: Vec<Option<i32>>,
+}
+
+impl Adjudicator {
+    pub fn new(config: AdjudicationConfig) -> Self {
+        Self {
+            config,
+            scores: Vec::new(),
+        }
+    }
+
+    /// Record the score the mover reported for the move it just played
+    pub fn record(&mut self, mover: Color, score: Option<Score>) -> Option<Adjudication> {
+        let score = score.map(|score| match mover {
+            Color::Black => score,
+            Color::White => score.negate(),
+        });
+        self.scores.push(score.map(Score::centipawns));
+
+        if let Some(resign) = &self.config.resign {
+            if let Some(window) = self.window(resign.move_count) {
+                if window.iter().all(|&cp| cp >= resign.score_cp) {
+                    return Some(Adjudication::Win(Color::Black));
+                }
+                if window.iter().all(|&cp| cp <= -resign.score_cp) {
+                    return Some(Adjudication::Win(Color::White));
+                }
+            }
+        }
+
+        if let Some(draw) = &self.config.draw {
+            if self.scores.len() >= draw.move_number {
+                if let Some(window) = self.window(draw.move_count) {
+                    if window.iter().all(|&cp| cp.abs() <= draw.score_cp) {
+                        return Some(Adjudication::Draw);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The last `move_count` moves of both engines, if all of them carried a score
+    fn window(&self, move_count: usize) -> Option<Vec<i32>> {
+        let plies = move_count.max(1) * 2;
+        if self.scores.len() < plies {
+            return None;
+        }
+        self.scores[self.scores.len() - plies..].iter().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resign_and_draw_adjudication() {
+        let mut adjudicator = Adjudicator::new(AdjudicationConfig {
+            resign: Some(ResignAdjudication { score_cp: 1000, move_count: 2 }),
+            draw: None,
+        });
+        // Black thinks it is winning, White agrees it is losing
+        assert_eq!(adjudicator.record(Color::Black, Some(Score::Cp(1200))), None);
+        assert_eq!(adjudicator.record(Color::White, Some(Score::Cp(-1500))), None);
+        assert_eq!(adjudicator.record(Color::Black, Some(Score::Mate(9))), None);
+        // One engine disagreeing breaks the streak
+        assert_eq!(adjudicator.record(Color::White, Some(Score::Cp(-200))), None);
+        assert_eq!(adjudicator.record(Color::Black, Some(Score::Cp(1300))), None);
+        assert_eq!(adjudicator.record(Color::White, Some(Score::Cp(-1100))), None);
+        assert_eq!(adjudicator.record(Color::Black, Some(Score::Cp(1400))), None);
+        assert_eq!(
+            adjudicator.record(Color::White, Some(Score::Cp(-1200))),
+            Some(Adjudication::Win(Color::Black))
+        );
+
+        let mut adjudicator = Adjudicator::new(AdjudicationConfig {
+            resign: None,
+            draw: Some(DrawAdjudication { move_number: 4, score_cp: 20, move_count: 1 }),
+        });
+        assert_eq!(adjudicator.record(Color::Black, Some(Score::Cp(5))), None);
+        assert_eq!(adjudicator.record(Color::White, Some(Score::Cp(-5))), None);
+        assert_eq!(adjudicator.record(Color::Black, None), None);
+        assert_eq!(
+            adjudicator.record(Color::White, Some(Score::Cp(10))),
+            None,
+            "a missing score must not count towards the window"
+        );
+        assert_eq!(adjudicator.record(Color::Black, Some(Score::Cp(0))), Some(Adjudication::Draw));
+    }
+}
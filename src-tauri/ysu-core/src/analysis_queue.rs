@@ -0,0 +1,404 @@
+//! Background queue of analysis jobs
+//! Games and positions are queued with an engine, search limit and priority and analyzed by a
+//! fixed number of workers without the UI driving each step. The queue is saved to
+//! `analysis_queue.json` on every change; jobs that were running when the app exited are
+//! queued again on the next start. Every change of a job is emitted as `analysis-job-update`.
+
+use crate::atomic_file;
+use crate::engine_process;
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_vs_engine::{spawn_initialized_engine, EngineVsEngineManager, SearchLimit};
+use crate::events::{SharedEventSink, ANALYSIS_JOB_UPDATE};
+use crate::game_record::GameRecordStorage;
+use crate::shogi_rules::{Move, Position};
+use crate::usi_info::Score;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, Notify, RwLock};
+use uuid::Uuid;
+
+/// What a job analyzes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum AnalysisTarget {
+    Position { sfen: String },
+    /// Every position of a saved game record, from the initial one to the last
+    Game { record_id: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+/// The engine's verdict on one position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct PositionAnalysis {
+    /// Moves played from the job's initial position
+    pub ply: usize,
+    pub sfen: String,
+    pub best_move: String,
+    /// From the point of view of the side to move
+    pub score: Option<Score>,
+    pub depth: Option<u32>,
+    pub pv: Vec<String>,
+}
+
+/// What to enqueue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisJobRequest {
+    /// Engine config ID; empty for the default analysis engine
+    #[serde(default)]
+    pub engine_id: String,
+    pub target: AnalysisTarget,
+    pub limit: SearchLimit,
+    /// Higher runs first; equal priorities run in the order they were queued
+    #[serde(default)]
+    pub priority: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct AnalysisJob {
+    pub id: String,
+    pub engine_id: String,
+    pub target: AnalysisTarget,
+    pub limit: SearchLimit,
+    pub priority: i32,
+    pub status: JobStatus,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// Positions to analyze, known once the job has started
+    pub total_positions: Option<usize>,
+    pub results: Vec<PositionAnalysis>,
+    pub error: Option<String>,
+}
+
+/// Positions of a target, with the number of moves played to reach each
+async fn target_positions(target: &AnalysisTarget) -> Result<Vec<(usize, String)>> {
+    match target {
+        AnalysisTarget::Position { sfen } => Ok(vec![(0, Position::from_sfen(sfen)?.to_sfen())]),
+        AnalysisTarget::Game { record_id } => {
+            let record = GameRecordStorage::load(record_id).await?;
+            let mut position = Position::from_sfen(&record.initial_sfen)?;
+            let mut positions = vec![(0, position.to_sfen())];
+            for (index, recorded) in record.moves.iter().enumerate() {
+                position.apply_move(&Move::from_usi(&recorded.usi)?)?;
+                positions.push((index + 1, position.to_sfen()));
+            }
+            Ok(positions)
+        }
+    }
+}
+
+/// The job to run next: highest priority, then oldest
+fn next_queued(jobs: &[AnalysisJob]) -> Option<usize> {
+    jobs.iter()
+        .enumerate()
+        .filter(|(_, job)| job.status == JobStatus::Queued)
+        .max_by(|(_, a), (_, b)| a.priority.cmp(&b.priority).then(b.created_at.cmp(&a.created_at)))
+        .map(|(index, _)| index)
+}
+
+pub struct AnalysisQueue {
+    jobs: Mutex<Vec<AnalysisJob>>,
+    /// Cancel flags of running jobs, by job ID
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    wake: Notify,
+    /// Where the queue is saved; None keeps it in memory only
+    path: Option<PathBuf>,
+    events: SharedEventSink,
+}
+
+impl AnalysisQueue {
+    pub fn get_storage_path() -> Result<PathBuf> {
+        Ok(EngineStorage::get_config_dir()?.join("analysis_queue.json"))
+    }
+
+    fn with_jobs(jobs: Vec<AnalysisJob>, path: Option<PathBuf>, events: SharedEventSink) -> Arc<Self> {
+        Arc::new(Self {
+            jobs: Mutex::new(jobs),
+            running: Mutex::new(HashMap::new()),
+            wake: Notify::new(),
+            path,
+            events,
+        })
+    }
+
+    /// A queue that is not saved to disk
+    pub fn in_memory(events: SharedEventSink) -> Arc<Self> {
+        Self::with_jobs(Vec::new(), None, events)
+    }
+
+    /// Load the saved queue, queueing interrupted jobs again from the start
+    pub async fn load(events: SharedEventSink) -> Result<Arc<Self>> {
+        let path = Self::get_storage_path()?;
+        let mut jobs: Vec<AnalysisJob> = if path.exists() {
+            serde_json::from_str(&tokio::fs::read_to_string(&path).await?)?
+        } else {
+            Vec::new()
+        };
+        for job in jobs.iter_mut().filter(|job| job.status == JobStatus::Running) {
+            log::info!("Requeueing interrupted analysis job {}", job.id);
+            job.status = JobStatus::Queued;
+            job.started_at = None;
+            job.results.clear();
+        }
+        Ok(Self::with_jobs(jobs, Some(path), events))
+    }
+
+    async fn save(&self, jobs: &[AnalysisJob]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let written = match serde_json::to_string_pretty(jobs) {
+            Ok(contents) => atomic_file::write_atomic(path, contents.as_bytes()).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = written {
+            log::error!("Failed to save analysis queue: {}", e);
+        }
+    }
+
+    /// Apply `change` to a job, then save the queue and emit the job
+    async fn update(&self, job_id: &str, change: impl FnOnce(&mut AnalysisJob)) -> Option<AnalysisJob> {
+        let mut jobs = self.jobs.lock().await;
+        let job = jobs.iter_mut().find(|job| job.id == job_id)?;
+        change(job);
+        let job = job.clone();
+        self.save(&jobs).await;
+        drop(jobs);
+        let _ = self.events.send(&ANALYSIS_JOB_UPDATE, &job);
+        Some(job)
+    }
+
+    pub async fn enqueue(&self, request: AnalysisJobRequest) -> Result<AnalysisJob> {
+        request.limit.validate()?;
+        if request.limit == SearchLimit::Clock {
+            return Err(anyhow!("Analysis needs a depth, nodes or movetime limit"));
+        }
+        if let AnalysisTarget::Position { sfen } = &request.target {
+            Position::from_sfen(sfen)?;
+        }
+        let job = AnalysisJob {
+            id: Uuid::new_v4().to_string(),
+            engine_id: request.engine_id,
+            target: request.target,
+            limit: request.limit,
+            priority: request.priority,
+            status: JobStatus::Queued,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            started_at: None,
+            finished_at: None,
+            total_positions: None,
+            results: Vec::new(),
+            error: None,
+        };
+        let mut jobs = self.jobs.lock().await;
+        jobs.push(job.clone());
+        self.save(&jobs).await;
+        drop(jobs);
+        let _ = self.events.send(&ANALYSIS_JOB_UPDATE, &job);
+        self.wake.notify_one();
+        Ok(job)
+    }
+
+    /// Cancel a queued job, or stop a running one after its current position
+    pub async fn cancel(&self, job_id: &str) -> Result<()> {
+        if let Some(cancel) = self.running.lock().await.get(job_id) {
+            cancel.store(true, Ordering::SeqCst);
+            return Ok(());
+        }
+        let status = self.get(job_id).await.map(|job| job.status);
+        match status {
+            Some(JobStatus::Queued) => {
+                self.update(job_id, |job| {
+                    job.status = JobStatus::Cancelled;
+                    job.finished_at = Some(chrono::Utc::now().to_rfc3339());
+                })
+                .await;
+                Ok(())
+            }
+            Some(status) => Err(anyhow!("Analysis job {} is already {:?}", job_id, status)),
+            None => Err(anyhow!("Analysis job not found: {}", job_id)),
+        }
+    }
+
+    pub async fn get(&self, job_id: &str) -> Option<AnalysisJob> {
+        self.jobs.lock().await.iter().find(|job| job.id == job_id).cloned()
+    }
+
+    /// All jobs in the order they were queued
+    pub async fn list(&self) -> Vec<AnalysisJob> {
+        self.jobs.lock().await.clone()
+    }
+
+    /// Drop finished jobs from the queue, returning how many were removed
+    pub async fn clear_finished(&self) -> usize {
+        let mut jobs = self.jobs.lock().await;
+        let before = jobs.len();
+        jobs.retain(|job| !job.status.is_finished());
+        let removed = before - jobs.len();
+        self.save(&jobs).await;
+        removed
+    }
+
+    /// Start `workers` background tasks that run queued jobs until the app exits
+    pub fn start(self: &Arc<Self>, engine_storage: Arc<RwLock<EngineStorage>>, workers: usize) {
+        for _ in 0..workers.max(1) {
+            let queue = self.clone();
+            let engine_storage = engine_storage.clone();
+            tokio::spawn(async move { queue.work(engine_storage).await });
+        }
+    }
+
+    async fn take_next(&self) -> Option<AnalysisJob> {
+        let mut jobs = self.jobs.lock().await;
+        let index = next_queued(&jobs)?;
+        let job = &mut jobs[index];
+        job.status = JobStatus::Running;
+        job.started_at = Some(chrono::Utc::now().to_rfc3339());
+        let job = job.clone();
+        self.save(&jobs).await;
+        drop(jobs);
+        let _ = self.events.send(&ANALYSIS_JOB_UPDATE, &job);
+        Some(job)
+    }
+
+    async fn work(&self, engine_storage: Arc<RwLock<EngineStorage>>) {
+        loop {
+            let Some(job) = self.take_next().await else {
+                // Woken on enqueue; the timeout covers a wakeup taken by another worker
+                let _ = tokio::time::timeout(Duration::from_secs(5), self.wake.notified()).await;
+                continue;
+            };
+            let cancel = Arc::new(AtomicBool::new(false));
+            self.running.lock().await.insert(job.id.clone(), cancel.clone());
+            log::info!("Running analysis job {} with engine {}", job.id, job.engine_id);
+
+            let outcome = self.run_job(&job, &engine_storage, &cancel).await;
+            self.running.lock().await.remove(&job.id);
+            let status = match &outcome {
+                Ok(()) if cancel.load(Ordering::SeqCst) => JobStatus::Cancelled,
+                Ok(()) => JobStatus::Completed,
+                Err(e) => {
+                    log::warn!("Analysis job {} failed: {}", job.id, e);
+                    JobStatus::Failed
+                }
+            };
+            self.update(&job.id, |job| {
+                job.status = status;
+                job.error = outcome.err().map(|e| e.to_string());
+                job.finished_at = Some(chrono::Utc::now().to_rfc3339());
+            })
+            .await;
+        }
+    }
+
+    async fn run_job(&self, job: &AnalysisJob, engine_storage: &RwLock<EngineStorage>, cancel: &AtomicBool) -> Result<()> {
+        let engine: EngineConfig = engine_storage
+            .read()
+            .await
+            .get_engine(&job.engine_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Engine not found: {}", job.engine_id))?;
+        let positions = target_positions(&job.target).await?;
+        self.update(&job.id, |job| job.total_positions = Some(positions.len())).await;
+
+        let (mut child, mut stdin, mut stdout) = spawn_initialized_engine(&engine, engine_storage).await?;
+        stdin.write_all(b"usinewgame\n").await?;
+        let go_cmd = job.limit.go_command(0);
+        let move_timeout = job.limit.move_timeout(0);
+
+        let mut outcome = Ok(());
+        for (ply, sfen) in positions {
+            if cancel.load(Ordering::SeqCst) {
+                break;
+            }
+            match EngineVsEngineManager::request_move(&mut stdin, &mut stdout, &sfen, &[], &go_cmd, move_timeout, |_, _| {}).await {
+                Ok(engine_move) => {
+                    let info = engine_move.info.unwrap_or_default();
+                    let analysis = PositionAnalysis {
+                        ply,
+                        sfen,
+                        best_move: engine_move.best_move,
+                        score: info.score,
+                        depth: info.depth,
+                        pv: info.pv,
+                    };
+                    self.update(&job.id, |job| job.results.push(analysis)).await;
+                }
+                Err(e) => {
+                    outcome = Err(e);
+                    break;
+                }
+            }
+        }
+
+        let _ = stdin.write_all(b"quit\n").await;
+        let _ = stdin.flush().await;
+        engine_process::kill(&mut child).await;
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::RecordingEventSink;
+
+    fn request(priority: i32) -> AnalysisJobRequest {
+        AnalysisJobRequest {
+            engine_id: "engine".to_string(),
+            target: AnalysisTarget::Position { sfen: "startpos".to_string() },
+            limit: SearchLimit::Depth { depth: 10 },
+            priority,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_order_and_cancel() {
+        let recorder = RecordingEventSink::new();
+        let queue = AnalysisQueue::in_memory(recorder.clone());
+        let low = queue.enqueue(request(0)).await.unwrap();
+        let high = queue.enqueue(request(5)).await.unwrap();
+        let low_later = queue.enqueue(request(0)).await.unwrap();
+
+        let jobs = queue.list().await;
+        assert_eq!(jobs[next_queued(&jobs).unwrap()].id, high.id);
+        queue.cancel(&high.id).await.unwrap();
+        let jobs = queue.list().await;
+        assert_eq!(jobs[next_queued(&jobs).unwrap()].id, low.id);
+        assert!(queue.cancel(&high.id).await.is_err());
+        assert_eq!(recorder.payloads("analysis-job-update").len(), 4);
+
+        assert_eq!(queue.clear_finished().await, 1);
+        assert_eq!(queue.list().await.len(), 2);
+        assert!(queue.get(&low_later.id).await.is_some());
+
+        let mut clock = request(0);
+        clock.limit = SearchLimit::Clock;
+        assert!(queue.enqueue(clock).await.is_err());
+    }
+}
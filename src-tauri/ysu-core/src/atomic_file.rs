@@ -0,0 +1,71 @@
+//! Crash-safe file writes with rotating backups
+
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+fn sibling(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))?;
+    Ok(path.with_file_name(format!("{}{}", name, suffix)))
+}
+
+/// Write to a temporary file next to `path`, flush it to disk and rename it over `path`,
+/// so a crash leaves either the old or the new contents but never a partial file
+pub async fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = sibling(path, ".tmp")?;
+    let mut file = tokio::fs::File::create(&tmp).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    drop(file);
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Rotating backup paths of `path`, newest first: `<name>.1.bak` … `<name>.<keep>.bak`
+pub fn backup_paths(path: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    (1..=keep).map(|n| sibling(path, &format!(".{}.bak", n))).collect()
+}
+
+/// Shift existing backups down by one and copy the current file in as the newest backup
+pub async fn rotate_backups(path: &Path, keep: usize) -> Result<()> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+    let backups = backup_paths(path, keep)?;
+    for index in (1..backups.len()).rev() {
+        if backups[index - 1].exists() {
+            tokio::fs::rename(&backups[index - 1], &backups[index]).await?;
+        }
+    }
+    tokio::fs::copy(path, &backups[0]).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_atomic_write_and_rotation() {
+        let dir = std::env::temp_dir().join(format!("shogi-vibe-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.json");
+
+        for n in 1..=4 {
+            rotate_backups(&path, 2).await.unwrap();
+            write_atomic(&path, format!("v{}", n).as_bytes()).await.unwrap();
+        }
+
+        let backups = backup_paths(&path, 2).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "v4");
+        assert_eq!(std::fs::read_to_string(&backups[0]).unwrap(), "v3");
+        assert_eq!(std::fs::read_to_string(&backups[1]).unwrap(), "v2");
+        assert!(!dir.join("data.json.3.bak").exists());
+        assert!(!dir.join("data.json.tmp").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+//! Auto-save of games in progress
+//! Game sessions and engine-vs-engine matches are written to `autosave/<id>.json` while they run
+//! and removed when they end, so any file left at startup belongs to a game interrupted by a
+//! crash or an accidental close.
+
+use crate::atomic_file;
+use crate::engine_storage::EngineStorage;
+use crate::engine_vs_engine::{EngineVsEngineConfig, EngineVsEngineState};
+use crate::game_session::{GameSession, GameSessionStore};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// What was saved: a session with its move tree and clocks, or a match with its config
+/// and state (moves and move times). The payloads are boxed to keep the enum small; they
+/// serialize the same as unboxed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SavedGame {
+    Session { session: Box<GameSession> },
+    Match { config: Box<EngineVsEngineConfig>, state: Box<EngineVsEngineState> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutosaveEntry {
+    /// Session or match ID
+    pub id: String,
+    pub saved_at: String,
+    #[serde(flatten)]
+    pub game: SavedGame,
+}
+
+impl AutosaveEntry {
+    pub fn session(session: &GameSession) -> Self {
+        Self {
+            id: session.id.clone(),
+            saved_at: chrono::Utc::now().to_rfc3339(),
+            game: SavedGame::Session { session: Box::new(session.clone()) },
+        }
+    }
+
+    pub fn match_state(config: &EngineVsEngineConfig, state: &EngineVsEngineState) -> Self {
+        Self {
+            id: state.match_id.clone(),
+            saved_at: chrono::Utc::now().to_rfc3339(),
+            game: SavedGame::Match {
+                config: Box::new(config.clone()),
+                state: Box::new(state.clone()),
+            },
+        }
+    }
+}
+
+pub struct AutosaveStorage;
+
+impl AutosaveStorage {
+    pub fn get_autosave_dir() -> Result<PathBuf> {
+        let dir = EngineStorage::get_config_dir()?.join("autosave");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    fn entry_path(id: &str) -> Result<PathBuf> {
+        // Session and match IDs are UUIDs; anything else could escape the directory
+        Uuid::parse_str(id).map_err(|_| anyhow!("Invalid game ID: {}", id))?;
+        Ok(Self::get_autosave_dir()?.join(format!("{}.json", id)))
+    }
+
+    pub async fn save(entry: &AutosaveEntry) -> Result<()> {
+        let path = Self::entry_path(&entry.id)?;
+        atomic_file::write_atomic(&path, serde_json::to_string(entry)?.as_bytes()).await
+    }
+
+    pub async fn load(id: &str) -> Result<AutosaveEntry> {
+        let path = Self::entry_path(id)?;
+        if !path.exists() {
+            return Err(anyhow!("No auto-saved game: {}", id));
+        }
+        Ok(serde_json::from_str(&tokio::fs::read_to_string(&path).await?)?)
+    }
+
+    /// Remove the save of a game that ended; a missing file is not an error
+    pub async fn remove(id: &str) -> Result<()> {
+        let path = Self::entry_path(id)?;
+        if path.exists() {
+            tokio::fs::remove_file(&path).await?;
+        }
+        Ok(())
+    }
+
+    /// All saved games, most recently saved first. Unreadable files are skipped.
+    pub async fn list() -> Result<Vec<AutosaveEntry>> {
+        let mut entries = Vec::new();
+        let mut dir = tokio::fs::read_dir(Self::get_autosave_dir()?).await?;
+        while let Some(file) = dir.next_entry().await? {
+            let path = file.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let parsed = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(serde_json::from_str::<AutosaveEntry>(&contents)?));
+            match parsed {
+                Ok(entry) => entries.push(entry),
+                Err(e) => log::warn!("Skipping unreadable auto-save {}: {}", path.display(), e),
+            }
+        }
+        entries.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+        Ok(entries)
+    }
+}
+
+/// Save every active session. The store stays locked while writing so a session that ends
+/// meanwhile cannot be written back after its save was removed. Returns the number saved.
+pub async fn save_sessions(sessions: &RwLock<GameSessionStore>) -> usize {
+    let sessions = sessions.read().await;
+    let mut saved = 0;
+    for session in sessions.iter() {
+        match AutosaveStorage::save(&AutosaveEntry::session(session)).await {
+            Ok(()) => saved += 1,
+            Err(e) => log::warn!("Failed to auto-save game session {}: {}", session.id, e),
+        }
+    }
+    saved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_roundtrip() {
+        let mut session = GameSession::new(None, None, None).unwrap();
+        session.apply_move("7g7f").unwrap();
+        let json = serde_json::to_value(AutosaveEntry::session(&session)).unwrap();
+        assert_eq!(json["kind"], "session");
+        assert_eq!(json["id"], session.id.as_str());
+
+        let entry: AutosaveEntry = serde_json::from_value(json).unwrap();
+        let SavedGame::Session { session: restored } = entry.game else {
+            panic!("expected a session");
+        };
+        assert_eq!(restored.move_history(), vec!["7g7f"]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Evaluating many positions with one engine process
+//! Labeling a dataset or scoring an opening book needs a score and best move for thousands
+//! of positions, and spawning an engine for each would spend most of the time starting it and
+//! loading its eval file. Here the engine is started and made ready once, gets a single
+//! `usinewgame` and then searches the positions one after another.
+
+use crate::engine_process;
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_vs_engine::{move_timeout, spawn_initialized_engine, EngineVsEngineManager};
+use crate::shogi_rules::parse_position;
+use crate::usi_info::Score;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+/// The engine's verdict on one position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionEvaluation {
+    /// The position as given
+    pub sfen: String,
+    pub best_move: Option<String>,
+    /// From the point of view of the side to move
+    pub score: Option<Score>,
+    pub depth: Option<u32>,
+    pub pv: Vec<String>,
+    pub time_ms: u64,
+    pub error: Option<String>,
+}
+
+impl PositionEvaluation {
+    fn failed(sfen: &str, error: String) -> Self {
+        Self {
+            sfen: sfen.to_string(),
+            best_move: None,
+            score: None,
+            depth: None,
+            pv: Vec::new(),
+            time_ms: 0,
+            error: Some(error),
+        }
+    }
+}
+
+/// The position `spec` ends in, as an SFEN; `spec` may be an SFEN or `startpos`, with moves
+fn final_sfen(spec: &str) -> Result<String> {
+    let (mut position, moves) = parse_position(spec)?;
+    for mv in &moves {
+        position.apply_move(mv)?;
+    }
+    Ok(position.to_sfen())
+}
+
+/// Search each of `sfens` for `movetime_ms` with one instance of the engine, which runs with
+/// its saved options and eval file. Results are in the order of `sfens`; a position that is
+/// invalid or that the engine failed on carries an error instead of a move.
+pub async fn evaluate_positions(
+    engine: &EngineConfig,
+    engine_storage: &RwLock<EngineStorage>,
+    sfens: &[String],
+    movetime_ms: u64,
+) -> Result<Vec<PositionEvaluation>> {
+    let (mut child, mut stdin, mut stdout) = spawn_initialized_engine(engine, engine_storage).await?;
+    stdin.write_all(b"usinewgame\n").await?;
+
+    let go_cmd = format!("go movetime {}", movetime_ms);
+    let mut evaluations = Vec::with_capacity(sfens.len());
+    let mut engine_error = None;
+    for sfen in sfens {
+        if let Some(error) = &engine_error {
+            evaluations.push(PositionEvaluation::failed(sfen, format!("Not evaluated: {}", error)));
+            continue;
+        }
+        let position = match final_sfen(sfen) {
+            Ok(position) => position,
+            Err(e) => {
+                evaluations.push(PositionEvaluation::failed(sfen, format!("Invalid position: {}", e)));
+                continue;
+            }
+        };
+
+        let start = tokio::time::Instant::now();
+        let search = EngineVsEngineManager::request_move(
+            &mut stdin,
+            &mut stdout,
+            &position,
+            &[],
+            &go_cmd,
+            move_timeout(movetime_ms),
+            |_, _| {},
+        )
+        .await;
+        let time_ms = start.elapsed().as_millis() as u64;
+        match search {
+            Ok(engine_move) => {
+                let info = engine_move.info.unwrap_or_default();
+                evaluations.push(PositionEvaluation {
+                    sfen: sfen.clone(),
+                    best_move: Some(engine_move.best_move),
+                    score: info.score,
+                    depth: info.depth,
+                    pv: info.pv,
+                    time_ms,
+                    error: None,
+                });
+            }
+            Err(e) => {
+                // The engine died or hung; the remaining positions would fail the same way
+                log::warn!("Evaluation of {} failed: {}", sfen, e);
+                evaluations.push(PositionEvaluation { time_ms, ..PositionEvaluation::failed(sfen, e.to_string()) });
+                engine_error = Some(e);
+            }
+        }
+    }
+
+    let _ = stdin.write_all(b"quit\n").await;
+    let _ = stdin.flush().await;
+    engine_process::kill(&mut child).await;
+    Ok(evaluations)
+}
+
@@ -0,0 +1,137 @@
+//! Scripted USI engine for integration tests
+//! Reads its behaviour from `mock_engine.json` in the working directory (the engine manager and
+//! match runner start engines in their own directory) and otherwise plays the first legal move.
+
+use serde::Deserialize;
+use std::io::{BufRead, Write};
+use std::time::Duration;
+use ysu_core::shogi_rules::{parse_position, Color, Position};
+
+/// Behaviour of the mock. `*_on_go` fields count `go` commands from 1.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MockScript {
+    name: Option<String>,
+    /// `option ...` lines advertised before usiok
+    options: Vec<String>,
+    usiok_delay_ms: u64,
+    /// Write this to stderr and exit with `exit_code` on `usi`, like an engine missing a
+    /// shared library
+    fail_usi: Option<String>,
+    readyok_delay_ms: u64,
+    /// Print this as `info string` and exit on `isready`, like an engine missing its eval file
+    fail_isready: Option<String>,
+    /// Never answer this `isready`, counting from 1, like a hung engine
+    hang_on_isready: Option<u32>,
+    bestmove_delay_ms: u64,
+    /// Lines that are not USI, printed before usiok and before every bestmove
+    garbage: Vec<String>,
+    /// Exit with `exit_code` instead of answering this `go`
+    crash_on_go: Option<u32>,
+    exit_code: i32,
+    /// Written to stderr just before crashing
+    crash_message: Option<String>,
+    /// Answer this `go` with a king move two squares forward
+    illegal_move_on_go: Option<u32>,
+    resign_on_go: Option<u32>,
+}
+
+fn reply(out: &mut impl Write, line: &str) {
+    let _ = writeln!(out, "{}", line);
+    let _ = out.flush();
+}
+
+fn sleep_ms(ms: u64) {
+    if ms > 0 {
+        std::thread::sleep(Duration::from_millis(ms));
+    }
+}
+
+fn current_position(spec: &str) -> Option<Position> {
+    let (mut position, moves) = parse_position(spec).ok()?;
+    for mv in &moves {
+        position.apply_move(mv).ok()?;
+    }
+    Some(position)
+}
+
+fn main() {
+    let script: MockScript = std::fs::read_to_string("mock_engine.json")
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+    let stdin = std::io::stdin();
+    let mut out = std::io::stdout();
+    let mut position_spec = "startpos".to_string();
+    let mut go_count = 0;
+    let mut isready_count = 0;
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let command = line.trim();
+        match command.split_whitespace().next().unwrap_or("") {
+            "usi" => {
+                if let Some(message) = &script.fail_usi {
+                    eprintln!("{}", message);
+                    std::process::exit(script.exit_code);
+                }
+                sleep_ms(script.usiok_delay_ms);
+                reply(&mut out, &format!("id name {}", script.name.as_deref().unwrap_or("Mock Engine")));
+                reply(&mut out, "id author ysu tests");
+                for option in &script.options {
+                    reply(&mut out, option);
+                }
+                for garbage in &script.garbage {
+                    reply(&mut out, garbage);
+                }
+                reply(&mut out, "usiok");
+            }
+            "isready" => {
+                isready_count += 1;
+                if script.hang_on_isready == Some(isready_count) {
+                    continue;
+                }
+                if let Some(message) = &script.fail_isready {
+                    reply(&mut out, &format!("info string {}", message));
+                    std::process::exit(script.exit_code);
+                }
+                sleep_ms(script.readyok_delay_ms);
+                reply(&mut out, "readyok");
+            }
+            "position" => position_spec = command.to_string(),
+            "go" => {
+                go_count += 1;
+                if script.crash_on_go == Some(go_count) {
+                    if let Some(message) = &script.crash_message {
+                        eprintln!("{}", message);
+                    }
+                    std::process::exit(script.exit_code);
+                }
+                sleep_ms(script.bestmove_delay_ms);
+                for garbage in &script.garbage {
+                    reply(&mut out, garbage);
+                }
+                let position = current_position(&position_spec);
+                let best_move = if script.resign_on_go == Some(go_count) {
+                    "resign".to_string()
+                } else if script.illegal_move_on_go == Some(go_count) {
+                    match position.map(|position| position.side_to_move()) {
+                        Some(Color::White) => "5a5c".to_string(),
+                        _ => "5i5g".to_string(),
+                    }
+                } else {
+                    match position.and_then(|position| position.legal_moves().first().copied()) {
+                        Some(mv) => {
+                            reply(&mut out, &format!("info depth 1 score cp 0 nodes 1 pv {}", mv.to_usi()));
+                            mv.to_usi()
+                        }
+                        None => "resign".to_string(),
+                    }
+                };
+                reply(&mut out, &format!("bestmove {}", best_move));
+            }
+            "quit" => break,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Automatic move commentary for game records
+//! Annotations come from the rules and from the scores the engines reported while playing
+
+use crate::game_record::GameRecord;
+use crate::shogi_rules::{Move, Position};
+use crate::usi_info::Score;
+use anyhow::{anyhow, Result};
+
+/// A drop of at least this many centipawns, from the mover's point of view, is called out
+pub const EVAL_SWING_CP: i32 = 300;
+
+/// Human-readable score, e.g. "+120" or "mate in 5"
+pub fn format_score(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("{:+}", cp),
+        Score::Mate(i32::MAX) => "mate".to_string(),
+        Score::Mate(plies) if plies == -i32::MAX => "mated".to_string(),
+        Score::Mate(plies) if plies >= 0 => format!("mate in {}", plies),
+        Score::Mate(plies) => format!("mated in {}", -plies),
+    }
+}
+
+/// Replace the commentary of every move in the record
+pub fn annotate(record: &mut GameRecord) -> Result<()> {
+    let mut position = Position::from_sfen(&record.initial_sfen)?;
+    // Leading moves played without a search score are taken to be book moves
+    let mut in_book = true;
+
+    for index in 0..record.moves.len() {
+        let mut notes = Vec::new();
+        let mv = Move::from_usi(&record.moves[index].usi)?;
+        let score = record.moves[index].score;
+
+        if position.legal_moves().len() == 1 {
+            notes.push("Only move".to_string());
+        }
+
+        if in_book && score.is_some() {
+            in_book = false;
+            if index > 0 {
+                notes.push("Out of book".to_string());
+            }
+        }
+
+        // Announce a forced mate the first time the mover sees it
+        if let Some(Score::Mate(plies)) = score {
+            let previous_own = index.checked_sub(2).and_then(|i| record.moves[i].score);
+            if plies > 0 && !matches!(previous_own, Some(Score::Mate(p)) if p > 0) {
+                notes.push(format!("Announces {}", format_score(Score::Mate(plies))));
+            }
+        }
+
+        // Compare the mover's expectation with what the opponent found in reply
+        let reply = record.moves.get(index + 1).and_then(|m| m.score).map(Score::negate);
+        if let (Some(before), Some(after)) = (score, reply) {
+            if before.centipawns().saturating_sub(after.centipawns()) >= EVAL_SWING_CP {
+                notes.push(format!("Evaluation swing: {} → {}", format_score(before), format_score(after)));
+            }
+        }
+
+        position
+            .apply_move(&mv)
+            .map_err(|e| anyhow!("Move {} ({}): {}", index + 1, mv.to_usi(), e))?;
+        if position.is_checkmate() {
+            notes.push("Checkmate".to_string());
+        }
+
+        record.moves[index].commentary = notes;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_record::RecordedMove;
+    use crate::shogi_rules::STARTPOS_SFEN;
+
+    fn recorded(usi: &str, score: Option<Score>) -> RecordedMove {
+        RecordedMove {
+            usi: usi.to_string(),
+            elapsed_ms: None,
+            score,
+            depth: None,
+            commentary: Vec::new(),
+            comment: None,
+            symbol: None,
+            variations: Vec::new(),
+            book: false,
+        }
+    }
+
+    #[test]
+    fn test_annotate_record() {
+        let mut record = GameRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            black_name: "A".to_string(),
+            white_name: "B".to_string(),
+            initial_sfen: STARTPOS_SFEN.to_string(),
+            moves: vec![
+                recorded("7g7f", None),
+                recorded("3c3d", None),
+                recorded("8h2b+", Some(Score::Cp(50))),
+                recorded("3a2b", Some(Score::Cp(-400))),
+                recorded("B*4e", Some(Score::Mate(3))),
+            ],
+            winner: None,
+            result: None,
+            termination: None,
+            black_timing: None,
+            white_timing: None,
+            match_config: None,
+            think_log: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        annotate(&mut record).unwrap();
+
+        assert!(record.moves[0].commentary.is_empty());
+        assert_eq!(record.moves[2].commentary, vec!["Out of book"]);
+        // White's recapture is refuted by a mate the opponent finds in reply
+        assert!(record.moves[3].commentary.iter().any(|c| c.starts_with("Evaluation swing: -400")));
+        assert_eq!(record.moves[4].commentary, vec!["Announces mate in 3"]);
+    }
+}
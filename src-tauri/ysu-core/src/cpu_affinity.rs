@@ -0,0 +1,77 @@
+//! CPU core pinning for engine processes
+//! Keeps concurrent matches from perturbing each other's timing. Supported on Linux and
+//! Windows; on other platforms the request is logged and ignored.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::process::{Child, Command};
+
+/// Cores each engine of a game is restricted to (0-based logical CPU indices)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CpuAffinity {
+    #[serde(default)]
+    pub engine1_cores: Vec<usize>,
+    #[serde(default)]
+    pub engine2_cores: Vec<usize>,
+}
+
+impl CpuAffinity {
+    /// Check that every requested core exists on this machine
+    pub fn validate(&self) -> Result<()> {
+        let available = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        for &core in self.engine1_cores.iter().chain(&self.engine2_cores) {
+            if core >= available {
+                return Err(anyhow!("CPU core {} out of range (this machine has {} cores)", core, available));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Configure a command so the spawned process starts pinned to `cores`
+#[cfg(target_os = "linux")]
+pub fn apply_before_spawn(command: &mut Command, cores: &[usize]) {
+    if cores.is_empty() {
+        return;
+    }
+    // Build the mask up front; only the syscall runs between fork and exec
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &core in cores {
+        unsafe { libc::CPU_SET(core, &mut set) };
+    }
+    unsafe {
+        command.pre_exec(move || {
+            if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply_before_spawn(_command: &mut Command, _cores: &[usize]) {}
+
+/// Pin an already spawned process to `cores`
+#[cfg(windows)]
+pub fn apply_after_spawn(child: &Child, cores: &[usize]) -> Result<()> {
+    use windows_sys::Win32::System::Threading::SetProcessAffinityMask;
+
+    if cores.is_empty() {
+        return Ok(());
+    }
+    let mask = cores.iter().fold(0usize, |mask, &core| mask | (1usize << core));
+    let handle = child.raw_handle().ok_or_else(|| anyhow!("Engine process already exited"))?;
+    if unsafe { SetProcessAffinityMask(handle, mask) } == 0 {
+        return Err(anyhow!("Failed to set CPU affinity: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn apply_after_spawn(_child: &Child, cores: &[usize]) -> Result<()> {
+    if !cores.is_empty() && !cfg!(target_os = "linux") {
+        log::warn!("CPU affinity is not supported on this platform; ignoring core list {:?}", cores);
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Decoding of engine output that is not UTF-8
+//! Older Japanese engines print names, options and `info string` messages in Shift_JIS (CP932).
+//! Lines are read as raw bytes and decoded one by one, so such a line is transcoded instead of
+//! failing the reader the way `AsyncBufReadExt::lines` does.
+
+use encoding_rs::SHIFT_JIS;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+/// Text encoding detected in an engine's output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    /// Shift_JIS / CP932
+    ShiftJis,
+}
+
+/// Decode one line of engine output. Valid UTF-8 (including plain ASCII) is taken as is;
+/// otherwise the line is decoded as CP932, and as lossy UTF-8 if that fails too.
+pub fn decode_line(bytes: &[u8]) -> (String, OutputEncoding) {
+    let bytes = strip_line_ending(bytes);
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), OutputEncoding::Utf8);
+    }
+    // encoding_rs's Shift_JIS is the WHATWG definition, i.e. Windows-31J / CP932
+    match SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes) {
+        Some(text) => (text.into_owned(), OutputEncoding::ShiftJis),
+        None => (String::from_utf8_lossy(bytes).into_owned(), OutputEncoding::Utf8),
+    }
+}
+
+fn strip_line_ending(bytes: &[u8]) -> &[u8] {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    bytes.strip_suffix(b"\r").unwrap_or(bytes)
+}
+
+/// Line reader for engine stdout/stderr that decodes every line, remembering whether any
+/// line needed Shift_JIS
+pub struct EngineLines<R> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+    encoding: OutputEncoding,
+}
+
+impl<R: AsyncRead + Unpin> EngineLines<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+            encoding: OutputEncoding::Utf8,
+        }
+    }
+
+    /// The next line without its line ending, or None at end of output
+    pub async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        self.buf.clear();
+        if self.reader.read_until(b'\n', &mut self.buf).await? == 0 {
+            return Ok(None);
+        }
+        let (line, encoding) = decode_line(&self.buf);
+        if encoding == OutputEncoding::ShiftJis {
+            self.encoding = OutputEncoding::ShiftJis;
+        }
+        Ok(Some(line))
+    }
+
+    /// Shift_JIS once any line has been decoded as such
+    pub fn encoding(&self) -> OutputEncoding {
+        self.encoding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_line() {
+        assert_eq!(decode_line(b"usiok\r\n"), ("usiok".to_string(), OutputEncoding::Utf8));
+        assert_eq!(
+            decode_line("id name 技巧\n".as_bytes()),
+            ("id name 技巧".to_string(), OutputEncoding::Utf8)
+        );
+
+        let (sjis, _, _) = SHIFT_JIS.encode("info string 評価関数を読み込みました");
+        assert_eq!(
+            decode_line(&sjis),
+            ("info string 評価関数を読み込みました".to_string(), OutputEncoding::ShiftJis)
+        );
+        // Not valid in either encoding
+        assert_eq!(decode_line(&[b'a', 0xff, 0xff]).1, OutputEncoding::Utf8);
+    }
+
+    #[tokio::test]
+    async fn test_engine_lines_survive_shift_jis() {
+        let (name, _, _) = SHIFT_JIS.encode("id name 将棋エンジン\r\n");
+        let mut output = name.into_owned();
+        output.extend_from_slice(b"id author someone\nusiok\n");
+
+        let mut lines = EngineLines::new(output.as_slice());
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("id name 将棋エンジン"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("id author someone"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("usiok"));
+        assert_eq!(lines.next_line().await.unwrap(), None);
+        assert_eq!(lines.encoding(), OutputEncoding::ShiftJis);
+    }
+}
@@ -0,0 +1,294 @@
+//! Health checks for configured engines
+//! A check times the `usi`/`usiok` and `isready`/`readyok` round trips, compares the binary
+//! with the hash recorded at validation and looks for the eval and book files the engine's
+//! options point at.
+
+use crate::engine_process;
+use crate::engine_storage::EngineConfig;
+use crate::engine_validator::{self, ValidationOptions};
+use anyhow::{anyhow, Result};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::time::{timeout, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    Unhealthy,
+    Disabled,
+}
+
+/// Result of checking one engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineHealth {
+    pub id: String,
+    pub name: String,
+    pub status: HealthStatus,
+    /// Time from sending `usi` to receiving `usiok`
+    pub usi_latency_ms: Option<u64>,
+    /// Time from sending `isready` to receiving `readyok`, including loading eval files
+    pub ready_latency_ms: Option<u64>,
+    /// Whether the binary still matches the recorded hash; None when no hash was recorded
+    pub binary_matches: Option<bool>,
+    /// Eval and book files or directories the engine's options refer to that do not exist
+    pub missing_files: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl EngineHealth {
+    fn new(engine: &EngineConfig, status: HealthStatus) -> Self {
+        Self {
+            id: engine.id.clone(),
+            name: engine.name.clone(),
+            status,
+            usi_latency_ms: None,
+            ready_latency_ms: None,
+            binary_matches: None,
+            missing_files: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+fn option_value<'a>(options: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .rev()
+        .find(|(option, _)| option.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty() && *value != "<empty>")
+}
+
+/// Eval, book and network paths named by `options` (EvalDir, EvalFile, BookDir, BookFile and
+/// dlshogi's DNN_Model) that do not exist. Relative paths are resolved against `engine_dir`, the directory engines run in.
+pub fn missing_files(engine_dir: &Path, options: &[(String, String)]) -> Vec<String> {
+    let resolve = |base: &Path, value: &str| -> PathBuf {
+        let path = Path::new(value);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            base.join(path)
+        }
+    };
+    let eval_dir = option_value(options, "EvalDir").map(|dir| resolve(engine_dir, dir));
+    let book_dir = option_value(options, "BookDir").map(|dir| resolve(engine_dir, dir));
+
+    let mut expected = Vec::new();
+    expected.extend(eval_dir.clone());
+    expected.extend(book_dir.clone());
+    if let Some(file) = option_value(options, "EvalFile") {
+        expected.push(resolve(eval_dir.as_deref().unwrap_or(engine_dir), file));
+    }
+    // YaneuraOu's BookFile is a combo whose "no_book" disables the book
+    if let Some(file) = option_value(options, "BookFile").filter(|file| *file != "no_book") {
+        expected.push(resolve(book_dir.as_deref().unwrap_or(engine_dir), file));
+    }
+    if let Some(model) = option_value(options, "DNN_Model") {
+        expected.push(resolve(engine_dir, model));
+    }
+
+    expected
+        .into_iter()
+        .filter(|path| !path.exists())
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+/// The options the engine starts with: recorded defaults overridden by `startup_options`
+fn effective_options(engine: &EngineConfig, startup_options: &[(String, String)]) -> Vec<(String, String)> {
+    let mut options: Vec<(String, String)> = engine
+        .metadata
+        .iter()
+        .flat_map(|metadata| metadata.options.iter())
+        .filter_map(|option| option.default.clone().map(|default| (option.name.clone(), default)))
+        .collect();
+    options.extend(startup_options.iter().cloned());
+    options
+}
+
+/// Check one engine, sending `startup_options` before `isready` the way it is started for games
+pub async fn check_engine(
+    engine: &EngineConfig,
+    startup_options: &[(String, String)],
+    validation: &ValidationOptions,
+) -> EngineHealth {
+    if !engine.enabled {
+        return EngineHealth::new(engine, HealthStatus::Disabled);
+    }
+    let mut health = EngineHealth::new(engine, HealthStatus::Unhealthy);
+
+    if engine.binary_sha256.is_some() {
+        match engine.check_binary().await {
+            Ok((_, changed)) => health.binary_matches = Some(changed.is_none()),
+            Err(e) => {
+                health.error = Some(format!("Engine executable not readable: {}", e));
+                return health;
+            }
+        }
+    }
+
+    let engine_dir = Path::new(&engine.path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    health.missing_files = missing_files(&engine_dir, &effective_options(engine, startup_options));
+
+    // The engine's own timeouts, where it has them, replace the check's
+    let overrides = engine.timeouts.unwrap_or_default();
+    let validation = ValidationOptions {
+        usi_timeout_ms: overrides.validation_ms.unwrap_or(validation.usi_timeout_ms),
+        ready_timeout_ms: overrides.readyok_ms.unwrap_or(validation.ready_timeout_ms),
+        options: startup_options.to_vec(),
+        ..validation.clone()
+    };
+    match measure_latency(&engine.path, &validation, &mut health).await {
+        Ok(()) if health.missing_files.is_empty() => health.status = HealthStatus::Healthy,
+        Ok(()) => {
+            health.error = Some(format!("Missing files: {}", health.missing_files.join(", ")));
+        }
+        Err(e) => {
+            log::warn!("Engine {} health check failed: {}", engine.name, e);
+            health.error = Some(e.to_string());
+        }
+    }
+    health
+}
+
+async fn cancelled(cancel: &AtomicBool) {
+    while !cancel.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Check `engines` (with their startup options) running at most `max_parallel` at a time.
+/// `on_result` gets each result with the engine's index as soon as its check finishes.
+/// Setting `cancel` abandons the checks still running, killing their engines, and skips the
+/// rest; only finished results are returned.
+pub async fn check_engines(
+    engines: &[(EngineConfig, Vec<(String, String)>)],
+    validation: &ValidationOptions,
+    max_parallel: usize,
+    cancel: &AtomicBool,
+    mut on_result: impl FnMut(usize, &EngineHealth),
+) -> Vec<EngineHealth> {
+    let mut checks = stream::iter(engines.iter().enumerate())
+        .map(|(index, (engine, startup_options))| async move {
+            if cancel.load(Ordering::SeqCst) {
+                return None;
+            }
+            tokio::select! {
+                health = check_engine(engine, startup_options, validation) => Some((index, health)),
+                _ = cancelled(cancel) => None,
+            }
+        })
+        .buffer_unordered(max_parallel.max(1));
+
+    let mut results = Vec::new();
+    while let Some(finished) = checks.next().await {
+        if let Some((index, health)) = finished {
+            on_result(index, &health);
+            results.push((index, health));
+        }
+    }
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, health)| health).collect()
+}
+
+async fn measure_latency(path: &str, validation: &ValidationOptions, health: &mut EngineHealth) -> Result<()> {
+    if !Path::new(path).exists() {
+        return Err(anyhow!("Engine executable not found at path: {}", path));
+    }
+    let (mut child, mut stdin, mut lines, stderr) = engine_validator::spawn_for_check(path)?;
+    let outcome = async {
+        let start = Instant::now();
+        stdin.write_all(b"usi\n").await?;
+        stdin.flush().await?;
+        timeout(Duration::from_millis(validation.usi_timeout_ms), async {
+            while let Some(line) = lines.next_line().await? {
+                if line.trim() == "usiok" {
+                    return Ok(());
+                }
+            }
+            Err(anyhow!("Engine did not respond with 'usiok'"))
+        })
+        .await
+        .map_err(|_| anyhow!("Timeout waiting for engine response"))??;
+        health.usi_latency_ms = Some(start.elapsed().as_millis() as u64);
+
+        let start = Instant::now();
+        engine_validator::check_ready(&mut stdin, &mut lines, validation).await?;
+        health.ready_latency_ms = Some(start.elapsed().as_millis() as u64);
+        Ok(())
+    }
+    .await;
+
+    let _ = stdin.write_all(b"quit\n").await;
+    let _ = stdin.flush().await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    engine_process::kill(&mut child).await;
+    match outcome {
+        Ok(()) => Ok(()),
+        Err(e) => Err(stderr.explain(e).await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let dir = std::env::temp_dir().join(format!("shogi-vibe-health-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("eval")).unwrap();
+        std::fs::write(dir.join("eval").join("nn.bin"), b"").unwrap();
+
+        let present = options(&[("EvalDir", "eval"), ("EvalFile", "nn.bin"), ("BookFile", "no_book")]);
+        assert!(missing_files(&dir, &present).is_empty());
+
+        let missing = options(&[
+            ("EvalDir", "eval"),
+            ("EvalFile", "nn.bin"),
+            ("EvalFile", "other.bin"),
+            ("BookDir", "book"),
+            ("DNN_Model", "model.onnx"),
+        ]);
+        let reported = missing_files(&dir, &missing);
+        assert_eq!(reported.len(), 3);
+        assert!(reported.iter().any(|path| path.ends_with("model.onnx")));
+        assert!(reported.iter().any(|path| path.ends_with("other.bin")));
+        assert!(reported.iter().any(|path| path.ends_with("book")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_engines_in_order_and_cancellable() {
+        let engine = |name: &str| {
+            let config = EngineConfig::new(name.to_string(), format!("/nonexistent/{}", name), None, false);
+            (config, Vec::new())
+        };
+        let engines = vec![engine("a"), engine("b"), engine("c")];
+        let validation = ValidationOptions::default();
+
+        let mut reported = Vec::new();
+        let results = check_engines(&engines, &validation, 2, &AtomicBool::new(false), |index, _| reported.push(index)).await;
+        assert_eq!(results.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert!(results.iter().all(|r| r.status == HealthStatus::Unhealthy));
+        reported.sort();
+        assert_eq!(reported, vec![0, 1, 2]);
+
+        let results = check_engines(&engines, &validation, 2, &AtomicBool::new(true), |_, _| {}).await;
+        assert!(results.is_empty());
+    }
+}
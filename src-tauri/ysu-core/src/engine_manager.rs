@@ -0,0 +1,2172 @@
+use crate::engine_encoding::EngineLines;
+use crate::engine_process;
+use crate::engine_storage::{EngineStorage, EngineUsage};
+use crate::engine_validator::EngineOption;
+use crate::metrics;
+use crate::multipv::{AnalysisSnapshot, MultiPvTracker};
+use crate::notation::{self, NotationStyle};
+use crate::output_buffer::{InfoRateLimiter, OutputBuffer, OutputThrottle};
+use crate::process_priority::{self, ProcessPriority};
+use crate::shogi_rules::{self, Move, Position, Square};
+use crate::timeouts::TimeoutConfig;
+use crate::usi_info;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::events::{
+    SharedEventSink, ANALYSIS_SNAPSHOT, ENGINE_BESTMOVE, ENGINE_CONSOLE, ENGINE_CRASHED, ENGINE_IDLE_STOPPED,
+    ENGINE_INIT_FAILED, ENGINE_INIT_PROGRESS, ENGINE_MESSAGE, ENGINE_UNRESPONSIVE, ENGINE_WARM_READY, USI_ERROR,
+    USI_MESSAGE,
+};
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{mpsc, Mutex, Notify, RwLock};
+use tokio::time::timeout;
+
+/// Represents the status of a USI engine
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineStatus {
+    Starting,
+    Ready,
+    /// Searching, from `go` until `bestmove`
+    Thinking,
+    Error,
+    Stopped,
+    /// The process exited on its own with a failure; `code` is None when a signal killed it
+    Crashed { code: Option<i32> },
+    /// Alive but did not answer a heartbeat `isready` in time; back to ready if it answers late
+    Unresponsive,
+}
+
+/// Lines of an engine's stderr kept for crash reports
+const STDERR_TAIL_LINES: usize = 20;
+/// Crash reports kept per engine config
+const CRASH_HISTORY_LIMIT: usize = 20;
+/// `info string` messages kept per engine instance
+const ENGINE_MESSAGE_LIMIT: usize = 50;
+
+fn default_retries() -> u32 {
+    1
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
+/// How often starting an engine is retried. How long each attempt may take is set by the
+/// usiok and readyok timeouts (see the timeouts module).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct StartupPolicy {
+    /// Further attempts after the first one fails
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+impl Default for StartupPolicy {
+    fn default() -> Self {
+        Self {
+            retries: default_retries(),
+            retry_delay_ms: default_retry_delay_ms(),
+        }
+    }
+}
+
+fn default_idle_timeout_minutes() -> u64 {
+    60
+}
+
+/// When engines that receive no commands are stopped to free their memory. Engines marked
+/// keep-alive are never stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct IdlePolicy {
+    /// Minutes without a command before an engine is stopped; 0 keeps engines running
+    #[serde(default = "default_idle_timeout_minutes")]
+    pub idle_timeout_minutes: u64,
+}
+
+impl Default for IdlePolicy {
+    fn default() -> Self {
+        Self {
+            idle_timeout_minutes: default_idle_timeout_minutes(),
+        }
+    }
+}
+
+/// Emitted as `engine-idle-stopped` when the idle policy stops an engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineIdleStopped {
+    pub runtime_id: String,
+    pub config_id: String,
+    pub name: String,
+    pub idle_minutes: u64,
+}
+
+fn default_heartbeat_interval_ms() -> u64 {
+    30_000
+}
+
+fn default_heartbeat_timeout_ms() -> u64 {
+    10_000
+}
+
+/// How the watchdog probes idle engines. An engine that is ready and has received no command
+/// for `interval_ms` is sent `isready`; without `readyok` within `timeout_ms` it is flagged
+/// unresponsive. Applies to running engines too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct HeartbeatPolicy {
+    /// Also how often the watchdog checks the process; 0 turns probing off
+    #[serde(default = "default_heartbeat_interval_ms")]
+    pub interval_ms: u64,
+    #[serde(default = "default_heartbeat_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            interval_ms: default_heartbeat_interval_ms(),
+            timeout_ms: default_heartbeat_timeout_ms(),
+        }
+    }
+}
+
+/// Emitted as `engine-unresponsive` when an engine misses a heartbeat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineUnresponsive {
+    pub runtime_id: String,
+    pub config_id: String,
+    pub name: String,
+    pub timeout_ms: u64,
+}
+
+/// Emitted as `engine-warm-ready` when a keep-warm engine has been started or, after a
+/// crash, restarted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineWarmReady {
+    pub config_id: String,
+    pub runtime_id: String,
+    pub respawned: bool,
+}
+
+/// Free text an engine sent as `info string`, e.g. a warning about a missing book. Emitted as
+/// `engine-message::<runtime id>` instead of with the rest of the engine's output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineMessage {
+    pub message: String,
+    pub timestamp: String,
+}
+
+/// The last `position` command sent to an instance, as returned by
+/// [`EngineManager::engine_position`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EnginePosition {
+    /// The command as sent
+    pub command: String,
+    pub start_sfen: String,
+    /// USI moves played from the start position
+    pub moves: Vec<String>,
+    /// The position after the moves
+    pub sfen: String,
+}
+
+/// The last `position` sent to an instance, with the position it ends in
+#[derive(Debug, Clone)]
+struct LastPosition {
+    sent: EnginePosition,
+    position: Position,
+    /// Destination of the last move, for notations that write a recapture as "same"
+    previous_to: Option<Square>,
+}
+
+impl LastPosition {
+    /// None if the command does not describe a legal position. A command that extends
+    /// `previous`, as each `position` of a game does, only has its new moves checked.
+    fn from_command(command: &str, previous: Option<&LastPosition>) -> Option<Self> {
+        let (start, moves) = shogi_rules::parse_position(command).ok()?;
+        let start_sfen = start.to_sfen();
+        let usi_moves: Vec<String> = moves.iter().map(|mv| mv.to_usi()).collect();
+        let (mut position, played) = match previous {
+            Some(previous) if previous.sent.start_sfen == start_sfen && usi_moves.starts_with(&previous.sent.moves) => {
+                (previous.position.clone(), previous.sent.moves.len())
+            }
+            _ => (start, 0),
+        };
+        for mv in &moves[played..] {
+            position.apply_move(mv).ok()?;
+        }
+        Some(Self {
+            sent: EnginePosition {
+                command: command.trim().to_string(),
+                start_sfen,
+                moves: usi_moves,
+                sfen: position.to_sfen(),
+            },
+            previous_to: moves.last().map(|mv| mv.to()),
+            position,
+        })
+    }
+}
+
+/// A bestmove in the notations the app can write
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct MoveNotations {
+    pub usi: String,
+    pub csa: String,
+    pub ki2: String,
+    pub kif: String,
+    pub western: String,
+}
+
+/// A `bestmove` checked against the last position sent to the engine, emitted as
+/// `engine-bestmove::<runtime id>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct BestMoveReport {
+    /// The move as the engine sent it, or `resign` or `win`
+    pub bestmove: String,
+    pub ponder: Option<String>,
+    /// The position the move answers; None if no valid `position` was sent
+    pub sfen: Option<String>,
+    /// Whether the move is legal in `sfen`; `win` only counts when the position qualifies for
+    /// a declaration. None when the position is unknown.
+    pub legal: Option<bool>,
+    /// Why the move was rejected
+    pub error: Option<String>,
+    /// Only for a legal move
+    pub notations: Option<MoveNotations>,
+    /// Position after a legal move
+    pub resulting_sfen: Option<String>,
+}
+
+/// Check a `bestmove` line against `last`. None if the line is not a bestmove.
+fn check_bestmove(line: &str, last: Option<&LastPosition>) -> Option<BestMoveReport> {
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some("bestmove") {
+        return None;
+    }
+    let bestmove = tokens.next().unwrap_or_default().to_string();
+    let ponder = match (tokens.next(), tokens.next()) {
+        (Some("ponder"), Some(ponder)) => Some(ponder.to_string()),
+        _ => None,
+    };
+    let mut report = BestMoveReport {
+        bestmove,
+        ponder,
+        sfen: last.map(|last| last.sent.sfen.clone()),
+        legal: None,
+        error: None,
+        notations: None,
+        resulting_sfen: None,
+    };
+    let Some(last) = last else {
+        return Some(report);
+    };
+
+    let checked = match report.bestmove.as_str() {
+        "resign" => Ok(None),
+        "win" if last.position.can_declare_win() => Ok(None),
+        "win" => Err(anyhow!("The position does not qualify for a win declaration")),
+        usi => Move::from_usi(usi).and_then(|mv| {
+            if !last.position.is_legal(&mv) {
+                return Err(anyhow!("Illegal move: {}", usi));
+            }
+            let format = |style| notation::format_move(&last.position, &mv, last.previous_to, style);
+            let notations = MoveNotations {
+                usi: mv.to_usi(),
+                csa: format(NotationStyle::Csa)?,
+                ki2: format(NotationStyle::Ki2)?,
+                kif: format(NotationStyle::Kif)?,
+                western: format(NotationStyle::Western)?,
+            };
+            let mut next = last.position.clone();
+            next.apply_move_unchecked(&mv);
+            Ok(Some((notations, next.to_sfen())))
+        }),
+    };
+    match checked {
+        Ok(played) => {
+            report.legal = Some(true);
+            if let Some((notations, resulting_sfen)) = played {
+                report.notations = Some(notations);
+                report.resulting_sfen = Some(resulting_sfen);
+            }
+        }
+        Err(e) => {
+            report.legal = Some(false);
+            report.error = Some(e.to_string());
+        }
+    }
+    Some(report)
+}
+
+/// Which way a console line went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleDirection {
+    /// Written to the engine's stdin
+    Sent,
+    /// Read from its stdout
+    Received,
+    /// Read from its stderr
+    Stderr,
+}
+
+/// A line to or from an engine in console mode, emitted unthrottled as
+/// `engine-console::<runtime id>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ConsoleLine {
+    pub direction: ConsoleDirection,
+    pub line: String,
+    pub timestamp: String,
+}
+
+fn emit_console_line(events: &SharedEventSink, engine_id: &RuntimeId, direction: ConsoleDirection, line: &str) {
+    let line = ConsoleLine {
+        direction,
+        line: line.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = events.send(&ENGINE_CONSOLE.scope(engine_id), &line) {
+        log::error!("Failed to emit console line: {}", e);
+    }
+}
+
+/// The text of a line that is only an `info string`, not search info that ends in one
+fn info_string(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("info")?.trim_start();
+    let text = rest.strip_prefix("string")?;
+    (text.is_empty() || text.starts_with(char::is_whitespace)).then(|| text.trim())
+}
+
+fn setoption_command(name: &str, value: &str) -> String {
+    format!("setoption name {} value {}", name, value)
+}
+
+/// The commands initializing an engine would send, and what looks wrong about them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct InitPreview {
+    pub commands: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Why `value` is not accepted by the engine's option definition, if it is not
+fn option_value_problem(option: &EngineOption, value: &str) -> Option<String> {
+    match option.option_type.as_str() {
+        "spin" => {
+            let Ok(number) = value.trim().parse::<i64>() else {
+                return Some(format!("{} is a spin option but {:?} is not a number", option.name, value));
+            };
+            let bound = |bound: &Option<String>| bound.as_deref().and_then(|bound| bound.parse::<i64>().ok());
+            match (bound(&option.min), bound(&option.max)) {
+                (Some(min), _) if number < min => Some(format!("{} = {} is below its minimum {}", option.name, number, min)),
+                (_, Some(max)) if number > max => Some(format!("{} = {} is above its maximum {}", option.name, number, max)),
+                _ => None,
+            }
+        }
+        "check" if value != "true" && value != "false" => {
+            Some(format!("{} is a check option but {:?} is not true or false", option.name, value))
+        }
+        "combo" if !option.var.iter().any(|var| var == value) => {
+            Some(format!("{} = {:?} is not one of {}", option.name, value, option.var.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+/// The USI lines [`EngineManager::initialize_engine_with_temp_options`] would send to the
+/// engine, from its stored config alone: `usi`, the `setoption` commands (`options` in place
+/// of the saved ones when given, then the eval file's) and `isready`. Options are checked
+/// against those the engine advertised when it was validated.
+pub fn preview_init(
+    storage: &EngineStorage,
+    engine_id: &str,
+    options: Option<&HashMap<String, String>>,
+) -> Result<InitPreview> {
+    let engine = storage.get_engine(engine_id).ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+    let mut warnings = Vec::new();
+    if let Err(e) = storage.eval_options_for(engine_id, None, None) {
+        warnings.push(format!("Not selecting eval file: {}", e));
+    }
+    let init_options = storage.init_options(engine_id, options, None);
+    match engine.metadata.as_ref() {
+        Some(metadata) => {
+            for (name, value) in &init_options {
+                match metadata.options.iter().find(|option| option.name == *name) {
+                    Some(option) => warnings.extend(option_value_problem(option, value)),
+                    None => warnings.push(format!("The engine does not advertise an option named {}", name)),
+                }
+            }
+        }
+        None => warnings.push("The engine has no validation metadata, so options were not checked".to_string()),
+    }
+
+    let mut commands = vec!["usi".to_string()];
+    commands.extend(init_options.iter().map(|(name, value)| setoption_command(name, value)));
+    commands.push("isready".to_string());
+    Ok(InitPreview { commands, warnings })
+}
+
+/// How an engine process ended on its own, emitted as `engine-crashed` and kept in the
+/// crash history of its config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct CrashReport {
+    pub runtime_id: String,
+    pub config_id: String,
+    pub name: String,
+    pub exit_code: Option<i32>,
+    /// Signal that killed the process (Unix only)
+    pub signal: Option<i32>,
+    /// Last lines the engine wrote to stderr, oldest first
+    pub stderr_tail: Vec<String>,
+    /// The position the engine was last sent, to resume from after a restart
+    #[serde(default)]
+    pub position: Option<EnginePosition>,
+    pub timestamp: String,
+}
+
+impl CrashReport {
+    /// One-line description for the `usi-error` event, followed by the stderr tail
+    pub fn message(&self) -> String {
+        let mut message = match (self.exit_code, self.signal) {
+            (Some(code), _) => format!("Engine process exited with code {}", code),
+            (None, Some(signal)) => format!("Engine process killed by signal {}", signal),
+            (None, None) => "Engine process died".to_string(),
+        };
+        if !self.stderr_tail.is_empty() {
+            message.push_str("; last stderr output:\n");
+            message.push_str(&self.stderr_tail.join("\n"));
+        }
+        message
+    }
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &std::process::ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &std::process::ExitStatus) -> Option<i32> {
+    None
+}
+
+/// A keep-warm engine's place in the pool
+#[derive(Debug, Clone)]
+enum WarmSlot {
+    Running(RuntimeId),
+    /// Could not be started; not retried until the engine leaves the pool and is added again
+    Failed,
+}
+
+/// Emitted as `engine-init-failed` when an engine could not be started after all attempts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineInitFailed {
+    pub config_id: String,
+    pub name: String,
+    pub attempts: u32,
+    pub error: String,
+}
+
+/// Steps of starting an engine, emitted as `engine-init-progress::<runtime id>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum InitStage {
+    Spawned,
+    UsiSent,
+    UsiokReceived,
+    OptionsSent,
+    IsreadySent,
+    ReadyokReceived,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct InitProgress {
+    pub stage: InitStage,
+    /// Time since initialization began; 0 for `spawned`
+    pub elapsed_ms: u64,
+}
+
+type EngineMap = Arc<RwLock<HashMap<RuntimeId, Arc<Mutex<EngineInstance>>>>>;
+
+/// Send a heartbeat `isready` to an engine and flag it unresponsive, emitting
+/// `engine-unresponsive`, if `readyok` does not come back within `timeout_ms`
+async fn probe_engine(engines: &EngineMap, engine_id: &RuntimeId, timeout_ms: u64, events: &SharedEventSink) {
+    let Some(engine) = engines.read().await.get(engine_id).cloned() else {
+        return;
+    };
+    let readyok = engine.lock().await.readyok.clone();
+    // Created before the heartbeat is sent, so a quick readyok is not missed
+    let answered = readyok.notified();
+    if engine.lock().await.send_heartbeat().await.is_err() {
+        // A closed stdin means the process is gone; the watchdog's next check reports it
+        return;
+    }
+    if timeout(Duration::from_millis(timeout_ms), answered).await.is_ok() {
+        return;
+    }
+
+    let mut engine = engine.lock().await;
+    if engine.status != EngineStatus::Ready {
+        return;
+    }
+    log::warn!("Engine {} did not answer a heartbeat within {}ms", engine_id, timeout_ms);
+    engine.status = EngineStatus::Unresponsive;
+    let unresponsive = EngineUnresponsive {
+        runtime_id: engine_id.to_string(),
+        config_id: engine_id.config_id.clone(),
+        name: engine.name.clone(),
+        timeout_ms,
+    };
+    drop(engine);
+    if let Err(e) = events.send(&ENGINE_UNRESPONSIVE, &unresponsive) {
+        log::error!("Failed to emit engine unresponsive: {}", e);
+    }
+}
+
+/// Identifies one running process of a configured engine. The instance number counts up
+/// per config for the lifetime of the app, so a stale ID never reaches a newer process.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RuntimeId {
+    pub config_id: String,
+    pub instance: u32,
+}
+
+impl fmt::Display for RuntimeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.config_id, self.instance)
+    }
+}
+
+/// Pick the running engine an ID refers to: an exact runtime ID, or a config ID
+/// when exactly one instance of that config is running
+fn resolve_runtime_id<'a>(
+    running: impl Iterator<Item = &'a RuntimeId>,
+    engine_id: &str,
+) -> Result<&'a RuntimeId> {
+    let mut instances = Vec::new();
+    for runtime_id in running {
+        if runtime_id.to_string() == engine_id {
+            return Ok(runtime_id);
+        }
+        if runtime_id.config_id == engine_id {
+            instances.push(runtime_id);
+        }
+    }
+    match instances.as_slice() {
+        [only] => Ok(only),
+        [] => Err(anyhow!("Engine not found: {}", engine_id)),
+        _ => Err(anyhow!(
+            "{} instances of engine {} are running; use a runtime ID",
+            instances.len(),
+            engine_id
+        )),
+    }
+}
+
+/// What a running engine instance is being used for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstancePurpose {
+    #[default]
+    Game,
+    Analysis,
+    Match,
+    Other,
+}
+
+/// Listing entry for a running engine instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineInstanceInfo {
+    pub runtime_id: String,
+    pub config_id: String,
+    pub instance: u32,
+    pub name: String,
+    pub purpose: InstancePurpose,
+    pub status: EngineStatus,
+    pub keep_alive: bool,
+    /// In console mode
+    pub console: bool,
+    /// Seconds since the last command was sent
+    pub idle_secs: u64,
+}
+
+/// Represents a USI engine instance
+#[derive(Debug)]
+pub struct EngineInstance {
+    pub id: String,
+    pub name: String,
+    pub purpose: InstancePurpose,
+    #[allow(dead_code)]
+    pub path: String,
+    pub status: EngineStatus,
+    /// Exempt from the idle policy
+    pub keep_alive: bool,
+    /// In console mode: every line in and out goes to `engine-console::<runtime id>` and the
+    /// status is no longer inferred from the output. Shared with the reader tasks.
+    console: Arc<AtomicBool>,
+    last_command_at: tokio::time::Instant,
+    /// When the current search was started with `go`
+    search_started_at: Option<tokio::time::Instant>,
+    /// PV lines of the current search, merged by the output reader
+    analysis: Arc<std::sync::Mutex<MultiPvTracker>>,
+    /// Set by every `position` command; the output reader checks `bestmove` against it and
+    /// crash reports include it. None before the first and after one that is not a legal
+    /// position.
+    last_position: Arc<std::sync::Mutex<Option<LastPosition>>>,
+    /// Last STDERR_TAIL_LINES lines of stderr, filled by the error reader
+    stderr_tail: Arc<std::sync::Mutex<VecDeque<String>>>,
+    /// Notified by the output reader on every `readyok`
+    readyok: Arc<Notify>,
+    /// Set while a heartbeat `isready` is unanswered; the output reader swallows its `readyok`
+    /// so it cannot be taken for the answer to an `isready` sent by the frontend
+    heartbeat_pending: Arc<AtomicBool>,
+    /// Last ENGINE_MESSAGE_LIMIT `info string` messages, oldest first
+    messages: Arc<std::sync::Mutex<VecDeque<EngineMessage>>>,
+    process: Option<Child>,
+    stdin: Option<ChildStdin>,
+    #[allow(dead_code)]
+    command_tx: mpsc::Sender<String>,
+    stop_tx: mpsc::Sender<()>,
+}
+
+impl EngineInstance {
+    /// Create a new engine instance (doesn't start the process yet)
+    pub fn new(runtime_id: &RuntimeId, name: String, path: String, purpose: InstancePurpose) -> Self {
+        let (command_tx, _command_rx) = mpsc::channel(100);
+        let (stop_tx, _stop_rx) = mpsc::channel(1);
+        
+        Self {
+            id: runtime_id.to_string(),
+            name,
+            purpose,
+            path,
+            status: EngineStatus::Stopped,
+            keep_alive: false,
+            console: Arc::new(AtomicBool::new(false)),
+            last_command_at: tokio::time::Instant::now(),
+            search_started_at: None,
+            analysis: Arc::new(std::sync::Mutex::new(MultiPvTracker::new())),
+            last_position: Arc::new(std::sync::Mutex::new(None)),
+            stderr_tail: Arc::new(std::sync::Mutex::new(VecDeque::with_capacity(STDERR_TAIL_LINES))),
+            readyok: Arc::new(Notify::new()),
+            heartbeat_pending: Arc::new(AtomicBool::new(false)),
+            messages: Arc::new(std::sync::Mutex::new(VecDeque::with_capacity(ENGINE_MESSAGE_LIMIT))),
+            process: None,
+            stdin: None,
+            command_tx,
+            stop_tx,
+        }
+    }
+
+    /// Send a USI command to the engine
+    pub async fn send_command(&mut self, command: &str) -> Result<()> {
+        if let Some(stdin) = &mut self.stdin {
+            self.last_command_at = tokio::time::Instant::now();
+            match command.split_whitespace().next() {
+                Some("go") => {
+                    self.analysis.lock().unwrap_or_else(|e| e.into_inner()).reset();
+                    self.search_started_at = Some(self.last_command_at);
+                    if !self.console.load(Ordering::Relaxed) {
+                        self.status = EngineStatus::Thinking;
+                    }
+                }
+                Some("position") => {
+                    let mut last_position = self.last_position.lock().unwrap_or_else(|e| e.into_inner());
+                    let last = LastPosition::from_command(command, last_position.as_ref());
+                    if last.is_none() {
+                        log::warn!("Engine {} was sent a position that is not legal: {}", self.id, command);
+                    }
+                    *last_position = last;
+                }
+                _ => {}
+            }
+            stdin.write_all(command.as_bytes()).await?;
+            stdin.write_all(b"\n").await?;
+            stdin.flush().await?;
+            
+            // Log important commands at info level, others at debug
+            let trimmed = command.trim();
+            if trimmed.starts_with("go ") || trimmed == "go" 
+                || trimmed.starts_with("position ") 
+                || trimmed == "usi" 
+                || trimmed == "isready"
+                || trimmed.starts_with("setoption ") {
+                log::info!("Sent command to engine {}: {}", self.id, command);
+            } else {
+                log::debug!("Sent command to engine {}: {}", self.id, command);
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("Engine stdin not available"))
+        }
+    }
+
+    /// Send `isready` as a heartbeat; unlike a command it does not count as activity for the
+    /// idle policy
+    async fn send_heartbeat(&mut self) -> Result<()> {
+        let stdin = self.stdin.as_mut().ok_or_else(|| anyhow!("Engine stdin not available"))?;
+        self.heartbeat_pending.store(true, Ordering::Relaxed);
+        stdin.write_all(b"isready\n").await?;
+        stdin.flush().await?;
+        log::debug!("Sent heartbeat to engine {}", self.id);
+        Ok(())
+    }
+
+    /// Whether the process has exited, e.g. after a crash
+    fn has_exited(&mut self) -> bool {
+        match &mut self.process {
+            Some(process) => !matches!(process.try_wait(), Ok(None)),
+            None => true,
+        }
+    }
+
+    /// The last valid position sent
+    fn position(&self) -> Option<EnginePosition> {
+        self.last_position.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|last| last.sent.clone())
+    }
+
+    /// Exit status of the process if it has exited; polling reaps it
+    fn exit_status(&mut self) -> Option<std::process::ExitStatus> {
+        self.process.as_mut()?.try_wait().ok().flatten()
+    }
+
+    /// Stop the engine process
+    pub async fn stop(&mut self) -> Result<()> {
+        log::info!("Stopping engine: {}", self.id);
+        
+        // Try to send quit command gracefully
+        if let Err(e) = self.send_command("quit").await {
+            log::warn!("Failed to send quit command to engine {}: {}", self.id, e);
+        }
+
+        // Signal the output reader task to stop
+        let _ = self.stop_tx.send(()).await;
+
+        // Kill the process, and any helpers it started, if it doesn't stop gracefully
+        if let Some(process) = &mut self.process {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            engine_process::kill(process).await;
+        }
+
+        self.status = EngineStatus::Stopped;
+        self.process = None;
+        self.stdin = None;
+
+        Ok(())
+    }
+}
+
+/// Shared state of an instance that its output reader updates
+struct ReaderState {
+    analysis: Arc<std::sync::Mutex<MultiPvTracker>>,
+    messages: Arc<std::sync::Mutex<VecDeque<EngineMessage>>>,
+    last_position: Arc<std::sync::Mutex<Option<LastPosition>>>,
+    console: Arc<AtomicBool>,
+    heartbeat_pending: Arc<AtomicBool>,
+}
+
+/// Manages all USI engine instances
+pub struct EngineManager {
+    engines: EngineMap,
+    /// Last instance number handed out per config ID
+    instance_counters: std::sync::Mutex<HashMap<String, u32>>,
+    /// Applied to engines spawned after it is set
+    output_throttle: std::sync::RwLock<OutputThrottle>,
+    startup_policy: std::sync::RwLock<StartupPolicy>,
+    idle_policy: std::sync::RwLock<IdlePolicy>,
+    /// Shared with the watchdogs of running engines
+    heartbeat_policy: Arc<std::sync::RwLock<HeartbeatPolicy>>,
+    /// Instances of keep-warm engines, by config ID
+    warm_pool: Mutex<HashMap<String, WarmSlot>>,
+    /// Most recent crashes this session, by config ID, oldest first
+    crash_history: Arc<std::sync::Mutex<HashMap<String, VecDeque<CrashReport>>>>,
+    /// Usage not yet written to storage, by config ID
+    usage: Arc<std::sync::Mutex<HashMap<String, EngineUsage>>>,
+    events: SharedEventSink,
+}
+
+impl EngineManager {
+    pub fn new(events: SharedEventSink) -> Self {
+        Self {
+            engines: Arc::new(RwLock::new(HashMap::new())),
+            instance_counters: std::sync::Mutex::new(HashMap::new()),
+            output_throttle: std::sync::RwLock::new(OutputThrottle::default()),
+            startup_policy: std::sync::RwLock::new(StartupPolicy::default()),
+            idle_policy: std::sync::RwLock::new(IdlePolicy::default()),
+            heartbeat_policy: Arc::new(std::sync::RwLock::new(HeartbeatPolicy::default())),
+            warm_pool: Mutex::new(HashMap::new()),
+            crash_history: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            usage: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            events,
+        }
+    }
+
+    fn record_usage(usage: &std::sync::Mutex<HashMap<String, EngineUsage>>, config_id: &str, recorded: EngineUsage) {
+        if !recorded.is_empty() {
+            usage
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(config_id.to_string())
+                .or_default()
+                .add(&recorded);
+        }
+    }
+
+    /// Set how stdout of engines spawned from now on is buffered and rate limited
+    pub fn set_output_throttle(&self, throttle: OutputThrottle) {
+        *self.output_throttle.write().unwrap_or_else(|e| e.into_inner()) = throttle;
+    }
+
+    /// Set the retries used when starting engines
+    pub fn set_startup_policy(&self, policy: StartupPolicy) {
+        *self.startup_policy.write().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    fn startup_policy(&self) -> StartupPolicy {
+        *self.startup_policy.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set when idle engines are stopped; applies to running engines too
+    pub fn set_idle_policy(&self, policy: IdlePolicy) {
+        *self.idle_policy.write().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    fn idle_policy(&self) -> IdlePolicy {
+        *self.idle_policy.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set how idle engines are probed for liveness; applies to running engines too
+    pub fn set_heartbeat_policy(&self, policy: HeartbeatPolicy) {
+        *self.heartbeat_policy.write().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    fn emit_init_progress(&self, runtime_id: &RuntimeId, stage: InitStage, since: Option<tokio::time::Instant>) {
+        let progress = InitProgress {
+            stage,
+            elapsed_ms: since.map_or(0, |since| since.elapsed().as_millis() as u64),
+        };
+        if let Err(e) = self.events.send(&ENGINE_INIT_PROGRESS.scope(runtime_id), &progress) {
+            log::error!("Failed to emit init progress event: {}", e);
+        }
+    }
+
+    fn next_runtime_id(&self, config_id: &str) -> RuntimeId {
+        let mut counters = self.instance_counters.lock().unwrap_or_else(|e| e.into_inner());
+        let counter = counters.entry(config_id.to_string()).or_insert(0);
+        *counter += 1;
+        RuntimeId {
+            config_id: config_id.to_string(),
+            instance: *counter,
+        }
+    }
+
+    /// Look up a running engine by runtime ID, or by config ID if it has a single instance
+    async fn find_engine(&self, engine_id: &str) -> Result<(RuntimeId, Arc<Mutex<EngineInstance>>)> {
+        let engines = self.engines.read().await;
+        let runtime_id = resolve_runtime_id(engines.keys(), engine_id)?.clone();
+        let engine = engines[&runtime_id].clone();
+        Ok((runtime_id, engine))
+    }
+
+    /// Resolve an engine ID (runtime ID, or config ID with a single instance) to its runtime ID
+    pub async fn runtime_id(&self, engine_id: &str) -> Result<RuntimeId> {
+        Ok(self.find_engine(engine_id).await?.0)
+    }
+
+    /// Spawn a new process for the engine config `config_id` and return its runtime ID.
+    /// Any number of instances of one config may run at once; USI events are emitted per
+    /// runtime ID (`usi-message::<runtime id>`), so instances never see each other's output.
+    pub async fn spawn_engine(
+        &self,
+        config_id: &str,
+        name: String,
+        path: String,
+        purpose: InstancePurpose,
+        priority: Option<ProcessPriority>,
+    ) -> Result<RuntimeId> {
+        log::info!("Spawning engine: {} at path: {} ({:?})", name, path, purpose);
+
+        // Create engine instance
+        let runtime_id = self.next_runtime_id(config_id);
+        let id = runtime_id.clone();
+        let mut engine = EngineInstance::new(&runtime_id, name.clone(), path.clone(), purpose);
+        engine.status = EngineStatus::Starting;
+
+        // Determine working directory - use the engine's directory
+        // This is critical for engines like Apery that need access to data files
+        let working_dir = std::path::Path::new(&path)
+            .parent()
+            .map(|p| p.to_path_buf());
+        
+        log::info!("Engine working directory: {:?}", working_dir);
+        
+        // Spawn the process
+        let mut command = Command::new(&path);
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        
+        // Set working directory if we have one
+        if let Some(dir) = working_dir {
+            command.current_dir(dir);
+        }
+        engine_process::prepare(&mut command);
+        process_priority::apply_before_spawn(&mut command, priority);
+        
+        let mut child = command.spawn()
+            .map_err(|e| anyhow!("Failed to spawn engine process: {}", e))?;
+        engine_process::attach(&child);
+        if let Err(e) = process_priority::apply_after_spawn(&child, priority) {
+            log::warn!("Engine {} priority: {}", runtime_id, e);
+        }
+
+        log::info!("Engine process spawned, PID: {:?}", child.id());
+        metrics::ENGINES_SPAWNED.increment();
+
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("Failed to get stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("Failed to get stdout"))?;
+        let stderr = child.stderr.take().ok_or_else(|| anyhow!("Failed to get stderr"))?;
+
+        engine.process = Some(child);
+        engine.stdin = Some(stdin);
+        let analysis = engine.analysis.clone();
+        let messages = engine.messages.clone();
+        let last_position = engine.last_position.clone();
+        let stderr_tail = engine.stderr_tail.clone();
+        let console = engine.console.clone();
+        let heartbeat_pending = engine.heartbeat_pending.clone();
+        let stdout_closed = Arc::new(Notify::new());
+
+        let engine_arc = Arc::new(Mutex::new(engine));
+
+        // Store the engine
+        {
+            let mut engines = self.engines.write().await;
+            engines.insert(id.clone(), engine_arc.clone());
+        }
+
+        // Spawn stdout reader task
+        let readers = ReaderState { analysis, messages, last_position, console: console.clone(), heartbeat_pending };
+        self.spawn_output_reader(id.clone(), stdout, readers, stdout_closed.clone()).await;
+
+        // Spawn stderr reader task
+        self.spawn_error_reader(id.clone(), stderr, stderr_tail, console).await;
+
+        // Spawn watchdog task
+        self.spawn_watchdog(id.clone(), stdout_closed).await;
+
+        // Give the engine process a moment to start up before we try to communicate
+        // This prevents race conditions where we try to write to stdin before the engine is ready
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        self.emit_init_progress(&id, InitStage::Spawned, None);
+        log::info!("Engine {} spawned successfully", id);
+        Ok(runtime_id)
+    }
+
+    /// Spawn a task to read engine stdout and a task to emit it. Status changes are applied
+    /// as lines arrive; emission goes through a bounded buffer and the info rate limit so a
+    /// flood of search output never holds up the reader or saturates the event sink.
+    /// Info lines are also merged into `analysis`, whose snapshot is emitted as
+    /// `analysis-snapshot::<runtime id>` at most every `snapshot_interval_ms`. `info string` lines
+    /// are kept in `messages` and emitted on their own. Each finished search is added to the
+    /// pending usage of the engine, and each `bestmove` is checked against the last position
+    /// and emitted as `engine-bestmove::<runtime id>`; the `readyok` answering a heartbeat is
+    /// not emitted at all. While `console` is set, lines bypass
+    /// all of this and go straight to `engine-console::<runtime id>`. `stdout_closed` is
+    /// notified when the engine closes its stdout, which usually means it exited.
+    async fn spawn_output_reader(
+        &self,
+        engine_id: RuntimeId,
+        stdout: ChildStdout,
+        readers: ReaderState,
+        stdout_closed: Arc<Notify>,
+    ) {
+        let ReaderState { analysis, messages, last_position, console, heartbeat_pending } = readers;
+        let events = self.events.clone();
+        let engines = self.engines.clone();
+        let usage = self.usage.clone();
+        let throttle = *self.output_throttle.read().unwrap_or_else(|e| e.into_inner());
+        let buffer = Arc::new(std::sync::Mutex::new(OutputBuffer::new(throttle.buffer_lines)));
+        let wake = Arc::new(Notify::new());
+
+        let reader_buffer = buffer.clone();
+        let reader_wake = wake.clone();
+        let reader_analysis = analysis.clone();
+        let reader_id = engine_id.clone();
+        let reader_events = events.clone();
+        tokio::spawn(async move {
+            let engine_id = reader_id;
+            let message_event = ENGINE_MESSAGE.scope(&engine_id);
+            let bestmove_event = ENGINE_BESTMOVE.scope(&engine_id);
+            let mut lines = EngineLines::new(stdout);
+
+            let mut line_count = 0;
+            while let Ok(Some(line)) = lines.next_line().await {
+                line_count += 1;
+                log::debug!("Engine {} output: {}", engine_id, line);
+
+                if console.load(Ordering::Relaxed) {
+                    emit_console_line(&reader_events, &engine_id, ConsoleDirection::Received, &line);
+                    continue;
+                }
+
+                // Update engine status based on output
+                if line.contains("usiok") {
+                    log::info!("Engine {} responded with usiok", engine_id);
+                    if let Some(engine) = engines.read().await.get(&engine_id) {
+                        engine.lock().await.status = EngineStatus::Ready;
+                    }
+                } else if line.contains("readyok") {
+                    log::info!("Engine {} responded with readyok", engine_id);
+                    if let Some(engine) = engines.read().await.get(&engine_id) {
+                        let mut engine = engine.lock().await;
+                        if engine.status != EngineStatus::Thinking {
+                            engine.status = EngineStatus::Ready;
+                        }
+                        engine.readyok.notify_waiters();
+                    }
+                    if heartbeat_pending.swap(false, Ordering::Relaxed) {
+                        continue;
+                    }
+                } else if line.starts_with("bestmove") {
+                    log::info!("Engine {} responded with bestmove: {}", engine_id, line);
+                    let nodes = {
+                        let mut analysis = reader_analysis.lock().unwrap_or_else(|e| e.into_inner());
+                        analysis.finish();
+                        analysis.nodes()
+                    };
+                    if let Some(engine) = engines.read().await.get(&engine_id) {
+                        let mut engine = engine.lock().await;
+                        engine.status = EngineStatus::Ready;
+                        let search_time = engine.search_started_at.take().map(|started| started.elapsed());
+                        let analysis_ms = match (engine.purpose, search_time) {
+                            (InstancePurpose::Analysis, Some(time)) => time.as_millis() as u64,
+                            _ => 0,
+                        };
+                        Self::record_usage(
+                            &usage,
+                            &engine_id.config_id,
+                            EngineUsage { nodes_searched: nodes.unwrap_or(0), analysis_ms, ..Default::default() },
+                        );
+                    }
+                    let report = {
+                        let last = last_position.lock().unwrap_or_else(|e| e.into_inner());
+                        check_bestmove(&line, last.as_ref())
+                    };
+                    if let Some(report) = report {
+                        if report.legal == Some(false) {
+                            let error = report.error.as_deref().unwrap_or_default();
+                            log::warn!("Engine {} sent a bad bestmove {}: {}", engine_id, report.bestmove, error);
+                        }
+                        if let Err(e) = reader_events.send(&bestmove_event, &report) {
+                            log::error!("Failed to emit bestmove: {}", e);
+                        }
+                    }
+                } else if line.starts_with("id ") {
+                    log::debug!("Engine {} identification: {}", engine_id, line);
+                } else if line.starts_with("option ") {
+                    log::debug!("Engine {} option: {}", engine_id, line);
+                } else if let Some(text) = info_string(&line) {
+                    log::info!("Engine {} message: {}", engine_id, text);
+                    let message = EngineMessage {
+                        message: text.to_string(),
+                        timestamp: chrono::Utc::now().to_rfc3339(),
+                    };
+                    {
+                        let mut messages = messages.lock().unwrap_or_else(|e| e.into_inner());
+                        if messages.len() == ENGINE_MESSAGE_LIMIT {
+                            messages.pop_front();
+                        }
+                        messages.push_back(message.clone());
+                    }
+                    if let Err(e) = reader_events.send(&message_event, &message) {
+                        log::error!("Failed to emit engine message: {}", e);
+                    }
+                    continue;
+                } else if let Some(info) = usi_info::parse_info(&line) {
+                    reader_analysis.lock().unwrap_or_else(|e| e.into_inner()).update(&info);
+                }
+
+                reader_buffer.lock().unwrap_or_else(|e| e.into_inner()).push(line);
+                reader_wake.notify_one();
+            }
+
+            reader_buffer.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+            reader_wake.notify_one();
+            stdout_closed.notify_one();
+            log::warn!("Engine {} stdout reader task ended after {} lines", engine_id, line_count);
+        });
+
+        tokio::spawn(async move {
+            let event_name = USI_MESSAGE.scope(&engine_id);
+            let snapshot_event = ANALYSIS_SNAPSHOT.scope(&engine_id);
+            let emit = |line: String| {
+                if let Err(e) = events.send(&event_name, &line) {
+                    log::error!("Failed to emit USI message event: {}", e);
+                }
+            };
+            let mut limiter = InfoRateLimiter::new(throttle.max_info_per_sec, tokio::time::Instant::now());
+            let snapshot_interval = Duration::from_millis(throttle.snapshot_interval_ms);
+            let mut next_snapshot = tokio::time::Instant::now();
+            let mut snapshot_pending = false;
+            loop {
+                let deadline = match (limiter.next_deadline(), snapshot_pending) {
+                    (Some(deadline), true) => Some(deadline.min(next_snapshot)),
+                    (None, true) => Some(next_snapshot),
+                    (deadline, false) => deadline,
+                };
+                tokio::select! {
+                    _ = wake.notified() => {}
+                    _ = async {
+                        match deadline {
+                            Some(deadline) => tokio::time::sleep_until(deadline).await,
+                            None => std::future::pending().await,
+                        }
+                    } => {}
+                }
+
+                let (lines, closed) = {
+                    let mut buffer = buffer.lock().unwrap_or_else(|e| e.into_inner());
+                    (buffer.drain(), buffer.closed)
+                };
+                let now = tokio::time::Instant::now();
+                for line in lines {
+                    limiter.admit(line, now).into_iter().for_each(&emit);
+                }
+                limiter.poll(now).into_iter().for_each(&emit);
+
+                let snapshot = {
+                    let mut analysis = analysis.lock().unwrap_or_else(|e| e.into_inner());
+                    snapshot_pending |= analysis.take_changed() && throttle.snapshot_interval_ms > 0;
+                    (snapshot_pending && (now >= next_snapshot || closed)).then(|| analysis.snapshot())
+                };
+                if let Some(snapshot) = snapshot {
+                    if let Err(e) = events.send(&snapshot_event, &snapshot) {
+                        log::error!("Failed to emit analysis snapshot: {}", e);
+                    }
+                    snapshot_pending = false;
+                    next_snapshot = now + snapshot_interval;
+                }
+                if closed {
+                    limiter.flush().into_iter().for_each(&emit);
+                    break;
+                }
+            }
+
+            let dropped = buffer.lock().unwrap_or_else(|e| e.into_inner()).dropped();
+            if dropped > 0 || limiter.coalesced() > 0 {
+                log::info!(
+                    "Engine {} output: {} info lines dropped, {} coalesced",
+                    engine_id,
+                    dropped,
+                    limiter.coalesced()
+                );
+            }
+        });
+    }
+
+    /// Spawn a task to read engine stderr and emit error events, keeping the last lines in
+    /// `stderr_tail` for crash reports. In console mode the lines go to the console instead.
+    async fn spawn_error_reader(
+        &self,
+        engine_id: RuntimeId,
+        stderr: tokio::process::ChildStderr,
+        stderr_tail: Arc<std::sync::Mutex<VecDeque<String>>>,
+        console: Arc<AtomicBool>,
+    ) {
+        let events = self.events.clone();
+
+        tokio::spawn(async move {
+            let mut lines = EngineLines::new(stderr);
+
+            let mut line_count = 0;
+            while let Ok(Some(line)) = lines.next_line().await {
+                line_count += 1;
+                log::warn!("Engine {} stderr: {}", engine_id, line);
+                {
+                    let mut tail = stderr_tail.lock().unwrap_or_else(|e| e.into_inner());
+                    if tail.len() == STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line.clone());
+                }
+
+                if console.load(Ordering::Relaxed) {
+                    emit_console_line(&events, &engine_id, ConsoleDirection::Stderr, &line);
+                    continue;
+                }
+
+                // Emit error event to frontend
+                if let Err(e) = events.send(&USI_ERROR.scope(&engine_id), &line) {
+                    log::error!("Failed to emit USI error event: {}", e);
+                }
+            }
+
+            log::warn!("Engine {} stderr reader task ended after {} lines", engine_id, line_count);
+        });
+    }
+
+    /// Spawn a watchdog task to detect crashes and hangs. A process that exits on its own is
+    /// reaped and, unless it exited successfully, reported with its exit code, signal and
+    /// stderr tail in `usi-error::<runtime id>` and `engine-crashed`, and added to its config's
+    /// crash history. Idle engines are probed per the heartbeat policy.
+    async fn spawn_watchdog(&self, engine_id: RuntimeId, stdout_closed: Arc<Notify>) {
+        let engines = self.engines.clone();
+        let events = self.events.clone();
+        let crash_history = self.crash_history.clone();
+        let heartbeat_policy = self.heartbeat_policy.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let heartbeat = *heartbeat_policy.read().unwrap_or_else(|e| e.into_inner());
+                let interval = match heartbeat.interval_ms {
+                    0 => Duration::from_secs(30),
+                    ms => Duration::from_millis(ms),
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    // Give a process that closed its stdout a moment to exit and flush stderr
+                    _ = stdout_closed.notified() => tokio::time::sleep(Duration::from_millis(200)).await,
+                }
+
+                let engines_lock = engines.read().await;
+                let Some(engine) = engines_lock.get(&engine_id) else {
+                    // Engine removed from manager, exit watchdog
+                    break;
+                };
+                let mut engine_lock = engine.lock().await;
+                if engine_lock.process.is_none() {
+                    // Engine stopped, exit watchdog
+                    break;
+                }
+                // `id()` stays set until the child is waited on, so poll its exit status,
+                // which also reaps it
+                let Some(status) = engine_lock.exit_status() else {
+                    let idle = heartbeat.interval_ms > 0
+                        && engine_lock.status == EngineStatus::Ready
+                        && engine_lock.search_started_at.is_none()
+                        && !engine_lock.console.load(Ordering::Relaxed)
+                        && engine_lock.last_command_at.elapsed() >= interval;
+                    drop(engine_lock);
+                    drop(engines_lock);
+                    if idle {
+                        probe_engine(&engines, &engine_id, heartbeat.timeout_ms, &events).await;
+                    }
+                    continue;
+                };
+
+                if status.success() {
+                    log::info!("Engine {} process exited", engine_id);
+                    engine_lock.status = EngineStatus::Stopped;
+                    break;
+                }
+
+                let report = CrashReport {
+                    runtime_id: engine_id.to_string(),
+                    config_id: engine_id.config_id.clone(),
+                    name: engine_lock.name.clone(),
+                    exit_code: status.code(),
+                    signal: exit_signal(&status),
+                    stderr_tail: engine_lock
+                        .stderr_tail
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .iter()
+                        .cloned()
+                        .collect(),
+                    position: engine_lock.position(),
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                };
+                log::error!("Engine {} crashed: {}", engine_id, report.message());
+                engine_lock.status = EngineStatus::Crashed { code: report.exit_code };
+                drop(engine_lock);
+                drop(engines_lock);
+
+                {
+                    let mut history = crash_history.lock().unwrap_or_else(|e| e.into_inner());
+                    let reports = history.entry(report.config_id.clone()).or_default();
+                    if reports.len() == CRASH_HISTORY_LIMIT {
+                        reports.pop_front();
+                    }
+                    reports.push_back(report.clone());
+                }
+
+                let _ = events.send(&USI_ERROR.scope(&engine_id), &report.message());
+                if let Err(e) = events.send(&ENGINE_CRASHED, &report) {
+                    log::error!("Failed to emit engine crash: {}", e);
+                }
+                break;
+            }
+
+            log::info!("Engine {} watchdog task ended", engine_id);
+        });
+    }
+
+    /// The last position sent to a running engine with its move list; None before the first
+    /// `position` command, after one that was not a legal position, and after `new_game`
+    pub async fn engine_position(&self, engine_id: &str) -> Result<Option<EnginePosition>> {
+        let (_, engine) = self.find_engine(engine_id).await?;
+        let position = engine.lock().await.position();
+        Ok(position)
+    }
+
+    /// The last `info string` messages of a running engine, oldest first
+    /// Accepts a runtime ID, or a config ID with a single running instance
+    pub async fn engine_messages(&self, engine_id: &str) -> Result<Vec<EngineMessage>> {
+        let (_, engine) = self.find_engine(engine_id).await?;
+        let messages = engine.lock().await.messages.clone();
+        let messages = messages.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(messages.iter().cloned().collect())
+    }
+
+    /// The consolidated analysis of a running engine's current or last search, as it stands
+    /// now. Accepts a runtime ID, or a config ID with a single running instance.
+    pub async fn latest_analysis(&self, engine_id: &str) -> Result<AnalysisSnapshot> {
+        let (_, engine) = self.find_engine(engine_id).await?;
+        let analysis = engine.lock().await.analysis.clone();
+        let snapshot = analysis.lock().unwrap_or_else(|e| e.into_inner()).snapshot();
+        Ok(snapshot)
+    }
+
+    /// Crashes of engines of config `config_id` this session, oldest first
+    pub fn crash_history(&self, config_id: &str) -> Vec<CrashReport> {
+        self.crash_history
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(config_id)
+            .map(|reports| reports.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Send a USI command to a specific engine
+    /// Accepts a runtime ID, or a config ID with a single running instance
+    pub async fn send_command(&self, engine_id: &str, command: &str) -> Result<()> {
+        let (runtime_id, engine) = self.find_engine(engine_id).await?;
+
+        let mut engine_lock = engine.lock().await;
+        engine_lock.send_command(command).await?;
+        if engine_lock.console.load(Ordering::Relaxed) {
+            emit_console_line(&self.events, &runtime_id, ConsoleDirection::Sent, command);
+        }
+        if command.trim() == "usinewgame" && engine_lock.purpose == InstancePurpose::Game {
+            Self::record_usage(&self.usage, &runtime_id.config_id, EngineUsage { games_played: 1, ..Default::default() });
+        }
+        Ok(())
+    }
+
+    /// Send a USI command with timeout
+    pub async fn send_command_with_timeout(
+        &self,
+        engine_id: &str,
+        command: &str,
+        timeout_duration: Duration,
+    ) -> Result<()> {
+        timeout(timeout_duration, self.send_command(engine_id, command))
+            .await
+            .map_err(|_| anyhow!("Command timeout"))?
+    }
+
+
+    /// Initialize an engine with temporary options (for one-time game use)
+    /// If temp_options is Some, use those; otherwise fall back to saved options.
+    /// The engine's eval file (or `eval_file_id`, if given) is selected last.
+    pub async fn initialize_engine_with_temp_options(
+        &self, 
+        engine_id: &str, 
+        engine_storage: &tokio::sync::RwLock<crate::engine_storage::EngineStorage>,
+        temp_options: Option<&std::collections::HashMap<String, String>>,
+        eval_file_id: Option<&str>,
+    ) -> Result<()> {
+        log::info!("Initializing engine with {} options: {}", 
+            if temp_options.is_some() { "temporary" } else { "saved" }, 
+            engine_id
+        );
+        let (runtime_id, _) = self.find_engine(engine_id).await?;
+        let init_start = tokio::time::Instant::now();
+        let timeouts = TimeoutConfig::for_engine(engine_storage.read().await.get_engine(&runtime_id.config_id));
+        let option_timeout = Duration::from_millis(timeouts.option_send_ms);
+
+        // Send usi command
+        log::info!("Sending 'usi' command to engine: {}", engine_id);
+        self.send_command_with_timeout(engine_id, "usi", Duration::from_secs(5))
+            .await?;
+        self.emit_init_progress(&runtime_id, InitStage::UsiSent, Some(init_start));
+
+        // Wait for usiok response by polling engine status
+        log::info!("Waiting for usiok from engine: {}", engine_id);
+        let start = tokio::time::Instant::now();
+        loop {
+            if start.elapsed() > Duration::from_millis(timeouts.usiok_ms) {
+                return Err(anyhow!("Timeout waiting for usiok after {} ms", timeouts.usiok_ms));
+            }
+            
+            let engines = self.engines.read().await;
+            
+            if let Some(engine) = engines.get(&runtime_id) {
+                let status = engine.lock().await.status.clone();
+                if matches!(status, EngineStatus::Ready) {
+                    log::info!("Received usiok from engine: {}", engine_id);
+                    self.emit_init_progress(&runtime_id, InitStage::UsiokReceived, Some(init_start));
+                    break;
+                }
+            } else {
+                return Err(anyhow!("Engine not found"));
+            }
+            
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        // Temporary or saved options, then the eval file's, which take precedence over saved
+        // EvalDir/EvalFile values
+        let options = engine_storage.read().await.init_options(&runtime_id.config_id, temp_options, eval_file_id);
+        if !options.is_empty() {
+            log::info!("Sending {} options to engine: {}", options.len(), engine_id);
+        }
+        for (option_name, option_value) in &options {
+            let option_command = setoption_command(option_name, option_value);
+            log::debug!("Sending option command: {}", option_command);
+            if let Err(e) = self.send_command_with_timeout(engine_id, &option_command, option_timeout).await {
+                log::warn!("Failed to send option '{}' to engine {}: {}", option_name, engine_id, e);
+            }
+        }
+
+        self.emit_init_progress(&runtime_id, InitStage::OptionsSent, Some(init_start));
+
+        // Back to Starting so that only readyok marks the engine Ready again
+        if let Some(engine) = self.engines.read().await.get(&runtime_id) {
+            engine.lock().await.status = EngineStatus::Starting;
+        }
+
+        // Send isready command
+        log::info!("Sending 'isready' command to engine: {}", engine_id);
+        self.send_command_with_timeout(engine_id, "isready", Duration::from_secs(5))
+            .await?;
+        self.emit_init_progress(&runtime_id, InitStage::IsreadySent, Some(init_start));
+
+        // Wait for readyok response by polling engine status
+        log::info!("Waiting for readyok from engine: {}", engine_id);
+        let start = tokio::time::Instant::now();
+        loop {
+            if start.elapsed() > Duration::from_millis(timeouts.readyok_ms) {
+                return Err(anyhow!("Timeout waiting for readyok after {} ms", timeouts.readyok_ms));
+            }
+            
+            let engines = self.engines.read().await;
+            
+            if let Some(engine) = engines.get(&runtime_id) {
+                let status = engine.lock().await.status.clone();
+                if matches!(status, EngineStatus::Ready) {
+                    log::info!("Received readyok from engine: {}", engine_id);
+                    self.emit_init_progress(&runtime_id, InitStage::ReadyokReceived, Some(init_start));
+                    break;
+                }
+            } else {
+                return Err(anyhow!("Engine not found"));
+            }
+            
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        log::info!("Engine initialization complete: {}", engine_id);
+        Ok(())
+    }
+
+
+    /// Spawn and initialize an engine, retrying per the startup policy. A failed attempt
+    /// moves the instance from Starting to Error, reports the reason as `usi-error::<runtime id>`
+    /// and stops it; when no attempt succeeds, `engine-init-failed` is emitted.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start_engine(
+        &self,
+        config_id: &str,
+        name: String,
+        path: String,
+        purpose: InstancePurpose,
+        priority: Option<ProcessPriority>,
+        engine_storage: &tokio::sync::RwLock<crate::engine_storage::EngineStorage>,
+        temp_options: Option<&std::collections::HashMap<String, String>>,
+        eval_file_id: Option<&str>,
+    ) -> Result<RuntimeId> {
+        if let Some(engine) = engine_storage.read().await.get_engine(config_id) {
+            engine.ensure_enabled()?;
+        }
+        let policy = self.startup_policy();
+        let attempts = policy.retries + 1;
+        let mut last_error = anyhow!("Engine was not started");
+
+        for attempt in 1..=attempts {
+            if attempt > 1 {
+                log::warn!("Retrying start of engine {} (attempt {} of {})", name, attempt, attempts);
+                tokio::time::sleep(Duration::from_millis(policy.retry_delay_ms)).await;
+            }
+            let runtime_id = match self.spawn_engine(config_id, name.clone(), path.clone(), purpose, priority).await {
+                Ok(runtime_id) => runtime_id,
+                Err(e) => {
+                    log::error!("Failed to spawn engine {}: {}", name, e);
+                    last_error = e;
+                    continue;
+                }
+            };
+            let engine_id = runtime_id.to_string();
+            match self
+                .initialize_engine_with_temp_options(&engine_id, engine_storage, temp_options, eval_file_id)
+                .await
+            {
+                Ok(()) => return Ok(runtime_id),
+                Err(e) => {
+                    log::error!("Failed to initialize engine {}: {}", engine_id, e);
+                    if let Ok((_, engine)) = self.find_engine(&engine_id).await {
+                        engine.lock().await.status = EngineStatus::Error;
+                    }
+                    let message = format!("Initialization failed (attempt {} of {}): {}", attempt, attempts, e);
+                    let _ = self.events.send(&USI_ERROR.scope(&engine_id), &message);
+                    let _ = self.stop_engine(&engine_id).await;
+                    last_error = e;
+                }
+            }
+        }
+
+        let failed = EngineInitFailed {
+            config_id: config_id.to_string(),
+            name: name.clone(),
+            attempts,
+            error: last_error.to_string(),
+        };
+        if let Err(e) = self.events.send(&ENGINE_INIT_FAILED, &failed) {
+            log::error!("Failed to emit engine init failure: {}", e);
+        }
+        Err(anyhow!("{} failed to start after {} attempts: {}", name, attempts, last_error))
+    }
+
+    /// Prepare a running engine for a new game in the order USI expects: a search in
+    /// progress, pondering included, is stopped and its `bestmove` awaited, then `isready` is
+    /// answered with `readyok` before `usinewgame` is sent. The position and analysis of the
+    /// previous game are forgotten. Waits use the engine's timeouts.
+    pub async fn new_game(&self, engine_id: &str, engine_storage: &RwLock<EngineStorage>) -> Result<()> {
+        let (runtime_id, engine) = self.find_engine(engine_id).await?;
+        let id = runtime_id.to_string();
+        let timeouts = TimeoutConfig::for_engine(engine_storage.read().await.get_engine(&runtime_id.config_id));
+        let (searching, readyok) = {
+            let engine = engine.lock().await;
+            if engine.console.load(Ordering::Relaxed) {
+                return Err(anyhow!("Engine {} is in console mode", runtime_id));
+            }
+            (engine.search_started_at.is_some(), engine.readyok.clone())
+        };
+
+        if searching {
+            log::info!("Stopping the search of engine {} before a new game", runtime_id);
+            self.send_command(&id, "stop").await?;
+            let stopped = timeout(Duration::from_millis(timeouts.bestmove_slack_ms), async {
+                while engine.lock().await.search_started_at.is_some() {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            })
+            .await;
+            if stopped.is_err() {
+                return Err(anyhow!("No bestmove within {} ms after stop", timeouts.bestmove_slack_ms));
+            }
+        }
+
+        {
+            let engine = engine.lock().await;
+            engine.analysis.lock().unwrap_or_else(|e| e.into_inner()).reset();
+            *engine.last_position.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+
+        // Created before isready is sent, so a quick readyok is not missed
+        let answered = readyok.notified();
+        self.send_command(&id, "isready").await?;
+        if timeout(Duration::from_millis(timeouts.readyok_ms), answered).await.is_err() {
+            return Err(anyhow!("Timeout waiting for readyok after {} ms", timeouts.readyok_ms));
+        }
+        self.send_command(&id, "usinewgame").await?;
+        log::info!("Engine {} is ready for a new game", runtime_id);
+        Ok(())
+    }
+
+    /// Stop a specific engine
+    /// Accepts a runtime ID, or a config ID with a single running instance
+    pub async fn stop_engine(&self, engine_id: &str) -> Result<()> {
+        let (runtime_id, engine) = self.find_engine(engine_id).await?;
+
+        let mut engine_lock = engine.lock().await;
+        engine_lock.stop().await?;
+
+        drop(engine_lock);
+
+        // Remove from manager using the actual runtime ID
+        self.engines.write().await.remove(&runtime_id);
+
+        Ok(())
+    }
+
+    /// Get engine status
+    /// Accepts a runtime ID, or a config ID with a single running instance
+    pub async fn get_engine_status(&self, engine_id: &str) -> Option<EngineStatus> {
+        let engine = self.find_engine(engine_id).await.ok().map(|(_, engine)| engine);
+        
+        engine.map(|engine| {
+            let engine_lock = futures::executor::block_on(engine.lock());
+            engine_lock.status.clone()
+        })
+    }
+
+    /// Get list of all engine runtime IDs
+    pub async fn list_engines(&self) -> Vec<String> {
+        self.engines.read().await.keys().map(RuntimeId::to_string).collect()
+    }
+
+    /// Describe running engine instances, optionally only those of one config
+    pub async fn list_instances(&self, config_id: Option<&str>) -> Vec<EngineInstanceInfo> {
+        let engines: Vec<(RuntimeId, Arc<Mutex<EngineInstance>>)> = self
+            .engines
+            .read()
+            .await
+            .iter()
+            .filter(|(runtime_id, _)| config_id.map_or(true, |id| runtime_id.config_id == id))
+            .map(|(runtime_id, engine)| (runtime_id.clone(), engine.clone()))
+            .collect();
+
+        let mut instances = Vec::with_capacity(engines.len());
+        for (runtime_id, engine) in engines {
+            let engine = engine.lock().await;
+            instances.push(EngineInstanceInfo {
+                runtime_id: runtime_id.to_string(),
+                config_id: runtime_id.config_id.clone(),
+                instance: runtime_id.instance,
+                name: engine.name.clone(),
+                purpose: engine.purpose,
+                status: engine.status.clone(),
+                keep_alive: engine.keep_alive,
+                console: engine.console.load(Ordering::Relaxed),
+                idle_secs: engine.last_command_at.elapsed().as_secs(),
+            });
+        }
+        instances.sort_by(|a, b| (&a.config_id, a.instance).cmp(&(&b.config_id, b.instance)));
+        instances
+    }
+
+    /// Exempt an engine from the idle policy, or make it subject to it again
+    pub async fn set_keep_alive(&self, engine_id: &str, keep_alive: bool) -> Result<()> {
+        let (_, engine) = self.find_engine(engine_id).await?;
+        engine.lock().await.keep_alive = keep_alive;
+        Ok(())
+    }
+
+    /// Put a running engine into console mode for interactive debugging. From now on every
+    /// line sent with [`Self::send_command`] and every line the engine prints, on stdout or
+    /// stderr, is emitted unthrottled as `engine-console::<runtime id>` and nowhere else; the
+    /// status is left as it is instead of following the output, no heartbeats are sent and
+    /// the idle policy does not apply. Returns the runtime ID.
+    pub async fn open_console(&self, engine_id: &str) -> Result<RuntimeId> {
+        let (runtime_id, engine) = self.find_engine(engine_id).await?;
+        let engine = engine.lock().await;
+        if engine.stdin.is_none() {
+            return Err(anyhow!("Engine {} is not running", runtime_id));
+        }
+        engine.console.store(true, Ordering::Relaxed);
+        log::info!("Engine {} in console mode", runtime_id);
+        Ok(runtime_id)
+    }
+
+    /// Leave console mode. Whatever was sent meanwhile is unknown to the status, so it is
+    /// reset to ready; stop any search started from the console first.
+    pub async fn close_console(&self, engine_id: &str) -> Result<()> {
+        let (runtime_id, engine) = self.find_engine(engine_id).await?;
+        let mut engine = engine.lock().await;
+        if engine.console.swap(false, Ordering::Relaxed) {
+            if engine.stdin.is_some() && !engine.has_exited() {
+                engine.status = EngineStatus::Ready;
+            }
+            engine.search_started_at = None;
+            log::info!("Engine {} left console mode", runtime_id);
+        }
+        Ok(())
+    }
+
+    /// Stop every engine that has had no command for longer than the idle policy allows,
+    /// emitting `engine-idle-stopped` for each. Returns the stopped engines.
+    pub async fn stop_idle_engines(&self) -> Vec<RuntimeId> {
+        let policy = self.idle_policy();
+        if policy.idle_timeout_minutes == 0 {
+            return Vec::new();
+        }
+        let limit = Duration::from_secs(policy.idle_timeout_minutes * 60);
+
+        let engines: Vec<(RuntimeId, Arc<Mutex<EngineInstance>>)> = self
+            .engines
+            .read()
+            .await
+            .iter()
+            .map(|(runtime_id, engine)| (runtime_id.clone(), engine.clone()))
+            .collect();
+        let mut stopped = Vec::new();
+        for (runtime_id, engine) in engines {
+            let (name, idle) = {
+                let engine = engine.lock().await;
+                let idle = engine.last_command_at.elapsed();
+                if engine.keep_alive || engine.console.load(Ordering::Relaxed) || idle < limit {
+                    continue;
+                }
+                (engine.name.clone(), idle)
+            };
+
+            log::info!("Stopping engine {} after {} minutes without commands", runtime_id, idle.as_secs() / 60);
+            if let Err(e) = self.stop_engine(&runtime_id.to_string()).await {
+                log::error!("Failed to stop idle engine {}: {}", runtime_id, e);
+                continue;
+            }
+            let event = EngineIdleStopped {
+                runtime_id: runtime_id.to_string(),
+                config_id: runtime_id.config_id.clone(),
+                name,
+                idle_minutes: idle.as_secs() / 60,
+            };
+            if let Err(e) = self.events.send(&ENGINE_IDLE_STOPPED, &event) {
+                log::error!("Failed to emit idle stop event: {}", e);
+            }
+            stopped.push(runtime_id);
+        }
+        stopped
+    }
+
+    /// Apply the idle policy once a minute, for as long as the manager lives
+    pub async fn run_idle_monitor(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            self.stop_idle_engines().await;
+        }
+    }
+
+    /// The running instance of a keep-warm engine, once it has been initialized
+    pub async fn warm_instance(&self, config_id: &str) -> Option<RuntimeId> {
+        let runtime_id = match self.warm_pool.lock().await.get(config_id) {
+            Some(WarmSlot::Running(runtime_id)) => runtime_id.clone(),
+            _ => return None,
+        };
+        let engine = self.engines.read().await.get(&runtime_id).cloned()?;
+        let status = engine.lock().await.status.clone();
+        matches!(status, EngineStatus::Ready | EngineStatus::Thinking).then_some(runtime_id)
+    }
+
+    /// Bring the warm pool in line with the enabled keep-warm engines: start the missing
+    /// ones, restart any that crashed or were stopped, and stop the ones no longer wanted.
+    /// Warm instances run for analysis and are exempt from the idle policy.
+    pub async fn maintain_warm_pool(&self, engine_storage: &RwLock<crate::engine_storage::EngineStorage>) {
+        let wanted: Vec<crate::engine_storage::EngineConfig> = engine_storage
+            .read()
+            .await
+            .get_all_engines()
+            .iter()
+            .filter(|engine| engine.keep_warm && engine.enabled)
+            .cloned()
+            .collect();
+
+        let unwanted: Vec<WarmSlot> = {
+            let mut pool = self.warm_pool.lock().await;
+            let config_ids: Vec<String> = pool
+                .keys()
+                .filter(|config_id| !wanted.iter().any(|engine| &engine.id == *config_id))
+                .cloned()
+                .collect();
+            config_ids.iter().filter_map(|config_id| pool.remove(config_id)).collect()
+        };
+        for slot in unwanted {
+            if let WarmSlot::Running(runtime_id) = slot {
+                log::info!("Stopping warm engine {}", runtime_id);
+                let _ = self.stop_engine(&runtime_id.to_string()).await;
+            }
+        }
+
+        for engine in wanted {
+            let slot = self.warm_pool.lock().await.get(&engine.id).cloned();
+            let respawned = match slot {
+                Some(WarmSlot::Failed) => continue,
+                Some(WarmSlot::Running(runtime_id)) => {
+                    let instance = self.engines.read().await.get(&runtime_id).cloned();
+                    let alive = match instance {
+                        Some(instance) => {
+                            let mut instance = instance.lock().await;
+                            !instance.has_exited()
+                                && !matches!(instance.status, EngineStatus::Error | EngineStatus::Unresponsive)
+                        }
+                        None => false,
+                    };
+                    if alive {
+                        continue;
+                    }
+                    log::warn!("Warm engine {} is gone; restarting it", runtime_id);
+                    let _ = self.stop_engine(&runtime_id.to_string()).await;
+                    true
+                }
+                None => false,
+            };
+
+            // Starting takes a while; the pool stays unlocked so lookups are not held up
+            let started = self
+                .start_engine(
+                    &engine.id,
+                    engine.name.clone(),
+                    engine.path.clone(),
+                    InstancePurpose::Analysis,
+                    engine.priority,
+                    engine_storage,
+                    None,
+                    None,
+                )
+                .await;
+            let slot = match started {
+                Ok(runtime_id) => {
+                    let _ = self.set_keep_alive(&runtime_id.to_string(), true).await;
+                    let ready = EngineWarmReady {
+                        config_id: engine.id.clone(),
+                        runtime_id: runtime_id.to_string(),
+                        respawned,
+                    };
+                    if let Err(e) = self.events.send(&ENGINE_WARM_READY, &ready) {
+                        log::error!("Failed to emit warm engine event: {}", e);
+                    }
+                    WarmSlot::Running(runtime_id)
+                }
+                Err(e) => {
+                    log::error!("Failed to keep engine {} warm: {}", engine.display_name, e);
+                    WarmSlot::Failed
+                }
+            };
+            self.warm_pool.lock().await.insert(engine.id, slot);
+        }
+    }
+
+    /// Fill the warm pool at start and check on it every few seconds for as long as the
+    /// manager lives. Passes never overlap, so one engine is never started twice.
+    pub async fn run_warm_pool(self: Arc<Self>, engine_storage: Arc<RwLock<crate::engine_storage::EngineStorage>>) {
+        loop {
+            self.maintain_warm_pool(&engine_storage).await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+
+    /// Add the usage recorded since the last flush to the engines' totals in storage. Usage of
+    /// engines removed in the meantime is dropped.
+    pub async fn flush_usage(&self, engine_storage: &RwLock<crate::engine_storage::EngineStorage>) -> Result<()> {
+        let usage = std::mem::take(&mut *self.usage.lock().unwrap_or_else(|e| e.into_inner()));
+        if usage.is_empty() {
+            return Ok(());
+        }
+        let mut storage = engine_storage.write().await;
+        for (config_id, recorded) in &usage {
+            if let Err(e) = storage.record_usage(config_id, recorded) {
+                log::debug!("Dropping usage of engine {}: {}", config_id, e);
+            }
+        }
+        storage.save_usage().await
+    }
+
+    /// Write recorded usage to storage once a minute, for as long as the manager lives
+    pub async fn run_usage_recorder(self: Arc<Self>, engine_storage: Arc<RwLock<crate::engine_storage::EngineStorage>>) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            if let Err(e) = self.flush_usage(&engine_storage).await {
+                log::error!("Failed to save engine usage: {}", e);
+            }
+        }
+    }
+
+    /// Stop all engines
+    pub async fn stop_all_engines(&self) -> Result<()> {
+        let engine_ids: Vec<String> = self.list_engines().await;
+
+        for engine_id in engine_ids {
+            if let Err(e) = self.stop_engine(&engine_id).await {
+                log::error!("Failed to stop engine {}: {}", engine_id, e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime_id(config_id: &str, instance: u32) -> RuntimeId {
+        RuntimeId {
+            config_id: config_id.to_string(),
+            instance,
+        }
+    }
+
+    #[test]
+    fn test_resolve_runtime_id() {
+        let running = [runtime_id("engine-1", 1), runtime_id("engine-10", 1), runtime_id("engine-10", 2)];
+
+        assert_eq!(resolve_runtime_id(running.iter(), "engine-10:2").unwrap(), &running[2]);
+        // A config ID selects its only instance, and is never matched as a prefix of another
+        assert_eq!(resolve_runtime_id(running.iter(), "engine-1").unwrap(), &running[0]);
+        assert!(resolve_runtime_id(running.iter(), "engine-10").is_err());
+        assert!(resolve_runtime_id(running.iter(), "engine").is_err());
+        assert!(resolve_runtime_id(running.iter(), "engine-1:2").is_err());
+    }
+
+    #[test]
+    fn test_preview_init() {
+        let metadata = crate::engine_validator::EngineMetadata {
+            options: [
+                "option name USI_Hash type spin default 256 min 1 max 4096",
+                "option name Threads type spin default 1 min 1 max 128",
+                "option name BookMoves type combo default none var none var narrow var wide",
+            ]
+            .iter()
+            .filter_map(|line| EngineOption::parse(line))
+            .collect(),
+            ..Default::default()
+        };
+        let mut storage = EngineStorage::default();
+        let engine_id = storage
+            .add_engine(crate::engine_storage::EngineConfig::new("Yane".to_string(), "/engines/yane".to_string(), Some(metadata), false))
+            .unwrap();
+        let saved = HashMap::from([("USI_Hash".to_string(), "1024".to_string()), ("Threads".to_string(), "4".to_string())]);
+        storage.save_engine_options(&engine_id, saved).unwrap();
+
+        let preview = preview_init(&storage, &engine_id, None).unwrap();
+        assert_eq!(
+            preview.commands,
+            ["usi", "setoption name Threads value 4", "setoption name USI_Hash value 1024", "isready"]
+        );
+        assert!(preview.warnings.is_empty());
+
+        let temp = HashMap::from([
+            ("USI_Hash".to_string(), "8192".to_string()),
+            ("BookMoves".to_string(), "huge".to_string()),
+            ("Ponder".to_string(), "true".to_string()),
+        ]);
+        let preview = preview_init(&storage, &engine_id, Some(&temp)).unwrap();
+        assert_eq!(preview.commands.len(), 5);
+        assert_eq!(
+            preview.warnings,
+            [
+                "BookMoves = \"huge\" is not one of none, narrow, wide",
+                "The engine does not advertise an option named Ponder",
+                "USI_Hash = 8192 is above its maximum 4096",
+            ]
+        );
+        assert!(preview_init(&storage, "missing", None).is_err());
+    }
+
+    #[test]
+    fn test_check_bestmove() {
+        let last = LastPosition::from_command("position startpos moves 7g7f 3c3d 8h2b+", None).unwrap();
+        let report = check_bestmove("bestmove 3a2b ponder B*4e", Some(&last)).unwrap();
+        assert_eq!((report.legal, report.ponder.as_deref()), (Some(true), Some("B*4e")));
+        let notations = report.notations.unwrap();
+        assert_eq!(
+            (notations.csa.as_str(), notations.ki2.as_str(), notations.kif.as_str(), notations.western.as_str()),
+            ("-3122GI", "△同　銀", "同　銀(31)", "Sx2b")
+        );
+        let after = LastPosition::from_command("position startpos moves 7g7f 3c3d 8h2b+ 3a2b", None).unwrap();
+        assert_eq!(report.resulting_sfen, Some(after.position.to_sfen()));
+        assert_eq!(report.sfen, Some(last.position.to_sfen()));
+        assert_eq!(last.sent.start_sfen, shogi_rules::STARTPOS_SFEN);
+        assert_eq!(last.sent.moves, ["7g7f", "3c3d", "8h2b+"]);
+        assert_eq!(last.sent.sfen, last.position.to_sfen());
+
+        // 4a holds White's own gold
+        let illegal = check_bestmove("bestmove 5a4a", Some(&last)).unwrap();
+        assert_eq!((illegal.legal, illegal.notations, illegal.resulting_sfen), (Some(false), None, None));
+        assert!(illegal.error.is_some());
+        assert_eq!(check_bestmove("bestmove resign", Some(&last)).unwrap().legal, Some(true));
+        assert_eq!(check_bestmove("bestmove win", Some(&last)).unwrap().legal, Some(false));
+        assert_eq!(check_bestmove("bestmove 7g7f", None).unwrap().legal, None);
+        assert_eq!(check_bestmove("info depth 1", Some(&last)), None);
+        assert!(LastPosition::from_command("position startpos moves 7g7e", None).is_none());
+
+        // Extending the last position only checks the new moves, with the same result
+        let extended = LastPosition::from_command("position startpos moves 7g7f 3c3d 8h2b+ 3a2b", Some(&last)).unwrap();
+        let fresh = LastPosition::from_command("position startpos moves 7g7f 3c3d 8h2b+ 3a2b", None).unwrap();
+        assert_eq!(extended.sent, fresh.sent);
+        assert!(LastPosition::from_command("position startpos moves 7g7f 3c3d 8h2b+ 7g7f", Some(&last)).is_none());
+        let other_line = LastPosition::from_command("position startpos moves 2g2f", Some(&last)).unwrap();
+        assert_eq!(other_line.sent.moves, ["2g2f"]);
+    }
+
+    #[test]
+    fn test_info_string() {
+        assert_eq!(info_string("info string Book file not found: user_book1.db"), Some("Book file not found: user_book1.db"));
+        assert_eq!(info_string("info string"), Some(""));
+        assert_eq!(info_string("info depth 10 score cp 30 string hello"), None);
+        assert_eq!(info_string("info stringent"), None);
+    }
+
+    /// Write an executable shell script standing in for an engine binary
+    #[cfg(unix)]
+    fn script_engine(body: &str) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("shogi-vibe-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("engine.sh");
+        std::fs::write(&path, format!("#!/bin/sh\n{}", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_output_reaches_event_sink() {
+        let path = script_engine(
+            "while read line; do\n  case \"$line\" in\n    usi) echo 'id name Echo'; echo usiok;;\n    quit) exit 0;;\n  esac\ndone\n",
+        );
+        let recorder = crate::events::RecordingEventSink::new();
+        let manager = EngineManager::new(recorder.clone());
+
+        let runtime_id = manager
+            .spawn_engine("echo", "Echo".to_string(), path.display().to_string(), InstancePurpose::Other, None)
+            .await
+            .unwrap();
+        manager.send_command("echo", "usi").await.unwrap();
+
+        let event = format!("usi-message::{}", runtime_id);
+        let usiok = recorder
+            .wait_for(&event, Duration::from_secs(5), |line| line == "usiok")
+            .await;
+        assert!(usiok.is_some());
+        assert_eq!(recorder.payloads(&event)[0], "id name Echo");
+        assert_eq!(manager.get_engine_status("echo").await, Some(EngineStatus::Ready));
+
+        manager.stop_engine(&runtime_id.to_string()).await.unwrap();
+        assert!(manager.list_engines().await.is_empty());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_console_mode() {
+        let path = script_engine(
+            "while read line; do\n  case \"$line\" in\n    usi) echo usiok; echo oops >&2;;\n    quit) exit 0;;\n  esac\ndone\n",
+        );
+        let recorder = crate::events::RecordingEventSink::new();
+        let manager = EngineManager::new(recorder.clone());
+        let runtime_id = manager
+            .spawn_engine("echo", "Echo".to_string(), path.display().to_string(), InstancePurpose::Other, None)
+            .await
+            .unwrap();
+
+        assert_eq!(manager.open_console("echo").await.unwrap(), runtime_id);
+        manager.send_command("echo", "usi").await.unwrap();
+        let event = format!("engine-console::{}", runtime_id);
+        for expected in ["usiok", "oops"] {
+            let line = recorder.wait_for(&event, Duration::from_secs(5), |line| line["line"] == expected).await;
+            assert!(line.is_some());
+        }
+        let lines = recorder.payloads(&event);
+        assert!(lines.iter().any(|line| line["direction"] == "sent" && line["line"] == "usi"));
+        assert!(lines.iter().any(|line| line["direction"] == "received" && line["line"] == "usiok"));
+        assert!(lines.iter().any(|line| line["direction"] == "stderr" && line["line"] == "oops"));
+        // The usiok did not count, and did not reach the regular output
+        assert_eq!(manager.get_engine_status("echo").await, Some(EngineStatus::Starting));
+        assert!(recorder.payloads(&format!("usi-message::{}", runtime_id)).is_empty());
+        assert!(manager.list_instances(None).await[0].console);
+
+        manager.close_console("echo").await.unwrap();
+        assert_eq!(manager.get_engine_status("echo").await, Some(EngineStatus::Ready));
+        manager.stop_engine(&runtime_id.to_string()).await.unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_new_game_stops_search_before_isready() {
+        // Logs what it receives next to itself; answers stop only after a moment
+        let path = script_engine(
+            "log=\"$(dirname \"$0\")/received\"\nwhile read line; do\n  echo \"$line\" >> \"$log\"\n  case \"$line\" in\n    stop) sleep 0.2; echo 'bestmove 3c3d';;\n    isready) echo readyok;;\n    quit) exit 0;;\n  esac\ndone\n",
+        );
+        let manager = EngineManager::new(crate::events::RecordingEventSink::new());
+        let storage = RwLock::new(EngineStorage::default());
+        let runtime_id = manager
+            .spawn_engine("game", "Game".to_string(), path.display().to_string(), InstancePurpose::Game, None)
+            .await
+            .unwrap();
+        manager.send_command("game", "position startpos moves 7g7f").await.unwrap();
+        manager.send_command("game", "go ponder").await.unwrap();
+        let position = manager.engine_position("game").await.unwrap().unwrap();
+        assert_eq!(position.command, "position startpos moves 7g7f");
+        assert_eq!(position.moves, ["7g7f"]);
+
+        manager.new_game("game", &storage).await.unwrap();
+        assert_eq!(manager.engine_position("game").await.unwrap(), None);
+        manager.stop_engine(&runtime_id.to_string()).await.unwrap();
+        let received = std::fs::read_to_string(path.with_file_name("received")).unwrap();
+        assert_eq!(
+            received.lines().collect::<Vec<_>>(),
+            ["position startpos moves 7g7f", "go ponder", "stop", "isready", "usinewgame", "quit"]
+        );
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_idle_engines_stopped_unless_kept_alive() {
+        let path = script_engine("while read line; do\n  [ \"$line\" = quit ] && exit 0\ndone\n");
+        let recorder = crate::events::RecordingEventSink::new();
+        let manager = EngineManager::new(recorder.clone());
+        manager.set_idle_policy(IdlePolicy { idle_timeout_minutes: 1 });
+
+        let idle = manager
+            .spawn_engine("idle", "Idle".to_string(), path.display().to_string(), InstancePurpose::Other, None)
+            .await
+            .unwrap();
+        let kept = manager
+            .spawn_engine("kept", "Kept".to_string(), path.display().to_string(), InstancePurpose::Other, None)
+            .await
+            .unwrap();
+        manager.set_keep_alive("kept", true).await.unwrap();
+        assert!(manager.stop_idle_engines().await.is_empty());
+
+        for engine in manager.engines.read().await.values() {
+            let mut engine = engine.lock().await;
+            engine.last_command_at = tokio::time::Instant::now()
+                .checked_sub(Duration::from_secs(120))
+                .unwrap();
+        }
+        assert_eq!(manager.stop_idle_engines().await, vec![idle.clone()]);
+        let stopped = recorder.payloads("engine-idle-stopped");
+        assert_eq!(stopped.len(), 1);
+        assert_eq!(stopped[0]["runtime_id"], idle.to_string());
+        assert_eq!(stopped[0]["idle_minutes"], 2);
+
+        let remaining = manager.list_instances(None).await;
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining[0].keep_alive);
+        manager.stop_engine(&kept.to_string()).await.unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+//! Checks that an engine file may be run, before it is added
+//! "Permission denied" on the first start is usually a binary that lost its execute bit when
+//! it was unpacked, or one the OS marked as downloaded from the internet: macOS quarantines
+//! it for Gatekeeper and Windows blocks it with a Zone.Identifier stream. Each of these can be
+//! fixed in place. A quarantined engine fails validation with [`Quarantined`], whose message
+//! code lets the UI explain what happened.
+
+use crate::message::Message;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[cfg(target_os = "macos")]
+const QUARANTINE_ATTRIBUTE: &str = "com.apple.quarantine";
+
+/// Files Windows starts directly
+#[cfg(windows)]
+const WINDOWS_EXTENSIONS: [&str; 3] = ["exe", "bat", "cmd"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionIssue {
+    /// No execute permission (Unix)
+    NotExecutable,
+    /// Carries the quarantine attribute, so Gatekeeper stops it from running (macOS)
+    Quarantined,
+    /// Marked as downloaded from the internet (Windows)
+    Blocked,
+    /// Not a file type Windows can start; cannot be fixed here
+    UnsupportedExtension,
+}
+
+impl PermissionIssue {
+    pub fn is_fixable(self) -> bool {
+        self != PermissionIssue::UnsupportedExtension
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            PermissionIssue::NotExecutable => "The file is not marked as executable",
+            PermissionIssue::Quarantined => "macOS has quarantined the file because it was downloaded",
+            PermissionIssue::Blocked => "Windows has blocked the file because it was downloaded",
+            PermissionIssue::UnsupportedExtension => "The file is not a program Windows can run (.exe)",
+        }
+    }
+}
+
+/// Error for an engine macOS will not run until its quarantine attribute is cleared
+#[derive(Debug)]
+pub struct Quarantined {
+    pub path: String,
+}
+
+impl Quarantined {
+    pub fn message(&self) -> Message {
+        crate::message!(
+            "engine_quarantined",
+            "{path} was downloaded from the internet and macOS has quarantined it; clear the quarantine to run it",
+            path = self.path
+        )
+    }
+}
+
+impl std::fmt::Display for Quarantined {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.message().fmt(f)
+    }
+}
+
+impl std::error::Error for Quarantined {}
+
+/// Fail with [`Quarantined`] if macOS would refuse to run the file at `path`
+pub async fn ensure_not_quarantined(path: &Path) -> Result<()> {
+    if is_quarantined(path).await {
+        return Err(Quarantined { path: path.display().to_string() }.into());
+    }
+    Ok(())
+}
+
+/// Problems that would keep the engine at `path` from starting; empty when there are none
+pub async fn check(path: &Path) -> Result<Vec<PermissionIssue>> {
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .map_err(|e| anyhow!("Engine not found at {}: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(anyhow!("Engine path is not a file: {}", path.display()));
+    }
+
+    let mut issues = Vec::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            issues.push(PermissionIssue::NotExecutable);
+        }
+    }
+    #[cfg(target_os = "macos")]
+    if is_quarantined(path).await {
+        issues.push(PermissionIssue::Quarantined);
+    }
+    #[cfg(windows)]
+    {
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        if !extension.is_some_and(|e| WINDOWS_EXTENSIONS.contains(&e.as_str())) {
+            issues.push(PermissionIssue::UnsupportedExtension);
+        }
+        if tokio::fs::metadata(zone_identifier(path)).await.is_ok() {
+            issues.push(PermissionIssue::Blocked);
+        }
+    }
+    Ok(issues)
+}
+
+/// Fix every fixable issue, then check again. Returns the issues left.
+pub async fn fix(path: &Path) -> Result<Vec<PermissionIssue>> {
+    for issue in check(path).await? {
+        match issue {
+            PermissionIssue::NotExecutable => make_executable(path).await?,
+            PermissionIssue::Quarantined => remove_quarantine(path).await?,
+            PermissionIssue::Blocked => unblock(path).await?,
+            PermissionIssue::UnsupportedExtension => {}
+        }
+        log::info!("Fixed {:?} on {}", issue, path.display());
+    }
+    check(path).await
+}
+
+#[cfg(unix)]
+async fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = tokio::fs::metadata(path).await?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    tokio::fs::set_permissions(path, permissions).await?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Whether the file carries macOS's quarantine attribute; always false elsewhere
+#[cfg(target_os = "macos")]
+pub async fn is_quarantined(path: &Path) -> bool {
+    tokio::process::Command::new("xattr")
+        .arg("-p")
+        .arg(QUARANTINE_ATTRIBUTE)
+        .arg(path)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub async fn is_quarantined(_path: &Path) -> bool {
+    false
+}
+
+/// Clear macOS's quarantine attribute from the file, allowing Gatekeeper to let it run
+#[cfg(target_os = "macos")]
+pub async fn remove_quarantine(path: &Path) -> Result<()> {
+    let output = tokio::process::Command::new("xattr")
+        .arg("-d")
+        .arg(QUARANTINE_ATTRIBUTE)
+        .arg(path)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Could not remove the quarantine attribute: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub async fn remove_quarantine(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// The alternate data stream Windows records a download's origin in
+#[cfg(windows)]
+fn zone_identifier(path: &Path) -> std::path::PathBuf {
+    let mut stream = path.as_os_str().to_owned();
+    stream.push(":Zone.Identifier");
+    stream.into()
+}
+
+#[cfg(windows)]
+async fn unblock(path: &Path) -> Result<()> {
+    tokio::fs::remove_file(zone_identifier(path)).await?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+async fn unblock(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fix_execute_bit() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("shogi-vibe-permissions-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        assert!(check(&path).await.unwrap().contains(&PermissionIssue::NotExecutable));
+        assert!(!fix(&path).await.unwrap().contains(&PermissionIssue::NotExecutable));
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o755);
+
+        assert!(check(&path.with_extension("missing")).await.is_err());
+        let quarantined = Quarantined { path: "/engines/yane".to_string() }.message();
+        assert_eq!(quarantined.code, "engine_quarantined");
+        assert_eq!(quarantined.params["path"], "/engines/yane");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+//! Platform setup shared by every spawned engine process
+//! On Windows engines start without a console window, and each one is assigned to a job object
+//! that the OS closes when the app exits, even after a crash, killing whatever engines are
+//! still running instead of leaving them orphaned. On Unix each engine leads its own process
+//! group, so helper processes it forks are killed along with it.
+
+use tokio::process::{Child, Command};
+
+/// Configure a command before an engine is spawned from it
+#[cfg(windows)]
+pub fn prepare(command: &mut Command) {
+    use windows_sys::Win32::System::Threading::CREATE_NO_WINDOW;
+    command.creation_flags(CREATE_NO_WINDOW);
+}
+
+#[cfg(unix)]
+pub fn prepare(command: &mut Command) {
+    command.process_group(0);
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn prepare(_command: &mut Command) {}
+
+/// Kill an engine with every process in its group and reap it, so no zombie is left behind
+pub async fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // The engine leads its own group (see `prepare`), whose ID is its PID
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+/// Job object with kill-on-close set, created on first use. The handle is kept open for the
+/// life of the app, so only the app's exit closes it. Stored as an integer to be shareable.
+#[cfg(windows)]
+fn kill_on_exit_job() -> Option<usize> {
+    use std::sync::OnceLock;
+    use windows_sys::Win32::System::JobObjects::{
+        CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    static JOB: OnceLock<Option<usize>> = OnceLock::new();
+    *JOB.get_or_init(|| unsafe {
+        let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+        if job.is_null() {
+            log::warn!("Failed to create engine job object: {}", std::io::Error::last_os_error());
+            return None;
+        }
+        let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        let set = SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &limits as *const _ as *const std::ffi::c_void,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        );
+        if set == 0 {
+            log::warn!("Failed to configure engine job object: {}", std::io::Error::last_os_error());
+            return None;
+        }
+        Some(job as usize)
+    })
+}
+
+/// Tie a spawned engine's lifetime to the app's. Failure is logged: the engine still works,
+/// it just may outlive a crash.
+#[cfg(windows)]
+pub fn attach(child: &Child) {
+    use windows_sys::Win32::System::JobObjects::AssignProcessToJobObject;
+
+    let (Some(job), Some(handle)) = (kill_on_exit_job(), child.raw_handle()) else {
+        return;
+    };
+    if unsafe { AssignProcessToJobObject(job as _, handle as _) } == 0 {
+        log::warn!("Failed to assign engine to job object: {}", std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(not(windows))]
+pub fn attach(_child: &Child) {}
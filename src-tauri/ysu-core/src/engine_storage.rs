@@ -0,0 +1,1604 @@
+use crate::atomic_file;
+use crate::engine_validator::{self, EngineMetadata, OptionDiff, OptionRename};
+use crate::eval_file::{self, EvalFile};
+use crate::process_priority::ProcessPriority;
+use crate::timeouts::TimeoutOverrides;
+use crate::usi_macro::{self, UsiMacro};
+use crate::win_rate::WinRateCalibration;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Configuration for a stored engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineConfig {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_display_name")]
+    pub display_name: String,
+    pub path: String,
+    pub metadata: Option<EngineMetadata>,
+    pub is_builtin: bool,
+    pub enabled: bool,
+    pub last_used: Option<String>,
+    pub created_at: String,
+    pub saved_options: Option<std::collections::HashMap<String, String>>,
+    /// Name of the group the engine is listed under, if any
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Free-form labels, e.g. "nnue" or "unstable"
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// User notes about the engine (markdown)
+    #[serde(default)]
+    pub notes: String,
+    /// OS scheduling priority used when spawning the engine, unless overridden
+    #[serde(default)]
+    pub priority: Option<ProcessPriority>,
+    /// SHA-256 of the engine binary when it was last validated
+    #[serde(default)]
+    pub binary_sha256: Option<String>,
+    /// Registered eval file the engine loads, sent as EvalDir/EvalFile options on spawn
+    #[serde(default)]
+    pub eval_file_id: Option<String>,
+    /// Keep an initialized instance running from app start for instant analysis
+    #[serde(default)]
+    pub keep_warm: bool,
+    /// How this engine's centipawns map to winning chances; the default scale when unset
+    #[serde(default)]
+    pub win_rate: Option<WinRateCalibration>,
+    /// Replacements for the app-wide timeouts, for engines that need longer
+    #[serde(default)]
+    pub timeouts: Option<TimeoutOverrides>,
+    /// Version of the built-in engine's files as last registered; unset for other engines
+    /// and for a workspace build
+    #[serde(default)]
+    pub builtin_version: Option<String>,
+    /// What the engine has been used for since it was registered
+    #[serde(default)]
+    pub usage: EngineUsage,
+    /// Named sequences of raw commands, for extensions the engine understands beyond USI
+    #[serde(default)]
+    pub macros: Vec<UsiMacro>,
+}
+
+/// Usage totals of an engine, added to as games and searches finish
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineUsage {
+    pub games_played: u64,
+    /// Time spent searching for analysis
+    pub analysis_ms: u64,
+    pub nodes_searched: u64,
+}
+
+impl EngineUsage {
+    pub fn add(&mut self, other: &EngineUsage) {
+        self.games_played = self.games_played.saturating_add(other.games_played);
+        self.analysis_ms = self.analysis_ms.saturating_add(other.analysis_ms);
+        self.nodes_searched = self.nodes_searched.saturating_add(other.nodes_searched);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// An engine binary that no longer matches the hash recorded at validation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct BinaryChanged {
+    pub engine_id: String,
+    pub path: String,
+    pub expected_sha256: String,
+    pub actual_sha256: String,
+}
+
+impl std::fmt::Display for BinaryChanged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Engine binary {} changed since last validation", self.path)
+    }
+}
+
+fn default_display_name() -> String {
+    String::new()
+}
+
+impl EngineConfig {
+    pub fn new(name: String, path: String, metadata: Option<EngineMetadata>, is_builtin: bool) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name: name.clone(),
+            display_name: name,
+            path,
+            metadata,
+            is_builtin,
+            enabled: true,
+            last_used: None,
+            created_at: now,
+            saved_options: None,
+            group: None,
+            tags: Vec::new(),
+            notes: String::new(),
+            priority: None,
+            binary_sha256: None,
+            eval_file_id: None,
+            keep_warm: false,
+            win_rate: None,
+            timeouts: None,
+            builtin_version: None,
+            usage: EngineUsage::default(),
+            macros: Vec::new(),
+        }
+    }
+
+    pub fn ensure_enabled(&self) -> Result<()> {
+        if !self.enabled {
+            let name = if self.display_name.is_empty() { &self.name } else { &self.display_name };
+            return Err(anyhow!("Engine {} is disabled; enable it to use it", name));
+        }
+        Ok(())
+    }
+
+    /// Record the hash of the binary as it is now, e.g. after validating it
+    pub async fn record_binary_hash(&mut self) {
+        match engine_validator::binary_sha256(&self.path).await {
+            Ok(hash) => self.binary_sha256 = Some(hash),
+            Err(e) => log::warn!("Could not hash engine binary {}: {}", self.path, e),
+        }
+    }
+
+    /// Hash the binary and compare it with the recorded hash.
+    /// Returns the current hash and, if it differs from the recorded one, the mismatch.
+    pub async fn check_binary(&self) -> Result<(String, Option<BinaryChanged>)> {
+        let actual = engine_validator::binary_sha256(&self.path).await?;
+        let changed = match &self.binary_sha256 {
+            Some(expected) if *expected != actual => Some(BinaryChanged {
+                engine_id: self.id.clone(),
+                path: self.path.clone(),
+                expected_sha256: expected.clone(),
+                actual_sha256: actual.clone(),
+            }),
+            _ => None,
+        };
+        Ok((actual, changed))
+    }
+
+    /// Whether the engine carries `tag`, ignoring case
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()))
+    }
+}
+
+/// What happened to an engine's saved options after its option set changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedOptionsMigration {
+    /// Saved values carried over to a renamed option
+    pub renamed: Vec<OptionRename>,
+    /// Saved values dropped because the option is gone or the value is no longer valid
+    pub pruned: Vec<String>,
+    /// Spin values moved into the new min/max range
+    pub clamped: Vec<String>,
+}
+
+impl EngineConfig {
+    /// Replace the metadata with a fresh validation's: record the binary's hash and migrate
+    /// saved options to the new option set
+    pub async fn update_metadata(&mut self, metadata: EngineMetadata) -> (OptionDiff, SavedOptionsMigration) {
+        let old_options = self.metadata.as_ref().map(|m| m.options.clone()).unwrap_or_default();
+        let diff = engine_validator::diff_options(&old_options, &metadata.options);
+        if !diff.is_empty() {
+            log::info!(
+                "Engine {} options changed: {} added, {} removed, {} renamed, {} changed",
+                self.id, diff.added.len(), diff.removed.len(), diff.renamed.len(), diff.changed.len()
+            );
+        }
+        self.metadata = Some(metadata);
+        self.record_binary_hash().await;
+        // Drop or fix saved options the upgraded engine would reject
+        let migration = self.migrate_saved_options(&diff);
+        if !migration.pruned.is_empty() {
+            log::info!("Pruned stale saved options for {}: {:?}", self.id, migration.pruned);
+        }
+        (diff, migration)
+    }
+
+    /// Bring saved options in line with freshly validated metadata: follow renames,
+    /// clamp spin values to the new range and drop anything the engine would reject
+    pub fn migrate_saved_options(&mut self, diff: &OptionDiff) -> SavedOptionsMigration {
+        let mut migration = SavedOptionsMigration::default();
+        let (Some(metadata), Some(saved)) = (self.metadata.as_ref(), self.saved_options.as_mut()) else {
+            return migration;
+        };
+
+        for rename in &diff.renamed {
+            if let Some(value) = saved.remove(&rename.from) {
+                saved.insert(rename.to.clone(), value);
+                migration.renamed.push(rename.clone());
+            }
+        }
+
+        let mut names: Vec<String> = saved.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            let value = saved[&name].clone();
+            let Some(option) = metadata.options.iter().find(|o| o.name == name) else {
+                saved.remove(&name);
+                migration.pruned.push(name);
+                continue;
+            };
+            match option.option_type.as_str() {
+                "spin" => {
+                    let Ok(number) = value.trim().parse::<i64>() else {
+                        saved.remove(&name);
+                        migration.pruned.push(name);
+                        continue;
+                    };
+                    let min = option.min.as_deref().and_then(|v| v.parse::<i64>().ok()).unwrap_or(i64::MIN);
+                    let max = option.max.as_deref().and_then(|v| v.parse::<i64>().ok()).unwrap_or(i64::MAX);
+                    let clamped = number.clamp(min, max.max(min));
+                    if clamped != number {
+                        saved.insert(name.clone(), clamped.to_string());
+                        migration.clamped.push(name);
+                    }
+                }
+                "check" if value != "true" && value != "false" => {
+                    saved.remove(&name);
+                    migration.pruned.push(name);
+                }
+                "combo" if !option.var.contains(&value) => {
+                    saved.remove(&name);
+                    migration.pruned.push(name);
+                }
+                _ => {}
+            }
+        }
+
+        migration
+    }
+}
+
+/// Current schema version of engines.json. To change the format, bump this and append
+/// a migration to `MIGRATIONS`.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Number of rotating backups of engines.json kept by `save`
+const BACKUP_COUNT: usize = 5;
+
+/// A migration rewrites the raw JSON from one schema version to the next
+type Migration = fn(&mut serde_json::Value) -> Result<()>;
+
+/// Ordered migrations; `MIGRATIONS[n]` upgrades version `n + 1` to `n + 2`
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// v1 stored `"version": "1.0"` and could have engines without a display name
+fn migrate_v1_to_v2(value: &mut serde_json::Value) -> Result<()> {
+    let root = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Engine storage root is not an object"))?;
+    root.remove("version");
+    if let Some(engines) = root.get_mut("engines").and_then(|e| e.as_array_mut()) {
+        for engine in engines.iter_mut().filter_map(|e| e.as_object_mut()) {
+            let display_name = engine.get("display_name").and_then(|d| d.as_str()).unwrap_or("");
+            if display_name.is_empty() {
+                let name = engine.get("name").cloned().unwrap_or_default();
+                log::info!("Migrating engine {}: setting display_name", name);
+                engine.insert("display_name".to_string(), name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `path` with `old_prefix` replaced by `new_prefix`, if it lies under `old_prefix`
+fn replace_prefix(path: &str, old_prefix: &str, new_prefix: &str) -> Option<String> {
+    let rest = Path::new(path).strip_prefix(old_prefix).ok()?;
+    if rest.as_os_str().is_empty() {
+        return Some(new_prefix.to_string());
+    }
+    Some(Path::new(new_prefix).join(rest).display().to_string())
+}
+
+/// Schema version of raw storage JSON. Files from before numbered schemas are version 1.
+fn schema_version_of(value: &serde_json::Value) -> u32 {
+    value
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .unwrap_or(1)
+}
+
+/// v2 marked one engine `is_favorite`, used for both games and analysis
+fn migrate_v2_to_v3(value: &mut serde_json::Value) -> Result<()> {
+    let root = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Engine storage root is not an object"))?;
+    let mut favorite = None;
+    if let Some(engines) = root.get_mut("engines").and_then(|e| e.as_array_mut()) {
+        for engine in engines.iter_mut().filter_map(|e| e.as_object_mut()) {
+            if engine.remove("is_favorite").and_then(|f| f.as_bool()) == Some(true) && favorite.is_none() {
+                favorite = engine.get("id").cloned();
+            }
+        }
+    }
+    if let Some(favorite) = favorite {
+        log::info!("Migrating favorite engine {} to the default game and analysis engine", favorite);
+        root.insert(
+            "default_engines".to_string(),
+            serde_json::json!({ "game": favorite, "analysis": favorite }),
+        );
+    }
+    Ok(())
+}
+
+/// Upgrade raw storage JSON to `SCHEMA_VERSION`
+fn migrate(value: &mut serde_json::Value) -> Result<()> {
+    let from = schema_version_of(value);
+    if from > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Engine storage schema version {} is newer than this app supports ({})",
+            from,
+            SCHEMA_VERSION
+        ));
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(from.saturating_sub(1) as usize) {
+        log::info!("Migrating engine storage from schema version {} to {}", index + 1, index + 2);
+        migration(value)?;
+    }
+    if let Some(root) = value.as_object_mut() {
+        root.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+    }
+    Ok(())
+}
+
+/// Pull every engine entry that still deserializes out of damaged storage text.
+/// Handles both valid JSON with bad entries and files truncated mid-write.
+/// Returns the recovered engines and the number of entries that had to be dropped.
+fn salvage_engines(contents: &str) -> (Vec<EngineConfig>, usize) {
+    let entries: Vec<serde_json::Value> = match serde_json::from_str::<serde_json::Value>(contents) {
+        Ok(mut value) => {
+            // Bring old entries up to date first; a failed migration still leaves raw entries to try
+            let _ = migrate(&mut value);
+            value
+                .get("engines")
+                .and_then(|e| e.as_array())
+                .cloned()
+                .unwrap_or_default()
+        }
+        Err(_) => engine_array_objects(contents)
+            .into_iter()
+            .filter_map(|text| serde_json::from_str(text).ok())
+            .collect(),
+    };
+
+    let total = entries.len();
+    let engines: Vec<EngineConfig> = entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value(entry).ok())
+        .collect();
+    let dropped = total - engines.len();
+    (engines, dropped)
+}
+
+/// Text of each complete `{...}` object inside the top-level "engines" array
+fn engine_array_objects(contents: &str) -> Vec<&str> {
+    let Some(array_start) = contents
+        .find("\"engines\"")
+        .and_then(|key| contents[key..].find('[').map(|offset| key + offset + 1))
+    else {
+        return Vec::new();
+    };
+
+    let mut objects = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut object_start = 0;
+    for (offset, c) in contents[array_start..].char_indices() {
+        let position = array_start + offset;
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    object_start = position;
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    objects.push(&contents[object_start..=position]);
+                }
+            }
+            ']' if depth == 0 => break,
+            _ => {}
+        }
+    }
+    objects
+}
+
+/// Outcome of `EngineStorage::repair`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageRepairReport {
+    /// Damaged files that were read
+    pub sources: Vec<String>,
+    /// Engines added back to storage
+    pub recovered_engines: usize,
+    /// Entries that could not be read
+    pub dropped_entries: usize,
+}
+
+/// What an engine is used for; each purpose has its own default engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnginePurpose {
+    Game,
+    Analysis,
+    /// Solving tsume (mate) problems
+    Tsume,
+}
+
+impl EnginePurpose {
+    pub const ALL: [EnginePurpose; 3] = [EnginePurpose::Game, EnginePurpose::Analysis, EnginePurpose::Tsume];
+}
+
+/// Config ID of the engine chosen for each purpose, if any
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DefaultEngines {
+    pub game: Option<String>,
+    pub analysis: Option<String>,
+    pub tsume: Option<String>,
+}
+
+impl DefaultEngines {
+    pub fn get(&self, purpose: EnginePurpose) -> Option<&str> {
+        match purpose {
+            EnginePurpose::Game => self.game.as_deref(),
+            EnginePurpose::Analysis => self.analysis.as_deref(),
+            EnginePurpose::Tsume => self.tsume.as_deref(),
+        }
+    }
+
+    fn slot_mut(&mut self, purpose: EnginePurpose) -> &mut Option<String> {
+        match purpose {
+            EnginePurpose::Game => &mut self.game,
+            EnginePurpose::Analysis => &mut self.analysis,
+            EnginePurpose::Tsume => &mut self.tsume,
+        }
+    }
+}
+
+/// Storage container for all engine configurations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineStorage {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    /// Engines in the user's manual sort order
+    pub engines: Vec<EngineConfig>,
+    /// Group names in display order
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Evaluation files engines can be pointed at
+    #[serde(default)]
+    pub eval_files: Vec<EvalFile>,
+    /// Engines chosen for games, analysis and tsume solving
+    #[serde(default)]
+    pub default_engines: DefaultEngines,
+    /// Removed engines that can still be restored, oldest first
+    #[serde(default)]
+    pub trash: Vec<TrashedEngine>,
+    /// Directory engine paths inside it are stored relative to, so moving it only takes
+    /// changing this. Paths are absolute in memory either way.
+    #[serde(default)]
+    pub engines_root: Option<String>,
+}
+
+/// A removed engine, kept with its saved options and usage until it is restored or purged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedEngine {
+    pub engine: EngineConfig,
+    pub removed_at: String,
+}
+
+/// An engine group with its members, in display order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineGroup {
+    pub name: String,
+    pub engines: Vec<EngineConfig>,
+}
+
+/// Engines arranged by group, as returned by `get_engines` when grouping is requested
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupedEngines {
+    pub groups: Vec<EngineGroup>,
+    pub ungrouped: Vec<EngineConfig>,
+}
+
+fn default_schema_version() -> u32 {
+    SCHEMA_VERSION
+}
+
+impl Default for EngineStorage {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            engines: Vec::new(),
+            groups: Vec::new(),
+            eval_files: Vec::new(),
+            default_engines: DefaultEngines::default(),
+            trash: Vec::new(),
+            engines_root: None,
+        }
+    }
+}
+
+impl EngineStorage {
+    /// Get the platform-appropriate application config directory
+    pub fn get_config_dir() -> Result<PathBuf> {
+        let config_dir = if cfg!(target_os = "windows") {
+            // Windows: %APPDATA%\shogi-vibe
+            std::env::var("APPDATA")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("."))
+                .join("shogi-vibe")
+        } else {
+            // Linux/macOS: ~/.config/shogi-vibe
+            dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("shogi-vibe")
+        };
+
+        // Create directory if it doesn't exist
+        std::fs::create_dir_all(&config_dir)?;
+
+        Ok(config_dir)
+    }
+
+    /// Get the platform-appropriate storage path
+    pub fn get_storage_path() -> Result<PathBuf> {
+        Ok(Self::get_config_dir()?.join("engines.json"))
+    }
+
+    /// Copy the storage file aside as `engines.json.<label>.bak`
+    async fn backup_storage_file(label: &str) -> Result<PathBuf> {
+        let path = Self::get_storage_path()?;
+        let backup = path.with_file_name(format!("engines.json.{}.bak", label));
+        tokio::fs::copy(&path, &backup).await?;
+        log::info!("Backed up engine storage to: {}", backup.display());
+        Ok(backup)
+    }
+
+    /// Copies of unreadable storage files kept by `load`, newest first
+    fn corrupt_backups() -> Result<Vec<PathBuf>> {
+        let mut backups: Vec<PathBuf> = std::fs::read_dir(Self::get_config_dir()?)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with("engines.json.corrupt-") && n.ends_with(".bak"))
+                    .unwrap_or(false)
+            })
+            .collect();
+        backups.sort();
+        backups.reverse();
+        Ok(backups)
+    }
+
+    /// Keep the default-engine invariants: defaults name existing engines, and the game
+    /// default is the lone engine or else the built-in engine when none is set. Returns
+    /// whether anything changed.
+    fn normalize_defaults(&mut self) -> bool {
+        let mut changed = false;
+        for purpose in EnginePurpose::ALL {
+            let slot = self.default_engines.slot_mut(purpose);
+            if slot.as_ref().is_some_and(|id| !self.engines.iter().any(|e| &e.id == id)) {
+                log::info!("Clearing default {:?} engine, which no longer exists", purpose);
+                *slot = None;
+                changed = true;
+            }
+        }
+        if self.default_engines.game.is_none() {
+            let fallback = match self.engines.as_slice() {
+                [only] => Some(only),
+                engines => engines.iter().find(|e| e.is_builtin),
+            };
+            if let Some(engine) = fallback {
+                log::info!("Making {} the default game engine", engine.display_name);
+                self.default_engines.game = Some(engine.id.clone());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Parse storage JSON of any supported schema version, returning it together with
+    /// the version it was stored as
+    fn parse(contents: &str) -> Result<(Self, u32)> {
+        let mut value: serde_json::Value = serde_json::from_str(contents)?;
+        let from_version = schema_version_of(&value);
+        migrate(&mut value)?;
+        let mut storage: Self = serde_json::from_value(value)?;
+        storage.resolve_paths();
+        Ok((storage, from_version))
+    }
+
+    /// Engine paths as they are in memory and in use, and as written to disk
+    fn engine_paths_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.engines
+            .iter_mut()
+            .chain(self.trash.iter_mut().map(|t| &mut t.engine))
+            .map(|e| &mut e.path)
+    }
+
+    /// Make paths stored relative to the engines root absolute
+    fn resolve_paths(&mut self) {
+        let Some(root) = self.engines_root.clone() else {
+            return;
+        };
+        for path in self.engine_paths_mut() {
+            if Path::new(path.as_str()).is_relative() {
+                *path = Path::new(&root).join(path.as_str()).display().to_string();
+            }
+        }
+    }
+
+    /// The storage as written to disk, with paths inside the engines root relative to it
+    fn portable(&self) -> Self {
+        let mut portable = self.clone();
+        if let Some(root) = self.engines_root.clone() {
+            for path in portable.engine_paths_mut() {
+                if let Some(relative) = replace_prefix(path, &root, "") {
+                    *path = relative;
+                }
+            }
+        }
+        portable
+    }
+
+    /// Set or clear the engines root. It must be an absolute path.
+    pub fn set_engines_root(&mut self, root: Option<String>) -> Result<()> {
+        let root = root.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+        if let Some(root) = &root {
+            if Path::new(root).is_relative() {
+                return Err(anyhow!("The engines root must be an absolute path"));
+            }
+        }
+        self.engines_root = root;
+        Ok(())
+    }
+
+    /// Rewrite every engine, trashed engine and eval file path under `old_prefix` to lie
+    /// under `new_prefix` instead, e.g. after the engines folder was moved to another drive.
+    /// The engines root moves along if it was under `old_prefix`. Returns the IDs of the
+    /// configured engines that moved.
+    pub fn relocate_engines(&mut self, old_prefix: &str, new_prefix: &str) -> Result<Vec<String>> {
+        if old_prefix.trim().is_empty() || new_prefix.trim().is_empty() {
+            return Err(anyhow!("Both the old and the new location are needed"));
+        }
+        let mut moved = Vec::new();
+        for engine in &mut self.engines {
+            if let Some(path) = replace_prefix(&engine.path, old_prefix, new_prefix) {
+                engine.path = path;
+                moved.push(engine.id.clone());
+            }
+        }
+        for trashed in &mut self.trash {
+            if let Some(path) = replace_prefix(&trashed.engine.path, old_prefix, new_prefix) {
+                trashed.engine.path = path;
+            }
+        }
+        for eval_file in &mut self.eval_files {
+            if let Some(path) = replace_prefix(&eval_file.path, old_prefix, new_prefix) {
+                eval_file.path = path;
+            }
+        }
+        if let Some(root) = self.engines_root.as_ref().and_then(|root| replace_prefix(root, old_prefix, new_prefix)) {
+            self.engines_root = Some(root);
+        }
+        Ok(moved)
+    }
+
+    /// Newest rotating backup that still parses
+    async fn load_latest_backup() -> Option<Self> {
+        let path = Self::get_storage_path().ok()?;
+        for backup in atomic_file::backup_paths(&path, BACKUP_COUNT).ok()? {
+            let Ok(contents) = tokio::fs::read_to_string(&backup).await else {
+                continue;
+            };
+            match Self::parse(&contents) {
+                Ok((storage, _)) => {
+                    log::warn!("Restored engine storage from backup: {}", backup.display());
+                    return Some(storage);
+                }
+                Err(e) => log::warn!("Skipping unreadable backup {}: {}", backup.display(), e),
+            }
+        }
+        None
+    }
+
+    /// Load engine storage from disk, migrating older schema versions.
+    /// An unreadable file is copied aside so `repair` can recover it later, and the
+    /// newest valid rotating backup is used in its place.
+    pub async fn load() -> Result<Self> {
+        let path = Self::get_storage_path()?;
+        
+        if !path.exists() {
+            log::info!("Engine storage file not found, creating new storage");
+            return Ok(Self::default());
+        }
+
+        log::info!("Loading engine storage from: {}", path.display());
+        let contents = tokio::fs::read_to_string(&path).await?;
+
+        // A file from a newer app version is not corrupt; refuse it rather than fall back
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(&contents) {
+            let version = schema_version_of(&value);
+            if version > SCHEMA_VERSION {
+                Self::backup_storage_file(&format!("v{}", version)).await?;
+                return Err(anyhow!(
+                    "Engine storage schema version {} is newer than this app supports ({})",
+                    version,
+                    SCHEMA_VERSION
+                ));
+            }
+        }
+
+        let (mut storage, from_version) = match Self::parse(&contents) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let error = Self::preserve_corrupt_file(e).await;
+                log::error!("{}", error);
+                let storage = Self::load_latest_backup().await.ok_or(error)?;
+                storage.save().await?;
+                (storage, SCHEMA_VERSION)
+            }
+        };
+
+        if from_version < SCHEMA_VERSION {
+            Self::backup_storage_file(&format!("v{}", from_version)).await?;
+        }
+
+        let defaults_changed = storage.normalize_defaults();
+        if from_version < SCHEMA_VERSION || defaults_changed {
+            log::info!("Saving migrated engine storage");
+            storage.save().await?;
+        }
+        
+        log::info!("Loaded {} engines from storage", storage.engines.len());
+        Ok(storage)
+    }
+
+    async fn preserve_corrupt_file(error: anyhow::Error) -> anyhow::Error {
+        let label = format!("corrupt-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S"));
+        match Self::backup_storage_file(&label).await {
+            Ok(backup) => anyhow!(
+                "Engine storage is corrupt ({}); a copy was kept at {} for repair_storage",
+                error,
+                backup.display()
+            ),
+            Err(backup_error) => anyhow!("Engine storage is corrupt ({}) and could not be backed up: {}", error, backup_error),
+        }
+    }
+
+    /// Recover engines from an unreadable storage file (or copies kept by `load`) and
+    /// merge them into this storage. Engines already present are left alone.
+    pub async fn repair(&mut self) -> Result<StorageRepairReport> {
+        let mut report = StorageRepairReport::default();
+        let mut sources = Self::corrupt_backups()?;
+        let path = Self::get_storage_path()?;
+        if path.exists() {
+            let contents = tokio::fs::read_to_string(&path).await?;
+            if Self::parse(&contents).is_err() {
+                sources.insert(0, path);
+            }
+        }
+
+        for source in sources {
+            let contents = tokio::fs::read_to_string(&source).await?;
+            let (engines, dropped) = salvage_engines(&contents);
+            log::info!("Recovered {} engines from {} ({} unreadable)", engines.len(), source.display(), dropped);
+            report.dropped_entries += dropped;
+            for engine in engines {
+                if self.engines.iter().any(|e| e.id == engine.id || e.path == engine.path) {
+                    continue;
+                }
+                self.engines.push(engine);
+                report.recovered_engines += 1;
+            }
+            report.sources.push(source.display().to_string());
+        }
+
+        self.resolve_paths();
+        self.normalize_defaults();
+        Ok(report)
+    }
+
+    /// Save engine storage to disk. The write is atomic, and the previous file is kept
+    /// as a rotating backup as long as it was readable.
+    pub async fn save(&self) -> Result<()> {
+        self.write(true).await
+    }
+
+    /// Save after only usage statistics changed. These writes come every minute and after
+    /// every match game, so they leave the backups alone; rotating them here would soon
+    /// replace every backup of a real change.
+    pub async fn save_usage(&self) -> Result<()> {
+        self.write(false).await
+    }
+
+    async fn write(&self, rotate_backups: bool) -> Result<()> {
+        let path = Self::get_storage_path()?;
+        log::info!("Saving engine storage to: {}", path.display());
+        
+        let contents = serde_json::to_string_pretty(&self.portable())?;
+        if rotate_backups {
+            let previous_is_valid = match tokio::fs::read_to_string(&path).await {
+                Ok(previous) => Self::parse(&previous).is_ok(),
+                Err(_) => false,
+            };
+            if previous_is_valid {
+                atomic_file::rotate_backups(&path, BACKUP_COUNT).await?;
+            }
+        }
+        atomic_file::write_atomic(&path, contents.as_bytes()).await?;
+        
+        log::info!("Saved {} engines to storage", self.engines.len());
+        Ok(())
+    }
+
+    /// Add a new engine configuration
+    pub fn add_engine(&mut self, config: EngineConfig) -> Result<String> {
+        // Check if an engine with the same path already exists
+        if self.engines.iter().any(|e| e.path == config.path) {
+            return Err(anyhow!("An engine with this path is already configured"));
+        }
+
+        let id = config.id.clone();
+        self.engines.push(config);
+        Ok(id)
+    }
+
+    /// Remove an engine by ID, moving it to the trash
+    pub fn remove_engine(&mut self, engine_id: &str) -> Result<()> {
+        let index = self
+            .engines
+            .iter()
+            .position(|e| e.id == engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+        let engine = self.engines.remove(index);
+        self.trash.push(TrashedEngine {
+            engine,
+            removed_at: chrono::Utc::now().to_rfc3339(),
+        });
+
+        self.prune_groups();
+        self.normalize_defaults();
+        Ok(())
+    }
+
+    /// Put a removed engine back at the end of the list. An eval file removed in the
+    /// meantime is cleared; an engine since configured at the same path blocks the restore.
+    pub fn restore_engine(&mut self, engine_id: &str) -> Result<()> {
+        let index = self
+            .trash
+            .iter()
+            .position(|t| t.engine.id == engine_id)
+            .ok_or_else(|| anyhow!("Engine not in trash: {}", engine_id))?;
+        let path = &self.trash[index].engine.path;
+        if self.engines.iter().any(|e| e.path == *path) {
+            return Err(anyhow!("An engine with this path is already configured"));
+        }
+        let mut engine = self.trash.remove(index).engine;
+        if engine.eval_file_id.as_ref().is_some_and(|id| self.get_eval_file(id).is_none()) {
+            engine.eval_file_id = None;
+        }
+        self.engines.push(engine);
+        self.prune_groups();
+        self.normalize_defaults();
+        Ok(())
+    }
+
+    /// Permanently delete one engine from the trash, or all of them with `None`.
+    /// Returns how many were deleted.
+    pub fn purge_trash(&mut self, engine_id: Option<&str>) -> Result<usize> {
+        let initial_len = self.trash.len();
+        match engine_id {
+            Some(id) => self.trash.retain(|t| t.engine.id != id),
+            None => self.trash.clear(),
+        }
+        if let (Some(id), true) = (engine_id, self.trash.len() == initial_len) {
+            return Err(anyhow!("Engine not in trash: {}", id));
+        }
+        Ok(initial_len - self.trash.len())
+    }
+
+    /// Permanently delete engines removed more than `retention_days` days ago; 0 keeps them
+    /// until the trash is purged. Returns how many were deleted.
+    pub fn purge_expired_trash(&mut self, retention_days: u32) -> usize {
+        if retention_days == 0 {
+            return 0;
+        }
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(retention_days.into());
+        let initial_len = self.trash.len();
+        self.trash.retain(|t| {
+            chrono::DateTime::parse_from_rfc3339(&t.removed_at).map_or(true, |removed_at| removed_at.with_timezone(&chrono::Utc) >= cutoff)
+        });
+        initial_len - self.trash.len()
+    }
+
+    /// Get an engine by ID
+    pub fn get_engine(&self, engine_id: &str) -> Option<&EngineConfig> {
+        self.engines.iter().find(|e| e.id == engine_id)
+    }
+
+    /// Get a mutable reference to an engine by ID
+    #[allow(dead_code)]
+    pub fn get_engine_mut(&mut self, engine_id: &str) -> Option<&mut EngineConfig> {
+        self.engines.iter_mut().find(|e| e.id == engine_id)
+    }
+
+    /// Update last used timestamp for an engine
+    pub fn update_last_used(&mut self, engine_id: &str) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found"))?;
+        
+        engine.last_used = Some(chrono::Utc::now().to_rfc3339());
+        Ok(())
+    }
+
+    /// Add to an engine's usage totals and mark it as used now
+    pub fn record_usage(&mut self, engine_id: &str, usage: &EngineUsage) -> Result<()> {
+        self.update_last_used(engine_id)?;
+        if let Some(engine) = self.get_engine_mut(engine_id) {
+            engine.usage.add(usage);
+        }
+        Ok(())
+    }
+
+    /// Check if the built-in engine is registered
+    pub fn has_builtin_engine(&self) -> bool {
+        self.engines.iter().any(|e| e.is_builtin)
+    }
+
+    /// Get all engine configurations
+    pub fn get_all_engines(&self) -> &[EngineConfig] {
+        &self.engines
+    }
+
+    /// Enable or disable an engine
+    pub fn set_engine_enabled(&mut self, engine_id: &str, enabled: bool) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found"))?;
+        
+        engine.enabled = enabled;
+        Ok(())
+    }
+
+    /// An engine that may be started, with an error saying why not otherwise
+    pub fn usable_engine(&self, engine_id: &str) -> Result<&EngineConfig> {
+        let engine = self.get_engine(engine_id).ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+        engine.ensure_enabled()?;
+        Ok(engine)
+    }
+
+    /// Save engine options
+    pub fn save_engine_options(&mut self, engine_id: &str, options: std::collections::HashMap<String, String>) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found"))?;
+        
+        engine.saved_options = Some(options);
+        Ok(())
+    }
+
+    /// Get saved engine options
+    pub fn get_engine_options(&self, engine_id: &str) -> Option<&std::collections::HashMap<String, String>> {
+        self.get_engine(engine_id)?.saved_options.as_ref()
+    }
+
+    /// Clone an engine with a new display name
+    pub fn clone_engine(&mut self, engine_id: &str, new_display_name: String) -> Result<String> {
+        let source_engine = self.get_engine(engine_id)
+            .ok_or_else(|| anyhow!("Source engine not found: {}", engine_id))?
+            .clone();
+
+        let mut cloned_engine = source_engine;
+        cloned_engine.id = Uuid::new_v4().to_string();
+        cloned_engine.display_name = new_display_name;
+        cloned_engine.is_builtin = false; // Cloned engines are never built-in
+        cloned_engine.created_at = chrono::Utc::now().to_rfc3339();
+        cloned_engine.last_used = None;
+        cloned_engine.usage = EngineUsage::default();
+
+        let new_id = cloned_engine.id.clone();
+        self.engines.push(cloned_engine);
+        Ok(new_id)
+    }
+
+    /// Update display name for an engine
+    pub fn update_display_name(&mut self, engine_id: &str, new_display_name: String) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found"))?;
+        
+        engine.display_name = new_display_name;
+        Ok(())
+    }
+
+    /// Choose the default engine for a purpose; `None` clears it
+    pub fn set_default_engine(&mut self, purpose: EnginePurpose, engine_id: Option<&str>) -> Result<()> {
+        if let Some(engine_id) = engine_id {
+            if self.get_engine(engine_id).is_none() {
+                return Err(anyhow!("Engine not found: {}", engine_id));
+            }
+        }
+        *self.default_engines.slot_mut(purpose) = engine_id.map(String::from);
+        Ok(())
+    }
+
+    /// The engine to use for a purpose when none is chosen: the purpose's default, then the
+    /// default game engine, then the built-in engine, then the first engine. Disabled
+    /// engines are skipped.
+    pub fn default_engine(&self, purpose: EnginePurpose) -> Option<&EngineConfig> {
+        let enabled = |id: Option<&str>| id.and_then(|id| self.get_engine(id)).filter(|e| e.enabled);
+        enabled(self.default_engines.get(purpose))
+            .or_else(|| enabled(self.default_engines.get(EnginePurpose::Game)))
+            .or_else(|| self.engines.iter().find(|e| e.enabled && e.is_builtin))
+            .or_else(|| self.engines.iter().find(|e| e.enabled))
+    }
+
+    /// Move an engine into a group, creating the group at the end of the list if it is new.
+    /// `None` or an empty name removes the engine from its group.
+    pub fn set_engine_group(&mut self, engine_id: &str, group: Option<String>) -> Result<()> {
+        let group = group.map(|g| g.trim().to_string()).filter(|g| !g.is_empty());
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+        engine.group = group.clone();
+
+        if let Some(group) = group {
+            if !self.groups.contains(&group) {
+                self.groups.push(group);
+            }
+        }
+        self.prune_groups();
+        Ok(())
+    }
+
+    /// Reorder the given engines among the positions they currently occupy, leaving all
+    /// other engines in place. Passing the members of one group reorders just that group.
+    pub fn reorder_engines(&mut self, engine_ids: &[String]) -> Result<()> {
+        let mut positions = Vec::with_capacity(engine_ids.len());
+        for (index, id) in engine_ids.iter().enumerate() {
+            if engine_ids[..index].contains(id) {
+                return Err(anyhow!("Engine listed twice: {}", id));
+            }
+            let position = self
+                .engines
+                .iter()
+                .position(|e| &e.id == id)
+                .ok_or_else(|| anyhow!("Engine not found: {}", id))?;
+            positions.push(position);
+        }
+
+        let reordered: Vec<EngineConfig> = positions.iter().map(|&p| self.engines[p].clone()).collect();
+        positions.sort_unstable();
+        for (position, engine) in positions.into_iter().zip(reordered) {
+            self.engines[position] = engine;
+        }
+        Ok(())
+    }
+
+    /// Set the display order of groups; every existing group must be listed exactly once
+    pub fn reorder_groups(&mut self, groups: Vec<String>) -> Result<()> {
+        let mut expected = self.groups.clone();
+        let mut given = groups.clone();
+        expected.sort();
+        given.sort();
+        if expected != given {
+            return Err(anyhow!("Group order must list each existing group exactly once"));
+        }
+        self.groups = groups;
+        Ok(())
+    }
+
+    /// Replace an engine's tags. Tags are trimmed and duplicates (ignoring case) dropped.
+    pub fn set_engine_tags(&mut self, engine_id: &str, tags: Vec<String>) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+
+        engine.tags.clear();
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !engine.has_tag(tag) {
+                engine.tags.push(tag.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace an engine's notes
+    pub fn set_engine_notes(&mut self, engine_id: &str, notes: String) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+
+        engine.notes = notes;
+        Ok(())
+    }
+
+    /// Set the default process priority of an engine
+    pub fn set_engine_priority(&mut self, engine_id: &str, priority: Option<ProcessPriority>) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+
+        engine.priority = priority;
+        Ok(())
+    }
+
+    /// Set whether an engine is kept running in the warm pool
+    pub fn set_engine_keep_warm(&mut self, engine_id: &str, keep_warm: bool) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+
+        engine.keep_warm = keep_warm;
+        Ok(())
+    }
+
+    /// Set or clear an engine's timeout overrides
+    pub fn set_engine_timeouts(&mut self, engine_id: &str, timeouts: Option<TimeoutOverrides>) -> Result<()> {
+        if let Some(timeouts) = &timeouts {
+            timeouts.validate()?;
+        }
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+
+        engine.timeouts = timeouts;
+        Ok(())
+    }
+
+    /// Replace an engine's command macros
+    pub fn set_engine_macros(&mut self, engine_id: &str, macros: Vec<UsiMacro>) -> Result<()> {
+        usi_macro::validate_macros(&macros)?;
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+
+        engine.macros = macros;
+        Ok(())
+    }
+
+    /// Set or clear an engine's win-rate calibration
+    pub fn set_engine_win_rate(&mut self, engine_id: &str, calibration: Option<WinRateCalibration>) -> Result<()> {
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+
+        engine.win_rate = calibration;
+        Ok(())
+    }
+
+    /// Register an eval file; a file already registered under the same path is updated instead
+    pub fn add_eval_file(&mut self, eval: EvalFile) -> String {
+        if let Some(existing) = self.eval_files.iter_mut().find(|e| e.path == eval.path) {
+            existing.name = eval.name;
+            existing.sha256 = eval.sha256;
+            existing.size_bytes = eval.size_bytes;
+            return existing.id.clone();
+        }
+        let id = eval.id.clone();
+        self.eval_files.push(eval);
+        id
+    }
+
+    /// Remove an eval file, detaching it from any engine that used it
+    pub fn remove_eval_file(&mut self, eval_file_id: &str) -> Result<()> {
+        let initial_len = self.eval_files.len();
+        self.eval_files.retain(|e| e.id != eval_file_id);
+        if self.eval_files.len() == initial_len {
+            return Err(anyhow!("Eval file not found: {}", eval_file_id));
+        }
+        for engine in &mut self.engines {
+            if engine.eval_file_id.as_deref() == Some(eval_file_id) {
+                engine.eval_file_id = None;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_eval_file(&self, eval_file_id: &str) -> Option<&EvalFile> {
+        self.eval_files.iter().find(|e| e.id == eval_file_id)
+    }
+
+    /// Point an engine at a registered eval file, or back at its built-in default with `None`
+    pub fn set_engine_eval_file(&mut self, engine_id: &str, eval_file_id: Option<String>) -> Result<()> {
+        if let Some(id) = &eval_file_id {
+            if self.get_eval_file(id).is_none() {
+                return Err(anyhow!("Eval file not found: {}", id));
+            }
+        }
+        let engine = self
+            .get_engine_mut(engine_id)
+            .ok_or_else(|| anyhow!("Engine not found: {}", engine_id))?;
+
+        engine.eval_file_id = eval_file_id;
+        Ok(())
+    }
+
+    /// Options that load the engine's eval file, `override_id` taking precedence over the
+    /// configured one. `options` are the engine's option definitions; when not given, the
+    /// ones recorded at validation are used.
+    pub fn eval_options_for(
+        &self,
+        engine_id: &str,
+        override_id: Option<&str>,
+        options: Option<&[engine_validator::EngineOption]>,
+    ) -> Result<Vec<(String, String)>> {
+        let Some(engine) = self.get_engine(engine_id) else {
+            return Ok(Vec::new());
+        };
+        let Some(eval_id) = override_id.or(engine.eval_file_id.as_deref()) else {
+            return Ok(Vec::new());
+        };
+        let eval = self
+            .get_eval_file(eval_id)
+            .ok_or_else(|| anyhow!("Eval file not found: {}", eval_id))?;
+        let recorded = engine.metadata.as_ref().map(|m| m.options.as_slice()).unwrap_or_default();
+        eval_file::eval_options(options.unwrap_or(recorded), eval)
+    }
+
+    /// Options sent to an engine before `isready`: its saved options, then those selecting
+    /// its eval file (or `eval_override`), which take precedence
+    pub fn startup_options(&self, engine_id: &str, eval_override: Option<&str>) -> Vec<(String, String)> {
+        self.init_options(engine_id, None, eval_override)
+    }
+
+    /// Like [`startup_options`](Self::startup_options), with `temp_options` in place of the
+    /// saved options when given
+    pub fn init_options(
+        &self,
+        engine_id: &str,
+        temp_options: Option<&std::collections::HashMap<String, String>>,
+        eval_override: Option<&str>,
+    ) -> Vec<(String, String)> {
+        let mut options: Vec<(String, String)> = temp_options
+            .or_else(|| self.get_engine_options(engine_id))
+            .map(|options| options.iter().map(|(name, value)| (name.clone(), value.clone())).collect())
+            .unwrap_or_default();
+        options.sort();
+        match self.eval_options_for(engine_id, eval_override, None) {
+            Ok(eval_options) => {
+                options.retain(|(name, _)| !eval_options.iter().any(|(eval_name, _)| eval_name == name));
+                options.extend(eval_options);
+            }
+            Err(e) => log::warn!("Not selecting eval file for engine {}: {}", engine_id, e),
+        }
+        options
+    }
+
+    /// All tags in use, sorted
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.engines.iter().flat_map(|e| &e.tags) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+        tags.sort_by_key(|t| t.to_lowercase());
+        tags
+    }
+
+    /// Engines in manual order, optionally only those carrying `tag` and only enabled or
+    /// disabled ones
+    pub fn filtered_engines(&self, tag: Option<&str>, enabled: Option<bool>) -> Vec<EngineConfig> {
+        self.engines
+            .iter()
+            .filter(|e| tag.map_or(true, |tag| e.has_tag(tag)))
+            .filter(|e| enabled.map_or(true, |enabled| e.enabled == enabled))
+            .cloned()
+            .collect()
+    }
+
+    /// Engines arranged by group in display order, with ungrouped engines last, filtered as
+    /// by `filtered_engines`
+    pub fn grouped_engines(&self, tag: Option<&str>, enabled: Option<bool>) -> GroupedEngines {
+        let engines = self.filtered_engines(tag, enabled);
+        let groups = self
+            .groups
+            .iter()
+            .map(|name| EngineGroup {
+                name: name.clone(),
+                engines: engines.iter().filter(|e| e.group.as_ref() == Some(name)).cloned().collect(),
+            })
+            .collect();
+        let ungrouped = engines.iter().filter(|e| e.group.is_none()).cloned().collect();
+        GroupedEngines { groups, ungrouped }
+    }
+
+    /// Keep the group list in step with the engines: drop groups nobody uses and
+    /// add any group an engine refers to that is missing from the list
+    fn prune_groups(&mut self) {
+        let engines = &self.engines;
+        self.groups.retain(|g| engines.iter().any(|e| e.group.as_ref() == Some(g)));
+        for engine in &self.engines {
+            if let Some(group) = &engine.group {
+                if !self.groups.contains(group) {
+                    self.groups.push(group.clone());
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine_validator::{diff_options, EngineOption};
+
+    fn options(lines: &[&str]) -> Vec<EngineOption> {
+        lines.iter().map(|line| EngineOption::parse(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_migrate_v1_and_salvage_truncated_file() {
+        let mut value = serde_json::json!({
+            "version": "1.0",
+            "engines": [{
+                "id": "a", "name": "Alpha", "display_name": "", "path": "/alpha", "metadata": null,
+                "is_builtin": false, "enabled": true, "last_used": null,
+                "created_at": "2024-01-01T00:00:00Z", "saved_options": null
+            }]
+        });
+        migrate(&mut value).unwrap();
+        let storage: EngineStorage = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(storage.schema_version, SCHEMA_VERSION);
+        assert_eq!(storage.engines[0].display_name, "Alpha");
+        assert!(value.get("version").is_none());
+
+        // A write cut off in the middle of the second engine keeps the first one
+        let full = serde_json::to_string_pretty(&EngineStorage {
+            schema_version: SCHEMA_VERSION,
+            engines: vec![storage.engines[0].clone(), EngineConfig::new("Beta {".to_string(), "/beta".to_string(), None, false)],
+            groups: Vec::new(),
+            eval_files: Vec::new(),
+            default_engines: DefaultEngines::default(),
+            trash: Vec::new(),
+            engines_root: None,
+        })
+        .unwrap();
+        let truncated = &full[..full.find("/beta").unwrap()];
+        let (engines, dropped) = salvage_engines(truncated);
+        assert_eq!(engines.len(), 1);
+        assert_eq!(engines[0].name, "Alpha");
+        assert_eq!(dropped, 0);
+
+        let mut future = serde_json::json!({ "schema_version": SCHEMA_VERSION + 1, "engines": [] });
+        assert!(migrate(&mut future).is_err());
+    }
+
+    #[test]
+    fn test_favorite_becomes_default_engines() {
+        let engine = |id: &str, is_favorite: bool| {
+            serde_json::json!({
+                "id": id, "name": id, "display_name": id, "path": format!("/{}", id), "metadata": null,
+                "is_builtin": id == "builtin", "enabled": true, "last_used": null,
+                "created_at": "2024-01-01T00:00:00Z", "saved_options": null, "is_favorite": is_favorite
+            })
+        };
+        let mut value = serde_json::json!({ "schema_version": 2, "engines": [engine("builtin", false), engine("yane", true)] });
+        migrate(&mut value).unwrap();
+        let mut storage: EngineStorage = serde_json::from_value(value).unwrap();
+        assert_eq!(storage.default_engines.game.as_deref(), Some("yane"));
+        assert_eq!(storage.default_engines.analysis.as_deref(), Some("yane"));
+        assert_eq!(storage.default_engine(EnginePurpose::Tsume).unwrap().id, "yane");
+
+        storage.set_default_engine(EnginePurpose::Tsume, Some("builtin")).unwrap();
+        assert!(storage.set_default_engine(EnginePurpose::Tsume, Some("missing")).is_err());
+        storage.set_engine_enabled("builtin", false).unwrap();
+        assert_eq!(storage.default_engine(EnginePurpose::Tsume).unwrap().id, "yane");
+
+        // Removing the default game engine falls back to the built-in one
+        storage.remove_engine("yane").unwrap();
+        assert_eq!(storage.default_engines.game.as_deref(), Some("builtin"));
+        assert!(storage.default_engines.analysis.is_none());
+        assert_eq!(storage.default_engines.tsume.as_deref(), Some("builtin"));
+    }
+
+    #[test]
+    fn test_usage_totals() {
+        let mut storage = EngineStorage::default();
+        let engine = EngineConfig::new("Yane".to_string(), "/yane".to_string(), None, false);
+        let id = engine.id.clone();
+        storage.engines.push(engine);
+
+        let game = EngineUsage { games_played: 1, nodes_searched: 5000, ..Default::default() };
+        storage.record_usage(&id, &game).unwrap();
+        storage.record_usage(&id, &game).unwrap();
+        let yane = storage.get_engine(&id).unwrap();
+        assert_eq!((yane.usage.games_played, yane.usage.nodes_searched), (2, 10_000));
+        assert!(yane.last_used.is_some());
+        assert!(storage.record_usage("missing", &game).is_err());
+
+        let clone = storage.clone_engine(&id, "Yane 2".to_string()).unwrap();
+        assert!(storage.get_engine(&clone).unwrap().usage.is_empty());
+    }
+
+    #[test]
+    fn test_trash() {
+        let mut storage = EngineStorage::default();
+        let id = storage.add_engine(EngineConfig::new("Yane".to_string(), "/yane".to_string(), None, false)).unwrap();
+        storage.save_engine_options(&id, [("Threads".to_string(), "8".to_string())].into()).unwrap();
+        storage.set_engine_group(&id, Some("NNUE".to_string())).unwrap();
+
+        storage.remove_engine(&id).unwrap();
+        assert!(storage.get_engine(&id).is_none());
+        assert!(storage.groups.is_empty());
+
+        // A new engine at the same path blocks the restore until it is removed
+        let other = storage.add_engine(EngineConfig::new("Yane 2".to_string(), "/yane".to_string(), None, false)).unwrap();
+        assert!(storage.restore_engine(&id).is_err());
+        storage.remove_engine(&other).unwrap();
+        storage.restore_engine(&id).unwrap();
+        let restored = storage.get_engine(&id).unwrap();
+        assert_eq!(restored.saved_options.as_ref().unwrap()["Threads"], "8");
+        assert_eq!(storage.groups, vec!["NNUE"]);
+
+        storage.trash[0].removed_at = "2020-01-01T00:00:00Z".to_string();
+        storage.remove_engine(&id).unwrap();
+        assert_eq!(storage.purge_expired_trash(0), 0);
+        assert_eq!(storage.purge_expired_trash(30), 1);
+        assert_eq!(storage.trash[0].engine.id, id);
+        assert!(storage.purge_trash(Some("missing")).is_err());
+        assert_eq!(storage.purge_trash(None).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_relative_paths_and_relocation() {
+        let root = std::env::temp_dir().join("engines");
+        let inside = root.join("yane").join("YaneuraOu").display().to_string();
+        let outside = std::env::temp_dir().join("apery").display().to_string();
+        let mut storage = EngineStorage::default();
+        let yane = storage.add_engine(EngineConfig::new("Yane".to_string(), inside.clone(), None, false)).unwrap();
+        storage.add_engine(EngineConfig::new("Apery".to_string(), outside.clone(), None, false)).unwrap();
+        assert!(storage.set_engines_root(Some("engines".to_string())).is_err());
+        storage.set_engines_root(Some(root.display().to_string())).unwrap();
+
+        let portable = storage.portable();
+        assert_eq!(Path::new(&portable.engines[0].path), Path::new("yane").join("YaneuraOu"));
+        assert_eq!(portable.engines[1].path, outside);
+        let (loaded, _) = EngineStorage::parse(&serde_json::to_string(&portable).unwrap()).unwrap();
+        assert_eq!(loaded.engines[0].path, inside);
+
+        let moved_root = std::env::temp_dir().join("moved");
+        let moved = storage.relocate_engines(&root.display().to_string(), &moved_root.display().to_string()).unwrap();
+        assert_eq!(moved, vec![yane.clone()]);
+        assert_eq!(Path::new(&storage.get_engine(&yane).unwrap().path), moved_root.join("yane").join("YaneuraOu"));
+        assert_eq!(storage.engines_root, Some(moved_root.display().to_string()));
+    }
+
+    #[test]
+    fn test_option_diff_and_saved_option_migration() {
+        let old = options(&[
+            "option name Hash type spin default 16 min 1 max 4096",
+            "option name Threads type spin default 1 min 1 max 64",
+            "option name EvalDir type string default eval",
+            "option name Style type combo default Normal var Normal var Aggressive",
+        ]);
+        let new = options(&[
+            "option name USI_Hash type spin default 16 min 1 max 1024",
+            "option name Threads type spin default 1 min 1 max 32",
+            "option name Style type combo default Normal var Normal var Solid",
+            "option name BookFile type string default book.bin",
+        ]);
+
+        let diff = diff_options(&old, &new);
+        assert_eq!(diff.added, vec!["BookFile"]);
+        assert_eq!(diff.removed, vec!["EvalDir"]);
+        assert_eq!((diff.renamed[0].from.as_str(), diff.renamed[0].to.as_str()), ("Hash", "USI_Hash"));
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].fields, vec!["max"]);
+
+        let mut engine = EngineConfig::new("Engine".to_string(), "/engine".to_string(), None, false);
+        engine.metadata = Some(EngineMetadata { name: "Engine".to_string(), options: new, ..Default::default() });
+        engine.saved_options = Some(
+            [("Hash", "2048"), ("Threads", "48"), ("EvalDir", "nn"), ("Style", "Aggressive")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+
+        let migration = engine.migrate_saved_options(&diff);
+        assert_eq!(migration.renamed.len(), 1);
+        assert_eq!(migration.pruned, vec!["EvalDir", "Style"]);
+        assert_eq!(migration.clamped, vec!["Threads", "USI_Hash"]);
+        let saved = engine.saved_options.unwrap();
+        assert_eq!(saved.get("USI_Hash").map(String::as_str), Some("1024"));
+        assert_eq!(saved.get("Threads").map(String::as_str), Some("32"));
+        assert_eq!(saved.len(), 2);
+    }
+
+    #[test]
+    fn test_engine_groups_ordering_and_tags() {
+        let mut storage = EngineStorage::default();
+        let ids: Vec<String> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| storage.add_engine(EngineConfig::new(name.to_string(), format!("/{}", name), None, false)).unwrap())
+            .collect();
+
+        storage.set_engine_group(&ids[0], Some("NNUE".to_string())).unwrap();
+        storage.set_engine_group(&ids[2], Some("NNUE".to_string())).unwrap();
+        storage.set_engine_group(&ids[1], Some(" Classic ".to_string())).unwrap();
+        assert_eq!(storage.groups, vec!["NNUE", "Classic"]);
+
+        // Reordering the members of a group leaves the other engines where they were
+        storage.reorder_engines(&[ids[2].clone(), ids[0].clone()]).unwrap();
+        let order: Vec<&str> = storage.engines.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(order, vec!["c", "b", "a", "d"]);
+        assert!(storage.reorder_engines(&[ids[0].clone(), ids[0].clone()]).is_err());
+
+        storage.reorder_groups(vec!["Classic".to_string(), "NNUE".to_string()]).unwrap();
+        assert!(storage.reorder_groups(vec!["NNUE".to_string()]).is_err());
+        let grouped = storage.grouped_engines(None, None);
+        assert_eq!(grouped.groups[0].name, "Classic");
+        assert_eq!(grouped.groups[1].engines.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), vec!["c", "a"]);
+        assert_eq!(grouped.ungrouped[0].name, "d");
+
+        // Emptying a group removes it
+        storage.set_engine_group(&ids[1], None).unwrap();
+        assert_eq!(storage.groups, vec!["NNUE"]);
+
+        storage.set_engine_tags(&ids[0], vec!["2GB NNUE".to_string(), " nnue ".to_string(), "NNUE".to_string()]).unwrap();
+        storage.set_engine_tags(&ids[3], vec!["unstable".to_string()]).unwrap();
+        assert_eq!(storage.get_engine(&ids[0]).unwrap().tags, vec!["2GB NNUE", "nnue"]);
+        assert_eq!(storage.all_tags(), vec!["2GB NNUE", "nnue", "unstable"]);
+        assert_eq!(storage.filtered_engines(Some("NNUE"), None).len(), 1);
+        storage.set_engine_enabled(&ids[0], false).unwrap();
+        assert_eq!(storage.filtered_engines(None, Some(false)).len(), 1);
+        assert!(storage.usable_engine(&ids[0]).unwrap_err().to_string().contains("disabled"));
+        let tagged = storage.grouped_engines(Some("unstable"), None);
+        assert!(tagged.groups[0].engines.is_empty());
+        assert_eq!(tagged.ungrouped[0].name, "d");
+    }
+
+    #[tokio::test]
+    async fn test_binary_change_detection() {
+        let path = std::env::temp_dir().join(format!("shogi-vibe-engine-{}", Uuid::new_v4()));
+        std::fs::write(&path, b"abc").unwrap();
+
+        let mut engine = EngineConfig::new("Engine".to_string(), path.display().to_string(), None, false);
+        engine.record_binary_hash().await;
+        assert_eq!(
+            engine.binary_sha256.as_deref(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert!(engine.check_binary().await.unwrap().1.is_none());
+
+        // Rebuilding the engine in place is reported
+        std::fs::write(&path, b"abcd").unwrap();
+        let (hash, changed) = engine.check_binary().await.unwrap();
+        let changed = changed.unwrap();
+        assert_eq!(changed.actual_sha256, hash);
+        assert_eq!(changed.expected_sha256, engine.binary_sha256.unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,751 @@
+use crate::engine_encoding::{EngineLines, OutputEncoding};
+use crate::engine_permissions;
+use crate::engine_process;
+use crate::shogi_rules::{Move, Position, STARTPOS_SFEN};
+use crate::timeouts::TimeoutConfig;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+/// Last lines of stderr added to a failed check
+const STDERR_CAPTURE_LINES: usize = 20;
+/// How long to wait for the rest of stderr once the engine has been stopped
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Engine metadata extracted during validation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EngineMetadata {
+    pub name: String,
+    pub author: Option<String>,
+    pub options: Vec<EngineOption>,
+    /// Encoding of the engine's `usi` response; Shift_JIS for many older Japanese engines
+    #[serde(default)]
+    pub encoding: OutputEncoding,
+    /// Every line the engine printed in answer to `usi`, up to and including `usiok`
+    #[serde(default)]
+    pub raw_banner: Vec<String>,
+    /// Version number taken from the engine name, e.g. "7.6.3"
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Well-known engine family, so the UI can offer family-specific presets
+    #[serde(default)]
+    pub family: Option<EngineFamily>,
+}
+
+/// Engine families with their own option conventions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineFamily {
+    YaneuraOu,
+    Apery,
+    Gikou,
+    Dlshogi,
+}
+
+impl EngineFamily {
+    /// Recognize the family from the `id` lines, falling back to family-specific options
+    pub fn detect(name: &str, author: Option<&str>, options: &[EngineOption]) -> Option<Self> {
+        let id = format!("{} {}", name, author.unwrap_or("")).to_lowercase();
+        let has_option = |option_name: &str| options.iter().any(|option| option.name == option_name);
+        if id.contains("dlshogi") || has_option("DNN_Model") || has_option("UCT_Threads") {
+            Some(Self::Dlshogi)
+        } else if id.contains("yaneuraou") || id.contains("やねうら王") {
+            Some(Self::YaneuraOu)
+        } else if id.contains("apery") {
+            Some(Self::Apery)
+        } else if id.contains("gikou") || id.contains("技巧") {
+            Some(Self::Gikou)
+        } else {
+            None
+        }
+    }
+}
+
+/// The first version-like token of an engine name: digits separated by dots,
+/// optionally prefixed with "v", in brackets or joined to the name with "_" ("Apery_v2.0.1")
+pub fn parse_version(name: &str) -> Option<String> {
+    name.split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .map(|token| token.trim_matches(|c: char| !c.is_alphanumeric() && c != '.'))
+        .map(|token| token.strip_prefix(['v', 'V']).unwrap_or(token))
+        .find(|token| {
+            token.contains('.')
+                && token.starts_with(|c: char| c.is_ascii_digit())
+                && token.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()))
+        })
+        .map(str::to_string)
+}
+
+/// USI engine option
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineOption {
+    pub name: String,
+    pub option_type: String,
+    pub default: Option<String>,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub var: Vec<String>,
+}
+
+/// Byte ranges of the whitespace-separated tokens of `line`
+fn token_spans(line: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                spans.push((s, i));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, line.len()));
+    }
+    spans
+}
+
+/// Keywords that start a field for an option type. String-like types take the whole rest
+/// of the line as their default, so they have no other keywords.
+fn field_keywords(option_type: &str) -> &'static [&'static str] {
+    match option_type {
+        "spin" => &["default", "min", "max"],
+        "combo" => &["default", "var"],
+        "check" => &["default"],
+        "button" => &[],
+        _ => &["default", "min", "max", "var"],
+    }
+}
+
+/// Types whose default runs to the end of the line and may contain spaces
+fn is_string_like(option_type: &str) -> bool {
+    matches!(option_type, "string" | "filename")
+}
+
+impl EngineOption {
+    /// Parse an option line from USI protocol
+    /// Format: option name <name> type <type> [default <value>] [min <value>] [max <value>] [var <value>]*
+    /// Names, string/filename defaults and combo values may contain spaces, which are kept
+    /// as the engine printed them. `<empty>` stands for an empty string default.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let spans = token_spans(line);
+        let token = |i: usize| &line[spans[i].0..spans[i].1];
+        // Text of tokens from..to (exclusive), with the spacing between them preserved
+        let text = |from: usize, to: usize| line[spans[from].0..spans[to - 1].1].to_string();
+
+        if spans.len() < 2 || token(0) != "option" || token(1) != "name" {
+            return None;
+        }
+        let type_index = (2..spans.len()).find(|&i| token(i) == "type")?;
+        if type_index == 2 || type_index + 1 >= spans.len() {
+            return None;
+        }
+        let name = text(2, type_index);
+        let option_type = token(type_index + 1).to_string();
+
+        let mut default = None;
+        let mut min = None;
+        let mut max = None;
+        let mut var = Vec::new();
+
+        let rest = type_index + 2;
+        if is_string_like(&option_type) {
+            if let Some(default_index) = (rest..spans.len()).find(|&i| token(i) == "default") {
+                let value = if default_index + 1 < spans.len() {
+                    text(default_index + 1, spans.len())
+                } else {
+                    String::new()
+                };
+                default = Some(if value == "<empty>" { String::new() } else { value });
+            }
+        } else {
+            let keywords = field_keywords(&option_type);
+            let mut i = rest;
+            while i < spans.len() {
+                let keyword = token(i);
+                if !keywords.contains(&keyword) {
+                    i += 1;
+                    continue;
+                }
+                let end = (i + 1..spans.len())
+                    .find(|&j| keywords.contains(&token(j)))
+                    .unwrap_or(spans.len());
+                let value = if end > i + 1 { Some(text(i + 1, end)) } else { None };
+                match (keyword, value) {
+                    ("default", value) => default = value.or(Some(String::new())),
+                    ("min", value) => min = value,
+                    ("max", value) => max = value,
+                    ("var", Some(value)) => var.push(value),
+                    _ => {}
+                }
+                i = end;
+            }
+        }
+
+        Some(Self {
+            name,
+            option_type,
+            default,
+            min,
+            max,
+            var,
+        })
+    }
+}
+
+/// An option whose definition changed between two validations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionChange {
+    pub name: String,
+    /// Which parts changed: "type", "default", "min", "max" or "var"
+    pub fields: Vec<String>,
+    pub old: EngineOption,
+    pub new: EngineOption,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// Differences between an engine's old and new option sets
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptionDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<OptionRename>,
+    pub changed: Vec<OptionChange>,
+}
+
+impl OptionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Name used to pair up renamed options, e.g. "USI_Hash" / "Hash" or "EvalDir" / "Eval_Dir"
+fn normalized_option_name(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    let lower = lower.strip_prefix("usi_").unwrap_or(&lower);
+    lower.chars().filter(|c| !matches!(c, '_' | '-' | ' ')).collect()
+}
+
+/// Compare two option sets. An option that disappears while one of the same type and a
+/// similar name appears is reported as a rename rather than a removal plus an addition.
+pub fn diff_options(old: &[EngineOption], new: &[EngineOption]) -> OptionDiff {
+    let mut diff = OptionDiff::default();
+    let mut added: Vec<&EngineOption> = new.iter().filter(|n| !old.iter().any(|o| o.name == n.name)).collect();
+
+    for old_option in old {
+        match new.iter().find(|n| n.name == old_option.name) {
+            Some(new_option) => {
+                let mut fields = Vec::new();
+                if old_option.option_type != new_option.option_type {
+                    fields.push("type");
+                }
+                if old_option.default != new_option.default {
+                    fields.push("default");
+                }
+                if old_option.min != new_option.min {
+                    fields.push("min");
+                }
+                if old_option.max != new_option.max {
+                    fields.push("max");
+                }
+                if old_option.var != new_option.var {
+                    fields.push("var");
+                }
+                if !fields.is_empty() {
+                    diff.changed.push(OptionChange {
+                        name: old_option.name.clone(),
+                        fields: fields.into_iter().map(String::from).collect(),
+                        old: old_option.clone(),
+                        new: new_option.clone(),
+                    });
+                }
+            }
+            None => {
+                let normalized = normalized_option_name(&old_option.name);
+                let renamed_to = added.iter().position(|n| {
+                    n.option_type == old_option.option_type && normalized_option_name(&n.name) == normalized
+                });
+                match renamed_to {
+                    Some(index) => diff.renamed.push(OptionRename {
+                        from: old_option.name.clone(),
+                        to: added.remove(index).name.clone(),
+                    }),
+                    None => diff.removed.push(old_option.name.clone()),
+                }
+            }
+        }
+    }
+
+    diff.added = added.into_iter().map(|n| n.name.clone()).collect();
+    diff
+}
+
+/// Hex-encoded SHA-256 of the file at `path`
+pub async fn binary_sha256(path: &str) -> Result<String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| anyhow!("Failed to open engine binary {}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn default_usi_timeout_ms() -> u64 {
+    TimeoutConfig::global().validation_ms
+}
+
+fn default_ready_timeout_ms() -> u64 {
+    TimeoutConfig::global().readyok_ms
+}
+
+/// How thoroughly to validate an engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationOptions {
+    /// How long to wait for `usiok`
+    #[serde(default = "default_usi_timeout_ms")]
+    pub usi_timeout_ms: u64,
+    /// Also send `isready` and wait for `readyok`. NNUE engines only load (and fail on
+    /// missing) eval files at this point.
+    #[serde(default)]
+    pub deep: bool,
+    /// How long to wait for `readyok` in deep mode; loading a large net can take a while
+    #[serde(default = "default_ready_timeout_ms")]
+    pub ready_timeout_ms: u64,
+    /// Options sent before `isready` in deep mode, e.g. the engine's saved and eval file options
+    #[serde(default)]
+    pub options: Vec<(String, String)>,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            usi_timeout_ms: default_usi_timeout_ms(),
+            deep: false,
+            ready_timeout_ms: default_ready_timeout_ms(),
+            options: Vec::new(),
+        }
+    }
+}
+
+/// The stderr of an engine being checked, read in the background. An engine that fails to
+/// start usually says why there (a shared library that is not found, an eval file it cannot
+/// open) and nowhere in its USI output.
+pub(crate) struct StderrCapture {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    reader: JoinHandle<()>,
+}
+
+impl StderrCapture {
+    fn start(stderr: ChildStderr) -> Self {
+        let lines = Arc::new(Mutex::new(VecDeque::new()));
+        let captured = lines.clone();
+        let reader = tokio::spawn(async move {
+            let mut stderr = EngineLines::new(stderr);
+            while let Ok(Some(line)) = stderr.next_line().await {
+                log::debug!("Engine validation stderr: {}", line);
+                let mut captured = captured.lock().unwrap_or_else(|e| e.into_inner());
+                if captured.len() == STDERR_CAPTURE_LINES {
+                    captured.pop_front();
+                }
+                captured.push_back(line);
+            }
+        });
+        Self { lines, reader }
+    }
+
+    /// Add what the engine printed to stderr to `error`. Call it after stopping the engine, so
+    /// that the output up to its exit has been read.
+    pub(crate) async fn explain(self, error: anyhow::Error) -> anyhow::Error {
+        let _ = timeout(STDERR_DRAIN_TIMEOUT, self.reader).await;
+        let lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        let text: Vec<&str> = lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+        if text.is_empty() {
+            error
+        } else {
+            anyhow!("{}. The engine printed to stderr:\n{}", error, text.join("\n"))
+        }
+    }
+}
+
+/// Spawn the engine process in its own directory, as it is run for games, so that
+/// relative eval and book paths resolve the same way
+pub(crate) fn spawn_for_check(path: &str) -> Result<(Child, ChildStdin, EngineLines<ChildStdout>, StderrCapture)> {
+    let mut command = Command::new(path);
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = std::path::Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        command.current_dir(dir);
+    }
+    engine_process::prepare(&mut command);
+    let mut child = command
+        .spawn()
+        .map_err(|e| anyhow!("Failed to spawn engine process: {}", e))?;
+    engine_process::attach(&child);
+
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to get stdin"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to get stdout"))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| anyhow!("Failed to get stderr"))?;
+    Ok((child, stdin, EngineLines::new(stdout), StderrCapture::start(stderr)))
+}
+
+/// Validate a USI engine and extract its metadata
+pub async fn validate_engine(path: &str) -> Result<EngineMetadata> {
+    validate_engine_with(path, &ValidationOptions::default()).await
+}
+
+/// Validate a USI engine with the given timeouts, optionally checking that it becomes ready
+pub async fn validate_engine_with(path: &str, validation: &ValidationOptions) -> Result<EngineMetadata> {
+    log::info!("Validating engine at path: {} (deep: {})", path, validation.deep);
+
+    // Check if the file exists
+    if !std::path::Path::new(path).exists() {
+        return Err(anyhow!("Engine executable not found at path: {}", path));
+    }
+    engine_permissions::ensure_not_quarantined(std::path::Path::new(path)).await?;
+
+    let (mut child, mut stdin, mut lines, stderr) = spawn_for_check(path)?;
+
+    // Send "usi" and read and parse the response with timeout. Failing to write is reported
+    // like a missing usiok, with stderr: an engine that exits at once closes its stdin.
+    let usi_timeout = Duration::from_millis(validation.usi_timeout_ms);
+    let result = timeout(usi_timeout, async {
+        stdin
+            .write_all(b"usi\n")
+            .await
+            .map_err(|e| anyhow!("Failed to write to engine: {}", e))?;
+        stdin.flush().await?;
+
+        let mut name = String::from("Unknown Engine");
+        let mut author = None;
+        let mut options = Vec::new();
+        let mut got_usiok = false;
+        let mut raw_banner = Vec::new();
+
+        while let Some(line) = lines.next_line().await? {
+            log::debug!("Engine validation output: {}", line);
+            raw_banner.push(line.clone());
+
+            if let Some(id_name) = line.strip_prefix("id name ") {
+                name = id_name.trim().to_string();
+            } else if let Some(id_author) = line.strip_prefix("id author ") {
+                author = Some(id_author.trim().to_string());
+            } else if line.starts_with("option name ") {
+                if let Some(option) = EngineOption::parse(&line) {
+                    options.push(option);
+                }
+            } else if line == "usiok" {
+                got_usiok = true;
+                break;
+            }
+        }
+
+        if !got_usiok {
+            return Err(anyhow!("Engine did not respond with 'usiok'"));
+        }
+
+        Ok::<EngineMetadata, anyhow::Error>(EngineMetadata {
+            version: parse_version(&name),
+            family: EngineFamily::detect(&name, author.as_deref(), &options),
+            encoding: lines.encoding(),
+            raw_banner,
+            name,
+            author,
+            options,
+        })
+    })
+    .await;
+
+    let result = match result {
+        Ok(Ok(metadata)) if validation.deep => check_ready(&mut stdin, &mut lines, validation).await.map(|_| metadata),
+        Ok(result) => result,
+        Err(_) => Err(anyhow!(
+            "Timeout waiting for engine response ({} seconds)",
+            usi_timeout.as_secs_f64()
+        )),
+    };
+
+    // Try to kill the process gracefully
+    let _ = stdin.write_all(b"quit\n").await;
+    let _ = stdin.flush().await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    engine_process::kill(&mut child).await;
+
+    match result {
+        Ok(metadata) => {
+            log::info!("Engine validation successful: {}", metadata.name);
+            Ok(metadata)
+        }
+        Err(e) => Err(stderr.explain(e).await),
+    }
+}
+
+/// Send the options and `isready`, and wait for `readyok`. Messages the engine prints meanwhile
+/// (usually `info string` complaints about eval files) are included in the error.
+pub(crate) async fn check_ready(
+    stdin: &mut ChildStdin,
+    lines: &mut EngineLines<ChildStdout>,
+    validation: &ValidationOptions,
+) -> Result<()> {
+    for (name, value) in &validation.options {
+        stdin.write_all(format!("setoption name {} value {}\n", name, value).as_bytes()).await?;
+    }
+    stdin.write_all(b"isready\n").await?;
+    stdin.flush().await?;
+
+    let ready_timeout = Duration::from_millis(validation.ready_timeout_ms);
+    let mut messages = Vec::new();
+    let outcome = timeout(ready_timeout, async {
+        while let Some(line) = lines.next_line().await? {
+            log::debug!("Engine validation output: {}", line);
+            if line.trim() == "readyok" {
+                return Ok(true);
+            }
+            if let Some(message) = line.strip_prefix("info string ") {
+                messages.push(message.trim().to_string());
+            }
+        }
+        Ok::<bool, anyhow::Error>(false)
+    })
+    .await;
+
+    let detail = if messages.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", messages.join("; "))
+    };
+    match outcome {
+        Ok(Ok(true)) => Ok(()),
+        Ok(Ok(false)) => Err(anyhow!("Engine exited during isready, likely missing eval files{}", detail)),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(anyhow!(
+            "Engine did not answer isready within {} seconds{}",
+            ready_timeout.as_secs(),
+            detail
+        )),
+    }
+}
+
+/// Outcome of a short search from the starting position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestResult {
+    pub passed: bool,
+    pub best_move: Option<String>,
+    /// Time from `go` to `bestmove`
+    pub elapsed_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+const SELF_TEST_MOVETIME_MS: u64 = 200;
+
+/// Play one move from the starting position (`usinewgame`, `position startpos`,
+/// `go movetime 200`) and check that a legal bestmove arrives. Catches engines that answer
+/// `usi` but cannot search, e.g. because of a missing book or a bad eval path.
+pub async fn self_test(path: &str, validation: &ValidationOptions) -> SelfTestResult {
+    let mut result = SelfTestResult {
+        passed: false,
+        best_move: None,
+        elapsed_ms: None,
+        error: None,
+    };
+    match run_self_test(path, validation, &mut result).await {
+        Ok(()) => result.passed = true,
+        Err(e) => {
+            log::warn!("Engine self-test failed for {}: {}", path, e);
+            result.error = Some(e.to_string());
+        }
+    }
+    result
+}
+
+async fn run_self_test(path: &str, validation: &ValidationOptions, result: &mut SelfTestResult) -> Result<()> {
+    let (mut child, mut stdin, mut lines, stderr) = spawn_for_check(path)?;
+    let usi_timeout = Duration::from_millis(validation.usi_timeout_ms);
+    let outcome = async {
+        stdin.write_all(b"usi\n").await?;
+        stdin.flush().await?;
+        timeout(usi_timeout, async {
+            while let Some(line) = lines.next_line().await? {
+                if line.trim() == "usiok" {
+                    return Ok(());
+                }
+            }
+            Err(anyhow!("Engine exited before usiok"))
+        })
+        .await
+        .map_err(|_| anyhow!("Timeout waiting for usiok"))??;
+        check_ready(&mut stdin, &mut lines, validation).await?;
+
+        stdin.write_all(format!("usinewgame\nposition startpos\ngo movetime {}\n", SELF_TEST_MOVETIME_MS).as_bytes()).await?;
+        stdin.flush().await?;
+        let start = tokio::time::Instant::now();
+        let best_move = timeout(Duration::from_millis(SELF_TEST_MOVETIME_MS) + usi_timeout, async {
+            while let Some(line) = lines.next_line().await? {
+                if let Some(rest) = line.strip_prefix("bestmove ") {
+                    return Ok(rest.split_whitespace().next().unwrap_or("").to_string());
+                }
+            }
+            Err(anyhow!("Engine exited while searching"))
+        })
+        .await
+        .map_err(|_| anyhow!("No bestmove within {} ms", SELF_TEST_MOVETIME_MS + validation.usi_timeout_ms))??;
+        result.elapsed_ms = Some(start.elapsed().as_millis() as u64);
+        result.best_move = Some(best_move.clone());
+
+        let position = Position::from_sfen(STARTPOS_SFEN)?;
+        match Move::from_usi(&best_move) {
+            Ok(mv) if position.is_legal(&mv) => Ok(()),
+            _ => Err(anyhow!("Engine played an illegal move from the starting position: {}", best_move)),
+        }
+    }
+    .await;
+
+    let _ = stdin.write_all(b"quit\n").await;
+    let _ = stdin.flush().await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    engine_process::kill(&mut child).await;
+    match outcome {
+        Ok(()) => Ok(()),
+        Err(e) => Err(stderr.explain(e).await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_option_spin() {
+        let line = "option name USI_Hash type spin default 16 min 1 max 1024";
+        let option = EngineOption::parse(line).unwrap();
+        assert_eq!(option.name, "USI_Hash");
+        assert_eq!(option.option_type, "spin");
+        assert_eq!(option.default, Some("16".to_string()));
+        assert_eq!(option.min, Some("1".to_string()));
+        assert_eq!(option.max, Some("1024".to_string()));
+    }
+
+    #[test]
+    fn test_parse_option_check() {
+        let line = "option name Ponder type check default false";
+        let option = EngineOption::parse(line).unwrap();
+        assert_eq!(option.name, "Ponder");
+        assert_eq!(option.option_type, "check");
+        assert_eq!(option.default, Some("false".to_string()));
+    }
+
+    #[test]
+    fn test_parse_option_string() {
+        let line = "option name BookFile type string default book.bin";
+        let option = EngineOption::parse(line).unwrap();
+        assert_eq!(option.name, "BookFile");
+        assert_eq!(option.option_type, "string");
+        assert_eq!(option.default, Some("book.bin".to_string()));
+    }
+
+    #[test]
+    fn test_parse_option_spaces_in_values() {
+        let option = EngineOption::parse("option name EvalDir type string default C:\\Program Files\\Engine  Data\\eval").unwrap();
+        assert_eq!(option.default, Some("C:\\Program Files\\Engine  Data\\eval".to_string()));
+
+        let option = EngineOption::parse("option name EvalFile type filename default nn model.bin").unwrap();
+        assert_eq!(option.option_type, "filename");
+        assert_eq!(option.default, Some("nn model.bin".to_string()));
+
+        let option = EngineOption::parse("option name DNN_Model type string default <empty>").unwrap();
+        assert_eq!(option.default, Some(String::new()));
+
+        // A value that looks like a keyword belongs to a string default
+        let option = EngineOption::parse("option name BookFile type string default min max var.db").unwrap();
+        assert_eq!(option.default, Some("min max var.db".to_string()));
+        assert_eq!((option.min, option.max), (None, None));
+
+        let option = EngineOption::parse("option name Strategy type combo default Strategy One var Strategy One var Ranging Rook").unwrap();
+        assert_eq!(option.default, Some("Strategy One".to_string()));
+        assert_eq!(option.var, vec!["Strategy One".to_string(), "Ranging Rook".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_real_engine_options() {
+        // YaneuraOu
+        let option = EngineOption::parse("option name USI_Hash type spin default 1024 min 1 max 33554432").unwrap();
+        assert_eq!((option.default.as_deref(), option.min.as_deref(), option.max.as_deref()), (Some("1024"), Some("1"), Some("33554432")));
+        let option = EngineOption::parse(
+            "option name BookFile type combo default standard_book.db var no_book var standard_book.db var yaneura_book1.db var user_book1.db",
+        )
+        .unwrap();
+        assert_eq!(option.var.len(), 4);
+        assert_eq!(option.default.as_deref(), Some("standard_book.db"));
+        let option = EngineOption::parse("option name ConsiderationMode type check default false").unwrap();
+        assert_eq!(option.default.as_deref(), Some("false"));
+
+        // Multi-word names and buttons
+        let option = EngineOption::parse("option name Clear Hash type button").unwrap();
+        assert_eq!((option.name.as_str(), option.option_type.as_str(), option.default), ("Clear Hash", "button", None));
+        let option = EngineOption::parse("option name Draw Value Black type spin default -2 min -30000 max 30000").unwrap();
+        assert_eq!(option.name, "Draw Value Black");
+        assert_eq!(option.min.as_deref(), Some("-30000"));
+
+        // USI_Variant style extension options
+        let option = EngineOption::parse("option name USI_Variant type combo default shogi var shogi var minishogi var chushogi").unwrap();
+        assert_eq!(option.name, "USI_Variant");
+        assert_eq!(option.var, vec!["shogi", "minishogi", "chushogi"]);
+
+        // Apery-style string without a default, and trailing whitespace
+        let option = EngineOption::parse("option name Eval_Dir type string  \r").unwrap();
+        assert_eq!(option.default, None);
+
+        assert!(EngineOption::parse("option name type spin").is_none());
+        assert!(EngineOption::parse("option name Hash").is_none());
+        assert!(EngineOption::parse("id name Engine").is_none());
+    }
+
+    #[test]
+    fn test_version_and_family() {
+        assert_eq!(parse_version("YaneuraOu NNUE 7.6.3 64ZEN2 TOURNAMENT").as_deref(), Some("7.6.3"));
+        assert_eq!(parse_version("Apery_v2.0.1").as_deref(), Some("2.0.1"));
+        assert_eq!(parse_version("dlshogi 2024").as_deref(), None);
+        assert_eq!(parse_version("Gikou 2 (v2.1.0-beta)").as_deref(), Some("2.1.0"));
+        assert_eq!(parse_version("Engine v1.2b").as_deref(), Some("1.2b"));
+
+        let options = vec![EngineOption::parse("option name DNN_Model type string default model.onnx").unwrap()];
+        assert_eq!(EngineFamily::detect("Kanzaki", None, &options), Some(EngineFamily::Dlshogi));
+        assert_eq!(EngineFamily::detect("YaneuraOu NNUE 7.6.3", Some("by yaneurao"), &[]), Some(EngineFamily::YaneuraOu));
+        assert_eq!(EngineFamily::detect("技巧2", None, &[]), Some(EngineFamily::Gikou));
+        assert_eq!(EngineFamily::detect("Apery", Some("Hiraoka Takuya"), &[]), Some(EngineFamily::Apery));
+        assert_eq!(EngineFamily::detect("Mystery", None, &[]), None);
+    }
+}
@@ -0,0 +1,1450 @@
+//! Engine vs Engine gameplay manager
+//! Manages automated games between two engines with spectator mode
+
+use crate::adjudication::{Adjudication, AdjudicationConfig, Adjudicator};
+use crate::autosave::{AutosaveEntry, AutosaveStorage};
+use crate::commentary;
+use crate::cpu_affinity::{self, CpuAffinity};
+use crate::engine_encoding;
+use crate::engine_process;
+use crate::engine_storage::EngineUsage;
+use crate::engine_validator::EngineOption;
+use crate::game_record::{GameRecord, GameRecordStorage, RecordedMove, Termination};
+use crate::game_session::position_command;
+use crate::handicap::Handicap;
+use crate::message;
+use crate::message::Message;
+use crate::metrics;
+use crate::process_priority::{self, ProcessPriority};
+use crate::shogi_rules::{parse_position, BoardState, Color, Position};
+use crate::think_log::{PlyThinking, ThinkLogStorage};
+use crate::timeouts::TimeoutConfig;
+use crate::usi_info::{self, UsiInfo};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::events::{
+    SharedEventSink, ENGINE_VS_ENGINE_ANALYSIS, ENGINE_VS_ENGINE_MOVE, ENGINE_VS_ENGINE_SUMMARY, ENGINE_VS_ENGINE_UPDATE,
+};
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+fn default_move_delay_ms() -> u64 {
+    500
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineVsEngineState {
+    pub match_id: String,
+    pub move_number: usize,
+    pub current_player: String, // "black" or "white"
+    pub position_sfen: String,
+    pub last_move: Option<String>,
+    pub move_history: Vec<String>,
+    /// Wall-clock time taken for each move in move_history
+    pub move_times_ms: Vec<u64>,
+    pub game_over: bool,
+    pub winner: Option<String>,
+    pub game_result: Option<String>,
+    /// `game_result` as a message code, for showing it in the user's language
+    #[serde(default)]
+    pub result_message: Option<Message>,
+    pub termination: Option<Termination>,
+    /// Parsed board and captured pieces for the current position
+    pub board: Option<BoardState>,
+}
+
+impl EngineVsEngineState {
+    fn set_result(&mut self, message: Message) {
+        self.game_result = Some(message.text.clone());
+        self.result_message = Some(message);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineVsEngineConfig {
+    pub engine1_id: String,
+    pub engine1_path: String,
+    pub engine1_name: String,
+    pub engine2_id: String,
+    pub engine2_path: String,
+    pub engine2_name: String,
+    pub initial_sfen: Option<String>,
+    /// Handicap starting position, used when no explicit initial_sfen is given
+    #[serde(default)]
+    pub handicap: Option<Handicap>,
+    pub time_per_move_ms: u64,
+    /// How each search is limited; the default clock mode uses time_per_move_ms
+    #[serde(default)]
+    pub search_limit: SearchLimit,
+    pub max_moves: usize,
+    /// Score-based resign/draw adjudication (disabled by default)
+    #[serde(default)]
+    pub adjudication: AdjudicationConfig,
+    /// Pin each engine to its own cores so concurrent games don't disturb each other
+    #[serde(default)]
+    pub cpu_affinity: Option<CpuAffinity>,
+    /// OS scheduling priority of each engine process
+    #[serde(default)]
+    pub engine1_priority: Option<ProcessPriority>,
+    #[serde(default)]
+    pub engine2_priority: Option<ProcessPriority>,
+    /// SHA-256 of each engine binary when the match started
+    #[serde(default)]
+    pub engine1_sha256: Option<String>,
+    #[serde(default)]
+    pub engine2_sha256: Option<String>,
+    /// Options for this match only, applied on top of each engine's saved options
+    #[serde(default)]
+    pub engine1_options: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub engine2_options: Option<HashMap<String, String>>,
+    /// Registered eval file each side loads for this match, instead of its configured one
+    #[serde(default)]
+    pub engine1_eval_file_id: Option<String>,
+    #[serde(default)]
+    pub engine2_eval_file_id: Option<String>,
+    /// Random seed sent to engines with a seed option and used to pick the opening.
+    /// Filled in with a fresh value when the match starts so every record can be replayed.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Candidate starting positions ("sfen ... moves ..." or "startpos moves ...");
+    /// one is picked from the seed when no explicit initial_sfen is given, and its moves
+    /// are played as book moves before opening_moves
+    #[serde(default)]
+    pub openings: Vec<String>,
+    /// Pause after each move so spectators can follow the game; 0 plays at full speed.
+    /// Can be changed while the match runs through `move_delay_handle`.
+    #[serde(default = "default_move_delay_ms")]
+    pub move_delay_ms: u64,
+    /// Time odds: each side's replacement for the time of the search limit (time_per_move_ms
+    /// in clock mode, movetime_ms in movetime mode). Node and depth limits take no odds.
+    #[serde(default)]
+    pub engine1_time_ms: Option<u64>,
+    #[serde(default)]
+    pub engine2_time_ms: Option<u64>,
+    /// USI moves played from the initial position before the engines take over, for
+    /// themed openings. Recorded as book moves.
+    #[serde(default)]
+    pub opening_moves: Vec<String>,
+    /// Archive every `info` line of both engines with the game record (see the think_log module)
+    #[serde(default)]
+    pub record_thinking: bool,
+}
+
+impl EngineVsEngineConfig {
+    pub fn validate_time_odds(&self) -> Result<()> {
+        if self.engine1_time_ms.is_none() && self.engine2_time_ms.is_none() {
+            return Ok(());
+        }
+        if matches!(self.search_limit, SearchLimit::Nodes { .. } | SearchLimit::Depth { .. }) {
+            return Err(anyhow!("Time odds need a clock or movetime limit"));
+        }
+        if self.engine1_time_ms == Some(0) || self.engine2_time_ms == Some(0) {
+            return Err(anyhow!("Time odds must be at least 1 ms"));
+        }
+        Ok(())
+    }
+
+    /// Search time of each side, Black (engine 1) first, with any time odds applied
+    pub fn side_times_ms(&self) -> [u64; 2] {
+        let base = match self.search_limit {
+            SearchLimit::Movetime { movetime_ms } => movetime_ms,
+            _ => self.time_per_move_ms,
+        };
+        [self.engine1_time_ms.unwrap_or(base), self.engine2_time_ms.unwrap_or(base)]
+    }
+
+    /// The `go` command and forfeit timeout for one side, given that side's engine timeouts.
+    /// In clock mode each engine sees its own time and its opponent's as btime/wtime.
+    pub fn search_for(&self, black: bool, timeouts: &TimeoutConfig) -> (String, Duration) {
+        let [black_ms, white_ms] = self.side_times_ms();
+        let own_ms = if black { black_ms } else { white_ms };
+        match self.search_limit {
+            SearchLimit::Clock => (
+                format!("go btime {} wtime {}", black_ms, white_ms),
+                timeouts.move_timeout(own_ms),
+            ),
+            SearchLimit::Movetime { .. } => (format!("go movetime {}", own_ms), timeouts.move_timeout(own_ms)),
+            limit => (limit.go_command(own_ms), limit.move_timeout(own_ms)),
+        }
+    }
+}
+
+/// Limit sent with each `go` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SearchLimit {
+    /// `go btime T wtime T` with T = time_per_move_ms
+    #[default]
+    Clock,
+    /// `go nodes N`
+    Nodes { nodes: u64 },
+    /// `go depth D`
+    Depth { depth: u32 },
+    /// `go movetime T`
+    Movetime { movetime_ms: u64 },
+}
+
+/// Forfeit timeout for node- and depth-limited searches, which have no time bound of their own
+const UNBOUNDED_MOVE_TIMEOUT: Duration = Duration::from_secs(300);
+
+impl SearchLimit {
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            SearchLimit::Nodes { nodes: 0 } => Err(anyhow!("nodes must be at least 1")),
+            SearchLimit::Depth { depth: 0 } => Err(anyhow!("depth must be at least 1")),
+            SearchLimit::Movetime { movetime_ms: 0 } => Err(anyhow!("movetime must be at least 1 ms")),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn go_command(&self, time_per_move_ms: u64) -> String {
+        match *self {
+            SearchLimit::Clock => format!("go btime {} wtime {}", time_per_move_ms, time_per_move_ms),
+            SearchLimit::Nodes { nodes } => format!("go nodes {}", nodes),
+            SearchLimit::Depth { depth } => format!("go depth {}", depth),
+            SearchLimit::Movetime { movetime_ms } => format!("go movetime {}", movetime_ms),
+        }
+    }
+
+    /// How long an engine may think before it forfeits the game
+    pub fn move_timeout(&self, time_per_move_ms: u64) -> Duration {
+        match *self {
+            SearchLimit::Clock => move_timeout(time_per_move_ms),
+            SearchLimit::Movetime { movetime_ms } => move_timeout(movetime_ms),
+            SearchLimit::Nodes { .. } | SearchLimit::Depth { .. } => UNBOUNDED_MOVE_TIMEOUT,
+        }
+    }
+}
+
+/// A live `info` line from one side, emitted as `engine-vs-engine-analysis`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineVsEngineAnalysis {
+    pub match_id: String,
+    pub side: String, // "black" or "white"
+    pub engine_name: String,
+    /// Move number being searched
+    pub move_number: usize,
+    pub info: UsiInfo,
+}
+
+/// A move as it is played, emitted as `engine-vs-engine-move`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct EngineVsEngineMove {
+    #[serde(rename = "move")]
+    pub usi_move: String,
+    pub engine: String,
+    pub move_number: usize,
+    pub elapsed_ms: u64,
+}
+
+/// An engine's reply to `go`
+#[derive(Debug, Clone)]
+pub struct EngineMove {
+    pub best_move: String,
+    /// Last main-line info that carried a score
+    pub info: Option<UsiInfo>,
+}
+
+/// Per-engine move time statistics for a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct MoveTimingStats {
+    pub moves: usize,
+    pub min_ms: u64,
+    pub avg_ms: u64,
+    pub max_ms: u64,
+    pub total_ms: u64,
+    /// How far the slowest move was from the forfeit timeout (negative if it was exceeded)
+    pub time_forfeit_margin_ms: i64,
+}
+
+impl MoveTimingStats {
+    pub fn from_times(times: &[u64], forfeit_timeout: Duration) -> Option<Self> {
+        let min_ms = *times.iter().min()?;
+        let max_ms = *times.iter().max()?;
+        let total_ms: u64 = times.iter().sum();
+        let forfeit_ms = forfeit_timeout.as_millis() as i64;
+
+        Some(Self {
+            moves: times.len(),
+            min_ms,
+            avg_ms: total_ms / times.len() as u64,
+            max_ms,
+            total_ms,
+            time_forfeit_margin_ms: forfeit_ms - max_ms as i64,
+        })
+    }
+}
+
+/// Final result of a match, emitted as `engine-vs-engine-summary`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct MatchSummary {
+    /// Also the ID of the saved game record
+    pub match_id: String,
+    pub engine1_name: String,
+    pub engine2_name: String,
+    pub winner: Option<String>,
+    pub game_result: Option<String>,
+    #[serde(default)]
+    pub result_message: Option<Message>,
+    pub total_moves: usize,
+    pub engine1_timing: Option<MoveTimingStats>,
+    pub engine2_timing: Option<MoveTimingStats>,
+}
+
+/// Option names engines commonly use for their random seed
+const SEED_OPTION_NAMES: &[&str] = &["Seed", "RandomSeed", "Random_Seed", "USI_Seed"];
+
+/// SplitMix64, used to derive reproducible choices from the match seed
+pub fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Pick one of the configured openings from the seed, returning its start position and the
+/// USI moves played from it so the record keeps the whole opening
+fn pick_opening(openings: &[String], seed: u64) -> Option<(String, Vec<String>)> {
+    if openings.is_empty() {
+        return None;
+    }
+    let spec = &openings[(splitmix64(seed) % openings.len() as u64) as usize];
+    let resolved = parse_position(spec).and_then(|(start, moves)| {
+        let mut position = start.clone();
+        for mv in &moves {
+            position.apply_move(mv)?;
+        }
+        Ok((start.to_sfen(), moves.into_iter().map(|mv| mv.to_usi()).collect()))
+    });
+    match resolved {
+        Ok(opening) => Some(opening),
+        Err(e) => {
+            log::warn!("Ignoring invalid opening '{}': {}", spec, e);
+            None
+        }
+    }
+}
+
+/// Fit the match seed into a spin option's range
+fn seed_option_value(option: &EngineOption, seed: u64) -> String {
+    let min = option.min.as_deref().and_then(|v| v.parse::<i64>().ok());
+    let max = option.max.as_deref().and_then(|v| v.parse::<i64>().ok());
+    match (min, max) {
+        (Some(min), Some(max)) if max >= min => {
+            // Only the full i64 range has more values than u64 can count; every seed fits it
+            let offset = match max.abs_diff(min).checked_add(1) {
+                Some(range) => seed % range,
+                None => seed,
+            };
+            min.wrapping_add(offset as i64).to_string()
+        }
+        _ => seed.to_string(),
+    }
+}
+
+/// Nodes searched by engine 1 (Black) and engine 2 (White) over a game whose first move was
+/// played by `first_mover`
+fn nodes_by_engine(move_infos: &[Option<UsiInfo>], first_mover: Color) -> [u64; 2] {
+    let engine1_first_ply = if first_mover == Color::Black { 0 } else { 1 };
+    let nodes = |first_ply: usize| -> u64 {
+        move_infos
+            .iter()
+            .skip(first_ply)
+            .step_by(2)
+            .filter_map(|info| info.as_ref().and_then(|info| info.nodes))
+            .sum()
+    };
+    [nodes(engine1_first_ply), nodes(1 - engine1_first_ply)]
+}
+
+/// Names shown for the two seats in events and records. Explicit labels win; when both seats
+/// would get the same name, as in self-play of one config, the options that differ between
+/// them are appended, e.g. "Engine [Threads=4]" and "Engine [Threads=8]".
+pub fn seat_labels(
+    names: (&str, &str),
+    options: (&HashMap<String, String>, &HashMap<String, String>),
+    labels: (Option<String>, Option<String>),
+) -> (String, String) {
+    let label = |explicit: Option<String>| explicit.filter(|l| !l.trim().is_empty());
+    let (label1, label2) = (label(labels.0), label(labels.1));
+    if names.0 != names.1 || (label1.is_some() && label2.is_some()) {
+        return (
+            label1.unwrap_or_else(|| names.0.to_string()),
+            label2.unwrap_or_else(|| names.1.to_string()),
+        );
+    }
+
+    let mut keys: Vec<&String> = options.0.keys().chain(options.1.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    let differing: Vec<&String> = keys.into_iter().filter(|key| options.0.get(*key) != options.1.get(*key)).collect();
+    let describe = |seat: &HashMap<String, String>, fallback: &str| {
+        if differing.is_empty() {
+            return format!("{} ({})", names.0, fallback);
+        }
+        let parts: Vec<String> = differing
+            .iter()
+            .map(|key| format!("{}={}", key, seat.get(*key).map(String::as_str).unwrap_or("default")))
+            .collect();
+        format!("{} [{}]", names.0, parts.join(", "))
+    };
+    (
+        label1.unwrap_or_else(|| describe(options.0, "1")),
+        label2.unwrap_or_else(|| describe(options.1, "2")),
+    )
+}
+
+/// How long an engine may think before it forfeits the game, with the app-wide bestmove slack
+pub fn move_timeout(time_ms: u64) -> Duration {
+    TimeoutConfig::global().move_timeout(time_ms)
+}
+
+/// Spawn `engine` in its own directory with its configured priority and initialize it with
+/// its saved options and eval file, for one-off jobs that drive the engine directly
+pub async fn spawn_initialized_engine(
+    engine: &crate::engine_storage::EngineConfig,
+    engine_storage: &tokio::sync::RwLock<crate::engine_storage::EngineStorage>,
+) -> Result<(Child, tokio::process::ChildStdin, tokio::process::ChildStdout)> {
+    let dir = std::path::Path::new(&engine.path)
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid engine path"))?;
+    let mut command = Command::new(&engine.path);
+    command
+        .current_dir(dir)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true);
+    engine_process::prepare(&mut command);
+    process_priority::apply_before_spawn(&mut command, engine.priority);
+    let mut child = command.spawn().map_err(|e| anyhow!("Failed to spawn engine: {}", e))?;
+    engine_process::attach(&child);
+    if let Err(e) = process_priority::apply_after_spawn(&child, engine.priority) {
+        log::warn!("Engine priority: {}", e);
+    }
+    let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("Failed to get engine stdin"))?;
+    let mut stdout = child.stdout.take().ok_or_else(|| anyhow!("Failed to get engine stdout"))?;
+    EngineVsEngineManager::initialize_engine_with_options(&mut stdin, &mut stdout, &engine.id, engine_storage, None, None, None)
+        .await?;
+    Ok((child, stdin, stdout))
+}
+
+pub struct EngineVsEngineManager {
+    events: SharedEventSink,
+    config: EngineVsEngineConfig,
+    state: Arc<Mutex<EngineVsEngineState>>,
+    engine1: Option<Child>,
+    engine2: Option<Child>,
+    engine_storage: Arc<tokio::sync::RwLock<crate::engine_storage::EngineStorage>>,
+    /// Save the state after every move so the match can be resumed after a crash
+    autosave: bool,
+    /// Current pause after each move, starting from the config's move_delay_ms
+    move_delay_ms: Arc<AtomicU64>,
+}
+
+impl EngineVsEngineManager {
+    pub fn new(events: SharedEventSink, mut config: EngineVsEngineConfig, engine_storage: Arc<tokio::sync::RwLock<crate::engine_storage::EngineStorage>>) -> Self {
+        let seed = *config.seed.get_or_insert_with(|| uuid::Uuid::new_v4().as_u64_pair().0);
+        let mut initial_sfen = config.initial_sfen.clone().filter(|sfen| !sfen.trim().is_empty());
+        if initial_sfen.is_none() {
+            // The picked opening's moves are played as book moves ahead of the configured ones.
+            // The config keeps the resolved opening instead of the candidates, so the saved
+            // config replays the same game without picking and prepending it again.
+            if let Some((start_sfen, moves)) = pick_opening(&config.openings, seed) {
+                config.opening_moves.splice(0..0, moves);
+                config.initial_sfen = Some(start_sfen.clone());
+                config.openings.clear();
+                initial_sfen = Some(start_sfen);
+            }
+        }
+        let initial_sfen = Handicap::resolve_initial_sfen(initial_sfen, config.handicap);
+
+        // Handicap and custom positions may start with White to move
+        let position = Position::from_sfen(&initial_sfen).ok();
+        let current_player = position
+            .as_ref()
+            .map(|position| position.side_to_move().as_str().to_string())
+            .unwrap_or_else(|| "black".to_string());
+
+        let state = EngineVsEngineState {
+            match_id: uuid::Uuid::new_v4().to_string(),
+            move_number: 1,
+            current_player,
+            position_sfen: initial_sfen,
+            last_move: None,
+            move_history: Vec::new(),
+            move_times_ms: Vec::new(),
+            game_over: false,
+            winner: None,
+            game_result: None,
+            result_message: None,
+            termination: None,
+            board: position.map(|position| position.board_state()),
+        };
+
+        Self {
+            events,
+            move_delay_ms: Arc::new(AtomicU64::new(config.move_delay_ms)),
+            config,
+            state: Arc::new(Mutex::new(state)),
+            engine1: None,
+            engine2: None,
+            engine_storage,
+            autosave: false,
+        }
+    }
+
+    /// Continue an interrupted match from its saved state. The engines are started afresh
+    /// and receive the moves played so far with each `position` command.
+    pub fn resume(
+        events: SharedEventSink,
+        config: EngineVsEngineConfig,
+        state: EngineVsEngineState,
+        engine_storage: Arc<tokio::sync::RwLock<crate::engine_storage::EngineStorage>>,
+    ) -> Result<Self> {
+        if state.game_over {
+            return Err(anyhow!("Match {} has already ended", state.match_id));
+        }
+        if state.move_times_ms.len() != state.move_history.len() {
+            return Err(anyhow!("Saved match {} has inconsistent move times", state.match_id));
+        }
+        Ok(Self {
+            events,
+            move_delay_ms: Arc::new(AtomicU64::new(config.move_delay_ms)),
+            config,
+            state: Arc::new(Mutex::new(state)),
+            engine1: None,
+            engine2: None,
+            engine_storage,
+            autosave: false,
+        })
+    }
+
+    /// Keep an auto-save of the match (see the autosave module) until it ends
+    pub fn enable_autosave(&mut self) {
+        self.autosave = true;
+    }
+
+    async fn save_progress(&self) {
+        if !self.autosave {
+            return;
+        }
+        // A resumed match keeps the pace it was last set to
+        let config = EngineVsEngineConfig {
+            move_delay_ms: self.move_delay_ms.load(Ordering::Relaxed),
+            ..self.config.clone()
+        };
+        let entry = AutosaveEntry::match_state(&config, &*self.state.lock().await);
+        if let Err(e) = AutosaveStorage::save(&entry).await {
+            log::warn!("Failed to auto-save match {}: {}", entry.id, e);
+        }
+    }
+
+    pub async fn match_id(&self) -> String {
+        self.state.lock().await.match_id.clone()
+    }
+
+    pub fn config(&self) -> &EngineVsEngineConfig {
+        &self.config
+    }
+
+    /// The pause after each move, for changing it while `run_match` owns the manager
+    pub fn move_delay_handle(&self) -> Arc<AtomicU64> {
+        self.move_delay_ms.clone()
+    }
+
+    /// The live match state, for observers while `run_match` owns the manager
+    pub fn state_handle(&self) -> Arc<Mutex<EngineVsEngineState>> {
+        self.state.clone()
+    }
+
+    /// Timeouts of engine 1 and engine 2, with their own overrides applied
+    async fn side_timeouts(&self) -> [TimeoutConfig; 2] {
+        let storage = self.engine_storage.read().await;
+        [
+            TimeoutConfig::for_engine(storage.get_engine(&self.config.engine1_id)),
+            TimeoutConfig::for_engine(storage.get_engine(&self.config.engine2_id)),
+        ]
+    }
+
+    /// Spawn both engines
+    async fn spawn_engines(&mut self) -> Result<()> {
+        log::info!("Spawning engines for engine-vs-engine match");
+        log::info!("Engine 1 path: {}", self.config.engine1_path);
+        log::info!("Engine 2 path: {}", self.config.engine2_path);
+
+        // Spawn engine 1
+        // Set working directory to the engine's directory so it can find its files
+        let engine1_dir = std::path::Path::new(&self.config.engine1_path)
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid engine 1 path"))?;
+        
+        let affinity = self.config.cpu_affinity.clone().unwrap_or_default();
+
+        let mut command = Command::new(&self.config.engine1_path);
+        command
+            .current_dir(engine1_dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        cpu_affinity::apply_before_spawn(&mut command, &affinity.engine1_cores);
+        engine_process::prepare(&mut command);
+        process_priority::apply_before_spawn(&mut command, self.config.engine1_priority);
+        let engine1 = command
+            .spawn()
+            .map_err(|e| anyhow!("Failed to spawn engine 1: {}", e))?;
+        engine_process::attach(&engine1);
+        cpu_affinity::apply_after_spawn(&engine1, &affinity.engine1_cores)?;
+        if let Err(e) = process_priority::apply_after_spawn(&engine1, self.config.engine1_priority) {
+            log::warn!("Engine 1 priority: {}", e);
+        }
+
+        log::info!("Engine 1 spawned successfully with working dir: {:?}", engine1_dir);
+        self.engine1 = Some(engine1);
+
+        // Spawn engine 2
+        let engine2_dir = std::path::Path::new(&self.config.engine2_path)
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid engine 2 path"))?;
+            
+        let mut command = Command::new(&self.config.engine2_path);
+        command
+            .current_dir(engine2_dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        cpu_affinity::apply_before_spawn(&mut command, &affinity.engine2_cores);
+        engine_process::prepare(&mut command);
+        process_priority::apply_before_spawn(&mut command, self.config.engine2_priority);
+        let engine2 = command
+            .spawn()
+            .map_err(|e| anyhow!("Failed to spawn engine 2: {}", e))?;
+        engine_process::attach(&engine2);
+        cpu_affinity::apply_after_spawn(&engine2, &affinity.engine2_cores)?;
+        if let Err(e) = process_priority::apply_after_spawn(&engine2, self.config.engine2_priority) {
+            log::warn!("Engine 2 priority: {}", e);
+        }
+
+        log::info!("Engine 2 spawned successfully");
+        self.engine2 = Some(engine2);
+
+        Ok(())
+    }
+
+    /// Initialize an engine with USI protocol and send saved options,
+    /// with `overrides` replacing saved values of the same name
+    pub async fn initialize_engine_with_options(
+        stdin: &mut tokio::process::ChildStdin,
+        stdout: &mut tokio::process::ChildStdout,
+        engine_id: &str,
+        engine_storage: &tokio::sync::RwLock<crate::engine_storage::EngineStorage>,
+        overrides: Option<&HashMap<String, String>>,
+        eval_file_id: Option<&str>,
+        seed: Option<u64>,
+    ) -> Result<()> {
+        use tokio::io::AsyncBufReadExt;
+        
+        log::info!("Initializing engine with USI protocol");
+        let timeouts = TimeoutConfig::for_engine(engine_storage.read().await.get_engine(engine_id));
+        let mut reader = BufReader::new(stdout);
+        let mut line = Vec::new();
+        
+        // Send usi command
+        log::info!("Sending 'usi' command");
+        stdin.write_all(b"usi\n").await?;
+        stdin.flush().await?;
+        log::info!("'usi' command sent, waiting for response...");
+
+        // Wait for usiok, remembering the advertised options
+        let mut found_usiok = false;
+        let mut engine_options: Vec<EngineOption> = Vec::new();
+        let start = tokio::time::Instant::now();
+        while start.elapsed() < Duration::from_millis(timeouts.usiok_ms) {
+            line.clear();
+            
+            // Use a short timeout for each read to allow checking elapsed time
+            match timeout(Duration::from_millis(100), reader.read_until(b'\n', &mut line)).await {
+                Ok(Ok(0)) => return Err(anyhow!("Engine closed connection")),
+                Ok(Ok(_)) => {
+                    let (text, _) = engine_encoding::decode_line(&line);
+                    let trimmed = text.trim();
+                    log::debug!("Engine init response: {}", trimmed);
+                    if trimmed == "usiok" {
+                        found_usiok = true;
+                        break;
+                    }
+                    if let Some(option) = EngineOption::parse(trimmed) {
+                        engine_options.push(option);
+                    }
+                }
+                Ok(Err(e)) => return Err(anyhow!("Failed to read from engine: {}", e)),
+                Err(_) => continue, // Timeout, try again
+            }
+        }
+        
+        if !found_usiok {
+            log::error!("Timeout waiting for usiok - no response from engine");
+            return Err(anyhow!("Timeout waiting for usiok"));
+        }
+
+        log::info!("Received usiok, sending saved options");
+
+        // Saved options, then the engine's eval file, then this match's overrides
+        let storage = engine_storage.read().await;
+        let mut options = storage.get_engine_options(engine_id).cloned().unwrap_or_default();
+        match storage.eval_options_for(engine_id, eval_file_id, Some(&engine_options)) {
+            Ok(eval_options) => options.extend(eval_options),
+            Err(e) => log::warn!("Not selecting eval file for engine {}: {}", engine_id, e),
+        }
+        if let Some(overrides) = overrides {
+            log::info!("Applying {} match option overrides to engine: {}", overrides.len(), engine_id);
+            options.extend(overrides.iter().map(|(name, value)| (name.clone(), value.clone())));
+        }
+        if !options.is_empty() {
+            log::info!("Sending {} options to engine: {}", options.len(), engine_id);
+            for (option_name, option_value) in &options {
+                let option_command = format!("setoption name {} value {}\n", option_name, option_value);
+                log::debug!("Sending option command: {}", option_command.trim());
+                if let Err(e) = stdin.write_all(option_command.as_bytes()).await {
+                    log::warn!("Failed to send option '{}' to engine {}: {}", option_name, engine_id, e);
+                    // Continue with other options even if one fails
+                }
+            }
+            stdin.flush().await?;
+        }
+        drop(storage);
+
+        // The match seed overrides any saved seed option
+        if let Some(seed) = seed {
+            let seed_option = engine_options
+                .iter()
+                .find(|option| SEED_OPTION_NAMES.iter().any(|name| option.name.eq_ignore_ascii_case(name)));
+            if let Some(option) = seed_option {
+                let option_command = format!("setoption name {} value {}\n", option.name, seed_option_value(option, seed));
+                log::info!("Sending seed option: {}", option_command.trim());
+                stdin.write_all(option_command.as_bytes()).await?;
+                stdin.flush().await?;
+            }
+        }
+
+        log::info!("Sending 'isready' command");
+        // Send isready
+        stdin.write_all(b"isready\n").await?;
+        stdin.flush().await?;
+        log::info!("'isready' command sent, waiting for response...");
+
+        // Wait for readyok
+        let mut found_readyok = false;
+        let start = tokio::time::Instant::now();
+        while start.elapsed() < Duration::from_millis(timeouts.readyok_ms) {
+            line.clear();
+            
+            match timeout(Duration::from_millis(100), reader.read_until(b'\n', &mut line)).await {
+                Ok(Ok(0)) => return Err(anyhow!("Engine closed connection")),
+                Ok(Ok(_)) => {
+                    let (text, _) = engine_encoding::decode_line(&line);
+                    let trimmed = text.trim();
+                    log::debug!("Engine ready response: {}", trimmed);
+                    if trimmed == "readyok" {
+                        found_readyok = true;
+                        break;
+                    }
+                }
+                Ok(Err(e)) => return Err(anyhow!("Failed to read from engine: {}", e)),
+                Err(_) => continue, // Timeout, try again
+            }
+        }
+        
+        if !found_readyok {
+            log::error!("Timeout waiting for readyok - no response from engine");
+            return Err(anyhow!("Timeout waiting for readyok"));
+        }
+
+        log::info!("Received readyok, engine initialization complete");
+        Ok(())
+    }
+
+    /// Request a move from an engine, passing each `info` line to `on_info` as it arrives
+    pub async fn request_move(
+        stdin: &mut tokio::process::ChildStdin,
+        stdout: &mut tokio::process::ChildStdout,
+        position_sfen: &str,
+        moves: &[String],
+        go_cmd: &str,
+        timeout_duration: Duration,
+        mut on_info: impl FnMut(&UsiInfo, &str),
+    ) -> Result<EngineMove> {
+        use tokio::io::AsyncBufReadExt;
+        
+        // Build position command
+        let initial_sfen = position_sfen.split(" moves").next().unwrap_or(position_sfen);
+        let pos_cmd = format!("{}\n", position_command(initial_sfen, moves));
+
+        stdin.write_all(pos_cmd.as_bytes()).await?;
+        stdin.flush().await?;
+
+        // Send go command
+        stdin.write_all(format!("{}\n", go_cmd).as_bytes()).await?;
+        stdin.flush().await?;
+
+        // Wait for bestmove
+        let mut reader = BufReader::new(stdout);
+        let mut line = Vec::new();
+        let mut last_info: Option<UsiInfo> = None;
+        let start = tokio::time::Instant::now();
+        
+        while start.elapsed() < timeout_duration {
+            line.clear();
+            
+            match timeout(Duration::from_millis(100), reader.read_until(b'\n', &mut line)).await {
+                Ok(Ok(0)) => return Err(anyhow!("Engine closed connection")),
+                Ok(Ok(_)) => {
+                    let (text, _) = engine_encoding::decode_line(&line);
+                    let trimmed = text.trim();
+                    log::debug!("Engine move response: {}", trimmed);
+                    if let Some(info) = usi_info::parse_info(trimmed) {
+                        on_info(&info, trimmed);
+                        if info.score.is_some() && info.multipv.unwrap_or(1) == 1 {
+                            last_info = Some(info);
+                        }
+                    } else if trimmed.starts_with("bestmove ") {
+                        let parts: Vec<&str> = trimmed.split_whitespace().collect();
+                        if parts.len() >= 2 {
+                            return Ok(EngineMove {
+                                best_move: parts[1].to_string(),
+                                info: last_info,
+                            });
+                        }
+                    }
+                }
+                Ok(Err(e)) => return Err(anyhow!("Failed to read from engine: {}", e)),
+                Err(_) => continue, // Timeout, try again
+            }
+        }
+        
+        Err(anyhow!("Timeout waiting for bestmove"))
+    }
+
+    /// Play the configured opening moves on a match that has no moves yet. Runs at the start
+    /// of run_match; calling it earlier surfaces an illegal move before the match is spawned.
+    pub async fn play_opening_moves(&self) -> Result<()> {
+        let mut state = self.state.lock().await;
+        if self.config.opening_moves.is_empty() || !state.move_history.is_empty() {
+            return Ok(());
+        }
+        let mut position = Position::from_sfen(&state.position_sfen)
+            .map_err(|e| anyhow!("Invalid initial position: {}", e))?;
+        for (index, usi) in self.config.opening_moves.iter().enumerate() {
+            position
+                .apply_usi_move(usi)
+                .map_err(|e| anyhow!("Illegal opening move {} ({}): {}", index + 1, usi, e))?;
+        }
+
+        state.move_history = self.config.opening_moves.clone();
+        state.move_times_ms = vec![0; state.move_history.len()];
+        state.move_number = state.move_history.len();
+        state.last_move = state.move_history.last().cloned();
+        state.current_player = position.side_to_move().as_str().to_string();
+        state.position_sfen = format!("{} moves {}", state.position_sfen, state.move_history.join(" "));
+        state.board = Some(position.board_state());
+        Ok(())
+    }
+
+    /// Run the engine-vs-engine match, returning its final state
+    pub async fn run_match(mut self) -> Result<EngineVsEngineState> {
+        log::info!("Starting engine-vs-engine match");
+        self.play_opening_moves().await?;
+
+        // Track the position with the rules module so moves can be validated and displayed.
+        // A resumed match replays the moves it already has.
+        let (mut position, resumed_times) = {
+            let state = self.state.lock().await;
+            let initial_sfen = state.position_sfen.split(" moves").next().unwrap_or(&state.position_sfen);
+            let mut position = Position::from_sfen(initial_sfen)
+                .map_err(|e| anyhow!("Invalid initial position: {}", e))?;
+            let mut times: [Vec<u64>; 2] = [Vec::new(), Vec::new()];
+            for (ply, (usi, &elapsed_ms)) in state.move_history.iter().zip(&state.move_times_ms).enumerate() {
+                // Opening moves were not searched and would skew the timing statistics
+                if ply >= self.config.opening_moves.len() {
+                    times[if position.side_to_move() == Color::Black { 0 } else { 1 }].push(elapsed_ms);
+                }
+                position
+                    .apply_usi_move(usi)
+                    .map_err(|e| anyhow!("Invalid saved move {}: {}", usi, e))?;
+            }
+            (position, times)
+        };
+
+        // Spawn engines
+        self.spawn_engines().await?;
+
+        // Get stdin/stdout handles
+        let engine1_stdin = self.engine1.as_mut()
+            .and_then(|e| e.stdin.take())
+            .ok_or_else(|| anyhow!("Failed to get engine 1 stdin"))?;
+        let engine1_stdout = self.engine1.as_mut()
+            .and_then(|e| e.stdout.take())
+            .ok_or_else(|| anyhow!("Failed to get engine 1 stdout"))?;
+
+        let engine2_stdin = self.engine2.as_mut()
+            .and_then(|e| e.stdin.take())
+            .ok_or_else(|| anyhow!("Failed to get engine 2 stdin"))?;
+        let engine2_stdout = self.engine2.as_mut()
+            .and_then(|e| e.stdout.take())
+            .ok_or_else(|| anyhow!("Failed to get engine 2 stdout"))?;
+
+        let mut engine1_stdin = engine1_stdin;
+        let mut engine1_stdout = engine1_stdout;
+        let mut engine2_stdin = engine2_stdin;
+        let mut engine2_stdout = engine2_stdout;
+
+        // Initialize both engines with saved options
+        Self::initialize_engine_with_options(
+            &mut engine1_stdin,
+            &mut engine1_stdout,
+            &self.config.engine1_id,
+            &self.engine_storage,
+            self.config.engine1_options.as_ref(),
+            self.config.engine1_eval_file_id.as_deref(),
+            self.config.seed,
+        )
+        .await?;
+        Self::initialize_engine_with_options(
+            &mut engine2_stdin,
+            &mut engine2_stdout,
+            &self.config.engine2_id,
+            &self.engine_storage,
+            self.config.engine2_options.as_ref(),
+            self.config.engine2_eval_file_id.as_deref(),
+            self.config.seed,
+        )
+        .await?;
+
+        // Send usinewgame to both
+        engine1_stdin.write_all(b"usinewgame\n").await?;
+        engine1_stdin.flush().await?;
+        engine2_stdin.write_all(b"usinewgame\n").await?;
+        engine2_stdin.flush().await?;
+
+        // Emit initial state
+        let moves_played = {
+            let state = self.state.lock().await;
+            let _ = self.events.send(&ENGINE_VS_ENGINE_UPDATE, &*state);
+            state.move_history.len()
+        };
+        self.save_progress().await;
+
+        // Move times per engine, including the final response of a resigning engine
+        let [mut engine1_times, mut engine2_times] = resumed_times;
+        let mut adjudicator = Adjudicator::new(self.config.adjudication.clone());
+        // Final search info behind each played move, kept for the game record; moves from
+        // before a resume have none
+        let mut move_infos: Vec<Option<UsiInfo>> = vec![None; moves_played];
+        let timeouts = self.side_timeouts().await;
+        let searches = [self.config.search_for(true, &timeouts[0]), self.config.search_for(false, &timeouts[1])];
+        let record_thinking = self.config.record_thinking;
+        let mut thinking: Vec<PlyThinking> = Vec::new();
+
+        // Main game loop
+        for move_num in moves_played + 1..=self.config.max_moves {
+            let state_guard = self.state.lock().await;
+            if state_guard.game_over {
+                break;
+            }
+            let current_sfen = state_guard.position_sfen.clone();
+            let move_history = state_guard.move_history.clone();
+            let is_black_turn = state_guard.current_player == "black";
+            let match_id = state_guard.match_id.clone();
+            drop(state_guard);
+
+            // Select engine based on turn
+            let (stdin, stdout, engine_name) = if is_black_turn {
+                (&mut engine1_stdin, &mut engine1_stdout, &self.config.engine1_name)
+            } else {
+                (&mut engine2_stdin, &mut engine2_stdout, &self.config.engine2_name)
+            };
+            let (go_cmd, move_timeout) = &searches[if is_black_turn { 0 } else { 1 }];
+
+            log::info!("Move {}: {} to move", move_num, if is_black_turn { "Black" } else { "White" });
+
+            // Request move from engine, streaming its thinking to spectators
+            let events = &self.events;
+            let side = if is_black_turn { "black" } else { "white" };
+            let mut think_lines = Vec::new();
+            let on_info = |info: &UsiInfo, line: &str| {
+                if record_thinking {
+                    think_lines.push(line.to_string());
+                }
+                let _ = events.send(&ENGINE_VS_ENGINE_ANALYSIS, &EngineVsEngineAnalysis {
+                    match_id: match_id.clone(),
+                    side: side.to_string(),
+                    engine_name: engine_name.clone(),
+                    move_number: move_num,
+                    info: info.clone(),
+                });
+            };
+            let move_start = tokio::time::Instant::now();
+            let search = Self::request_move(
+                stdin,
+                stdout,
+                &current_sfen,
+                &move_history,
+                go_cmd,
+                *move_timeout,
+                on_info,
+            ).await;
+            if record_thinking {
+                thinking.push(PlyThinking {
+                    ply: move_num,
+                    side: side.to_string(),
+                    engine_name: engine_name.clone(),
+                    lines: think_lines,
+                });
+            }
+            let engine_move = match search {
+                Ok(engine_move) => engine_move,
+                Err(e) => {
+                    log::error!("Error getting move from {}: {}", engine_name, e);
+                    // Engine error - opponent wins
+                    let mut state = self.state.lock().await;
+                    state.game_over = true;
+                    state.winner = Some(if is_black_turn { "white".to_string() } else { "black".to_string() });
+                    state.set_result(message!(
+                        "engine_failed_to_respond",
+                        "{engine} failed to respond",
+                        engine = engine_name
+                    ));
+                    state.termination = Some(Termination::EngineFailure);
+                    let _ = self.events.send(&ENGINE_VS_ENGINE_UPDATE, &*state);
+                    break;
+                }
+            };
+            let elapsed_ms = move_start.elapsed().as_millis() as u64;
+            let best_move = engine_move.best_move;
+            if is_black_turn {
+                engine1_times.push(elapsed_ms);
+            } else {
+                engine2_times.push(elapsed_ms);
+            }
+
+            // Check for resignation
+            if best_move == "resign" {
+                let mut state = self.state.lock().await;
+                state.game_over = true;
+                state.winner = Some(if is_black_turn { "white".to_string() } else { "black".to_string() });
+                state.set_result(message!("engine_resigned", "{engine} resigned", engine = engine_name));
+                state.termination = Some(Termination::Resignation);
+                let _ = self.events.send(&ENGINE_VS_ENGINE_UPDATE, &*state);
+                log::info!("Game over: {} resigned", engine_name);
+                break;
+            }
+
+            // Entering-king declaration: the declarer wins if the 27-point rule is met, otherwise loses
+            if best_move == "win" {
+                let mut state = self.state.lock().await;
+                state.game_over = true;
+                if position.can_declare_win() {
+                    state.winner = Some(if is_black_turn { "black".to_string() } else { "white".to_string() });
+                    state.set_result(message!(
+                        "entering_king_win",
+                        "{engine} declared an entering-king win",
+                        engine = engine_name
+                    ));
+                    state.termination = Some(Termination::EnteringKing);
+                } else {
+                    state.winner = Some(if is_black_turn { "white".to_string() } else { "black".to_string() });
+                    state.set_result(message!(
+                        "invalid_entering_king_declaration",
+                        "{engine} made an invalid entering-king declaration",
+                        engine = engine_name
+                    ));
+                    state.termination = Some(Termination::IllegalMove);
+                }
+                let _ = self.events.send(&ENGINE_VS_ENGINE_UPDATE, &*state);
+                log::info!("Game over: {}", state.game_result.as_deref().unwrap_or_default());
+                break;
+            }
+
+            // Validate the move against the rules before accepting it
+            if let Err(e) = position.apply_usi_move(&best_move) {
+                let mut state = self.state.lock().await;
+                state.game_over = true;
+                state.winner = Some(if is_black_turn { "white".to_string() } else { "black".to_string() });
+                state.set_result(message!(
+                    "illegal_move",
+                    "{engine} played an illegal move: {usi_move}",
+                    engine = engine_name,
+                    usi_move = best_move
+                ));
+                state.termination = Some(Termination::IllegalMove);
+                let _ = self.events.send(&ENGINE_VS_ENGINE_UPDATE, &*state);
+                log::warn!("Game over: {} played an illegal move ({})", engine_name, e);
+                break;
+            }
+
+            // Update state with new move
+            {
+                let mut state = self.state.lock().await;
+                state.move_history.push(best_move.clone());
+                state.move_times_ms.push(elapsed_ms);
+                move_infos.push(engine_move.info.clone());
+                state.last_move = Some(best_move.clone());
+                state.current_player = if is_black_turn { "white".to_string() } else { "black".to_string() };
+                state.move_number = move_num;
+                
+                // Update position SFEN to include all moves played
+                let initial_sfen = current_sfen.split(" moves").next().unwrap_or(&current_sfen);
+                if state.move_history.is_empty() {
+                    state.position_sfen = initial_sfen.to_string();
+                } else {
+                    state.position_sfen = format!("{} moves {}", initial_sfen, state.move_history.join(" "));
+                }
+                state.board = Some(position.board_state());
+
+                if position.is_checkmate() {
+                    state.game_over = true;
+                    state.winner = Some(if is_black_turn { "black".to_string() } else { "white".to_string() });
+                    state.set_result(message!("checkmate", "{engine} delivered checkmate", engine = engine_name));
+                    state.termination = Some(Termination::Checkmate);
+                    log::info!("Game over: {} delivered checkmate", engine_name);
+                } else {
+                    let mover = if is_black_turn { Color::Black } else { Color::White };
+                    let score = engine_move.info.as_ref().and_then(|info| info.score);
+                    if let Some(adjudication) = adjudicator.record(mover, score) {
+                        state.game_over = true;
+                        match adjudication {
+                            Adjudication::Win(color) => {
+                                let winner_name = match color {
+                                    Color::Black => &self.config.engine1_name,
+                                    Color::White => &self.config.engine2_name,
+                                };
+                                state.winner = Some(color.as_str().to_string());
+                                state.set_result(message!(
+                                    "adjudicated_win",
+                                    "Adjudicated win for {engine}",
+                                    engine = winner_name
+                                ));
+                                state.termination = Some(Termination::Adjudication);
+                            }
+                            Adjudication::Draw => {
+                                state.winner = Some("draw".to_string());
+                                state.set_result(message!("adjudicated_draw", "Adjudicated draw"));
+                                state.termination = Some(Termination::Adjudication);
+                            }
+                        }
+                        log::info!("Game over: {}", state.game_result.as_deref().unwrap_or_default());
+                    }
+                }
+
+                // Emit update
+                let _ = self.events.send(&ENGINE_VS_ENGINE_UPDATE, &*state);
+                metrics::MATCH_MOVES.increment();
+                metrics::MATCH_MOVE_TIME.record(Duration::from_millis(elapsed_ms));
+                let _ = self.events.send(&ENGINE_VS_ENGINE_MOVE, &EngineVsEngineMove {
+                    usi_move: best_move.clone(),
+                    engine: engine_name.clone(),
+                    move_number: move_num,
+                    elapsed_ms,
+                });
+            }
+
+            log::info!("{} played: {}", engine_name, best_move);
+            self.save_progress().await;
+
+            let move_delay_ms = self.move_delay_ms.load(Ordering::Relaxed);
+            if move_delay_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(move_delay_ms)).await;
+            }
+        }
+
+        // Check if max moves reached
+        {
+            let mut state = self.state.lock().await;
+            if !state.game_over {
+                state.game_over = true;
+                state.set_result(message!("max_moves_reached", "Maximum moves reached"));
+                state.termination = Some(Termination::MaxMoves);
+                state.winner = Some("draw".to_string());
+                let _ = self.events.send(&ENGINE_VS_ENGINE_UPDATE, &*state);
+            }
+        }
+
+        self.finish_match(&engine1_times, &engine2_times, &move_infos, &thinking).await;
+
+        // Cleanup engines
+        let _ = engine1_stdin.write_all(b"quit\n").await;
+        let _ = engine1_stdin.flush().await;
+        let _ = engine2_stdin.write_all(b"quit\n").await;
+        let _ = engine2_stdin.flush().await;
+
+        if let Some(mut proc) = self.engine1.take() {
+            engine_process::kill(&mut proc).await;
+        }
+        if let Some(mut proc) = self.engine2.take() {
+            engine_process::kill(&mut proc).await;
+        }
+
+        log::info!("Engine-vs-engine match completed");
+        let final_state = self.state.lock().await.clone();
+        Ok(final_state)
+    }
+
+    /// Save the game record and emit the final summary
+    async fn finish_match(
+        &self,
+        engine1_times: &[u64],
+        engine2_times: &[u64],
+        move_infos: &[Option<UsiInfo>],
+        thinking: &[PlyThinking],
+    ) {
+        let state = self.state.lock().await.clone();
+        let timeouts = self.side_timeouts().await;
+        let (_, engine1_timeout) = self.config.search_for(true, &timeouts[0]);
+        let (_, engine2_timeout) = self.config.search_for(false, &timeouts[1]);
+
+        let summary = MatchSummary {
+            match_id: state.match_id.clone(),
+            engine1_name: self.config.engine1_name.clone(),
+            engine2_name: self.config.engine2_name.clone(),
+            winner: state.winner.clone(),
+            game_result: state.game_result.clone(),
+            result_message: state.result_message.clone(),
+            total_moves: state.move_history.len(),
+            engine1_timing: MoveTimingStats::from_times(engine1_times, engine1_timeout),
+            engine2_timing: MoveTimingStats::from_times(engine2_times, engine2_timeout),
+        };
+
+        let think_log = if self.config.record_thinking {
+            match ThinkLogStorage::save(&state.match_id, thinking).await {
+                Ok(file_name) => Some(file_name),
+                Err(e) => {
+                    log::warn!("Failed to save thinking log: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let initial_sfen = state.position_sfen.split(" moves").next().unwrap_or(&state.position_sfen);
+        let mut record = GameRecord {
+            id: state.match_id.clone(),
+            black_name: self.config.engine1_name.clone(),
+            white_name: self.config.engine2_name.clone(),
+            initial_sfen: initial_sfen.to_string(),
+            moves: state
+                .move_history
+                .iter()
+                .zip(&state.move_times_ms)
+                .zip(move_infos)
+                .enumerate()
+                .map(|(ply, ((usi, &elapsed_ms), info))| {
+                    let book = ply < self.config.opening_moves.len();
+                    RecordedMove {
+                        usi: usi.clone(),
+                        elapsed_ms: (!book).then_some(elapsed_ms),
+                        score: info.as_ref().and_then(|info| info.score),
+                        depth: info.as_ref().and_then(|info| info.depth),
+                        commentary: Vec::new(),
+                        comment: None,
+                        symbol: None,
+                        variations: Vec::new(),
+                        book,
+                    }
+                })
+                .collect(),
+            winner: state.winner.clone(),
+            result: state.game_result.clone(),
+            termination: state.termination,
+            black_timing: summary.engine1_timing.clone(),
+            white_timing: summary.engine2_timing.clone(),
+            match_config: Some(self.config.clone()),
+            think_log,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        let first_mover = Position::from_sfen(initial_sfen).map_or(Color::Black, |position| position.side_to_move());
+        self.record_usage(move_infos, first_mover).await;
+
+        if let Err(e) = commentary::annotate(&mut record) {
+            log::warn!("Failed to generate commentary: {}", e);
+        }
+        if let Err(e) = GameRecordStorage::save(&record).await {
+            log::error!("Failed to save game record: {}", e);
+        }
+        if self.autosave {
+            if let Err(e) = AutosaveStorage::remove(&state.match_id).await {
+                log::warn!("Failed to remove auto-save of match {}: {}", state.match_id, e);
+            }
+        }
+
+        metrics::MATCH_GAMES.increment();
+        let _ = self.events.send(&ENGINE_VS_ENGINE_SUMMARY, &summary);
+    }
+
+    /// Count the game for both engines along with the nodes each searched
+    async fn record_usage(&self, move_infos: &[Option<UsiInfo>], first_mover: Color) {
+        let [engine1_nodes, engine2_nodes] = nodes_by_engine(move_infos, first_mover);
+        let mut storage = self.engine_storage.write().await;
+        for (engine_id, nodes_searched) in [(&self.config.engine1_id, engine1_nodes), (&self.config.engine2_id, engine2_nodes)] {
+            let usage = EngineUsage { games_played: 1, nodes_searched, ..Default::default() };
+            if let Err(e) = storage.record_usage(engine_id, &usage) {
+                log::warn!("Failed to record usage of engine {}: {}", engine_id, e);
+            }
+        }
+        if let Err(e) = storage.save_usage().await {
+            log::error!("Failed to save engine usage: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_timing_stats() {
+        let forfeit_timeout = SearchLimit::Clock.move_timeout(1000);
+        assert!(MoveTimingStats::from_times(&[], forfeit_timeout).is_none());
+
+        let stats = MoveTimingStats::from_times(&[100, 300, 200], forfeit_timeout).unwrap();
+        assert_eq!((stats.min_ms, stats.avg_ms, stats.max_ms, stats.total_ms), (100, 200, 300, 600));
+        assert_eq!(stats.time_forfeit_margin_ms, 11_000 - 300);
+
+        assert_eq!(SearchLimit::Nodes { nodes: 100_000 }.go_command(1000), "go nodes 100000");
+        assert_eq!(SearchLimit::Movetime { movetime_ms: 500 }.move_timeout(1000), Duration::from_secs(10));
+        assert!(SearchLimit::Depth { depth: 0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_time_odds() {
+        let mut config: EngineVsEngineConfig = serde_json::from_value(serde_json::json!({
+            "engine1_id": "strong",
+            "engine1_path": "strong",
+            "engine1_name": "Strong",
+            "engine2_id": "weak",
+            "engine2_path": "weak",
+            "engine2_name": "Weak",
+            "time_per_move_ms": 10_000,
+            "max_moves": 200,
+            "engine1_time_ms": 1000,
+        }))
+        .unwrap();
+        assert_eq!(config.side_times_ms(), [1000, 10_000]);
+        let timeouts = TimeoutConfig::default();
+        assert_eq!(
+            config.search_for(true, &timeouts),
+            ("go btime 1000 wtime 10000".to_string(), Duration::from_secs(11))
+        );
+        assert_eq!(
+            config.search_for(false, &timeouts),
+            ("go btime 1000 wtime 10000".to_string(), Duration::from_secs(20))
+        );
+
+        config.search_limit = SearchLimit::Movetime { movetime_ms: 3000 };
+        assert_eq!(config.search_for(false, &timeouts).0, "go movetime 3000");
+        assert_eq!(config.search_for(true, &timeouts).0, "go movetime 1000");
+        assert!(config.validate_time_odds().is_ok());
+
+        config.search_limit = SearchLimit::Nodes { nodes: 1000 };
+        assert!(config.validate_time_odds().is_err());
+    }
+
+    #[test]
+    fn test_seeded_choices_are_reproducible() {
+        let openings = vec![
+            "startpos moves 7g7f 3c3d".to_string(),
+            "startpos moves 2g2f 8c8d".to_string(),
+            "startpos moves 7g7f 8c8d".to_string(),
+        ];
+        let first = pick_opening(&openings, 42).unwrap();
+        assert_eq!(pick_opening(&openings, 42).unwrap(), first);
+        // The opening keeps its start position and moves rather than collapsing to one SFEN
+        assert_eq!(Position::from_sfen(&first.0).unwrap().ply(), 1);
+        assert_eq!(first.1.len(), 2);
+        assert!(openings.contains(&format!("startpos moves {}", first.1.join(" "))));
+        assert!(pick_opening(&["startpos moves 7g7f 7g7f".to_string()], 42).is_none());
+
+        let option = EngineOption::parse("option name RandomSeed type spin default 0 min 0 max 99").unwrap();
+        assert_eq!(seed_option_value(&option, 1234), "34");
+        let option = EngineOption::parse("option name Seed type spin default 0 min -5 max 5").unwrap();
+        assert_eq!(seed_option_value(&option, 1234), "-3");
+        let option = EngineOption::parse(&format!("option name Seed type spin default 0 min {} max {}", i64::MIN, i64::MAX)).unwrap();
+        assert_eq!(seed_option_value(&option, 0), i64::MIN.to_string());
+        assert_eq!(seed_option_value(&option, u64::MAX), i64::MAX.to_string());
+    }
+
+    #[test]
+    fn test_nodes_by_engine_follows_the_first_mover() {
+        let info = |nodes| Some(UsiInfo { nodes: Some(nodes), ..Default::default() });
+        let infos = [info(1), info(10), None, info(100)];
+        assert_eq!(nodes_by_engine(&infos, Color::Black), [1, 110]);
+        // Handicap games start with White, so engine 2 plays the even plies
+        assert_eq!(nodes_by_engine(&infos, Color::White), [110, 1]);
+    }
+
+    #[test]
+    fn test_saved_config_replays_picked_opening() {
+        let config: EngineVsEngineConfig = serde_json::from_value(serde_json::json!({
+            "engine1_id": "a",
+            "engine1_path": "a",
+            "engine1_name": "A",
+            "engine2_id": "b",
+            "engine2_path": "b",
+            "engine2_name": "B",
+            "time_per_move_ms": 1000,
+            "max_moves": 200,
+            "openings": ["startpos moves 7g7f 3c3d"],
+            "opening_moves": ["2g2f"],
+        }))
+        .unwrap();
+        let storage = Arc::new(tokio::sync::RwLock::new(crate::engine_storage::EngineStorage::default()));
+        let events: SharedEventSink = Arc::new(crate::events::NullEventSink);
+
+        let first = EngineVsEngineManager::new(events.clone(), config, storage.clone());
+        assert_eq!(first.config().opening_moves, ["7g7f", "3c3d", "2g2f"]);
+        assert!(first.config().openings.is_empty());
+
+        let replay = EngineVsEngineManager::new(events, first.config().clone(), storage);
+        assert_eq!(replay.config().opening_moves, first.config().opening_moves);
+        assert_eq!(replay.config().initial_sfen, first.config().initial_sfen);
+    }
+
+    #[test]
+    fn test_seat_labels() {
+        let map = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        let old = map(&[("Threads", "4"), ("Hash", "256")]);
+        let new = map(&[("Threads", "8"), ("Hash", "256"), ("MultiPV", "2")]);
+
+        assert_eq!(
+            seat_labels(("Engine", "Engine"), (&old, &new), (None, None)),
+            ("Engine [MultiPV=default, Threads=4]".to_string(), "Engine [MultiPV=2, Threads=8]".to_string())
+        );
+        assert_eq!(
+            seat_labels(("Engine", "Engine"), (&old, &old), (None, Some("New net".to_string()))),
+            ("Engine (1)".to_string(), "New net".to_string())
+        );
+        assert_eq!(
+            seat_labels(("Alpha", "Beta"), (&old, &new), (None, None)),
+            ("Alpha".to_string(), "Beta".to_string())
+        );
+    }
+}
+
@@ -0,0 +1,111 @@
+//! Evaluation files (NNUE nets, KPPT tables) tracked separately from engine binaries
+//! An engine config points at one registered eval file; the matching EvalDir/EvalFile
+//! options are sent whenever the engine is started.
+
+use crate::engine_validator::{self, EngineOption};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use uuid::Uuid;
+
+/// A registered evaluation file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalFile {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub added_at: String,
+}
+
+impl EvalFile {
+    /// Describe the file at `path`, hashing its contents
+    pub async fn from_path(name: String, path: String) -> Result<Self> {
+        let metadata = tokio::fs::metadata(&path)
+            .await
+            .map_err(|e| anyhow!("Eval file not found at {}: {}", path, e))?;
+        if !metadata.is_file() {
+            return Err(anyhow!("Eval file is not a regular file: {}", path));
+        }
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            sha256: engine_validator::binary_sha256(&path).await?,
+            size_bytes: metadata.len(),
+            path,
+            added_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+}
+
+fn find_option<'a>(options: &'a [EngineOption], name: &str) -> Option<&'a EngineOption> {
+    options.iter().find(|option| option.name.eq_ignore_ascii_case(name))
+}
+
+/// The `setoption` name/value pairs that make an engine load `eval`, using the option
+/// names the engine advertises. Engines with both EvalDir and EvalFile get the directory
+/// and file name separately; engines with only one of them get the full path or directory.
+pub fn eval_options(options: &[EngineOption], eval: &EvalFile) -> Result<Vec<(String, String)>> {
+    let path = Path::new(&eval.path);
+    let dir = path.parent().map(|p| p.display().to_string()).unwrap_or_default();
+    let file_name = path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+
+    match (find_option(options, "EvalDir"), find_option(options, "EvalFile")) {
+        (Some(eval_dir), Some(eval_file)) => Ok(vec![
+            (eval_dir.name.clone(), dir),
+            (eval_file.name.clone(), file_name),
+        ]),
+        (None, Some(eval_file)) => Ok(vec![(eval_file.name.clone(), eval.path.clone())]),
+        (Some(eval_dir), None) => {
+            log::warn!(
+                "Engine only has an {} option; {} must have the file name the engine expects",
+                eval_dir.name,
+                eval.path
+            );
+            Ok(vec![(eval_dir.name.clone(), dir)])
+        }
+        (None, None) => Err(anyhow!("Engine has no EvalDir or EvalFile option")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(lines: &[&str]) -> Vec<EngineOption> {
+        lines.iter().map(|line| EngineOption::parse(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_eval_options() {
+        let eval = EvalFile {
+            id: "net".to_string(),
+            name: "Test net".to_string(),
+            path: "/nets/suisho5/nn.bin".to_string(),
+            sha256: String::new(),
+            size_bytes: 0,
+            added_at: String::new(),
+        };
+
+        let both = options(&[
+            "option name EvalDir type string default eval",
+            "option name EvalFile type string default nn.bin",
+        ]);
+        assert_eq!(
+            eval_options(&both, &eval).unwrap(),
+            vec![
+                ("EvalDir".to_string(), "/nets/suisho5".to_string()),
+                ("EvalFile".to_string(), "nn.bin".to_string())
+            ]
+        );
+
+        let file_only = options(&["option name evalfile type filename default <empty>"]);
+        assert_eq!(
+            eval_options(&file_only, &eval).unwrap(),
+            vec![("evalfile".to_string(), "/nets/suisho5/nn.bin".to_string())]
+        );
+
+        assert!(eval_options(&options(&["option name Hash type spin default 16"]), &eval).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Evaluation graph series for charting a game
+//! Engines report scores from the side to move; here every score is turned to Black's point
+//! of view and mates are pinned to the edge of the chart, so the frontend only plots numbers.
+
+use crate::analysis_queue::PositionAnalysis;
+use crate::game_record::GameRecord;
+use crate::shogi_rules::{Color, Position};
+use crate::usi_info::Score;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Scores beyond this many centipawns, and all mates, are drawn at the edge of the chart
+pub const GRAPH_CLAMP_CP: i32 = 3000;
+
+/// One score on the chart
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalPoint {
+    /// Moves played when the score applies (1 = after the first move)
+    pub move_number: usize,
+    /// Side whose engine reported the score
+    pub side: Color,
+    /// Black's advantage in centipawns, clamped to ±GRAPH_CLAMP_CP
+    pub cp: i32,
+    /// Plies to mate when the score is a mate; positive when Black mates
+    pub mate: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalGraph {
+    pub record_id: String,
+    pub clamp_cp: i32,
+    /// Scores the players' engines reported for their own moves
+    pub game: Vec<EvalPoint>,
+    /// Scores from a completed analysis of the game, one per position
+    pub analysis: Vec<EvalPoint>,
+}
+
+/// Turn a score reported by `side` into a point from Black's point of view
+pub fn eval_point(move_number: usize, side: Color, score: Score) -> EvalPoint {
+    let score = if side == Color::White { score.negate() } else { score };
+    match score {
+        Score::Cp(cp) => EvalPoint {
+            move_number,
+            side,
+            cp: cp.clamp(-GRAPH_CLAMP_CP, GRAPH_CLAMP_CP),
+            mate: None,
+        },
+        Score::Mate(plies) => EvalPoint {
+            move_number,
+            side,
+            // Mate + 0 means the mover is giving mate; only "mated" scores are negative
+            cp: if plies >= 0 { GRAPH_CLAMP_CP } else { -GRAPH_CLAMP_CP },
+            mate: Some(plies),
+        },
+    }
+}
+
+/// Side to move after `moves` moves from a position where `first` was to move
+fn side_after(first: Color, moves: usize) -> Color {
+    if moves % 2 == 0 {
+        first
+    } else {
+        first.opponent()
+    }
+}
+
+impl EvalGraph {
+    /// Series of the scores stored with each move of the record
+    pub fn from_record(record: &GameRecord) -> Result<Self> {
+        let first = Position::from_sfen(&record.initial_sfen)?.side_to_move();
+        let game = record
+            .moves
+            .iter()
+            .enumerate()
+            .filter_map(|(index, recorded)| {
+                // A move's score was reported by its mover before the move was played
+                recorded.score.map(|score| eval_point(index + 1, side_after(first, index), score))
+            })
+            .collect();
+        Ok(Self {
+            record_id: record.id.clone(),
+            clamp_cp: GRAPH_CLAMP_CP,
+            game,
+            analysis: Vec::new(),
+        })
+    }
+
+    /// Add the series of an analysis of the record's positions
+    pub fn with_analysis(mut self, record: &GameRecord, results: &[PositionAnalysis]) -> Result<Self> {
+        let first = Position::from_sfen(&record.initial_sfen)?.side_to_move();
+        self.analysis = results
+            .iter()
+            .filter_map(|analysis| {
+                analysis
+                    .score
+                    .map(|score| eval_point(analysis.ply, side_after(first, analysis.ply), score))
+            })
+            .collect();
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_record::RecordedMove;
+
+    fn recorded(usi: &str, score: Option<Score>) -> RecordedMove {
+        RecordedMove {
+            usi: usi.to_string(),
+            elapsed_ms: None,
+            score,
+            depth: None,
+            commentary: Vec::new(),
+            comment: None,
+            symbol: None,
+            variations: Vec::new(),
+            book: false,
+        }
+    }
+
+    #[test]
+    fn test_scores_normalized_to_black() {
+        let record = GameRecord {
+            id: "game".to_string(),
+            black_name: "A".to_string(),
+            white_name: "B".to_string(),
+            initial_sfen: crate::shogi_rules::STARTPOS_SFEN.to_string(),
+            moves: vec![
+                recorded("7g7f", Some(Score::Cp(50))),
+                recorded("3c3d", Some(Score::Cp(-40))),
+                recorded("8h2b+", None),
+                recorded("3a2b", Some(Score::Mate(-7))),
+                recorded("B*4e", Some(Score::Cp(9000))),
+            ],
+            winner: None,
+            result: None,
+            termination: None,
+            black_timing: None,
+            white_timing: None,
+            match_config: None,
+            think_log: None,
+            created_at: String::new(),
+        };
+        let graph = EvalGraph::from_record(&record).unwrap();
+        let cps: Vec<(usize, i32, Option<i32>)> = graph.game.iter().map(|p| (p.move_number, p.cp, p.mate)).collect();
+        assert_eq!(
+            cps,
+            vec![(1, 50, None), (2, 40, None), (4, GRAPH_CLAMP_CP, Some(7)), (5, GRAPH_CLAMP_CP, None)]
+        );
+        assert_eq!(graph.game[1].side, Color::White);
+
+        let analysis = vec![PositionAnalysis {
+            ply: 1,
+            sfen: String::new(),
+            best_move: "3c3d".to_string(),
+            score: Some(Score::Cp(120)),
+            depth: Some(20),
+            pv: Vec::new(),
+        }];
+        let graph = graph.with_analysis(&record, &analysis).unwrap();
+        assert_eq!(graph.analysis[0].cp, -120);
+    }
+}
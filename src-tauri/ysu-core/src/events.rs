@@ -0,0 +1,352 @@
+//! Event delivery for engine output and match progress
+//! The library never talks to a UI directly; the embedding app supplies an [`EventSink`]
+//! (the Tauri app forwards everything to the webview).
+//!
+//! Every event the library emits is declared below as an [`Event`] or [`ScopedEvent`], which
+//! ties its name to its payload type. With the `ts` feature the payloads also derive TypeScript
+//! definitions; see [`typescript`].
+
+use crate::analysis_queue::AnalysisJob;
+use crate::engine_manager::{
+    BestMoveReport, ConsoleLine, CrashReport, EngineIdleStopped, EngineInitFailed, EngineMessage, EngineUnresponsive,
+    EngineWarmReady, InitProgress,
+};
+use crate::engine_storage::BinaryChanged;
+use crate::engine_vs_engine::{EngineVsEngineAnalysis, EngineVsEngineMove, EngineVsEngineState, MatchSummary};
+use crate::metrics;
+use crate::multipv::AnalysisSnapshot;
+use crate::position_match::PositionMatchProgress;
+use crate::selfplay::DatagenProgress;
+use crate::test_suite::SuiteProgress;
+use crate::tournament::{RoundStandings, TournamentProgress};
+use anyhow::Result;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Version of the event names and payloads. Bump it when an event is renamed or removed or a
+/// payload changes in a way existing listeners would misread; new events and new fields do
+/// not need it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// An event with a fixed name and the type of its payload
+pub struct Event<P> {
+    pub name: Cow<'static, str>,
+    payload: PhantomData<fn(&P)>,
+}
+
+impl<P> Event<P> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            payload: PhantomData,
+        }
+    }
+}
+
+/// An event emitted separately for each engine instance, as `name::<runtime id>`
+pub struct ScopedEvent<P> {
+    pub name: &'static str,
+    payload: PhantomData<fn(&P)>,
+}
+
+impl<P> ScopedEvent<P> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            payload: PhantomData,
+        }
+    }
+
+    /// The event for one instance
+    pub fn scope(&self, id: &impl fmt::Display) -> Event<P> {
+        Event {
+            name: Cow::Owned(format!("{}::{}", self.name, id)),
+            payload: PhantomData,
+        }
+    }
+}
+
+/// Raw lines the engine printed, after output throttling
+pub const USI_MESSAGE: ScopedEvent<String> = ScopedEvent::new("usi-message");
+/// Errors about the engine: write failures, crashes, failed start attempts
+pub const USI_ERROR: ScopedEvent<String> = ScopedEvent::new("usi-error");
+/// Free-text `info string` output
+pub const ENGINE_MESSAGE: ScopedEvent<EngineMessage> = ScopedEvent::new("engine-message");
+/// Merged MultiPV lines of the current search
+pub const ANALYSIS_SNAPSHOT: ScopedEvent<AnalysisSnapshot> = ScopedEvent::new("analysis-snapshot");
+pub const ENGINE_INIT_PROGRESS: ScopedEvent<InitProgress> = ScopedEvent::new("engine-init-progress");
+/// Each `bestmove`, checked against the last position sent
+pub const ENGINE_BESTMOVE: ScopedEvent<BestMoveReport> = ScopedEvent::new("engine-bestmove");
+/// Every line to and from an engine in console mode
+pub const ENGINE_CONSOLE: ScopedEvent<ConsoleLine> = ScopedEvent::new("engine-console");
+
+pub const ENGINE_CRASHED: Event<CrashReport> = Event::new("engine-crashed");
+pub const ENGINE_UNRESPONSIVE: Event<EngineUnresponsive> = Event::new("engine-unresponsive");
+pub const ENGINE_INIT_FAILED: Event<EngineInitFailed> = Event::new("engine-init-failed");
+pub const ENGINE_IDLE_STOPPED: Event<EngineIdleStopped> = Event::new("engine-idle-stopped");
+pub const ENGINE_WARM_READY: Event<EngineWarmReady> = Event::new("engine-warm-ready");
+pub const ENGINE_BINARY_CHANGED: Event<BinaryChanged> = Event::new("engine-binary-changed");
+
+pub const ENGINE_VS_ENGINE_UPDATE: Event<EngineVsEngineState> = Event::new("engine-vs-engine-update");
+pub const ENGINE_VS_ENGINE_ANALYSIS: Event<EngineVsEngineAnalysis> = Event::new("engine-vs-engine-analysis");
+pub const ENGINE_VS_ENGINE_MOVE: Event<EngineVsEngineMove> = Event::new("engine-vs-engine-move");
+pub const ENGINE_VS_ENGINE_SUMMARY: Event<MatchSummary> = Event::new("engine-vs-engine-summary");
+
+pub const TOURNAMENT_PROGRESS: Event<TournamentProgress> = Event::new("tournament-progress");
+/// Standings after each completed round
+pub const TOURNAMENT_STANDINGS: Event<RoundStandings> = Event::new("tournament-standings");
+pub const TEST_SUITE_PROGRESS: Event<SuiteProgress> = Event::new("test-suite-progress");
+pub const ANALYSIS_JOB_UPDATE: Event<AnalysisJob> = Event::new("analysis-job-update");
+/// Counts of a self-play data generation run after each game
+pub const DATAGEN_PROGRESS: Event<DatagenProgress> = Event::new("datagen-progress");
+/// Results of each start position of a position match, with the running aggregate
+pub const POSITION_MATCH_PROGRESS: Event<PositionMatchProgress> = Event::new("position-match-progress");
+
+/// Receives named events with JSON payloads
+pub trait EventSink: Send + Sync {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) -> Result<()>;
+}
+
+impl dyn EventSink {
+    /// Serialize `payload` and emit it as `event`
+    pub fn emit<S: Serialize>(&self, event: &str, payload: S) -> Result<()> {
+        let start = std::time::Instant::now();
+        let emitted = self.emit_value(event, serde_json::to_value(payload)?);
+        metrics::EVENTS_EMITTED.increment();
+        metrics::EMIT_LATENCY.record_since(start);
+        emitted
+    }
+
+    pub fn send<P: Serialize>(&self, event: &Event<P>, payload: &P) -> Result<()> {
+        self.emit(&event.name, payload)
+    }
+}
+
+/// Shared handle to the sink, cloned into reader tasks and match runners
+pub type SharedEventSink = Arc<dyn EventSink>;
+
+/// Discards every event, for tools that only need the return values
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullEventSink;
+
+impl EventSink for NullEventSink {
+    fn emit_value(&self, _event: &str, _payload: serde_json::Value) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps every event in memory so tests can check what the engine manager or a match emitted
+#[derive(Debug, Default)]
+pub struct RecordingEventSink {
+    events: Mutex<Vec<(String, serde_json::Value)>>,
+}
+
+impl RecordingEventSink {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// All events so far, oldest first
+    pub fn events(&self) -> Vec<(String, serde_json::Value)> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Payloads of the events named `event`, oldest first
+    pub fn payloads(&self, event: &str) -> Vec<serde_json::Value> {
+        self.events()
+            .into_iter()
+            .filter(|(name, _)| name == event)
+            .map(|(_, payload)| payload)
+            .collect()
+    }
+
+    /// Wait until an event named `event` has a payload matching `matches`, polling until `wait` runs out
+    pub async fn wait_for(
+        &self,
+        event: &str,
+        wait: Duration,
+        matches: impl Fn(&serde_json::Value) -> bool,
+    ) -> Option<serde_json::Value> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            if let Some(payload) = self.payloads(event).into_iter().find(|payload| matches(payload)) {
+                return Some(payload);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return None;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    pub fn clear(&self) {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+impl EventSink for RecordingEventSink {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) -> Result<()> {
+        self.events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((event.to_string(), payload));
+        Ok(())
+    }
+}
+
+/// TypeScript definitions of the events, so the frontend contract is checked by the compiler.
+/// `cargo test --features ts export_typescript` in the app writes them to `src/types/generated`.
+#[cfg(feature = "ts")]
+pub mod typescript {
+    use super::*;
+    use std::path::Path;
+    use ts_rs::TS;
+
+    /// One event as it appears in the generated definitions
+    pub struct Binding {
+        name: String,
+        scoped: bool,
+        /// TypeScript name of the payload type
+        payload: String,
+        /// Module the payload type is exported to; None for built-in types such as `string`
+        module: Option<String>,
+        export: fn(&Path) -> Result<(), ts_rs::ExportError>,
+    }
+
+    impl<P: TS + 'static> Event<P> {
+        pub fn binding(&self) -> Binding {
+            binding::<P>(&self.name, false)
+        }
+    }
+
+    impl<P: TS + 'static> ScopedEvent<P> {
+        pub fn binding(&self) -> Binding {
+            binding::<P>(self.name, true)
+        }
+    }
+
+    fn binding<P: TS + 'static>(name: &str, scoped: bool) -> Binding {
+        Binding {
+            name: name.to_string(),
+            scoped,
+            payload: P::name(),
+            module: P::output_path().map(|path| path.with_extension("").display().to_string()),
+            export: |dir| match P::output_path() {
+                Some(_) => P::export_all_to(dir),
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// The events emitted by this library
+    pub fn bindings() -> Vec<Binding> {
+        vec![
+            USI_MESSAGE.binding(),
+            USI_ERROR.binding(),
+            ENGINE_MESSAGE.binding(),
+            ANALYSIS_SNAPSHOT.binding(),
+            ENGINE_INIT_PROGRESS.binding(),
+            ENGINE_BESTMOVE.binding(),
+            ENGINE_CONSOLE.binding(),
+            ENGINE_CRASHED.binding(),
+            ENGINE_UNRESPONSIVE.binding(),
+            ENGINE_INIT_FAILED.binding(),
+            ENGINE_IDLE_STOPPED.binding(),
+            ENGINE_WARM_READY.binding(),
+            ENGINE_BINARY_CHANGED.binding(),
+            ENGINE_VS_ENGINE_UPDATE.binding(),
+            ENGINE_VS_ENGINE_ANALYSIS.binding(),
+            ENGINE_VS_ENGINE_MOVE.binding(),
+            ENGINE_VS_ENGINE_SUMMARY.binding(),
+            TOURNAMENT_PROGRESS.binding(),
+            TOURNAMENT_STANDINGS.binding(),
+            TEST_SUITE_PROGRESS.binding(),
+            ANALYSIS_JOB_UPDATE.binding(),
+            DATAGEN_PROGRESS.binding(),
+            POSITION_MATCH_PROGRESS.binding(),
+        ]
+    }
+
+    /// Export every payload type to `dir`, one module per type, and write `events.ts` mapping
+    /// event names to payloads
+    pub fn write(dir: &Path, bindings: &[Binding]) -> Result<()> {
+        for binding in bindings {
+            (binding.export)(dir)?;
+        }
+        // ts-rs declares 64-bit integers as bigint, but serde_json sends them as plain numbers
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "ts") {
+                let contents = std::fs::read_to_string(&path)?;
+                std::fs::write(&path, contents.replace("bigint", "number"))?;
+            }
+        }
+        std::fs::write(dir.join("events.ts"), index(bindings))?;
+        Ok(())
+    }
+
+    fn index(bindings: &[Binding]) -> String {
+        let mut modules: Vec<(&str, &str)> = bindings
+            .iter()
+            .filter_map(|binding| binding.module.as_deref().map(|module| (binding.payload.as_str(), module)))
+            .collect();
+        modules.sort();
+        modules.dedup();
+
+        let mut out = String::from("// Generated by `cargo test --features ts export_typescript`; do not edit.\n");
+        for (payload, module) in modules {
+            out += &format!("import type {{ {} }} from \"./{}\";\n", payload, module);
+        }
+        out += &format!("\nexport const EVENT_SCHEMA_VERSION = {};\n", SCHEMA_VERSION);
+        for (scoped, doc, name) in [
+            (false, "Payloads of events with fixed names", "EventPayloads"),
+            (true, "Payloads of per-engine events, emitted as `${name}::${runtimeId}`", "ScopedEventPayloads"),
+        ] {
+            out += &format!("\n/** {} */\nexport interface {} {{\n", doc, name);
+            for binding in bindings.iter().filter(|binding| binding.scoped == scoped) {
+                out += &format!("  \"{}\": {};\n", binding.name, binding.payload);
+            }
+            out += "}\n";
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Update {
+        move_number: usize,
+    }
+
+    #[tokio::test]
+    async fn test_recording_sink() {
+        let recorder = RecordingEventSink::new();
+        let sink: SharedEventSink = recorder.clone();
+        sink.emit("usi-message::e:1", "usiok").unwrap();
+        sink.emit("engine-vs-engine-update", Update { move_number: 3 }).unwrap();
+
+        assert_eq!(recorder.payloads("usi-message::e:1"), vec![serde_json::json!("usiok")]);
+        let update = recorder
+            .wait_for("engine-vs-engine-update", Duration::from_millis(50), |payload| payload["move_number"] == 3)
+            .await;
+        assert!(update.is_some());
+        assert!(recorder.wait_for("engine-vs-engine-summary", Duration::from_millis(20), |_| true).await.is_none());
+
+        let engine_message = EngineMessage {
+            message: "hash 256MB".to_string(),
+            timestamp: String::new(),
+        };
+        sink.send(&ENGINE_MESSAGE.scope(&"e:2"), &engine_message).unwrap();
+        assert_eq!(recorder.payloads("engine-message::e:2")[0]["message"], "hash 256MB");
+
+        recorder.clear();
+        assert!(recorder.events().is_empty());
+    }
+}
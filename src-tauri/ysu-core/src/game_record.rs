@@ -0,0 +1,288 @@
+//! Saved game records
+//! Each finished game is stored as its own JSON file under the config directory
+
+use crate::engine_storage::EngineStorage;
+use crate::engine_vs_engine::{EngineVsEngineConfig, MoveTimingStats};
+use crate::shogi_rules::Position;
+use crate::think_log::ThinkLogStorage;
+use crate::usi_info::Score;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// A move as played in a recorded game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMove {
+    pub usi: String,
+    /// Wall-clock time the player took for this move
+    #[serde(default)]
+    pub elapsed_ms: Option<u64>,
+    /// Score the mover reported for this move, from its own point of view
+    #[serde(default)]
+    pub score: Option<Score>,
+    #[serde(default)]
+    pub depth: Option<u32>,
+    /// Machine-generated annotations (see the commentary module)
+    #[serde(default)]
+    pub commentary: Vec<String>,
+    /// User comment, possibly spanning several lines
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub symbol: Option<MoveSymbol>,
+    /// Alternatives to this move, each played from the position before it
+    #[serde(default)]
+    pub variations: Vec<Variation>,
+    /// Played from the book or forced by the match setup rather than searched
+    #[serde(default)]
+    pub book: bool,
+}
+
+/// Annotation symbol judging a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveSymbol {
+    #[serde(rename = "!!")]
+    Brilliant,
+    #[serde(rename = "!")]
+    Good,
+    #[serde(rename = "!?")]
+    Interesting,
+    #[serde(rename = "?!")]
+    Dubious,
+    #[serde(rename = "?")]
+    Mistake,
+    #[serde(rename = "??")]
+    Blunder,
+}
+
+impl MoveSymbol {
+    pub const ALL: [MoveSymbol; 6] = [
+        MoveSymbol::Brilliant,
+        MoveSymbol::Good,
+        MoveSymbol::Interesting,
+        MoveSymbol::Dubious,
+        MoveSymbol::Mistake,
+        MoveSymbol::Blunder,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MoveSymbol::Brilliant => "!!",
+            MoveSymbol::Good => "!",
+            MoveSymbol::Interesting => "!?",
+            MoveSymbol::Dubious => "?!",
+            MoveSymbol::Mistake => "?",
+            MoveSymbol::Blunder => "??",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|symbol| symbol.as_str() == text.trim())
+    }
+}
+
+/// A line of USI moves branching off the game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variation {
+    pub moves: Vec<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// How a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    Resignation,
+    Checkmate,
+    /// Valid entering-king (nyugyoku) declaration
+    EnteringKing,
+    /// Illegal move or invalid declaration
+    IllegalMove,
+    /// Engine crashed or did not answer in time
+    EngineFailure,
+    Adjudication,
+    MaxMoves,
+}
+
+/// A complete game record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub id: String,
+    pub black_name: String,
+    pub white_name: String,
+    pub initial_sfen: String,
+    pub moves: Vec<RecordedMove>,
+    pub winner: Option<String>,
+    pub result: Option<String>,
+    #[serde(default)]
+    pub termination: Option<Termination>,
+    #[serde(default)]
+    pub black_timing: Option<MoveTimingStats>,
+    #[serde(default)]
+    pub white_timing: Option<MoveTimingStats>,
+    /// Configuration of the engine-vs-engine match that produced this game
+    #[serde(default)]
+    pub match_config: Option<EngineVsEngineConfig>,
+    /// File name of the engines' archived thinking output (see the think_log module)
+    #[serde(default)]
+    pub think_log: Option<String>,
+    pub created_at: String,
+}
+
+impl GameRecord {
+    /// The position before the move at `index` (0-based) was played
+    pub fn position_before(&self, index: usize) -> Result<Position> {
+        if index >= self.moves.len() {
+            return Err(anyhow!("Move index {} out of range ({} moves)", index, self.moves.len()));
+        }
+        let mut position = Position::from_sfen(&self.initial_sfen)?;
+        for (number, recorded) in self.moves[..index].iter().enumerate() {
+            position
+                .apply_usi_move(&recorded.usi)
+                .map_err(|e| anyhow!("Move {} ({}): {}", number + 1, recorded.usi, e))?;
+        }
+        Ok(position)
+    }
+
+    /// Set or clear the user comment and symbol of a move. Blank comments are cleared.
+    pub fn annotate_move(&mut self, index: usize, comment: Option<String>, symbol: Option<MoveSymbol>) -> Result<()> {
+        let recorded = self
+            .moves
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("Move index {} out of range", index))?;
+        recorded.comment = comment.filter(|comment| !comment.trim().is_empty());
+        recorded.symbol = symbol;
+        Ok(())
+    }
+
+    /// Add an alternative to the move at `index` after checking the line is legal
+    pub fn add_variation(&mut self, index: usize, variation: Variation) -> Result<()> {
+        let mut position = self.position_before(index)?;
+        if variation.moves.is_empty() {
+            return Err(anyhow!("Variation has no moves"));
+        }
+        for (number, usi) in variation.moves.iter().enumerate() {
+            position
+                .apply_usi_move(usi)
+                .map_err(|e| anyhow!("Variation move {} ({}): {}", number + 1, usi, e))?;
+        }
+        self.moves[index].variations.push(variation);
+        Ok(())
+    }
+
+    pub fn remove_variation(&mut self, index: usize, variation_index: usize) -> Result<Variation> {
+        let variations = &mut self
+            .moves
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("Move index {} out of range", index))?
+            .variations;
+        if variation_index >= variations.len() {
+            return Err(anyhow!("Variation {} not found on move {}", variation_index, index + 1));
+        }
+        Ok(variations.remove(variation_index))
+    }
+}
+
+/// Lightweight listing entry for a saved record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecordSummary {
+    pub id: String,
+    pub black_name: String,
+    pub white_name: String,
+    pub winner: Option<String>,
+    pub result: Option<String>,
+    pub move_count: usize,
+    pub created_at: String,
+}
+
+impl From<&GameRecord> for GameRecordSummary {
+    fn from(record: &GameRecord) -> Self {
+        Self {
+            id: record.id.clone(),
+            black_name: record.black_name.clone(),
+            white_name: record.white_name.clone(),
+            winner: record.winner.clone(),
+            result: record.result.clone(),
+            move_count: record.moves.len(),
+            created_at: record.created_at.clone(),
+        }
+    }
+}
+
+pub struct GameRecordStorage;
+
+impl GameRecordStorage {
+    /// Directory holding one `<id>.json` file per record
+    pub fn get_records_dir() -> Result<PathBuf> {
+        let dir = EngineStorage::get_config_dir()?.join("records");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    fn record_path(record_id: &str) -> Result<PathBuf> {
+        // Record IDs are UUIDs; rejecting anything else keeps lookups inside the records directory
+        Uuid::parse_str(record_id).map_err(|_| anyhow!("Invalid game record ID: {}", record_id))?;
+        Ok(Self::get_records_dir()?.join(format!("{}.json", record_id)))
+    }
+
+    /// Write a record to disk, replacing any existing record with the same ID
+    pub async fn save(record: &GameRecord) -> Result<PathBuf> {
+        let path = Self::record_path(&record.id)?;
+        let contents = serde_json::to_string_pretty(record)?;
+        tokio::fs::write(&path, contents).await?;
+        log::info!("Saved game record to: {}", path.display());
+        Ok(path)
+    }
+
+    pub async fn load(record_id: &str) -> Result<GameRecord> {
+        let path = Self::record_path(record_id)?;
+        if !path.exists() {
+            return Err(anyhow!("Game record not found: {}", record_id));
+        }
+        let contents = tokio::fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// List all saved records, newest first. Unreadable files are skipped.
+    pub async fn list() -> Result<Vec<GameRecordSummary>> {
+        let records = Self::load_all().await?;
+        Ok(records.iter().map(GameRecordSummary::from).collect())
+    }
+
+    /// Load every saved record, newest first. Unreadable files are skipped.
+    pub async fn load_all() -> Result<Vec<GameRecord>> {
+        let mut records = Vec::new();
+        let mut entries = tokio::fs::read_dir(Self::get_records_dir()?).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let parsed = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(serde_json::from_str::<GameRecord>(&contents)?));
+            match parsed {
+                Ok(record) => records.push(record),
+                Err(e) => log::warn!("Skipping unreadable game record {}: {}", path.display(), e),
+            }
+        }
+        records.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(records)
+    }
+
+    pub async fn delete(record_id: &str) -> Result<()> {
+        let path = Self::record_path(record_id)?;
+        if !path.exists() {
+            return Err(anyhow!("Game record not found: {}", record_id));
+        }
+        tokio::fs::remove_file(&path).await?;
+        if let Err(e) = ThinkLogStorage::delete(record_id).await {
+            log::warn!("Failed to delete thinking log of {}: {}", record_id, e);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+//! Game sessions for human-vs-engine play
+//! The backend keeps the authoritative move list so it can build correct `position` commands
+
+use crate::engine_vs_engine::{splitmix64, SearchLimit};
+use crate::handicap::Handicap;
+use crate::move_tree::{MoveNode, MoveTree};
+use crate::shogi_rules::{Position, STARTPOS_SFEN};
+use crate::strength::StrengthLevel;
+use crate::usi_info::UsiInfo;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A game in progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSession {
+    pub id: String,
+    pub initial_sfen: String,
+    pub handicap: Option<Handicap>,
+    /// Moves played and explored; the game continues from the tree's current node
+    pub moves: MoveTree,
+    /// Runtime ID of the engine playing in this session, if any
+    pub engine_id: Option<String>,
+    /// Strength level the engine plays at, if one was applied
+    #[serde(default)]
+    pub strength: Option<StrengthLevel>,
+    /// Search limit for the engine's moves; None plays on the game clock
+    #[serde(default)]
+    pub search_limit: Option<SearchLimit>,
+    /// Weakening by random choice among the engine's top moves
+    #[serde(default)]
+    pub randomization: Option<MoveRandomization>,
+    /// Seed for the random move choice, combined with the move number
+    #[serde(default)]
+    pub seed: u64,
+    /// Clocks as last reported by the frontend, which runs them; kept so an auto-saved
+    /// game resumes with the time left
+    #[serde(default)]
+    pub clocks: Option<GameClocks>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameClocks {
+    pub black_remaining_ms: u64,
+    pub white_remaining_ms: u64,
+}
+
+/// Weakens an engine by asking for several lines (MultiPV) and picking one at random,
+/// weighting each line by exp(-(best score - line score) / temperature)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct MoveRandomization {
+    pub multi_pv: u32,
+    /// Higher temperatures make worse moves more likely
+    pub temperature_cp: f64,
+    /// Lines scoring this much below the best line are never picked
+    pub max_loss_cp: i32,
+}
+
+impl MoveRandomization {
+    /// Pick one of the engine's top moves. `lines` are the latest info per MultiPV index;
+    /// `random` is any 64-bit random value. Returns None if no line has a move and score.
+    pub fn choose_move(&self, lines: &[UsiInfo], random: u64) -> Option<String> {
+        let mut candidates: Vec<(&str, i32)> = lines
+            .iter()
+            .filter_map(|info| Some((info.pv.first()?.as_str(), info.score?.centipawns())))
+            .collect();
+        let best = candidates.iter().map(|&(_, score)| score).max()?;
+        candidates.retain(|&(_, score)| best - score <= self.max_loss_cp);
+
+        let temperature = self.temperature_cp.max(1.0);
+        let weights: Vec<f64> = candidates
+            .iter()
+            .map(|&(_, score)| (-((best - score) as f64) / temperature).exp())
+            .collect();
+        let total: f64 = weights.iter().sum();
+        // Top 53 bits give a uniform value in [0, 1)
+        let mut target = (random >> 11) as f64 / (1u64 << 53) as f64 * total;
+        for (&(usi, _), weight) in candidates.iter().zip(&weights) {
+            if target < *weight {
+                return Some(usi.to_string());
+            }
+            target -= weight;
+        }
+        candidates.last().map(|&(usi, _)| usi.to_string())
+    }
+}
+
+/// The latest line per MultiPV index from a search's info output, best line first
+pub fn multipv_lines(infos: &[UsiInfo]) -> Vec<UsiInfo> {
+    let mut lines: HashMap<u32, &UsiInfo> = HashMap::new();
+    for info in infos.iter().filter(|info| info.score.is_some() && !info.pv.is_empty()) {
+        lines.insert(info.multipv.unwrap_or(1), info);
+    }
+    let mut lines: Vec<(u32, UsiInfo)> = lines.into_iter().map(|(index, info)| (index, info.clone())).collect();
+    lines.sort_by_key(|(index, _)| *index);
+    lines.into_iter().map(|(_, info)| info).collect()
+}
+
+impl GameSession {
+    pub fn new(initial_sfen: Option<String>, handicap: Option<Handicap>, engine_id: Option<String>) -> Result<Self> {
+        let initial_sfen = Handicap::resolve_initial_sfen(initial_sfen, handicap);
+        // Validate and normalize (also accepts "startpos")
+        let initial_sfen = Position::from_sfen(&initial_sfen)?.to_sfen();
+
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            initial_sfen,
+            handicap,
+            moves: MoveTree::default(),
+            engine_id,
+            strength: None,
+            search_limit: None,
+            randomization: None,
+            seed: Uuid::new_v4().as_u64_pair().0,
+            clocks: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Moves leading to the current position
+    pub fn move_history(&self) -> Vec<String> {
+        self.moves.current_moves()
+    }
+
+    /// The current position after all moves
+    pub fn position(&self) -> Result<Position> {
+        let mut position = Position::from_sfen(&self.initial_sfen)?;
+        for usi in &self.move_history() {
+            position.apply_usi_move(usi)?;
+        }
+        Ok(position)
+    }
+
+    /// Play a move after validating it against the current position. A move other than the
+    /// one already played here starts a variation.
+    pub fn apply_move(&mut self, usi: &str) -> Result<()> {
+        let mut position = self.position()?;
+        let mv = position.apply_usi_move(usi)?;
+        self.moves.play(&mv.to_usi());
+        Ok(())
+    }
+
+    /// Show the position at `path` in the move tree (empty for the initial position)
+    pub fn navigate(&mut self, path: &[usize]) -> Result<()> {
+        self.moves.go_to(path)
+    }
+
+    /// Make the variation at `path` the main line at its branching point
+    pub fn promote_variation(&mut self, path: &[usize]) -> Result<()> {
+        self.moves.promote(path)
+    }
+
+    /// Delete the variation at `path` and the moves after it
+    pub fn delete_variation(&mut self, path: &[usize]) -> Result<MoveNode> {
+        self.moves.delete(path)
+    }
+
+    /// The move the engine plays: its best move, or with randomization a weighted random
+    /// pick among the lines it reported. Falls back to `best_move` if the pick is unusable.
+    pub fn choose_engine_move(&self, best_move: &str, infos: &[UsiInfo]) -> String {
+        let Some(randomization) = &self.randomization else {
+            return best_move.to_string();
+        };
+        let random = splitmix64(self.seed ^ self.moves.current.len() as u64);
+        let chosen = randomization.choose_move(&multipv_lines(infos), random);
+        match chosen {
+            Some(usi) if self.position().is_ok_and(|mut position| position.apply_usi_move(&usi).is_ok()) => usi,
+            _ => best_move.to_string(),
+        }
+    }
+
+    /// Take back the last move. It stays in the move tree, so playing another move there
+    /// keeps both lines.
+    pub fn undo_move(&mut self) -> Result<String> {
+        self.moves.back().ok_or_else(|| anyhow!("No moves to undo"))
+    }
+
+    /// The USI `position` command for the current state. Handicap and custom positions
+    /// must be sent as SFEN since `startpos` always means the even-game position.
+    pub fn position_command(&self) -> String {
+        position_command(&self.initial_sfen, &self.move_history())
+    }
+}
+
+/// Build a USI `position` command from an initial SFEN and moves played from it
+pub fn position_command(initial_sfen: &str, moves: &[String]) -> String {
+    let base = if initial_sfen == STARTPOS_SFEN {
+        "position startpos".to_string()
+    } else {
+        format!("position sfen {}", initial_sfen)
+    };
+    if moves.is_empty() {
+        base
+    } else {
+        format!("{} moves {}", base, moves.join(" "))
+    }
+}
+
+/// Active game sessions by ID
+#[derive(Debug, Default)]
+pub struct GameSessionStore {
+    sessions: HashMap<String, GameSession>,
+}
+
+impl GameSessionStore {
+    pub fn insert(&mut self, session: GameSession) -> String {
+        let id = session.id.clone();
+        self.sessions.insert(id.clone(), session);
+        id
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<&GameSession> {
+        self.sessions.get(session_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GameSession> {
+        self.sessions.values()
+    }
+
+    pub fn get_mut(&mut self, session_id: &str) -> Result<&mut GameSession> {
+        self.sessions
+            .get_mut(session_id)
+            .ok_or_else(|| anyhow!("Game session not found: {}", session_id))
+    }
+
+    pub fn remove(&mut self, session_id: &str) -> Result<GameSession> {
+        self.sessions
+            .remove(session_id)
+            .ok_or_else(|| anyhow!("Game session not found: {}", session_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shogi_rules::Color;
+
+    #[test]
+    fn test_handicap_session_position_command() {
+        let mut session = GameSession::new(None, Some(Handicap::Bishop), None).unwrap();
+        assert_eq!(session.position().unwrap().side_to_move(), Color::White);
+        session.apply_move("5a4b").unwrap();
+        assert!(session.apply_move("5a4b").is_err());
+        assert_eq!(
+            session.position_command(),
+            format!("position sfen {} moves 5a4b", Handicap::Bishop.sfen())
+        );
+
+        let even = GameSession::new(Some("startpos".to_string()), None, None).unwrap();
+        assert_eq!(even.position_command(), "position startpos");
+    }
+
+    #[test]
+    fn test_undo_then_new_move_branches() {
+        let mut session = GameSession::new(None, None, None).unwrap();
+        session.apply_move("7g7f").unwrap();
+        session.apply_move("3c3d").unwrap();
+        assert_eq!(session.undo_move().unwrap(), "3c3d");
+        session.apply_move("8c8d").unwrap();
+        assert_eq!(session.position_command(), "position startpos moves 7g7f 8c8d");
+        assert_eq!(session.moves.main_line(), vec!["7g7f", "3c3d"]);
+
+        session.promote_variation(&[0, 1]).unwrap();
+        assert_eq!(session.moves.main_line(), vec!["7g7f", "8c8d"]);
+        session.navigate(&[0, 1]).unwrap();
+        assert_eq!(session.position_command(), "position startpos moves 7g7f 3c3d");
+        session.delete_variation(&[0, 1]).unwrap();
+        assert_eq!(session.position_command(), "position startpos moves 7g7f");
+    }
+
+    #[test]
+    fn test_randomized_move_choice() {
+        let infos: Vec<UsiInfo> = [
+            "info depth 8 multipv 1 score cp 120 pv 7g7f 3c3d",
+            "info depth 8 multipv 2 score cp 80 pv 2g2f 8c8d",
+            "info depth 8 multipv 3 score cp -900 pv 1g1f",
+            "info depth 9 multipv 1 score cp 100 pv 7g7f 8c8d",
+        ]
+        .iter()
+        .filter_map(|line| crate::usi_info::parse_info(line))
+        .collect();
+        let lines = multipv_lines(&infos);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].depth, Some(9));
+
+        let randomization = MoveRandomization { multi_pv: 3, temperature_cp: 50.0, max_loss_cp: 300 };
+        assert_eq!(randomization.choose_move(&lines, 0).as_deref(), Some("7g7f"));
+        assert_eq!(randomization.choose_move(&lines, u64::MAX).as_deref(), Some("2g2f"));
+        assert!(randomization.choose_move(&[], 0).is_none());
+
+        let mut session = GameSession::new(None, None, None).unwrap();
+        assert_eq!(session.choose_engine_move("7g7f", &infos), "7g7f");
+        session.randomization = Some(randomization);
+        assert!(["7g7f", "2g2f"].contains(&session.choose_engine_move("7g7f", &infos).as_str()));
+    }
+}
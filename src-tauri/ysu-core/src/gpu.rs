@@ -0,0 +1,187 @@
+//! GPU detection and the dlshogi engine profile
+//! Deep-learning engines load their network onto the GPU on `isready`, which can take minutes,
+//! and search with one set of threads per GPU (UCT_Threads, UCT_Threads2, ...). Engines of the
+//! dlshogi family get longer timeouts when registered, extra GPUs enabled, and a warning when
+//! no CUDA-capable GPU is found.
+
+use crate::engine_process;
+use crate::engine_storage::EngineConfig;
+use crate::engine_validator::{EngineFamily, EngineMetadata};
+use crate::timeouts::TimeoutOverrides;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+const NVIDIA_SMI_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpuDevice {
+    pub name: String,
+    pub memory_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpuInfo {
+    /// An NVIDIA driver answered, so CUDA engines can run
+    pub cuda_available: bool,
+    pub driver_version: Option<String>,
+    pub gpus: Vec<GpuDevice>,
+}
+
+/// Parse `nvidia-smi --query-gpu=name,memory.total,driver_version --format=csv,noheader,nounits`
+pub fn parse_nvidia_smi(output: &str) -> GpuInfo {
+    let mut info = GpuInfo::default();
+    for line in output.lines().filter(|line| !line.trim().is_empty()) {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let Some(name) = fields.first().filter(|name| !name.is_empty()) else {
+            continue;
+        };
+        info.gpus.push(GpuDevice {
+            name: name.to_string(),
+            memory_mb: fields.get(1).and_then(|memory| memory.parse().ok()),
+        });
+        if info.driver_version.is_none() {
+            info.driver_version = fields.get(2).filter(|version| !version.is_empty()).map(|version| version.to_string());
+        }
+    }
+    info.cuda_available = !info.gpus.is_empty();
+    info
+}
+
+/// Ask the NVIDIA driver for the installed GPUs. No driver, or one that does not answer,
+/// means no CUDA.
+pub async fn detect() -> GpuInfo {
+    let mut command = Command::new("nvidia-smi");
+    command
+        .args(["--query-gpu=name,memory.total,driver_version", "--format=csv,noheader,nounits"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    engine_process::prepare(&mut command);
+    match tokio::time::timeout(NVIDIA_SMI_TIMEOUT, command.output()).await {
+        Ok(Ok(output)) if output.status.success() => parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout)),
+        Ok(Ok(output)) => {
+            log::info!("nvidia-smi exited with {}; assuming no CUDA GPU", output.status);
+            GpuInfo::default()
+        }
+        Ok(Err(e)) => {
+            log::info!("nvidia-smi not available ({}); assuming no CUDA GPU", e);
+            GpuInfo::default()
+        }
+        Err(_) => {
+            log::warn!("nvidia-smi did not answer within {:?}", NVIDIA_SMI_TIMEOUT);
+            GpuInfo::default()
+        }
+    }
+}
+
+pub fn is_deep_learning(metadata: &EngineMetadata) -> bool {
+    metadata.family == Some(EngineFamily::Dlshogi)
+}
+
+/// Timeouts for loading a network onto the GPU and for the slower first searches
+pub fn deep_learning_timeouts() -> TimeoutOverrides {
+    TimeoutOverrides {
+        readyok_ms: Some(300_000),
+        bestmove_slack_ms: Some(30_000),
+        ..Default::default()
+    }
+}
+
+/// Saved options that give each GPU after the first as many search threads as the first.
+/// dlshogi reads UCT_Threads2..UCT_Threads16 and leaves a GPU unused while its value is 0.
+pub fn multi_gpu_options(metadata: &EngineMetadata, gpu_count: usize) -> Vec<(String, String)> {
+    let Some(threads) = metadata
+        .options
+        .iter()
+        .find(|option| option.name == "UCT_Threads")
+        .and_then(|option| option.default.clone())
+    else {
+        return Vec::new();
+    };
+    (2..=gpu_count)
+        .map(|gpu| format!("UCT_Threads{}", gpu))
+        .filter(|name| metadata.options.iter().any(|option| option.name == *name))
+        .map(|name| (name, threads.clone()))
+        .collect()
+}
+
+/// Apply the deep-learning profile to an engine being registered: timeouts it has not set,
+/// and extra GPUs. Returns warnings for the user; empty for other engines.
+pub fn apply_profile(config: &mut EngineConfig, gpu: &GpuInfo) -> Vec<String> {
+    let Some(metadata) = config.metadata.as_ref().filter(|metadata| is_deep_learning(metadata)) else {
+        return Vec::new();
+    };
+    let gpu_options = multi_gpu_options(metadata, gpu.gpus.len());
+    let mut warnings = Vec::new();
+    if !gpu.cuda_available {
+        warnings.push(format!(
+            "{} is a GPU engine, but no CUDA-capable GPU was found; it may fail to start or run very slowly",
+            config.name
+        ));
+    }
+
+    config.timeouts.get_or_insert_with(deep_learning_timeouts);
+    if !gpu_options.is_empty() {
+        log::info!("Enabling {} extra GPUs for {}", gpu_options.len(), config.name);
+        config.saved_options.get_or_insert_with(Default::default).extend(gpu_options);
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine_validator::EngineOption;
+
+    fn option(name: &str, default: &str) -> EngineOption {
+        EngineOption {
+            name: name.to_string(),
+            option_type: "spin".to_string(),
+            default: Some(default.to_string()),
+            min: None,
+            max: None,
+            var: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_nvidia_smi() {
+        let info = parse_nvidia_smi("NVIDIA GeForce RTX 4090, 24564, 550.54.14\nNVIDIA GeForce RTX 3060, 12288, 550.54.14\n");
+        assert!(info.cuda_available);
+        assert_eq!(info.driver_version.as_deref(), Some("550.54.14"));
+        assert_eq!(info.gpus.len(), 2);
+        assert_eq!(info.gpus[1].memory_mb, Some(12288));
+
+        assert!(!parse_nvidia_smi("").cuda_available);
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let metadata = EngineMetadata {
+            name: "dlshogi".to_string(),
+            author: None,
+            options: vec![option("DNN_Model", "model.onnx"), option("UCT_Threads", "2"), option("UCT_Threads2", "0")],
+            encoding: Default::default(),
+            raw_banner: Vec::new(),
+            version: None,
+            family: Some(EngineFamily::Dlshogi),
+        };
+        let engine = |metadata| EngineConfig::new("dlshogi".to_string(), "/engines/dlshogi".to_string(), metadata, false);
+        let two_gpus = parse_nvidia_smi("A, 1, 1\nB, 1, 1\n");
+
+        let mut config = engine(Some(metadata.clone()));
+        assert!(apply_profile(&mut config, &two_gpus).is_empty());
+        assert_eq!(config.timeouts, Some(deep_learning_timeouts()));
+        assert_eq!(config.saved_options.unwrap()["UCT_Threads2"], "2");
+
+        let mut config = engine(Some(metadata));
+        assert_eq!(apply_profile(&mut config, &GpuInfo::default()).len(), 1);
+        assert!(config.saved_options.is_none());
+
+        let mut config = engine(None);
+        assert!(apply_profile(&mut config, &GpuInfo::default()).is_empty());
+        assert!(config.timeouts.is_none());
+    }
+}
@@ -0,0 +1,107 @@
+//! Standard handicap (komaochi) starting positions
+//! In handicap games the handicap giver (uwate) plays White and moves first
+
+use serde::{Deserialize, Serialize};
+
+/// Standard handicap types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Handicap {
+    Lance,
+    RightLance,
+    Bishop,
+    Rook,
+    RookLance,
+    TwoPiece,
+    FourPiece,
+    SixPiece,
+    EightPiece,
+    TenPiece,
+}
+
+impl Handicap {
+    pub const ALL: [Handicap; 10] = [
+        Handicap::Lance,
+        Handicap::RightLance,
+        Handicap::Bishop,
+        Handicap::Rook,
+        Handicap::RookLance,
+        Handicap::TwoPiece,
+        Handicap::FourPiece,
+        Handicap::SixPiece,
+        Handicap::EightPiece,
+        Handicap::TenPiece,
+    ];
+
+    /// Starting position in SFEN (White to move)
+    pub fn sfen(self) -> &'static str {
+        match self {
+            Handicap::Lance => "lnsgkgsn1/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::RightLance => "1nsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::Bishop => "lnsgkgsnl/1r7/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::Rook => "lnsgkgsnl/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::RookLance => "lnsgkgsn1/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::TwoPiece => "lnsgkgsnl/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::FourPiece => "1nsgkgsn1/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::SixPiece => "2sgkgs2/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::EightPiece => "3gkg3/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            Handicap::TenPiece => "4k4/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+        }
+    }
+
+    /// Display name in English
+    pub fn name(self) -> &'static str {
+        match self {
+            Handicap::Lance => "Lance",
+            Handicap::RightLance => "Right Lance",
+            Handicap::Bishop => "Bishop",
+            Handicap::Rook => "Rook",
+            Handicap::RookLance => "Rook and Lance",
+            Handicap::TwoPiece => "2-Piece",
+            Handicap::FourPiece => "4-Piece",
+            Handicap::SixPiece => "6-Piece",
+            Handicap::EightPiece => "8-Piece",
+            Handicap::TenPiece => "10-Piece",
+        }
+    }
+
+    /// Traditional Japanese name, as written in KIF headers (手合割)
+    pub fn japanese_name(self) -> &'static str {
+        match self {
+            Handicap::Lance => "香落ち",
+            Handicap::RightLance => "右香落ち",
+            Handicap::Bishop => "角落ち",
+            Handicap::Rook => "飛車落ち",
+            Handicap::RookLance => "飛香落ち",
+            Handicap::TwoPiece => "二枚落ち",
+            Handicap::FourPiece => "四枚落ち",
+            Handicap::SixPiece => "六枚落ち",
+            Handicap::EightPiece => "八枚落ち",
+            Handicap::TenPiece => "十枚落ち",
+        }
+    }
+
+    /// Resolve the starting SFEN for a game: an explicit SFEN wins over a handicap,
+    /// which wins over the standard starting position
+    pub fn resolve_initial_sfen(initial_sfen: Option<String>, handicap: Option<Handicap>) -> String {
+        initial_sfen
+            .filter(|sfen| !sfen.trim().is_empty())
+            .or_else(|| handicap.map(|h| h.sfen().to_string()))
+            .unwrap_or_else(|| crate::shogi_rules::STARTPOS_SFEN.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shogi_rules::{Color, Position};
+
+    #[test]
+    fn test_handicap_positions_are_valid_and_white_to_move() {
+        for handicap in Handicap::ALL {
+            let position = Position::from_sfen(handicap.sfen()).unwrap();
+            assert_eq!(position.side_to_move(), Color::White, "{:?}", handicap);
+            assert!(!position.legal_moves().is_empty());
+        }
+    }
+}
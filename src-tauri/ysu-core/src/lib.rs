@@ -0,0 +1,48 @@
+//! USI engine management shared by the Tauri app and other shogi tools: running engine
+//! processes, validating and storing engine configs, and engine-vs-engine matches.
+//! Nothing here depends on Tauri; events go through an [`EventSink`].
+
+pub mod adjudication;
+pub mod analysis_queue;
+pub mod atomic_file;
+pub mod autosave;
+pub mod batch_eval;
+pub mod commentary;
+pub mod cpu_affinity;
+pub mod engine_encoding;
+pub mod engine_health;
+pub mod engine_manager;
+pub mod engine_permissions;
+pub mod engine_process;
+pub mod engine_storage;
+pub mod engine_validator;
+pub mod engine_vs_engine;
+pub mod eval_file;
+pub mod eval_graph;
+pub mod events;
+pub mod game_record;
+pub mod game_session;
+pub mod gpu;
+pub mod handicap;
+pub mod message;
+pub mod metrics;
+pub mod move_tree;
+pub mod multipv;
+pub mod notation;
+pub mod opening_book;
+pub mod position_match;
+pub mod output_buffer;
+pub mod process_priority;
+pub mod selfplay;
+pub mod series_stats;
+pub mod shogi_rules;
+pub mod strength;
+pub mod test_suite;
+pub mod think_log;
+pub mod timeouts;
+pub mod tournament;
+pub mod usi_info;
+pub mod usi_macro;
+pub mod win_rate;
+
+pub use events::{EventSink, NullEventSink, RecordingEventSink};
@@ -0,0 +1,67 @@
+//! User-facing messages as codes with parameters
+//! Text built here would be English only, while most users read the UI in Japanese. A message
+//! carries a stable code the UI looks up in its own catalog and the values to fill in; the
+//! English text goes along for logs and for codes the catalog does not know yet.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct Message {
+    /// Stable identifier, e.g. "engine_not_found"
+    pub code: String,
+    /// Values the message refers to, by the names its template uses
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    /// English text
+    pub text: String,
+}
+
+impl Message {
+    pub fn new(code: &str, text: String) -> Self {
+        Self {
+            code: code.to_string(),
+            params: BTreeMap::new(),
+            text,
+        }
+    }
+
+    pub fn with(mut self, name: &str, value: &impl fmt::Display) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Build a [`Message`] from a code and an English template whose `{name}` placeholders are
+/// also the parameter names:
+/// `message!("engine_not_found", "Engine not found: {engine_id}", engine_id = id)`
+#[macro_export]
+macro_rules! message {
+    ($code:expr, $template:literal $(, $name:ident = $value:expr)* $(,)?) => {{
+        $( let $name = &$value; )*
+        $crate::message::Message::new($code, format!($template))
+            $( .with(stringify!($name), $name) )*
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_message_macro() {
+        let engine_id = "yane".to_string();
+        let message = message!("engine_not_found", "Engine not found: {engine_id}", engine_id = engine_id);
+        assert_eq!(message.text, "Engine not found: yane");
+        assert_eq!(message.params["engine_id"], "yane");
+
+        let json = serde_json::to_value(&message!("adjudicated_draw", "Adjudicated draw")).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": "adjudicated_draw", "params": {}, "text": "Adjudicated draw" }));
+    }
+}
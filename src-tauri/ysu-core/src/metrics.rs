@@ -0,0 +1,243 @@
+//! Local performance metrics, for profiling the backend under heavy analysis loads
+//! Nothing is sent anywhere: counters and histograms live in this process and are only read
+//! by [`snapshot`] (the app's get_metrics_snapshot). Recording is a few atomic adds plus an
+//! uncontended lock, cheap enough for hot paths such as emitting every engine info line.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Rates are averaged over this many most recent seconds
+const RATE_WINDOW_SECS: usize = 60;
+
+/// Upper bounds of the histogram buckets in microseconds; a last bucket takes the rest
+const BUCKET_BOUNDS_US: [u64; 14] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 1_000_000, 10_000_000,
+];
+
+/// Engine processes started
+pub static ENGINES_SPAWNED: Counter = Counter::new("engines_spawned");
+/// Commands the host application dispatched
+pub static COMMANDS_INVOKED: Counter = Counter::new("commands_invoked");
+/// Events handed to the event sink
+pub static EVENTS_EMITTED: Counter = Counter::new("events_emitted");
+/// Moves played in engine-vs-engine matches
+pub static MATCH_MOVES: Counter = Counter::new("match_moves");
+/// Engine-vs-engine games finished
+pub static MATCH_GAMES: Counter = Counter::new("match_games");
+
+/// Time to serialize an event and hand it to the sink
+pub static EMIT_LATENCY: Histogram = Histogram::new("emit_latency");
+/// Time from dispatching a command to its result, as far as the host application records it
+pub static COMMAND_DURATION: Histogram = Histogram::new("command_duration");
+/// Time an engine took to answer `go` in engine-vs-engine matches
+pub static MATCH_MOVE_TIME: Histogram = Histogram::new("match_move_time");
+
+const COUNTERS: [&Counter; 5] = [&ENGINES_SPAWNED, &COMMANDS_INVOKED, &EVENTS_EMITTED, &MATCH_MOVES, &MATCH_GAMES];
+const HISTOGRAMS: [&Histogram; 3] = [&EMIT_LATENCY, &COMMAND_DURATION, &MATCH_MOVE_TIME];
+
+fn started() -> Instant {
+    static STARTED: OnceLock<Instant> = OnceLock::new();
+    *STARTED.get_or_init(Instant::now)
+}
+
+/// Count of something that happened, with its rate over the last minute
+pub struct Counter {
+    name: &'static str,
+    total: AtomicU64,
+    /// Counts per second since [`started`], in a ring indexed by second
+    recent: Mutex<[(u64, u64); RATE_WINDOW_SECS]>,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            total: AtomicU64::new(0),
+            recent: Mutex::new([(0, 0); RATE_WINDOW_SECS]),
+        }
+    }
+
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, count: u64) {
+        self.add_at(count, started().elapsed().as_secs());
+    }
+
+    fn add_at(&self, count: u64, second: u64) {
+        self.total.fetch_add(count, Ordering::Relaxed);
+        let mut recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let slot = &mut recent[second as usize % RATE_WINDOW_SECS];
+        if slot.0 != second {
+            *slot = (second, 0);
+        }
+        slot.1 += count;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Average per second over the last minute, or since start if that is shorter
+    fn per_second_at(&self, now: Duration) -> f64 {
+        let second = now.as_secs();
+        let recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let count: u64 = recent
+            .iter()
+            .filter(|(slot_second, _)| second.saturating_sub(*slot_second) < RATE_WINDOW_SECS as u64)
+            .map(|(_, count)| count)
+            .sum();
+        let window = now.as_secs_f64().clamp(1.0, RATE_WINDOW_SECS as f64);
+        count as f64 / window
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_BUCKET: AtomicU64 = AtomicU64::new(0);
+
+/// Distribution of durations in fixed buckets
+pub struct Histogram {
+    name: &'static str,
+    buckets: [AtomicU64; BUCKET_BOUNDS_US.len() + 1],
+    count: AtomicU64,
+    sum_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+impl Histogram {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            buckets: [EMPTY_BUCKET; BUCKET_BOUNDS_US.len() + 1],
+            count: AtomicU64::new(0),
+            sum_us: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, duration: Duration) {
+        let us = duration.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = BUCKET_BOUNDS_US.iter().position(|&bound| us <= bound).unwrap_or(BUCKET_BOUNDS_US.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    /// Record the time since `start`
+    pub fn record_since(&self, start: Instant) {
+        self.record(start.elapsed());
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        let count = self.count.load(Ordering::Relaxed);
+        let max_us = self.max_us.load(Ordering::Relaxed);
+        let buckets: Vec<u64> = self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).collect();
+        // Upper bound of the bucket the quantile falls in, but never above the largest value seen
+        let quantile = |q: f64| -> u64 {
+            let rank = ((count as f64 * q).ceil() as u64).max(1);
+            let mut seen = 0;
+            for (i, bucket) in buckets.iter().enumerate() {
+                seen += bucket;
+                if seen >= rank {
+                    return BUCKET_BOUNDS_US.get(i).copied().unwrap_or(max_us).min(max_us);
+                }
+            }
+            max_us
+        };
+        HistogramSnapshot {
+            count,
+            mean_us: self.sum_us.load(Ordering::Relaxed).checked_div(count).unwrap_or(0),
+            p50_us: quantile(0.5),
+            p95_us: quantile(0.95),
+            p99_us: quantile(0.99),
+            max_us,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct CounterSnapshot {
+    pub total: u64,
+    /// Average rate over the last minute
+    pub per_second: f64,
+}
+
+/// Quantiles are the upper bound of the bucket they fall in
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct HistogramSnapshot {
+    pub count: u64,
+    pub mean_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct MetricsSnapshot {
+    /// Seconds since the first metric was recorded
+    pub uptime_secs: u64,
+    pub counters: BTreeMap<String, CounterSnapshot>,
+    pub histograms: BTreeMap<String, HistogramSnapshot>,
+}
+
+/// Current value of every metric
+pub fn snapshot() -> MetricsSnapshot {
+    let now = started().elapsed();
+    MetricsSnapshot {
+        uptime_secs: now.as_secs(),
+        counters: COUNTERS
+            .iter()
+            .map(|counter| {
+                let snapshot = CounterSnapshot { total: counter.total(), per_second: counter.per_second_at(now) };
+                (counter.name.to_string(), snapshot)
+            })
+            .collect(),
+        histograms: HISTOGRAMS
+            .iter()
+            .map(|histogram| (histogram.name.to_string(), histogram.snapshot()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_rate_window() {
+        let counter = Counter::new("test");
+        counter.add_at(30, 0);
+        counter.add_at(60, 100);
+        counter.add_at(60, 159);
+        assert_eq!(counter.total(), 150);
+        // The count from second 0 has left the window
+        assert_eq!(counter.per_second_at(Duration::from_secs(159)), 2.0);
+        assert_eq!(counter.per_second_at(Duration::from_secs(500)), 0.0);
+    }
+
+    #[test]
+    fn test_histogram_quantiles() {
+        let histogram = Histogram::new("test");
+        for _ in 0..98 {
+            histogram.record(Duration::from_micros(80));
+        }
+        histogram.record(Duration::from_millis(3));
+        histogram.record(Duration::from_millis(20));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 100);
+        assert_eq!(snapshot.p50_us, 100);
+        assert_eq!(snapshot.p99_us, 5_000);
+        assert_eq!(snapshot.max_us, 20_000);
+        assert_eq!(Histogram::new("empty").snapshot().p50_us, 0);
+    }
+}
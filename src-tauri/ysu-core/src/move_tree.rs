@@ -0,0 +1,250 @@
+//! Variation tree of the moves played from a position
+//! Every node lists its continuations with the main line first. Nodes are addressed by their
+//! path: the child index taken at each move from the root, so `[0, 0, 1]` is the second
+//! alternative to the third move of the main line.
+
+use crate::game_record::GameRecord;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveNode {
+    pub usi: String,
+    /// Continuations, main line first
+    #[serde(default)]
+    pub children: Vec<MoveNode>,
+}
+
+impl MoveNode {
+    fn new(usi: &str) -> Self {
+        Self {
+            usi: usi.to_string(),
+            children: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveTree {
+    /// First moves from the initial position, main line first
+    pub children: Vec<MoveNode>,
+    /// Path of the node whose position is shown; empty at the initial position
+    pub current: Vec<usize>,
+}
+
+impl MoveTree {
+    /// Tree of a saved game: its moves as the main line and each move's variations as
+    /// alternatives to it
+    pub fn from_record(record: &GameRecord) -> Self {
+        let mut tree = Self::default();
+        for recorded in &record.moves {
+            let branch_point = tree.current.clone();
+            for variation in &recorded.variations {
+                tree.current = branch_point.clone();
+                for usi in &variation.moves {
+                    tree.play(usi);
+                }
+            }
+            tree.current = branch_point;
+            tree.play(&recorded.usi);
+            // Alternatives were added first; keep the game's own move as the main line
+            let path = tree.current.clone();
+            tree.promote(&path).expect("move was just played");
+        }
+        tree.current.clear();
+        tree
+    }
+
+    fn children_at(&self, path: &[usize]) -> Option<&Vec<MoveNode>> {
+        let mut children = &self.children;
+        for &index in path {
+            children = &children.get(index)?.children;
+        }
+        Some(children)
+    }
+
+    fn children_at_mut(&mut self, path: &[usize]) -> Option<&mut Vec<MoveNode>> {
+        let mut children = &mut self.children;
+        for &index in path {
+            children = &mut children.get_mut(index)?.children;
+        }
+        Some(children)
+    }
+
+    pub fn node(&self, path: &[usize]) -> Option<&MoveNode> {
+        let (&last, parent) = path.split_last()?;
+        self.children_at(parent)?.get(last)
+    }
+
+    /// USI moves from the initial position to the node at `path`
+    pub fn moves_to(&self, path: &[usize]) -> Result<Vec<String>> {
+        let mut children = &self.children;
+        let mut moves = Vec::with_capacity(path.len());
+        for &index in path {
+            let node = children
+                .get(index)
+                .ok_or_else(|| anyhow!("No variation at {:?}", path))?;
+            moves.push(node.usi.clone());
+            children = &node.children;
+        }
+        Ok(moves)
+    }
+
+    /// USI moves leading to the current node
+    pub fn current_moves(&self) -> Vec<String> {
+        self.moves_to(&self.current).unwrap_or_default()
+    }
+
+    /// USI moves of the main line, following the first continuation from the root
+    pub fn main_line(&self) -> Vec<String> {
+        let mut moves = Vec::new();
+        let mut children = &self.children;
+        while let Some(node) = children.first() {
+            moves.push(node.usi.clone());
+            children = &node.children;
+        }
+        moves
+    }
+
+    /// Play a move from the current node. An existing continuation with the same move is
+    /// followed; otherwise the move starts a new variation (or the main line at a leaf).
+    pub fn play(&mut self, usi: &str) {
+        let current = self.current.clone();
+        let children = self.children_at_mut(&current).expect("current path exists");
+        let index = match children.iter().position(|node| node.usi == usi) {
+            Some(index) => index,
+            None => {
+                children.push(MoveNode::new(usi));
+                children.len() - 1
+            }
+        };
+        self.current.push(index);
+    }
+
+    /// Step back one move, keeping the move in the tree. Returns the move stepped over.
+    pub fn back(&mut self) -> Option<String> {
+        let usi = self.node(&self.current)?.usi.clone();
+        self.current.pop();
+        Some(usi)
+    }
+
+    /// Follow the main continuation of the current node
+    pub fn forward(&mut self) -> Option<String> {
+        let usi = self.children_at(&self.current)?.first()?.usi.clone();
+        self.current.push(0);
+        Some(usi)
+    }
+
+    pub fn go_to(&mut self, path: &[usize]) -> Result<()> {
+        if !path.is_empty() && self.node(path).is_none() {
+            return Err(anyhow!("No variation at {:?}", path));
+        }
+        self.current = path.to_vec();
+        Ok(())
+    }
+
+    /// Make the node at `path` the main continuation of its parent
+    pub fn promote(&mut self, path: &[usize]) -> Result<()> {
+        let (&index, parent) = path.split_last().ok_or_else(|| anyhow!("Cannot promote the initial position"))?;
+        let children = self
+            .children_at_mut(parent)
+            .filter(|children| index < children.len())
+            .ok_or_else(|| anyhow!("No variation at {:?}", path))?;
+        let node = children.remove(index);
+        children.insert(0, node);
+
+        if self.current.len() > parent.len() && self.current.starts_with(parent) {
+            let step = &mut self.current[parent.len()];
+            if *step == index {
+                *step = 0;
+            } else if *step < index {
+                *step += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the node at `path` with everything after it. If the current node was inside
+    /// the removed branch, the parent becomes current.
+    pub fn delete(&mut self, path: &[usize]) -> Result<MoveNode> {
+        let (&index, parent) = path.split_last().ok_or_else(|| anyhow!("Cannot delete the initial position"))?;
+        let children = self
+            .children_at_mut(parent)
+            .filter(|children| index < children.len())
+            .ok_or_else(|| anyhow!("No variation at {:?}", path))?;
+        let node = children.remove(index);
+
+        if self.current.len() > parent.len() && self.current.starts_with(parent) {
+            let step = self.current[parent.len()];
+            if step == index {
+                self.current.truncate(parent.len());
+            } else if step > index {
+                self.current[parent.len()] -= 1;
+            }
+        }
+        Ok(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(list: &[&str]) -> Vec<String> {
+        list.iter().map(|usi| usi.to_string()).collect()
+    }
+
+    #[test]
+    fn test_branch_promote_delete() {
+        let mut tree = MoveTree::default();
+        for usi in ["7g7f", "3c3d", "2g2f"] {
+            tree.play(usi);
+        }
+        tree.back();
+        tree.back();
+        tree.play("8c8d");
+        tree.play("2g2f");
+        assert_eq!(tree.current, vec![0, 1, 0]);
+        assert_eq!(tree.main_line(), moves(&["7g7f", "3c3d", "2g2f"]));
+
+        // Playing an existing move follows it instead of adding a branch
+        tree.go_to(&[0]).unwrap();
+        tree.play("3c3d");
+        assert_eq!(tree.current, vec![0, 0]);
+        assert_eq!(tree.node(&[0]).unwrap().children.len(), 2);
+
+        tree.go_to(&[0, 1, 0]).unwrap();
+        tree.promote(&[0, 1]).unwrap();
+        assert_eq!(tree.current, vec![0, 0, 0]);
+        assert_eq!(tree.main_line(), moves(&["7g7f", "8c8d", "2g2f"]));
+        assert_eq!(tree.current_moves(), moves(&["7g7f", "8c8d", "2g2f"]));
+
+        tree.delete(&[0, 0]).unwrap();
+        assert_eq!(tree.current, vec![0]);
+        assert_eq!(tree.main_line(), moves(&["7g7f", "3c3d", "2g2f"]));
+        assert!(tree.go_to(&[0, 1]).is_err());
+        assert!(tree.delete(&[]).is_err());
+        assert_eq!(tree.forward().as_deref(), Some("3c3d"));
+    }
+
+    #[test]
+    fn test_from_record() {
+        let mut record: GameRecord = serde_json::from_value(serde_json::json!({
+            "id": "game",
+            "black_name": "A",
+            "white_name": "B",
+            "initial_sfen": crate::shogi_rules::STARTPOS_SFEN,
+            "moves": [{ "usi": "7g7f" }, { "usi": "3c3d" }, { "usi": "2g2f" }],
+            "winner": null,
+            "result": null,
+            "created_at": "",
+        }))
+        .unwrap();
+        record.add_variation(1, crate::game_record::Variation { moves: moves(&["8c8d", "2g2f"]), comment: None }).unwrap();
+
+        let tree = MoveTree::from_record(&record);
+        assert_eq!(tree.main_line(), moves(&["7g7f", "3c3d", "2g2f"]));
+        assert_eq!(tree.moves_to(&[0, 1, 0]).unwrap(), moves(&["7g7f", "8c8d", "2g2f"]));
+        assert!(tree.current.is_empty());
+    }
+}
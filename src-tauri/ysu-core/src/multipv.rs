@@ -0,0 +1,193 @@
+//! Consolidated MultiPV analysis built from a stream of `info` lines
+//! Engines report each principal variation on its own line and often interleave them: with
+//! MultiPV 3, line 1 may already be at depth 21 while lines 2 and 3 are still at depth 20,
+//! and a late line of an older iteration can arrive after a newer one. The tracker keeps the
+//! deepest report per PV index, so a snapshot is always the best current picture.
+
+use crate::usi_info::{Score, UsiInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One principal variation of the search
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct PvLine {
+    /// 1-based MultiPV index; 1 is the engine's best move
+    pub multipv: u32,
+    /// From the point of view of the side to move
+    pub score: Option<Score>,
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub pv: Vec<String>,
+}
+
+/// All PV lines of a search as they stand, emitted as `analysis-snapshot::<runtime id>`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct AnalysisSnapshot {
+    /// Ordered by MultiPV index
+    pub lines: Vec<PvLine>,
+    /// Depth every line has reached
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time_ms: Option<u64>,
+    pub hashfull: Option<u32>,
+    /// The engine has sent its bestmove
+    pub finished: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct MultiPvTracker {
+    lines: BTreeMap<u32, PvLine>,
+    nodes: Option<u64>,
+    nps: Option<u64>,
+    time_ms: Option<u64>,
+    hashfull: Option<u32>,
+    finished: bool,
+    changed: bool,
+}
+
+impl MultiPvTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the previous search, e.g. when a new `go` is sent
+    pub fn reset(&mut self) {
+        *self = Self {
+            changed: true,
+            ..Self::default()
+        };
+    }
+
+    /// Merge one `info` line. A report for a PV index that is shallower than the one already
+    /// held is a late line of an earlier iteration and only its search statistics are kept.
+    pub fn update(&mut self, info: &UsiInfo) {
+        if let Some(nodes) = info.nodes {
+            self.nodes = Some(self.nodes.map_or(nodes, |known| known.max(nodes)));
+        }
+        if let Some(time_ms) = info.time_ms {
+            self.time_ms = Some(self.time_ms.map_or(time_ms, |known| known.max(time_ms)));
+        }
+        self.nps = info.nps.or(self.nps);
+        self.hashfull = info.hashfull.or(self.hashfull);
+        self.changed = true;
+
+        // Lines without a score, such as `currmove` progress, carry no variation
+        let Some(score) = info.score else {
+            return;
+        };
+        let multipv = info.multipv.unwrap_or(1);
+        if let Some(known) = self.lines.get(&multipv) {
+            if info.depth < known.depth {
+                return;
+            }
+        }
+        let pv = match self.lines.get(&multipv) {
+            // A bound-only update without a PV keeps the variation it refines
+            Some(known) if info.pv.is_empty() => known.pv.clone(),
+            _ => info.pv.clone(),
+        };
+        self.lines.insert(
+            multipv,
+            PvLine {
+                multipv,
+                score: Some(score),
+                depth: info.depth,
+                seldepth: info.seldepth,
+                nodes: info.nodes,
+                pv,
+            },
+        );
+    }
+
+    /// Record the end of the search
+    pub fn finish(&mut self) {
+        self.finished = true;
+        self.changed = true;
+    }
+
+    /// Nodes searched so far, as last reported
+    pub fn nodes(&self) -> Option<u64> {
+        self.nodes
+    }
+
+    /// Whether anything changed since the last call, clearing the flag
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    pub fn snapshot(&self) -> AnalysisSnapshot {
+        // A lagging line can still show the move a deeper line above it has since taken;
+        // list each first move once, under its best rank
+        let mut lines: Vec<PvLine> = Vec::with_capacity(self.lines.len());
+        for line in self.lines.values() {
+            let duplicate = line.pv.first().is_some_and(|first| {
+                lines.iter().any(|kept| kept.pv.first() == Some(first))
+            });
+            if !duplicate {
+                lines.push(line.clone());
+            }
+        }
+        AnalysisSnapshot {
+            depth: lines.iter().map(|line| line.depth.unwrap_or(0)).min(),
+            lines,
+            nodes: self.nodes,
+            nps: self.nps,
+            time_ms: self.time_ms,
+            hashfull: self.hashfull,
+            finished: self.finished,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usi_info::parse_info;
+
+    fn feed(tracker: &mut MultiPvTracker, lines: &[&str]) {
+        for line in lines {
+            tracker.update(&parse_info(line).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_interleaved_multipv_lines() {
+        let mut tracker = MultiPvTracker::new();
+        feed(&mut tracker, &[
+            "info depth 20 multipv 1 score cp 50 nodes 1000 pv 7g7f 3c3d",
+            "info depth 20 multipv 2 score cp 30 nodes 1000 pv 2g2f 8c8d",
+            "info depth 21 multipv 1 score cp 45 nodes 2000 pv 2g2f 3c3d",
+            // Late line of depth 19 must not replace the depth 20 line
+            "info depth 19 multipv 2 score cp 90 nodes 900 pv 5g5f",
+            "info depth 21 currmove 7g7f nodes 2500 nps 100000",
+        ]);
+        let snapshot = tracker.snapshot();
+        // Line 2 still shows 2g2f, which line 1 has since taken
+        assert_eq!(snapshot.lines.len(), 1);
+        assert_eq!(snapshot.lines[0].pv, vec!["2g2f", "3c3d"]);
+        assert_eq!(snapshot.nodes, Some(2500));
+        assert_eq!(snapshot.nps, Some(100000));
+
+        feed(&mut tracker, &[
+            "info depth 21 multipv 2 score cp 40 pv 7g7f 3c3d",
+            "info depth 22 multipv 2 score cp 35 upperbound",
+        ]);
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.lines.len(), 2);
+        assert_eq!(snapshot.lines[1].score, Some(Score::Cp(35)));
+        assert_eq!(snapshot.lines[1].pv, vec!["7g7f", "3c3d"]);
+        assert_eq!(snapshot.depth, Some(21));
+        assert!(tracker.take_changed());
+        assert!(!tracker.take_changed());
+
+        tracker.finish();
+        assert!(tracker.snapshot().finished);
+        tracker.reset();
+        assert!(tracker.snapshot().lines.is_empty());
+        assert!(!tracker.snapshot().finished);
+    }
+}
@@ -0,0 +1,602 @@
+//! Move notation conversion between USI, CSA, Japanese KIF/KI2 and Western (Hodges) notation
+//! Disambiguation is computed from the legal moves of the position, so it follows the actual rules
+
+use crate::shogi_rules::{Color, Move, PieceType, Position, Square};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Supported move notations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotationStyle {
+    /// USI coordinates (7g7f, P*5e)
+    Usi,
+    /// Japanese KI2 (▲７六歩, △同　角成)
+    Ki2,
+    /// Western / Hodges (P-7f, Bx2b+)
+    Western,
+    /// CSA protocol moves (+7776FU, -0055KA)
+    Csa,
+    /// KIF move column (７六歩(77), 同　角成(88), ５五角打)
+    Kif,
+}
+
+const FULLWIDTH_DIGITS: [char; 9] = ['１', '２', '３', '４', '５', '６', '７', '８', '９'];
+const KANJI_NUMERALS: [char; 9] = ['一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+/// Kanji name of a piece as used in KIF/KI2 records
+pub fn piece_kanji(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::Pawn => "歩",
+        PieceType::Lance => "香",
+        PieceType::Knight => "桂",
+        PieceType::Silver => "銀",
+        PieceType::Gold => "金",
+        PieceType::Bishop => "角",
+        PieceType::Rook => "飛",
+        PieceType::King => "玉",
+        PieceType::PromotedPawn => "と",
+        PieceType::PromotedLance => "成香",
+        PieceType::PromotedKnight => "成桂",
+        PieceType::PromotedSilver => "成銀",
+        PieceType::Horse => "馬",
+        PieceType::Dragon => "龍",
+    }
+}
+
+/// Japanese square notation, e.g. "７六"
+pub fn square_kanji(square: Square) -> String {
+    format!(
+        "{}{}",
+        FULLWIDTH_DIGITS[(square.file - 1) as usize],
+        KANJI_NUMERALS[(square.rank - 1) as usize]
+    )
+}
+
+fn side_mark(color: Color) -> &'static str {
+    match color {
+        Color::Black => "▲",
+        Color::White => "△",
+    }
+}
+
+/// Whether a board move could have promoted (so declining must be written as 不成 / =)
+fn promotion_available(position: &Position, mv: &Move) -> bool {
+    match *mv {
+        Move::Normal { from, to, .. } => {
+            let color = position.side_to_move();
+            position
+                .piece_at(from)
+                .map(|piece| {
+                    piece.piece_type.promote().is_some()
+                        && (from.in_promotion_zone(color) || to.in_promotion_zone(color))
+                })
+                .unwrap_or(false)
+        }
+        Move::Drop { .. } => false,
+    }
+}
+
+/// Origin squares of every piece of the same kind that can legally move to `to`
+fn competing_origins(position: &Position, piece_type: PieceType, to: Square) -> Vec<Square> {
+    let color = position.side_to_move();
+    let mut origins: Vec<Square> = Vec::new();
+    for mv in position.legal_moves() {
+        if let Move::Normal { from, to: target, .. } = mv {
+            let same_kind = position
+                .piece_at(from)
+                .map(|piece| piece.color == color && piece.piece_type == piece_type)
+                .unwrap_or(false);
+            if target == to && same_kind && !origins.contains(&from) {
+                origins.push(from);
+            }
+        }
+    }
+    origins
+}
+
+/// KI2 relative-position modifier (右, 左, 直, 上, 引, 寄 or combinations)
+fn ki2_modifier(position: &Position, from: Square, to: Square, piece_type: PieceType) -> String {
+    let origins = competing_origins(position, piece_type, to);
+    if origins.len() <= 1 {
+        return String::new();
+    }
+
+    let color = position.side_to_move();
+    let forward_distance = |sq: Square| -> i8 {
+        match color {
+            Color::Black => sq.rank as i8 - to.rank as i8,
+            Color::White => to.rank as i8 - sq.rank as i8,
+        }
+    };
+    let vertical = |sq: Square| -> &'static str {
+        match forward_distance(sq) {
+            d if d > 0 => "上",
+            d if d < 0 => "引",
+            _ => "寄",
+        }
+    };
+    // Larger value means further to the mover's right
+    let rightness = |sq: Square| -> i8 {
+        match color {
+            Color::Black => -(sq.file as i8),
+            Color::White => sq.file as i8,
+        }
+    };
+
+    let my_vertical = vertical(from);
+    let group: Vec<Square> = origins.iter().copied().filter(|&sq| vertical(sq) == my_vertical).collect();
+    if group.len() == 1 {
+        return my_vertical.to_string();
+    }
+
+    let is_major = matches!(piece_type, PieceType::Horse | PieceType::Dragon);
+    if !is_major && from.file == to.file && forward_distance(from) > 0 {
+        return "直".to_string();
+    }
+
+    let mine = rightness(from);
+    let is_rightmost = |squares: &[Square]| squares.iter().all(|&sq| sq == from || rightness(sq) < mine);
+    let is_leftmost = |squares: &[Square]| squares.iter().all(|&sq| sq == from || rightness(sq) > mine);
+
+    let horizontal = if is_rightmost(&group) {
+        "右"
+    } else if is_leftmost(&group) {
+        "左"
+    } else {
+        return my_vertical.to_string();
+    };
+
+    let unique_overall = if horizontal == "右" { is_rightmost(&origins) } else { is_leftmost(&origins) };
+    if unique_overall {
+        horizontal.to_string()
+    } else {
+        format!("{}{}", horizontal, my_vertical)
+    }
+}
+
+/// Format a move in KI2 notation. `previous_to` enables the 同 (same square) shorthand.
+fn format_ki2(position: &Position, mv: &Move, previous_to: Option<Square>, with_mark: bool) -> Result<String> {
+    let color = position.side_to_move();
+    let to = mv.to();
+    let destination = |name: &str| -> String {
+        if previous_to == Some(to) {
+            // A full-width space keeps single-character pieces aligned ("同　歩")
+            if name.chars().count() == 1 {
+                "同　".to_string()
+            } else {
+                "同".to_string()
+            }
+        } else {
+            square_kanji(to)
+        }
+    };
+
+    let body = match *mv {
+        Move::Normal { from, promote, .. } => {
+            let piece = position
+                .piece_at(from)
+                .ok_or_else(|| anyhow!("No piece on {} for move {}", from.to_usi(), mv.to_usi()))?;
+            let name = piece_kanji(piece.piece_type);
+            let promotion = if promote {
+                "成"
+            } else if promotion_available(position, mv) {
+                "不成"
+            } else {
+                ""
+            };
+            format!(
+                "{}{}{}{}",
+                destination(name),
+                name,
+                ki2_modifier(position, from, to, piece.piece_type),
+                promotion
+            )
+        }
+        Move::Drop { piece_type, .. } => {
+            let name = piece_kanji(piece_type);
+            // 打 is only written when a board piece of the same kind could also reach the square
+            let needs_drop_mark = !competing_origins(position, piece_type, to).is_empty();
+            format!("{}{}{}", destination(name), name, if needs_drop_mark { "打" } else { "" })
+        }
+    };
+
+    Ok(if with_mark { format!("{}{}", side_mark(color), body) } else { body })
+}
+
+/// Format a move in Western (Hodges) notation. The origin square is written only when
+/// needed for disambiguation unless `force_origin` is set.
+fn format_western(position: &Position, mv: &Move, force_origin: bool) -> Result<String> {
+    match *mv {
+        Move::Normal { from, to, promote } => {
+            let piece = position
+                .piece_at(from)
+                .ok_or_else(|| anyhow!("No piece on {} for move {}", from.to_usi(), mv.to_usi()))?;
+            let letter = if piece.piece_type.is_promoted() {
+                format!("+{}", piece.piece_type.usi_char())
+            } else {
+                piece.piece_type.usi_char().to_string()
+            };
+            let origin = if force_origin || competing_origins(position, piece.piece_type, to).len() > 1 {
+                from.to_usi()
+            } else {
+                String::new()
+            };
+            let separator = if position.piece_at(to).is_some() { "x" } else { "-" };
+            let promotion = if promote {
+                "+"
+            } else if promotion_available(position, mv) {
+                "="
+            } else {
+                ""
+            };
+            Ok(format!("{}{}{}{}{}", letter, origin, separator, to.to_usi(), promotion))
+        }
+        Move::Drop { piece_type, to } => Ok(format!("{}*{}", piece_type.usi_char(), to.to_usi())),
+    }
+}
+
+/// Format a move the way KIF records it: destination, piece, then 成/不成/打 and the
+/// origin square in parentheses, e.g. "７六歩(77)", "同　角成(88)", "５五角打"
+pub fn format_kif_move(position: &Position, mv: &Move, previous_to: Option<Square>) -> Result<String> {
+    let destination = if previous_to == Some(mv.to()) {
+        "同　".to_string()
+    } else {
+        square_kanji(mv.to())
+    };
+    match *mv {
+        Move::Normal { from, promote, .. } => {
+            let piece = position
+                .piece_at(from)
+                .ok_or_else(|| anyhow!("No piece on {}", from.to_usi()))?;
+            let suffix = if promote {
+                "成"
+            } else if promotion_available(position, mv) {
+                "不成"
+            } else {
+                ""
+            };
+            Ok(format!(
+                "{}{}{}({}{})",
+                destination,
+                piece_kanji(piece.piece_type),
+                suffix,
+                from.file,
+                from.rank
+            ))
+        }
+        Move::Drop { piece_type, .. } => Ok(format!("{}{}打", destination, piece_kanji(piece_type))),
+    }
+}
+
+/// Format a single legal move in the given notation
+pub fn format_move(position: &Position, mv: &Move, previous_to: Option<Square>, style: NotationStyle) -> Result<String> {
+    if !position.is_legal(mv) {
+        return Err(anyhow!("Illegal move: {}", mv.to_usi()));
+    }
+    match style {
+        NotationStyle::Usi => Ok(mv.to_usi()),
+        NotationStyle::Ki2 => format_ki2(position, mv, previous_to, true),
+        NotationStyle::Western => format_western(position, mv, false),
+        NotationStyle::Csa => usi_to_csa(position, mv),
+        NotationStyle::Kif => format_kif_move(position, mv, previous_to),
+    }
+}
+
+/// Format a sequence of USI moves played from `position`
+pub fn format_moves(position: &Position, moves: &[String], style: NotationStyle) -> Result<Vec<String>> {
+    let mut position = position.clone();
+    let mut previous_to = None;
+    let mut formatted = Vec::with_capacity(moves.len());
+    for (index, usi) in moves.iter().enumerate() {
+        let mv = Move::from_usi(usi)?;
+        let text = format_move(&position, &mv, previous_to, style)
+            .map_err(|e| anyhow!("Move {} ({}): {}", index + 1, usi, e))?;
+        formatted.push(text);
+        position.apply_move_unchecked(&mv);
+        previous_to = Some(mv.to());
+    }
+    Ok(formatted)
+}
+
+/// Normalize KI2 input: drop side marks and spaces, unify digit and kanji variants.
+/// A digit directly after the file digit is read as the rank ("76歩" == "７六歩").
+fn normalize_ki2(text: &str) -> String {
+    let mut normalized = String::new();
+    let mut previous_was_file = false;
+    for c in text.chars() {
+        if matches!(c, '▲' | '△' | '☗' | '☖' | '▽' | ' ' | '　') {
+            continue;
+        }
+        let digit = match c {
+            '1'..='9' => Some((c as u32 - '1' as u32) as usize),
+            '１'..='９' => Some((c as u32 - '１' as u32) as usize),
+            _ => None,
+        };
+        match digit {
+            Some(index) if previous_was_file => {
+                normalized.push(KANJI_NUMERALS[index]);
+                previous_was_file = false;
+            }
+            Some(index) => {
+                normalized.push(FULLWIDTH_DIGITS[index]);
+                previous_was_file = true;
+            }
+            None => {
+                normalized.push(match c {
+                    '王' => '玉',
+                    '竜' => '龍',
+                    '仝' => '同',
+                    other => other,
+                });
+                previous_was_file = false;
+            }
+        }
+    }
+    normalized
+}
+
+fn normalize_western(text: &str) -> String {
+    text.trim().replace('x', "-")
+}
+
+/// Parse a move written in the given notation into a legal move of `position`
+pub fn parse_move(position: &Position, text: &str, previous_to: Option<Square>, style: NotationStyle) -> Result<Move> {
+    let text = text.trim();
+    let exact = match style {
+        NotationStyle::Usi => Some(Move::from_usi(text)?),
+        NotationStyle::Csa => Some(csa_to_usi(position, text)?),
+        _ => None,
+    };
+    if let Some(mv) = exact {
+        if !position.is_legal(&mv) {
+            return Err(anyhow!("Illegal move: {}", text));
+        }
+        return Ok(mv);
+    }
+
+    let wanted = match style {
+        NotationStyle::Ki2 | NotationStyle::Kif => normalize_ki2(text),
+        _ => normalize_western(text),
+    };
+
+    let mut matches: Vec<Move> = Vec::new();
+    for mv in position.legal_moves() {
+        let spellings = match style {
+            NotationStyle::Ki2 => {
+                let mut spellings = vec![
+                    normalize_ki2(&format_ki2(position, &mv, previous_to, false)?),
+                    // Writing the explicit square instead of 同 is also accepted
+                    normalize_ki2(&format_ki2(position, &mv, None, false)?),
+                ];
+                if let Move::Drop { .. } = mv {
+                    // 打 written even when not strictly required
+                    let plain = spellings[1].clone();
+                    spellings.push(format!("{}打", plain.trim_end_matches('打')));
+                }
+                spellings
+            }
+            NotationStyle::Kif => vec![
+                normalize_ki2(&format_kif_move(position, &mv, previous_to)?),
+                normalize_ki2(&format_kif_move(position, &mv, None)?),
+            ],
+            _ => vec![
+                normalize_western(&format_western(position, &mv, false)?),
+                // A redundant origin square is accepted as well ("P7g-7f")
+                normalize_western(&format_western(position, &mv, true)?),
+            ],
+        };
+        if spellings.contains(&wanted) && !matches.contains(&mv) {
+            matches.push(mv);
+        }
+    }
+
+    match matches.len() {
+        0 => Err(anyhow!("No legal move matches '{}'", text)),
+        1 => Ok(matches[0]),
+        _ => Err(anyhow!("Ambiguous move '{}' ({} candidates)", text, matches.len())),
+    }
+}
+
+/// Parse a sequence of moves in the given notation and return them as USI strings
+pub fn parse_moves(position: &Position, texts: &[String], style: NotationStyle) -> Result<Vec<String>> {
+    let mut position = position.clone();
+    let mut previous_to = None;
+    let mut moves = Vec::with_capacity(texts.len());
+    for (index, text) in texts.iter().enumerate() {
+        let mv = parse_move(&position, text, previous_to, style)
+            .map_err(|e| anyhow!("Move {}: {}", index + 1, e))?;
+        moves.push(mv.to_usi());
+        position.apply_move_unchecked(&mv);
+        previous_to = Some(mv.to());
+    }
+    Ok(moves)
+}
+
+/// Rewrite moves played from `position` from one notation into another
+pub fn convert_moves(position: &Position, texts: &[String], from: NotationStyle, to: NotationStyle) -> Result<Vec<String>> {
+    let usi_moves = parse_moves(position, texts, from)?;
+    format_moves(position, &usi_moves, to)
+}
+
+/// Two-letter CSA code of a piece, e.g. "FU" or "UM"
+pub fn csa_piece_code(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::Pawn => "FU",
+        PieceType::Lance => "KY",
+        PieceType::Knight => "KE",
+        PieceType::Silver => "GI",
+        PieceType::Gold => "KI",
+        PieceType::Bishop => "KA",
+        PieceType::Rook => "HI",
+        PieceType::King => "OU",
+        PieceType::PromotedPawn => "TO",
+        PieceType::PromotedLance => "NY",
+        PieceType::PromotedKnight => "NK",
+        PieceType::PromotedSilver => "NG",
+        PieceType::Horse => "UM",
+        PieceType::Dragon => "RY",
+    }
+}
+
+pub fn parse_csa_piece(code: &str) -> Option<PieceType> {
+    [
+        PieceType::Pawn,
+        PieceType::Lance,
+        PieceType::Knight,
+        PieceType::Silver,
+        PieceType::Gold,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::King,
+        PieceType::PromotedPawn,
+        PieceType::PromotedLance,
+        PieceType::PromotedKnight,
+        PieceType::PromotedSilver,
+        PieceType::Horse,
+        PieceType::Dragon,
+    ]
+    .into_iter()
+    .find(|&piece_type| csa_piece_code(piece_type) == code)
+}
+
+fn csa_sign(color: Color) -> char {
+    match color {
+        Color::Black => '+',
+        Color::White => '-',
+    }
+}
+
+/// Square from two CSA digits ("77"); "00" (a drop) gives None
+pub fn parse_csa_square(digits: &str) -> Result<Option<Square>> {
+    let mut chars = digits.chars();
+    let (file, rank) = match (chars.next().and_then(|c| c.to_digit(10)), chars.next().and_then(|c| c.to_digit(10))) {
+        (Some(file), Some(rank)) => (file as u8, rank as u8),
+        _ => return Err(anyhow!("Invalid CSA square: {}", digits)),
+    };
+    if (file, rank) == (0, 0) {
+        return Ok(None);
+    }
+    Square::new(file, rank)
+        .map(Some)
+        .ok_or_else(|| anyhow!("Invalid CSA square: {}", digits))
+}
+
+/// Convert a CSA move such as "+7776FU" or "-0055KA" (a trailing ",T<n>" is ignored)
+pub fn csa_to_usi(position: &Position, csa: &str) -> Result<Move> {
+    let csa = csa.split(',').next().unwrap_or(csa).trim();
+    if csa.len() != 7 || !csa.is_ascii() {
+        return Err(anyhow!("Invalid CSA move: {}", csa));
+    }
+    if !csa.starts_with(csa_sign(position.side_to_move())) {
+        return Err(anyhow!("CSA move {} is for the side not to move", csa));
+    }
+    let from = parse_csa_square(&csa[1..3])?;
+    let to = parse_csa_square(&csa[3..5])?.ok_or_else(|| anyhow!("Invalid CSA move: {}", csa))?;
+    let piece_type = parse_csa_piece(&csa[5..7]).ok_or_else(|| anyhow!("Invalid CSA piece in move: {}", csa))?;
+
+    match from {
+        None => Ok(Move::Drop { piece_type, to }),
+        Some(from) => {
+            let moving = position
+                .piece_at(from)
+                .ok_or_else(|| anyhow!("No piece on the from-square of CSA move {}", csa))?;
+            // CSA names the piece after the move, so a change to a promoted piece is a promotion
+            let promote = !moving.piece_type.is_promoted() && piece_type.is_promoted();
+            Ok(Move::Normal { from, to, promote })
+        }
+    }
+}
+
+/// Convert a move in `position` to CSA notation, e.g. "+2822UM"
+pub fn usi_to_csa(position: &Position, mv: &Move) -> Result<String> {
+    let sign = csa_sign(position.side_to_move());
+    match *mv {
+        Move::Drop { piece_type, to } => Ok(format!("{}00{}{}{}", sign, to.file, to.rank, csa_piece_code(piece_type))),
+        Move::Normal { from, to, promote } => {
+            let piece = position
+                .piece_at(from)
+                .ok_or_else(|| anyhow!("No piece on {} for move {}", from.to_usi(), mv.to_usi()))?;
+            let piece_type = if promote {
+                piece.piece_type.promote().ok_or_else(|| anyhow!("Piece on {} cannot promote", from.to_usi()))?
+            } else {
+                piece.piece_type
+            };
+            Ok(format!("{}{}{}{}{}{}", sign, from.file, from.rank, to.file, to.rank, csa_piece_code(piece_type)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usi_moves(moves: &[&str]) -> Vec<String> {
+        moves.iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn test_ki2_basic_and_same_square() {
+        let moves = usi_moves(&["7g7f", "3c3d", "8h2b+", "3a2b"]);
+        let ki2 = format_moves(&Position::default(), &moves, NotationStyle::Ki2).unwrap();
+        assert_eq!(ki2, vec!["▲７六歩", "△３四歩", "▲２二角成", "△同　銀"]);
+    }
+
+    #[test]
+    fn test_ki2_disambiguation() {
+        // Both golds can reach 5h: 6i is to Black's left, 4i to Black's right
+        let position = Position::default();
+        let right = format_move(&position, &Move::from_usi("4i5h").unwrap(), None, NotationStyle::Ki2).unwrap();
+        let left = format_move(&position, &Move::from_usi("6i5h").unwrap(), None, NotationStyle::Ki2).unwrap();
+        assert_eq!(right, "▲５八金右");
+        assert_eq!(left, "▲５八金左");
+    }
+
+    #[test]
+    fn test_ki2_drop_and_non_promotion() {
+        let position = Position::from_sfen("4k4/9/9/9/9/9/9/4G4/4K4 b G 1").unwrap();
+        let drop = format_move(&position, &Move::from_usi("G*5g").unwrap(), None, NotationStyle::Ki2).unwrap();
+        assert_eq!(drop, "▲５七金打");
+        let drop_far = format_move(&position, &Move::from_usi("G*5b").unwrap(), None, NotationStyle::Ki2).unwrap();
+        assert_eq!(drop_far, "▲５二金");
+
+        let silver = Position::from_sfen("4k4/9/9/4S4/9/9/9/9/4K4 b - 1").unwrap();
+        let declined = format_move(&silver, &Move::from_usi("5d5c").unwrap(), None, NotationStyle::Ki2).unwrap();
+        assert_eq!(declined, "▲５三銀不成");
+    }
+
+    #[test]
+    fn test_western_notation() {
+        let moves = usi_moves(&["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]);
+        let western = format_moves(&Position::default(), &moves, NotationStyle::Western).unwrap();
+        assert_eq!(western, vec!["P-7f", "P-3d", "Bx2b+", "Sx2b", "B*4e"]);
+
+        let gold = format_move(&Position::default(), &Move::from_usi("6i5h").unwrap(), None, NotationStyle::Western).unwrap();
+        assert_eq!(gold, "G6i-5h");
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let moves = usi_moves(&["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]);
+        let position = Position::default();
+        for style in [NotationStyle::Ki2, NotationStyle::Western] {
+            let formatted = format_moves(&position, &moves, style).unwrap();
+            assert_eq!(parse_moves(&position, &formatted, style).unwrap(), moves);
+        }
+        let typed = usi_moves(&["76歩", "3四歩", "22角成", "同銀"]);
+        assert_eq!(parse_moves(&position, &typed, NotationStyle::Ki2).unwrap(), moves[..4].to_vec());
+        assert!(parse_moves(&position, &usi_moves(&["58金"]), NotationStyle::Ki2).is_err());
+    }
+
+    #[test]
+    fn test_csa_and_kif_conversion() {
+        let moves = usi_moves(&["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"]);
+        let position = Position::default();
+        let csa = format_moves(&position, &moves, NotationStyle::Csa).unwrap();
+        assert_eq!(csa, vec!["+7776FU", "-3334FU", "+8822UM", "-3122GI", "+0045KA"]);
+        let kif = convert_moves(&position, &csa, NotationStyle::Csa, NotationStyle::Kif).unwrap();
+        assert_eq!(kif, vec!["７六歩(77)", "３四歩(33)", "２二角成(88)", "同　銀(31)", "４五角打"]);
+        assert_eq!(convert_moves(&position, &kif, NotationStyle::Kif, NotationStyle::Usi).unwrap(), moves);
+        assert!(parse_moves(&position, &usi_moves(&["-3334FU"]), NotationStyle::Csa).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Opening books built from stored games
+//! Every move played from a position in the selected games counts towards that position's
+//! entry, weighted by how the game went for the side that played it: two points for a win,
+//! one for a draw. The app keeps books as JSON; [`to_yaneuraou`] writes the same book as a
+//! YaneuraOu book (YANEURAOU-DB2016), which most USI engines can load as their `BookFile`.
+
+use crate::game_record::GameRecord;
+use crate::shogi_rules::{Color, Position};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Version of the JSON book format
+pub const BOOK_VERSION: u32 = 1;
+
+const YANEURAOU_HEADER: &str = "#YANEURAOU-DB2016 1.00";
+
+/// Which stored games go into a book. Unset fields do not filter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BookFilter {
+    /// Only these records
+    #[serde(default)]
+    pub record_ids: Option<Vec<String>>,
+    /// Only games this player (engine display name) played, on either side
+    #[serde(default)]
+    pub player: Option<String>,
+    /// Only games created at or after this RFC 3339 time
+    #[serde(default)]
+    pub since: Option<String>,
+    /// Only games created before this RFC 3339 time
+    #[serde(default)]
+    pub until: Option<String>,
+}
+
+impl BookFilter {
+    pub fn matches(&self, record: &GameRecord) -> bool {
+        self.record_ids.as_ref().map_or(true, |ids| ids.contains(&record.id))
+            && self
+                .player
+                .as_ref()
+                .map_or(true, |player| &record.black_name == player || &record.white_name == player)
+            && self.since.as_ref().map_or(true, |since| record.created_at.as_str() >= since.as_str())
+            && self.until.as_ref().map_or(true, |until| record.created_at.as_str() < until.as_str())
+    }
+}
+
+/// A move of a book position with the results of the games it was played in
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BookMove {
+    pub usi: String,
+    /// Points for the side that played it: 2 per win, 1 per draw
+    pub weight: u32,
+    pub count: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Average score the player reported for the move, in centipawns
+    #[serde(default)]
+    pub score: Option<i32>,
+    #[serde(default)]
+    pub depth: Option<u32>,
+    #[serde(skip)]
+    score_sum: (i64, u32),
+    #[serde(skip)]
+    depth_sum: (u64, u32),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpeningBook {
+    pub version: u32,
+    pub created_at: String,
+    /// Games the book was built from
+    pub games: usize,
+    /// Moves by position, keyed by SFEN without the move number; heaviest move first
+    pub positions: BTreeMap<String, Vec<BookMove>>,
+}
+
+/// SFEN without its move number, so transpositions share an entry
+pub fn position_key(position: &Position) -> String {
+    let sfen = position.to_sfen();
+    match sfen.rsplit_once(' ') {
+        Some((key, _)) => key.to_string(),
+        None => sfen,
+    }
+}
+
+/// Aggregate the first `max_depth` moves of every decided or drawn game into a book, keeping
+/// moves played at least `min_count` times. Games without a result are left out.
+pub fn build(records: &[GameRecord], min_count: u32, max_depth: usize) -> OpeningBook {
+    let mut positions: BTreeMap<String, Vec<BookMove>> = BTreeMap::new();
+    let mut games = 0;
+    for record in records {
+        let winner = match record.winner.as_deref() {
+            Some("black") => Some(Color::Black),
+            Some("white") => Some(Color::White),
+            Some("draw") => None,
+            _ => continue,
+        };
+        let Ok(mut position) = Position::from_sfen(&record.initial_sfen) else {
+            log::warn!("Skipping game {} with an invalid start position", record.id);
+            continue;
+        };
+        games += 1;
+
+        for recorded in record.moves.iter().take(max_depth) {
+            let mover = position.side_to_move();
+            let key = position_key(&position);
+            if position.apply_usi_move(&recorded.usi).is_err() {
+                log::warn!("Game {} has an illegal move {}; using the moves before it", record.id, recorded.usi);
+                break;
+            }
+            let moves = positions.entry(key).or_default();
+            let index = match moves.iter().position(|book_move| book_move.usi == recorded.usi) {
+                Some(index) => index,
+                None => {
+                    moves.push(BookMove { usi: recorded.usi.clone(), ..Default::default() });
+                    moves.len() - 1
+                }
+            };
+            let book_move = &mut moves[index];
+            book_move.count += 1;
+            match winner {
+                Some(color) if color == mover => {
+                    book_move.wins += 1;
+                    book_move.weight += 2;
+                }
+                Some(_) => book_move.losses += 1,
+                None => {
+                    book_move.draws += 1;
+                    book_move.weight += 1;
+                }
+            }
+            if let Some(crate::usi_info::Score::Cp(cp)) = recorded.score {
+                book_move.score_sum = (book_move.score_sum.0 + cp as i64, book_move.score_sum.1 + 1);
+            }
+            if let Some(depth) = recorded.depth {
+                book_move.depth_sum = (book_move.depth_sum.0 + depth as u64, book_move.depth_sum.1 + 1);
+            }
+        }
+    }
+
+    for moves in positions.values_mut() {
+        moves.retain(|book_move| book_move.count >= min_count);
+        for book_move in moves.iter_mut() {
+            let (score_sum, scores) = book_move.score_sum;
+            book_move.score = (scores > 0).then(|| (score_sum / scores as i64) as i32);
+            let (depth_sum, depths) = book_move.depth_sum;
+            book_move.depth = (depths > 0).then(|| (depth_sum / depths as u64) as u32);
+        }
+        moves.sort_by(|a, b| b.weight.cmp(&a.weight).then(b.count.cmp(&a.count)).then(a.usi.cmp(&b.usi)));
+    }
+    positions.retain(|_, moves| !moves.is_empty());
+
+    OpeningBook {
+        version: BOOK_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        games,
+        positions,
+    }
+}
+
+impl OpeningBook {
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        tokio::fs::write(path, serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+
+    pub async fn load(path: &Path) -> Result<Self> {
+        let contents = tokio::fs::read_to_string(path).await?;
+        let book: OpeningBook = serde_json::from_str(&contents)?;
+        if book.version > BOOK_VERSION {
+            return Err(anyhow!("Book version {} is newer than this app supports", book.version));
+        }
+        Ok(book)
+    }
+
+    /// Moves listed in total
+    pub fn move_count(&self) -> usize {
+        self.positions.values().map(Vec::len).sum()
+    }
+}
+
+/// The book in YaneuraOu's text format: positions sorted by SFEN, each followed by its moves
+/// as `move ponder score depth weight`. The ponder move is the book's top reply, if any.
+pub fn to_yaneuraou(book: &OpeningBook) -> String {
+    let mut out = format!("{}\n", YANEURAOU_HEADER);
+    for (key, moves) in &book.positions {
+        let Ok(position) = Position::from_sfen(key) else {
+            continue;
+        };
+        // The move number does not affect lookups; YaneuraOu writes one anyway
+        out += &format!("sfen {} 1\n", key);
+        for book_move in moves {
+            let mut next = position.clone();
+            let ponder = match next.apply_usi_move(&book_move.usi) {
+                Ok(_) => book.positions.get(&position_key(&next)).and_then(|replies| replies.first()),
+                Err(_) => None,
+            };
+            out += &format!(
+                "{} {} {} {} {}\n",
+                book_move.usi,
+                ponder.map_or("none", |reply| reply.usi.as_str()),
+                book_move.score.unwrap_or(0),
+                book_move.depth.unwrap_or(0),
+                book_move.weight
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shogi_rules::STARTPOS_SFEN;
+
+    fn game(moves: &[&str], winner: &str) -> GameRecord {
+        serde_json::from_value(serde_json::json!({
+            "id": uuid::Uuid::new_v4().to_string(),
+            "black_name": "A",
+            "white_name": "B",
+            "initial_sfen": STARTPOS_SFEN,
+            "moves": moves.iter().map(|usi| serde_json::json!({ "usi": usi })).collect::<Vec<_>>(),
+            "winner": winner,
+            "result": null,
+            "created_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_build_weights_moves_by_result() {
+        let records = vec![
+            game(&["7g7f", "3c3d", "2g2f"], "black"),
+            game(&["7g7f", "8c8d"], "white"),
+            game(&["2g2f", "8c8d"], "draw"),
+            game(&["2g2f"], "unknown"),
+        ];
+        let book = build(&records, 1, 2);
+        assert_eq!(book.games, 3);
+        let start = &book.positions[&position_key(&Position::default())];
+        assert_eq!(start[0].usi, "7g7f");
+        assert_eq!((start[0].count, start[0].wins, start[0].losses, start[0].weight), (2, 1, 1, 2));
+        assert_eq!((start[1].usi.as_str(), start[1].weight), ("2g2f", 1));
+        // max_depth leaves out the third move of the first game
+        assert_eq!(book.move_count(), 5);
+
+        assert_eq!(build(&records, 2, 2).move_count(), 1);
+
+        let db = to_yaneuraou(&book);
+        assert!(db.starts_with("#YANEURAOU-DB2016 1.00\nsfen "));
+        // White's best reply to 7g7f is the one it won with
+        assert!(db.contains("\n7g7f 8c8d 0 0 2\n"));
+    }
+}
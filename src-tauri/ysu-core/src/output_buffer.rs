@@ -0,0 +1,220 @@
+//! Buffering and rate limiting between an engine's stdout and event emission
+//! Engines searching deep can print thousands of `info` lines a second. The reader queues lines
+//! in a bounded buffer that drops the oldest `info` lines when full, and the emitter sends at most
+//! `max_info_per_sec` of them, coalescing the rest into the newest one. Everything else
+//! (`bestmove`, `readyok`, `info string`, ...) is always emitted, in order.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+fn default_max_info_per_sec() -> u32 {
+    50
+}
+
+fn default_buffer_lines() -> usize {
+    1000
+}
+
+fn default_snapshot_interval_ms() -> u64 {
+    100
+}
+
+/// How engine output is forwarded to the event sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct OutputThrottle {
+    /// Most search `info` lines emitted per second for one engine; 0 emits all of them
+    #[serde(default = "default_max_info_per_sec")]
+    pub max_info_per_sec: u32,
+    /// Lines queued between reader and emitter before the oldest `info` lines are dropped
+    #[serde(default = "default_buffer_lines")]
+    pub buffer_lines: usize,
+    /// Shortest time between two `analysis-snapshot` events of one engine; 0 sends none, for
+    /// frontends that poll the latest analysis instead
+    #[serde(default = "default_snapshot_interval_ms")]
+    pub snapshot_interval_ms: u64,
+}
+
+impl Default for OutputThrottle {
+    fn default() -> Self {
+        Self {
+            max_info_per_sec: default_max_info_per_sec(),
+            buffer_lines: default_buffer_lines(),
+            snapshot_interval_ms: default_snapshot_interval_ms(),
+        }
+    }
+}
+
+/// Search info that may be dropped or coalesced; `info string` messages are kept
+pub fn is_search_info(line: &str) -> bool {
+    line.starts_with("info ") && !line.starts_with("info string")
+}
+
+/// Lines read from an engine and not yet emitted
+#[derive(Debug)]
+pub struct OutputBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+    dropped: u64,
+    /// The engine's stdout has ended
+    pub closed: bool,
+}
+
+impl OutputBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            capacity: capacity.max(1),
+            dropped: 0,
+            closed: false,
+        }
+    }
+
+    /// Queue a line. When the buffer is full the oldest search info line makes room;
+    /// other lines are never dropped, so the buffer may grow past capacity with them.
+    pub fn push(&mut self, line: String) {
+        if self.lines.len() >= self.capacity {
+            if let Some(index) = self.lines.iter().position(|queued| is_search_info(queued)) {
+                self.lines.remove(index);
+                self.dropped += 1;
+            } else if is_search_info(&line) {
+                self.dropped += 1;
+                return;
+            }
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn drain(&mut self) -> Vec<String> {
+        self.lines.drain(..).collect()
+    }
+
+    /// Lines dropped because the emitter fell behind
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// Limits search info lines per one-second window, keeping the newest line over the limit
+#[derive(Debug)]
+pub struct InfoRateLimiter {
+    max_per_sec: u32,
+    window_start: Instant,
+    sent_in_window: u32,
+    pending: Option<String>,
+    coalesced: u64,
+}
+
+impl InfoRateLimiter {
+    pub fn new(max_per_sec: u32, now: Instant) -> Self {
+        Self {
+            max_per_sec,
+            window_start: now,
+            sent_in_window: 0,
+            pending: None,
+            coalesced: 0,
+        }
+    }
+
+    fn roll_window(&mut self, now: Instant, out: &mut Vec<String>) {
+        if now.duration_since(self.window_start) >= WINDOW {
+            self.window_start = now;
+            self.sent_in_window = 0;
+            if let Some(pending) = self.pending.take() {
+                self.sent_in_window = 1;
+                out.push(pending);
+            }
+        }
+    }
+
+    /// The lines to emit now for `line`, in order
+    pub fn admit(&mut self, line: String, now: Instant) -> Vec<String> {
+        let mut out = Vec::new();
+        self.roll_window(now, &mut out);
+        if !is_search_info(&line) {
+            // The latest search info belongs before bestmove and other replies
+            out.extend(self.pending.take());
+            out.push(line);
+        } else if self.max_per_sec == 0 || self.sent_in_window < self.max_per_sec {
+            self.sent_in_window += 1;
+            out.push(line);
+        } else if self.pending.replace(line).is_some() {
+            self.coalesced += 1;
+        }
+        out
+    }
+
+    /// A held-back line whose window has passed
+    pub fn poll(&mut self, now: Instant) -> Option<String> {
+        let mut out = Vec::new();
+        self.roll_window(now, &mut out);
+        out.pop()
+    }
+
+    /// When `poll` should be called next, if a line is held back
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|_| self.window_start + WINDOW)
+    }
+
+    /// Release the held-back line regardless of the limit, e.g. when the engine exits
+    pub fn flush(&mut self) -> Option<String> {
+        self.pending.take()
+    }
+
+    /// Info lines replaced by newer ones before they could be emitted
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(depth: u32) -> String {
+        format!("info depth {} score cp 10 pv 7g7f", depth)
+    }
+
+    #[test]
+    fn test_buffer_drops_oldest_info() {
+        let mut buffer = OutputBuffer::new(3);
+        buffer.push(info(1));
+        buffer.push("readyok".to_string());
+        buffer.push(info(2));
+        buffer.push(info(3));
+        assert_eq!(buffer.drain(), vec!["readyok".to_string(), info(2), info(3)]);
+        assert_eq!(buffer.dropped(), 1);
+
+        for line in ["id name A", "usiok", "readyok", "bestmove 7g7f"] {
+            buffer.push(line.to_string());
+        }
+        buffer.push(info(4));
+        assert_eq!(buffer.drain().len(), 4);
+        assert_eq!(buffer.dropped(), 2);
+    }
+
+    #[test]
+    fn test_rate_limiter_coalesces_info() {
+        let start = Instant::now();
+        let mut limiter = InfoRateLimiter::new(2, start);
+        assert_eq!(limiter.admit(info(1), start), vec![info(1)]);
+        assert_eq!(limiter.admit(info(2), start), vec![info(2)]);
+        assert!(limiter.admit(info(3), start).is_empty());
+        assert!(limiter.admit(info(4), start).is_empty());
+        assert_eq!(limiter.coalesced(), 1);
+        assert_eq!(limiter.next_deadline(), Some(start + WINDOW));
+        assert_eq!(limiter.admit("info string loading".to_string(), start), vec![info(4), "info string loading".to_string()]);
+
+        assert!(limiter.admit(info(5), start).is_empty());
+        assert_eq!(limiter.poll(start + Duration::from_millis(500)), None);
+        assert_eq!(limiter.poll(start + WINDOW), Some(info(5)));
+        assert_eq!(limiter.next_deadline(), None);
+
+        let mut unlimited = InfoRateLimiter::new(0, start);
+        assert!((1..=100).all(|depth| unlimited.admit(info(depth), start).len() == 1));
+    }
+}
@@ -0,0 +1,345 @@
+//! Matched-pair games from a list of start positions, scored per position
+//! Overall Elo says whether a change helps on average, but not where: an endgame fix can
+//! vanish in games decided in the middlegame. Here two engines play each listed position
+//! twice, once with each colour, so a position that favours one side counts the same for
+//! both engines. The report gives the score of engine A per position with a 95% confidence
+//! interval, and overall as pentanomial Elo, error bars and LOS.
+
+use crate::adjudication::AdjudicationConfig;
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_vs_engine::{EngineVsEngineConfig, EngineVsEngineManager, SearchLimit};
+use crate::events::{SharedEventSink, POSITION_MATCH_PROGRESS};
+use crate::series_stats::{SeriesStats, Z_95};
+use crate::shogi_rules::parse_position;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+fn default_max_moves() -> usize {
+    256
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionMatchConfig {
+    /// Config ID of the engine the scores are reported for
+    pub engine_a_id: String,
+    pub engine_b_id: String,
+    /// Start positions, each an SFEN or `startpos`, optionally followed by moves
+    pub positions: Vec<String>,
+    pub time_per_move_ms: u64,
+    #[serde(default)]
+    pub search_limit: SearchLimit,
+    #[serde(default = "default_max_moves")]
+    pub max_moves: usize,
+    #[serde(default)]
+    pub adjudication: AdjudicationConfig,
+}
+
+impl PositionMatchConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.engine_a_id == self.engine_b_id {
+            return Err(anyhow!("The two engines must be different"));
+        }
+        if self.positions.is_empty() {
+            return Err(anyhow!("No positions given"));
+        }
+        if self.max_moves == 0 {
+            return Err(anyhow!("max_moves must be at least 1"));
+        }
+        for (index, spec) in self.positions.iter().enumerate() {
+            resolve_position(spec).map_err(|e| anyhow!("Position {}: {}", index + 1, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// The position `spec` ends in, as an SFEN
+fn resolve_position(spec: &str) -> Result<String> {
+    let (mut position, moves) = parse_position(spec)?;
+    for mv in &moves {
+        position.apply_move(mv)?;
+    }
+    Ok(position.to_sfen())
+}
+
+/// One game of a pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct PairGame {
+    /// "black" or "white"
+    pub engine_a_color: String,
+    /// Points engine A scored: 1, 0.5 or 0. None if the game could not be played.
+    pub score: Option<f64>,
+    pub match_id: Option<String>,
+    pub plies: usize,
+    pub error: Option<String>,
+}
+
+/// Score of engine A over a set of games
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ScoreSummary {
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// Points per game, from 0 to 1
+    pub score: f64,
+    /// 95% confidence interval of the score
+    pub score_low: f64,
+    pub score_high: f64,
+    /// Elo difference the score corresponds to; None for a score of 0 or 1
+    pub elo: Option<f64>,
+}
+
+impl ScoreSummary {
+    /// Summarize the games of one position, with the interval from their spread
+    fn from_games(games: &[f64]) -> Self {
+        let count = |value: f64| games.iter().filter(|&&score| score == value).count();
+        let mut summary = ScoreSummary {
+            games: games.len(),
+            wins: count(1.0),
+            draws: count(0.5),
+            losses: count(0.0),
+            ..Default::default()
+        };
+        if games.is_empty() {
+            return summary;
+        }
+        let n = games.len() as f64;
+        let mean = games.iter().sum::<f64>() / n;
+        let variance = games.iter().map(|game| (game - mean).powi(2)).sum::<f64>() / n;
+        let margin = Z_95 * (variance / n).sqrt();
+        summary.score = mean;
+        summary.score_low = (mean - margin).max(0.0);
+        summary.score_high = (mean + margin).min(1.0);
+        summary.elo = crate::series_stats::elo_from_score(mean);
+        summary
+    }
+}
+
+/// Both games of one start position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct PositionResult {
+    pub index: usize,
+    /// The position as given
+    pub position: String,
+    pub games: Vec<PairGame>,
+    pub summary: ScoreSummary,
+}
+
+impl PositionResult {
+    fn scores(&self) -> Vec<f64> {
+        self.games.iter().filter_map(|game| game.score).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionMatchReport {
+    pub engine_a_id: String,
+    pub engine_a_name: String,
+    pub engine_b_id: String,
+    pub engine_b_name: String,
+    pub positions: Vec<PositionResult>,
+    /// Over the positions where both games were played
+    pub overall: SeriesStats,
+}
+
+/// Emitted as `position-match-progress` after each position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct PositionMatchProgress {
+    pub index: usize,
+    pub total: usize,
+    pub result: PositionResult,
+    /// Aggregate over the positions played so far
+    pub overall: SeriesStats,
+}
+
+fn overall_summary(results: &[PositionResult]) -> SeriesStats {
+    let pairs: Vec<(f64, f64)> = results
+        .iter()
+        .map(PositionResult::scores)
+        .filter(|scores| scores.len() == 2)
+        .map(|scores| (scores[0], scores[1]))
+        .collect();
+    SeriesStats::from_pairs(&pairs)
+}
+
+fn display_name(engine: &EngineConfig) -> String {
+    if engine.display_name.is_empty() { engine.name.clone() } else { engine.display_name.clone() }
+}
+
+fn game_config(config: &PositionMatchConfig, black: &EngineConfig, white: &EngineConfig, sfen: &str) -> EngineVsEngineConfig {
+    EngineVsEngineConfig {
+        engine1_id: black.id.clone(),
+        engine1_path: black.path.clone(),
+        engine1_name: display_name(black),
+        engine2_id: white.id.clone(),
+        engine2_path: white.path.clone(),
+        engine2_name: display_name(white),
+        initial_sfen: Some(sfen.to_string()),
+        handicap: None,
+        time_per_move_ms: config.time_per_move_ms,
+        search_limit: config.search_limit,
+        max_moves: config.max_moves,
+        adjudication: config.adjudication.clone(),
+        cpu_affinity: None,
+        engine1_priority: black.priority,
+        engine2_priority: white.priority,
+        engine1_sha256: black.binary_sha256.clone(),
+        engine2_sha256: white.binary_sha256.clone(),
+        engine1_options: None,
+        engine2_options: None,
+        engine1_eval_file_id: None,
+        engine2_eval_file_id: None,
+        seed: None,
+        openings: Vec::new(),
+        move_delay_ms: 0,
+        engine1_time_ms: None,
+        engine2_time_ms: None,
+        opening_moves: Vec::new(),
+        record_thinking: false,
+    }
+}
+
+/// Play every position twice, engine A taking Black in the first game and White in the
+/// second, and emit `position-match-progress` after each position. A game that fails is
+/// reported with its error and left out of the scores.
+pub async fn run_position_match(
+    events: SharedEventSink,
+    engine_storage: Arc<RwLock<EngineStorage>>,
+    config: PositionMatchConfig,
+) -> Result<PositionMatchReport> {
+    config.validate()?;
+    let (engine_a, engine_b) = {
+        let storage = engine_storage.read().await;
+        (storage.usable_engine(&config.engine_a_id)?.clone(), storage.usable_engine(&config.engine_b_id)?.clone())
+    };
+
+    let mut results = Vec::with_capacity(config.positions.len());
+    for (index, spec) in config.positions.iter().enumerate() {
+        let sfen = resolve_position(spec)?;
+        let mut games = Vec::with_capacity(2);
+        for (black, white, engine_a_color) in [(&engine_a, &engine_b, "black"), (&engine_b, &engine_a, "white")] {
+            let manager = EngineVsEngineManager::new(
+                events.clone(),
+                game_config(&config, black, white, &sfen),
+                engine_storage.clone(),
+            );
+            games.push(match manager.run_match().await {
+                Ok(state) => PairGame {
+                    engine_a_color: engine_a_color.to_string(),
+                    score: Some(match state.winner.as_deref() {
+                        Some(winner) if winner == engine_a_color => 1.0,
+                        Some("black") | Some("white") => 0.0,
+                        _ => 0.5,
+                    }),
+                    match_id: Some(state.match_id),
+                    plies: state.move_history.len(),
+                    error: None,
+                },
+                Err(e) => {
+                    log::warn!("Position {} with engine A as {} failed: {}", index + 1, engine_a_color, e);
+                    PairGame {
+                        engine_a_color: engine_a_color.to_string(),
+                        score: None,
+                        match_id: None,
+                        plies: 0,
+                        error: Some(e.to_string()),
+                    }
+                }
+            });
+        }
+
+        let scores: Vec<f64> = games.iter().filter_map(|game| game.score).collect();
+        let result = PositionResult {
+            index,
+            position: spec.clone(),
+            summary: ScoreSummary::from_games(&scores),
+            games,
+        };
+        log::info!("Position match {}/{}: engine A scored {:?}", index + 1, config.positions.len(), scores);
+        results.push(result.clone());
+        let _ = events.send(&POSITION_MATCH_PROGRESS, &PositionMatchProgress {
+            index,
+            total: config.positions.len(),
+            result,
+            overall: overall_summary(&results),
+        });
+    }
+
+    Ok(PositionMatchReport {
+        engine_a_id: engine_a.id.clone(),
+        engine_a_name: display_name(&engine_a),
+        engine_b_id: engine_b.id.clone(),
+        engine_b_name: display_name(&engine_b),
+        overall: overall_summary(&results),
+        positions: results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(scores: &[Option<f64>]) -> PositionResult {
+        PositionResult {
+            index: 0,
+            position: "startpos".to_string(),
+            games: scores
+                .iter()
+                .map(|&score| PairGame {
+                    engine_a_color: "black".to_string(),
+                    score,
+                    match_id: None,
+                    plies: 0,
+                    error: None,
+                })
+                .collect(),
+            summary: ScoreSummary::default(),
+        }
+    }
+
+    #[test]
+    fn test_summaries() {
+        let results = [
+            result(&[Some(1.0), Some(0.5)]),
+            result(&[Some(1.0), Some(0.0)]),
+            result(&[Some(0.5), Some(0.5)]),
+            result(&[Some(1.0), None]),
+        ];
+        // Pair averages 0.75, 0.5 and 0.5; the unfinished pair is left out
+        let overall = overall_summary(&results);
+        assert_eq!((overall.games, overall.wins, overall.draws, overall.losses), (6, 2, 3, 1));
+        assert_eq!(overall.pentanomial, Some(vec![0, 0, 2, 1, 0]));
+        assert!((overall.elo.unwrap() - 58.451).abs() < 1e-3);
+
+        let won = ScoreSummary::from_games(&[1.0, 1.0]);
+        assert_eq!((won.score, won.score_low, won.score_high, won.elo), (1.0, 1.0, 1.0, None));
+        let split = ScoreSummary::from_games(&[1.0, 0.5]);
+        assert!((split.score_low - (0.75 - Z_95 * (0.0625f64 / 2.0).sqrt())).abs() < 1e-9);
+        // The interval is clamped to possible scores
+        assert_eq!(split.score_high, 1.0);
+        assert_eq!(ScoreSummary::from_games(&[]), ScoreSummary::default());
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = |a: &str, positions: &[&str]| PositionMatchConfig {
+            engine_a_id: a.to_string(),
+            engine_b_id: "b".to_string(),
+            positions: positions.iter().map(|position| position.to_string()).collect(),
+            time_per_move_ms: 100,
+            search_limit: SearchLimit::default(),
+            max_moves: 256,
+            adjudication: AdjudicationConfig::default(),
+        };
+        assert!(config("a", &["startpos moves 7g7f", "4k4/9/9/9/9/9/9/9/4K4 b 2R 1"]).validate().is_ok());
+        assert!(config("b", &["startpos"]).validate().is_err());
+        assert!(config("a", &[]).validate().is_err());
+        assert!(config("a", &["startpos moves 7g7e"]).validate().is_err());
+    }
+}
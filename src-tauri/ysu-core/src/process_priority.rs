@@ -0,0 +1,98 @@
+//! OS scheduling priority for engine processes
+//! Maps a portable priority level to a nice value on Unix and a priority class on Windows.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::process::{Child, Command};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessPriority {
+    Idle,
+    BelowNormal,
+    #[default]
+    Normal,
+    AboveNormal,
+    High,
+}
+
+impl ProcessPriority {
+    /// Unix nice value; negative values usually need elevated privileges
+    pub fn nice(self) -> i32 {
+        match self {
+            ProcessPriority::Idle => 19,
+            ProcessPriority::BelowNormal => 10,
+            ProcessPriority::Normal => 0,
+            ProcessPriority::AboveNormal => -5,
+            ProcessPriority::High => -10,
+        }
+    }
+}
+
+/// Configure a command so the spawned process starts at `priority`
+#[cfg(unix)]
+pub fn apply_before_spawn(command: &mut Command, priority: Option<ProcessPriority>) {
+    let Some(nice) = priority.map(ProcessPriority::nice).filter(|&nice| nice != 0) else {
+        return;
+    };
+    // Failure is ignored here and reported by `apply_after_spawn`, which can log
+    unsafe {
+        command.pre_exec(move || {
+            libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn apply_before_spawn(_command: &mut Command, _priority: Option<ProcessPriority>) {}
+
+/// Check that the spawned process really runs at `priority`
+#[cfg(unix)]
+pub fn apply_after_spawn(child: &Child, priority: Option<ProcessPriority>) -> Result<()> {
+    let Some(nice) = priority.map(ProcessPriority::nice).filter(|&nice| nice != 0) else {
+        return Ok(());
+    };
+    let Some(pid) = child.id() else {
+        return Err(anyhow::anyhow!("Engine process already exited"));
+    };
+    let actual = unsafe { libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t) };
+    if actual != nice {
+        return Err(anyhow::anyhow!(
+            "Could not set nice value {} (process runs at {}); raising priority usually needs elevated privileges",
+            nice,
+            actual
+        ));
+    }
+    Ok(())
+}
+
+/// Set the priority class of an already spawned process
+#[cfg(windows)]
+pub fn apply_after_spawn(child: &Child, priority: Option<ProcessPriority>) -> Result<()> {
+    use windows_sys::Win32::System::Threading::{
+        SetPriorityClass, ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS,
+        IDLE_PRIORITY_CLASS,
+    };
+
+    let class = match priority {
+        None | Some(ProcessPriority::Normal) => return Ok(()),
+        Some(ProcessPriority::Idle) => IDLE_PRIORITY_CLASS,
+        Some(ProcessPriority::BelowNormal) => BELOW_NORMAL_PRIORITY_CLASS,
+        Some(ProcessPriority::AboveNormal) => ABOVE_NORMAL_PRIORITY_CLASS,
+        Some(ProcessPriority::High) => HIGH_PRIORITY_CLASS,
+    };
+    let handle = child.raw_handle().ok_or_else(|| anyhow::anyhow!("Engine process already exited"))?;
+    if unsafe { SetPriorityClass(handle, class) } == 0 {
+        return Err(anyhow::anyhow!("Failed to set priority class: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn apply_after_spawn(_child: &Child, priority: Option<ProcessPriority>) -> Result<()> {
+    if priority.is_some_and(|p| p != ProcessPriority::Normal) {
+        log::warn!("Process priority is not supported on this platform; ignoring {:?}", priority);
+    }
+    Ok(())
+}
@@ -0,0 +1,578 @@
+//! Self-play training data generation
+//! One engine plays itself with a fixed node budget, game after game, and every position it
+//! searched is written with the score it found, its move and the game's outcome. A few random
+//! opening moves keep the games apart. Records use YaneuraOu's `PackedSfenValue` layout, which
+//! its learner and most NNUE trainers read directly:
+//!
+//! | bytes  | field                                                            |
+//! |--------|------------------------------------------------------------------|
+//! | 0-31   | position, Huffman coded (see [`pack_sfen`])                      |
+//! | 32-33  | score from the side to move, i16; mate in n is ±(32000 - n)      |
+//! | 34-35  | best move, YaneuraOu's 16-bit move (see [`move16`])              |
+//! | 36-37  | ply of the position, u16                                         |
+//! | 38     | outcome for the side to move: 1 win, 0 draw, -1 loss            |
+//! | 39     | padding                                                          |
+//!
+//! All integers are little-endian. Output goes to numbered shards in one directory next to
+//! a `datagen.json` holding the counts so far; a stopped or crashed run continues where it
+//! left off when started again on the same directory.
+
+use crate::atomic_file;
+use crate::engine_process;
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_vs_engine::{spawn_initialized_engine, EngineVsEngineManager, SearchLimit};
+use crate::events::{SharedEventSink, DATAGEN_PROGRESS};
+use crate::process_priority::ProcessPriority;
+use crate::shogi_rules::{Color, Move, Piece, PieceType, Position, Square, STARTPOS_SFEN};
+use crate::usi_info::Score;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::{ChildStdin, ChildStdout};
+use tokio::sync::RwLock;
+
+/// Size of one record
+pub const RECORD_SIZE: usize = 40;
+
+const STATE_FILE: &str = "datagen.json";
+
+/// YaneuraOu's mate value; mate in n plies is scored VALUE_MATE - n
+const VALUE_MATE: i32 = 32000;
+
+/// A position repeated this often is a draw (sennichite)
+const REPETITION_LIMIT: u32 = 4;
+
+fn default_max_moves() -> u32 {
+    320
+}
+
+fn default_random_opening_plies() -> u32 {
+    8
+}
+
+fn default_eval_limit() -> i32 {
+    3000
+}
+
+fn default_shard_positions() -> u64 {
+    1_000_000
+}
+
+fn default_priority() -> ProcessPriority {
+    ProcessPriority::BelowNormal
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatagenConfig {
+    pub engine_id: String,
+    /// Directory the shards and `datagen.json` are written to
+    pub output_dir: String,
+    /// Search budget per move (`go nodes`)
+    pub nodes: u64,
+    /// Games to play in total, counting those of earlier runs; None runs until stopped
+    #[serde(default)]
+    pub games: Option<u64>,
+    /// A game still going after this many moves is a draw
+    #[serde(default = "default_max_moves")]
+    pub max_moves: u32,
+    /// Random legal moves played before the engine takes over; these positions are not written
+    #[serde(default = "default_random_opening_plies")]
+    pub random_opening_plies: u32,
+    /// A game ends as soon as a score reaches this many centipawns either way
+    #[serde(default = "default_eval_limit")]
+    pub eval_limit: i32,
+    /// Records per shard file
+    #[serde(default = "default_shard_positions")]
+    pub shard_positions: u64,
+    /// Pause between games, so the machine stays usable
+    #[serde(default)]
+    pub pause_between_games_ms: u64,
+    /// Priority of the engine process, instead of the engine's own setting
+    #[serde(default = "default_priority")]
+    pub priority: ProcessPriority,
+    /// Seed of the opening moves; a random seed when unset
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl DatagenConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.output_dir.trim().is_empty() {
+            return Err(anyhow!("An output directory is required"));
+        }
+        if self.nodes == 0 {
+            return Err(anyhow!("nodes must be at least 1"));
+        }
+        if self.max_moves == 0 {
+            return Err(anyhow!("max_moves must be at least 1"));
+        }
+        if self.eval_limit <= 0 {
+            return Err(anyhow!("eval_limit must be positive"));
+        }
+        if self.shard_positions == 0 {
+            return Err(anyhow!("shard_positions must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+/// Counts of a data generation run, saved in `datagen.json` and emitted as `datagen-progress`
+/// after each game
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct DatagenProgress {
+    pub output_dir: String,
+    pub games_played: u64,
+    pub positions_written: u64,
+    pub black_wins: u64,
+    pub white_wins: u64,
+    pub draws: u64,
+    /// Shard being written, numbered from 0
+    pub shard: u32,
+    pub games_target: Option<u64>,
+    /// Records written per second in this run
+    pub positions_per_second: f64,
+    pub updated_at: String,
+}
+
+impl DatagenProgress {
+    /// The saved progress of the run writing to `output_dir`, if there is one
+    pub async fn load(output_dir: &Path) -> Result<Option<Self>> {
+        match tokio::fs::read(output_dir.join(STATE_FILE)).await {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&mut self, output_dir: &Path) -> Result<()> {
+        self.updated_at = chrono::Utc::now().to_rfc3339();
+        atomic_file::write_atomic(&output_dir.join(STATE_FILE), &serde_json::to_vec_pretty(self)?).await
+    }
+}
+
+/// Huffman codes of the unpromoted piece types on the board, written least significant bit
+/// first. In hand a piece drops the last bit of its code.
+fn huffman_code(piece_type: PieceType) -> (u32, u32) {
+    match piece_type.unpromote() {
+        PieceType::Pawn => (0x01, 2),
+        PieceType::Lance => (0x03, 4),
+        PieceType::Knight => (0x0b, 4),
+        PieceType::Silver => (0x07, 4),
+        PieceType::Bishop => (0x1f, 6),
+        PieceType::Rook => (0x3f, 6),
+        _ => (0x0f, 5),
+    }
+}
+
+/// Hand pieces in YaneuraOu's piece type order
+const PACKED_HAND_ORDER: [PieceType; 7] = [
+    PieceType::Pawn,
+    PieceType::Lance,
+    PieceType::Knight,
+    PieceType::Silver,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Gold,
+];
+
+struct BitWriter {
+    data: [u8; 32],
+    cursor: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) -> Result<()> {
+        for bit in 0..bits {
+            if self.cursor == self.data.len() * 8 {
+                return Err(anyhow!("Position does not fit in 256 bits"));
+            }
+            if (value >> bit) & 1 == 1 {
+                self.data[self.cursor / 8] |= 1 << (self.cursor % 8);
+            }
+            self.cursor += 1;
+        }
+        Ok(())
+    }
+}
+
+/// YaneuraOu's square index: 1a = 0, 1b = 1, ..., 9i = 80
+fn square_index(square: Square) -> u32 {
+    (square.file as u32 - 1) * 9 + square.rank as u32 - 1
+}
+
+fn color_bit(color: Color) -> u32 {
+    match color {
+        Color::Black => 0,
+        Color::White => 1,
+    }
+}
+
+/// YaneuraOu's `PackedSfen`: the side to move (1 bit), both kings' squares (7 bits each,
+/// black first), every other square from 1a to 9i, then the pieces in hand. An empty square
+/// is a 0 bit. A piece is its Huffman code, a promoted bit (except gold) and its colour bit;
+/// in hand the code loses its last bit and the promoted bit is always 0. The 40 pieces of
+/// shogi always fill exactly 256 bits.
+pub fn pack_sfen(position: &Position) -> Result<[u8; 32]> {
+    Ok(pack(position)?.data)
+}
+
+fn pack(position: &Position) -> Result<BitWriter> {
+    let squares: Vec<Square> = (1..=9)
+        .flat_map(|file| (1..=9).filter_map(move |rank| Square::new(file, rank)))
+        .collect();
+    let king_square = |color: Color| {
+        squares
+            .iter()
+            .find(|&&square| position.piece_at(square) == Some(Piece { piece_type: PieceType::King, color }))
+            .map_or(81, |&square| square_index(square))
+    };
+
+    let mut writer = BitWriter { data: [0; 32], cursor: 0 };
+    writer.write(color_bit(position.side_to_move()), 1)?;
+    writer.write(king_square(Color::Black), 7)?;
+    writer.write(king_square(Color::White), 7)?;
+    for &square in &squares {
+        match position.piece_at(square) {
+            Some(piece) if piece.piece_type == PieceType::King => {}
+            Some(piece) => {
+                let (code, bits) = huffman_code(piece.piece_type);
+                writer.write(code, bits)?;
+                if piece.piece_type != PieceType::Gold {
+                    writer.write(piece.piece_type.is_promoted() as u32, 1)?;
+                }
+                writer.write(color_bit(piece.color), 1)?;
+            }
+            None => writer.write(0, 1)?,
+        }
+    }
+    for color in [Color::Black, Color::White] {
+        for piece_type in PACKED_HAND_ORDER {
+            for _ in 0..position.hand_count(color, piece_type) {
+                let (code, bits) = huffman_code(piece_type);
+                writer.write(code >> 1, bits - 1)?;
+                if piece_type != PieceType::Gold {
+                    writer.write(0, 1)?;
+                }
+                writer.write(color_bit(color), 1)?;
+            }
+        }
+    }
+    Ok(writer)
+}
+
+/// YaneuraOu's 16-bit move: destination in bits 0-6, origin in bits 7-13 (for a drop, the
+/// dropped piece type, pawn = 1 … gold = 7), bit 14 for drops and bit 15 for promotions
+pub fn move16(mv: Move) -> u16 {
+    match mv {
+        Move::Normal { from, to, promote } => {
+            (square_index(to) | (square_index(from) << 7) | ((promote as u32) << 15)) as u16
+        }
+        Move::Drop { piece_type, to } => {
+            let piece = PACKED_HAND_ORDER.iter().position(|&pt| pt == piece_type).unwrap_or(0) as u32 + 1;
+            (square_index(to) | (piece << 7) | (1 << 14)) as u16
+        }
+    }
+}
+
+/// Score in YaneuraOu's units, from the side to move
+fn score_value(score: Score) -> i32 {
+    match score {
+        Score::Cp(cp) => cp.clamp(-(VALUE_MATE - 1), VALUE_MATE - 1),
+        Score::Mate(plies) if plies >= 0 => VALUE_MATE - plies.min(VALUE_MATE),
+        Score::Mate(plies) => -VALUE_MATE - plies.max(-VALUE_MATE),
+    }
+}
+
+struct Record {
+    sfen: [u8; 32],
+    score: i32,
+    best_move: u16,
+    ply: u32,
+    side_to_move: Color,
+}
+
+impl Record {
+    fn to_bytes(&self, winner: Option<Color>) -> [u8; RECORD_SIZE] {
+        let result: i8 = match winner {
+            Some(color) if color == self.side_to_move => 1,
+            Some(_) => -1,
+            None => 0,
+        };
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[..32].copy_from_slice(&self.sfen);
+        bytes[32..34].copy_from_slice(&(self.score as i16).to_le_bytes());
+        bytes[34..36].copy_from_slice(&self.best_move.to_le_bytes());
+        bytes[36..38].copy_from_slice(&(self.ply.min(u16::MAX as u32) as u16).to_le_bytes());
+        bytes[38] = result as u8;
+        bytes
+    }
+}
+
+/// SplitMix64, for picking opening moves
+struct OpeningRng(u64);
+
+impl OpeningRng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// The position without its move number, for spotting repetitions
+fn repetition_key(position: &Position) -> String {
+    let sfen = position.to_sfen();
+    sfen.rsplit_once(' ').map_or(sfen.clone(), |(key, _)| key.to_string())
+}
+
+/// Play one game against itself. Returns the searched positions and the winner, None for
+/// a draw.
+async fn play_game(
+    stdin: &mut ChildStdin,
+    stdout: &mut ChildStdout,
+    config: &DatagenConfig,
+    rng: &mut OpeningRng,
+) -> Result<(Vec<Record>, Option<Color>)> {
+    stdin.write_all(b"usinewgame\n").await?;
+    let limit = SearchLimit::Nodes { nodes: config.nodes };
+    let go_cmd = limit.go_command(0);
+
+    let mut position = Position::default();
+    let mut moves = Vec::new();
+    for _ in 0..config.random_opening_plies {
+        let legal = position.legal_moves();
+        if legal.is_empty() {
+            break;
+        }
+        let mv = legal[rng.below(legal.len())];
+        position.apply_move_unchecked(&mv);
+        moves.push(mv.to_usi());
+    }
+
+    let mut records = Vec::new();
+    let mut repetitions: HashMap<String, u32> = HashMap::new();
+    let winner = loop {
+        let side = position.side_to_move();
+        if moves.len() >= config.max_moves as usize {
+            break None;
+        }
+        if position.legal_moves().is_empty() {
+            break Some(side.opponent());
+        }
+        let seen = repetitions.entry(repetition_key(&position)).or_default();
+        *seen += 1;
+        if *seen >= REPETITION_LIMIT {
+            break None;
+        }
+
+        let reply = EngineVsEngineManager::request_move(
+            stdin,
+            stdout,
+            STARTPOS_SFEN,
+            &moves,
+            &go_cmd,
+            limit.move_timeout(0),
+            |_, _| {},
+        )
+        .await?;
+        match reply.best_move.as_str() {
+            "resign" => break Some(side.opponent()),
+            "win" => break Some(side),
+            usi => {
+                let mv = Move::from_usi(usi)?;
+                if !position.is_legal(&mv) {
+                    return Err(anyhow!("Engine played an illegal move: {}", usi));
+                }
+                let score = reply.info.and_then(|info| info.score).map(score_value);
+                if let Some(score) = score {
+                    records.push(Record {
+                        sfen: pack_sfen(&position)?,
+                        score,
+                        best_move: move16(mv),
+                        ply: position.ply(),
+                        side_to_move: side,
+                    });
+                }
+                position.apply_move_unchecked(&mv);
+                moves.push(usi.to_string());
+                if let Some(score) = score.filter(|score| score.abs() >= config.eval_limit) {
+                    break Some(if score > 0 { side } else { side.opponent() });
+                }
+            }
+        }
+    };
+    Ok((records, winner))
+}
+
+fn shard_path(output_dir: &Path, shard: u32) -> PathBuf {
+    output_dir.join(format!("selfplay-{:05}.bin", shard))
+}
+
+/// Appends records to the current shard, moving on to the next when it is full
+struct ShardWriter {
+    output_dir: PathBuf,
+    shard: u32,
+    file: tokio::fs::File,
+    /// Records in the current shard
+    records: u64,
+    limit: u64,
+}
+
+impl ShardWriter {
+    /// Open `shard` for appending. A record cut short by a crash is dropped.
+    async fn open(output_dir: &Path, shard: u32, limit: u64) -> Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(shard_path(output_dir, shard))
+            .await?;
+        let len = file.metadata().await?.len();
+        let whole = len - len % RECORD_SIZE as u64;
+        if whole != len {
+            log::warn!("Dropping a partial record at the end of shard {}", shard);
+            file.set_len(whole).await?;
+        }
+        Ok(Self {
+            output_dir: output_dir.to_path_buf(),
+            shard,
+            file,
+            records: whole / RECORD_SIZE as u64,
+            limit,
+        })
+    }
+
+    async fn write(&mut self, mut records: &[[u8; RECORD_SIZE]]) -> Result<()> {
+        while !records.is_empty() {
+            if self.records >= self.limit {
+                self.file.sync_all().await?;
+                *self = Self::open(&self.output_dir, self.shard + 1, self.limit).await?;
+            }
+            let count = records.len().min((self.limit - self.records) as usize);
+            self.file.write_all(&records[..count].concat()).await?;
+            self.records += count as u64;
+            records = &records[count..];
+        }
+        self.file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Generate self-play data until `config.games` games have been played in total or `stop`
+/// is set, continuing the run already in `config.output_dir` if there is one
+pub async fn run_datagen(
+    events: SharedEventSink,
+    engine_storage: Arc<RwLock<EngineStorage>>,
+    config: DatagenConfig,
+    stop: Arc<AtomicBool>,
+) -> Result<DatagenProgress> {
+    config.validate()?;
+    let engine: EngineConfig = engine_storage
+        .read()
+        .await
+        .get_engine(&config.engine_id)
+        .cloned()
+        .ok_or_else(|| anyhow!("Engine not found: {}", config.engine_id))?;
+    let engine = EngineConfig { priority: Some(config.priority), ..engine };
+
+    let output_dir = PathBuf::from(&config.output_dir);
+    tokio::fs::create_dir_all(&output_dir).await?;
+    let mut progress = DatagenProgress::load(&output_dir).await?.unwrap_or_default();
+    if progress.games_played > 0 {
+        log::info!("Continuing self-play data in {} after {} games", output_dir.display(), progress.games_played);
+    }
+    progress.output_dir = config.output_dir.clone();
+    progress.games_target = config.games;
+    let mut writer = ShardWriter::open(&output_dir, progress.shard, config.shard_positions).await?;
+
+    let seed = config.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    });
+    // Mix in the games played so a continued run does not replay the same openings
+    let mut rng = OpeningRng(seed ^ progress.games_played.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+
+    let (mut child, mut stdin, mut stdout) = spawn_initialized_engine(&engine, &engine_storage).await?;
+    let started = tokio::time::Instant::now();
+    let mut positions_this_run = 0u64;
+    let result = loop {
+        if stop.load(Ordering::SeqCst) || config.games.is_some_and(|games| progress.games_played >= games) {
+            break Ok(());
+        }
+        let (records, winner) = match play_game(&mut stdin, &mut stdout, &config, &mut rng).await {
+            Ok(game) => game,
+            Err(e) => break Err(e),
+        };
+        let bytes: Vec<[u8; RECORD_SIZE]> = records.iter().map(|record| record.to_bytes(winner)).collect();
+        if let Err(e) = writer.write(&bytes).await {
+            break Err(e);
+        }
+
+        positions_this_run += bytes.len() as u64;
+        progress.games_played += 1;
+        progress.positions_written += bytes.len() as u64;
+        match winner {
+            Some(Color::Black) => progress.black_wins += 1,
+            Some(Color::White) => progress.white_wins += 1,
+            None => progress.draws += 1,
+        }
+        progress.shard = writer.shard;
+        progress.positions_per_second = positions_this_run as f64 / started.elapsed().as_secs_f64().max(0.001);
+        if let Err(e) = progress.save(&output_dir).await {
+            log::warn!("Failed to save self-play progress: {}", e);
+        }
+        let _ = events.send(&DATAGEN_PROGRESS, &progress);
+
+        if config.pause_between_games_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(config.pause_between_games_ms)).await;
+        }
+    };
+
+    let _ = stdin.write_all(b"quit\n").await;
+    let _ = stdin.flush().await;
+    engine_process::kill(&mut child).await;
+    writer.file.sync_all().await?;
+    result.map(|()| progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_sfen_fills_256_bits() {
+        // The starting position, then a promoted piece on the board and bishops in hand
+        for spec in ["startpos", "startpos moves 7g7f 3c3d 8h2b+", "startpos moves 7g7f 3c3d 8h2b+ 3a2b"] {
+            let (mut position, moves) = crate::shogi_rules::parse_position(spec).unwrap();
+            for mv in &moves {
+                position.apply_move(mv).unwrap();
+            }
+            let packed = pack(&position).unwrap();
+            assert_eq!(packed.cursor, 256, "{}", spec);
+            // The side to move is the first bit and the black king's square (5i = 44) follows
+            assert_eq!(packed.data[0] & 1, color_bit(position.side_to_move()) as u8);
+            assert_eq!((packed.data[0] >> 1) & 0x7f, 44);
+        }
+    }
+
+    #[test]
+    fn test_move16() {
+        assert_eq!(move16(Move::from_usi("7g7f").unwrap()), 59 | (60 << 7));
+        assert_eq!(move16(Move::from_usi("8h2b+").unwrap()), 10 | (70 << 7) | (1 << 15));
+        assert_eq!(move16(Move::from_usi("P*5e").unwrap()), 40 | (1 << 7) | (1 << 14));
+        assert_eq!(score_value(Score::Mate(3)), 31997);
+        assert_eq!(score_value(Score::Mate(-2)), -31998);
+    }
+}
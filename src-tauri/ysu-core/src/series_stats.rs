@@ -0,0 +1,164 @@
+//! Elo difference, error bars and likelihood of superiority for a series of games
+//! Win/draw/loss counts alone invite reading noise as strength: 6-4 over ten games says almost
+//! nothing. The figures here follow the usual engine-testing practice. Single games are
+//! modelled as trinomial (win, draw, loss); games played in colour-swapped pairs from the
+//! same opening as pentanomial, over the five possible pair scores, which accounts for the
+//! opening deciding both games of a pair and gives honest, usually tighter, intervals.
+
+use serde::{Deserialize, Serialize};
+
+/// Two-sided 95% quantile of the normal distribution
+pub const Z_95: f64 = 1.959964;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum StatsModel {
+    /// Each game counted on its own
+    #[default]
+    Trinomial,
+    /// Games counted in colour-swapped pairs
+    Pentanomial,
+}
+
+/// Results of a series from the point of view of its first engine
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct SeriesStats {
+    pub model: StatsModel,
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// Pairs scoring 0, 0.5, 1, 1.5 and 2 points; only for the pentanomial model
+    pub pentanomial: Option<Vec<usize>>,
+    /// Points per game, from 0 to 1
+    pub score: f64,
+    pub draw_ratio: f64,
+    /// Elo difference; None when every game was won or every game lost
+    pub elo: Option<f64>,
+    /// 95% confidence interval of the Elo difference; an open end is None
+    pub elo_low: Option<f64>,
+    pub elo_high: Option<f64>,
+    /// Likelihood of superiority: the probability that the first engine is the stronger
+    pub los: f64,
+}
+
+/// Elo difference at which the expected score is `score`
+pub fn elo_from_score(score: f64) -> Option<f64> {
+    (score > 0.0 && score < 1.0).then(|| -400.0 * (1.0 / score - 1.0).log10())
+}
+
+/// Error function, after Abramowitz and Stegun 7.1.26 (absolute error below 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 { -y } else { y }
+}
+
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+impl SeriesStats {
+    /// Each entry is one game's score for the first engine: 1, 0.5 or 0
+    pub fn from_games(scores: &[f64]) -> Self {
+        let mut stats = Self::tally(scores);
+        stats.fill(scores);
+        stats
+    }
+
+    /// Each entry is the first engine's scores in the two games of a pair
+    pub fn from_pairs(pairs: &[(f64, f64)]) -> Self {
+        let scores: Vec<f64> = pairs.iter().flat_map(|&(first, second)| [first, second]).collect();
+        let mut stats = Self::tally(&scores);
+        stats.model = StatsModel::Pentanomial;
+        let mut pentanomial = vec![0; 5];
+        for (first, second) in pairs {
+            pentanomial[((first + second) * 2.0).round().clamp(0.0, 4.0) as usize] += 1;
+        }
+        stats.pentanomial = Some(pentanomial);
+        let pair_scores: Vec<f64> = pairs.iter().map(|(first, second)| (first + second) / 2.0).collect();
+        stats.fill(&pair_scores);
+        stats
+    }
+
+    fn tally(scores: &[f64]) -> Self {
+        let count = |value: f64| scores.iter().filter(|&&score| score == value).count();
+        Self {
+            games: scores.len(),
+            wins: count(1.0),
+            draws: count(0.5),
+            losses: count(0.0),
+            ..Default::default()
+        }
+    }
+
+    /// Score, Elo and LOS from independent samples of the per-game score
+    fn fill(&mut self, samples: &[f64]) {
+        if samples.is_empty() {
+            return;
+        }
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / n;
+        let std_error = (variance / n).sqrt();
+
+        self.score = mean;
+        self.draw_ratio = self.draws as f64 / self.games as f64;
+        self.elo = elo_from_score(mean);
+        self.elo_low = elo_from_score(mean - Z_95 * std_error);
+        self.elo_high = elo_from_score(mean + Z_95 * std_error);
+        self.los = if std_error > 0.0 {
+            normal_cdf((mean - 0.5) / std_error)
+        } else if mean > 0.5 {
+            1.0
+        } else if mean < 0.5 {
+            0.0
+        } else {
+            0.5
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games(wins: usize, draws: usize, losses: usize) -> Vec<f64> {
+        [vec![1.0; wins], vec![0.5; draws], vec![0.0; losses]].concat()
+    }
+
+    #[test]
+    fn test_trinomial() {
+        let stats = SeriesStats::from_games(&games(60, 0, 40));
+        assert_eq!((stats.model, stats.games, stats.wins, stats.losses), (StatsModel::Trinomial, 100, 60, 40));
+        assert!((stats.elo.unwrap() - 70.437).abs() < 1e-3);
+        assert!((stats.elo_low.unwrap() - 2.767).abs() < 1e-3);
+        assert!((stats.elo_high.unwrap() - 143.909).abs() < 1e-3);
+        assert!((stats.los - 0.97938).abs() < 1e-4);
+        assert_eq!(stats.pentanomial, None);
+
+        let drawn = SeriesStats::from_games(&games(0, 4, 0));
+        assert_eq!((drawn.elo, drawn.los, drawn.draw_ratio), (Some(0.0), 0.5, 1.0));
+        let swept = SeriesStats::from_games(&games(3, 0, 0));
+        assert_eq!((swept.elo, swept.elo_low, swept.los), (None, None, 1.0));
+        assert_eq!(SeriesStats::from_games(&[]), SeriesStats::default());
+    }
+
+    #[test]
+    fn test_pentanomial() {
+        let pairs = [(1.0, 0.0), (1.0, 1.0), (0.5, 0.5), (0.0, 0.5), (1.0, 0.5)];
+        let stats = SeriesStats::from_pairs(&pairs);
+        assert_eq!(stats.model, StatsModel::Pentanomial);
+        assert_eq!(stats.pentanomial, Some(vec![0, 1, 2, 1, 1]));
+        assert_eq!((stats.games, stats.wins, stats.draws, stats.losses), (10, 4, 4, 2));
+        assert_eq!(stats.score, 0.6);
+        assert!((stats.draw_ratio - 0.4).abs() < 1e-9);
+        // The pair scores vary less than the games, so the pentanomial interval is narrower
+        let trinomial = SeriesStats::from_games(&games(4, 4, 2));
+        assert_eq!(trinomial.elo, stats.elo);
+        assert!(stats.elo_high.unwrap() - stats.elo_low.unwrap() < trinomial.elo_high.unwrap() - trinomial.elo_low.unwrap());
+    }
+}
@@ -0,0 +1,1001 @@
+//! Shogi rules: board representation, SFEN parsing and legal move generation
+//! Used by the backend wherever it needs to understand positions (notation, match validation)
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Standard initial position in SFEN
+pub const STARTPOS_SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+
+/// Side to move / owner of a piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Black,
+    White,
+}
+
+impl Color {
+    pub fn opponent(self) -> Self {
+        match self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+
+    /// Name used in events and match state ("black" / "white")
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Color::Black => "black",
+            Color::White => "white",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Color::Black => 0,
+            Color::White => 1,
+        }
+    }
+
+    /// Rank delta for one step "forward" from this side's point of view
+    fn forward(self) -> i8 {
+        match self {
+            Color::Black => -1,
+            Color::White => 1,
+        }
+    }
+}
+
+/// Piece kinds, including promoted pieces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum PieceType {
+    Pawn,
+    Lance,
+    Knight,
+    Silver,
+    Gold,
+    Bishop,
+    Rook,
+    King,
+    PromotedPawn,
+    PromotedLance,
+    PromotedKnight,
+    PromotedSilver,
+    Horse,
+    Dragon,
+}
+
+/// Pieces that can be held in hand, in SFEN hand order
+pub const HAND_PIECE_TYPES: [PieceType; 7] = [
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Gold,
+    PieceType::Silver,
+    PieceType::Knight,
+    PieceType::Lance,
+    PieceType::Pawn,
+];
+
+const KING_STEPS: &[(i8, i8)] = &[(-1, 1), (0, 1), (1, 1), (-1, 0), (1, 0), (-1, -1), (0, -1), (1, -1)];
+const GOLD_STEPS: &[(i8, i8)] = &[(-1, 1), (0, 1), (1, 1), (-1, 0), (1, 0), (0, -1)];
+const SILVER_STEPS: &[(i8, i8)] = &[(-1, 1), (0, 1), (1, 1), (-1, -1), (1, -1)];
+const KNIGHT_STEPS: &[(i8, i8)] = &[(-1, 2), (1, 2)];
+const PAWN_STEPS: &[(i8, i8)] = &[(0, 1)];
+const ORTHOGONAL: &[(i8, i8)] = &[(0, 1), (-1, 0), (1, 0), (0, -1)];
+const DIAGONAL: &[(i8, i8)] = &[(-1, 1), (1, 1), (-1, -1), (1, -1)];
+const FORWARD: &[(i8, i8)] = &[(0, 1)];
+
+/// Step offsets and sliding directions of a piece
+type Movement = (&'static [(i8, i8)], &'static [(i8, i8)]);
+
+impl PieceType {
+    /// The promoted form of this piece, if it can promote
+    pub fn promote(self) -> Option<PieceType> {
+        match self {
+            PieceType::Pawn => Some(PieceType::PromotedPawn),
+            PieceType::Lance => Some(PieceType::PromotedLance),
+            PieceType::Knight => Some(PieceType::PromotedKnight),
+            PieceType::Silver => Some(PieceType::PromotedSilver),
+            PieceType::Bishop => Some(PieceType::Horse),
+            PieceType::Rook => Some(PieceType::Dragon),
+            _ => None,
+        }
+    }
+
+    /// The unpromoted form of this piece (the piece goes to hand as this type)
+    pub fn unpromote(self) -> PieceType {
+        match self {
+            PieceType::PromotedPawn => PieceType::Pawn,
+            PieceType::PromotedLance => PieceType::Lance,
+            PieceType::PromotedKnight => PieceType::Knight,
+            PieceType::PromotedSilver => PieceType::Silver,
+            PieceType::Horse => PieceType::Bishop,
+            PieceType::Dragon => PieceType::Rook,
+            other => other,
+        }
+    }
+
+    pub fn is_promoted(self) -> bool {
+        self.unpromote() != self
+    }
+
+    /// USI/SFEN letter of the base piece (uppercase)
+    pub fn usi_char(self) -> char {
+        match self.unpromote() {
+            PieceType::Pawn => 'P',
+            PieceType::Lance => 'L',
+            PieceType::Knight => 'N',
+            PieceType::Silver => 'S',
+            PieceType::Gold => 'G',
+            PieceType::Bishop => 'B',
+            PieceType::Rook => 'R',
+            _ => 'K',
+        }
+    }
+
+    /// Parse an unpromoted piece from its USI/SFEN letter (either case)
+    pub fn from_usi_char(c: char) -> Option<PieceType> {
+        match c.to_ascii_uppercase() {
+            'P' => Some(PieceType::Pawn),
+            'L' => Some(PieceType::Lance),
+            'N' => Some(PieceType::Knight),
+            'S' => Some(PieceType::Silver),
+            'G' => Some(PieceType::Gold),
+            'B' => Some(PieceType::Bishop),
+            'R' => Some(PieceType::Rook),
+            'K' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+
+    /// Step moves and sliding directions, relative to the owner's forward direction
+    fn movement(self) -> Movement {
+        match self {
+            PieceType::Pawn => (PAWN_STEPS, &[]),
+            PieceType::Lance => (&[], FORWARD),
+            PieceType::Knight => (KNIGHT_STEPS, &[]),
+            PieceType::Silver => (SILVER_STEPS, &[]),
+            PieceType::Gold
+            | PieceType::PromotedPawn
+            | PieceType::PromotedLance
+            | PieceType::PromotedKnight
+            | PieceType::PromotedSilver => (GOLD_STEPS, &[]),
+            PieceType::Bishop => (&[], DIAGONAL),
+            PieceType::Rook => (&[], ORTHOGONAL),
+            PieceType::King => (KING_STEPS, &[]),
+            PieceType::Horse => (ORTHOGONAL, DIAGONAL),
+            PieceType::Dragon => (DIAGONAL, ORTHOGONAL),
+        }
+    }
+
+    fn hand_index(self) -> Option<usize> {
+        HAND_PIECE_TYPES.iter().position(|&pt| pt == self)
+    }
+}
+
+/// A piece on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct Piece {
+    pub piece_type: PieceType,
+    pub color: Color,
+}
+
+impl Piece {
+    /// SFEN token for this piece ("P", "+p", ...)
+    pub fn to_sfen(self) -> String {
+        let letter = if self.color == Color::Black {
+            self.piece_type.usi_char()
+        } else {
+            self.piece_type.usi_char().to_ascii_lowercase()
+        };
+        if self.piece_type.is_promoted() {
+            format!("+{}", letter)
+        } else {
+            letter.to_string()
+        }
+    }
+}
+
+/// A board square: file 1-9 (right to left from Black's view), rank 1-9 (top to bottom)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Square {
+    pub file: u8,
+    pub rank: u8,
+}
+
+impl Square {
+    pub fn new(file: u8, rank: u8) -> Option<Self> {
+        if (1..=9).contains(&file) && (1..=9).contains(&rank) {
+            Some(Self { file, rank })
+        } else {
+            None
+        }
+    }
+
+    /// Parse a USI square such as "7g"
+    pub fn from_usi(s: &str) -> Option<Self> {
+        let mut chars = s.chars();
+        let file = chars.next()?.to_digit(10)? as u8;
+        let rank_char = chars.next()?;
+        if chars.next().is_some() || !('a'..='i').contains(&rank_char) {
+            return None;
+        }
+        Self::new(file, rank_char as u8 - b'a' + 1)
+    }
+
+    pub fn to_usi(self) -> String {
+        format!("{}{}", self.file, (b'a' + self.rank - 1) as char)
+    }
+
+    fn offset(self, file_delta: i8, rank_delta: i8) -> Option<Self> {
+        let file = self.file as i8 + file_delta;
+        let rank = self.rank as i8 + rank_delta;
+        if (1..=9).contains(&file) && (1..=9).contains(&rank) {
+            Some(Self { file: file as u8, rank: rank as u8 })
+        } else {
+            None
+        }
+    }
+
+    /// Whether this square is in the promotion zone of the given side
+    pub fn in_promotion_zone(self, color: Color) -> bool {
+        match color {
+            Color::Black => self.rank <= 3,
+            Color::White => self.rank >= 7,
+        }
+    }
+
+    /// Distance of this square from the given side's back rank (1 = furthest rank)
+    fn relative_rank(self, color: Color) -> u8 {
+        match color {
+            Color::Black => self.rank,
+            Color::White => 10 - self.rank,
+        }
+    }
+
+    fn all() -> impl Iterator<Item = Square> {
+        (1..=9).flat_map(|rank| (1..=9).rev().map(move |file| Square { file, rank }))
+    }
+}
+
+/// A move in USI terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Move {
+    Normal { from: Square, to: Square, promote: bool },
+    Drop { piece_type: PieceType, to: Square },
+}
+
+impl Move {
+    /// Parse a USI move string ("7g7f", "8h2b+", "P*5e")
+    pub fn from_usi(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some((piece, to)) = s.split_once('*') {
+            let mut piece_chars = piece.chars();
+            let piece_type = match (piece_chars.next(), piece_chars.next()) {
+                (Some(c), None) if c.is_ascii_uppercase() => PieceType::from_usi_char(c),
+                _ => None,
+            }
+            .filter(|pt| pt.hand_index().is_some())
+            .ok_or_else(|| anyhow!("Invalid drop piece in move: {}", s))?;
+            let to = Square::from_usi(to).ok_or_else(|| anyhow!("Invalid square in move: {}", s))?;
+            return Ok(Move::Drop { piece_type, to });
+        }
+
+        let (squares, promote) = match s.strip_suffix('+') {
+            Some(rest) => (rest, true),
+            None => (s, false),
+        };
+        if squares.len() != 4 || !squares.is_ascii() {
+            return Err(anyhow!("Invalid USI move: {}", s));
+        }
+        let from = Square::from_usi(&squares[0..2]).ok_or_else(|| anyhow!("Invalid USI move: {}", s))?;
+        let to = Square::from_usi(&squares[2..4]).ok_or_else(|| anyhow!("Invalid USI move: {}", s))?;
+        Ok(Move::Normal { from, to, promote })
+    }
+
+    pub fn to_usi(self) -> String {
+        match self {
+            Move::Normal { from, to, promote } => {
+                format!("{}{}{}", from.to_usi(), to.to_usi(), if promote { "+" } else { "" })
+            }
+            Move::Drop { piece_type, to } => format!("{}*{}", piece_type.usi_char(), to.to_usi()),
+        }
+    }
+
+    pub fn to(self) -> Square {
+        match self {
+            Move::Normal { to, .. } | Move::Drop { to, .. } => to,
+        }
+    }
+}
+
+/// Serializable snapshot of a position for frontends that have no shogi logic of their own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct BoardState {
+    /// board[row][col] where row 0 is rank 1 (a) and col 0 is file 9, the same order as SFEN
+    pub board: [[Option<Piece>; 9]; 9],
+    /// Captured pieces held by Black, by piece type
+    pub black_hand: BTreeMap<PieceType, u8>,
+    /// Captured pieces held by White, by piece type
+    pub white_hand: BTreeMap<PieceType, u8>,
+    pub side_to_move: Color,
+    /// Whether the side to move is in check
+    pub in_check: bool,
+}
+
+/// A complete shogi position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    /// board[rank - 1][9 - file], i.e. the same order as SFEN rows
+    board: [[Option<Piece>; 9]; 9],
+    /// Pieces in hand per side, indexed like HAND_PIECE_TYPES
+    hands: [[u8; 7]; 2],
+    side_to_move: Color,
+    ply: u32,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::from_sfen(STARTPOS_SFEN).expect("startpos SFEN is valid")
+    }
+}
+
+impl Position {
+    /// Parse an SFEN string. Also accepts "startpos" and a leading "sfen " keyword.
+    pub fn from_sfen(sfen: &str) -> Result<Self> {
+        let sfen = sfen.trim();
+        let sfen = sfen.strip_prefix("sfen ").unwrap_or(sfen).trim();
+        if sfen == "startpos" {
+            return Self::from_sfen(STARTPOS_SFEN);
+        }
+
+        let fields: Vec<&str> = sfen.split_whitespace().collect();
+        if fields.len() < 3 {
+            return Err(anyhow!("Invalid SFEN (expected board, side and hands): {}", sfen));
+        }
+
+        let mut board = [[None; 9]; 9];
+        let rows: Vec<&str> = fields[0].split('/').collect();
+        if rows.len() != 9 {
+            return Err(anyhow!("Invalid SFEN board (expected 9 ranks): {}", fields[0]));
+        }
+        for (rank_index, row) in rows.iter().enumerate() {
+            let mut col = 0usize;
+            let mut promoted = false;
+            for c in row.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    if promoted {
+                        return Err(anyhow!("Invalid SFEN: '+' must precede a piece in rank {}", rank_index + 1));
+                    }
+                    col += empty as usize;
+                } else if c == '+' {
+                    promoted = true;
+                    continue;
+                } else {
+                    let base = PieceType::from_usi_char(c)
+                        .ok_or_else(|| anyhow!("Invalid SFEN piece '{}'", c))?;
+                    let piece_type = if promoted {
+                        base.promote().ok_or_else(|| anyhow!("Piece '{}' cannot be promoted", c))?
+                    } else {
+                        base
+                    };
+                    if col >= 9 {
+                        return Err(anyhow!("Invalid SFEN: rank {} has more than 9 squares", rank_index + 1));
+                    }
+                    let color = if c.is_ascii_uppercase() { Color::Black } else { Color::White };
+                    board[rank_index][col] = Some(Piece { piece_type, color });
+                    col += 1;
+                }
+                promoted = false;
+            }
+            if col != 9 || promoted {
+                return Err(anyhow!("Invalid SFEN: rank {} does not have 9 squares", rank_index + 1));
+            }
+        }
+
+        let side_to_move = match fields[1] {
+            "b" => Color::Black,
+            "w" => Color::White,
+            other => return Err(anyhow!("Invalid SFEN side to move: {}", other)),
+        };
+
+        let mut hands = [[0u8; 7]; 2];
+        if fields[2] != "-" {
+            let mut count: u32 = 0;
+            for c in fields[2].chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    count = count * 10 + digit;
+                    continue;
+                }
+                let piece_type = PieceType::from_usi_char(c)
+                    .and_then(|pt| pt.hand_index().map(|index| (pt, index)))
+                    .ok_or_else(|| anyhow!("Invalid SFEN hand piece '{}'", c))?;
+                let color = if c.is_ascii_uppercase() { Color::Black } else { Color::White };
+                let amount = if count == 0 { 1 } else { count };
+                hands[color.index()][piece_type.1] = hands[color.index()][piece_type.1]
+                    .saturating_add(amount.min(u8::MAX as u32) as u8);
+                count = 0;
+            }
+            if count != 0 {
+                return Err(anyhow!("Invalid SFEN hand: trailing count in '{}'", fields[2]));
+            }
+        }
+
+        let ply = match fields.get(3) {
+            Some(ply) => ply.parse().map_err(|_| anyhow!("Invalid SFEN move number: {}", ply))?,
+            None => 1,
+        };
+
+        Ok(Self { board, hands, side_to_move, ply })
+    }
+
+    /// Serialize the position as SFEN
+    pub fn to_sfen(&self) -> String {
+        let rows: Vec<String> = self
+            .board
+            .iter()
+            .map(|row| {
+                let mut out = String::new();
+                let mut empty = 0;
+                for square in row {
+                    match square {
+                        Some(piece) => {
+                            if empty > 0 {
+                                out.push_str(&empty.to_string());
+                                empty = 0;
+                            }
+                            out.push_str(&piece.to_sfen());
+                        }
+                        None => empty += 1,
+                    }
+                }
+                if empty > 0 {
+                    out.push_str(&empty.to_string());
+                }
+                out
+            })
+            .collect();
+
+        let mut hands = String::new();
+        for color in [Color::Black, Color::White] {
+            for (index, piece_type) in HAND_PIECE_TYPES.iter().enumerate() {
+                let count = self.hands[color.index()][index];
+                if count == 0 {
+                    continue;
+                }
+                if count > 1 {
+                    hands.push_str(&count.to_string());
+                }
+                hands.push_str(&Piece { piece_type: *piece_type, color }.to_sfen());
+            }
+        }
+        if hands.is_empty() {
+            hands.push('-');
+        }
+
+        let side = if self.side_to_move == Color::Black { "b" } else { "w" };
+        format!("{} {} {} {}", rows.join("/"), side, hands, self.ply)
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    /// Move number as recorded in SFEN (1 for the initial position)
+    #[allow(dead_code)]
+    pub fn ply(&self) -> u32 {
+        self.ply
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.board[(square.rank - 1) as usize][(9 - square.file) as usize]
+    }
+
+    fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        self.board[(square.rank - 1) as usize][(9 - square.file) as usize] = piece;
+    }
+
+    /// Number of pieces of the given type a side holds in hand
+    pub fn hand_count(&self, color: Color, piece_type: PieceType) -> u8 {
+        piece_type
+            .hand_index()
+            .map(|index| self.hands[color.index()][index])
+            .unwrap_or(0)
+    }
+
+    fn king_square(&self, color: Color) -> Option<Square> {
+        Square::all().find(|&sq| {
+            self.piece_at(sq) == Some(Piece { piece_type: PieceType::King, color })
+        })
+    }
+
+    /// Squares a piece standing on `from` attacks (including squares occupied by own pieces)
+    fn attacked_squares(&self, from: Square, piece: Piece) -> Vec<Square> {
+        let forward = piece.color.forward();
+        let (steps, slides) = piece.piece_type.movement();
+        let mut targets = Vec::new();
+        for &(file_delta, rank_delta) in steps {
+            if let Some(to) = from.offset(file_delta, rank_delta * forward) {
+                targets.push(to);
+            }
+        }
+        for &(file_delta, rank_delta) in slides {
+            let mut current = from;
+            while let Some(to) = current.offset(file_delta, rank_delta * forward) {
+                targets.push(to);
+                if self.piece_at(to).is_some() {
+                    break;
+                }
+                current = to;
+            }
+        }
+        targets
+    }
+
+    /// Whether `square` is attacked by any piece of `by`
+    pub fn is_attacked(&self, square: Square, by: Color) -> bool {
+        Square::all().any(|from| match self.piece_at(from) {
+            Some(piece) if piece.color == by => self.attacked_squares(from, piece).contains(&square),
+            _ => false,
+        })
+    }
+
+    /// Whether the given side's king is currently in check
+    pub fn in_check(&self, color: Color) -> bool {
+        self.king_square(color)
+            .map(|king| self.is_attacked(king, color.opponent()))
+            .unwrap_or(false)
+    }
+
+    /// Whether a piece of this type may stand on `to` without ever being able to move again
+    fn is_dead_square(piece_type: PieceType, color: Color, to: Square) -> bool {
+        let relative_rank = to.relative_rank(color);
+        match piece_type {
+            PieceType::Pawn | PieceType::Lance => relative_rank == 1,
+            PieceType::Knight => relative_rank <= 2,
+            _ => false,
+        }
+    }
+
+    fn has_unpromoted_pawn_on_file(&self, color: Color, file: u8) -> bool {
+        (1..=9).any(|rank| {
+            self.piece_at(Square { file, rank }) == Some(Piece { piece_type: PieceType::Pawn, color })
+        })
+    }
+
+    /// Moves that obey piece movement rules but may leave the king in check
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let color = self.side_to_move;
+        let mut moves = Vec::new();
+
+        for from in Square::all() {
+            let piece = match self.piece_at(from) {
+                Some(piece) if piece.color == color => piece,
+                _ => continue,
+            };
+            for to in self.attacked_squares(from, piece) {
+                if matches!(self.piece_at(to), Some(target) if target.color == color) {
+                    continue;
+                }
+                let can_promote = piece.piece_type.promote().is_some()
+                    && (from.in_promotion_zone(color) || to.in_promotion_zone(color));
+                if can_promote {
+                    moves.push(Move::Normal { from, to, promote: true });
+                }
+                if !Self::is_dead_square(piece.piece_type, color, to) {
+                    moves.push(Move::Normal { from, to, promote: false });
+                }
+            }
+        }
+
+        for (index, &piece_type) in HAND_PIECE_TYPES.iter().enumerate() {
+            if self.hands[color.index()][index] == 0 {
+                continue;
+            }
+            for to in Square::all() {
+                if self.piece_at(to).is_some() || Self::is_dead_square(piece_type, color, to) {
+                    continue;
+                }
+                if piece_type == PieceType::Pawn && self.has_unpromoted_pawn_on_file(color, to.file) {
+                    continue;
+                }
+                moves.push(Move::Drop { piece_type, to });
+            }
+        }
+
+        moves
+    }
+
+    /// Whether `mv` is one of the pseudo-legal moves, without generating them all
+    fn is_pseudo_legal(&self, mv: &Move) -> bool {
+        let color = self.side_to_move;
+        match *mv {
+            Move::Normal { from, to, promote } => {
+                let piece = match self.piece_at(from) {
+                    Some(piece) if piece.color == color => piece,
+                    _ => return false,
+                };
+                if matches!(self.piece_at(to), Some(target) if target.color == color)
+                    || !self.attacked_squares(from, piece).contains(&to)
+                {
+                    return false;
+                }
+                if promote {
+                    piece.piece_type.promote().is_some()
+                        && (from.in_promotion_zone(color) || to.in_promotion_zone(color))
+                } else {
+                    !Self::is_dead_square(piece.piece_type, color, to)
+                }
+            }
+            Move::Drop { piece_type, to } => {
+                piece_type.hand_index().is_some()
+                    && self.hand_count(color, piece_type) > 0
+                    && self.piece_at(to).is_none()
+                    && !Self::is_dead_square(piece_type, color, to)
+                    && !(piece_type == PieceType::Pawn && self.has_unpromoted_pawn_on_file(color, to.file))
+            }
+        }
+    }
+
+    /// Whether a pseudo-legal move leaves the king safe and, if asked, is not a pawn drop
+    /// that mates
+    fn is_legal_pseudo_move(&self, mv: &Move, check_drop_pawn_mate: bool) -> bool {
+        let color = self.side_to_move;
+        let mut next = self.clone();
+        next.apply_move_unchecked(mv);
+        if next.in_check(color) {
+            return false;
+        }
+        if check_drop_pawn_mate
+            && matches!(mv, Move::Drop { piece_type: PieceType::Pawn, .. })
+            && next.in_check(color.opponent())
+            && next.legal_moves_impl(false).is_empty()
+        {
+            // Uchifuzume: checkmating by pawn drop is illegal
+            return false;
+        }
+        true
+    }
+
+    fn legal_moves_impl(&self, check_drop_pawn_mate: bool) -> Vec<Move> {
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|mv| self.is_legal_pseudo_move(mv, check_drop_pawn_mate))
+            .collect()
+    }
+
+    /// All legal moves for the side to move
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.legal_moves_impl(true)
+    }
+
+    /// Whether `mv` is legal. Checks only this move, so replaying a long game stays cheap.
+    pub fn is_legal(&self, mv: &Move) -> bool {
+        self.is_pseudo_legal(mv) && self.is_legal_pseudo_move(mv, true)
+    }
+
+    /// Reasons the position could not arise in a game: wrong number of kings, more pieces
+    /// than the set contains, two unpromoted pawns of one side on a file, pieces on ranks
+    /// they can never leave, or the side that just moved still in check. Empty if plausible.
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for color in [Color::Black, Color::White] {
+            let kings = Square::all()
+                .filter(|&sq| self.piece_at(sq) == Some(Piece { piece_type: PieceType::King, color }))
+                .count();
+            if kings != 1 {
+                errors.push(format!("{} has {} kings", color.as_str(), kings));
+            }
+        }
+
+        let mut counts: BTreeMap<PieceType, u32> = BTreeMap::new();
+        for square in Square::all() {
+            if let Some(piece) = self.piece_at(square) {
+                *counts.entry(piece.piece_type.unpromote()).or_default() += 1;
+            }
+        }
+        for color in [Color::Black, Color::White] {
+            for piece_type in HAND_PIECE_TYPES {
+                *counts.entry(piece_type).or_default() += self.hand_count(color, piece_type) as u32;
+            }
+        }
+        for (piece_type, count) in counts {
+            let limit = match piece_type {
+                PieceType::Pawn => 18,
+                PieceType::Bishop | PieceType::Rook | PieceType::King => 2,
+                _ => 4,
+            };
+            if count > limit {
+                errors.push(format!("{} {:?} pieces (at most {})", count, piece_type, limit));
+            }
+        }
+
+        for color in [Color::Black, Color::White] {
+            for file in 1..=9 {
+                let pawns = (1..=9)
+                    .filter(|&rank| self.piece_at(Square { file, rank }) == Some(Piece { piece_type: PieceType::Pawn, color }))
+                    .count();
+                if pawns > 1 {
+                    errors.push(format!("{} has {} pawns on file {}", color.as_str(), pawns, file));
+                }
+            }
+        }
+
+        for square in Square::all() {
+            if let Some(piece) = self.piece_at(square) {
+                if Self::is_dead_square(piece.piece_type, piece.color, square) {
+                    errors.push(format!("{} {:?} on {} can never move", piece.color.as_str(), piece.piece_type, square.to_usi()));
+                }
+            }
+        }
+
+        if self.in_check(self.side_to_move.opponent()) {
+            errors.push(format!("{} is in check but it is {} to move", self.side_to_move.opponent().as_str(), self.side_to_move.as_str()));
+        }
+        errors
+    }
+
+    /// Snapshot of the board and hands for display
+    pub fn board_state(&self) -> BoardState {
+        let hand = |color: Color| -> BTreeMap<PieceType, u8> {
+            HAND_PIECE_TYPES
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| self.hands[color.index()][*index] > 0)
+                .map(|(index, piece_type)| (*piece_type, self.hands[color.index()][index]))
+                .collect()
+        };
+        BoardState {
+            board: self.board,
+            black_hand: hand(Color::Black),
+            white_hand: hand(Color::White),
+            side_to_move: self.side_to_move,
+            in_check: self.in_check(self.side_to_move),
+        }
+    }
+
+    /// Whether the side to move is checkmated
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check(self.side_to_move) && self.legal_moves().is_empty()
+    }
+
+    /// Whether the side to move may declare a win under the 27-point entering-king
+    /// (nyugyoku) rule used by USI `bestmove win`. The remaining-time condition is up
+    /// to the caller.
+    pub fn can_declare_win(&self) -> bool {
+        let color = self.side_to_move;
+        let in_camp = |square: Square| square.in_promotion_zone(color);
+        let points = |piece_type: PieceType| match piece_type.unpromote() {
+            PieceType::Rook | PieceType::Bishop => 5,
+            _ => 1,
+        };
+
+        match self.king_square(color) {
+            Some(king) if in_camp(king) => {}
+            _ => return false,
+        }
+        if self.in_check(color) {
+            return false;
+        }
+
+        let camp_pieces: Vec<PieceType> = Square::all()
+            .filter(|&square| in_camp(square))
+            .filter_map(|square| self.piece_at(square))
+            .filter(|piece| piece.color == color && piece.piece_type != PieceType::King)
+            .map(|piece| piece.piece_type)
+            .collect();
+        if camp_pieces.len() < 10 {
+            return false;
+        }
+
+        let hand_points: u32 = HAND_PIECE_TYPES
+            .iter()
+            .zip(self.hands[color.index()])
+            .map(|(&piece_type, count)| points(piece_type) * count as u32)
+            .sum();
+        let total = hand_points + camp_pieces.into_iter().map(points).sum::<u32>();
+
+        // Sente needs 28 points, gote 27
+        match color {
+            Color::Black => total >= 28,
+            Color::White => total >= 27,
+        }
+    }
+
+    /// Apply a move without checking legality
+    pub fn apply_move_unchecked(&mut self, mv: &Move) {
+        let color = self.side_to_move;
+        match *mv {
+            Move::Normal { from, to, promote } => {
+                if let Some(mut piece) = self.piece_at(from) {
+                    if let Some(captured) = self.piece_at(to) {
+                        if let Some(index) = captured.piece_type.unpromote().hand_index() {
+                            self.hands[color.index()][index] = self.hands[color.index()][index].saturating_add(1);
+                        }
+                    }
+                    if promote {
+                        piece.piece_type = piece.piece_type.promote().unwrap_or(piece.piece_type);
+                    }
+                    self.set_piece(from, None);
+                    self.set_piece(to, Some(piece));
+                }
+            }
+            Move::Drop { piece_type, to } => {
+                if let Some(index) = piece_type.hand_index() {
+                    self.hands[color.index()][index] = self.hands[color.index()][index].saturating_sub(1);
+                }
+                self.set_piece(to, Some(Piece { piece_type, color }));
+            }
+        }
+        self.side_to_move = color.opponent();
+        self.ply += 1;
+    }
+
+    /// Apply a move after verifying it is legal
+    pub fn apply_move(&mut self, mv: &Move) -> Result<()> {
+        if !self.is_legal(mv) {
+            return Err(anyhow!("Illegal move: {}", mv.to_usi()));
+        }
+        self.apply_move_unchecked(mv);
+        Ok(())
+    }
+
+    /// Parse and apply a USI move after verifying it is legal
+    pub fn apply_usi_move(&mut self, usi: &str) -> Result<Move> {
+        let mv = Move::from_usi(usi)?;
+        self.apply_move(&mv)?;
+        Ok(mv)
+    }
+}
+
+/// Parse a USI position description into the starting position and its move list.
+/// Accepts "position startpos moves ...", "startpos moves ...", "sfen <sfen> moves ..."
+/// and a bare "<sfen> moves ..." as stored in match state.
+pub fn parse_position(spec: &str) -> Result<(Position, Vec<Move>)> {
+    let spec = spec.trim();
+    let spec = spec.strip_prefix("position ").unwrap_or(spec).trim();
+    let (base, moves) = match spec.split_once(" moves") {
+        Some((base, moves)) => (base, moves),
+        None => (spec, ""),
+    };
+    let position = Position::from_sfen(base)?;
+    let moves = moves
+        .split_whitespace()
+        .map(Move::from_usi)
+        .collect::<Result<Vec<_>>>()?;
+    Ok((position, moves))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startpos_roundtrip_and_move_count() {
+        let position = Position::default();
+        assert_eq!(position.to_sfen(), STARTPOS_SFEN);
+        assert_eq!(position.legal_moves().len(), 30);
+    }
+
+    #[test]
+    fn test_sfen_with_hands_roundtrip() {
+        let sfen = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w 2P3pb 10";
+        let position = Position::from_sfen(sfen).unwrap();
+        assert_eq!(position.hand_count(Color::Black, PieceType::Pawn), 2);
+        assert_eq!(position.hand_count(Color::White, PieceType::Pawn), 3);
+        assert_eq!(position.to_sfen(), "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w 2Pb3p 10");
+    }
+
+    #[test]
+    fn test_apply_moves_and_capture() {
+        let (mut position, moves) =
+            parse_position("startpos moves 7g7f 3c3d 8h2b+").unwrap();
+        for mv in &moves {
+            position.apply_move(mv).unwrap();
+        }
+        assert_eq!(position.hand_count(Color::Black, PieceType::Bishop), 1);
+        assert_eq!(
+            position.piece_at(Square::from_usi("2b").unwrap()),
+            Some(Piece { piece_type: PieceType::Horse, color: Color::Black })
+        );
+        assert_eq!(position.side_to_move(), Color::White);
+    }
+
+    #[test]
+    fn test_rejects_illegal_moves() {
+        let mut position = Position::default();
+        assert!(position.apply_usi_move("7g7e").is_err());
+        assert!(position.apply_usi_move("P*5e").is_err());
+    }
+
+    #[test]
+    fn test_double_pawn_and_drop_pawn_mate() {
+        // Nifu: black already has a pawn on every file
+        let position = Position::default();
+        let with_pawn = Position::from_sfen("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b P 1").unwrap();
+        assert!(!with_pawn.legal_moves().iter().any(|mv| matches!(mv, Move::Drop { .. })));
+        assert!(position.legal_moves().iter().all(|mv| matches!(mv, Move::Normal { .. })));
+
+        // Uchifuzume: P*1b would mate the king on 1a, so it is illegal
+        let mate_by_pawn = Position::from_sfen("7lk/9/8G/9/9/9/9/9/K8 b P 1").unwrap();
+        let drop = Move::from_usi("P*1b").unwrap();
+        assert!(!mate_by_pawn.is_legal(&drop));
+    }
+
+    #[test]
+    fn test_is_legal_agrees_with_legal_moves() {
+        let positions = [
+            STARTPOS_SFEN,
+            // Pieces in hand, promotions and a king in check
+            "lnsgk2nl/1r4gs1/p1pppp1pp/6p2/1p5P1/2P6/PPBPPPP1P/2G4R1/LNS1KGSNL w Bp 12",
+            "7lk/9/8G/9/9/9/9/9/K8 b P 1",
+            "4k4/9/4b4/9/9/9/9/4R4/4K4 w GNp 1",
+        ];
+        let squares: Vec<Square> = Square::all().collect();
+        for sfen in positions {
+            let position = Position::from_sfen(sfen).unwrap();
+            let legal = position.legal_moves();
+            let normal = squares.iter().flat_map(|&from| {
+                squares.iter().flat_map(move |&to| [false, true].map(|promote| Move::Normal { from, to, promote }))
+            });
+            let drops = HAND_PIECE_TYPES
+                .iter()
+                .chain([&PieceType::King, &PieceType::Dragon])
+                .flat_map(|&piece_type| squares.iter().map(move |&to| Move::Drop { piece_type, to }));
+            for mv in normal.chain(drops) {
+                assert_eq!(position.is_legal(&mv), legal.contains(&mv), "{} in {}", mv.to_usi(), sfen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_checkmate_detection() {
+        let position = Position::from_sfen("7lk/9/8G/9/9/9/9/9/K8 b G 1").unwrap();
+        let mut after = position.clone();
+        after.apply_usi_move("G*1b").unwrap();
+        assert!(after.is_checkmate());
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        assert!(Position::default().validation_errors().is_empty());
+
+        let no_white_king = Position::from_sfen("9/9/9/9/9/9/9/9/K8 b - 1").unwrap();
+        assert_eq!(no_white_king.validation_errors(), vec!["white has 0 kings".to_string()]);
+
+        let nifu = Position::from_sfen("4k4/9/9/4P4/9/4P4/9/9/4K4 b - 1").unwrap();
+        assert_eq!(nifu.validation_errors().len(), 1);
+
+        let dead_knight = Position::from_sfen("4k3N/9/9/9/9/9/9/9/4K4 b - 1").unwrap();
+        assert!(dead_knight.validation_errors()[0].contains("1a"));
+
+        let too_many_rooks = Position::from_sfen("4k4/9/9/9/9/9/9/9/4K4 b 3R 1").unwrap();
+        assert!(too_many_rooks.validation_errors()[0].contains("Rook"));
+
+        // White's king is attacked by the rook on 5h while Black is to move
+        let mover_in_check = Position::from_sfen("4k4/9/9/9/9/9/9/4R4/3K5 b - 1").unwrap();
+        assert_eq!(mover_in_check.validation_errors().len(), 1);
+    }
+
+    #[test]
+    fn test_entering_king_declaration() {
+        // 11 pieces in camp worth 19 points, plus 9 points in hand
+        let position = Position::from_sfen("9/1R2K2B1/+P+P+P+P+P+P+P+P+P/9/9/9/9/9/k8 b 2G2S2N2LP 1").unwrap();
+        assert!(position.can_declare_win());
+
+        // 27 points is enough for gote but not for sente
+        let short = Position::from_sfen("9/1R2K2B1/+P+P+P+P+P+P+P+P+P/9/9/9/9/9/k8 b 2G2S2N2L 1").unwrap();
+        assert!(!short.can_declare_win());
+
+        assert!(!Position::default().can_declare_win());
+    }
+}
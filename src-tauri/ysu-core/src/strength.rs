@@ -0,0 +1,196 @@
+//! Playing strength levels for human-vs-engine games
+//! Each level maps to a search limit and skill-style options. Built-in presets cover common
+//! option names; users can replace a level's preset per engine in settings.
+
+use crate::engine_storage::EngineConfig;
+use crate::engine_vs_engine::SearchLimit;
+use crate::game_session::MoveRandomization;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum StrengthLevel {
+    Beginner,
+    Novice,
+    Intermediate,
+    Advanced,
+    Expert,
+    Max,
+}
+
+impl StrengthLevel {
+    pub const ALL: [StrengthLevel; 6] = [
+        StrengthLevel::Beginner,
+        StrengthLevel::Novice,
+        StrengthLevel::Intermediate,
+        StrengthLevel::Advanced,
+        StrengthLevel::Expert,
+        StrengthLevel::Max,
+    ];
+
+    /// Built-in preset. Options the engine doesn't advertise are skipped when applied,
+    /// so presets list the skill option names of several engine families.
+    pub fn default_preset(self) -> StrengthPreset {
+        let (search_limit, skill, depth) = match self {
+            StrengthLevel::Beginner => (Some(SearchLimit::Nodes { nodes: 1_000 }), Some(0), Some(1)),
+            StrengthLevel::Novice => (Some(SearchLimit::Nodes { nodes: 10_000 }), Some(4), Some(3)),
+            StrengthLevel::Intermediate => (Some(SearchLimit::Nodes { nodes: 100_000 }), Some(10), Some(6)),
+            StrengthLevel::Advanced => (Some(SearchLimit::Nodes { nodes: 1_000_000 }), Some(15), None),
+            StrengthLevel::Expert => (Some(SearchLimit::Movetime { movetime_ms: 3_000 }), None, None),
+            StrengthLevel::Max => (None, None, None),
+        };
+        let mut options = HashMap::new();
+        if let Some(skill) = skill {
+            options.insert("SkillLevel".to_string(), skill.to_string());
+        }
+        if let Some(depth) = depth {
+            options.insert("DepthLimit".to_string(), depth.to_string());
+            options.insert("USI_MaxDepth".to_string(), depth.to_string());
+        }
+        // Low levels also pick among the top moves, for engines without skill options
+        let randomization = match self {
+            StrengthLevel::Beginner => Some((5, 150.0, 1000)),
+            StrengthLevel::Novice => Some((4, 80.0, 500)),
+            StrengthLevel::Intermediate => Some((3, 30.0, 200)),
+            _ => None,
+        }
+        .map(|(multi_pv, temperature_cp, max_loss_cp)| MoveRandomization { multi_pv, temperature_cp, max_loss_cp });
+        StrengthPreset { search_limit, options, randomization }
+    }
+}
+
+/// Concrete limits for one strength level
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct StrengthPreset {
+    /// Limit for each `go`; None plays on the game clock
+    #[serde(default)]
+    pub search_limit: Option<SearchLimit>,
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+    #[serde(default)]
+    pub randomization: Option<MoveRandomization>,
+}
+
+/// A strength level resolved against one engine's options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedStrength {
+    pub level: StrengthLevel,
+    pub search_limit: Option<SearchLimit>,
+    /// `setoption` name/value pairs, using the engine's own option names
+    pub options: Vec<(String, String)>,
+    /// Random choice among the top moves; the MultiPV option is included in `options`
+    pub randomization: Option<MoveRandomization>,
+    /// Preset options the engine doesn't have
+    pub skipped: Vec<String>,
+}
+
+/// Resolve `level` for `engine`, using its custom preset from `presets` if there is one.
+/// Spin values are clamped into the option's range.
+pub fn resolve(
+    level: StrengthLevel,
+    engine: &EngineConfig,
+    presets: &HashMap<String, HashMap<StrengthLevel, StrengthPreset>>,
+) -> ResolvedStrength {
+    let preset = presets
+        .get(&engine.id)
+        .and_then(|levels| levels.get(&level))
+        .cloned()
+        .unwrap_or_else(|| level.default_preset());
+
+    let mut names: Vec<&String> = preset.options.keys().collect();
+    names.sort();
+    let mut options = Vec::new();
+    let mut skipped = Vec::new();
+    for name in names {
+        let value = &preset.options[name];
+        let Some(metadata) = &engine.metadata else {
+            options.push((name.clone(), value.clone()));
+            continue;
+        };
+        match metadata.options.iter().find(|option| option.name.eq_ignore_ascii_case(name)) {
+            Some(option) if option.option_type == "spin" => {
+                let parse = |v: &Option<String>| v.as_deref().and_then(|v| v.parse::<i64>().ok());
+                let value = match value.parse::<i64>() {
+                    Ok(number) => {
+                        let number = parse(&option.min).map_or(number, |min| number.max(min));
+                        parse(&option.max).map_or(number, |max| number.min(max)).to_string()
+                    }
+                    Err(_) => value.clone(),
+                };
+                options.push((option.name.clone(), value));
+            }
+            Some(option) => options.push((option.name.clone(), value.clone())),
+            None => skipped.push(name.clone()),
+        }
+    }
+
+    // Randomization needs several lines, so it only applies to engines with MultiPV
+    let multipv_option = match &engine.metadata {
+        Some(metadata) => metadata
+            .options
+            .iter()
+            .find(|option| option.name.eq_ignore_ascii_case("MultiPV"))
+            .map(|option| option.name.clone()),
+        None => Some("MultiPV".to_string()),
+    };
+    let randomization = match (preset.randomization, multipv_option) {
+        (Some(randomization), Some(name)) => {
+            options.retain(|(option, _)| option != &name);
+            options.push((name, randomization.multi_pv.to_string()));
+            Some(randomization)
+        }
+        (Some(_), None) => {
+            skipped.push("MultiPV".to_string());
+            None
+        }
+        (None, _) => None,
+    };
+
+    ResolvedStrength {
+        level,
+        // The clock is the session's normal behaviour, so only other limits are reported
+        search_limit: preset.search_limit.filter(|limit| *limit != SearchLimit::Clock),
+        options,
+        randomization,
+        skipped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine_validator::{EngineMetadata, EngineOption};
+
+    #[test]
+    fn test_resolve_strength() {
+        let metadata = EngineMetadata {
+            name: "Engine".to_string(),
+            options: vec![EngineOption::parse("option name SkillLevel type spin default 20 min 2 max 20").unwrap()],
+            ..Default::default()
+        };
+        let engine = EngineConfig::new("Engine".to_string(), "/engines/engine".to_string(), Some(metadata), false);
+
+        let beginner = resolve(StrengthLevel::Beginner, &engine, &HashMap::new());
+        assert_eq!(beginner.search_limit, Some(SearchLimit::Nodes { nodes: 1_000 }));
+        assert_eq!(beginner.options, vec![("SkillLevel".to_string(), "2".to_string())]);
+        assert_eq!(
+            beginner.skipped,
+            vec!["DepthLimit".to_string(), "USI_MaxDepth".to_string(), "MultiPV".to_string()]
+        );
+        assert!(beginner.randomization.is_none());
+
+        let custom = StrengthPreset {
+            search_limit: Some(SearchLimit::Depth { depth: 2 }),
+            options: HashMap::new(),
+            randomization: None,
+        };
+        let presets = HashMap::from([(engine.id.clone(), HashMap::from([(StrengthLevel::Beginner, custom)]))]);
+        let beginner = resolve(StrengthLevel::Beginner, &engine, &presets);
+        assert_eq!(beginner.search_limit, Some(SearchLimit::Depth { depth: 2 }));
+        assert!(beginner.options.is_empty());
+        assert_eq!(resolve(StrengthLevel::Max, &engine, &presets).search_limit, None);
+    }
+}
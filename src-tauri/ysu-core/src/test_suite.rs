@@ -0,0 +1,230 @@
+//! Test suites of positions with known best moves, the shogi counterpart of EPD suites
+//! Each line of a suite file is a position followed by `;`-separated fields:
+//!
+//! ```text
+//! # comment
+//! lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1; bm 7g7f 2g2f; id opening-1
+//! startpos moves 7g7f 3c3d; bm 2g2f; am 8h2b+
+//! ```
+//!
+//! `bm` lists moves that solve the position and `am` moves that fail it, both in USI.
+
+use crate::engine_process;
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_vs_engine::{move_timeout, spawn_initialized_engine, EngineVsEngineManager};
+use crate::events::{SharedEventSink, TEST_SUITE_PROGRESS};
+use crate::shogi_rules::{parse_position, Move};
+use crate::usi_info::Score;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+/// One position of a suite
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestPosition {
+    pub id: String,
+    /// The position after any moves given in the suite line
+    pub sfen: String,
+    pub best_moves: Vec<String>,
+    pub avoid_moves: Vec<String>,
+}
+
+impl TestPosition {
+    /// Whether `engine_move` solves the position
+    pub fn is_solved_by(&self, engine_move: &str) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.iter().any(|mv| mv == engine_move))
+            && !self.avoid_moves.iter().any(|mv| mv == engine_move)
+    }
+}
+
+fn usi_moves(field: &str, line_number: usize) -> Result<Vec<String>> {
+    field
+        .split_whitespace()
+        .map(|mv| {
+            Move::from_usi(mv)
+                .map(Move::to_usi)
+                .map_err(|e| anyhow!("Line {}: {}", line_number, e))
+        })
+        .collect()
+}
+
+/// Parse a suite file. Positions without an `id` are named after their line number.
+pub fn parse_suite(text: &str) -> Result<Vec<TestPosition>> {
+    let mut positions = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split(';').map(str::trim);
+        let spec = fields.next().unwrap_or_default();
+        let (mut position, moves) = parse_position(spec).map_err(|e| anyhow!("Line {}: {}", line_number, e))?;
+        for mv in &moves {
+            position.apply_move(mv).map_err(|e| anyhow!("Line {}: {}", line_number, e))?;
+        }
+
+        let mut test = TestPosition {
+            id: format!("line-{}", line_number),
+            sfen: position.to_sfen(),
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+        };
+        for field in fields.filter(|field| !field.is_empty()) {
+            let (key, value) = field.split_once(char::is_whitespace).unwrap_or((field, ""));
+            match key {
+                "bm" => test.best_moves = usi_moves(value, line_number)?,
+                "am" => test.avoid_moves = usi_moves(value, line_number)?,
+                "id" => test.id = value.trim().trim_matches('"').to_string(),
+                other => return Err(anyhow!("Line {}: unknown field '{}'", line_number, other)),
+            }
+        }
+        if test.best_moves.is_empty() && test.avoid_moves.is_empty() {
+            return Err(anyhow!("Line {}: no bm or am field", line_number));
+        }
+        positions.push(test);
+    }
+    Ok(positions)
+}
+
+/// Outcome of one position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct TestResult {
+    pub id: String,
+    pub sfen: String,
+    pub engine_move: Option<String>,
+    pub solved: bool,
+    pub time_ms: u64,
+    pub score: Option<Score>,
+    pub depth: Option<u32>,
+    pub error: Option<String>,
+}
+
+/// Results of running a suite through one engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuiteReport {
+    pub engine_id: String,
+    pub engine_name: String,
+    pub time_per_position_ms: u64,
+    pub solved: usize,
+    pub total: usize,
+    pub results: Vec<TestResult>,
+}
+
+/// Emitted as `test-suite-progress` after each position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct SuiteProgress {
+    pub index: usize,
+    pub total: usize,
+    pub result: TestResult,
+}
+
+/// Search every position for `time_per_position_ms` with the engine and compare its move
+/// with the expected ones. The engine runs with its saved options and eval file.
+pub async fn run_suite(
+    engine: &EngineConfig,
+    engine_storage: &RwLock<EngineStorage>,
+    positions: &[TestPosition],
+    time_per_position_ms: u64,
+    events: SharedEventSink,
+) -> Result<SuiteReport> {
+    let (mut child, mut stdin, mut stdout) = spawn_initialized_engine(engine, engine_storage).await?;
+
+    let go_cmd = format!("go movetime {}", time_per_position_ms);
+    let mut results = Vec::with_capacity(positions.len());
+    for (index, test) in positions.iter().enumerate() {
+        let start = tokio::time::Instant::now();
+        stdin.write_all(b"usinewgame\n").await?;
+        let search = EngineVsEngineManager::request_move(
+            &mut stdin,
+            &mut stdout,
+            &test.sfen,
+            &[],
+            &go_cmd,
+            move_timeout(time_per_position_ms),
+            |_, _| {},
+        )
+        .await;
+        let time_ms = start.elapsed().as_millis() as u64;
+
+        let result = match search {
+            Ok(engine_move) => TestResult {
+                id: test.id.clone(),
+                sfen: test.sfen.clone(),
+                solved: test.is_solved_by(&engine_move.best_move),
+                time_ms,
+                score: engine_move.info.as_ref().and_then(|info| info.score),
+                depth: engine_move.info.as_ref().and_then(|info| info.depth),
+                engine_move: Some(engine_move.best_move),
+                error: None,
+            },
+            Err(e) => TestResult {
+                id: test.id.clone(),
+                sfen: test.sfen.clone(),
+                engine_move: None,
+                solved: false,
+                time_ms,
+                score: None,
+                depth: None,
+                error: Some(e.to_string()),
+            },
+        };
+        log::info!("Test position {}: {:?} solved={}", result.id, result.engine_move, result.solved);
+        let _ = events.send(&TEST_SUITE_PROGRESS, &SuiteProgress {
+            index,
+            total: positions.len(),
+            result: result.clone(),
+        });
+        let failed = result.error.is_some();
+        results.push(result);
+        if failed {
+            // The engine died or hung; the remaining positions would fail the same way
+            break;
+        }
+    }
+
+    let _ = stdin.write_all(b"quit\n").await;
+    let _ = stdin.flush().await;
+    engine_process::kill(&mut child).await;
+
+    Ok(SuiteReport {
+        engine_id: engine.id.clone(),
+        engine_name: engine.display_name.clone(),
+        time_per_position_ms,
+        solved: results.iter().filter(|result| result.solved).count(),
+        total: positions.len(),
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_suite() {
+        let suite = parse_suite(
+            "# opening\n\
+             lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1; bm 7g7f 2g2f; id \"opening-1\"\n\
+             \n\
+             startpos moves 7g7f 3c3d; am 8h2b+\n",
+        )
+        .unwrap();
+        assert_eq!(suite.len(), 2);
+        assert_eq!(suite[0].id, "opening-1");
+        assert!(suite[0].is_solved_by("2g2f"));
+        assert!(!suite[0].is_solved_by("5g5f"));
+        assert_eq!(suite[1].id, "line-4");
+        assert_eq!(suite[1].sfen, "lnsgkgsnl/1r5b1/pppppp1pp/6p2/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL b - 3");
+        assert!(!suite[1].is_solved_by("8h2b+"));
+        assert!(suite[1].is_solved_by("2g2f"));
+
+        assert!(parse_suite("startpos; id no-expectation").is_err());
+        assert!(parse_suite("startpos; bm 7g7f; xx 1").is_err());
+        assert!(parse_suite("startpos moves 7g7e; bm 2g2f").is_err());
+    }
+}
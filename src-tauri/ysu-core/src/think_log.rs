@@ -0,0 +1,83 @@
+//! Archived thinking output of engine-vs-engine games
+//! A match with `record_thinking` set keeps every `info` line each engine sends, by ply, and
+//! writes them when the game ends to `think_logs/<match id>.jsonl.gz`: one gzip-compressed
+//! JSON line per ply. The game record references the file by name.
+
+use crate::atomic_file;
+use crate::engine_storage::EngineStorage;
+use anyhow::{anyhow, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Everything one engine printed while searching one move
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlyThinking {
+    /// 1-based ply of the move the search produced
+    pub ply: usize,
+    /// "black" or "white"
+    pub side: String,
+    pub engine_name: String,
+    /// Raw `info` lines in the order they arrived
+    pub lines: Vec<String>,
+}
+
+pub struct ThinkLogStorage;
+
+impl ThinkLogStorage {
+    pub fn get_think_logs_dir() -> Result<PathBuf> {
+        let dir = EngineStorage::get_config_dir()?.join("think_logs");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    pub fn file_name(game_id: &str) -> String {
+        format!("{}.jsonl.gz", game_id)
+    }
+
+    fn log_path(game_id: &str) -> Result<PathBuf> {
+        Uuid::parse_str(game_id).map_err(|_| anyhow!("Invalid game ID: {}", game_id))?;
+        Ok(Self::get_think_logs_dir()?.join(Self::file_name(game_id)))
+    }
+
+    /// Write the log of a game, returning its file name
+    pub async fn save(game_id: &str, plies: &[PlyThinking]) -> Result<String> {
+        let path = Self::log_path(game_id)?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        for ply in plies {
+            serde_json::to_writer(&mut encoder, ply)?;
+            encoder.write_all(b"\n")?;
+        }
+        atomic_file::write_atomic(&path, &encoder.finish()?).await?;
+        Ok(Self::file_name(game_id))
+    }
+
+    /// The thinking behind the move at `ply` (1-based)
+    pub async fn load_ply(game_id: &str, ply: usize) -> Result<PlyThinking> {
+        let path = Self::log_path(game_id)?;
+        if !path.exists() {
+            return Err(anyhow!("No thinking log for game {}", game_id));
+        }
+        let compressed = tokio::fs::read(&path).await?;
+        for line in BufReader::new(GzDecoder::new(compressed.as_slice())).lines() {
+            let entry: PlyThinking = serde_json::from_str(&line?)?;
+            if entry.ply == ply {
+                return Ok(entry);
+            }
+        }
+        Err(anyhow!("No thinking recorded for ply {} of game {}", ply, game_id))
+    }
+
+    /// Remove the log of a game; a missing file is not an error
+    pub async fn delete(game_id: &str) -> Result<()> {
+        let path = Self::log_path(game_id)?;
+        if path.exists() {
+            tokio::fs::remove_file(&path).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! How long to wait on engines at each step of the protocol
+//! App-wide values come from the settings and are held here for the whole process. An engine's
+//! config may override any of them, since large NNUE and deep-learning engines can take far
+//! longer to start or to return a move than the defaults allow.
+
+use crate::engine_storage::EngineConfig;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUTS: TimeoutConfig = TimeoutConfig {
+    usiok_ms: 10_000,
+    readyok_ms: 60_000,
+    option_send_ms: 2000,
+    bestmove_slack_ms: 10_000,
+    validation_ms: 5000,
+};
+
+static GLOBAL: RwLock<TimeoutConfig> = RwLock::new(DEFAULT_TIMEOUTS);
+
+fn default_usiok_ms() -> u64 {
+    DEFAULT_TIMEOUTS.usiok_ms
+}
+
+fn default_readyok_ms() -> u64 {
+    DEFAULT_TIMEOUTS.readyok_ms
+}
+
+fn default_option_send_ms() -> u64 {
+    DEFAULT_TIMEOUTS.option_send_ms
+}
+
+fn default_bestmove_slack_ms() -> u64 {
+    DEFAULT_TIMEOUTS.bestmove_slack_ms
+}
+
+fn default_validation_ms() -> u64 {
+    DEFAULT_TIMEOUTS.validation_ms
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct TimeoutConfig {
+    /// Wait for `usiok` after `usi` when starting an engine
+    #[serde(default = "default_usiok_ms")]
+    pub usiok_ms: u64,
+    /// Wait for `readyok` after `isready`; engines load their eval files at this point
+    #[serde(default = "default_readyok_ms")]
+    pub readyok_ms: u64,
+    /// Writing one `setoption` command
+    #[serde(default = "default_option_send_ms")]
+    pub option_send_ms: u64,
+    /// Time allowed beyond the search time for `bestmove` before the engine forfeits
+    #[serde(default = "default_bestmove_slack_ms")]
+    pub bestmove_slack_ms: u64,
+    /// Wait for `usiok` when validating or health-checking an engine
+    #[serde(default = "default_validation_ms")]
+    pub validation_ms: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        DEFAULT_TIMEOUTS
+    }
+}
+
+/// Per-engine replacements for app-wide timeouts; unset fields keep the app-wide value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutOverrides {
+    pub usiok_ms: Option<u64>,
+    pub readyok_ms: Option<u64>,
+    pub option_send_ms: Option<u64>,
+    pub bestmove_slack_ms: Option<u64>,
+    pub validation_ms: Option<u64>,
+}
+
+impl TimeoutOverrides {
+    pub fn validate(&self) -> Result<()> {
+        let values = [
+            self.usiok_ms,
+            self.readyok_ms,
+            self.option_send_ms,
+            self.bestmove_slack_ms,
+            self.validation_ms,
+        ];
+        if values.contains(&Some(0)) {
+            return Err(anyhow!("Timeouts must be positive"));
+        }
+        Ok(())
+    }
+}
+
+impl TimeoutConfig {
+    /// The app-wide timeouts
+    pub fn global() -> Self {
+        *GLOBAL.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replace the app-wide timeouts; engines started from now on use them
+    pub fn set_global(config: TimeoutConfig) {
+        *GLOBAL.write().unwrap_or_else(|e| e.into_inner()) = config;
+    }
+
+    /// The app-wide timeouts with `engine`'s overrides applied
+    pub fn for_engine(engine: Option<&EngineConfig>) -> Self {
+        Self::global().with_overrides(engine.and_then(|engine| engine.timeouts.as_ref()))
+    }
+
+    pub fn with_overrides(&self, overrides: Option<&TimeoutOverrides>) -> Self {
+        let Some(overrides) = overrides else {
+            return *self;
+        };
+        Self {
+            usiok_ms: overrides.usiok_ms.unwrap_or(self.usiok_ms),
+            readyok_ms: overrides.readyok_ms.unwrap_or(self.readyok_ms),
+            option_send_ms: overrides.option_send_ms.unwrap_or(self.option_send_ms),
+            bestmove_slack_ms: overrides.bestmove_slack_ms.unwrap_or(self.bestmove_slack_ms),
+            validation_ms: overrides.validation_ms.unwrap_or(self.validation_ms),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let values = [
+            self.usiok_ms,
+            self.readyok_ms,
+            self.option_send_ms,
+            self.bestmove_slack_ms,
+            self.validation_ms,
+        ];
+        if values.contains(&0) {
+            return Err(anyhow!("Timeouts must be positive"));
+        }
+        Ok(())
+    }
+
+    /// How long an engine may think on a search of `time_ms` before it forfeits
+    pub fn move_timeout(&self, time_ms: u64) -> Duration {
+        Duration::from_secs(time_ms / 1000) + Duration::from_millis(self.bestmove_slack_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_replace_only_set_fields() {
+        let base = TimeoutConfig::default();
+        let overrides = TimeoutOverrides {
+            readyok_ms: Some(300_000),
+            ..Default::default()
+        };
+        let resolved = base.with_overrides(Some(&overrides));
+        assert_eq!(resolved.readyok_ms, 300_000);
+        assert_eq!(resolved.usiok_ms, base.usiok_ms);
+        assert_eq!(base.with_overrides(None), base);
+
+        assert!(overrides.validate().is_ok());
+        assert!(TimeoutOverrides { usiok_ms: Some(0), ..Default::default() }.validate().is_err());
+        assert_eq!(base.move_timeout(1500), Duration::from_secs(11));
+
+        let partial: TimeoutConfig = serde_json::from_str(r#"{ "usiok_ms": 20000 }"#).unwrap();
+        assert_eq!(partial, TimeoutConfig { usiok_ms: 20_000, ..base });
+    }
+}
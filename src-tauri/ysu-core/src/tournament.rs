@@ -0,0 +1,878 @@
+//! Round-robin and Swiss tournaments between configured engines
+//! In a round robin every pair of engines plays `games_per_pairing` games with colours
+//! alternating, through the match runner. A Swiss tournament plays a fixed number of rounds,
+//! pairing engines with equal or similar scores that have not met yet, so large pools need
+//! far fewer games. The tournament is saved after every game, so a stopped or interrupted run
+//! continues with the first unplayed game.
+
+use crate::adjudication::AdjudicationConfig;
+use crate::atomic_file;
+use crate::engine_storage::{EngineConfig, EngineStorage};
+use crate::engine_vs_engine::{splitmix64, EngineVsEngineConfig, EngineVsEngineManager, SearchLimit};
+use crate::events::{SharedEventSink, TOURNAMENT_PROGRESS, TOURNAMENT_STANDINGS};
+use crate::series_stats::SeriesStats;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Games whose durations make up the moving average behind the ETA
+const ETA_WINDOW: usize = 10;
+/// Progress is also emitted this often while a long game is being played
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+/// Pairing attempts before a Swiss round gives up on avoiding rematches
+const PAIRING_BUDGET: usize = 100_000;
+
+fn default_games_per_pairing() -> u32 {
+    2
+}
+
+fn default_max_moves() -> usize {
+    256
+}
+
+/// How games are scheduled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "system", rename_all = "snake_case")]
+pub enum Pairing {
+    /// Every engine meets every other engine
+    #[default]
+    RoundRobin,
+    /// `rounds` rounds, each paired from the standings after the previous one. Round 1
+    /// pairs engines in the order they are listed.
+    Swiss { rounds: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentConfig {
+    pub name: String,
+    /// Config IDs of the participating engines
+    pub engine_ids: Vec<String>,
+    /// Games each pair of engines plays; colours alternate, and each two games of a pairing
+    /// start from the same opening
+    #[serde(default = "default_games_per_pairing")]
+    pub games_per_pairing: u32,
+    pub time_per_move_ms: u64,
+    #[serde(default)]
+    pub search_limit: SearchLimit,
+    #[serde(default = "default_max_moves")]
+    pub max_moves: usize,
+    #[serde(default)]
+    pub adjudication: AdjudicationConfig,
+    #[serde(default)]
+    pub openings: Vec<String>,
+    #[serde(default)]
+    pub pairing: Pairing,
+}
+
+impl TournamentConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.engine_ids.len() < 2 {
+            return Err(anyhow!("A tournament needs at least two engines"));
+        }
+        for (index, engine_id) in self.engine_ids.iter().enumerate() {
+            if self.engine_ids[..index].contains(engine_id) {
+                return Err(anyhow!("Engine {} is listed twice", engine_id));
+            }
+        }
+        if self.games_per_pairing == 0 {
+            return Err(anyhow!("games_per_pairing must be at least 1"));
+        }
+        if self.pairing == (Pairing::Swiss { rounds: 0 }) {
+            return Err(anyhow!("A Swiss tournament needs at least one round"));
+        }
+        self.search_limit.validate()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum TournamentStatus {
+    Running,
+    Paused,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameOutcome {
+    BlackWin,
+    WhiteWin,
+    Draw,
+}
+
+/// One scheduled game; the result fields are filled in once it has been played
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentGame {
+    /// Swiss round, or the game's number within its pairing in a round robin
+    pub round: u32,
+    pub black_id: String,
+    pub white_id: String,
+    /// Seed of the match, shared by the two games of a colour-swapped pair
+    pub seed: u64,
+    pub match_id: Option<String>,
+    pub outcome: Option<GameOutcome>,
+    pub plies: usize,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tournament {
+    pub id: String,
+    pub config: TournamentConfig,
+    /// Engine names at creation, by config ID, so results stay readable after an engine is renamed
+    pub engine_names: HashMap<String, String>,
+    pub status: TournamentStatus,
+    /// Games scheduled so far; a Swiss tournament adds each round once the previous one ends
+    pub games: Vec<TournamentGame>,
+    /// Engines left out of a Swiss round with an odd number of engines
+    #[serde(default)]
+    pub byes: Vec<Bye>,
+    /// Statistics of each pairing over its played games, updated after every game
+    #[serde(default)]
+    pub head_to_head: Vec<HeadToHead>,
+    /// Source of the game seeds of later Swiss rounds
+    #[serde(default)]
+    pub seed: u64,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A Swiss round sat out, scored as a win of each of the round's games
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bye {
+    pub round: u32,
+    pub engine_id: String,
+}
+
+/// Score of one engine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct Standing {
+    pub engine_id: String,
+    pub name: String,
+    pub points: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub games: u32,
+    /// Sum of the points of every opponent met, once per game; the Swiss tiebreak
+    pub buchholz: f64,
+}
+
+/// Results between two engines, from the point of view of `engine_a_id`, the one listed first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct HeadToHead {
+    pub engine_a_id: String,
+    pub engine_b_id: String,
+    pub stats: SeriesStats,
+}
+
+/// Emitted as `tournament-standings` when a round has been played
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct RoundStandings {
+    pub tournament_id: String,
+    pub round: u32,
+    pub standings: Vec<Standing>,
+}
+
+/// Emitted as `tournament-progress` after every game and every PROGRESS_INTERVAL in between
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct TournamentProgress {
+    pub tournament_id: String,
+    pub status: TournamentStatus,
+    pub games_completed: usize,
+    pub games_total: usize,
+    pub standings: Vec<Standing>,
+    pub head_to_head: Vec<HeadToHead>,
+    pub average_plies: Option<f64>,
+    /// Moving average over the last ETA_WINDOW games
+    pub average_game_ms: Option<u64>,
+    pub eta_ms: Option<u64>,
+}
+
+impl Tournament {
+    /// Schedule a tournament: all games of a round robin, or the first round of a Swiss one.
+    /// `engine_names` maps config IDs to display names.
+    pub fn new(config: TournamentConfig, engine_names: HashMap<String, String>) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut tournament = Self {
+            id: Uuid::new_v4().to_string(),
+            config,
+            engine_names,
+            status: TournamentStatus::Paused,
+            games: Vec::new(),
+            byes: Vec::new(),
+            head_to_head: Vec::new(),
+            seed: Uuid::new_v4().as_u64_pair().0,
+            error: None,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        match tournament.config.pairing {
+            Pairing::RoundRobin => tournament.schedule_round_robin(),
+            Pairing::Swiss { .. } => {
+                tournament.pair_next_round();
+            }
+        }
+        tournament
+    }
+
+    fn schedule_round_robin(&mut self) {
+        let engine_ids = &self.config.engine_ids;
+        for game in 0..self.config.games_per_pairing {
+            for (i, first) in engine_ids.iter().enumerate() {
+                for (j, second) in engine_ids.iter().enumerate().skip(i + 1) {
+                    let (black, white) = if game % 2 == 0 { (first, second) } else { (second, first) };
+                    let pairing = (i * engine_ids.len() + j) as u64;
+                    self.games.push(TournamentGame {
+                        round: game + 1,
+                        black_id: black.clone(),
+                        white_id: white.clone(),
+                        seed: splitmix64(self.seed ^ (pairing << 32) ^ (game / 2) as u64),
+                        match_id: None,
+                        outcome: None,
+                        plies: 0,
+                        duration_ms: 0,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Swiss rounds paired so far
+    fn rounds_paired(&self) -> u32 {
+        self.games.iter().map(|game| game.round).chain(self.byes.iter().map(|bye| bye.round)).max().unwrap_or(0)
+    }
+
+    /// Pair the next Swiss round once every scheduled game has been played. Engines are ranked
+    /// by points and Buchholz; the lowest-ranked engine without a bye sits out when their
+    /// number is odd, and each engine is paired with the highest-ranked one it has not met.
+    /// Returns the index of the round's first game, or None when there is nothing to pair.
+    pub fn pair_next_round(&mut self) -> Option<usize> {
+        let Pairing::Swiss { rounds } = self.config.pairing else {
+            return None;
+        };
+        let round = self.rounds_paired() + 1;
+        if round > rounds || self.played().count() < self.games.len() {
+            return None;
+        }
+
+        let mut ranked: Vec<String> = if round == 1 {
+            self.config.engine_ids.clone()
+        } else {
+            self.standings().into_iter().map(|standing| standing.engine_id).collect()
+        };
+        if ranked.len() % 2 == 1 {
+            let sits_out = ranked
+                .iter()
+                .rposition(|engine_id| !self.byes.iter().any(|bye| &bye.engine_id == engine_id))
+                .unwrap_or(ranked.len() - 1);
+            let engine_id = ranked.remove(sits_out);
+            self.byes.push(Bye { round, engine_id });
+        }
+
+        let met: HashSet<(&str, &str)> = self
+            .games
+            .iter()
+            .flat_map(|game| {
+                [(game.black_id.as_str(), game.white_id.as_str()), (game.white_id.as_str(), game.black_id.as_str())]
+            })
+            .collect();
+        let mut budget = PAIRING_BUDGET;
+        let pairs = pair_unmet(&ranked, &met, &mut budget).unwrap_or_else(|| {
+            log::warn!("Tournament {} round {}: no pairing avoids rematches", self.id, round);
+            ranked.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect()
+        });
+
+        // Black goes first to the engine that has had it less often
+        let mut black_balance: HashMap<&str, i64> = HashMap::new();
+        for game in &self.games {
+            *black_balance.entry(&game.black_id).or_default() += 1;
+            *black_balance.entry(&game.white_id).or_default() -= 1;
+        }
+        let first_game = self.games.len();
+        let mut scheduled = Vec::new();
+        for (index, (higher, lower)) in pairs.into_iter().enumerate() {
+            let balance = |engine_id: &str| black_balance.get(engine_id).copied().unwrap_or(0);
+            let (first, second) = if balance(&lower) < balance(&higher) { (lower, higher) } else { (higher, lower) };
+            for game in 0..self.config.games_per_pairing {
+                let (black, white) = if game % 2 == 0 { (&first, &second) } else { (&second, &first) };
+                scheduled.push(TournamentGame {
+                    round,
+                    black_id: black.clone(),
+                    white_id: white.clone(),
+                    seed: splitmix64(self.seed ^ ((round as u64) << 48) ^ ((index as u64) << 32) ^ (game / 2) as u64),
+                    match_id: None,
+                    outcome: None,
+                    plies: 0,
+                    duration_ms: 0,
+                });
+            }
+        }
+        self.games.extend(scheduled);
+        Some(first_game)
+    }
+
+    /// Every game of every round has been played
+    pub fn is_complete(&self) -> bool {
+        let all_paired = match self.config.pairing {
+            Pairing::RoundRobin => true,
+            Pairing::Swiss { rounds } => self.rounds_paired() >= rounds,
+        };
+        all_paired && self.played().count() == self.games.len()
+    }
+
+    /// Games the finished tournament will have, including Swiss rounds not yet paired
+    pub fn games_total(&self) -> usize {
+        match self.config.pairing {
+            Pairing::RoundRobin => self.games.len(),
+            Pairing::Swiss { rounds } => {
+                let per_round = self.config.engine_ids.len() / 2 * self.config.games_per_pairing as usize;
+                let unpaired = rounds.saturating_sub(self.rounds_paired()) as usize;
+                self.games.len() + unpaired * per_round
+            }
+        }
+    }
+
+    pub fn name_of(&self, engine_id: &str) -> String {
+        self.engine_names.get(engine_id).cloned().unwrap_or_else(|| engine_id.to_string())
+    }
+
+    fn played(&self) -> impl Iterator<Item = &TournamentGame> {
+        self.games.iter().filter(|game| game.outcome.is_some())
+    }
+
+    /// Standings by points, then Buchholz, then name
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self
+            .config
+            .engine_ids
+            .iter()
+            .map(|engine_id| Standing {
+                engine_id: engine_id.clone(),
+                name: self.name_of(engine_id),
+                points: 0.0,
+                wins: 0,
+                losses: 0,
+                draws: 0,
+                games: 0,
+                buchholz: 0.0,
+            })
+            .collect();
+        for game in self.played() {
+            // 1 for a Black win, -1 for a White win
+            let black_result = match game.outcome {
+                Some(GameOutcome::BlackWin) => 1,
+                Some(GameOutcome::WhiteWin) => -1,
+                _ => 0,
+            };
+            for (engine_id, result) in [(&game.black_id, black_result), (&game.white_id, -black_result)] {
+                let Some(standing) = standings.iter_mut().find(|standing| &standing.engine_id == engine_id) else {
+                    continue;
+                };
+                standing.games += 1;
+                match result {
+                    1 => {
+                        standing.wins += 1;
+                        standing.points += 1.0;
+                    }
+                    -1 => standing.losses += 1,
+                    _ => {
+                        standing.draws += 1;
+                        standing.points += 0.5;
+                    }
+                }
+            }
+        }
+        for bye in &self.byes {
+            if let Some(standing) = standings.iter_mut().find(|standing| standing.engine_id == bye.engine_id) {
+                standing.points += self.config.games_per_pairing as f64;
+            }
+        }
+
+        let points: HashMap<String, f64> =
+            standings.iter().map(|standing| (standing.engine_id.clone(), standing.points)).collect();
+        for game in self.played() {
+            for (engine_id, opponent_id) in [(&game.black_id, &game.white_id), (&game.white_id, &game.black_id)] {
+                if let Some(standing) = standings.iter_mut().find(|standing| &standing.engine_id == engine_id) {
+                    standing.buchholz += points.get(opponent_id).copied().unwrap_or(0.0);
+                }
+            }
+        }
+
+        standings.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then_with(|| b.buchholz.total_cmp(&a.buchholz))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        standings
+    }
+
+    /// Statistics of every pairing that has played. Once each played game of a pairing has
+    /// its colour-swapped partner (same seed) played too, the pairs are scored pentanomially;
+    /// until then the games are scored one by one.
+    pub fn compute_head_to_head(&self) -> Vec<HeadToHead> {
+        let mut head_to_head = Vec::new();
+        for (index, engine_a) in self.config.engine_ids.iter().enumerate() {
+            for engine_b in &self.config.engine_ids[index + 1..] {
+                // Engine A's score in each game, grouped by seed
+                let mut by_seed: Vec<(u64, Vec<f64>)> = Vec::new();
+                for game in self.played() {
+                    let a_is_black = match (&game.black_id, &game.white_id) {
+                        (black, white) if black == engine_a && white == engine_b => true,
+                        (black, white) if black == engine_b && white == engine_a => false,
+                        _ => continue,
+                    };
+                    let score = match (game.outcome, a_is_black) {
+                        (Some(GameOutcome::Draw), _) => 0.5,
+                        (Some(GameOutcome::BlackWin), true) | (Some(GameOutcome::WhiteWin), false) => 1.0,
+                        _ => 0.0,
+                    };
+                    match by_seed.iter_mut().find(|(seed, _)| *seed == game.seed) {
+                        Some((_, scores)) => scores.push(score),
+                        None => by_seed.push((game.seed, vec![score])),
+                    }
+                }
+                if by_seed.is_empty() {
+                    continue;
+                }
+                let stats = if by_seed.iter().all(|(_, scores)| scores.len() == 2) {
+                    let pairs: Vec<(f64, f64)> = by_seed.iter().map(|(_, scores)| (scores[0], scores[1])).collect();
+                    SeriesStats::from_pairs(&pairs)
+                } else {
+                    let scores: Vec<f64> = by_seed.into_iter().flat_map(|(_, scores)| scores).collect();
+                    SeriesStats::from_games(&scores)
+                };
+                head_to_head.push(HeadToHead {
+                    engine_a_id: engine_a.clone(),
+                    engine_b_id: engine_b.clone(),
+                    stats,
+                });
+            }
+        }
+        head_to_head
+    }
+
+    /// Progress with an ETA from the recent average game duration, less the time the game
+    /// in progress has already taken
+    pub fn progress(&self, current_game_elapsed_ms: u64) -> TournamentProgress {
+        let played: Vec<&TournamentGame> = self.played().collect();
+        let recent = &played[played.len().saturating_sub(ETA_WINDOW)..];
+        let average_game_ms = (!recent.is_empty())
+            .then(|| recent.iter().map(|game| game.duration_ms).sum::<u64>() / recent.len() as u64);
+        let games_total = self.games_total();
+        let remaining = (games_total - played.len()) as u64;
+        let eta_ms = average_game_ms.map(|average| (average * remaining).saturating_sub(current_game_elapsed_ms));
+
+        TournamentProgress {
+            tournament_id: self.id.clone(),
+            status: self.status,
+            games_completed: played.len(),
+            games_total,
+            standings: self.standings(),
+            head_to_head: self.compute_head_to_head(),
+            average_plies: (!played.is_empty())
+                .then(|| played.iter().map(|game| game.plies as f64).sum::<f64>() / played.len() as f64),
+            average_game_ms,
+            eta_ms,
+        }
+    }
+}
+
+/// Pair `ranked` in order so that no pair has met before, trying the highest-ranked unmet
+/// opponent first. Gives up once `budget` attempts are used up.
+fn pair_unmet(ranked: &[String], met: &HashSet<(&str, &str)>, budget: &mut usize) -> Option<Vec<(String, String)>> {
+    let Some((first, rest)) = ranked.split_first() else {
+        return Some(Vec::new());
+    };
+    for (index, opponent) in rest.iter().enumerate() {
+        if *budget == 0 {
+            return None;
+        }
+        *budget -= 1;
+        if met.contains(&(first.as_str(), opponent.as_str())) {
+            continue;
+        }
+        let mut remaining = rest.to_vec();
+        remaining.remove(index);
+        if let Some(mut pairs) = pair_unmet(&remaining, met, budget) {
+            pairs.insert(0, (first.clone(), opponent.clone()));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+/// File formats for rating tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultsFormat {
+    /// Tag-only PGN games, read by ordo, bayeselo and cutechess tools. PGN's first mover is
+    /// "White", so the shogi Black (sente) player goes in the White tag.
+    Pgn,
+    /// `black,white,result` rows with the result as Black's score (1, 0.5 or 0)
+    Csv,
+}
+
+fn pgn_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The played games of a tournament in `format`
+pub fn export_results(tournament: &Tournament, format: ResultsFormat) -> String {
+    let mut out = String::new();
+    if format == ResultsFormat::Csv {
+        out.push_str("black,white,result\n");
+    }
+    // RFC 3339 starts with the date; PGN wants it as YYYY.MM.DD
+    let date = tournament.created_at.get(..10).unwrap_or("????-??-??").replace('-', ".");
+
+    for game in tournament.games.iter().filter(|game| game.outcome.is_some()) {
+        let (black, white) = (tournament.name_of(&game.black_id), tournament.name_of(&game.white_id));
+        let (pgn_result, black_score) = match game.outcome {
+            Some(GameOutcome::BlackWin) => ("1-0", "1"),
+            Some(GameOutcome::WhiteWin) => ("0-1", "0"),
+            _ => ("1/2-1/2", "0.5"),
+        };
+        match format {
+            ResultsFormat::Pgn => {
+                out.push_str(&format!("[Event \"{}\"]\n", pgn_tag_value(&tournament.config.name)));
+                out.push_str("[Site \"?\"]\n");
+                out.push_str(&format!("[Date \"{}\"]\n", date));
+                out.push_str(&format!("[Round \"{}\"]\n", game.round));
+                out.push_str(&format!("[White \"{}\"]\n", pgn_tag_value(&black)));
+                out.push_str(&format!("[Black \"{}\"]\n", pgn_tag_value(&white)));
+                out.push_str(&format!("[Result \"{}\"]\n", pgn_result));
+                out.push_str(&format!("[PlyCount \"{}\"]\n\n", game.plies));
+                out.push_str(&format!("{}\n\n", pgn_result));
+            }
+            ResultsFormat::Csv => {
+                out.push_str(&format!("{},{},{}\n", csv_field(&black), csv_field(&white), black_score));
+            }
+        }
+    }
+    out
+}
+
+pub struct TournamentStorage;
+
+impl TournamentStorage {
+    /// Directory holding one `<id>.json` file per tournament
+    pub fn get_tournaments_dir() -> Result<PathBuf> {
+        let dir = EngineStorage::get_config_dir()?.join("tournaments");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    fn tournament_path(tournament_id: &str) -> Result<PathBuf> {
+        Uuid::parse_str(tournament_id).map_err(|_| anyhow!("Invalid tournament ID: {}", tournament_id))?;
+        Ok(Self::get_tournaments_dir()?.join(format!("{}.json", tournament_id)))
+    }
+
+    pub async fn save(tournament: &Tournament) -> Result<()> {
+        let path = Self::tournament_path(&tournament.id)?;
+        let contents = serde_json::to_string_pretty(tournament)?;
+        atomic_file::write_atomic(&path, contents.as_bytes()).await
+    }
+
+    pub async fn load(tournament_id: &str) -> Result<Tournament> {
+        let path = Self::tournament_path(tournament_id)?;
+        if !path.exists() {
+            return Err(anyhow!("Tournament not found: {}", tournament_id));
+        }
+        let contents = tokio::fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub async fn delete(tournament_id: &str) -> Result<()> {
+        let path = Self::tournament_path(tournament_id)?;
+        if path.exists() {
+            tokio::fs::remove_file(&path).await?;
+        }
+        Ok(())
+    }
+
+    /// List all saved tournaments, newest first. Unreadable files are skipped.
+    pub async fn list() -> Result<Vec<Tournament>> {
+        let mut tournaments = Vec::new();
+        let mut entries = tokio::fs::read_dir(Self::get_tournaments_dir()?).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let parsed = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(serde_json::from_str::<Tournament>(&contents)?));
+            match parsed {
+                Ok(tournament) => tournaments.push(tournament),
+                Err(e) => log::warn!("Skipping unreadable tournament {}: {}", path.display(), e),
+            }
+        }
+        tournaments.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(tournaments)
+    }
+}
+
+fn match_config(tournament: &Tournament, black: &EngineConfig, white: &EngineConfig, seed: u64) -> EngineVsEngineConfig {
+    let config = &tournament.config;
+    EngineVsEngineConfig {
+        engine1_id: black.id.clone(),
+        engine1_path: black.path.clone(),
+        engine1_name: tournament.name_of(&black.id),
+        engine2_id: white.id.clone(),
+        engine2_path: white.path.clone(),
+        engine2_name: tournament.name_of(&white.id),
+        initial_sfen: None,
+        handicap: None,
+        time_per_move_ms: config.time_per_move_ms,
+        search_limit: config.search_limit,
+        max_moves: config.max_moves,
+        adjudication: config.adjudication.clone(),
+        cpu_affinity: None,
+        engine1_priority: black.priority,
+        engine2_priority: white.priority,
+        engine1_sha256: black.binary_sha256.clone(),
+        engine2_sha256: white.binary_sha256.clone(),
+        engine1_options: None,
+        engine2_options: None,
+        engine1_eval_file_id: None,
+        engine2_eval_file_id: None,
+        seed: Some(seed),
+        openings: config.openings.clone(),
+        move_delay_ms: 0,
+        engine1_time_ms: None,
+        engine2_time_ms: None,
+        opening_moves: Vec::new(),
+        record_thinking: false,
+    }
+}
+
+/// Play the unplayed games until the tournament completes or `stop` is set, saving after
+/// each game and emitting `tournament-progress`, and `tournament-standings` after each round
+pub async fn run_tournament(
+    events: SharedEventSink,
+    engine_storage: Arc<RwLock<EngineStorage>>,
+    mut tournament: Tournament,
+    stop: Arc<AtomicBool>,
+) -> Tournament {
+    tournament.status = TournamentStatus::Running;
+    let result = play_games(&events, &engine_storage, &mut tournament, &stop).await;
+    tournament.status = match result {
+        Ok(()) if tournament.is_complete() => TournamentStatus::Completed,
+        Ok(()) => TournamentStatus::Paused,
+        Err(e) => {
+            log::error!("Tournament {} failed: {}", tournament.id, e);
+            tournament.error = Some(e.to_string());
+            TournamentStatus::Failed
+        }
+    };
+    tournament.updated_at = chrono::Utc::now().to_rfc3339();
+    if let Err(e) = TournamentStorage::save(&tournament).await {
+        log::error!("Failed to save tournament {}: {}", tournament.id, e);
+    }
+    let _ = events.send(&TOURNAMENT_PROGRESS, &tournament.progress(0));
+    tournament
+}
+
+async fn play_games(
+    events: &SharedEventSink,
+    engine_storage: &Arc<RwLock<EngineStorage>>,
+    tournament: &mut Tournament,
+    stop: &AtomicBool,
+) -> Result<()> {
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let next = tournament.games.iter().position(|game| game.outcome.is_none());
+        let Some(index) = next.or_else(|| tournament.pair_next_round()) else {
+            break;
+        };
+        let game = tournament.games[index].clone();
+        let (black, white) = {
+            let storage = engine_storage.read().await;
+            let find = |engine_id: &str| {
+                storage.usable_engine(engine_id).cloned()
+            };
+            (find(&game.black_id)?, find(&game.white_id)?)
+        };
+        log::info!(
+            "Tournament {} game {}/{}: {} vs {}",
+            tournament.id,
+            index + 1,
+            tournament.games.len(),
+            tournament.name_of(&black.id),
+            tournament.name_of(&white.id)
+        );
+
+        let manager = EngineVsEngineManager::new(
+            events.clone(),
+            match_config(tournament, &black, &white, game.seed),
+            engine_storage.clone(),
+        );
+        let start = tokio::time::Instant::now();
+        let run = manager.run_match();
+        tokio::pin!(run);
+        let mut ticker = tokio::time::interval_at(start + PROGRESS_INTERVAL, PROGRESS_INTERVAL);
+        let final_state = loop {
+            tokio::select! {
+                result = &mut run => break result?,
+                _ = ticker.tick() => {
+                    let elapsed_ms = start.elapsed().as_millis() as u64;
+                    let _ = events.send(&TOURNAMENT_PROGRESS, &tournament.progress(elapsed_ms));
+                }
+            }
+        };
+
+        let played = &mut tournament.games[index];
+        played.outcome = Some(match final_state.winner.as_deref() {
+            Some("black") => GameOutcome::BlackWin,
+            Some("white") => GameOutcome::WhiteWin,
+            _ => GameOutcome::Draw,
+        });
+        played.match_id = Some(final_state.match_id);
+        played.plies = final_state.move_history.len();
+        played.duration_ms = start.elapsed().as_millis() as u64;
+        tournament.head_to_head = tournament.compute_head_to_head();
+        tournament.updated_at = chrono::Utc::now().to_rfc3339();
+        TournamentStorage::save(tournament).await?;
+        let _ = events.send(&TOURNAMENT_PROGRESS, &tournament.progress(0));
+
+        let round = tournament.games[index].round;
+        if tournament.games.iter().all(|game| game.round != round || game.outcome.is_some()) {
+            let _ = events.send(&TOURNAMENT_STANDINGS, &RoundStandings {
+                tournament_id: tournament.id.clone(),
+                round,
+                standings: tournament.standings(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(engines: &[&str], games_per_pairing: u32) -> TournamentConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": "test",
+            "engine_ids": engines,
+            "games_per_pairing": games_per_pairing,
+            "time_per_move_ms": 100,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_schedule_standings_and_eta() {
+        let names = HashMap::from([("a".to_string(), "Alpha".to_string())]);
+        let mut tournament = Tournament::new(config(&["a", "b", "c"], 2), names);
+        assert_eq!(tournament.games.len(), 6);
+        // The second game of each pairing swaps colours and keeps the opening
+        assert_eq!((tournament.games[0].black_id.as_str(), tournament.games[0].white_id.as_str()), ("a", "b"));
+        assert_eq!((tournament.games[3].black_id.as_str(), tournament.games[3].white_id.as_str()), ("b", "a"));
+        assert_eq!(tournament.games[0].seed, tournament.games[3].seed);
+        assert_ne!(tournament.games[0].seed, tournament.games[1].seed);
+
+        for (game, outcome, duration_ms) in [
+            (0, GameOutcome::BlackWin, 60_000),
+            (1, GameOutcome::Draw, 40_000),
+            (3, GameOutcome::WhiteWin, 50_000),
+        ] {
+            let game = &mut tournament.games[game];
+            game.outcome = Some(outcome);
+            game.duration_ms = duration_ms;
+            game.plies = 100;
+        }
+        let progress = tournament.progress(20_000);
+        assert_eq!(progress.games_completed, 3);
+        assert_eq!(progress.average_game_ms, Some(50_000));
+        assert_eq!(progress.eta_ms, Some(130_000));
+        assert_eq!(progress.average_plies, Some(100.0));
+
+        let standings = &progress.standings;
+        assert_eq!(standings[0].name, "Alpha");
+        assert_eq!((standings[0].points, standings[0].wins, standings[0].draws), (2.5, 2, 1));
+        assert_eq!(standings[1].engine_id, "c");
+        assert_eq!(standings[2].losses, 2);
+
+        // a-b has played both games of its pair, a-c only one game
+        let head_to_head = &progress.head_to_head;
+        assert_eq!(head_to_head.len(), 2);
+        assert_eq!((head_to_head[0].engine_a_id.as_str(), head_to_head[0].engine_b_id.as_str()), ("a", "b"));
+        assert_eq!(head_to_head[0].stats.pentanomial, Some(vec![0, 0, 0, 0, 1]));
+        assert_eq!((head_to_head[0].stats.score, head_to_head[0].stats.los), (1.0, 1.0));
+        assert_eq!(head_to_head[1].stats.model, crate::series_stats::StatsModel::Trinomial);
+        assert_eq!(head_to_head[1].stats.draw_ratio, 1.0);
+
+        let pgn = export_results(&tournament, ResultsFormat::Pgn);
+        assert_eq!(pgn.matches("[Event \"test\"]").count(), 3);
+        assert!(pgn.contains("[White \"Alpha\"]\n[Black \"b\"]\n[Result \"1-0\"]"));
+        assert!(pgn.contains("[Round \"2\"]\n[White \"b\"]\n[Black \"Alpha\"]\n[Result \"0-1\"]"));
+        let csv = export_results(&tournament, ResultsFormat::Csv);
+        assert_eq!(csv.lines().collect::<Vec<_>>(), ["black,white,result", "Alpha,b,1", "Alpha,c,0.5", "b,Alpha,0"]);
+
+        assert!(config(&["a"], 2).validate().is_err());
+        assert!(config(&["a", "a"], 2).validate().is_err());
+        assert!(config(&["a", "b"], 0).validate().is_err());
+    }
+
+    #[test]
+    fn test_swiss_pairing() {
+        let mut swiss = config(&["a", "b", "c", "d", "e"], 1);
+        swiss.pairing = Pairing::Swiss { rounds: 3 };
+        let mut tournament = Tournament::new(swiss, HashMap::new());
+        fn pairs(games: &[TournamentGame]) -> Vec<(&str, &str)> {
+            games.iter().map(|game| (game.black_id.as_str(), game.white_id.as_str())).collect()
+        }
+        // Round 1 pairs in listed order; the last engine sits out
+        assert_eq!(pairs(&tournament.games), [("a", "b"), ("c", "d")]);
+        assert_eq!(tournament.byes, [Bye { round: 1, engine_id: "e".to_string() }]);
+        assert_eq!(tournament.games_total(), 6);
+        assert!(tournament.pair_next_round().is_none());
+
+        tournament.games[0].outcome = Some(GameOutcome::BlackWin);
+        tournament.games[1].outcome = Some(GameOutcome::WhiteWin);
+        let standings = tournament.standings();
+        assert_eq!(standings.iter().map(|s| s.engine_id.as_str()).collect::<Vec<_>>(), ["a", "d", "e", "b", "c"]);
+        assert_eq!((standings[2].points, standings[2].games), (1.0, 0));
+
+        // Leaders meet, the bye goes to the lowest engine without one, and Black goes to the
+        // engine that had White
+        assert_eq!(tournament.pair_next_round(), Some(2));
+        assert_eq!(pairs(&tournament.games[2..]), [("d", "a"), ("b", "e")]);
+        assert_eq!(tournament.byes[1], Bye { round: 2, engine_id: "c".to_string() });
+        assert!(!tournament.is_complete());
+
+        tournament.games[2].outcome = Some(GameOutcome::WhiteWin);
+        tournament.games[3].outcome = Some(GameOutcome::Draw);
+        let standings = tournament.standings();
+        assert_eq!(standings[0].engine_id, "a");
+        // a met b (0.5) and d (1)
+        assert_eq!((standings[0].points, standings[0].buchholz), (2.0, 1.5));
+    }
+}
@@ -0,0 +1,143 @@
+//! Parsing of USI `info` lines reported by engines while searching
+
+use serde::{Deserialize, Serialize};
+
+/// Centipawn value used for mate scores, minus the distance to mate
+pub const MATE_SCORE_CP: i32 = 100_000;
+const MAX_MATE_PLY: i32 = 10_000;
+
+/// Search score from the point of view of the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum Score {
+    Cp(i32),
+    /// Plies to mate; negative when the side to move is getting mated.
+    /// A bare `mate +` / `mate -` (distance unknown) is stored as `±i32::MAX`.
+    Mate(i32),
+}
+
+impl Score {
+    /// The score as centipawns, with mates mapped near ±MATE_SCORE_CP
+    pub fn centipawns(self) -> i32 {
+        match self {
+            Score::Cp(cp) => cp,
+            Score::Mate(plies) if plies >= 0 => MATE_SCORE_CP - plies.min(MAX_MATE_PLY),
+            Score::Mate(plies) => -(MATE_SCORE_CP - plies.saturating_neg().min(MAX_MATE_PLY)),
+        }
+    }
+
+    /// The same score seen from the other side; `i32::MIN` becomes `i32::MAX`
+    pub fn negate(self) -> Self {
+        match self {
+            Score::Cp(cp) => Score::Cp(cp.saturating_neg()),
+            Score::Mate(plies) => Score::Mate(plies.saturating_neg()),
+        }
+    }
+}
+
+/// Fields of a single `info` line. Fields the engine didn't send are left empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct UsiInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub time_ms: Option<u64>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub hashfull: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    pub currmove: Option<String>,
+    pub pv: Vec<String>,
+    /// Free text after `string`
+    pub string: Option<String>,
+}
+
+/// Parse an `info ...` line. Returns None for any other line.
+pub fn parse_info(line: &str) -> Option<UsiInfo> {
+    let rest = line.trim().strip_prefix("info")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    // `string` swallows the rest of the line, so split it off first
+    let (fields, string) = match rest.find(" string") {
+        Some(pos) if rest[pos + 7..].is_empty() || rest[pos + 7..].starts_with(' ') => {
+            (&rest[..pos], Some(rest[pos + 7..].trim().to_string()))
+        }
+        _ => (rest, None),
+    };
+
+    let mut info = UsiInfo {
+        string,
+        ..Default::default()
+    };
+    let mut tokens = fields.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => info.depth = tokens.next().and_then(|v| v.parse().ok()),
+            "seldepth" => info.seldepth = tokens.next().and_then(|v| v.parse().ok()),
+            "time" => info.time_ms = tokens.next().and_then(|v| v.parse().ok()),
+            "nodes" => info.nodes = tokens.next().and_then(|v| v.parse().ok()),
+            "nps" => info.nps = tokens.next().and_then(|v| v.parse().ok()),
+            "hashfull" => info.hashfull = tokens.next().and_then(|v| v.parse().ok()),
+            "multipv" => info.multipv = tokens.next().and_then(|v| v.parse().ok()),
+            "currmove" => info.currmove = tokens.next().map(|v| v.to_string()),
+            "score" => {
+                info.score = match (tokens.next(), tokens.next()) {
+                    (Some("cp"), Some(value)) => value.parse().ok().map(Score::Cp),
+                    (Some("mate"), Some("+")) => Some(Score::Mate(i32::MAX)),
+                    (Some("mate"), Some("-")) => Some(Score::Mate(-i32::MAX)),
+                    (Some("mate"), Some(value)) => value.parse().ok().map(Score::Mate),
+                    _ => None,
+                };
+                // Bound markers qualify the score; the value is still the best estimate
+                if matches!(tokens.peek(), Some(&"lowerbound") | Some(&"upperbound")) {
+                    tokens.next();
+                }
+            }
+            "pv" => {
+                info.pv = tokens.by_ref().map(|v| v.to_string()).collect();
+            }
+            _ => {}
+        }
+    }
+
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negate_extremes() {
+        assert_eq!(Score::Mate(i32::MIN).negate(), Score::Mate(i32::MAX));
+        assert_eq!(Score::Cp(i32::MIN).negate(), Score::Cp(i32::MAX));
+        assert_eq!(Score::Mate(-3).negate(), Score::Mate(3));
+    }
+
+    #[test]
+    fn test_parse_info_line() {
+        let info = parse_info("info depth 12 seldepth 18 score cp -35 lowerbound nodes 123456 nps 800000 time 154 hashfull 12 pv 7g7f 3c3d 2g2f").unwrap();
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.seldepth, Some(18));
+        assert_eq!(info.score, Some(Score::Cp(-35)));
+        assert_eq!(info.nodes, Some(123456));
+        assert_eq!(info.time_ms, Some(154));
+        assert_eq!(info.pv, vec!["7g7f", "3c3d", "2g2f"]);
+
+        let mate = parse_info("info depth 5 score mate -3 pv 8h2b+").unwrap();
+        assert_eq!(mate.score, Some(Score::Mate(-3)));
+        assert_eq!(mate.score.unwrap().centipawns(), -(MATE_SCORE_CP - 3));
+        assert_eq!(parse_info("info score mate +").unwrap().score.unwrap().centipawns(), MATE_SCORE_CP - 10_000);
+
+        let string = parse_info("info string depth 3 means nothing here").unwrap();
+        assert_eq!(string.depth, None);
+        assert_eq!(string.string.as_deref(), Some("depth 3 means nothing here"));
+
+        assert!(parse_info("bestmove 7g7f").is_none());
+        assert!(parse_info("infox depth 1").is_none());
+    }
+}
@@ -0,0 +1,160 @@
+//! Named sequences of raw USI lines, stored per engine
+//! Some engines take commands outside USI: YaneuraOu's `eval` and `matsuri`, learning
+//! commands such as `gensfen` and `learn`. A macro keeps such a sequence with the engine that
+//! understands it. Lines may contain `{name}` placeholders, filled from the parameters given
+//! when the macro is run; braces around anything else are left as they are.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct UsiMacro {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Sent in order, each as one command
+    pub lines: Vec<String>,
+}
+
+fn is_parameter_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Split `line` into literal text and placeholder names
+fn segments(line: &str) -> Vec<(bool, &str)> {
+    let mut segments = Vec::new();
+    let mut rest = line;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+            break;
+        };
+        let name = &rest[open + 1..close];
+        if is_parameter_name(name) {
+            segments.push((false, &rest[..open]));
+            segments.push((true, name));
+        } else {
+            segments.push((false, &rest[..=close]));
+        }
+        rest = &rest[close + 1..];
+    }
+    segments.push((false, rest));
+    segments
+}
+
+impl UsiMacro {
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow!("A macro needs a name"));
+        }
+        if self.lines.iter().all(|line| line.trim().is_empty()) {
+            return Err(anyhow!("Macro {} has no commands", self.name));
+        }
+        if self.lines.iter().any(|line| line.contains(['\n', '\r'])) {
+            return Err(anyhow!("Each line of macro {} must be a single command", self.name));
+        }
+        Ok(())
+    }
+
+    /// Names of the placeholders, in order of first use
+    pub fn parameters(&self) -> Vec<String> {
+        let mut parameters: Vec<String> = Vec::new();
+        for line in &self.lines {
+            for (is_parameter, text) in segments(line) {
+                if is_parameter && !parameters.iter().any(|known| known == text) {
+                    parameters.push(text.to_string());
+                }
+            }
+        }
+        parameters
+    }
+
+    /// The lines to send, with placeholders filled in and blank lines left out. Fails if a
+    /// parameter is missing or a value would split a command into several.
+    pub fn expand(&self, params: &HashMap<String, String>) -> Result<Vec<String>> {
+        let missing: Vec<String> =
+            self.parameters().into_iter().filter(|name| !params.contains_key(name)).collect();
+        if !missing.is_empty() {
+            return Err(anyhow!("Macro {} needs a value for {}", self.name, missing.join(", ")));
+        }
+        if let Some((name, _)) = params.iter().find(|(_, value)| value.contains(['\n', '\r'])) {
+            return Err(anyhow!("The value of {} must not contain line breaks", name));
+        }
+        Ok(self
+            .lines
+            .iter()
+            .map(|line| {
+                segments(line)
+                    .into_iter()
+                    .map(|(is_parameter, text)| if is_parameter { params[text].as_str() } else { text })
+                    .collect::<String>()
+            })
+            .filter(|line| !line.trim().is_empty())
+            .collect())
+    }
+}
+
+/// Check a set of macros for an engine: each valid, and no two with the same name
+pub fn validate_macros(macros: &[UsiMacro]) -> Result<()> {
+    for (index, usi_macro) in macros.iter().enumerate() {
+        usi_macro.validate()?;
+        if macros[..index].iter().any(|other| other.name == usi_macro.name) {
+            return Err(anyhow!("There are two macros named {}", usi_macro.name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let usi_macro = UsiMacro {
+            name: "gensfen".to_string(),
+            description: String::new(),
+            lines: vec![
+                "setoption name Threads value {threads}".to_string(),
+                "".to_string(),
+                "gensfen depth {depth} loop {count} output_file_name {file} {not a placeholder}".to_string(),
+                "isready {depth}".to_string(),
+            ],
+        };
+        assert_eq!(usi_macro.parameters(), ["threads", "depth", "count", "file"]);
+
+        let mut params: HashMap<String, String> = [("threads", "8"), ("depth", "6"), ("count", "1000000")]
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        assert_eq!(usi_macro.expand(&params).unwrap_err().to_string(), "Macro gensfen needs a value for file");
+
+        params.insert("file".to_string(), "sfen.bin".to_string());
+        assert_eq!(
+            usi_macro.expand(&params).unwrap(),
+            [
+                "setoption name Threads value 8",
+                "gensfen depth 6 loop 1000000 output_file_name sfen.bin {not a placeholder}",
+                "isready 6",
+            ]
+        );
+
+        params.insert("file".to_string(), "a.bin\nquit".to_string());
+        assert!(usi_macro.expand(&params).is_err());
+    }
+
+    #[test]
+    fn test_validate_macros() {
+        let named = |name: &str, lines: &[&str]| UsiMacro {
+            name: name.to_string(),
+            description: String::new(),
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+        };
+        assert!(validate_macros(&[named("eval", &["eval"]), named("matsuri", &["matsuri"])]).is_ok());
+        assert!(validate_macros(&[named("eval", &["eval"]), named("eval", &["eval"])]).is_err());
+        assert!(validate_macros(&[named("", &["eval"])]).is_err());
+        assert!(validate_macros(&[named("empty", &[" "])]).is_err());
+        assert!(validate_macros(&[named("two", &["eval\nquit"])]).is_err());
+    }
+}
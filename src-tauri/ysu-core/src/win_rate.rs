@@ -0,0 +1,117 @@
+//! Conversion of engine scores to winning chances and display text
+//! Centipawn scales differ between engines, so each engine can carry its own calibration:
+//! the centipawn value at which the side ahead wins about 73% of games (1 / (1 + e^-1)).
+
+use crate::shogi_rules::Color;
+use crate::usi_info::Score;
+use serde::{Deserialize, Serialize};
+
+fn default_scale_cp() -> f64 {
+    600.0
+}
+
+/// Logistic mapping from centipawns to win probability: p = 1 / (1 + e^(-cp / scale_cp))
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WinRateCalibration {
+    #[serde(default = "default_scale_cp")]
+    pub scale_cp: f64,
+}
+
+impl Default for WinRateCalibration {
+    fn default() -> Self {
+        Self {
+            scale_cp: default_scale_cp(),
+        }
+    }
+}
+
+impl WinRateCalibration {
+    /// Winning chance of the side the score is reported for, between 0 and 1
+    pub fn win_rate(&self, score: Score) -> f64 {
+        match score {
+            Score::Cp(cp) => 1.0 / (1.0 + (-(cp as f64) / self.scale_cp.max(1.0)).exp()),
+            Score::Mate(plies) if plies >= 0 => 1.0,
+            Score::Mate(_) => 0.0,
+        }
+    }
+}
+
+/// A score ready for display, from Black's point of view
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormattedScore {
+    /// Black's winning chance, between 0 and 1
+    pub black_win_rate: f64,
+    /// Side that is ahead; None when the position is even
+    pub leader: Option<Color>,
+    /// "+123", "-45", "0", "Mate in 7" or "Mated in 4", from Black's point of view
+    pub score_text: String,
+    /// e.g. "78% for Black" or "Even"
+    pub win_rate_text: String,
+}
+
+/// Format a score that the engine reported with `side_to_move` to move
+pub fn format_score(score: Score, side_to_move: Color, calibration: &WinRateCalibration) -> FormattedScore {
+    let score = if side_to_move == Color::White { score.negate() } else { score };
+    let black_win_rate = calibration.win_rate(score);
+
+    let score_text = match score {
+        Score::Cp(0) => "0".to_string(),
+        Score::Cp(cp) => format!("{:+}", cp),
+        // A bare `mate +` / `mate -` carries no distance
+        Score::Mate(plies) if plies.unsigned_abs() == i32::MAX as u32 => {
+            if plies > 0 { "Mate".to_string() } else { "Mated".to_string() }
+        }
+        Score::Mate(plies) if plies >= 0 => format!("Mate in {}", plies),
+        Score::Mate(plies) => format!("Mated in {}", -plies),
+    };
+
+    let percent = (black_win_rate * 100.0).round() as u32;
+    let leader = match percent {
+        50 => None,
+        p if p > 50 => Some(Color::Black),
+        _ => Some(Color::White),
+    };
+    let win_rate_text = match leader {
+        None => "Even".to_string(),
+        Some(Color::Black) => format!("{}% for Black", percent),
+        Some(Color::White) => format!("{}% for White", 100 - percent),
+    };
+
+    FormattedScore {
+        black_win_rate,
+        leader,
+        score_text,
+        win_rate_text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_score() {
+        let calibration = WinRateCalibration::default();
+        assert_eq!(calibration.win_rate(Score::Cp(0)), 0.5);
+        assert!((calibration.win_rate(Score::Cp(600)) - 0.731).abs() < 0.001);
+
+        let formatted = format_score(Score::Cp(760), Color::Black, &calibration);
+        assert_eq!(formatted.score_text, "+760");
+        assert_eq!(formatted.win_rate_text, "78% for Black");
+
+        // White to move and ahead: the score turns negative for Black
+        let formatted = format_score(Score::Cp(760), Color::White, &calibration);
+        assert_eq!(formatted.score_text, "-760");
+        assert_eq!(formatted.win_rate_text, "78% for White");
+        assert_eq!(formatted.leader, Some(Color::White));
+
+        let formatted = format_score(Score::Mate(-4), Color::White, &calibration);
+        assert_eq!(formatted.score_text, "Mate in 4");
+        assert_eq!(formatted.black_win_rate, 1.0);
+        assert_eq!(format_score(Score::Cp(1), Color::Black, &calibration).win_rate_text, "Even");
+
+        // A flatter engine needs more centipawns for the same chance
+        let flat = WinRateCalibration { scale_cp: 1200.0 };
+        assert_eq!(format_score(Score::Cp(1520), Color::Black, &flat).win_rate_text, "78% for Black");
+    }
+}
@@ -0,0 +1,32 @@
+//! Harness for tests that run the mock engine as a real child process
+
+use std::path::{Path, PathBuf};
+
+/// A copy of the mock engine binary in its own directory, next to the script it follows
+pub struct MockEngine {
+    dir: PathBuf,
+    pub path: PathBuf,
+}
+
+impl MockEngine {
+    /// `script` is the JSON behaviour of the mock, e.g. `{"resign_on_go": 2}`
+    pub fn new(script: serde_json::Value) -> Self {
+        let dir = std::env::temp_dir().join(format!("shogi-vibe-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let binary = Path::new(env!("CARGO_BIN_EXE_mock_usi_engine"));
+        let path = dir.join(binary.file_name().unwrap());
+        std::fs::copy(binary, &path).unwrap();
+        std::fs::write(dir.join("mock_engine.json"), script.to_string()).unwrap();
+        Self { dir, path }
+    }
+
+    pub fn path_string(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+impl Drop for MockEngine {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
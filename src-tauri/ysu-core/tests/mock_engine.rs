@@ -0,0 +1,577 @@
+//! Engine manager and match loop against the scripted mock engine
+
+mod common;
+
+use common::MockEngine;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use ysu_core::batch_eval;
+use ysu_core::engine_manager::{EngineManager, EngineStatus, HeartbeatPolicy, InstancePurpose, StartupPolicy};
+use ysu_core::engine_storage::{EngineConfig, EngineStorage};
+use ysu_core::engine_validator::{self_test, validate_engine, validate_engine_with, ValidationOptions};
+use ysu_core::engine_vs_engine::{EngineVsEngineConfig, EngineVsEngineManager, EngineVsEngineState};
+use ysu_core::game_record::{GameRecordStorage, Termination};
+use ysu_core::position_match::{self, PositionMatchConfig};
+use ysu_core::selfplay::{self, DatagenConfig};
+use ysu_core::think_log::ThinkLogStorage;
+use ysu_core::timeouts::TimeoutOverrides;
+use ysu_core::tournament::{self, Tournament, TournamentStatus, TournamentStorage};
+use ysu_core::usi_info::Score;
+use ysu_core::RecordingEventSink;
+
+async fn spawn(manager: &EngineManager, engine: &MockEngine, config_id: &str) -> String {
+    manager
+        .spawn_engine(config_id, "Mock".to_string(), engine.path_string(), InstancePurpose::Other, None)
+        .await
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_manager_initializes_slow_noisy_engine() {
+    let engine = MockEngine::new(json!({
+        "usiok_delay_ms": 300,
+        "readyok_delay_ms": 300,
+        "garbage": ["", "Loading eval... ok", "\tinfo string not quite usi"],
+    }));
+    let recorder = RecordingEventSink::new();
+    let manager = EngineManager::new(recorder.clone());
+    let storage = RwLock::new(EngineStorage::default());
+
+    let runtime_id = spawn(&manager, &engine, "slow").await;
+    manager
+        .initialize_engine_with_temp_options(&runtime_id, &storage, None, None)
+        .await
+        .unwrap();
+    assert_eq!(manager.get_engine_status(&runtime_id).await, Some(EngineStatus::Ready));
+    let stages: Vec<_> = recorder
+        .payloads(&format!("engine-init-progress::{}", runtime_id))
+        .iter()
+        .map(|progress| progress["stage"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        stages,
+        ["spawned", "usi_sent", "usiok_received", "options_sent", "isready_sent", "readyok_received"]
+    );
+    let event = format!("usi-message::{}", runtime_id);
+    assert!(recorder.payloads(&event).contains(&json!("Loading eval... ok")));
+
+    manager.send_command(&runtime_id, "position startpos").await.unwrap();
+    manager.send_command(&runtime_id, "go btime 0 wtime 0 byoyomi 100").await.unwrap();
+    let bestmove = recorder
+        .wait_for(&event, Duration::from_secs(5), |line| line.as_str().is_some_and(|line| line.starts_with("bestmove ")))
+        .await;
+    assert!(bestmove.is_some());
+    let snapshot = recorder
+        .wait_for(&format!("analysis-snapshot::{}", runtime_id), Duration::from_secs(5), |snapshot| {
+            snapshot["finished"] == json!(true)
+        })
+        .await
+        .unwrap();
+    assert_eq!(snapshot["lines"][0]["depth"], json!(1));
+    let latest = manager.latest_analysis(&runtime_id).await.unwrap();
+    assert!(latest.finished);
+    assert_eq!(latest.lines[0].depth, Some(1));
+
+    // info string goes to its own event, not the USI stream
+    let messages = manager.engine_messages(&runtime_id).await.unwrap();
+    assert!(messages.iter().all(|message| message.message == "not quite usi"));
+    assert_eq!(messages.len(), 2);
+    assert_eq!(recorder.payloads(&format!("engine-message::{}", runtime_id)).len(), 2);
+    assert!(recorder.payloads(&event).iter().all(|line| !line.to_string().contains("not quite usi")));
+
+    manager.stop_all_engines().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_manager_retries_then_reports_init_failure() {
+    let engine = MockEngine::new(json!({ "readyok_delay_ms": 2000 }));
+    let recorder = RecordingEventSink::new();
+    let manager = EngineManager::new(recorder.clone());
+    manager.set_startup_policy(StartupPolicy { retries: 1, retry_delay_ms: 10 });
+    let mut storage = EngineStorage::default();
+    let config_id = storage
+        .add_engine(EngineConfig::new("Mock".to_string(), engine.path_string(), None, false))
+        .unwrap();
+    let impatient = TimeoutOverrides { readyok_ms: Some(300), ..Default::default() };
+    storage.set_engine_timeouts(&config_id, Some(impatient)).unwrap();
+    let storage = RwLock::new(storage);
+
+    let result = manager
+        .start_engine(&config_id, "Mock".to_string(), engine.path_string(), InstancePurpose::Other, None, &storage, None, None)
+        .await;
+    assert!(result.is_err());
+    let failed = recorder.payloads("engine-init-failed");
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["attempts"], json!(2));
+    assert!(failed[0]["error"].as_str().unwrap().contains("readyok"));
+    assert!(manager.list_engines().await.is_empty());
+
+    storage.write().await.set_engine_timeouts(&config_id, None).unwrap();
+    let runtime_id = manager
+        .start_engine(&config_id, "Mock".to_string(), engine.path_string(), InstancePurpose::Other, None, &storage, None, None)
+        .await
+        .unwrap();
+    assert_eq!(manager.get_engine_status(&runtime_id.to_string()).await, Some(EngineStatus::Ready));
+    manager.stop_all_engines().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_manager_reports_crashed_engine() {
+    let engine = MockEngine::new(json!({ "crash_on_go": 1, "exit_code": 3, "crash_message": "Out of memory" }));
+    let recorder = RecordingEventSink::new();
+    let manager = EngineManager::new(recorder.clone());
+
+    let runtime_id = spawn(&manager, &engine, "crashing").await;
+    manager.send_command(&runtime_id, "position startpos").await.unwrap();
+    manager.send_command(&runtime_id, "go byoyomi 100").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // The process is gone, so writing to it eventually fails instead of hanging
+    let mut failed = false;
+    for _ in 0..20 {
+        if manager.send_command(&runtime_id, "isready").await.is_err() {
+            failed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(failed);
+    assert!(recorder.payloads(&format!("usi-message::{}", runtime_id)).iter().all(|line| !line.as_str().unwrap_or("").starts_with("bestmove")));
+
+    let crash = recorder.wait_for("engine-crashed", Duration::from_secs(5), |_| true).await.unwrap();
+    assert_eq!(crash["exit_code"], json!(3));
+    assert_eq!(crash["stderr_tail"], json!(["Out of memory"]));
+    assert_eq!(
+        manager.get_engine_status(&runtime_id).await,
+        Some(EngineStatus::Crashed { code: Some(3) })
+    );
+    let errors = recorder.payloads(&format!("usi-error::{}", runtime_id));
+    assert!(errors.iter().any(|error| error.as_str().unwrap_or("").contains("exited with code 3")));
+    assert_eq!(manager.crash_history("crashing").len(), 1);
+
+    manager.stop_all_engines().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_heartbeat_flags_hung_engine() {
+    // The first isready is the one sent while initializing
+    let engine = MockEngine::new(json!({ "hang_on_isready": 2 }));
+    let recorder = RecordingEventSink::new();
+    let manager = EngineManager::new(recorder.clone());
+    manager.set_heartbeat_policy(HeartbeatPolicy { interval_ms: 200, timeout_ms: 300 });
+    let storage = RwLock::new(EngineStorage::default());
+
+    let runtime_id = spawn(&manager, &engine, "hung").await;
+    manager
+        .initialize_engine_with_temp_options(&runtime_id, &storage, None, None)
+        .await
+        .unwrap();
+    let unresponsive = recorder
+        .wait_for("engine-unresponsive", Duration::from_secs(5), |_| true)
+        .await
+        .unwrap();
+    assert_eq!(unresponsive["runtime_id"], json!(runtime_id));
+    assert_eq!(manager.get_engine_status(&runtime_id).await, Some(EngineStatus::Unresponsive));
+
+    manager.stop_all_engines().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_heartbeat_skips_searches_and_hides_its_readyok() {
+    let engine = MockEngine::new(json!({ "bestmove_delay_ms": 1000 }));
+    let recorder = RecordingEventSink::new();
+    let manager = EngineManager::new(recorder.clone());
+    manager.set_heartbeat_policy(HeartbeatPolicy { interval_ms: 200, timeout_ms: 300 });
+    let storage = RwLock::new(EngineStorage::default());
+
+    let runtime_id = spawn(&manager, &engine, "searching").await;
+    manager
+        .initialize_engine_with_temp_options(&runtime_id, &storage, None, None)
+        .await
+        .unwrap();
+    manager.send_command(&runtime_id, "position startpos").await.unwrap();
+    manager.send_command(&runtime_id, "go infinite").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(manager.get_engine_status(&runtime_id).await, Some(EngineStatus::Thinking));
+
+    recorder
+        .wait_for(&format!("engine-bestmove::{}", runtime_id), Duration::from_secs(5), |_| true)
+        .await
+        .unwrap();
+    // Idle again, so heartbeats go out; only the readyok of initialization reaches the frontend
+    tokio::time::sleep(Duration::from_millis(700)).await;
+    assert_eq!(manager.get_engine_status(&runtime_id).await, Some(EngineStatus::Ready));
+    let messages = recorder.payloads(&format!("usi-message::{}", runtime_id));
+    assert_eq!(messages.iter().filter(|line| line.as_str() == Some("readyok")).count(), 1);
+
+    manager.stop_all_engines().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_warm_pool_restarts_crashed_engine() {
+    let engine = MockEngine::new(json!({ "crash_on_go": 1 }));
+    let recorder = RecordingEventSink::new();
+    let manager = EngineManager::new(recorder.clone());
+    let mut storage = EngineStorage::default();
+    let config_id = storage
+        .add_engine(EngineConfig::new("Mock".to_string(), engine.path_string(), None, false))
+        .unwrap();
+    storage.set_engine_keep_warm(&config_id, true).unwrap();
+    let storage = RwLock::new(storage);
+
+    manager.maintain_warm_pool(&storage).await;
+    let first = manager.warm_instance(&config_id).await.unwrap();
+    manager.send_command(&first.to_string(), "position startpos").await.unwrap();
+    manager.send_command(&first.to_string(), "go byoyomi 100").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    manager.maintain_warm_pool(&storage).await;
+    let second = manager.warm_instance(&config_id).await.unwrap();
+    assert_ne!(first, second);
+    let ready = recorder.payloads("engine-warm-ready");
+    assert_eq!(ready.len(), 2);
+    assert_eq!(ready[1]["respawned"], json!(true));
+    assert_eq!(manager.list_instances(None).await.len(), 1);
+
+    storage.write().await.set_engine_keep_warm(&config_id, false).unwrap();
+    manager.maintain_warm_pool(&storage).await;
+    assert!(manager.warm_instance(&config_id).await.is_none());
+    assert!(manager.list_engines().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_deep_validation_catches_failing_isready() {
+    let engine = MockEngine::new(json!({ "name": "Broken NNUE 1.0", "fail_isready": "Error! nn.bin not found", "exit_code": 1 }));
+    let metadata = validate_engine(&engine.path_string()).await.unwrap();
+    assert_eq!(metadata.name, "Broken NNUE 1.0");
+    assert_eq!(metadata.version.as_deref(), Some("1.0"));
+    assert_eq!(metadata.raw_banner.last().map(String::as_str), Some("usiok"));
+
+    let deep = ValidationOptions { deep: true, ..Default::default() };
+    let error = validate_engine_with(&engine.path_string(), &deep).await.unwrap_err().to_string();
+    assert!(error.contains("nn.bin not found"), "{}", error);
+
+    let slow = MockEngine::new(json!({ "readyok_delay_ms": 2000 }));
+    let impatient = ValidationOptions { deep: true, ready_timeout_ms: 200, ..Default::default() };
+    assert!(validate_engine_with(&slow.path_string(), &impatient).await.is_err());
+    let patient = ValidationOptions { deep: true, ready_timeout_ms: 5000, ..Default::default() };
+    assert!(validate_engine_with(&slow.path_string(), &patient).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_validation_error_includes_stderr() {
+    let message = "error while loading shared libraries: libtbb.so.2: cannot open shared object file";
+    let engine = MockEngine::new(json!({ "fail_usi": message, "exit_code": 127 }));
+    let error = validate_engine(&engine.path_string()).await.unwrap_err().to_string();
+    assert!(error.starts_with("Engine did not respond with 'usiok'"), "{}", error);
+    assert!(error.ends_with(message), "{}", error);
+
+    let result = self_test(&engine.path_string(), &ValidationOptions::default()).await;
+    assert!(result.error.unwrap().contains("libtbb.so.2"));
+}
+
+#[tokio::test]
+async fn test_self_test_checks_bestmove_legality() {
+    let engine = MockEngine::new(json!({}));
+    let result = self_test(&engine.path_string(), &ValidationOptions::default()).await;
+    assert!(result.passed, "{:?}", result.error);
+    assert!(result.best_move.is_some());
+
+    let illegal = MockEngine::new(json!({ "illegal_move_on_go": 1 }));
+    let result = self_test(&illegal.path_string(), &ValidationOptions::default()).await;
+    assert!(!result.passed);
+    assert_eq!(result.best_move.as_deref(), Some("5i5g"));
+}
+
+#[tokio::test]
+async fn test_batch_evaluation_reuses_one_engine() {
+    let engine = MockEngine::new(json!({ "crash_on_go": 3 }));
+    let mut storage = EngineStorage::default();
+    let engine_id = storage.add_engine(EngineConfig::new("Mock".to_string(), engine.path_string(), None, false)).unwrap();
+    let config = storage.get_engine(&engine_id).unwrap().clone();
+    let sfens: Vec<String> = ["startpos", "startpos moves 7g7f", "not a position", "startpos moves 2g2f", "startpos"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+    let evaluations = batch_eval::evaluate_positions(&config, &RwLock::new(storage), &sfens, 50).await.unwrap();
+    assert_eq!(evaluations.len(), 5);
+    assert!(evaluations[0].best_move.is_some());
+    assert_eq!(evaluations[1].score, Some(Score::Cp(0)));
+    assert!(evaluations[2].error.as_deref().unwrap().starts_with("Invalid position"));
+    // The third go crashes the engine; nothing after it is searched
+    assert!(evaluations[3].error.is_some());
+    assert!(evaluations[4].error.as_deref().unwrap().starts_with("Not evaluated"));
+}
+
+#[tokio::test]
+async fn test_selfplay_datagen_writes_and_resumes_shards() {
+    let engine = MockEngine::new(json!({}));
+    let mut storage = EngineStorage::default();
+    let engine_id = storage.add_engine(EngineConfig::new("Mock".to_string(), engine.path_string(), None, false)).unwrap();
+    let storage = Arc::new(RwLock::new(storage));
+    let output_dir = std::env::temp_dir().join(format!("shogi-vibe-datagen-{}", uuid::Uuid::new_v4()));
+    let config = |games: u64| -> DatagenConfig {
+        serde_json::from_value(json!({
+            "engine_id": engine_id,
+            "output_dir": output_dir.display().to_string(),
+            "nodes": 1,
+            "games": games,
+            "max_moves": 16,
+            "random_opening_plies": 2,
+            "shard_positions": 10,
+            "seed": 7,
+        }))
+        .unwrap()
+    };
+    let run = |games: u64| {
+        let recorder = RecordingEventSink::new();
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        (recorder.clone(), selfplay::run_datagen(recorder, storage.clone(), config(games), stop))
+    };
+
+    let (recorder, datagen) = run(2);
+    let progress = datagen.await.unwrap();
+    assert_eq!(progress.games_played, 2);
+    assert_eq!(recorder.payloads("datagen-progress").len(), 2);
+    // The mock never resigns: each game ends by max_moves, or earlier by repetition
+    assert!(progress.positions_written > 0 && progress.positions_written <= 28);
+
+    let (_, datagen) = run(3);
+    let progress = datagen.await.unwrap();
+    assert_eq!(progress.games_played, 3);
+    let mut bytes = 0;
+    for entry in std::fs::read_dir(&output_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "bin") {
+            bytes += std::fs::metadata(&path).unwrap().len();
+        }
+    }
+    assert_eq!(bytes, progress.positions_written * selfplay::RECORD_SIZE as u64);
+    std::fs::remove_dir_all(&output_dir).unwrap();
+}
+
+fn match_config(black: &MockEngine, white: &MockEngine) -> EngineVsEngineConfig {
+    serde_json::from_value(json!({
+        "engine1_id": "black",
+        "engine1_path": black.path_string(),
+        "engine1_name": "Black Mock",
+        "engine2_id": "white",
+        "engine2_path": white.path_string(),
+        "engine2_name": "White Mock",
+        "initial_sfen": null,
+        "time_per_move_ms": 100,
+        "max_moves": 20,
+        "move_delay_ms": 0,
+    }))
+    .unwrap()
+}
+
+/// Play a match between two mocks, deleting the game record it saves
+async fn play(black: serde_json::Value, white: serde_json::Value) -> (EngineVsEngineState, Arc<RecordingEventSink>) {
+    let (black, white) = (MockEngine::new(black), MockEngine::new(white));
+    let recorder = RecordingEventSink::new();
+    let storage = Arc::new(RwLock::new(EngineStorage::default()));
+    let manager = EngineVsEngineManager::new(recorder.clone(), match_config(&black, &white), storage);
+    let state = manager.run_match().await.unwrap();
+    let _ = GameRecordStorage::delete(&state.match_id).await;
+    (state, recorder)
+}
+
+#[tokio::test]
+async fn test_match_ends_on_resignation() {
+    let (state, recorder) = play(json!({ "garbage": ["???"] }), json!({ "resign_on_go": 2 })).await;
+    assert_eq!(state.termination, Some(Termination::Resignation));
+    assert_eq!(state.winner.as_deref(), Some("black"));
+    assert_eq!(state.move_history.len(), 3);
+    assert_eq!(recorder.payloads("engine-vs-engine-move").len(), 3);
+    assert!(!recorder.payloads("engine-vs-engine-analysis").is_empty());
+    assert_eq!(recorder.payloads("engine-vs-engine-summary")[0]["total_moves"], 3);
+}
+
+#[tokio::test]
+async fn test_match_rejects_illegal_move() {
+    let (state, _) = play(json!({}), json!({ "illegal_move_on_go": 1 })).await;
+    assert_eq!(state.termination, Some(Termination::IllegalMove));
+    assert_eq!(state.winner.as_deref(), Some("black"));
+    assert_eq!(state.move_history.len(), 1);
+}
+
+#[tokio::test]
+async fn test_match_forfeits_crashed_engine() {
+    let (state, _) = play(json!({ "crash_on_go": 1 }), json!({})).await;
+    assert_eq!(state.termination, Some(Termination::EngineFailure));
+    assert_eq!(state.winner.as_deref(), Some("white"));
+    assert!(state.move_history.is_empty());
+}
+
+#[tokio::test]
+async fn test_match_forfeits_slow_engine() {
+    let (state, _) = play(json!({}), json!({ "bestmove_delay_ms": 15_000 })).await;
+    assert_eq!(state.termination, Some(Termination::EngineFailure));
+    assert_eq!(state.winner.as_deref(), Some("black"));
+}
+
+#[tokio::test]
+async fn test_resumed_match_continues_from_saved_moves() {
+    let (black, white) = (MockEngine::new(json!({})), MockEngine::new(json!({ "resign_on_go": 1 })));
+    let recorder = RecordingEventSink::new();
+    let storage = Arc::new(RwLock::new(EngineStorage::default()));
+    let config = match_config(&black, &white);
+
+    // As auto-saved after Black's first move
+    let state: EngineVsEngineState = serde_json::from_value(json!({
+        "match_id": uuid::Uuid::new_v4().to_string(),
+        "move_number": 1,
+        "current_player": "white",
+        "position_sfen": format!("{} moves 7g7f", ysu_core::shogi_rules::STARTPOS_SFEN),
+        "last_move": "7g7f",
+        "move_history": ["7g7f"],
+        "move_times_ms": [1_500],
+        "game_over": false,
+        "winner": null,
+        "game_result": null,
+        "termination": null,
+        "board": null,
+    }))
+    .unwrap();
+
+    let manager = EngineVsEngineManager::resume(recorder.clone(), config, state, storage).unwrap();
+    let state = manager.run_match().await.unwrap();
+    let _ = GameRecordStorage::delete(&state.match_id).await;
+    assert_eq!(state.termination, Some(Termination::Resignation));
+    assert_eq!(state.winner.as_deref(), Some("black"));
+    assert_eq!(state.move_history, vec!["7g7f"]);
+    assert!(recorder.payloads("engine-vs-engine-move").is_empty());
+    assert_eq!(recorder.payloads("engine-vs-engine-summary")[0]["engine1_timing"]["moves"], 1);
+}
+
+#[tokio::test]
+async fn test_match_plays_forced_opening_moves() {
+    let (black, white) = (MockEngine::new(json!({})), MockEngine::new(json!({ "resign_on_go": 1 })));
+    let storage = Arc::new(RwLock::new(EngineStorage::default()));
+    let mut config = match_config(&black, &white);
+    config.opening_moves = vec!["7g7f".to_string(), "7g7f".to_string()];
+    let manager = EngineVsEngineManager::new(RecordingEventSink::new(), config.clone(), storage.clone());
+    assert!(manager.play_opening_moves().await.is_err());
+
+    config.opening_moves = vec!["7g7f".to_string(), "3c3d".to_string()];
+    let recorder = RecordingEventSink::new();
+    let manager = EngineVsEngineManager::new(recorder.clone(), config, storage);
+    let state = manager.run_match().await.unwrap();
+    let record = GameRecordStorage::load(&state.match_id).await.unwrap();
+    let _ = GameRecordStorage::delete(&state.match_id).await;
+    assert_eq!(state.termination, Some(Termination::Resignation));
+    assert_eq!(&state.move_history[..2], ["7g7f", "3c3d"]);
+    assert_eq!(state.move_history.len(), 3);
+    assert_eq!(record.moves.iter().map(|mv| mv.book).collect::<Vec<_>>(), vec![true, true, false]);
+    assert_eq!(recorder.payloads("engine-vs-engine-summary")[0]["engine1_timing"]["moves"], 1);
+}
+
+#[tokio::test]
+async fn test_match_archives_thinking() {
+    let (black, white) = (MockEngine::new(json!({})), MockEngine::new(json!({ "resign_on_go": 1 })));
+    let mut config = match_config(&black, &white);
+    config.record_thinking = true;
+    let storage = Arc::new(RwLock::new(EngineStorage::default()));
+    let manager = EngineVsEngineManager::new(RecordingEventSink::new(), config, storage);
+    let state = manager.run_match().await.unwrap();
+    let record = GameRecordStorage::load(&state.match_id).await.unwrap();
+    let thinking = ThinkLogStorage::load_ply(&state.match_id, 1).await.unwrap();
+    // Deleting the record removes its log too
+    GameRecordStorage::delete(&state.match_id).await.unwrap();
+
+    assert_eq!(record.think_log, Some(ThinkLogStorage::file_name(&state.match_id)));
+    assert_eq!(thinking.side, "black");
+    assert!(thinking.lines[0].starts_with("info depth 1 score cp 0"));
+    assert!(ThinkLogStorage::load_ply(&state.match_id, 1).await.is_err());
+}
+
+#[tokio::test]
+async fn test_tournament_plays_all_games_and_reports_progress() {
+    let (first, second) = (MockEngine::new(json!({})), MockEngine::new(json!({ "resign_on_go": 3 })));
+    let mut storage = EngineStorage::default();
+    let mut engine_ids = Vec::new();
+    for engine in [&first, &second] {
+        engine_ids.push(storage.add_engine(EngineConfig::new("Mock".to_string(), engine.path_string(), None, false)).unwrap());
+    }
+    let config = serde_json::from_value(json!({
+        "name": "mocks",
+        "engine_ids": engine_ids,
+        "games_per_pairing": 2,
+        "time_per_move_ms": 100,
+        "max_moves": 20,
+    }))
+    .unwrap();
+    let recorder = RecordingEventSink::new();
+    let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let tournament = tournament::run_tournament(
+        recorder.clone(),
+        Arc::new(RwLock::new(storage)),
+        Tournament::new(config, Default::default()),
+        stop,
+    )
+    .await;
+    for game in &tournament.games {
+        let _ = GameRecordStorage::delete(game.match_id.as_deref().unwrap()).await;
+    }
+    TournamentStorage::delete(&tournament.id).await.unwrap();
+
+    assert_eq!(tournament.status, TournamentStatus::Completed, "{:?}", tournament.error);
+    let progress = recorder.payloads("tournament-progress");
+    assert_eq!(progress.len(), 3);
+    assert_eq!(progress[0]["games_completed"], 1);
+    assert!(progress[0]["eta_ms"].is_u64());
+    // Each game of a two-engine round robin is a round of its own
+    assert_eq!(recorder.payloads("tournament-standings").len(), 2);
+    let standings = tournament.standings();
+    assert_eq!(standings.iter().map(|standing| standing.points).sum::<f64>(), 2.0);
+    // The resigning mock loses as both colours
+    assert_eq!(standings[0].engine_id, engine_ids[0]);
+    assert_eq!(standings[0].wins, 2);
+    assert_eq!(tournament.head_to_head.len(), 1);
+    assert_eq!(tournament.head_to_head[0].stats.pentanomial, Some(vec![0, 0, 0, 0, 1]));
+    assert_eq!(progress[2]["head_to_head"][0]["stats"]["los"], 1.0);
+}
+
+#[tokio::test]
+async fn test_position_match_plays_each_position_with_both_colours() {
+    let (first, second) = (MockEngine::new(json!({})), MockEngine::new(json!({ "resign_on_go": 2 })));
+    let mut storage = EngineStorage::default();
+    let mut engine_ids = Vec::new();
+    for engine in [&first, &second] {
+        engine_ids.push(storage.add_engine(EngineConfig::new("Mock".to_string(), engine.path_string(), None, false)).unwrap());
+    }
+    let config: PositionMatchConfig = serde_json::from_value(json!({
+        "engine_a_id": engine_ids[0],
+        "engine_b_id": engine_ids[1],
+        "positions": ["startpos", "startpos moves 7g7f 3c3d"],
+        "time_per_move_ms": 100,
+        "max_moves": 20,
+    }))
+    .unwrap();
+    let recorder = RecordingEventSink::new();
+    let report = position_match::run_position_match(recorder.clone(), Arc::new(RwLock::new(storage)), config)
+        .await
+        .unwrap();
+    for game in report.positions.iter().flat_map(|position| &position.games) {
+        let _ = GameRecordStorage::delete(game.match_id.as_deref().unwrap()).await;
+    }
+
+    assert_eq!(report.positions.len(), 2);
+    for position in &report.positions {
+        let colors: Vec<_> = position.games.iter().map(|game| game.engine_a_color.as_str()).collect();
+        assert_eq!(colors, ["black", "white"]);
+        assert_eq!(position.summary.score, 1.0);
+    }
+    // The resigning mock loses every game
+    assert_eq!((report.overall.games, report.overall.wins), (4, 4));
+    assert_eq!(report.overall.elo, None);
+    assert_eq!((report.overall.pentanomial.clone(), report.overall.los), (Some(vec![0, 0, 0, 0, 2]), 1.0));
+    let progress = recorder.payloads("position-match-progress");
+    assert_eq!(progress.len(), 2);
+    assert_eq!(progress[0]["overall"]["games"], 2);
+}
@@ -1,8 +1,8 @@
 import { useState, useEffect } from 'react';
 import { useNavigate } from 'react-router-dom';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../utils/invoke';
 import { open } from '@tauri-apps/plugin-dialog';
-import type { EngineConfig, CommandResponse, EngineMetadata, EngineHealthResult } from '../types/engine';
+import type { EngineConfig, CommandResponse, DefaultEngines, EngineMetadata, EngineHealthResult } from '../types/engine';
 import { EngineOptionsModal } from './EngineOptionsModal';
 import './EngineManagementPage.css';
 
@@ -11,6 +11,7 @@ export function EngineManagementPage() {
   const [engines, setEngines] = useState<EngineConfig[]>([]);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
+  const [defaultGameEngineId, setDefaultGameEngineId] = useState<string | null>(null);
   const [addingEngine, setAddingEngine] = useState(false);
   const [validating, setValidating] = useState(false);
   const [healthCheckResults, setHealthCheckResults] = useState<Map<string, EngineHealthResult>>(new Map());
@@ -38,6 +39,8 @@ export function EngineManagementPage() {
       
       if (response.success && response.data) {
         setEngines(response.data);
+        const defaults = await invoke<CommandResponse<DefaultEngines>>('get_default_engines');
+        setDefaultGameEngineId(defaults.data?.defaults.game ?? null);
       } else {
         setError(response.message || 'Failed to load engines');
       }
@@ -99,6 +102,14 @@ export function EngineManagementPage() {
         if (!newEngineName && response.data.name) {
           setNewEngineName(response.data.name);
         }
+      } else if (response.code === 'engine_quarantined'
+        && confirm(`${response.message}\n\nOnly do this for engines from a source you trust. Clear the quarantine now?`)) {
+        const cleared = await invoke<CommandResponse>('clear_engine_quarantine', { path });
+        if (cleared.success) {
+          await validateEnginePath(path);
+        } else {
+          setError(cleared.message || 'Failed to clear quarantine');
+        }
       } else {
         setError(response.message || 'Engine validation failed');
         setValidationResult(null);
@@ -133,6 +144,13 @@ export function EngineManagementPage() {
         setValidationResult(null);
         // Reload engines
         await loadEngines();
+      } else if ((response.data as any)?.fixable && confirm(`${response.message}\n\nFix the file's permissions and try again?`)) {
+        const fixed = await invoke<CommandResponse>('fix_engine_permissions', { path: newEnginePath.trim() });
+        if (fixed.success && fixed.data?.remaining_issues?.length === 0) {
+          await handleAddEngine();
+        } else {
+          setError(fixed.message || 'Could not fix the engine file\'s permissions');
+        }
       } else {
         setError(response.message || 'Failed to add engine');
       }
@@ -218,18 +236,19 @@ export function EngineManagementPage() {
   const handleToggleFavorite = async (engineId: string) => {
     try {
       setError(null);
-      const response = await invoke<CommandResponse>('set_favorite_engine', {
+      const response = await invoke<CommandResponse>('set_default_engine', {
+        purpose: 'game',
         engineId,
       });
 
       if (response.success) {
-        await loadEngines(); // Reload to show updated favorite status
+        await loadEngines(); // Reload to show the updated default
       } else {
-        setError(response.message || 'Failed to set favorite engine');
+        setError(response.message || 'Failed to set default engine');
       }
     } catch (err) {
-      setError(`Error setting favorite engine: ${err}`);
-      console.error('Error setting favorite engine:', err);
+      setError(`Error setting default engine: ${err}`);
+      console.error('Error setting default engine:', err);
     }
   };
 
@@ -375,9 +394,9 @@ export function EngineManagementPage() {
                       <button
                         onClick={() => handleToggleFavorite(engine.id)}
                         className="favorite-button"
-                        title={engine.is_favorite ? "Unmark as favorite" : "Mark as favorite"}
+                        title={engine.id === defaultGameEngineId ? "Default game engine" : "Make default game engine"}
                       >
-                        {engine.is_favorite ? '⭐' : '☆'}
+                        {engine.id === defaultGameEngineId ? '⭐' : '☆'}
                       </button>
                       {engine.display_name}
                       {engine.is_builtin && <span className="builtin-badge">Built-in</span>}
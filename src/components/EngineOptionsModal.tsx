@@ -1,5 +1,5 @@
 import { useState, useEffect, useMemo } from 'react';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../utils/invoke';
 import type { EngineConfig, EngineOption, CommandResponse } from '../types/engine';
 import './EngineOptionsModal.css';
 
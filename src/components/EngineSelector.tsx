@@ -1,6 +1,6 @@
 import { useState, useEffect } from 'react';
-import { invoke } from '@tauri-apps/api/core';
-import type { EngineConfig, CommandResponse } from '../types/engine';
+import { invoke } from '../utils/invoke';
+import type { EngineConfig, CommandResponse, DefaultEngines } from '../types/engine';
 import './EngineSelector.css';
 
 interface EngineSelectorProps {
@@ -21,6 +21,7 @@ export function EngineSelector({
   const [engines, setEngines] = useState<EngineConfig[]>([]);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
+  const [defaultEngineId, setDefaultEngineId] = useState<string | null>(null);
 
   useEffect(() => {
     loadEngines();
@@ -32,25 +33,17 @@ export function EngineSelector({
       setError(null);
       const response = await invoke<CommandResponse<EngineConfig[]>>('get_engines');
       
+      const defaults = await invoke<CommandResponse<DefaultEngines>>('get_default_engines');
+      const gameEngineId = defaults.data?.resolved.game ?? null;
+      setDefaultEngineId(gameEngineId);
+
       if (response.success && response.data) {
         const enabledEngines = response.data.filter(e => e.enabled);
         setEngines(enabledEngines);
         
-        // Auto-select favorite engine if nothing selected (only if autoSelect is enabled)
+        // Auto-select the default game engine if nothing selected (only if autoSelect is enabled)
         if (autoSelect && !selectedEngineId && enabledEngines.length > 0 && onEngineSelect) {
-          // First, try to find the favorite engine
-          const favoriteEngine = enabledEngines.find(e => e.is_favorite);
-          if (favoriteEngine) {
-            onEngineSelect(favoriteEngine.id);
-          } else {
-            // Fallback to built-in engine if no favorite is set
-            const builtinEngine = enabledEngines.find(e => e.is_builtin);
-            if (builtinEngine) {
-              onEngineSelect(builtinEngine.id);
-            } else if (enabledEngines.length > 0) {
-              onEngineSelect(enabledEngines[0].id);
-            }
-          }
+          onEngineSelect(gameEngineId ?? enabledEngines[0].id);
         }
       } else {
         setError(response.message || 'Failed to load engines');
@@ -112,7 +105,7 @@ export function EngineSelector({
         {includeNone && <option value="">None (Human only)</option>}
         {engines.map((engine) => (
           <option key={engine.id} value={engine.id}>
-            {engine.is_favorite ? '⭐ ' : ''}{engine.display_name} {engine.is_builtin ? '(Built-in)' : ''}
+            {engine.id === defaultEngineId ? '⭐ ' : ''}{engine.display_name} {engine.is_builtin ? '(Built-in)' : ''}
           </option>
         ))}
       </select>
@@ -23,9 +23,9 @@ import { loadWallpaperImages, loadBoardImages } from '../utils/imageLoader';
 import { GameFormat, GameData, generateGame } from '../utils/gameFormats';
 import { playPieceMoveSound, playCheckmateSound, playDrawSound, setSoundsEnabled, setVolume, getVolume } from '../utils/audio';
 import { sendUsiCommand, parseBestMove, parseEngineInfo, sendIsReadyAndWait } from '../utils/tauriEngine';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../utils/invoke';
 import { listen } from '@tauri-apps/api/event';
-import type { CommandResponse, EngineConfig } from '../types/engine';
+import type { CommandResponse, DefaultEngines, EngineConfig } from '../types/engine';
 import { useTauriEvents } from '../hooks/useTauriEvents';
 import './GamePage.css';
 
@@ -1273,17 +1273,17 @@ const GamePage: React.FC<GamePageProps> = ({
 
       // Store engines for display name lookup in USI monitor
       setAvailableEngines(response.data);
+      const defaults = await invoke<CommandResponse<DefaultEngines>>('get_default_engines');
+      const analysisEngineId = defaults.data?.resolved.analysis ?? undefined;
 
       // Always spawn player 1 engine (AI or recommendation engine for human)
       let player1EngineId = settings.player1EngineId;
       if (settings.player1Type === 'human') {
-        // For human players, use the recommendation engine from settings, or fall back to favorite
+        // For human players, use the recommendation engine from settings, or fall back to the default analysis engine
         if (recommendationEngineId) {
           player1EngineId = recommendationEngineId;
         } else {
-          const favoriteEngine = response.data.find(e => e.is_favorite);
-          const defaultEngine = favoriteEngine || response.data.find(e => e.is_builtin) || response.data[0];
-          player1EngineId = defaultEngine?.id;
+          player1EngineId = analysisEngineId ?? response.data[0]?.id;
         }
       }
       
@@ -1293,18 +1293,18 @@ const GamePage: React.FC<GamePageProps> = ({
         console.log('[initializeTauriEngines] Player 1 engine found:', engine);
         
         if (engine) {
-          // Generate unique runtime ID (timestamp + random) to allow same engine for both players
-          const runtimeId = `${player1EngineId}-${Date.now()}-${Math.random().toString(36).substring(2, 9)}`;
-          console.log('[initializeTauriEngines] Spawning player 1 engine with runtime ID:', runtimeId);
-          const spawnResult = await invoke<CommandResponse>('spawn_engine', {
-            engineId: runtimeId,
+          // The backend assigns each spawned instance its own runtime ID, so the same engine can play both sides
+          console.log('[initializeTauriEngines] Spawning player 1 engine:', player1EngineId);
+          const spawnResult = await invoke<CommandResponse<{ engine_id: string }>>('spawn_engine', {
+            engineId: player1EngineId,
             name: engine.name,
             path: engine.path,
             tempOptions: settings.player1TempOptions || null,
           });
           console.log('[initializeTauriEngines] Player 1 spawn result:', spawnResult);
           
-          if (spawnResult.success) {
+          if (spawnResult.success && spawnResult.data) {
+            const runtimeId = spawnResult.data.engine_id;
             engineIds.push(runtimeId);
             runtimePlayer1Id = runtimeId;
             names.set(runtimeId, engine.display_name || engine.name);
@@ -1327,13 +1327,11 @@ const GamePage: React.FC<GamePageProps> = ({
       // Always spawn player 2 engine (AI or recommendation engine for human)
       let player2EngineId = settings.player2EngineId;
       if (settings.player2Type === 'human') {
-        // For human players, use the recommendation engine from settings, or fall back to favorite
+        // For human players, use the recommendation engine from settings, or fall back to the default analysis engine
         if (recommendationEngineId) {
           player2EngineId = recommendationEngineId;
         } else {
-          const favoriteEngine = response.data.find(e => e.is_favorite);
-          const defaultEngine = favoriteEngine || response.data.find(e => e.is_builtin) || response.data[0];
-          player2EngineId = defaultEngine?.id;
+          player2EngineId = analysisEngineId ?? response.data[0]?.id;
         }
       }
       
@@ -1343,18 +1341,18 @@ const GamePage: React.FC<GamePageProps> = ({
         console.log('[initializeTauriEngines] Player 2 engine found:', engine);
         
         if (engine) {
-          // Generate unique runtime ID (timestamp + random) to allow same engine for both players
-          const runtimeId = `${player2EngineId}-${Date.now()}-${Math.random().toString(36).substring(2, 9)}`;
-          console.log('[initializeTauriEngines] Spawning player 2 engine with runtime ID:', runtimeId);
-          const spawnResult = await invoke<CommandResponse>('spawn_engine', {
-            engineId: runtimeId,
+          // The backend assigns each spawned instance its own runtime ID, so the same engine can play both sides
+          console.log('[initializeTauriEngines] Spawning player 2 engine:', player2EngineId);
+          const spawnResult = await invoke<CommandResponse<{ engine_id: string }>>('spawn_engine', {
+            engineId: player2EngineId,
             name: engine.name,
             path: engine.path,
             tempOptions: settings.player2TempOptions || null,
           });
           console.log('[initializeTauriEngines] Player 2 spawn result:', spawnResult);
           
-          if (spawnResult.success) {
+          if (spawnResult.success && spawnResult.data) {
+            const runtimeId = spawnResult.data.engine_id;
             engineIds.push(runtimeId);
             runtimePlayer2Id = runtimeId;
             names.set(runtimeId, engine.display_name || engine.name);
@@ -5,8 +5,8 @@ import { useTheme, getThemeDisplayName, getThemeDescription, type Theme } from '
 import { playPreviewSound } from '../utils/audio';
 import { EngineSelector } from './EngineSelector';
 import { EngineOptionsModal } from './EngineOptionsModal';
-import type { EngineConfig } from '../types/engine';
-import { invoke } from '@tauri-apps/api/core';
+import type { CommandResponse, DefaultEngines, EngineConfig } from '../types/engine';
+import { invoke } from '../utils/invoke';
 import '../styles/settings.css';
 
 type Notation = 'western' | 'kifu' | 'usi' | 'csa';
@@ -106,13 +106,11 @@ const SettingsPanel: React.FC<SettingsPanelProps> = ({
       if (response.success && response.data) {
         setEngines(response.data);
         
-        // Auto-select favorite engine if no recommendation engine is selected
+        // Auto-select the default analysis engine if no recommendation engine is selected
         if (!recommendationEngineId && response.data.length > 0) {
-          const favoriteEngine = response.data.find(e => e.is_favorite);
-          const defaultEngine = favoriteEngine || response.data.find(e => e.is_builtin) || response.data[0];
-          if (defaultEngine) {
-            onRecommendationEngineChange(defaultEngine.id);
-          }
+          const defaults = await invoke<CommandResponse<DefaultEngines>>('get_default_engines');
+          const defaultEngineId = defaults.data?.resolved.analysis ?? response.data[0].id;
+          onRecommendationEngineChange(defaultEngineId);
         }
       }
     } catch (error) {
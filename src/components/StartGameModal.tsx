@@ -4,7 +4,7 @@ import { Record } from 'tsshogi';
 import { EngineSelector } from './EngineSelector';
 import { EngineOptionsModal } from './EngineOptionsModal';
 import type { EngineConfig } from '../types/engine';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../utils/invoke';
 import { loadNewGameSettings, saveNewGameSettings } from '../utils/persistence';
 
 interface StartGameModalProps {
@@ -85,7 +85,7 @@ const StartGameModal: React.FC<StartGameModalProps> = ({ isOpen, onClose, onStar
 
   const loadEngines = async () => {
     try {
-      const response = await invoke<any>('get_engines');
+      const response = await invoke<any>('get_engines', { enabled: true });
       if (response.success && response.data) {
         setEngines(response.data);
       }
@@ -6,7 +6,7 @@
  */
 
 import { useState, useEffect } from 'react';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../utils/invoke';
 import { EngineSelector } from './EngineSelector';
 import { TauriUsiMonitor } from './TauriUsiMonitor';
 import { useTauriEvents } from '../hooks/useTauriEvents';
@@ -89,24 +89,25 @@ export function TauriGameDemo() {
           }
 
           // Spawn the engine
-          const spawnResponse = await invoke<CommandResponse>('spawn_engine', {
+          const spawnResponse = await invoke<CommandResponse<{ engine_id: string }>>('spawn_engine', {
             engineId: selectedEngineId,
             name: engine.name,
             path: engine.path,
             tempOptions: null,
           });
 
-          if (!spawnResponse.success) {
+          if (!spawnResponse.success || !spawnResponse.data) {
             console.error('Failed to spawn engine:', spawnResponse.message);
             return;
           }
 
-          setActiveEngineId(selectedEngineId);
+          const runtimeId = spawnResponse.data.engine_id;
+          setActiveEngineId(runtimeId);
           setEngineName(engine.name);
 
           // Initialize with USI handshake
-          await sendUsiCommand(selectedEngineId, 'usinewgame');
-          await sendUsiCommand(selectedEngineId, 'isready');
+          await sendUsiCommand(runtimeId, 'usinewgame');
+          await sendUsiCommand(runtimeId, 'isready');
         }
       } catch (error) {
         console.error('Error initializing engine:', error);
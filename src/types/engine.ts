@@ -6,6 +6,29 @@ export enum EngineStatus {
   Thinking = "thinking",
   Error = "error",
   Stopped = "stopped",
+  Unresponsive = "unresponsive",
+}
+
+/** Status of an engine that exited on its own with a failure; code is null when a signal killed it */
+export interface EngineCrashedStatus {
+  crashed: { code: number | null };
+}
+
+/** Emitted as `engine-crashed` and returned by get_engine_crash_history */
+export interface CrashReport {
+  runtime_id: string;
+  config_id: string;
+  name: string;
+  exit_code: number | null;
+  signal: number | null;
+  stderr_tail: string[];
+  timestamp: string;
+}
+
+/** Emitted as `engine-message::<runtime id>` and returned by get_engine_messages */
+export interface EngineMessage {
+  message: string;
+  timestamp: string;
 }
 
 export interface EngineOption {
@@ -33,19 +56,58 @@ export interface EngineConfig {
   enabled: boolean;
   last_used?: string;
   created_at: string;
-  is_favorite: boolean;
+  usage?: EngineUsage;
+}
+
+export interface EngineUsage {
+  games_played: number;
+  analysis_ms: number;
+  nodes_searched: number;
+}
+
+/** Returned by get_engine_stats */
+export interface EngineStats {
+  usage: EngineUsage;
+  analysis_hours: number;
+  last_used: string | null;
+}
+
+export type EnginePurpose = "game" | "analysis" | "tsume";
+
+/** Returned by get_default_engines: the chosen engine per purpose, and what each resolves to */
+export interface DefaultEngines {
+  defaults: Record<EnginePurpose, string | null>;
+  resolved: Record<EnginePurpose, string | null>;
+}
+
+export type BulkEngineAction = "enable" | "disable" | "delete" | "revalidate" | "health_check";
+
+/** One engine's outcome in the results of bulk_engine_action */
+export interface BulkEngineResult {
+  engine_id: string;
+  success: boolean;
+  error: string | null;
+  data: any | null;
 }
 
 export interface CommandResponse<T = any> {
   success: boolean;
   message?: string;
   data?: T;
+  /** Message code of a failure, for showing it in the user's language (see utils/messages) */
+  code?: string;
+  /** Values the message refers to */
+  params?: Record<string, string>;
 }
 
 export interface EngineHealthResult {
   id: string;
   name: string;
   status: "healthy" | "unhealthy" | "disabled";
-  error?: string;
+  usi_latency_ms?: number | null;
+  ready_latency_ms?: number | null;
+  binary_matches?: boolean | null;
+  missing_files?: string[];
+  error?: string | null;
 }
 
@@ -3,7 +3,7 @@
  * Provides a bridge between the existing USI controller and Tauri backend
  */
 
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '../utils/invoke';
 import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import type { CommandResponse } from '../types/engine';
 import { EventEmitter } from '../utils/events';
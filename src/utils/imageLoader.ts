@@ -43,7 +43,7 @@ async function isTauriAvailable(): Promise<boolean> {
  */
 async function loadImagesViaTauri(directory: string): Promise<string[]> {
   try {
-    const { invoke } = await import('@tauri-apps/api/core');
+    const { invoke } = await import('./invoke');
     const images = await invoke<string[]>('list_image_files', { directory });
     console.log(`Loaded ${images.length} images from ${directory} via Tauri`);
     return images;
@@ -0,0 +1,71 @@
+import { invoke as tauriInvoke, type InvokeArgs, type InvokeOptions } from '@tauri-apps/api/core';
+
+type CommandOutcome = 'success' | 'failure' | 'error';
+
+interface CommandResult {
+  callId: string;
+  outcome: CommandOutcome;
+  message?: string;
+}
+
+/** Commands that only serve the audit trail itself */
+const UNREPORTED_COMMANDS = new Set(['record_command_results', 'get_command_history']);
+
+/** Header the backend's audit trail reads the call ID from */
+const CALL_ID_HEADER = 'ysu-call-id';
+
+/** Outcomes are sent to the backend together, at most this often */
+const REPORT_INTERVAL_MS = 1000;
+
+let pendingResults: CommandResult[] = [];
+let reportTimer: ReturnType<typeof setTimeout> | null = null;
+
+/**
+ * `invoke` from @tauri-apps/api that also reports how the command ended to the backend's
+ * audit trail (see `get_command_history`). The backend records the call and its arguments
+ * itself but cannot see the response, so each call carries an ID that its outcome is
+ * reported under; concurrent calls of one command are told apart that way.
+ */
+export async function invoke<T>(cmd: string, args?: InvokeArgs, options?: InvokeOptions): Promise<T> {
+  if (UNREPORTED_COMMANDS.has(cmd)) {
+    return tauriInvoke<T>(cmd, args, options);
+  }
+  const callId = crypto.randomUUID();
+  const headers = new Headers(options?.headers);
+  headers.set(CALL_ID_HEADER, callId);
+
+  let result: T;
+  try {
+    result = await tauriInvoke<T>(cmd, args, { ...options, headers });
+  } catch (error) {
+    report({ callId, outcome: 'error', message: String(error) });
+    throw error;
+  }
+  const response = result as { success?: unknown; message?: unknown } | null;
+  if (response && typeof response === 'object' && response.success === false) {
+    report({
+      callId,
+      outcome: 'failure',
+      message: typeof response.message === 'string' ? response.message : undefined,
+    });
+  } else {
+    report({ callId, outcome: 'success' });
+  }
+  return result;
+}
+
+function report(result: CommandResult): void {
+  pendingResults.push(result);
+  if (reportTimer === null) {
+    reportTimer = setTimeout(flushResults, REPORT_INTERVAL_MS);
+  }
+}
+
+function flushResults(): void {
+  const results = pendingResults;
+  pendingResults = [];
+  reportTimer = null;
+  tauriInvoke('record_command_results', { results }).catch(() => {
+    // The audit trail is best effort; never let it fail the command itself
+  });
+}
@@ -0,0 +1,72 @@
+import type { CommandResponse } from '../types/engine';
+
+/** A backend message: a stable code, the values it refers to and its English text */
+export interface Message {
+  code: string;
+  params: Record<string, string>;
+  text: string;
+}
+
+type Catalog = Record<string, string>;
+
+/**
+ * Japanese templates by message code. `{name}` is replaced by the parameter of that name.
+ * Codes missing here fall back to the English text the backend sent.
+ */
+const ja: Catalog = {
+  engine_not_found: 'エンジンが見つかりません: {engine_id}',
+  engines_not_found: 'エンジンが見つかりません: {engine_ids}',
+  engine_cannot_run: 'エンジンを実行できません: {problems}',
+  engine_quarantined: '{path} はインターネットからダウンロードされたため macOS により隔離されています。実行するには隔離を解除してください',
+  engine_validation_failed: 'エンジンの検証に失敗しました: {error}',
+  validation_failed: '検証に失敗しました: {error}',
+  failed_to_start_engine: 'エンジンを起動できませんでした: {error}',
+  failed_to_stop_engine: 'エンジンを停止できませんでした: {error}',
+  failed_to_add_engine: 'エンジンを追加できませんでした: {error}',
+  failed_to_remove_engine: 'エンジンを削除できませんでした: {error}',
+  failed_to_save_configuration: '設定を保存できませんでした: {error}',
+  failed_to_save_settings: '設定を保存できませんでした: {error}',
+  cannot_remove_builtin_engine: '内蔵エンジンは削除できません',
+  invalid_position: '局面が不正です: {error}',
+  invalid_settings: '設定が不正です: {error}',
+  game_session_not_found: '対局が見つかりません: {session_id}',
+  failed_to_apply_move: '指し手を適用できませんでした: {error}',
+  failed_to_load_game_record: '棋譜を読み込めませんでした: {error}',
+  failed_to_save_game_record: '棋譜を保存できませんでした: {error}',
+  failed_to_import_kif: 'KIF を読み込めませんでした: {error}',
+  failed_to_export_kif: 'KIF を書き出せませんでした: {error}',
+  no_running_match: '実行中の対局がありません: {match_id}',
+  csa_already_connected: 'すでに CSA サーバーに接続しています',
+  csa_not_connected: 'CSA サーバーに接続していません',
+  engine_failed_to_respond: '{engine} が応答しませんでした',
+  engine_resigned: '{engine} が投了しました',
+  entering_king_win: '{engine} が入玉宣言勝ちしました',
+  invalid_entering_king_declaration: '{engine} の入玉宣言は条件を満たしていません',
+  illegal_move: '{engine} が反則手を指しました: {usi_move}',
+  checkmate: '{engine} の勝ち（詰み）',
+  adjudicated_win: '{engine} の判定勝ち',
+  adjudicated_draw: '判定により引き分け',
+  max_moves_reached: '最大手数に達しました',
+};
+
+const catalogs: Record<string, Catalog> = { ja };
+
+/** The message in `language`, or its English text when the catalog has no entry for its code */
+export function localizeMessage(message: Message, language: string): string {
+  const template = catalogs[language]?.[message.code];
+  if (!template) {
+    return message.text;
+  }
+  return template.replace(/\{(\w+)\}/g, (placeholder, name) => message.params[name] ?? placeholder);
+}
+
+/** The error of a failed command in `language` */
+export function localizeResponse(response: CommandResponse, language: string): string | undefined {
+  if (!response.code) {
+    return response.message;
+  }
+  return localizeMessage(
+    { code: response.code, params: response.params ?? {}, text: response.message ?? '' },
+    language,
+  );
+}
@@ -3,7 +3,7 @@
  * This module provides a bridge between the game logic and Tauri backend
  */
 
-import { invoke } from '@tauri-apps/api/core';
+import { invoke } from './invoke';
 import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import type { CommandResponse } from '../types/engine';
 